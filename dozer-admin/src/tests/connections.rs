@@ -27,6 +27,7 @@ mod grpc_service {
                     port: 50051,
                     adapter: "default".to_owned(),
                     schemas: None,
+                    journal_path: None,
                 })),
             }),
         };
@@ -100,6 +101,7 @@ mod grpc_service {
                         port: 50051,
                         adapter: "default".to_owned(),
                         schemas: None,
+                        journal_path: None,
                     })),
                 }),
             })
@@ -1,7 +1,7 @@
 use crate::auth::Access;
 use crate::errors::{ApiError, AuthError};
-use dozer_cache::cache::expression::QueryExpression;
-use dozer_cache::cache::RecordWithId;
+use dozer_cache::cache::expression::{AggregateExpression, QueryExpression};
+use dozer_cache::cache::{AggregateGroup, RecordVersion, RecordWithId};
 use dozer_cache::{AccessFilter, CacheReader};
 use dozer_types::types::Schema;
 
@@ -9,12 +9,24 @@ pub fn get_record(
     cache_reader: &CacheReader,
     key: &[u8],
     access: Option<Access>,
-) -> Result<RecordWithId, ApiError> {
+) -> Result<(Schema, RecordWithId), ApiError> {
     let access_filter = get_access_filter(access)?;
-    let record = cache_reader
+    cache_reader
         .get(key, &access_filter)
-        .map_err(ApiError::NotFound)?;
-    Ok(record)
+        .map_err(ApiError::NotFound)
+}
+
+/// Get the versions of a record, oldest first, with the schema of the returned fields
+pub fn get_record_history(
+    cache_reader: &CacheReader,
+    endpoint_name: &str,
+    key: &[u8],
+    access: Option<Access>,
+) -> Result<(Schema, Vec<RecordVersion>), ApiError> {
+    let access_filter = get_access_filter(access)?;
+    cache_reader
+        .history(endpoint_name, key, &access_filter)
+        .map_err(ApiError::NotFound)
 }
 
 pub fn get_records_count(
@@ -29,20 +41,32 @@ pub fn get_records_count(
         .map_err(ApiError::CountFailed)
 }
 
-/// Get multiple records
-pub fn get_records<'a>(
-    cache_reader: &'a CacheReader,
+/// Get multiple records, with the schema of the returned fields
+pub fn get_records(
+    cache_reader: &CacheReader,
     endpoint_name: &str,
     exp: &mut QueryExpression,
     access: Option<Access>,
-) -> Result<(&'a Schema, Vec<RecordWithId>), ApiError> {
+) -> Result<(Schema, Vec<RecordWithId>), ApiError> {
     let access_filter = get_access_filter(access)?;
     cache_reader
         .query(endpoint_name, exp, access_filter)
         .map_err(ApiError::QueryFailed)
 }
 
-fn get_access_filter(access: Option<Access>) -> Result<AccessFilter, ApiError> {
+pub fn get_records_aggregate(
+    cache_reader: &CacheReader,
+    endpoint_name: &str,
+    exp: &mut AggregateExpression,
+    access: Option<Access>,
+) -> Result<Vec<AggregateGroup>, ApiError> {
+    let access_filter = get_access_filter(access)?;
+    cache_reader
+        .aggregate(endpoint_name, exp, access_filter)
+        .map_err(ApiError::AggregateFailed)
+}
+
+pub fn get_access_filter(access: Option<Access>) -> Result<AccessFilter, ApiError> {
     match access {
         None | Some(Access::All) => Ok(AccessFilter {
             filter: None,
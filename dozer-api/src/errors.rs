@@ -33,6 +33,8 @@ pub enum ApiError {
     CountFailed(#[source] CacheError),
     #[error("Failed to query cache")]
     QueryFailed(#[source] CacheError),
+    #[error("Failed to aggregate records")]
+    AggregateFailed(#[source] CacheError),
     #[error("Internal error: {0}")]
     InternalError(#[from] BoxedError),
     #[error("Type error: {0}")]
@@ -149,6 +151,7 @@ impl actix_web::error::ResponseError for ApiError {
             | ApiError::CacheNotFound(_)
             | ApiError::QueryFailed(_)
             | ApiError::CountFailed(_)
+            | ApiError::AggregateFailed(_)
             | ApiError::FailedToBindToAddress(_, _) => StatusCode::INTERNAL_SERVER_ERROR,
         }
     }
@@ -59,6 +59,36 @@ impl<'a> OpenApiGenerator<'a> {
         // Simple expression
     }
 
+    fn generate_id_parameter(&self) -> ReferenceOr<Parameter> {
+        ReferenceOr::Item(Parameter::Path {
+            parameter_data: ParameterData {
+                name: "id".to_owned(),
+                description: Some(format!(
+                    "Primary key of the document - {} ",
+                    self.endpoint
+                        .index
+                        .to_owned()
+                        .unwrap()
+                        .primary_key
+                        .join(", ")
+                )),
+                required: true,
+                format: ParameterSchemaOrContent::Schema(ReferenceOr::Item(Schema {
+                    schema_data: SchemaData {
+                        ..Default::default()
+                    },
+                    schema_kind: SchemaKind::Type(Type::Integer(Default::default())),
+                })),
+                deprecated: None,
+                example: None,
+                examples: IndexMap::new(),
+                explode: None,
+                extensions: IndexMap::new(),
+            },
+            style: PathStyle::Simple,
+        })
+    }
+
     fn generate_get_route(&self) -> ReferenceOr<PathItem> {
         let responses = Responses {
             responses: indexmap::indexmap! {
@@ -75,25 +105,7 @@ impl<'a> OpenApiGenerator<'a> {
                     .to_owned(),
             ),
             operation_id: Some(format!("{}-by-id", self.endpoint.name)),
-            parameters: vec![ReferenceOr::Item(Parameter::Path {
-                parameter_data: ParameterData {
-                    name: "id".to_owned(),
-                    description: Some(format!("Primary key of the document - {} ", self.endpoint.index.to_owned().unwrap().primary_key.join(", "))),
-                    required: true,
-                    format: ParameterSchemaOrContent::Schema(ReferenceOr::Item(Schema {
-                        schema_data: SchemaData {
-                            ..Default::default()
-                        },
-                        schema_kind: SchemaKind::Type(Type::Integer(Default::default())),
-                    })),
-                    deprecated: None,
-                    example: None,
-                    examples: IndexMap::new(),
-                    explode: None,
-                    extensions: IndexMap::new(),
-                },
-                style: PathStyle::Simple,
-            })],
+            parameters: vec![self.generate_id_parameter()],
             responses,
             ..Default::default()
         });
@@ -103,6 +115,31 @@ impl<'a> OpenApiGenerator<'a> {
         })
     }
 
+    fn generate_history_route(&self) -> ReferenceOr<PathItem> {
+        let responses = Responses {
+            responses: indexmap::indexmap! {
+                StatusCode::Code(200) => ReferenceOr::Item(create_reference_response(format!("Versions of a {} record", self.endpoint.name), format!("#/components/schemas/{}", self.get_plural_name())))
+            },
+            ..Default::default()
+        };
+        let operation = Some(Operation {
+            tags: vec![format!("{}", self.endpoint.name)],
+            summary: Some("Fetch the versions of a single document record by primary key".to_owned()),
+            description: Some(
+                "Versions are returned oldest first, with the commits they were current between in __dozer_valid_from and __dozer_valid_to"
+                    .to_owned(),
+            ),
+            operation_id: Some(format!("{}-history-by-id", self.endpoint.name)),
+            parameters: vec![self.generate_id_parameter()],
+            responses,
+            ..Default::default()
+        });
+        ReferenceOr::Item(PathItem {
+            get: operation,
+            ..Default::default()
+        })
+    }
+
     fn generate_list_route(&self) -> ReferenceOr<PathItem> {
         let responses = Responses {
             responses: indexmap::indexmap! {
@@ -185,7 +222,7 @@ impl<'a> OpenApiGenerator<'a> {
             tags: vec![format!("{}", self.endpoint.name)],
             summary: Some("Query documents based on an expression".to_owned()),
             description: Some(
-                "Documents can be queried based on a simple or a composite expression".to_owned(),
+                "Documents can be queried based on a simple or a composite expression, returning the $select fields or all fields".to_owned(),
             ),
             operation_id: Some(format!("query-{}", self.endpoint.name)),
             request_body: Some(ReferenceOr::Item(request_body)),
@@ -198,17 +235,76 @@ impl<'a> OpenApiGenerator<'a> {
         })
     }
 
+    fn generate_aggregate_route(&self) -> ReferenceOr<PathItem> {
+        let example = match self.schema.fields.first() {
+            Some(field) => json!({ "$group_by": [field.name], "$aggregates": ["$count"] }),
+            None => json!({ "$aggregates": ["$count"] }),
+        };
+        let request_body = RequestBody {
+            content: indexmap::indexmap! {
+                "application/json".to_owned() => MediaType { example: Some(example), ..Default::default() }
+            },
+            required: true,
+            ..Default::default()
+        };
+        let responses = Responses {
+            responses: indexmap::indexmap! {
+                StatusCode::Code(200) => ReferenceOr::Item(
+                    create_response(
+                        "One object per group, with the group_by fields and the aggregates".to_string(),
+                        Schema {
+                            schema_data: Default::default(),
+                            schema_kind: SchemaKind::Type(Type::Array(ArrayType {
+                                items: Some(ReferenceOr::boxed_item(Schema {
+                                    schema_data: Default::default(),
+                                    schema_kind: SchemaKind::Type(Type::Object(Default::default())),
+                                })),
+                                min_items: None,
+                                max_items: None,
+                                unique_items: false,
+                            })),
+                        }
+                    )
+                )
+            },
+            ..Default::default()
+        };
+        let operation = Some(Operation {
+            tags: vec![format!("{}", self.endpoint.name)],
+            summary: Some("Aggregate documents based on an expression".to_owned()),
+            description: Some(
+                "Computes $count, $sum, $avg, $min and $max of the documents matching $filter, grouped by $group_by".to_owned(),
+            ),
+            operation_id: Some(format!("aggregate-{}", self.endpoint.name)),
+            request_body: Some(ReferenceOr::Item(request_body)),
+            responses,
+            ..Default::default()
+        });
+        ReferenceOr::Item(PathItem {
+            post: operation,
+            ..Default::default()
+        })
+    }
+
     fn _generate_available_paths(&self) -> Paths {
         let get_list = self.generate_list_route();
         let get_by_id_item = self.generate_get_route();
         let count_list = self.generate_count_route();
         let query_list = self.generate_query_route();
-        let path_items = indexmap::indexmap! {
+        let aggregate_list = self.generate_aggregate_route();
+        let mut path_items = indexmap::indexmap! {
             self.endpoint.path.to_owned() => get_list,
             format!("{}/{}", self.endpoint.path.to_owned(), "{id}") => get_by_id_item,
             format!("{}/count", self.endpoint.path.to_owned()) => count_list,
-            format!("{}/query", self.endpoint.path.to_owned()) => query_list
+            format!("{}/query", self.endpoint.path.to_owned()) => query_list,
+            format!("{}/aggregate", self.endpoint.path.to_owned()) => aggregate_list
         };
+        if self.endpoint.history {
+            path_items.insert(
+                format!("{}/{}/history", self.endpoint.path.to_owned(), "{id}"),
+                self.generate_history_route(),
+            );
+        }
         Paths {
             paths: path_items,
             ..Default::default()
@@ -4,15 +4,15 @@ use std::sync::Arc;
 use crate::auth::Access;
 
 use crate::grpc::shared_impl;
-use crate::grpc::types_helper::{map_field_definitions, map_record};
+use crate::grpc::types_helper::{map_aggregate_group, map_field_definitions, map_record};
 use crate::RoCacheEndpoint;
 use dozer_types::grpc_types::common::common_grpc_service_server::CommonGrpcService;
 use tokio_stream::wrappers::ReceiverStream;
 use tonic::{Request, Response, Status};
 
 use dozer_types::grpc_types::common::{
-    CountResponse, GetEndpointsRequest, GetEndpointsResponse, GetFieldsRequest, GetFieldsResponse,
-    OnEventRequest, QueryRequest, QueryResponse,
+    AggregateRequest, AggregateResponse, CountResponse, GetEndpointsRequest, GetEndpointsResponse,
+    GetFieldsRequest, GetFieldsResponse, OnEventRequest, QueryRequest, QueryResponse,
 };
 use dozer_types::grpc_types::types::Operation;
 
@@ -85,21 +85,45 @@ impl CommonGrpcService for CommonService {
     ) -> Result<Response<QueryResponse>, Status> {
         let (cache_endpoint, query_request, access) = self.parse_request(request)?;
 
-        let cache_reader = cache_endpoint.cache_reader();
         let (schema, records) = shared_impl::query(
-            &cache_reader,
+            &cache_endpoint.cache_reader(),
             &cache_endpoint.endpoint.name,
             query_request.query.as_deref(),
             access,
         )?;
 
-        let fields = map_field_definitions(schema.fields.clone());
+        let fields = map_field_definitions(schema.fields);
         let records = records.into_iter().map(map_record).collect();
         let reply = QueryResponse { fields, records };
 
         Ok(Response::new(reply))
     }
 
+    async fn aggregate(
+        &self,
+        request: Request<AggregateRequest>,
+    ) -> Result<Response<AggregateResponse>, Status> {
+        let parts = request.into_parts();
+        let mut extensions = parts.1;
+        let aggregate_request = parts.2;
+        let access = extensions.remove::<Access>();
+        let endpoint = &aggregate_request.endpoint;
+        let cache_endpoint = self
+            .endpoint_map
+            .get(endpoint)
+            .ok_or_else(|| Status::invalid_argument(endpoint))?;
+
+        let groups = shared_impl::aggregate(
+            &cache_endpoint.cache_reader(),
+            &cache_endpoint.endpoint.name,
+            &aggregate_request.query,
+            access,
+        )?;
+
+        let groups = groups.into_iter().map(map_aggregate_group).collect();
+        Ok(Response::new(AggregateResponse { groups }))
+    }
+
     type OnEventStream = ResponseStream;
 
     async fn on_event(&self, request: Request<OnEventRequest>) -> EventResult<Self::OnEventStream> {
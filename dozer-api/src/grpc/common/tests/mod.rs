@@ -1,18 +1,23 @@
 use std::time::Duration;
 
+use crate::auth::Access;
 use crate::grpc::typed::tests::{
     fake_internal_pipeline_server::start_fake_internal_grpc_pipeline, service::setup_pipeline,
 };
 
 use dozer_types::grpc_types::{
     common::{
-        common_grpc_service_server::CommonGrpcService, GetEndpointsRequest, GetFieldsRequest,
-        OnEventRequest, QueryRequest,
+        common_grpc_service_server::CommonGrpcService, AggregateRequest, GetEndpointsRequest,
+        GetFieldsRequest, OnEventRequest, QueryRequest,
+    },
+    types::{
+        value, EventType, FieldDefinition, Operation, OperationType, Record, RecordWithId, Type,
+        Value,
     },
-    types::{value, EventType, FieldDefinition, OperationType, RecordWithId, Type, Value},
 };
 use dozer_types::models::api_config::default_api_config;
-use tokio::sync::oneshot;
+use dozer_types::serde_json::{self, json};
+use tokio::sync::{broadcast, oneshot};
 use tonic::Request;
 
 use super::CommonService;
@@ -79,6 +84,55 @@ async fn test_grpc_common_count_and_query() {
     let (count, records) = count_and_query(&service, endpoint, Some(limit)).await;
     assert_eq!(count, 11);
     assert_eq!(records.len(), 11);
+
+    // Query with projection.
+    let response = service
+        .query(Request::new(QueryRequest {
+            endpoint: endpoint.to_string(),
+            query: Some(r#"{ "$select": ["film_id"], "$limit": 1 }"#.to_string()),
+        }))
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(response.fields.len(), 1);
+    assert_eq!(response.fields[0].name, "film_id");
+    assert_eq!(response.records[0].record.as_ref().unwrap().values.len(), 1);
+}
+
+#[tokio::test]
+async fn test_grpc_common_aggregate() {
+    let service = setup_common_service().await;
+    let response = service
+        .aggregate(Request::new(AggregateRequest {
+            endpoint: "films".to_string(),
+            query: r#"{ "$group_by": ["release_year"], "$aggregates": ["$count", { "$min": "film_id" }] }"#
+                .to_string(),
+        }))
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(response.groups.len(), 1);
+    let group = &response.groups[0];
+    assert_eq!(
+        group.key,
+        vec![Value {
+            value: Some(value::Value::UintValue(2006))
+        }]
+    );
+    assert_eq!(
+        group.values[0],
+        Value {
+            value: Some(value::Value::UintValue(52))
+        }
+    );
+
+    let response = service
+        .aggregate(Request::new(AggregateRequest {
+            endpoint: "films".to_string(),
+            query: r#"{ "$aggregates": [{ "$avg": "description" }] }"#.to_string(),
+        }))
+        .await;
+    assert!(response.is_err());
 }
 
 #[tokio::test]
@@ -168,3 +222,118 @@ async fn test_grpc_common_on_event() {
         }
     );
 }
+
+fn film(film_id: u64) -> Record {
+    Record {
+        values: vec![
+            Value {
+                value: Some(value::Value::UintValue(film_id)),
+            },
+            Value {
+                value: Some(value::Value::StringValue("description".to_string())),
+            },
+            Value { value: None },
+            Value { value: None },
+            Value { value: None },
+        ],
+        version: 1,
+    }
+}
+
+fn request<T>(request: T, access: &Access) -> Request<T> {
+    let mut request = Request::new(request);
+    request.extensions_mut().insert(access.clone());
+    request
+}
+
+#[tokio::test]
+async fn test_grpc_common_access_filter() {
+    let (endpoints, _) = setup_pipeline().await;
+    let (sender, receiver) = broadcast::channel(16);
+    let service = CommonService::new(endpoints, Some(receiver));
+    let access: Access = serde_json::from_value(
+        json!({"Custom":{"get_records":{"filter":{"film_id":268},"fields":["film_id"]}}}),
+    )
+    .unwrap();
+
+    for (query, expected) in [
+        (None, vec![268]),
+        (Some(r#"{"$filter":{"film_id":524}}"#), vec![]),
+    ] {
+        let query_request = QueryRequest {
+            endpoint: "films".to_string(),
+            query: query.map(str::to_string),
+        };
+        let count = service
+            .count(request(query_request.clone(), &access))
+            .await
+            .unwrap()
+            .into_inner()
+            .count;
+        assert_eq!(count, expected.len() as u64);
+        let records = service
+            .query(request(query_request, &access))
+            .await
+            .unwrap()
+            .into_inner()
+            .records;
+        let ids = records
+            .into_iter()
+            .map(|record| record.record.unwrap().values[0].clone())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            ids,
+            expected
+                .into_iter()
+                .map(|id| Value {
+                    value: Some(value::Value::UintValue(id))
+                })
+                .collect::<Vec<_>>()
+        );
+    }
+
+    let on_event_request = |filter: Option<&str>| {
+        request(
+            OnEventRequest {
+                endpoint: "films".to_string(),
+                r#type: EventType::All as i32,
+                filter: filter.map(str::to_string),
+            },
+            &access,
+        )
+    };
+    let status = service
+        .on_event(on_event_request(Some(r#"{"description": "description"}"#)))
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), tonic::Code::PermissionDenied);
+    let mut rx = service
+        .on_event(on_event_request(None))
+        .await
+        .unwrap()
+        .into_inner()
+        .into_inner();
+    let operation = |typ: OperationType, old, new| Operation {
+        typ: typ as i32,
+        old,
+        new: Some(new),
+        new_id: None,
+        endpoint_name: "films".to_string(),
+    };
+    // Another tenant's insert isn't sent, and moving the record out of the tenant deletes it. Only
+    // the readable fields have values.
+    sender
+        .send(operation(OperationType::Insert, None, film(524)))
+        .unwrap();
+    sender
+        .send(operation(
+            OperationType::Update,
+            Some(film(268)),
+            film(1000),
+        ))
+        .unwrap();
+    let received = rx.recv().await.unwrap().unwrap();
+    let mut deleted = film(268);
+    deleted.values[1].value = None;
+    assert_eq!(received, operation(OperationType::Delete, None, deleted));
+}
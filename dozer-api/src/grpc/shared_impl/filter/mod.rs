@@ -1,111 +1,71 @@
-use dozer_cache::cache::expression::{FilterExpression, Operator};
-use dozer_types::{
-    json_value_to_field,
-    ordered_float::OrderedFloat,
-    types::{Field, Schema},
-};
+use dozer_cache::cache::RecordFilter;
+use dozer_types::types::Schema;
 
-use dozer_types::grpc_types::types::{value, Operation, OperationType, Record, Value};
+use dozer_types::grpc_types::types::{Operation, OperationType, Record};
 
-pub fn op_satisfies_filter(
-    op: &Operation,
-    filter: Option<&FilterExpression>,
+use crate::grpc::types_helper::map_internal_record;
+
+/// Restricts `op` to the records satisfying `access_filter`, matching them like the cache does.
+/// An update moving a record out of the accessible ones is sent as a delete of the old record, and
+/// one moving a record in is sent as an insert of the new record.
+pub fn op_within_access_filter(
+    op: Operation,
+    access_filter: Option<&RecordFilter>,
     schema: &Schema,
-) -> bool {
-    if let Some(filter) = filter {
-        if op.typ == OperationType::Insert as i32 || op.typ == OperationType::Delete as i32 {
-            record_satisfies_filter(op.new.as_ref().unwrap(), filter, schema)
-        } else if op.typ == OperationType::Update as i32 {
-            record_satisfies_filter(op.old.as_ref().unwrap(), filter, schema)
-                || record_satisfies_filter(op.new.as_ref().unwrap(), filter, schema)
-        } else {
-            false
+) -> Option<Operation> {
+    let Some(access_filter) = access_filter else {
+        return Some(op);
+    };
+    if op.typ == OperationType::Insert as i32 || op.typ == OperationType::Delete as i32 {
+        matching_records(&op, access_filter, schema).1.then_some(op)
+    } else if op.typ == OperationType::Update as i32 {
+        match matching_records(&op, access_filter, schema) {
+            (true, true) => Some(op),
+            (true, false) => Some(Operation {
+                typ: OperationType::Delete as i32,
+                new: op.old,
+                old: None,
+                new_id: None,
+                endpoint_name: op.endpoint_name,
+            }),
+            (false, true) => Some(Operation {
+                typ: OperationType::Insert as i32,
+                old: None,
+                ..op
+            }),
+            (false, false) => None,
         }
     } else {
-        true
+        None
     }
 }
 
-fn record_satisfies_filter(record: &Record, filter: &FilterExpression, schema: &Schema) -> bool {
-    match filter {
-        FilterExpression::And(filters) => filters
-            .iter()
-            .all(|filter| record_satisfies_filter(record, filter, schema)),
-        FilterExpression::Simple(field_name, operator, value) => {
-            let Some((field_index, field_definition)) = schema
-                .fields
-                .iter()
-                .enumerate()
-                .find(|(_, field)| field.name == *field_name)
-             else {
-                return false;
-            };
-
-            let Some(filed_value) = record.values.get(field_index) else {
-                return false;
-            };
-
-            let Ok(value) = json_value_to_field(value.clone(), field_definition.typ, field_definition.nullable) else {
-                return false;
-            };
-
-            field_satisfies_op(filed_value, *operator, &value)
+/// Clears the values at `positions` in the records of `op`.
+pub fn clear_values(mut op: Operation, positions: &[usize]) -> Operation {
+    for record in op.old.iter_mut().chain(op.new.iter_mut()) {
+        for position in positions {
+            if let Some(value) = record.values.get_mut(*position) {
+                value.value = None;
+            }
         }
     }
+    op
 }
 
-fn field_satisfies_op(field: &Value, operator: Operator, value: &Field) -> bool {
-    match operator {
-        Operator::LT => match (field.value.as_ref().unwrap(), value) {
-            (value::Value::UintValue(n), Field::UInt(m)) => n < m,
-            (value::Value::IntValue(n), Field::Int(m)) => n < m,
-            (value::Value::FloatValue(n), Field::Float(m)) => &OrderedFloat(*n) < m,
-            (value::Value::BoolValue(n), Field::Boolean(m)) => n < m,
-            (value::Value::StringValue(n), Field::String(m)) => n < m,
-            (value::Value::BytesValue(n), Field::Binary(m)) => n < m,
-            _ => false,
-        },
-        Operator::LTE => match (field.value.as_ref().unwrap(), value) {
-            (value::Value::UintValue(n), Field::UInt(m)) => n <= m,
-            (value::Value::IntValue(n), Field::Int(m)) => n <= m,
-            (value::Value::FloatValue(n), Field::Float(m)) => &OrderedFloat(*n) <= m,
-            (value::Value::BoolValue(n), Field::Boolean(m)) => n <= m,
-            (value::Value::StringValue(n), Field::String(m)) => n <= m,
-            (value::Value::BytesValue(n), Field::Binary(m)) => n <= m,
-            _ => false,
-        },
-        Operator::EQ => match (field.value.as_ref().unwrap(), value) {
-            (value::Value::UintValue(n), Field::UInt(m)) => n == m,
-            (value::Value::IntValue(n), Field::Int(m)) => n == m,
-            (value::Value::FloatValue(n), Field::Float(m)) => &OrderedFloat(*n) == m,
-            (value::Value::BoolValue(n), Field::Boolean(m)) => n == m,
-            (value::Value::StringValue(n), Field::String(m)) => n == m,
-            (value::Value::BytesValue(n), Field::Binary(m)) => n == m,
-            _ => false,
-        },
-        Operator::GT => match (field.value.as_ref().unwrap(), value) {
-            (value::Value::UintValue(n), Field::UInt(m)) => n > m,
-            (value::Value::IntValue(n), Field::Int(m)) => n > m,
-            (value::Value::FloatValue(n), Field::Float(m)) => &OrderedFloat(*n) > m,
-            (value::Value::BoolValue(n), Field::Boolean(m)) => n > m,
-            (value::Value::StringValue(n), Field::String(m)) => n > m,
-            (value::Value::BytesValue(n), Field::Binary(m)) => n > m,
-            _ => false,
-        },
-        Operator::GTE => match (field.value.as_ref().unwrap(), value) {
-            (value::Value::UintValue(n), Field::UInt(m)) => n >= m,
-            (value::Value::IntValue(n), Field::Int(m)) => n >= m,
-            (value::Value::FloatValue(n), Field::Float(m)) => &OrderedFloat(*n) >= m,
-            (value::Value::BoolValue(n), Field::Boolean(m)) => n >= m,
-            (value::Value::StringValue(n), Field::String(m)) => n >= m,
-            (value::Value::BytesValue(n), Field::Binary(m)) => n >= m,
-            _ => false,
-        },
-        Operator::Contains => match (field.value.as_ref().unwrap(), value) {
-            (value::Value::StringValue(n), Field::String(m)) => n.contains(m),
-            _ => false,
-        },
-        Operator::MatchesAll | Operator::MatchesAny => unimplemented!(),
+/// Whether the old and new records of `op` satisfy `filter`, matching them like the cache does.
+/// Inserts and deletes only have a new record.
+pub fn matching_records(op: &Operation, filter: &RecordFilter, schema: &Schema) -> (bool, bool) {
+    let matches = |record: &Option<Record>| {
+        record.as_ref().map_or(false, |record| {
+            filter.matches(&map_internal_record(record, schema))
+        })
+    };
+    if op.typ == OperationType::Update as i32 {
+        (matches(&op.old), matches(&op.new))
+    } else if op.typ == OperationType::Insert as i32 || op.typ == OperationType::Delete as i32 {
+        (false, matches(&op.new))
+    } else {
+        (false, false)
     }
 }
 
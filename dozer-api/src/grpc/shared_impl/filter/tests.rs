@@ -1,781 +1,179 @@
+use dozer_cache::cache::expression::{FilterExpression, Operator};
 use dozer_cache::cache::test_utils::schema_1;
+use dozer_types::grpc_types::types::{value, Value};
 use dozer_types::serde_json::json;
 
 use super::*;
 
-fn test_field_satisfies_op_impl(
-    field: value::Value,
-    operator: Operator,
-    value: Field,
-    expected: bool,
-) {
-    assert_eq!(
-        field_satisfies_op(&Value { value: Some(field) }, operator, &value),
-        expected
-    );
+fn int(value: i64) -> Value {
+    Value {
+        value: Some(value::Value::IntValue(value)),
+    }
 }
 
-#[test]
-fn test_field_satisfies_op() {
-    test_field_satisfies_op_impl(
-        value::Value::UintValue(1),
-        Operator::LT,
-        Field::UInt(2),
-        true,
-    );
-    test_field_satisfies_op_impl(
-        value::Value::UintValue(1),
-        Operator::LT,
-        Field::UInt(1),
-        false,
-    );
-    test_field_satisfies_op_impl(
-        value::Value::UintValue(1),
-        Operator::LT,
-        Field::UInt(0),
-        false,
-    );
-    test_field_satisfies_op_impl(
-        value::Value::UintValue(1),
-        Operator::LTE,
-        Field::UInt(2),
-        true,
-    );
-    test_field_satisfies_op_impl(
-        value::Value::UintValue(1),
-        Operator::LTE,
-        Field::UInt(1),
-        true,
-    );
-    test_field_satisfies_op_impl(
-        value::Value::UintValue(1),
-        Operator::LTE,
-        Field::UInt(0),
-        false,
-    );
-    test_field_satisfies_op_impl(
-        value::Value::UintValue(1),
-        Operator::EQ,
-        Field::UInt(2),
-        false,
-    );
-    test_field_satisfies_op_impl(
-        value::Value::UintValue(1),
-        Operator::EQ,
-        Field::UInt(1),
-        true,
-    );
-    test_field_satisfies_op_impl(
-        value::Value::UintValue(1),
-        Operator::EQ,
-        Field::UInt(0),
-        false,
-    );
-    test_field_satisfies_op_impl(
-        value::Value::UintValue(1),
-        Operator::GT,
-        Field::UInt(2),
-        false,
-    );
-    test_field_satisfies_op_impl(
-        value::Value::UintValue(1),
-        Operator::GT,
-        Field::UInt(1),
-        false,
-    );
-    test_field_satisfies_op_impl(
-        value::Value::UintValue(1),
-        Operator::GT,
-        Field::UInt(0),
-        true,
-    );
-    test_field_satisfies_op_impl(
-        value::Value::UintValue(1),
-        Operator::GTE,
-        Field::UInt(2),
-        false,
-    );
-    test_field_satisfies_op_impl(
-        value::Value::UintValue(1),
-        Operator::GTE,
-        Field::UInt(1),
-        true,
-    );
-    test_field_satisfies_op_impl(
-        value::Value::UintValue(1),
-        Operator::GTE,
-        Field::UInt(0),
-        true,
-    );
+fn string(value: &str) -> Value {
+    Value {
+        value: Some(value::Value::StringValue(value.into())),
+    }
+}
 
-    test_field_satisfies_op_impl(value::Value::IntValue(1), Operator::LT, Field::Int(2), true);
-    test_field_satisfies_op_impl(
-        value::Value::IntValue(1),
-        Operator::LT,
-        Field::Int(1),
-        false,
-    );
-    test_field_satisfies_op_impl(
-        value::Value::IntValue(1),
-        Operator::LT,
-        Field::Int(0),
-        false,
-    );
-    test_field_satisfies_op_impl(
-        value::Value::IntValue(1),
-        Operator::LTE,
-        Field::Int(2),
-        true,
-    );
-    test_field_satisfies_op_impl(
-        value::Value::IntValue(1),
-        Operator::LTE,
-        Field::Int(1),
-        true,
-    );
-    test_field_satisfies_op_impl(
-        value::Value::IntValue(1),
-        Operator::LTE,
-        Field::Int(0),
-        false,
-    );
-    test_field_satisfies_op_impl(
-        value::Value::IntValue(1),
-        Operator::EQ,
-        Field::Int(2),
-        false,
-    );
-    test_field_satisfies_op_impl(value::Value::IntValue(1), Operator::EQ, Field::Int(1), true);
-    test_field_satisfies_op_impl(
-        value::Value::IntValue(1),
-        Operator::EQ,
-        Field::Int(0),
-        false,
-    );
-    test_field_satisfies_op_impl(
-        value::Value::IntValue(1),
-        Operator::GT,
-        Field::Int(2),
-        false,
-    );
-    test_field_satisfies_op_impl(
-        value::Value::IntValue(1),
-        Operator::GT,
-        Field::Int(1),
-        false,
-    );
-    test_field_satisfies_op_impl(value::Value::IntValue(1), Operator::GT, Field::Int(0), true);
-    test_field_satisfies_op_impl(
-        value::Value::IntValue(1),
-        Operator::GTE,
-        Field::Int(2),
-        false,
-    );
-    test_field_satisfies_op_impl(
-        value::Value::IntValue(1),
-        Operator::GTE,
-        Field::Int(1),
-        true,
-    );
-    test_field_satisfies_op_impl(
-        value::Value::IntValue(1),
-        Operator::GTE,
-        Field::Int(0),
-        true,
-    );
+fn null() -> Value {
+    Value { value: None }
+}
 
-    test_field_satisfies_op_impl(
-        value::Value::FloatValue(1.0),
-        Operator::LT,
-        Field::Float(OrderedFloat(2.0)),
-        true,
-    );
-    test_field_satisfies_op_impl(
-        value::Value::FloatValue(1.0),
-        Operator::LT,
-        Field::Float(OrderedFloat(1.0)),
-        false,
-    );
-    test_field_satisfies_op_impl(
-        value::Value::FloatValue(1.0),
-        Operator::LT,
-        Field::Float(OrderedFloat(0.0)),
-        false,
-    );
-    test_field_satisfies_op_impl(
-        value::Value::FloatValue(1.0),
-        Operator::LTE,
-        Field::Float(OrderedFloat(2.0)),
-        true,
-    );
-    test_field_satisfies_op_impl(
-        value::Value::FloatValue(1.0),
-        Operator::LTE,
-        Field::Float(OrderedFloat(1.0)),
-        true,
-    );
-    test_field_satisfies_op_impl(
-        value::Value::FloatValue(1.0),
-        Operator::LTE,
-        Field::Float(OrderedFloat(0.0)),
-        false,
-    );
-    test_field_satisfies_op_impl(
-        value::Value::FloatValue(1.0),
-        Operator::EQ,
-        Field::Float(OrderedFloat(2.0)),
-        false,
-    );
-    test_field_satisfies_op_impl(
-        value::Value::FloatValue(1.0),
-        Operator::EQ,
-        Field::Float(OrderedFloat(1.0)),
-        true,
-    );
-    test_field_satisfies_op_impl(
-        value::Value::FloatValue(1.0),
-        Operator::EQ,
-        Field::Float(OrderedFloat(0.0)),
-        false,
-    );
-    test_field_satisfies_op_impl(
-        value::Value::FloatValue(1.0),
-        Operator::GT,
-        Field::Float(OrderedFloat(2.0)),
-        false,
-    );
-    test_field_satisfies_op_impl(
-        value::Value::FloatValue(1.0),
-        Operator::GT,
-        Field::Float(OrderedFloat(1.0)),
-        false,
-    );
-    test_field_satisfies_op_impl(
-        value::Value::FloatValue(1.0),
-        Operator::GT,
-        Field::Float(OrderedFloat(0.0)),
-        true,
-    );
-    test_field_satisfies_op_impl(
-        value::Value::FloatValue(1.0),
-        Operator::GTE,
-        Field::Float(OrderedFloat(2.0)),
-        false,
-    );
-    test_field_satisfies_op_impl(
-        value::Value::FloatValue(1.0),
-        Operator::GTE,
-        Field::Float(OrderedFloat(1.0)),
-        true,
-    );
-    test_field_satisfies_op_impl(
-        value::Value::FloatValue(1.0),
-        Operator::GTE,
-        Field::Float(OrderedFloat(0.0)),
-        true,
-    );
+fn insert(values: Vec<Value>) -> Operation {
+    Operation {
+        typ: OperationType::Insert as _,
+        old: None,
+        new: Some(Record { values, version: 1 }),
+        new_id: None,
+        endpoint_name: "".into(),
+    }
+}
 
-    test_field_satisfies_op_impl(
-        value::Value::BoolValue(true),
-        Operator::LT,
-        Field::Boolean(true),
-        false,
-    );
-    test_field_satisfies_op_impl(
-        value::Value::BoolValue(true),
-        Operator::LT,
-        Field::Boolean(false),
-        false,
-    );
-    test_field_satisfies_op_impl(
-        value::Value::BoolValue(false),
-        Operator::LT,
-        Field::Boolean(true),
-        true,
-    );
-    test_field_satisfies_op_impl(
-        value::Value::BoolValue(false),
-        Operator::LT,
-        Field::Boolean(false),
-        false,
-    );
-    test_field_satisfies_op_impl(
-        value::Value::BoolValue(true),
-        Operator::LTE,
-        Field::Boolean(true),
-        true,
-    );
-    test_field_satisfies_op_impl(
-        value::Value::BoolValue(true),
-        Operator::LTE,
-        Field::Boolean(false),
-        false,
-    );
-    test_field_satisfies_op_impl(
-        value::Value::BoolValue(false),
-        Operator::LTE,
-        Field::Boolean(true),
-        true,
-    );
-    test_field_satisfies_op_impl(
-        value::Value::BoolValue(false),
-        Operator::LTE,
-        Field::Boolean(false),
-        true,
-    );
-    test_field_satisfies_op_impl(
-        value::Value::BoolValue(true),
-        Operator::EQ,
-        Field::Boolean(true),
-        true,
-    );
-    test_field_satisfies_op_impl(
-        value::Value::BoolValue(true),
-        Operator::EQ,
-        Field::Boolean(false),
-        false,
-    );
-    test_field_satisfies_op_impl(
-        value::Value::BoolValue(false),
-        Operator::EQ,
-        Field::Boolean(true),
-        false,
-    );
-    test_field_satisfies_op_impl(
-        value::Value::BoolValue(false),
-        Operator::EQ,
-        Field::Boolean(false),
-        true,
-    );
-    test_field_satisfies_op_impl(
-        value::Value::BoolValue(true),
-        Operator::GT,
-        Field::Boolean(true),
-        false,
-    );
-    test_field_satisfies_op_impl(
-        value::Value::BoolValue(true),
-        Operator::GT,
-        Field::Boolean(false),
-        true,
-    );
-    test_field_satisfies_op_impl(
-        value::Value::BoolValue(false),
-        Operator::GT,
-        Field::Boolean(true),
-        false,
-    );
-    test_field_satisfies_op_impl(
-        value::Value::BoolValue(false),
-        Operator::GT,
-        Field::Boolean(false),
-        false,
-    );
-    test_field_satisfies_op_impl(
-        value::Value::BoolValue(true),
-        Operator::GTE,
-        Field::Boolean(true),
-        true,
-    );
-    test_field_satisfies_op_impl(
-        value::Value::BoolValue(true),
-        Operator::GTE,
-        Field::Boolean(false),
-        true,
-    );
-    test_field_satisfies_op_impl(
-        value::Value::BoolValue(false),
-        Operator::GTE,
-        Field::Boolean(true),
-        false,
-    );
-    test_field_satisfies_op_impl(
-        value::Value::BoolValue(false),
-        Operator::GTE,
-        Field::Boolean(false),
-        true,
-    );
+#[test]
+fn test_matching_records() {
+    let (schema, secondary_indexes) = schema_1();
+    let op = insert(vec![int(1), string("mary has a little lamb"), null()]);
 
-    test_field_satisfies_op_impl(
-        value::Value::StringValue("b".into()),
-        Operator::LT,
-        Field::String("c".into()),
-        true,
-    );
-    test_field_satisfies_op_impl(
-        value::Value::StringValue("b".into()),
-        Operator::LT,
-        Field::String("b".into()),
-        false,
-    );
-    test_field_satisfies_op_impl(
-        value::Value::StringValue("b".into()),
-        Operator::LT,
-        Field::String("a".into()),
-        false,
-    );
-    test_field_satisfies_op_impl(
-        value::Value::StringValue("b".into()),
-        Operator::LTE,
-        Field::String("c".into()),
-        true,
-    );
-    test_field_satisfies_op_impl(
-        value::Value::StringValue("b".into()),
-        Operator::LTE,
-        Field::String("b".into()),
-        true,
-    );
-    test_field_satisfies_op_impl(
-        value::Value::StringValue("b".into()),
-        Operator::LTE,
-        Field::String("a".into()),
-        false,
-    );
-    test_field_satisfies_op_impl(
-        value::Value::StringValue("b".into()),
-        Operator::EQ,
-        Field::String("c".into()),
-        false,
-    );
-    test_field_satisfies_op_impl(
-        value::Value::StringValue("b".into()),
-        Operator::EQ,
-        Field::String("b".into()),
-        true,
-    );
-    test_field_satisfies_op_impl(
-        value::Value::StringValue("b".into()),
-        Operator::EQ,
-        Field::String("a".into()),
-        false,
-    );
-    test_field_satisfies_op_impl(
-        value::Value::StringValue("b".into()),
-        Operator::GT,
-        Field::String("c".into()),
-        false,
-    );
-    test_field_satisfies_op_impl(
-        value::Value::StringValue("b".into()),
-        Operator::GT,
-        Field::String("b".into()),
-        false,
-    );
-    test_field_satisfies_op_impl(
-        value::Value::StringValue("b".into()),
-        Operator::GT,
-        Field::String("a".into()),
-        true,
-    );
-    test_field_satisfies_op_impl(
-        value::Value::StringValue("b".into()),
-        Operator::GTE,
-        Field::String("c".into()),
-        false,
-    );
-    test_field_satisfies_op_impl(
-        value::Value::StringValue("b".into()),
-        Operator::GTE,
-        Field::String("b".into()),
-        true,
-    );
-    test_field_satisfies_op_impl(
-        value::Value::StringValue("b".into()),
-        Operator::GTE,
-        Field::String("a".into()),
-        true,
-    );
+    let check = |filter, expected| {
+        let filter = RecordFilter::new(&schema, &secondary_indexes, &filter).unwrap();
+        assert_eq!(matching_records(&op, &filter, &schema), (false, expected));
+    };
 
-    test_field_satisfies_op_impl(
-        value::Value::BytesValue(vec![1]),
-        Operator::LT,
-        Field::Binary(vec![2]),
-        true,
-    );
-    test_field_satisfies_op_impl(
-        value::Value::BytesValue(vec![1]),
-        Operator::LT,
-        Field::Binary(vec![1]),
-        false,
-    );
-    test_field_satisfies_op_impl(
-        value::Value::BytesValue(vec![1]),
-        Operator::LT,
-        Field::Binary(vec![0]),
-        false,
-    );
-    test_field_satisfies_op_impl(
-        value::Value::BytesValue(vec![1]),
-        Operator::LTE,
-        Field::Binary(vec![2]),
-        true,
-    );
-    test_field_satisfies_op_impl(
-        value::Value::BytesValue(vec![1]),
-        Operator::LTE,
-        Field::Binary(vec![1]),
-        true,
-    );
-    test_field_satisfies_op_impl(
-        value::Value::BytesValue(vec![1]),
-        Operator::LTE,
-        Field::Binary(vec![0]),
-        false,
-    );
-    test_field_satisfies_op_impl(
-        value::Value::BytesValue(vec![1]),
-        Operator::EQ,
-        Field::Binary(vec![2]),
-        false,
-    );
-    test_field_satisfies_op_impl(
-        value::Value::BytesValue(vec![1]),
-        Operator::EQ,
-        Field::Binary(vec![1]),
+    check(
+        FilterExpression::Simple("a".into(), Operator::EQ, json!(1)),
         true,
     );
-    test_field_satisfies_op_impl(
-        value::Value::BytesValue(vec![1]),
-        Operator::EQ,
-        Field::Binary(vec![0]),
-        false,
-    );
-    test_field_satisfies_op_impl(
-        value::Value::BytesValue(vec![1]),
-        Operator::GT,
-        Field::Binary(vec![2]),
-        false,
-    );
-    test_field_satisfies_op_impl(
-        value::Value::BytesValue(vec![1]),
-        Operator::GT,
-        Field::Binary(vec![1]),
+    check(
+        FilterExpression::Simple("a".into(), Operator::EQ, json!(2)),
         false,
     );
-    test_field_satisfies_op_impl(
-        value::Value::BytesValue(vec![1]),
-        Operator::GT,
-        Field::Binary(vec![0]),
+    check(
+        FilterExpression::And(vec![
+            FilterExpression::Simple("a".into(), Operator::EQ, json!(1)),
+            FilterExpression::Simple("b".into(), Operator::Contains, "lamb".into()),
+        ]),
         true,
     );
-    test_field_satisfies_op_impl(
-        value::Value::BytesValue(vec![1]),
-        Operator::GTE,
-        Field::Binary(vec![2]),
+    check(
+        FilterExpression::And(vec![
+            FilterExpression::Simple("a".into(), Operator::EQ, json!(1)),
+            FilterExpression::Simple("b".into(), Operator::EQ, "c".into()),
+        ]),
         false,
     );
-    test_field_satisfies_op_impl(
-        value::Value::BytesValue(vec![1]),
-        Operator::GTE,
-        Field::Binary(vec![1]),
-        true,
-    );
-    test_field_satisfies_op_impl(
-        value::Value::BytesValue(vec![1]),
-        Operator::GTE,
-        Field::Binary(vec![0]),
-        true,
-    );
-
-    test_field_satisfies_op_impl(
-        value::Value::FloatValue(1.0),
-        Operator::LT,
-        Field::Float(OrderedFloat(2.0)),
+    check(
+        FilterExpression::Or(vec![
+            FilterExpression::Simple("a".into(), Operator::EQ, json!(2)),
+            FilterExpression::Simple("a".into(), Operator::LT, json!(2)),
+        ]),
         true,
     );
-    test_field_satisfies_op_impl(
-        value::Value::FloatValue(1.0),
-        Operator::LT,
-        Field::Float(OrderedFloat(1.0)),
-        false,
-    );
-    test_field_satisfies_op_impl(
-        value::Value::FloatValue(1.0),
-        Operator::LT,
-        Field::Float(OrderedFloat(0.0)),
+    check(
+        FilterExpression::Not(Box::new(FilterExpression::Simple(
+            "a".into(),
+            Operator::EQ,
+            json!(1),
+        ))),
         false,
     );
-    test_field_satisfies_op_impl(
-        value::Value::FloatValue(1.0),
-        Operator::LTE,
-        Field::Float(OrderedFloat(2.0)),
+    check(
+        FilterExpression::Simple("a".into(), Operator::NE, json!(2)),
         true,
     );
-    test_field_satisfies_op_impl(
-        value::Value::FloatValue(1.0),
-        Operator::LTE,
-        Field::Float(OrderedFloat(1.0)),
+    check(
+        FilterExpression::Simple("a".into(), Operator::In, json!([2, 1])),
         true,
     );
-    test_field_satisfies_op_impl(
-        value::Value::FloatValue(1.0),
-        Operator::LTE,
-        Field::Float(OrderedFloat(0.0)),
+    check(
+        FilterExpression::Simple("a".into(), Operator::In, json!([3])),
         false,
     );
-    test_field_satisfies_op_impl(
-        value::Value::FloatValue(1.0),
-        Operator::EQ,
-        Field::Float(OrderedFloat(2.0)),
+
+    // Nulls never satisfy `$ne`, like in the cache.
+    check(
+        FilterExpression::Simple("c".into(), Operator::NE, json!(2)),
         false,
     );
-    test_field_satisfies_op_impl(
-        value::Value::FloatValue(1.0),
-        Operator::EQ,
-        Field::Float(OrderedFloat(1.0)),
+    check(
+        FilterExpression::Simple("c".into(), Operator::EQ, json!(null)),
         true,
     );
-    test_field_satisfies_op_impl(
-        value::Value::FloatValue(1.0),
-        Operator::EQ,
-        Field::Float(OrderedFloat(0.0)),
-        false,
-    );
-    test_field_satisfies_op_impl(
-        value::Value::FloatValue(1.0),
-        Operator::GT,
-        Field::Float(OrderedFloat(2.0)),
-        false,
-    );
-    test_field_satisfies_op_impl(
-        value::Value::FloatValue(1.0),
-        Operator::GT,
-        Field::Float(OrderedFloat(1.0)),
+    check(
+        FilterExpression::Simple("c".into(), Operator::GT, json!(0)),
         false,
     );
-    test_field_satisfies_op_impl(
-        value::Value::FloatValue(1.0),
-        Operator::GT,
-        Field::Float(OrderedFloat(0.0)),
+
+    check(
+        FilterExpression::Simple("b".into(), Operator::MatchesPhrase, "little lamb".into()),
         true,
     );
-    test_field_satisfies_op_impl(
-        value::Value::FloatValue(1.0),
-        Operator::GTE,
-        Field::Float(OrderedFloat(2.0)),
+    check(
+        FilterExpression::Simple("b".into(), Operator::MatchesPhrase, "lamb little".into()),
         false,
     );
-    test_field_satisfies_op_impl(
-        value::Value::FloatValue(1.0),
-        Operator::GTE,
-        Field::Float(OrderedFloat(1.0)),
-        true,
-    );
-    test_field_satisfies_op_impl(
-        value::Value::FloatValue(1.0),
-        Operator::GTE,
-        Field::Float(OrderedFloat(0.0)),
+    check(
+        FilterExpression::Simple("b".into(), Operator::MatchesPrefix, "lit".into()),
         true,
     );
-
-    test_field_satisfies_op_impl(
-        value::Value::StringValue("abc".into()),
-        Operator::Contains,
-        Field::String("abc".into()),
+    check(
+        FilterExpression::Simple("b".into(), Operator::MatchesAny, "big lamb".into()),
         true,
     );
-    test_field_satisfies_op_impl(
-        value::Value::StringValue("abc".into()),
-        Operator::Contains,
-        Field::String("d".into()),
-        false,
-    );
-
-    test_field_satisfies_op_impl(
-        value::Value::UintValue(0),
-        Operator::LT,
-        Field::Int(0),
-        false,
-    );
-    test_field_satisfies_op_impl(
-        value::Value::UintValue(0),
-        Operator::LTE,
-        Field::Int(0),
-        false,
-    );
-    test_field_satisfies_op_impl(
-        value::Value::UintValue(0),
-        Operator::EQ,
-        Field::Int(0),
-        false,
-    );
-    test_field_satisfies_op_impl(
-        value::Value::UintValue(0),
-        Operator::GT,
-        Field::Int(0),
-        false,
-    );
-    test_field_satisfies_op_impl(
-        value::Value::UintValue(0),
-        Operator::GTE,
-        Field::Int(0),
-        false,
-    );
-    test_field_satisfies_op_impl(
-        value::Value::UintValue(0),
-        Operator::Contains,
-        Field::Int(0),
+    check(
+        FilterExpression::Simple("b".into(), Operator::MatchesAll, "big lamb".into()),
         false,
     );
 }
 
 #[test]
-fn test_record_satisfies_filter() {
-    let schema = schema_1().0;
-    let record = Record {
-        values: vec![
-            Value {
-                value: Some(value::Value::IntValue(1)),
-            },
-            Value {
-                value: Some(value::Value::StringValue("b".into())),
-            },
-            Value {
-                value: Some(value::Value::IntValue(3)),
-            },
-        ],
+fn test_matching_records_of_updates() {
+    let (schema, secondary_indexes) = schema_1();
+    let old = Record {
+        values: vec![int(1), string("b"), int(3)],
         version: 1,
     };
-
-    let check = |filter, expected| {
-        assert_eq!(record_satisfies_filter(&record, &filter, &schema), expected);
+    let new = Record {
+        values: vec![int(2), string("b"), int(3)],
+        version: 1,
+    };
+    let op = |typ: OperationType, old: Option<&Record>| Operation {
+        typ: typ as _,
+        old: old.cloned(),
+        new: Some(new.clone()),
+        new_id: None,
+        endpoint_name: "".into(),
+    };
+    let check = |op: Operation, a: i64, expected| {
+        let filter = RecordFilter::new(
+            &schema,
+            &secondary_indexes,
+            &FilterExpression::Simple("a".into(), Operator::EQ, json!(a)),
+        )
+        .unwrap();
+        assert_eq!(matching_records(&op, &filter, &schema), expected);
     };
 
-    check(
-        FilterExpression::Simple("a".into(), Operator::EQ, json!(1)),
-        true,
-    );
-    check(
-        FilterExpression::Simple("a".into(), Operator::EQ, json!(2)),
-        false,
-    );
-    check(
-        FilterExpression::And(vec![
-            FilterExpression::Simple("a".into(), Operator::EQ, json!(1)),
-            FilterExpression::Simple("b".into(), Operator::EQ, "b".into()),
-        ]),
-        true,
-    );
-    check(
-        FilterExpression::And(vec![
-            FilterExpression::Simple("a".into(), Operator::EQ, json!(1)),
-            FilterExpression::Simple("b".into(), Operator::EQ, "c".into()),
-        ]),
-        false,
-    );
+    check(op(OperationType::Insert, None), 1, (false, false));
+    check(op(OperationType::Insert, None), 2, (false, true));
+    check(op(OperationType::Delete, None), 1, (false, false));
+    check(op(OperationType::Delete, None), 2, (false, true));
+    check(op(OperationType::Update, Some(&old)), 1, (true, false));
+    check(op(OperationType::Update, Some(&old)), 2, (false, true));
+    check(op(OperationType::Update, Some(&old)), 3, (false, false));
 }
 
 #[test]
-fn test_op_satisfies_filter() {
-    let schema = schema_1().0;
-    let old = Record {
+fn test_op_within_access_filter() {
+    let (schema, secondary_indexes) = schema_1();
+    let record = |tenant: Option<&str>| Record {
         values: vec![
             Value {
                 value: Some(value::Value::IntValue(1)),
             },
             Value {
-                value: Some(value::Value::StringValue("b".into())),
+                value: tenant.map(|tenant| value::Value::StringValue(tenant.into())),
             },
             Value {
                 value: Some(value::Value::IntValue(3)),
@@ -783,64 +181,69 @@ fn test_op_satisfies_filter() {
         ],
         version: 1,
     };
-    let new = Record {
-        values: vec![
-            Value {
-                value: Some(value::Value::IntValue(2)),
-            },
-            Value {
-                value: Some(value::Value::StringValue("b".into())),
-            },
-            Value {
-                value: Some(value::Value::IntValue(3)),
-            },
-        ],
-        version: 1,
+    let access_filter = RecordFilter::new(
+        &schema,
+        &secondary_indexes,
+        &FilterExpression::Simple("b".into(), Operator::EQ, "tenant_a".into()),
+    )
+    .unwrap();
+    let operation = |typ: OperationType, old, new| Operation {
+        typ: typ as _,
+        old,
+        new: Some(new),
+        new_id: None,
+        endpoint_name: "".into(),
     };
-    let filter1 = FilterExpression::Simple("a".into(), Operator::EQ, json!(1));
-    let filter2 = FilterExpression::Simple("a".into(), Operator::EQ, json!(2));
-    let filter3 = FilterExpression::Simple("a".into(), Operator::EQ, json!(3));
-
-    let check = |typ, old: Option<&Record>, new: &Record, filter, expected| {
+    let check = |op: Operation, expected: Option<Operation>| {
         assert_eq!(
-            op_satisfies_filter(
-                &Operation {
-                    typ: typ as _,
-                    old: old.cloned(),
-                    new: Some(new.clone()),
-                    new_id: None,
-                    endpoint_name: "".into()
-                },
-                filter,
-                &schema
-            ),
+            op_within_access_filter(op, Some(&access_filter), &schema),
             expected
         );
     };
 
-    check(OperationType::Insert, None, &new, Some(&filter1), false);
-    check(OperationType::Insert, None, &new, Some(&filter2), true);
-    check(OperationType::Delete, None, &new, Some(&filter1), false);
-    check(OperationType::Delete, None, &new, Some(&filter2), true);
+    let insert = operation(OperationType::Insert, None, record(Some("tenant_a")));
+    check(insert.clone(), Some(insert));
     check(
+        operation(OperationType::Insert, None, record(Some("tenant_b"))),
+        None,
+    );
+    check(operation(OperationType::Delete, None, record(None)), None);
+    let update = operation(
         OperationType::Update,
-        Some(&old),
-        &new,
-        Some(&filter1),
-        true,
+        Some(record(Some("tenant_a"))),
+        record(Some("tenant_a")),
     );
+    check(update.clone(), Some(update));
     check(
-        OperationType::Update,
-        Some(&old),
-        &new,
-        Some(&filter2),
-        true,
+        operation(
+            OperationType::Update,
+            Some(record(Some("tenant_a"))),
+            record(Some("tenant_b")),
+        ),
+        Some(operation(
+            OperationType::Delete,
+            None,
+            record(Some("tenant_a")),
+        )),
     );
     check(
-        OperationType::Update,
-        Some(&old),
-        &new,
-        Some(&filter3),
-        false,
+        operation(
+            OperationType::Update,
+            Some(record(Some("tenant_b"))),
+            record(Some("tenant_a")),
+        ),
+        Some(operation(
+            OperationType::Insert,
+            None,
+            record(Some("tenant_a")),
+        )),
+    );
+    check(
+        operation(
+            OperationType::Update,
+            Some(record(Some("tenant_b"))),
+            record(None),
+        ),
+        None,
     );
 }
@@ -1,5 +1,7 @@
-use dozer_cache::cache::expression::{default_limit_for_query, QueryExpression};
-use dozer_cache::cache::RecordWithId;
+use dozer_cache::cache::expression::{
+    default_limit_for_query, AggregateExpression, QueryExpression,
+};
+use dozer_cache::cache::{AggregateGroup, RecordFilter, RecordWithId};
 use dozer_cache::CacheReader;
 use dozer_types::grpc_types::types::Operation;
 use dozer_types::log::warn;
@@ -10,7 +12,7 @@ use tokio::sync::broadcast::Receiver;
 use tokio_stream::wrappers::ReceiverStream;
 use tonic::{Code, Response, Status};
 
-use crate::api_helper::{get_records, get_records_count};
+use crate::api_helper::{get_access_filter, get_records, get_records_aggregate, get_records_count};
 use crate::auth::Access;
 
 mod filter;
@@ -50,12 +52,12 @@ pub fn count(
     )?)
 }
 
-pub fn query<'a>(
-    reader: &'a CacheReader,
-    endpoint_name: &'a str,
+pub fn query(
+    reader: &CacheReader,
+    endpoint_name: &str,
     query: Option<&str>,
     access: Option<Access>,
-) -> Result<(&'a Schema, Vec<RecordWithId>), Status> {
+) -> Result<(Schema, Vec<RecordWithId>), Status> {
     let mut query = parse_query(query, QueryExpression::with_default_limit)?;
     if query.limit.is_none() {
         query.limit = Some(default_limit_for_query());
@@ -64,16 +66,30 @@ pub fn query<'a>(
     Ok((schema, records))
 }
 
+pub fn aggregate(
+    reader: &CacheReader,
+    endpoint_name: &str,
+    expression: &str,
+    access: Option<Access>,
+) -> Result<Vec<AggregateGroup>, Status> {
+    let mut expression =
+        serde_json::from_str::<AggregateExpression>(expression).map_err(from_error)?;
+    Ok(get_records_aggregate(
+        reader,
+        endpoint_name,
+        &mut expression,
+        access,
+    )?)
+}
+
 pub fn on_event<T: Send + 'static>(
     reader: &CacheReader,
     endpoint_name: &str,
     filter: Option<&str>,
     mut broadcast_receiver: Option<Receiver<Operation>>,
-    _access: Option<Access>,
+    access: Option<Access>,
     event_mapper: impl Fn(Operation) -> Option<T> + Send + Sync + 'static,
 ) -> Result<Response<ReceiverStream<T>>, Status> {
-    // TODO: Use access.
-
     if broadcast_receiver.is_none() {
         return Err(Status::unavailable(
             "on_event is not enabled. This is currently an experimental feature. Enable it in the config.",
@@ -90,11 +106,34 @@ pub fn on_event<T: Send + 'static>(
         }
         None => None,
     };
-    let schema = reader
+    let access_filter = get_access_filter(access)?;
+    if let Some(filter) = &filter {
+        access_filter
+            .check_filter(filter)
+            .map_err(|e| Status::permission_denied(e.to_string()))?;
+    }
+    let (schema, secondary_indexes) = reader
         .get_schema_and_indexes_by_name(endpoint_name)
-        .map_err(|_| Status::invalid_argument(endpoint_name))?
-        .0
-        .clone();
+        .map_err(|_| Status::invalid_argument(endpoint_name))?;
+    // Values of the fields that can't be read are cleared, so records keep the endpoint's fields.
+    let unreadable = schema
+        .fields
+        .iter()
+        .enumerate()
+        .filter(|(_, field)| access_filter.check_field(&field.name).is_err())
+        .map(|(index, _)| index)
+        .collect::<Vec<_>>();
+    // The client's filter is matched like the cache matches queries.
+    let filter = filter
+        .map(|filter| RecordFilter::new(schema, secondary_indexes, &filter))
+        .transpose()
+        .map_err(|e| Status::invalid_argument(e.to_string()))?;
+    let access_filter = access_filter
+        .filter
+        .map(|filter| RecordFilter::new(schema, secondary_indexes, &filter))
+        .transpose()
+        .map_err(from_error)?;
+    let schema = schema.clone();
 
     let (tx, rx) = tokio::sync::mpsc::channel(1);
 
@@ -104,7 +143,17 @@ pub fn on_event<T: Send + 'static>(
                 let event = broadcast_receiver.recv().await;
                 match event {
                     Ok(op) => {
-                        if filter::op_satisfies_filter(&op, filter.as_ref(), &schema) {
+                        let Some(op) =
+                            filter::op_within_access_filter(op, access_filter.as_ref(), &schema)
+                        else {
+                            continue;
+                        };
+                        let satisfied = filter.as_ref().map_or(true, |filter| {
+                            let (old, new) = filter::matching_records(&op, filter, &schema);
+                            old || new
+                        });
+                        if satisfied {
+                            let op = filter::clear_values(op, &unreadable);
                             if let Some(event) = event_mapper(op) {
                                 if (tx.send(event).await).is_err() {
                                     // receiver dropped
@@ -5,6 +5,7 @@ use crate::generator::protoc::generator::{
 use crate::grpc::types_helper::map_record;
 use dozer_cache::cache::RecordWithId;
 use dozer_types::grpc_types::types as GrpcTypes;
+use dozer_types::types::{Field, Schema};
 use prost_reflect::{DynamicMessage, ReflectMessage, Value};
 
 use super::TypedResponse;
@@ -156,6 +157,43 @@ pub fn query_response_to_typed_response(
     TypedResponse::new(msg)
 }
 
+/// Moves the values of the projected fields to their positions in `schema`, because typed messages
+/// have all fields. Fields that weren't selected are left unset.
+pub fn unproject_records(
+    records: Vec<RecordWithId>,
+    projected_schema: &Schema,
+    schema: &Schema,
+) -> Vec<RecordWithId> {
+    if projected_schema.fields.len() == schema.fields.len() {
+        return records;
+    }
+
+    let positions = schema
+        .fields
+        .iter()
+        .map(|field| {
+            projected_schema
+                .fields
+                .iter()
+                .position(|projected_field| projected_field.name == field.name)
+        })
+        .collect::<Vec<_>>();
+    records
+        .into_iter()
+        .map(|mut record| {
+            let mut values = std::mem::take(&mut record.record.values);
+            record.record.values = positions
+                .iter()
+                .map(|position| match position {
+                    Some(position) => std::mem::replace(&mut values[*position], Field::Null),
+                    None => Field::Null,
+                })
+                .collect();
+            record
+        })
+        .collect()
+}
+
 pub fn token_response(token: String, response_desc: TokenResponseDesc) -> TypedResponse {
     let mut msg = DynamicMessage::new(response_desc.message);
     msg.set_field(
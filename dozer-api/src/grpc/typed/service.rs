@@ -2,7 +2,7 @@ use super::{
     codec::TypedCodec,
     helper::{
         count_response_to_typed_response, on_event_to_typed_response,
-        query_response_to_typed_response, token_response,
+        query_response_to_typed_response, token_response, unproject_records,
     },
     DynamicMessage, TypedResponse,
 };
@@ -324,7 +324,12 @@ fn query(
     let mut parts = request.into_parts();
     let (query, access) = parse_request(&mut parts)?;
 
-    let (_, records) = shared_impl::query(reader, endpoint_name, query.as_deref(), access)?;
+    let (projected_schema, records) =
+        shared_impl::query(reader, endpoint_name, query.as_deref(), access)?;
+    let (schema, _) = reader
+        .get_schema_and_indexes_by_name(endpoint_name)
+        .map_err(|_| Status::invalid_argument(endpoint_name))?;
+    let records = unproject_records(records, &projected_schema, schema);
     let res = query_response_to_typed_response(records, response_desc);
     Ok(Response::new(res))
 }
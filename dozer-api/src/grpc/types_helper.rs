@@ -1,9 +1,15 @@
-use dozer_cache::cache::RecordWithId as CacheRecordWithId;
+use dozer_cache::cache::{
+    AggregateGroup as CacheAggregateGroup, RecordWithId as CacheRecordWithId,
+};
+use dozer_types::chrono::{NaiveDate, TimeZone, Utc};
 use dozer_types::ordered_float::OrderedFloat;
 use dozer_types::rust_decimal::Decimal;
-use dozer_types::types::{Field, FieldType, Record as DozerRecord, DATE_FORMAT};
+use dozer_types::types::{
+    DozerPoint, Field, FieldType, Record as DozerRecord, Schema, DATE_FORMAT,
+};
 use prost_reflect::prost_types::Timestamp;
 
+use dozer_types::grpc_types::common::AggregateGroup;
 use dozer_types::grpc_types::types::{
     value, Operation, OperationType, PointType, Record, RecordWithId, RustDecimal, Type, Value,
 };
@@ -57,6 +63,18 @@ fn record_to_internal_record(record: DozerRecord) -> Record {
     }
 }
 
+/// Maps an operation record back to a record of `schema`, so it can be matched like the records in
+/// the cache. Decimals lose their scale, which operations don't carry.
+pub fn map_internal_record(record: &Record, schema: &Schema) -> DozerRecord {
+    let values = record
+        .values
+        .iter()
+        .zip(&schema.fields)
+        .map(|(value, field)| prost_value_to_field(value, field.typ))
+        .collect();
+    DozerRecord::new(schema.identifier, values, Some(record.version))
+}
+
 pub fn map_record(record: CacheRecordWithId) -> RecordWithId {
     RecordWithId {
         id: record.id,
@@ -64,6 +82,13 @@ pub fn map_record(record: CacheRecordWithId) -> RecordWithId {
     }
 }
 
+pub fn map_aggregate_group(group: CacheAggregateGroup) -> AggregateGroup {
+    AggregateGroup {
+        key: group.key.into_iter().map(field_to_prost_value).collect(),
+        values: group.values.into_iter().map(field_to_prost_value).collect(),
+    }
+}
+
 fn map_x_y_to_prost_coord_map((x, y): (OrderedFloat<f64>, OrderedFloat<f64>)) -> Value {
     Value {
         value: Some(value::Value::PointValue(PointType { x: x.0, y: y.0 })),
@@ -126,6 +151,33 @@ fn field_to_prost_value(f: Field) -> Value {
     }
 }
 
+fn prost_value_to_field(value: &Value, typ: FieldType) -> Field {
+    let Some(value) = &value.value else {
+        return Field::Null;
+    };
+    match (value, typ) {
+        (value::Value::UintValue(n), _) => Field::UInt(*n),
+        (value::Value::IntValue(n), _) => Field::Int(*n),
+        (value::Value::FloatValue(n), _) => Field::Float(OrderedFloat(*n)),
+        (value::Value::BoolValue(n), _) => Field::Boolean(*n),
+        (value::Value::StringValue(s), FieldType::Text) => Field::Text(s.clone()),
+        (value::Value::StringValue(s) | value::Value::DateValue(s), FieldType::Date) => {
+            NaiveDate::parse_from_str(s, DATE_FORMAT).map_or(Field::Null, Field::Date)
+        }
+        (value::Value::StringValue(s) | value::Value::DateValue(s), _) => Field::String(s.clone()),
+        (value::Value::BytesValue(b), FieldType::Bson) => Field::Bson(b.clone()),
+        (value::Value::BytesValue(b), _) => Field::Binary(b.clone()),
+        (value::Value::DecimalValue(d), _) => {
+            Field::Decimal(Decimal::from_parts(d.lo, d.mid, d.hi, d.flags != 0, 0))
+        }
+        (value::Value::TimestampValue(ts), _) => Utc
+            .timestamp_opt(ts.seconds, ts.nanos as u32)
+            .single()
+            .map_or(Field::Null, |ts| Field::Timestamp(ts.into())),
+        (value::Value::PointValue(point), _) => Field::Point(DozerPoint::from((point.x, point.y))),
+    }
+}
+
 pub fn map_field_definitions(
     fields: Vec<dozer_types::types::FieldDefinition>,
 ) -> Vec<dozer_types::grpc_types::types::FieldDefinition> {
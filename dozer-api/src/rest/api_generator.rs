@@ -2,7 +2,9 @@ use std::sync::Arc;
 
 use actix_web::web::ReqData;
 use actix_web::{web, HttpResponse};
-use dozer_cache::cache::expression::{default_limit_for_query, QueryExpression, Skip};
+use dozer_cache::cache::expression::{
+    default_limit_for_query, AggregateExpression, QueryExpression, Skip,
+};
 use dozer_cache::cache::{index, RecordWithId};
 use dozer_cache::CacheReader;
 use dozer_types::chrono::SecondsFormat;
@@ -14,7 +16,9 @@ use dozer_types::ordered_float::OrderedFloat;
 use dozer_types::types::{Field, Schema, DATE_FORMAT};
 use openapiv3::OpenAPI;
 
-use crate::api_helper::{get_record, get_records, get_records_count};
+use crate::api_helper::{
+    get_record, get_record_history, get_records, get_records_aggregate, get_records_count,
+};
 use crate::generator::oapi::generator::OpenApiGenerator;
 use crate::RoCacheEndpoint;
 use crate::{auth::Access, errors::ApiError};
@@ -55,12 +59,55 @@ pub async fn get(
     path: web::Path<String>,
 ) -> Result<HttpResponse, ApiError> {
     let cache_reader = &cache_endpoint.cache_reader();
+    let key = parse_primary_key(cache_reader, &cache_endpoint.endpoint.name, path.as_str())?;
+    let (schema, record) = get_record(cache_reader, &key, access.map(|a| a.into_inner()))?;
+
+    Ok(record_to_map(record, &schema).map(|map| HttpResponse::Ok().json(map))?)
+}
+
+// Generated history function, returning the versions of a record with the commits they were current between
+pub async fn history(
+    access: Option<ReqData<Access>>,
+    cache_endpoint: ReqData<Arc<RoCacheEndpoint>>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    let cache_reader = &cache_endpoint.cache_reader();
+    let endpoint_name = &cache_endpoint.endpoint.name;
+    let key = parse_primary_key(cache_reader, endpoint_name, path.as_str())?;
+    let (schema, versions) = get_record_history(
+        cache_reader,
+        endpoint_name,
+        &key,
+        access.map(|a| a.into_inner()),
+    )?;
+
+    let mut maps = vec![];
+    for version in versions {
+        let mut map = record_to_map(version.record, &schema)?;
+        map.insert(
+            "__dozer_valid_from".to_string(),
+            Value::from(version.valid_from),
+        );
+        map.insert(
+            "__dozer_valid_to".to_string(),
+            Value::from(version.valid_to),
+        );
+        maps.push(map);
+    }
+    Ok(HttpResponse::Ok().json(maps))
+}
+
+/// Parses the primary key of a record from the path, which only single field keys support.
+fn parse_primary_key(
+    cache_reader: &CacheReader,
+    endpoint_name: &str,
+    key: &str,
+) -> Result<Vec<u8>, ApiError> {
     let schema = &cache_reader
-        .get_schema_and_indexes_by_name(&cache_endpoint.endpoint.name)
+        .get_schema_and_indexes_by_name(endpoint_name)
         .map_err(ApiError::SchemaNotFound)?
         .0;
 
-    let key = path.as_str();
     let key = if schema.primary_index.is_empty() {
         return Err(ApiError::NoPrimaryKey);
     } else if schema.primary_index.len() == 1 {
@@ -70,14 +117,7 @@ pub async fn get(
         return Err(ApiError::MultiIndexFetch(key.to_string()));
     };
 
-    let key = index::get_primary_key(&[0], &[key]);
-    let record = get_record(
-        &cache_endpoint.cache_reader(),
-        &key,
-        access.map(|a| a.into_inner()),
-    )?;
-
-    Ok(record_to_map(record, schema).map(|map| HttpResponse::Ok().json(map))?)
+    Ok(index::get_primary_key(&[0], &[key]))
 }
 
 // Generated list function for multiple records with a default query expression
@@ -145,6 +185,37 @@ pub async fn query(
         .map(|maps| HttpResponse::Ok().json(maps))
 }
 
+// Generated aggregate function, returning one object per group with the `$group_by` fields and the aggregates
+pub async fn aggregate(
+    access: Option<ReqData<Access>>,
+    cache_endpoint: ReqData<Arc<RoCacheEndpoint>>,
+    aggregate_info: web::Json<Value>,
+) -> Result<HttpResponse, ApiError> {
+    let mut expression = serde_json::from_value::<AggregateExpression>(aggregate_info.0)
+        .map_err(ApiError::map_deserialization_error)?;
+
+    let groups = get_records_aggregate(
+        &cache_endpoint.cache_reader(),
+        &cache_endpoint.endpoint.name,
+        &mut expression,
+        access.map(|a| a.into_inner()),
+    )?;
+    let maps = groups
+        .into_iter()
+        .map(|group| {
+            let mut map = IndexMap::new();
+            for (name, field) in expression.group_by.iter().zip(group.key) {
+                map.insert(name.clone(), field_to_json_value(field));
+            }
+            for (aggregate, field) in expression.aggregates.iter().zip(group.values) {
+                map.insert(aggregate.to_string(), field_to_json_value(field));
+            }
+            map
+        })
+        .collect::<Vec<_>>();
+    Ok(HttpResponse::Ok().json(maps))
+}
+
 /// Get multiple records
 fn get_records_map(
     access: Option<ReqData<Access>>,
@@ -160,7 +231,7 @@ fn get_records_map(
         access.map(|a| a.into_inner()),
     )?;
     for record in records.into_iter() {
-        let map = record_to_map(record, schema)?;
+        let map = record_to_map(record, &schema)?;
         maps.push(map);
     }
     Ok(maps)
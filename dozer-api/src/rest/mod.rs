@@ -115,8 +115,10 @@ impl ApiServer {
                         })
                         .route("/count", web::post().to(api_generator::count))
                         .route("/query", web::post().to(api_generator::query))
+                        .route("/aggregate", web::post().to(api_generator::aggregate))
                         .route("/oapi", web::post().to(api_generator::generate_oapi))
                         .route("/{id}", web::get().to(api_generator::get))
+                        .route("/{id}/history", web::get().to(api_generator::history))
                         .route("/", web::get().to(api_generator::list))
                         .route("", web::get().to(api_generator::list)),
                 )
@@ -118,3 +118,59 @@ async fn _call_auth_token_api(
     let req = req.to_request();
     actix_web::test::call_service(&app, req).await
 }
+
+#[actix_web::test]
+async fn access_filter_rejects_other_records() {
+    let secret = "secret";
+    let endpoint = test_utils::get_endpoint();
+    let cache_manager = test_utils::initialize_cache(&endpoint.name, None);
+    let api_server = ApiServer::create_app_entry(
+        Some(ApiSecurity::Jwt(secret.to_string())),
+        CorsOptions::Permissive,
+        vec![Arc::new(
+            RoCacheEndpoint::new(&*cache_manager, endpoint.clone()).unwrap(),
+        )],
+    );
+    let app = actix_web::test::init_service(api_server).await;
+
+    let access = dozer_types::serde_json::from_value(
+        json!({"Custom":{"get_records":{"filter":{"film_id":268},"fields":[]}}}),
+    )
+    .unwrap();
+    let token = Authorizer::new(secret, None, None)
+        .generate_token(access, None)
+        .unwrap();
+    let authorization = ("Authorization", format!("Bearer {token}"));
+
+    let get = |id: u64| {
+        actix_web::test::TestRequest::get()
+            .uri(&format!("{}/{id}", endpoint.path))
+            .append_header(authorization.clone())
+            .to_request()
+    };
+    let res = actix_web::test::call_service(&app, get(268)).await;
+    assert!(res.status().is_success());
+    let res = actix_web::test::call_service(&app, get(524)).await;
+    assert!(!res.status().is_success());
+
+    for (query, expected) in [
+        (json!({}), vec![json!(268)]),
+        (json!({"$filter": {"film_id": 524}}), vec![]),
+    ] {
+        let req = actix_web::test::TestRequest::post()
+            .uri(&format!("{}/query", endpoint.path))
+            .append_header(authorization.clone())
+            .set_json(query)
+            .to_request();
+        let res = actix_web::test::call_service(&app, req).await;
+        assert!(res.status().is_success());
+        let body: Value = actix_web::test::read_body_json(res).await;
+        let ids = body
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|record| record["film_id"].clone())
+            .collect::<Vec<_>>();
+        assert_eq!(ids, expected);
+    }
+}
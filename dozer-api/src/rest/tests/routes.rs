@@ -19,7 +19,7 @@ fn test_generate_oapi() {
     );
     let generated = oapi_generator.generate_oas3();
 
-    assert_eq!(generated.paths.paths.len(), 4, " paths must be generated");
+    assert_eq!(generated.paths.paths.len(), 6, " paths must be generated");
 }
 
 #[actix_web::test]
@@ -126,6 +126,87 @@ async fn count_and_query_route() {
     assert_eq!(records.len(), 11);
 }
 
+#[actix_web::test]
+async fn query_select_route() {
+    let endpoint = test_utils::get_endpoint();
+    let cache_manager = test_utils::initialize_cache(&endpoint.name, None);
+    let api_server = ApiServer::create_app_entry(
+        None,
+        CorsOptions::Permissive,
+        vec![Arc::new(
+            RoCacheEndpoint::new(&*cache_manager, endpoint.clone()).unwrap(),
+        )],
+    );
+    let app = actix_web::test::init_service(api_server).await;
+
+    let (count, records) = count_and_query(
+        &endpoint.path,
+        &app,
+        Some(json!({"$filter": {"film_id": 268}, "$select": ["release_year", "film_id"]})),
+    )
+    .await;
+    assert_eq!(count, 1);
+    let mut keys = records[0].as_object().unwrap().keys().collect::<Vec<_>>();
+    keys.sort();
+    assert_eq!(
+        keys,
+        vec![
+            "__dozer_record_id",
+            "__dozer_record_version",
+            "film_id",
+            "release_year"
+        ]
+    );
+
+    // Unknown field.
+    let req = actix_web::test::TestRequest::post()
+        .uri(&format!("{}/query", endpoint.path))
+        .set_json(json!({"$select": ["title"]}))
+        .to_request();
+    let res = actix_web::test::call_service(&app, req).await;
+    assert!(!res.status().is_success());
+}
+
+#[actix_web::test]
+async fn aggregate_route() {
+    let endpoint = test_utils::get_endpoint();
+    let cache_manager = test_utils::initialize_cache(&endpoint.name, None);
+    let api_server = ApiServer::create_app_entry(
+        None,
+        CorsOptions::Permissive,
+        vec![Arc::new(
+            RoCacheEndpoint::new(&*cache_manager, endpoint.clone()).unwrap(),
+        )],
+    );
+    let app = actix_web::test::init_service(api_server).await;
+
+    let req = actix_web::test::TestRequest::post()
+        .uri(&format!("{}/aggregate", endpoint.path))
+        .set_json(json!({
+            "$filter": {"release_year": 2006},
+            "$group_by": ["release_year"],
+            "$aggregates": ["$count", {"$max": "film_id"}]
+        }))
+        .to_request();
+    let res = actix_web::test::call_service(&app, req).await;
+    assert!(res.status().is_success());
+
+    let body: Value = actix_web::test::read_body_json(res).await;
+    let groups = body.as_array().unwrap();
+    assert_eq!(groups.len(), 1);
+    assert_eq!(groups[0]["release_year"], json!(2006));
+    assert_eq!(groups[0]["$count"], json!(52));
+    assert!(groups[0]["$max(film_id)"].is_u64());
+
+    // Sum of a string field.
+    let req = actix_web::test::TestRequest::post()
+        .uri(&format!("{}/aggregate", endpoint.path))
+        .set_json(json!({"$aggregates": [{"$sum": "description"}]}))
+        .to_request();
+    let res = actix_web::test::call_service(&app, req).await;
+    assert!(!res.status().is_success());
+}
+
 #[actix_web::test]
 async fn get_route() {
     let endpoint = test_utils::get_endpoint();
@@ -154,3 +235,42 @@ async fn get_route() {
         "Must be equal"
     );
 }
+
+#[actix_web::test]
+async fn history_route() {
+    let endpoint = test_utils::get_endpoint();
+    let cache_manager = test_utils::initialize_cache(&endpoint.name, None);
+    let api_server = ApiServer::create_app_entry(
+        None,
+        CorsOptions::Permissive,
+        vec![Arc::new(
+            RoCacheEndpoint::new(&*cache_manager, endpoint.clone()).unwrap(),
+        )],
+    );
+    let app = actix_web::test::init_service(api_server).await;
+    let req = actix_web::test::TestRequest::get()
+        .uri(&format!("{}/{}/history", endpoint.path, 268))
+        .to_request();
+
+    let res = actix_web::test::call_service(&app, req).await;
+    assert!(res.status().is_success());
+
+    let body: Value = actix_web::test::read_body_json(res).await;
+    let versions = body.as_array().expect("Must return an array");
+    assert_eq!(versions.len(), 1);
+    assert_eq!(versions[0]["film_id"], json!(268));
+    assert_eq!(versions[0]["__dozer_valid_from"], json!(1));
+    assert_eq!(versions[0]["__dozer_valid_to"], Value::Null);
+
+    // The records were inserted by the first commit.
+    for (as_of, num_records) in [(0, 0), (1, 1)] {
+        let req = actix_web::test::TestRequest::post()
+            .uri(&format!("{}/query", endpoint.path))
+            .set_json(json!({ "$as_of": as_of, "$filter": { "film_id": 268 } }))
+            .to_request();
+        let res = actix_web::test::call_service(&app, req).await;
+        assert!(res.status().is_success());
+        let body: Value = actix_web::test::read_body_json(res).await;
+        assert_eq!(body.as_array().unwrap().len(), num_records);
+    }
+}
@@ -64,8 +64,11 @@ pub fn get_endpoint() -> ApiEndpoint {
         path: "/films".to_string(),
         index: Some(ApiIndex {
             primary_key: vec!["film_id".to_string()],
+            ..Default::default()
         }),
         table_name: "film".to_string(),
+        retention: None,
+        history: true,
     }
 }
 
@@ -112,6 +115,7 @@ pub fn initialize_cache(
             secondary_indexes,
         )])
         .unwrap();
+    cache.set_history(schema_name, true).unwrap();
     let records = get_sample_records(schema);
     for mut record in records {
         cache.insert(&mut record.record).unwrap();
@@ -1,3 +1,5 @@
+use std::fmt::{Display, Formatter};
+
 use dozer_types::serde::{Deserialize, Serialize};
 use dozer_types::serde_json::Value;
 mod query_helper;
@@ -18,12 +20,21 @@ impl Default for Skip {
     }
 }
 
+/// Name of the sort option sorting records by their relevance to the full text filters, using
+/// BM25 scores.
+pub const SCORE_SORT_FIELD: &str = "$score";
+
 #[derive(Clone, Debug, PartialEq)]
 pub struct QueryExpression {
     pub filter: Option<FilterExpression>,
     pub order_by: SortOptions,
     pub limit: Option<usize>,
     pub skip: Skip,
+    /// Names of the fields to return, all fields if `None`. Applied by `CacheReader`.
+    pub select: Option<Vec<String>>,
+    /// Commit whose state is queried, the latest state if `None`. Only schemas keeping history
+    /// can be queried as of a commit.
+    pub as_of: Option<u64>,
 }
 
 pub fn default_limit_for_query() -> usize {
@@ -37,6 +48,8 @@ impl QueryExpression {
             order_by: Default::default(),
             limit: Some(default_limit_for_query()),
             skip: Default::default(),
+            select: None,
+            as_of: None,
         }
     }
 
@@ -46,6 +59,8 @@ impl QueryExpression {
             order_by: Default::default(),
             limit: None,
             skip: Default::default(),
+            select: None,
+            as_of: None,
         }
     }
 }
@@ -68,6 +83,8 @@ impl QueryExpression {
             order_by: SortOptions(order_by),
             limit,
             skip,
+            select: None,
+            as_of: None,
         }
     }
 }
@@ -77,6 +94,8 @@ pub enum FilterExpression {
     // a = 1, a containts "s", a > 4
     Simple(String, Operator, Value),
     And(Vec<FilterExpression>),
+    Or(Vec<FilterExpression>),
+    Not(Box<FilterExpression>),
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
@@ -92,35 +111,135 @@ pub enum Operator {
     GT,
     #[serde(rename = "$gte")]
     GTE,
+    #[serde(rename = "$ne")]
+    NE,
+    /// The value is an array, matching any of its elements.
+    #[serde(rename = "$in")]
+    In,
     #[serde(rename = "$contains")]
     Contains,
     #[serde(rename = "$matches_any")]
     MatchesAny,
     #[serde(rename = "$matches_all")]
     MatchesAll,
+    /// The value is a string, matching text with its terms in the same order, next to each other.
+    #[serde(rename = "$matches_phrase")]
+    MatchesPhrase,
+    /// The value is a string, matching text with a term starting with it.
+    #[serde(rename = "$matches_prefix")]
+    MatchesPrefix,
+    /// The value is `{"center": point, "meters": number}`, matching points within that geodesic
+    /// distance of the center.
+    #[serde(rename = "$within_radius")]
+    WithinRadius,
+    /// The value is `{"min": point, "max": point}`, matching points whose longitude (`x`) and
+    /// latitude (`y`) are within the box. A box with `min.x > max.x` crosses the antimeridian.
+    #[serde(rename = "$within_bbox")]
+    WithinBbox,
+    /// The value is a point, matching all points and sorting them by geodesic distance to it.
+    #[serde(rename = "$nearest")]
+    Nearest,
 }
 
 impl Operator {
     pub fn supported_by_sorted_inverted(&self) -> bool {
         match self {
             Operator::LT | Operator::LTE | Operator::EQ | Operator::GT | Operator::GTE => true,
-            Operator::Contains | Operator::MatchesAny | Operator::MatchesAll => false,
+            Operator::NE
+            | Operator::In
+            | Operator::Contains
+            | Operator::MatchesAny
+            | Operator::MatchesAll
+            | Operator::MatchesPhrase
+            | Operator::MatchesPrefix
+            | Operator::WithinRadius
+            | Operator::WithinBbox
+            | Operator::Nearest => false,
         }
     }
 
     pub fn supported_by_full_text(&self) -> bool {
         match self {
-            Operator::LT | Operator::LTE | Operator::EQ | Operator::GT | Operator::GTE => false,
-            Operator::Contains | Operator::MatchesAny | Operator::MatchesAll => true,
+            Operator::LT
+            | Operator::LTE
+            | Operator::EQ
+            | Operator::GT
+            | Operator::GTE
+            | Operator::NE
+            | Operator::In
+            | Operator::WithinRadius
+            | Operator::WithinBbox
+            | Operator::Nearest => false,
+            Operator::Contains
+            | Operator::MatchesAny
+            | Operator::MatchesAll
+            | Operator::MatchesPhrase
+            | Operator::MatchesPrefix => true,
         }
     }
 
+    pub fn supported_by_spatial(&self) -> bool {
+        matches!(
+            self,
+            Operator::WithinRadius | Operator::WithinBbox | Operator::Nearest
+        )
+    }
+
     pub fn is_range_operator(&self) -> bool {
         match self {
             Operator::LT | Operator::LTE | Operator::GT | Operator::GTE => true,
-            Operator::EQ | Operator::Contains | Operator::MatchesAny | Operator::MatchesAll => {
-                false
-            }
+            Operator::EQ
+            | Operator::NE
+            | Operator::In
+            | Operator::Contains
+            | Operator::MatchesAny
+            | Operator::MatchesAll
+            | Operator::MatchesPhrase
+            | Operator::MatchesPrefix
+            | Operator::WithinRadius
+            | Operator::WithinBbox
+            | Operator::Nearest => false,
+        }
+    }
+}
+
+/// Aggregates of the records matching `filter`, grouped by the values of the `group_by` fields.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(crate = "dozer_types::serde")]
+pub struct AggregateExpression {
+    #[serde(rename = "$filter", default, skip_serializing_if = "Option::is_none")]
+    pub filter: Option<FilterExpression>,
+    #[serde(rename = "$group_by", default, skip_serializing_if = "Vec::is_empty")]
+    pub group_by: Vec<String>,
+    #[serde(rename = "$aggregates")]
+    pub aggregates: Vec<Aggregate>,
+}
+
+/// An aggregate function. `null` values are ignored by all functions but `$count`, which counts
+/// records.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(crate = "dozer_types::serde")]
+pub enum Aggregate {
+    #[serde(rename = "$count")]
+    Count,
+    #[serde(rename = "$sum")]
+    Sum(String),
+    #[serde(rename = "$avg")]
+    Avg(String),
+    #[serde(rename = "$min")]
+    Min(String),
+    #[serde(rename = "$max")]
+    Max(String),
+}
+
+impl Display for Aggregate {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Aggregate::Count => write!(f, "$count"),
+            Aggregate::Sum(field) => write!(f, "$sum({field})"),
+            Aggregate::Avg(field) => write!(f, "$avg({field})"),
+            Aggregate::Min(field) => write!(f, "$min({field})"),
+            Aggregate::Max(field) => write!(f, "$max({field})"),
         }
     }
 }
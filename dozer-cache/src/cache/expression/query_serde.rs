@@ -29,6 +29,10 @@ impl<'de> Deserialize<'de> for FilterExpression {
                 while let Some(key) = map.next_key::<String>()? {
                     if key == "$and" {
                         expressions.push(FilterExpression::And(map.next_value()?));
+                    } else if key == "$or" {
+                        expressions.push(FilterExpression::Or(map.next_value()?));
+                    } else if key == "$not" {
+                        expressions.push(FilterExpression::Not(Box::new(map.next_value()?)));
                     } else {
                         let operator_and_value = map.next_value::<OperatorAndValue>()?;
                         expressions.push(FilterExpression::Simple(
@@ -65,6 +69,16 @@ impl Serialize for FilterExpression {
                 state.serialize_entry("$and", &expressions)?;
                 state.end()
             }
+            FilterExpression::Or(expressions) => {
+                let mut state = serializer.serialize_map(Some(1))?;
+                state.serialize_entry("$or", &expressions)?;
+                state.end()
+            }
+            FilterExpression::Not(expression) => {
+                let mut state = serializer.serialize_map(Some(1))?;
+                state.serialize_entry("$not", expression)?;
+                state.end()
+            }
         }
     }
 }
@@ -133,6 +147,8 @@ impl<'de> Deserialize<'de> for QueryExpression {
                 let mut order_by = None;
                 let mut limit = None;
                 let mut skip = None;
+                let mut select = None;
+                let mut as_of = None;
                 while let Some(key) = map.next_key::<String>()? {
                     match key.as_str() {
                         "$filter" => {
@@ -156,6 +172,12 @@ impl<'de> Deserialize<'de> for QueryExpression {
                             }
                             skip = Some(Skip::After(map.next_value()?));
                         }
+                        "$select" => {
+                            select = Some(map.next_value()?);
+                        }
+                        "$as_of" => {
+                            as_of = Some(map.next_value()?);
+                        }
                         _ => {}
                     }
                 }
@@ -164,6 +186,8 @@ impl<'de> Deserialize<'de> for QueryExpression {
                     order_by: order_by.unwrap_or_default(),
                     limit,
                     skip: skip.unwrap_or_default(),
+                    select,
+                    as_of,
                 })
             }
         }
@@ -176,7 +200,7 @@ impl Serialize for QueryExpression {
     where
         S: Serializer,
     {
-        let mut state = serializer.serialize_map(Some(4))?;
+        let mut state = serializer.serialize_map(Some(6))?;
         if let Some(filter) = &self.filter {
             state.serialize_entry("$filter", filter)?;
         }
@@ -196,6 +220,12 @@ impl Serialize for QueryExpression {
                 state.serialize_entry("$after", &after)?;
             }
         }
+        if let Some(select) = &self.select {
+            state.serialize_entry("$select", select)?;
+        }
+        if let Some(as_of) = self.as_of {
+            state.serialize_entry("$as_of", &as_of)?;
+        }
         state.end()
     }
 }
@@ -2,6 +2,7 @@ use crate::cache::expression::FilterExpression;
 use crate::cache::expression::Operator;
 use crate::cache::expression::Skip;
 use crate::cache::expression::SortOptions;
+use crate::cache::expression::{Aggregate, AggregateExpression};
 use crate::cache::expression::{
     QueryExpression,
     SortDirection::{Ascending, Descending},
@@ -19,9 +20,16 @@ fn test_operators() {
         (Operator::LT, "$lt"),
         (Operator::LTE, "$lte"),
         (Operator::EQ, "$eq"),
+        (Operator::NE, "$ne"),
+        (Operator::In, "$in"),
         (Operator::Contains, "$contains"),
         (Operator::MatchesAny, "$matches_any"),
         (Operator::MatchesAll, "$matches_all"),
+        (Operator::MatchesPhrase, "$matches_phrase"),
+        (Operator::MatchesPrefix, "$matches_prefix"),
+        (Operator::WithinRadius, "$within_radius"),
+        (Operator::WithinBbox, "$within_bbox"),
+        (Operator::Nearest, "$nearest"),
     ];
     for (op, op_str) in operators {
         let fetched = serde_json::from_value(Value::String(op_str.to_string())).unwrap();
@@ -84,6 +92,14 @@ fn test_filter_query_deserialize_simple() {
         json!({ "a": null }),
         FilterExpression::Simple("a".to_string(), Operator::EQ, Value::Null),
     );
+    test_deserialize_filter(
+        json!({"a": {"$within_radius": {"center": {"x": 1, "y": 2}, "meters": 100}}}),
+        FilterExpression::Simple(
+            "a".to_string(),
+            Operator::WithinRadius,
+            json!({"center": {"x": 1, "y": 2}, "meters": 100}),
+        ),
+    );
 
     test_deserialize_filter_error(json!({"a":  []}));
     test_deserialize_filter_error(json!({"a":  {}}));
@@ -129,6 +145,43 @@ fn test_filter_query_deserialize_complex() {
     test_deserialize_filter_error(json!({"and": [{"a":  {"$lt": 1}}]}));
 }
 
+#[test]
+fn test_filter_query_deserialize_or_not_in() {
+    test_deserialize_filter(
+        json!({"$or": [{"a":  {"$lt": 3}}, {"b":  {"$gt": 5}}]}),
+        FilterExpression::Or(vec![
+            FilterExpression::Simple("a".to_string(), Operator::LT, Value::from(3)),
+            FilterExpression::Simple("b".to_string(), Operator::GT, Value::from(5)),
+        ]),
+    );
+    test_deserialize_filter(
+        json!({"$not": {"a":  1}}),
+        FilterExpression::Not(Box::new(FilterExpression::Simple(
+            "a".to_string(),
+            Operator::EQ,
+            Value::from(1),
+        ))),
+    );
+    test_deserialize_filter(
+        json!({"status":  {"$in": ["a", "b"]}}),
+        FilterExpression::Simple("status".to_string(), Operator::In, json!(["a", "b"])),
+    );
+    test_deserialize_filter(
+        json!({"a":  {"$ne": 1}, "$or": [{"b": 2}, {"c": 3}]}),
+        FilterExpression::And(vec![
+            FilterExpression::Simple("a".to_string(), Operator::NE, Value::from(1)),
+            FilterExpression::Or(vec![
+                FilterExpression::Simple("b".to_string(), Operator::EQ, Value::from(2)),
+                FilterExpression::Simple("c".to_string(), Operator::EQ, Value::from(3)),
+            ]),
+        ]),
+    );
+
+    test_deserialize_filter_error(json!({"$or": {}}));
+    test_deserialize_filter_error(json!({"$not": []}));
+    test_deserialize_filter_error(json!({"$or": [{"a":  {"ne": 1}}]}));
+}
+
 #[test]
 fn test_sort_options_query_deserialize() {
     test_deserialize_sort_options(json!({}), vec![]);
@@ -213,11 +266,27 @@ fn test_query_expression_deserialize() {
             Skip::Skip(0),
         ),
     );
+    test_deserialize_query(
+        json!({"$select": ["a", "b"]}),
+        QueryExpression {
+            select: Some(vec!["a".to_string(), "b".to_string()]),
+            ..QueryExpression::new(None, vec![], None, Skip::Skip(0))
+        },
+    );
+    test_deserialize_query(
+        json!({"$as_of": 3}),
+        QueryExpression {
+            as_of: Some(3),
+            ..QueryExpression::new(None, vec![], None, Skip::Skip(0))
+        },
+    );
 }
 
 #[test]
 fn test_query_expression_deserialize_error() {
     test_deserialize_query_error(json!({ "$skip": 20, "$after": 30 }));
+    test_deserialize_query_error(json!({ "$select": "a" }));
+    test_deserialize_query_error(json!({ "$as_of": -1 }));
 }
 
 fn test_deserialize_query(a: Value, b: QueryExpression) {
@@ -249,3 +318,38 @@ fn test_deserialize_sort_options(json: Value, expected: Vec<SortOption>) {
 fn test_deserialize_sort_options_error(json: Value) {
     assert!(serde_json::from_value::<SortOptions>(json).is_err());
 }
+
+#[test]
+fn test_aggregate_expression_deserialize() {
+    let expression = serde_json::from_value::<AggregateExpression>(json!({
+        "$filter": { "a": 1 },
+        "$group_by": ["b"],
+        "$aggregates": ["$count", { "$sum": "c" }, { "$avg": "c" }, { "$min": "d" }, { "$max": "d" }]
+    }))
+    .unwrap();
+    assert_eq!(
+        expression,
+        AggregateExpression {
+            filter: Some(FilterExpression::Simple(
+                "a".to_string(),
+                Operator::EQ,
+                Value::from(1)
+            )),
+            group_by: vec!["b".to_string()],
+            aggregates: vec![
+                Aggregate::Count,
+                Aggregate::Sum("c".to_string()),
+                Aggregate::Avg("c".to_string()),
+                Aggregate::Min("d".to_string()),
+                Aggregate::Max("d".to_string()),
+            ],
+        }
+    );
+
+    let expression =
+        serde_json::from_value::<AggregateExpression>(json!({ "$aggregates": ["$count"] }))
+            .unwrap();
+    assert_eq!(expression.filter, None);
+    assert!(expression.group_by.is_empty());
+    assert!(serde_json::from_value::<AggregateExpression>(json!({})).is_err());
+}
@@ -78,6 +78,18 @@ fn test_serialize_filter_complex() {
         json!({"$and":[{"a":  {"$lt": 1}}, {"b":  {"$gte": 3}}, {"c": 3}]}),
         three_fields,
     );
+
+    test_serialize_filter(
+        json!({"$or": [{"a":  {"$in": [1, 2]}}, {"$not": {"b":  {"$ne": 3}}}]}),
+        FilterExpression::Or(vec![
+            FilterExpression::Simple("a".to_string(), Operator::In, json!([1, 2])),
+            FilterExpression::Not(Box::new(FilterExpression::Simple(
+                "b".to_string(),
+                Operator::NE,
+                Value::from(3),
+            ))),
+        ]),
+    );
 }
 
 #[test]
@@ -214,6 +226,23 @@ fn test_serialize_query_expression() {
         },
         json!({"$after": 10}),
     );
+
+    test_serialize_query_expression_impl(
+        QueryExpression {
+            limit: None,
+            select: Some(vec!["a".to_string()]),
+            ..Default::default()
+        },
+        json!({"$select": ["a"]}),
+    );
+    test_serialize_query_expression_impl(
+        QueryExpression {
+            limit: None,
+            as_of: Some(3),
+            ..Default::default()
+        },
+        json!({"$as_of": 3}),
+    );
 }
 
 fn test_serialize_query_expression_impl(query: QueryExpression, json: Value) {
@@ -0,0 +1,101 @@
+//! Terms of the full text index.
+//!
+//! Text is split into Unicode words, which the index's [`TextAnalyzer`] may lowercase, filter and
+//! stem. Queries are analyzed the same way, so they find the terms of the records.
+
+use dozer_types::types::TextAnalyzer;
+use rust_stemmers::{Algorithm, Stemmer};
+use unicode_segmentation::UnicodeSegmentation;
+
+/// English stop words, the same as Lucene's default set.
+const STOP_WORDS: [&str; 33] = [
+    "a", "an", "and", "are", "as", "at", "be", "but", "by", "for", "if", "in", "into", "is", "it",
+    "no", "not", "of", "on", "or", "such", "that", "the", "their", "then", "there", "these",
+    "they", "this", "to", "was", "will", "with",
+];
+
+/// Returns the terms of `text`, in order. Stop words are removed, so the terms of a phrase are
+/// consecutive whether it has stop words or not.
+pub fn analyze(analyzer: &TextAnalyzer, text: &str) -> Vec<String> {
+    let stemmer = analyzer
+        .stemming
+        .then(|| Stemmer::create(Algorithm::English));
+    text.unicode_words()
+        .filter(|word| !analyzer.stop_words || !is_stop_word(word))
+        .map(|word| {
+            let word = if analyzer.lowercase {
+                word.to_lowercase()
+            } else {
+                word.to_string()
+            };
+            match &stemmer {
+                Some(stemmer) => stemmer.stem(&word).into_owned(),
+                None => word,
+            }
+        })
+        .collect()
+}
+
+/// Returns the prefix of the terms `prefix` finds. It's lowercased if the analyzer lowercases
+/// words, but not stemmed, as the prefix of a word usually isn't a word.
+pub fn analyze_prefix(analyzer: &TextAnalyzer, prefix: &str) -> String {
+    if analyzer.lowercase {
+        prefix.to_lowercase()
+    } else {
+        prefix.to_string()
+    }
+}
+
+fn is_stop_word(word: &str) -> bool {
+    STOP_WORDS.contains(&word.to_lowercase().as_str())
+}
+
+pub fn get_full_text_secondary_index(token: &str) -> Vec<u8> {
+    token.as_bytes().to_vec()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_analyze() {
+        let text = "The Quick brown foxes are running";
+        assert_eq!(
+            analyze(&TextAnalyzer::default(), text),
+            vec!["The", "Quick", "brown", "foxes", "are", "running"]
+        );
+        assert_eq!(
+            analyze(
+                &TextAnalyzer {
+                    lowercase: true,
+                    ..Default::default()
+                },
+                text
+            ),
+            vec!["the", "quick", "brown", "foxes", "are", "running"]
+        );
+        assert_eq!(
+            analyze(
+                &TextAnalyzer {
+                    lowercase: true,
+                    stop_words: true,
+                    stemming: true,
+                },
+                text
+            ),
+            vec!["quick", "brown", "fox", "run"]
+        );
+        assert_eq!(
+            analyze_prefix(
+                &TextAnalyzer {
+                    lowercase: true,
+                    stemming: true,
+                    ..Default::default()
+                },
+                "Runn"
+            ),
+            "runn"
+        );
+    }
+}
@@ -13,6 +13,11 @@ use dozer_types::types::Field;
 
 use crate::errors::CompareError;
 
+mod full_text;
+pub use full_text::{analyze, analyze_prefix, get_full_text_secondary_index};
+mod spatial;
+pub use spatial::{get_spatial_key_ranges, get_spatial_secondary_index, BoundingBox};
+
 pub fn get_primary_key(primary_index: &[usize], values: &[Field]) -> Vec<u8> {
     debug_assert!(
         !primary_index.is_empty(),
@@ -67,10 +72,6 @@ pub fn compare_composite_secondary_index(a: &[u8], b: &[u8]) -> Result<Ordering,
     })
 }
 
-pub fn get_full_text_secondary_index(token: &str) -> Vec<u8> {
-    token.as_bytes().to_vec()
-}
-
 fn get_composite_secondary_index(fields: &[&Field]) -> Vec<u8> {
     fn get_field_encoding_len(field: &Field) -> usize {
         8 + field.encoding_len()
@@ -0,0 +1,182 @@
+//! Keys of the spatial index.
+//!
+//! The world is divided into a grid of `2^BITS` columns of longitude (`x`) and `2^BITS` rows of
+//! latitude (`y`). The key of a cell interleaves the bits of its column and row (Z-order), so the
+//! cells of any quadtree node have consecutive keys, and a region is covered by a few key ranges.
+
+use std::collections::VecDeque;
+
+use dozer_types::types::DozerPoint;
+
+/// Number of bits of the column and the row of a cell, giving cells of about 2.4 meters.
+const BITS: u32 = 24;
+
+/// Maximum number of quadtree nodes a region is covered with. More nodes fit the region better,
+/// but need more index scans.
+const MAX_COVERING_NODES: usize = 64;
+
+/// A box of longitude and latitude, `min` being the south west corner.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoundingBox {
+    pub min: (f64, f64),
+    pub max: (f64, f64),
+}
+
+impl BoundingBox {
+    pub const WORLD: BoundingBox = BoundingBox {
+        min: (-180.0, -90.0),
+        max: (180.0, 90.0),
+    };
+}
+
+pub fn get_spatial_secondary_index(point: &DozerPoint) -> Vec<u8> {
+    let column = to_cell(point.0.x().0, -180.0, 180.0);
+    let row = to_cell(point.0.y().0, -90.0, 90.0);
+    interleave(column, row).to_be_bytes().to_vec()
+}
+
+/// Returns inclusive key ranges, in ascending order, covering all cells intersecting the box.
+/// The ranges may cover cells outside the box, so the points found must be checked again.
+pub fn get_spatial_key_ranges(bounding_box: &BoundingBox) -> Vec<(Vec<u8>, Vec<u8>)> {
+    let min_column = to_cell(bounding_box.min.0, -180.0, 180.0);
+    let max_column = to_cell(bounding_box.max.0, -180.0, 180.0);
+    let min_row = to_cell(bounding_box.min.1, -90.0, 90.0);
+    let max_row = to_cell(bounding_box.max.1, -90.0, 90.0);
+    if min_column > max_column || min_row > max_row {
+        return vec![];
+    }
+
+    // Refine the quadtree breadth first, so all parts of the region are covered equally finely.
+    let mut covering = vec![];
+    let mut queue = VecDeque::from([(0u32, 0u32, BITS)]);
+    while let Some((column, row, size_bits)) = queue.pop_front() {
+        let last_column = column + ((1 << size_bits) - 1);
+        let last_row = row + ((1 << size_bits) - 1);
+        let contained = min_column <= column
+            && last_column <= max_column
+            && min_row <= row
+            && last_row <= max_row;
+        if contained || size_bits == 0 || covering.len() + queue.len() + 4 > MAX_COVERING_NODES {
+            covering.push((column, row, size_bits));
+            continue;
+        }
+        let half = 1 << (size_bits - 1);
+        for (child_column, child_row) in [
+            (column, row),
+            (column + half, row),
+            (column, row + half),
+            (column + half, row + half),
+        ] {
+            if child_column <= max_column
+                && min_column < child_column + half
+                && child_row <= max_row
+                && min_row < child_row + half
+            {
+                queue.push_back((child_column, child_row, size_bits - 1));
+            }
+        }
+    }
+
+    let mut ranges = covering
+        .into_iter()
+        .map(|(column, row, size_bits)| {
+            let start = interleave(column, row);
+            (start, start + ((1u64 << (2 * size_bits)) - 1))
+        })
+        .collect::<Vec<_>>();
+    ranges.sort_unstable();
+    let mut merged: Vec<(u64, u64)> = vec![];
+    for (start, end) in ranges {
+        match merged.last_mut() {
+            Some(last) if last.1 + 1 == start => last.1 = end,
+            _ => merged.push((start, end)),
+        }
+    }
+    merged
+        .into_iter()
+        .map(|(start, end)| (start.to_be_bytes().to_vec(), end.to_be_bytes().to_vec()))
+        .collect()
+}
+
+/// Coordinates out of range are put in the border cells, so they're still found by the regions
+/// covering these cells.
+fn to_cell(value: f64, min: f64, max: f64) -> u32 {
+    let num_cells = (1u32 << BITS) as f64;
+    let cell = ((value - min) / (max - min) * num_cells).floor();
+    if cell.is_nan() {
+        0
+    } else {
+        cell.clamp(0.0, num_cells - 1.0) as u32
+    }
+}
+
+fn interleave(column: u32, row: u32) -> u64 {
+    spread(column) | (spread(row) << 1)
+}
+
+/// Moves bit `i` of `value` to bit `2 * i`.
+fn spread(value: u32) -> u64 {
+    let mut value = value as u64;
+    value = (value | (value << 16)) & 0x0000_ffff_0000_ffff;
+    value = (value | (value << 8)) & 0x00ff_00ff_00ff_00ff;
+    value = (value | (value << 4)) & 0x0f0f_0f0f_0f0f_0f0f;
+    value = (value | (value << 2)) & 0x3333_3333_3333_3333;
+    (value | (value << 1)) & 0x5555_5555_5555_5555
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn is_covered(ranges: &[(Vec<u8>, Vec<u8>)], point: (f64, f64)) -> bool {
+        let key = get_spatial_secondary_index(&DozerPoint::from(point));
+        ranges
+            .iter()
+            .any(|(start, end)| start <= &key && &key <= end)
+    }
+
+    #[test]
+    fn test_interleave() {
+        assert_eq!(interleave(0, 0), 0);
+        assert_eq!(interleave(1, 0), 1);
+        assert_eq!(interleave(0, 1), 2);
+        assert_eq!(interleave(3, 3), 15);
+        assert_eq!(
+            interleave((1 << BITS) - 1, (1 << BITS) - 1),
+            (1 << (2 * BITS)) - 1
+        );
+    }
+
+    #[test]
+    fn test_spatial_key_ranges() {
+        let ranges = get_spatial_key_ranges(&BoundingBox::WORLD);
+        assert_eq!(ranges.len(), 1);
+
+        let bounding_box = BoundingBox {
+            min: (-0.12, 51.5),
+            max: (-0.11, 51.51),
+        };
+        let ranges = get_spatial_key_ranges(&bounding_box);
+        assert!(ranges.len() <= MAX_COVERING_NODES);
+        assert!(ranges.windows(2).all(|ranges| ranges[0].1 < ranges[1].0));
+        for point in [(-0.12, 51.5), (-0.115, 51.505), (-0.11, 51.51)] {
+            assert!(is_covered(&ranges, point));
+        }
+        for point in [(0.0, 51.5), (-0.115, 50.0), (2.35, 48.85)] {
+            assert!(!is_covered(&ranges, point));
+        }
+
+        // Out of range coordinates are in the border cells.
+        let ranges = get_spatial_key_ranges(&BoundingBox {
+            min: (179.0, 89.0),
+            max: (180.0, 90.0),
+        });
+        assert!(is_covered(&ranges, (200.0, 100.0)));
+
+        assert!(get_spatial_key_ranges(&BoundingBox {
+            min: (1.0, 0.0),
+            max: (0.0, 1.0),
+        })
+        .is_empty());
+    }
+}
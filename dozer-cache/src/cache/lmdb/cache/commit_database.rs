@@ -0,0 +1,73 @@
+use dozer_storage::{
+    lmdb::{Database, DatabaseFlags, RwTransaction, Transaction, WriteFlags},
+    lmdb_storage::LmdbEnvironmentManager,
+};
+
+use crate::errors::{CacheError, QueryError};
+
+/// Number of commits of the cache. Versions written before the `n`th commit belong to commit `n`.
+#[derive(Debug, Clone, Copy)]
+pub struct CommitDatabase(Database);
+
+const COMMIT_KEY: &[u8] = b"commit";
+
+impl CommitDatabase {
+    pub fn new(env: &mut LmdbEnvironmentManager) -> Result<Self, CacheError> {
+        Ok(Self(env.create_database(
+            Some("commit"),
+            Some(DatabaseFlags::empty()),
+        )?))
+    }
+
+    /// Returns the latest commit, 0 before the first commit.
+    pub fn get<T: Transaction>(&self, txn: &T) -> Result<u64, CacheError> {
+        match txn.get(self.0, &COMMIT_KEY) {
+            Ok(commit) => Ok(u64::from_be_bytes(
+                commit
+                    .try_into()
+                    .expect("Commit must be a u64 in this database"),
+            )),
+            Err(dozer_storage::lmdb::Error::NotFound) => Ok(0),
+            Err(e) => Err(CacheError::Query(QueryError::GetValue(e))),
+        }
+    }
+
+    /// Returns the commit the current transaction will be.
+    pub fn next<T: Transaction>(&self, txn: &T) -> Result<u64, CacheError> {
+        Ok(self.get(txn)? + 1)
+    }
+
+    /// Records the commit of the current transaction, returning it.
+    pub fn increment(&self, txn: &mut RwTransaction) -> Result<u64, CacheError> {
+        let commit = self.next(txn)?;
+        txn.put(
+            self.0,
+            &COMMIT_KEY,
+            &commit.to_be_bytes(),
+            WriteFlags::empty(),
+        )
+        .map_err(|e| CacheError::Query(QueryError::InsertValue(e)))?;
+        Ok(commit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cache::lmdb::utils::{init_env, CacheOptions};
+
+    use super::*;
+
+    #[test]
+    fn test_commit_database() {
+        let mut env = init_env(&CacheOptions::default()).unwrap().0;
+        let db = CommitDatabase::new(&mut env).unwrap();
+        let txn = env.create_txn().unwrap();
+        let mut txn = txn.write();
+
+        assert_eq!(db.get(txn.txn()).unwrap(), 0);
+        assert_eq!(db.next(txn.txn()).unwrap(), 1);
+        assert_eq!(db.increment(txn.txn_mut()).unwrap(), 1);
+        assert_eq!(db.increment(txn.txn_mut()).unwrap(), 2);
+        assert_eq!(db.get(txn.txn()).unwrap(), 2);
+    }
+}
@@ -0,0 +1,245 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use dozer_storage::lmdb::RwTransaction;
+use dozer_storage::lmdb_storage::SharedTransaction;
+use dozer_types::chrono::{self, DateTime, Utc};
+use dozer_types::crossbeam::channel::{self, RecvTimeoutError, Sender};
+use dozer_types::log::{debug, error};
+use dozer_types::types::{FieldType, IndexDefinition, Record, Schema, SchemaIdentifier};
+
+use super::{
+    EvictedDatabase, FullTextLengthDatabase, HistoryDatabase, LmdbRwCache, RecordDatabase,
+    SecondaryIndexDatabases, TtlDatabase,
+};
+use crate::cache::lmdb::indexer::Indexer;
+use crate::cache::{OnEvict, RetentionPolicy};
+use crate::errors::CacheError;
+
+/// How often the compactor checks the records against the retention policy.
+const COMPACTION_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Removes the records of a schema over the limits of a retention policy, along with their
+/// secondary index entries and versions.
+///
+/// Expired records are found in the TTL index of the schema, and the earliest inserted ones in
+/// the record ids of the schema, so only the removed records are read.
+///
+/// Records are removed in the shared transaction of the cache. The cache commits its writes with
+/// their checkpoint, so if it has uncommitted writes the removals are persisted by its next
+/// commit, and otherwise they're committed right away.
+#[derive(Debug, Clone)]
+pub struct Evictor {
+    txn: SharedTransaction,
+    uncommitted: Arc<AtomicBool>,
+    db: RecordDatabase,
+    evicted: EvictedDatabase,
+    history: HistoryDatabase,
+    ttl_db: TtlDatabase,
+    secondary_indexes: SecondaryIndexDatabases,
+    full_text_lengths: FullTextLengthDatabase,
+    schema_id: SchemaIdentifier,
+    schema: Schema,
+    indexes: Vec<IndexDefinition>,
+    // Index of the timestamp or date field and the retention period
+    ttl: Option<(usize, chrono::Duration)>,
+    max_records: Option<u64>,
+    max_bytes: Option<u64>,
+}
+
+impl Evictor {
+    pub fn new(
+        cache: &LmdbRwCache,
+        (schema, indexes): (Schema, Vec<IndexDefinition>),
+        policy: &RetentionPolicy,
+    ) -> Result<Self, CacheError> {
+        let schema_id = schema.identifier.ok_or(CacheError::SchemaHasNoIdentifier)?;
+
+        let ttl = policy
+            .ttl
+            .as_ref()
+            .map(|(name, period)| {
+                let idx = schema
+                    .fields
+                    .iter()
+                    .position(|field| &field.name == name)
+                    .ok_or_else(|| CacheError::RetentionFieldNotFound(name.clone()))?;
+                match schema.fields[idx].typ {
+                    FieldType::Timestamp | FieldType::Date => {}
+                    typ => return Err(CacheError::InvalidRetentionField(name.clone(), typ)),
+                }
+                // Periods too long for `chrono` never expire anything.
+                let period =
+                    chrono::Duration::from_std(*period).unwrap_or(chrono::Duration::max_value());
+                Ok((idx, period))
+            })
+            .transpose()?;
+
+        let secondary_indexes = cache
+            .common
+            .secondary_indexes
+            .iter()
+            .filter(|((id, _), _)| *id == schema_id)
+            .map(|(key, db)| (*key, *db))
+            .collect();
+
+        Ok(Self {
+            txn: cache.txn.clone(),
+            uncommitted: cache.uncommitted.clone(),
+            db: cache.common.db,
+            evicted: cache.evicted_db,
+            history: cache.common.history,
+            ttl_db: cache.ttl_db,
+            secondary_indexes,
+            full_text_lengths: cache.common.full_text_lengths,
+            schema_id,
+            schema,
+            indexes,
+            ttl,
+            max_records: policy.max_records,
+            max_bytes: policy.max_bytes,
+        })
+    }
+
+    pub fn schema_id(&self) -> SchemaIdentifier {
+        self.schema_id
+    }
+
+    /// Index of the field records expire by, if any.
+    pub fn ttl_field(&self) -> Option<usize> {
+        self.ttl.map(|(idx, _)| idx)
+    }
+
+    /// Removes the expired records, then the earliest inserted ones until the records are within
+    /// `max_records` and `max_bytes`. Returns the removed records.
+    pub fn evict(&self, now: DateTime<Utc>) -> Result<Vec<Record>, CacheError> {
+        let mut txn = self.txn.write();
+        let mut evicted = vec![];
+
+        if let Some(until) = self
+            .ttl
+            .and_then(|(_, period)| now.checked_sub_signed(period))
+        {
+            for id in self.ttl_db.until(txn.txn(), self.schema_id, until)? {
+                evicted.push(self.evict_record(txn.txn_mut(), id)?);
+            }
+        }
+
+        if self.max_records.is_some() || self.max_bytes.is_some() {
+            let totals = self.db.totals_of(txn.txn(), self.schema_id)?;
+            let (mut count, mut bytes) = (totals.count, totals.bytes);
+            let mut ids = vec![];
+            self.db
+                .for_each_id_of(txn.txn(), self.schema_id, |id, size| {
+                    let over_count = matches!(self.max_records, Some(max) if count > max);
+                    let over_bytes = matches!(self.max_bytes, Some(max) if bytes > max);
+                    if !over_count && !over_bytes {
+                        return false;
+                    }
+                    ids.push(id);
+                    count -= 1;
+                    bytes -= size;
+                    true
+                })?;
+            for id in ids {
+                evicted.push(self.evict_record(txn.txn_mut(), id)?);
+            }
+        }
+
+        if !evicted.is_empty() && !self.uncommitted.load(Ordering::SeqCst) {
+            txn.commit_and_renew()?;
+        }
+        Ok(evicted)
+    }
+
+    /// Adds the records of the schema to the TTL index, replacing its entries.
+    pub fn index_ttl(&self, txn: &mut RwTransaction) -> Result<(), CacheError> {
+        self.ttl_db.clear(txn, self.schema_id)?;
+        if let Some(field) = self.ttl_field() {
+            for (id, record) in self.db.records_of(txn, self.schema_id)? {
+                self.ttl_db
+                    .insert(txn, self.schema_id, &record.values[field], id)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn evict_record(&self, txn: &mut RwTransaction, id: [u8; 8]) -> Result<Record, CacheError> {
+        let record = self.db.get(txn, id)?;
+        self.db.delete(txn, id)?;
+
+        let indexer = Indexer {
+            secondary_indexes: &self.secondary_indexes,
+            full_text_lengths: self.full_text_lengths,
+        };
+        indexer.delete_indexes(txn, &record, &self.schema, &self.indexes, id)?;
+        if let Some(field) = self.ttl_field() {
+            self.ttl_db
+                .delete(txn, self.schema_id, &record.values[field], id)?;
+        }
+        self.history.remove(txn, self.schema_id, id)?;
+
+        let version = record
+            .version
+            .expect("All records in cache should have a version");
+        self.evicted.insert(txn, id, version)?;
+        Ok(record)
+    }
+}
+
+/// Runs an `Evictor` periodically in a background thread, until dropped.
+#[derive(Debug)]
+pub struct Compactor {
+    ttl_field: Option<usize>,
+    stop: Option<Sender<()>>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Compactor {
+    pub fn start(schema_name: String, evictor: Evictor, on_evict: Option<OnEvict>) -> Self {
+        let ttl_field = evictor.ttl_field();
+        let (stop, stopped) = channel::bounded::<()>(1);
+        let handle = thread::spawn(move || loop {
+            match stopped.recv_timeout(COMPACTION_INTERVAL) {
+                Err(RecvTimeoutError::Timeout) => match evictor.evict(Utc::now()) {
+                    Ok(records) if records.is_empty() => {}
+                    Ok(records) => {
+                        debug!(
+                            "Evicted {} records of {schema_name} from cache",
+                            records.len()
+                        );
+                        if let Some(on_evict) = &on_evict {
+                            records.into_iter().for_each(on_evict);
+                        }
+                    }
+                    Err(e) => error!("Failed to evict records of {schema_name} from cache: {e}"),
+                },
+                Ok(()) | Err(RecvTimeoutError::Disconnected) => break,
+            }
+        });
+        Self {
+            ttl_field,
+            stop: Some(stop),
+            handle: Some(handle),
+        }
+    }
+
+    /// Index of the field the records of the schema expire by, if any.
+    pub fn ttl_field(&self) -> Option<usize> {
+        self.ttl_field
+    }
+}
+
+impl Drop for Compactor {
+    fn drop(&mut self) {
+        // Dropping the sender disconnects the channel, which stops the thread.
+        drop(self.stop.take());
+        if let Some(handle) = self.handle.take() {
+            if handle.join().is_err() {
+                error!("Cache compactor thread panicked");
+            }
+        }
+    }
+}
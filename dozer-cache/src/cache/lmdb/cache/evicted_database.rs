@@ -0,0 +1,77 @@
+use dozer_storage::{
+    lmdb::{Database, DatabaseFlags, RwTransaction, Transaction, WriteFlags},
+    lmdb_storage::LmdbEnvironmentManager,
+};
+
+use crate::errors::{CacheError, QueryError};
+
+/// Versions of the records removed by a retention policy, by id.
+///
+/// The source may still update or delete these records, which continues from the version they had.
+#[derive(Debug, Clone, Copy)]
+pub struct EvictedDatabase(Database);
+
+impl EvictedDatabase {
+    pub fn new(env: &mut LmdbEnvironmentManager) -> Result<Self, CacheError> {
+        Ok(Self(env.create_database(
+            Some("evicted"),
+            Some(DatabaseFlags::INTEGER_KEY),
+        )?))
+    }
+
+    pub fn insert(
+        &self,
+        txn: &mut RwTransaction,
+        id: [u8; 8],
+        version: u32,
+    ) -> Result<(), CacheError> {
+        txn.put(self.0, &id, &version.to_be_bytes(), WriteFlags::empty())
+            .map_err(|e| CacheError::Query(QueryError::InsertValue(e)))
+    }
+
+    pub fn get<T: Transaction>(&self, txn: &T, id: [u8; 8]) -> Result<Option<u32>, CacheError> {
+        match txn.get(self.0, &id) {
+            Ok(version) => {
+                Ok(Some(u32::from_be_bytes(version.try_into().expect(
+                    "All values must be u32 versions in this database",
+                ))))
+            }
+            Err(dozer_storage::lmdb::Error::NotFound) => Ok(None),
+            Err(e) => Err(CacheError::Query(QueryError::GetValue(e))),
+        }
+    }
+
+    /// Removes the version of the record with this id, returning it if the record was evicted.
+    pub fn remove(&self, txn: &mut RwTransaction, id: [u8; 8]) -> Result<Option<u32>, CacheError> {
+        let version = self.get(txn, id)?;
+        if version.is_some() {
+            txn.del(self.0, &id, None)
+                .map_err(|e| CacheError::Query(QueryError::DeleteValue(e)))?;
+        }
+        Ok(version)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cache::lmdb::utils::{init_env, CacheOptions};
+
+    use super::*;
+
+    #[test]
+    fn test_evicted_database() {
+        let mut env = init_env(&CacheOptions::default()).unwrap().0;
+        let db = EvictedDatabase::new(&mut env).unwrap();
+        let txn = env.create_txn().unwrap();
+        let mut txn = txn.write();
+
+        let id = 1u64.to_be_bytes();
+        assert_eq!(db.get(txn.txn(), id).unwrap(), None);
+        assert_eq!(db.remove(txn.txn_mut(), id).unwrap(), None);
+
+        db.insert(txn.txn_mut(), id, 3).unwrap();
+        assert_eq!(db.get(txn.txn(), id).unwrap(), Some(3));
+        assert_eq!(db.remove(txn.txn_mut(), id).unwrap(), Some(3));
+        assert_eq!(db.get(txn.txn(), id).unwrap(), None);
+    }
+}
@@ -0,0 +1,114 @@
+use dozer_storage::{
+    lmdb::{Database, DatabaseFlags, RwTransaction, Transaction, WriteFlags},
+    lmdb_storage::LmdbEnvironmentManager,
+};
+use dozer_types::types::SchemaIdentifier;
+
+use super::helper;
+use crate::errors::{CacheError, QueryError};
+
+/// Total number of terms of the text indexed by each full text index, by schema and index.
+///
+/// Divided by the number of records of the schema, it's the average length of the text that
+/// ranking compares the length of each record to.
+#[derive(Debug, Clone, Copy)]
+pub struct FullTextLengthDatabase(Database);
+
+impl FullTextLengthDatabase {
+    pub fn new(
+        env: &mut LmdbEnvironmentManager,
+        create_if_not_exist: bool,
+    ) -> Result<Self, CacheError> {
+        let flags = create_if_not_exist.then_some(DatabaseFlags::empty());
+        Ok(Self(env.create_database(Some("full_text_lengths"), flags)?))
+    }
+
+    pub fn get<T: Transaction>(
+        &self,
+        txn: &T,
+        schema_id: SchemaIdentifier,
+        index: usize,
+    ) -> Result<u64, CacheError> {
+        match txn.get(self.0, &index_key(schema_id, index)) {
+            Ok(length) => Ok(u64::from_be_bytes(
+                length
+                    .try_into()
+                    .expect("All values must be u64 lengths in this database"),
+            )),
+            Err(dozer_storage::lmdb::Error::NotFound) => Ok(0),
+            Err(e) => Err(CacheError::Query(QueryError::GetValue(e))),
+        }
+    }
+
+    pub fn add(
+        &self,
+        txn: &mut RwTransaction,
+        schema_id: SchemaIdentifier,
+        index: usize,
+        length: u64,
+    ) -> Result<(), CacheError> {
+        let total = self.get(txn, schema_id, index)? + length;
+        self.put(txn, schema_id, index, total)
+    }
+
+    pub fn subtract(
+        &self,
+        txn: &mut RwTransaction,
+        schema_id: SchemaIdentifier,
+        index: usize,
+        length: u64,
+    ) -> Result<(), CacheError> {
+        let total = self.get(txn, schema_id, index)?.saturating_sub(length);
+        self.put(txn, schema_id, index, total)
+    }
+
+    fn put(
+        &self,
+        txn: &mut RwTransaction,
+        schema_id: SchemaIdentifier,
+        index: usize,
+        total: u64,
+    ) -> Result<(), CacheError> {
+        txn.put(
+            self.0,
+            &index_key(schema_id, index),
+            &total.to_be_bytes(),
+            WriteFlags::empty(),
+        )
+        .map_err(|e| CacheError::Query(QueryError::InsertValue(e)))
+    }
+}
+
+fn index_key(schema_id: SchemaIdentifier, index: usize) -> [u8; 10] {
+    let mut key = [0; 10];
+    key[..6].copy_from_slice(&helper::schema_key(schema_id));
+    key[6..].copy_from_slice(&(index as u32).to_be_bytes());
+    key
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cache::lmdb::utils::{init_env, CacheOptions};
+
+    use super::*;
+
+    #[test]
+    fn test_full_text_length_database() {
+        let mut env = init_env(&CacheOptions::default()).unwrap().0;
+        let db = FullTextLengthDatabase::new(&mut env, true).unwrap();
+        let txn = env.create_txn().unwrap();
+        let mut txn = txn.write();
+
+        let schema_id = SchemaIdentifier { id: 1, version: 1 };
+        assert_eq!(db.get(txn.txn(), schema_id, 0).unwrap(), 0);
+
+        db.add(txn.txn_mut(), schema_id, 0, 5).unwrap();
+        db.add(txn.txn_mut(), schema_id, 0, 3).unwrap();
+        db.add(txn.txn_mut(), schema_id, 1, 2).unwrap();
+        assert_eq!(db.get(txn.txn(), schema_id, 0).unwrap(), 8);
+        assert_eq!(db.get(txn.txn(), schema_id, 1).unwrap(), 2);
+
+        db.subtract(txn.txn_mut(), schema_id, 0, 5).unwrap();
+        assert_eq!(db.get(txn.txn(), schema_id, 0).unwrap(), 3);
+    }
+}
@@ -1,7 +1,7 @@
 use crate::errors::{CacheError, QueryError};
 use dozer_storage::lmdb::{Database, Transaction};
 use dozer_storage::lmdb_sys as ffi;
-use dozer_types::{bincode, serde};
+use dozer_types::{bincode, serde, types::SchemaIdentifier};
 use std::{cmp::Ordering, ffi::c_void};
 pub fn get<T>(txn: &impl Transaction, db: Database, id: &[u8]) -> Result<T, CacheError>
 where
@@ -45,3 +45,11 @@ pub fn lmdb_stat<T: Transaction>(
         Err(dozer_storage::lmdb::Error::from_err_code(code))
     }
 }
+
+/// Key of the entries of a schema in databases shared by all schemas.
+pub fn schema_key(schema_id: SchemaIdentifier) -> [u8; 6] {
+    let mut key = [0; 6];
+    key[..4].copy_from_slice(&schema_id.id.to_be_bytes());
+    key[4..].copy_from_slice(&schema_id.version.to_be_bytes());
+    key
+}
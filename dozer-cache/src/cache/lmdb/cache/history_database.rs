@@ -0,0 +1,387 @@
+use dozer_storage::{
+    lmdb::{Cursor, Database, DatabaseFlags, RwTransaction, Transaction, WriteFlags},
+    lmdb_storage::LmdbEnvironmentManager,
+};
+use dozer_types::{
+    bincode,
+    types::{Record, SchemaIdentifier},
+};
+
+use super::{helper, id_from_bytes};
+use crate::cache::{RecordVersion, RecordWithId};
+use crate::errors::{CacheError, QueryError};
+
+/// Versions of the records of the schemas keeping history, and the commit each of these schemas
+/// keeps history since.
+///
+/// Versions are keyed by schema, record id and a sequence number, so the versions of a schema are
+/// adjacent, and the versions of a record are adjacent and in the order they were written. The
+/// sequence number of the latest version of each record and the number of versions of each schema
+/// are kept so writes and scan limits don't need to read the versions.
+#[derive(Debug, Clone, Copy)]
+pub struct HistoryDatabase {
+    versions: Database,
+    latest: Database,
+    counts: Database,
+    since: Database,
+}
+
+impl HistoryDatabase {
+    pub fn new(
+        env: &mut LmdbEnvironmentManager,
+        create_if_not_exist: bool,
+    ) -> Result<Self, CacheError> {
+        let flags = if create_if_not_exist {
+            Some(DatabaseFlags::empty())
+        } else {
+            None
+        };
+        let versions = env.create_database(Some("history"), flags)?;
+        let latest = env.create_database(Some("history_latest"), flags)?;
+        let counts = env.create_database(Some("history_counts"), flags)?;
+        let since = env.create_database(Some("history_since"), flags)?;
+        Ok(Self {
+            versions,
+            latest,
+            counts,
+            since,
+        })
+    }
+
+    /// Returns the commit the schema keeps history since, `None` if it doesn't keep history.
+    pub fn since<T: Transaction>(
+        &self,
+        txn: &T,
+        schema_id: SchemaIdentifier,
+    ) -> Result<Option<u64>, CacheError> {
+        get_u64(txn, self.since, &helper::schema_key(schema_id))
+    }
+
+    pub fn keep_since(
+        &self,
+        txn: &mut RwTransaction,
+        schema_id: SchemaIdentifier,
+        commit: u64,
+    ) -> Result<(), CacheError> {
+        put(
+            txn,
+            self.since,
+            &helper::schema_key(schema_id),
+            &commit.to_be_bytes(),
+        )
+    }
+
+    /// Stops keeping history for the schema, removing the versions of its records.
+    pub fn forget(
+        &self,
+        txn: &mut RwTransaction,
+        schema_id: SchemaIdentifier,
+    ) -> Result<(), CacheError> {
+        let prefix = helper::schema_key(schema_id);
+        delete(txn, self.since, &prefix)?;
+        delete(txn, self.counts, &prefix)?;
+        for key in keys_with_prefix(txn, self.versions, &prefix)? {
+            delete(txn, self.versions, &key)?;
+        }
+        for key in keys_with_prefix(txn, self.latest, &prefix)? {
+            delete(txn, self.latest, &key)?;
+        }
+        Ok(())
+    }
+
+    /// Adds `record` as the latest version of the record with this id, written by `commit`.
+    pub fn insert(
+        &self,
+        txn: &mut RwTransaction,
+        schema_id: SchemaIdentifier,
+        id: [u8; 8],
+        record: &Record,
+        commit: u64,
+    ) -> Result<(), CacheError> {
+        let record_key = record_key(schema_id, id);
+        let sequence = self
+            .latest_sequence(txn, &record_key)?
+            .map_or(0, |sequence| sequence + 1);
+        let version = RecordVersion {
+            record: RecordWithId::new(id_from_bytes(id), record.clone()),
+            valid_from: commit,
+            valid_to: None,
+        };
+        self.put_version(txn, &record_key, sequence, &version)?;
+        put(txn, self.latest, &record_key, &sequence.to_be_bytes())?;
+        self.add_to_count(txn, schema_id, 1)
+    }
+
+    /// Marks the latest version of the record with this id as replaced or deleted by `commit`.
+    pub fn close(
+        &self,
+        txn: &mut RwTransaction,
+        schema_id: SchemaIdentifier,
+        id: [u8; 8],
+        commit: u64,
+    ) -> Result<(), CacheError> {
+        let record_key = record_key(schema_id, id);
+        let Some(sequence) = self.latest_sequence(txn, &record_key)? else {
+            return Ok(());
+        };
+        let mut version: RecordVersion =
+            helper::get(txn, self.versions, &version_key(&record_key, sequence))?;
+        if version.valid_to.is_none() {
+            version.valid_to = Some(commit);
+            self.put_version(txn, &record_key, sequence, &version)?;
+        }
+        Ok(())
+    }
+
+    /// Removes all versions of the record with this id.
+    pub fn remove(
+        &self,
+        txn: &mut RwTransaction,
+        schema_id: SchemaIdentifier,
+        id: [u8; 8],
+    ) -> Result<(), CacheError> {
+        let record_key = record_key(schema_id, id);
+        let keys = keys_with_prefix(txn, self.versions, &record_key)?;
+        if keys.is_empty() {
+            return Ok(());
+        }
+        for key in &keys {
+            delete(txn, self.versions, key)?;
+        }
+        delete(txn, self.latest, &record_key)?;
+        self.add_to_count(txn, schema_id, -(keys.len() as i64))
+    }
+
+    /// Returns the versions of the record with this id, oldest first.
+    pub fn versions<T: Transaction>(
+        &self,
+        txn: &T,
+        schema_id: SchemaIdentifier,
+        id: [u8; 8],
+    ) -> Result<Vec<RecordVersion>, CacheError> {
+        let mut versions = vec![];
+        self.for_each_version(txn, &record_key(schema_id, id), |version| {
+            versions.push(version);
+        })?;
+        Ok(versions)
+    }
+
+    /// Returns the records of the schema as of `commit`, by id.
+    pub fn as_of<T: Transaction>(
+        &self,
+        txn: &T,
+        schema_id: SchemaIdentifier,
+        commit: u64,
+    ) -> Result<Vec<RecordWithId>, CacheError> {
+        let mut records = vec![];
+        self.for_each_version(txn, &helper::schema_key(schema_id), |version| {
+            let current = version.valid_from <= commit
+                && version.valid_to.map_or(true, |valid_to| valid_to > commit);
+            if current {
+                records.push(version.record);
+            }
+        })?;
+        Ok(records)
+    }
+
+    /// Returns the number of versions of the schema.
+    pub fn count<T: Transaction>(
+        &self,
+        txn: &T,
+        schema_id: SchemaIdentifier,
+    ) -> Result<usize, CacheError> {
+        Ok(get_u64(txn, self.counts, &helper::schema_key(schema_id))?.unwrap_or(0) as usize)
+    }
+
+    fn latest_sequence<T: Transaction>(
+        &self,
+        txn: &T,
+        record_key: &[u8],
+    ) -> Result<Option<u32>, CacheError> {
+        match txn.get(self.latest, &record_key) {
+            Ok(sequence) => {
+                Ok(Some(u32::from_be_bytes(sequence.try_into().expect(
+                    "All values must be u32 sequence numbers in this database",
+                ))))
+            }
+            Err(dozer_storage::lmdb::Error::NotFound) => Ok(None),
+            Err(e) => Err(CacheError::Query(QueryError::GetValue(e))),
+        }
+    }
+
+    fn add_to_count(
+        &self,
+        txn: &mut RwTransaction,
+        schema_id: SchemaIdentifier,
+        delta: i64,
+    ) -> Result<(), CacheError> {
+        let count = self.count(txn, schema_id)? as i64 + delta;
+        put(
+            txn,
+            self.counts,
+            &helper::schema_key(schema_id),
+            &(count as u64).to_be_bytes(),
+        )
+    }
+
+    /// Calls `f` with the versions whose keys start with `prefix`, in key order.
+    fn for_each_version<T: Transaction>(
+        &self,
+        txn: &T,
+        prefix: &[u8],
+        mut f: impl FnMut(RecordVersion),
+    ) -> Result<(), CacheError> {
+        let mut cursor = txn
+            .open_ro_cursor(self.versions)
+            .map_err(|e| CacheError::Internal(Box::new(e)))?;
+        for entry in cursor.iter_from(prefix) {
+            let (key, version) = entry.map_err(|e| CacheError::Internal(Box::new(e)))?;
+            if !key.starts_with(prefix) {
+                break;
+            }
+            f(bincode::deserialize(version).map_err(CacheError::map_deserialization_error)?);
+        }
+        Ok(())
+    }
+
+    fn put_version(
+        &self,
+        txn: &mut RwTransaction,
+        record_key: &[u8; 14],
+        sequence: u32,
+        version: &RecordVersion,
+    ) -> Result<(), CacheError> {
+        let encoded = bincode::serialize(version).map_err(CacheError::map_serialization_error)?;
+        put(
+            txn,
+            self.versions,
+            &version_key(record_key, sequence),
+            &encoded,
+        )
+    }
+}
+
+fn record_key(schema_id: SchemaIdentifier, id: [u8; 8]) -> [u8; 14] {
+    let mut key = [0; 14];
+    key[..6].copy_from_slice(&helper::schema_key(schema_id));
+    key[6..].copy_from_slice(&id);
+    key
+}
+
+fn version_key(record_key: &[u8; 14], sequence: u32) -> [u8; 18] {
+    let mut key = [0; 18];
+    key[..14].copy_from_slice(record_key);
+    key[14..].copy_from_slice(&sequence.to_be_bytes());
+    key
+}
+
+fn keys_with_prefix<T: Transaction>(
+    txn: &T,
+    db: Database,
+    prefix: &[u8],
+) -> Result<Vec<Vec<u8>>, CacheError> {
+    let mut cursor = txn
+        .open_ro_cursor(db)
+        .map_err(|e| CacheError::Internal(Box::new(e)))?;
+    let mut keys = vec![];
+    for entry in cursor.iter_from(prefix) {
+        let (key, _) = entry.map_err(|e| CacheError::Internal(Box::new(e)))?;
+        if !key.starts_with(prefix) {
+            break;
+        }
+        keys.push(key.to_vec());
+    }
+    Ok(keys)
+}
+
+fn get_u64<T: Transaction>(txn: &T, db: Database, key: &[u8]) -> Result<Option<u64>, CacheError> {
+    match txn.get(db, &key) {
+        Ok(value) => Ok(Some(u64::from_be_bytes(
+            value
+                .try_into()
+                .expect("All values must be u64 in this database"),
+        ))),
+        Err(dozer_storage::lmdb::Error::NotFound) => Ok(None),
+        Err(e) => Err(CacheError::Query(QueryError::GetValue(e))),
+    }
+}
+
+fn put(txn: &mut RwTransaction, db: Database, key: &[u8], value: &[u8]) -> Result<(), CacheError> {
+    txn.put(db, &key, &value, WriteFlags::empty())
+        .map_err(|e| CacheError::Query(QueryError::InsertValue(e)))
+}
+
+fn delete(txn: &mut RwTransaction, db: Database, key: &[u8]) -> Result<(), CacheError> {
+    match txn.del(db, &key, None) {
+        Ok(()) | Err(dozer_storage::lmdb::Error::NotFound) => Ok(()),
+        Err(e) => Err(CacheError::Query(QueryError::DeleteValue(e))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use dozer_types::types::Field;
+
+    use crate::cache::lmdb::utils::{init_env, CacheOptions};
+
+    use super::*;
+
+    #[test]
+    fn test_history_database() {
+        let mut env = init_env(&CacheOptions::default()).unwrap().0;
+        let db = HistoryDatabase::new(&mut env, true).unwrap();
+        let txn = env.create_txn().unwrap();
+        let mut txn = txn.write();
+
+        let schema_id = SchemaIdentifier { id: 1, version: 1 };
+        assert_eq!(db.since(txn.txn(), schema_id).unwrap(), None);
+        db.keep_since(txn.txn_mut(), schema_id, 2).unwrap();
+        assert_eq!(db.since(txn.txn(), schema_id).unwrap(), Some(2));
+
+        let record = |value| Record::new(Some(schema_id), vec![Field::Int(value)], Some(1));
+        let (id_1, id_2) = (1u64.to_be_bytes(), 2u64.to_be_bytes());
+        db.insert(txn.txn_mut(), schema_id, id_1, &record(1), 3)
+            .unwrap();
+        db.insert(txn.txn_mut(), schema_id, id_2, &record(2), 3)
+            .unwrap();
+        db.close(txn.txn_mut(), schema_id, id_1, 4).unwrap();
+        db.insert(txn.txn_mut(), schema_id, id_1, &record(3), 4)
+            .unwrap();
+        db.close(txn.txn_mut(), schema_id, id_2, 5).unwrap();
+        // Versions of other schemas are kept apart.
+        let other_id = SchemaIdentifier { id: 2, version: 1 };
+        db.insert(txn.txn_mut(), other_id, id_1, &record(4), 5)
+            .unwrap();
+
+        let versions = db.versions(txn.txn(), schema_id, id_1).unwrap();
+        assert_eq!(
+            versions
+                .iter()
+                .map(|version| (version.valid_from, version.valid_to))
+                .collect::<Vec<_>>(),
+            vec![(3, Some(4)), (4, None)]
+        );
+        assert_eq!(versions[1].record.record, record(3));
+
+        let values = |commit| {
+            db.as_of(txn.txn(), schema_id, commit)
+                .unwrap()
+                .into_iter()
+                .map(|record| record.record.values[0].clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(values(2), vec![]);
+        assert_eq!(values(3), vec![Field::Int(1), Field::Int(2)]);
+        assert_eq!(values(4), vec![Field::Int(3), Field::Int(2)]);
+        assert_eq!(values(5), vec![Field::Int(3)]);
+        assert_eq!(db.count(txn.txn(), schema_id).unwrap(), 3);
+        assert_eq!(db.count(txn.txn(), other_id).unwrap(), 1);
+
+        db.remove(txn.txn_mut(), schema_id, id_2).unwrap();
+        assert_eq!(db.versions(txn.txn(), schema_id, id_2).unwrap(), vec![]);
+        db.forget(txn.txn_mut(), schema_id).unwrap();
+        assert_eq!(db.since(txn.txn(), schema_id).unwrap(), None);
+        assert_eq!(db.count(txn.txn(), schema_id).unwrap(), 0);
+        assert_eq!(db.versions(txn.txn(), other_id, id_1).unwrap().len(), 1);
+    }
+}
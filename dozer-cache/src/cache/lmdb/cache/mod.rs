@@ -1,6 +1,8 @@
 use std::collections::HashMap;
 use std::fmt::Debug;
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 
 use dozer_storage::lmdb::{RoTransaction, RwTransaction, Transaction};
 use dozer_storage::lmdb_storage::{
@@ -13,29 +15,41 @@ use dozer_types::parking_lot::RwLockReadGuard;
 use dozer_types::types::{Field, FieldType, IndexDefinition, Record};
 use dozer_types::types::{Schema, SchemaIdentifier};
 
-use super::super::{RoCache, RwCache};
+use super::super::{OnEvict, RetentionPolicy, RoCache, RwCache};
 use super::indexer::Indexer;
 use super::utils::{self, CacheReadOptions};
 use super::utils::{CacheOptions, CacheOptionsKind};
-use crate::cache::expression::QueryExpression;
+use crate::cache::expression::{AggregateExpression, QueryExpression, Skip};
 use crate::cache::index::get_primary_key;
-use crate::cache::RecordWithId;
-use crate::errors::CacheError;
+use crate::cache::{AggregateGroup, RecordVersion, RecordWithId};
+use crate::errors::{CacheError, QueryError};
+use compactor::{Compactor, Evictor};
 use query::LmdbQueryHandler;
 
 mod checkpoint_database;
+mod commit_database;
+mod compactor;
+mod evicted_database;
+mod full_text_length_database;
 mod helper;
+mod history_database;
 mod id_database;
 mod query;
 mod record_database;
 mod schema_database;
 mod secondary_index_database;
+mod ttl_database;
 
 use checkpoint_database::CheckpointDatabase;
+use commit_database::CommitDatabase;
+use evicted_database::EvictedDatabase;
+pub use full_text_length_database::FullTextLengthDatabase;
+use history_database::HistoryDatabase;
 pub use id_database::IdDatabase;
 pub use record_database::RecordDatabase;
 use schema_database::SchemaDatabase;
 use secondary_index_database::SecondaryIndexDatabase;
+use ttl_database::TtlDatabase;
 
 pub type SecondaryIndexDatabases = HashMap<(SchemaIdentifier, usize), SecondaryIndexDatabase>;
 
@@ -49,6 +63,9 @@ pub struct CacheCommonOptions {
     /// The chunk size when calculating intersection of index queries.
     pub intersection_chunk_size: usize,
 
+    /// Maximum number of records a query may scan when no secondary index answers it.
+    pub max_scan_records: usize,
+
     /// Provide a path where db will be created. If nothing is provided, will default to a temp location.
     /// Db path will be `PathBuf.join(String)`.
     pub path: Option<(PathBuf, String)>,
@@ -60,6 +77,7 @@ impl Default for CacheCommonOptions {
             max_readers: 1000,
             max_db_size: 1000,
             intersection_chunk_size: 100,
+            max_scan_records: 100_000,
             path: None,
         }
     }
@@ -101,7 +119,14 @@ impl Default for CacheWriteOptions {
 pub struct LmdbRwCache {
     common: LmdbCacheCommon,
     checkpoint_db: CheckpointDatabase,
+    commit_db: CommitDatabase,
+    evicted_db: EvictedDatabase,
+    ttl_db: TtlDatabase,
     txn: SharedTransaction,
+    /// Whether `txn` has writes not committed yet.
+    uncommitted: Arc<AtomicBool>,
+    /// Compactors of the schemas with a retention policy.
+    compactors: HashMap<SchemaIdentifier, Compactor>,
 }
 
 impl LmdbRwCache {
@@ -135,11 +160,19 @@ impl LmdbRwCache {
         })?;
         let common = LmdbCacheCommon::new(&mut env, common_options, name, false)?;
         let checkpoint_db = CheckpointDatabase::new(&mut env)?;
+        let commit_db = CommitDatabase::new(&mut env)?;
+        let evicted_db = EvictedDatabase::new(&mut env)?;
+        let ttl_db = TtlDatabase::new(&mut env)?;
         let txn = env.create_txn()?;
         Ok(Self {
             common,
             checkpoint_db,
+            commit_db,
+            evicted_db,
+            ttl_db,
             txn,
+            uncommitted: Arc::new(AtomicBool::new(false)),
+            compactors: HashMap::new(),
         })
     }
 }
@@ -186,6 +219,51 @@ impl<C: LmdbCache> RoCache for C {
         Ok((schema, records))
     }
 
+    fn aggregate(
+        &self,
+        schema_name: &str,
+        expression: &AggregateExpression,
+    ) -> Result<Vec<AggregateGroup>, CacheError> {
+        let txn = self.begin_txn()?;
+        let txn = txn.as_txn();
+        let (schema, secondary_indexes) = self
+            .common()
+            .schema_db
+            .get_schema_from_name(schema_name)
+            .ok_or_else(|| CacheError::SchemaNotFound(schema_name.to_string()))?;
+        let query = QueryExpression::new(expression.filter.clone(), vec![], None, Skip::Skip(0));
+        let handler = LmdbQueryHandler::new(self.common(), txn, schema, secondary_indexes, &query);
+        handler.aggregate(&expression.group_by, &expression.aggregates)
+    }
+
+    fn explain(&self, schema_name: &str, query: &QueryExpression) -> Result<String, CacheError> {
+        let txn = self.begin_txn()?;
+        let txn = txn.as_txn();
+        let (schema, secondary_indexes) = self
+            .common()
+            .schema_db
+            .get_schema_from_name(schema_name)
+            .ok_or_else(|| CacheError::SchemaNotFound(schema_name.to_string()))?;
+        let handler = LmdbQueryHandler::new(self.common(), txn, schema, secondary_indexes, query);
+        Ok(handler.plan()?.to_string())
+    }
+
+    fn history(&self, schema_name: &str, key: &[u8]) -> Result<Vec<RecordVersion>, CacheError> {
+        let txn = self.begin_txn()?;
+        let txn = txn.as_txn();
+        let (schema, _) = self
+            .common()
+            .schema_db
+            .get_schema_from_name(schema_name)
+            .ok_or_else(|| CacheError::SchemaNotFound(schema_name.to_string()))?;
+        let schema_id = schema.identifier.ok_or(CacheError::SchemaHasNoIdentifier)?;
+        if self.common().history.since(txn, schema_id)?.is_none() {
+            return Err(CacheError::HistoryNotKept);
+        }
+        let id = self.common().id.get(txn, key)?;
+        self.common().history.versions(txn, schema_id, id)
+    }
+
     fn get_schema_and_indexes_by_name(
         &self,
         name: &str,
@@ -199,10 +277,17 @@ impl<C: LmdbCache> RoCache for C {
     }
 
     fn get_schema(&self, schema_identifier: SchemaIdentifier) -> Result<&Schema, CacheError> {
+        self.get_schema_and_indexes(schema_identifier)
+            .map(|(schema, _)| schema)
+    }
+
+    fn get_schema_and_indexes(
+        &self,
+        schema_identifier: SchemaIdentifier,
+    ) -> Result<&(Schema, Vec<IndexDefinition>), CacheError> {
         self.common()
             .schema_db
             .get_schema(schema_identifier)
-            .map(|(schema, _)| schema)
             .ok_or(CacheError::SchemaIdentifierNotFound(schema_identifier))
     }
 }
@@ -211,25 +296,35 @@ impl RwCache for LmdbRwCache {
     fn insert(&self, record: &mut Record) -> Result<u64, CacheError> {
         let (schema, secondary_indexes) = self.get_schema_and_indexes_from_record(record)?;
         record.version = Some(INITIAL_RECORD_VERSION);
-        self.insert_impl(record, schema, secondary_indexes)
+        let mut txn = self.txn.write();
+        self.uncommitted.store(true, Ordering::SeqCst);
+        self.insert_impl(txn.txn_mut(), record, schema, secondary_indexes)
     }
 
     fn delete(&self, key: &[u8]) -> Result<u32, CacheError> {
-        let (_, _, version) = self.delete_impl(key)?;
-        Ok(version)
+        let mut txn = self.txn.write();
+        self.uncommitted.store(true, Ordering::SeqCst);
+        self.delete_impl(txn.txn_mut(), key)
     }
 
     fn update(&self, key: &[u8], record: &mut Record) -> Result<u32, CacheError> {
-        let (schema, secondary_indexes, old_version) = self.delete_impl(key)?;
+        let (schema, secondary_indexes) = self.get_schema_and_indexes_from_record(record)?;
+        // The lock is held for both steps, so the compactor can't evict the record in between.
+        let mut txn = self.txn.write();
+        self.uncommitted.store(true, Ordering::SeqCst);
+        let txn = txn.txn_mut();
+        let old_version = self.delete_impl(txn, key)?;
         record.version = Some(old_version + 1);
-        self.insert_impl(record, schema, secondary_indexes)?;
+        self.insert_impl(txn, record, schema, secondary_indexes)?;
         Ok(old_version)
     }
 
     fn commit(&self, checkpoint: &SourceStates) -> Result<(), CacheError> {
         let mut txn = self.txn.write();
         self.checkpoint_db.write(txn.txn_mut(), checkpoint)?;
+        self.commit_db.increment(txn.txn_mut())?;
         txn.commit_and_renew()?;
+        self.uncommitted.store(false, Ordering::SeqCst);
         Ok(())
     }
 
@@ -237,38 +332,94 @@ impl RwCache for LmdbRwCache {
         let txn = self.txn.read();
         self.checkpoint_db.read(txn.txn())
     }
-}
 
-impl LmdbRwCache {
-    fn delete_impl(&self, key: &[u8]) -> Result<(&Schema, &[IndexDefinition], u32), CacheError> {
-        let record = self.get(key)?.record;
-        let (schema, secondary_indexes) = self.get_schema_and_indexes_from_record(&record)?;
+    fn set_retention_policy(
+        &mut self,
+        schema_name: &str,
+        policy: RetentionPolicy,
+        on_evict: Option<OnEvict>,
+    ) -> Result<(), CacheError> {
+        let evictor = self.evictor(schema_name, &policy)?;
+        let schema_id = evictor.schema_id();
+        // Dropping the previous compactor stops it.
+        self.compactors.remove(&schema_id);
+        evictor.index_ttl(self.txn.write().txn_mut())?;
+        if policy != RetentionPolicy::default() {
+            self.compactors.insert(
+                schema_id,
+                Compactor::start(schema_name.to_string(), evictor, on_evict),
+            );
+        }
+        Ok(())
+    }
 
+    fn set_history(&self, schema_name: &str, keep: bool) -> Result<(), CacheError> {
+        let (schema, _) = self
+            .common
+            .schema_db
+            .get_schema_from_name(schema_name)
+            .ok_or_else(|| CacheError::SchemaNotFound(schema_name.to_string()))?;
+        let schema_id = schema.identifier.ok_or(CacheError::SchemaHasNoIdentifier)?;
         let mut txn = self.txn.write();
         let txn = txn.txn_mut();
+        let history = self.common.history;
+        match (keep, history.since(txn, schema_id)?.is_some()) {
+            (true, false) => {
+                // The records already in the cache are their version as of the latest commit.
+                let commit = self.commit_db.get(txn)?;
+                for (id, record) in self.common.db.records_of(txn, schema_id)? {
+                    history.insert(txn, schema_id, id, &record, commit)?;
+                }
+                history.keep_since(txn, schema_id, commit)
+            }
+            (false, true) => history.forget(txn, schema_id),
+            _ => Ok(()),
+        }
+    }
+}
 
+impl LmdbRwCache {
+    /// Deletes the record with primary key `key`, returning its version. Records evicted by a
+    /// retention policy only have their version left.
+    fn delete_impl(&self, txn: &mut RwTransaction, key: &[u8]) -> Result<u32, CacheError> {
         let id = self.common.id.get(txn, key)?;
+        let Some(record) = self.common.db.get_opt(txn, id)? else {
+            return self.evicted_db.remove(txn, id)?.ok_or(CacheError::Query(
+                QueryError::GetValue(dozer_storage::lmdb::Error::NotFound),
+            ));
+        };
+        let (schema, secondary_indexes) = self.get_schema_and_indexes_from_record(&record)?;
+
         self.common.db.delete(txn, id)?;
 
         let indexer = Indexer {
             secondary_indexes: &self.common.secondary_indexes,
+            full_text_lengths: self.common.full_text_lengths,
         };
         indexer.delete_indexes(txn, &record, schema, secondary_indexes, id)?;
+        if let Some((schema_id, field)) = self.ttl_field(schema)? {
+            self.ttl_db
+                .delete(txn, schema_id, &record.values[field], id)?;
+        }
+
+        if let Some(schema_id) = self.history_schema_id(txn, schema)? {
+            let commit = self.commit_db.next(txn)?;
+            self.common.history.close(txn, schema_id, id, commit)?;
+        }
+
         let version = record
             .version
             .expect("All records in cache should have a version");
-        Ok((schema, secondary_indexes, version))
+        Ok(version)
     }
 
     fn insert_impl(
         &self,
+        txn: &mut RwTransaction,
         record: &Record,
         schema: &Schema,
         secondary_indexes: &[IndexDefinition],
     ) -> Result<u64, CacheError> {
-        let mut txn = self.txn.write();
-        let txn = txn.txn_mut();
-
         let id = if schema.primary_index.is_empty() {
             self.common.id.get_or_generate(txn, None)?
         } else {
@@ -276,15 +427,63 @@ impl LmdbRwCache {
             self.common.id.get_or_generate(txn, Some(&primary_key))?
         };
         self.common.db.insert(txn, id, record)?;
+        // A record with this primary key may have been evicted.
+        self.evicted_db.remove(txn, id)?;
 
         let indexer = Indexer {
             secondary_indexes: &self.common.secondary_indexes,
+            full_text_lengths: self.common.full_text_lengths,
         };
 
         indexer.build_indexes(txn, record, schema, secondary_indexes, id)?;
+        if let Some((schema_id, field)) = self.ttl_field(schema)? {
+            self.ttl_db
+                .insert(txn, schema_id, &record.values[field], id)?;
+        }
+
+        if let Some(schema_id) = self.history_schema_id(txn, schema)? {
+            let commit = self.commit_db.next(txn)?;
+            self.common
+                .history
+                .insert(txn, schema_id, id, record, commit)?;
+        }
 
         Ok(id_from_bytes(id))
     }
+
+    /// Returns the identifier of the schema if it keeps history.
+    fn history_schema_id(
+        &self,
+        txn: &RwTransaction,
+        schema: &Schema,
+    ) -> Result<Option<SchemaIdentifier>, CacheError> {
+        let schema_id = schema.identifier.ok_or(CacheError::SchemaHasNoIdentifier)?;
+        Ok(self
+            .common
+            .history
+            .since(txn, schema_id)?
+            .map(|_| schema_id))
+    }
+
+    fn evictor(&self, schema_name: &str, policy: &RetentionPolicy) -> Result<Evictor, CacheError> {
+        let schema = self
+            .common
+            .schema_db
+            .get_schema_from_name(schema_name)
+            .ok_or_else(|| CacheError::SchemaNotFound(schema_name.to_string()))?;
+        Evictor::new(self, schema.clone(), policy)
+    }
+
+    /// Returns the identifier of the schema and the index of the field its records expire by, if
+    /// its retention policy has a time to live.
+    fn ttl_field(&self, schema: &Schema) -> Result<Option<(SchemaIdentifier, usize)>, CacheError> {
+        let schema_id = schema.identifier.ok_or(CacheError::SchemaHasNoIdentifier)?;
+        Ok(self
+            .compactors
+            .get(&schema_id)
+            .and_then(Compactor::ttl_field)
+            .map(|field| (schema_id, field)))
+    }
 }
 
 fn id_from_bytes(bytes: [u8; 8]) -> u64 {
@@ -408,7 +607,9 @@ pub struct LmdbCacheCommon {
     db: RecordDatabase,
     id: IdDatabase,
     secondary_indexes: SecondaryIndexDatabases,
+    full_text_lengths: FullTextLengthDatabase,
     schema_db: SchemaDatabase,
+    history: HistoryDatabase,
     cache_options: CacheCommonOptions,
     /// File name of the database.
     name: String,
@@ -425,6 +626,8 @@ impl LmdbCacheCommon {
         let db = RecordDatabase::new(env, !read_only)?;
         let id = IdDatabase::new(env, !read_only)?;
         let schema_db = SchemaDatabase::new(env, !read_only)?;
+        let history = HistoryDatabase::new(env, !read_only)?;
+        let full_text_lengths = FullTextLengthDatabase::new(env, !read_only)?;
 
         // Open existing secondary index databases.
         let mut secondary_indexe_databases = HashMap::default();
@@ -440,7 +643,9 @@ impl LmdbCacheCommon {
             db,
             id,
             secondary_indexes: secondary_indexe_databases,
+            full_text_lengths,
             schema_db,
+            history,
             cache_options: options,
             name,
         })
@@ -477,5 +682,17 @@ mod tests {
         ) -> (&SharedTransaction, &SecondaryIndexDatabases) {
             (&self.txn, &self.common.secondary_indexes)
         }
+
+        /// Evicts the records over `policy` once, as of `now`.
+        pub fn evict(
+            &self,
+            schema_name: &str,
+            policy: &RetentionPolicy,
+            now: dozer_types::chrono::DateTime<dozer_types::chrono::Utc>,
+        ) -> Result<usize, CacheError> {
+            let evictor = self.evictor(schema_name, policy)?;
+            evictor.index_ttl(self.txn.write().txn_mut())?;
+            Ok(evictor.evict(now)?.len())
+        }
     }
 }
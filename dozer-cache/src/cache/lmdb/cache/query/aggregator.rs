@@ -0,0 +1,316 @@
+use std::collections::BTreeMap;
+
+use dozer_types::ordered_float::OrderedFloat;
+use dozer_types::rust_decimal::Decimal;
+use dozer_types::types::{Field, FieldType, Record, Schema};
+
+use crate::cache::expression::Aggregate;
+use crate::cache::AggregateGroup;
+use crate::errors::{CacheError, PlanError};
+
+/// Computes aggregates of records, grouped by the values of some fields.
+#[derive(Debug)]
+pub struct Aggregator<'a> {
+    schema: &'a Schema,
+    group_by: Vec<usize>,
+    aggregates: Vec<(Function, usize)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Function {
+    Count,
+    Sum,
+    Avg,
+    Min,
+    Max,
+}
+
+#[derive(Debug, Clone)]
+enum Accumulator {
+    Count(u64),
+    Sum(Option<Sum>),
+    Avg(Option<Sum>, u64),
+    Min(Option<Field>),
+    Max(Option<Field>),
+}
+
+/// Sums are kept in wider types so that integer sums never overflow.
+#[derive(Debug, Clone, Copy)]
+enum Sum {
+    UInt(u128),
+    Int(i128),
+    Float(f64),
+    Decimal(Decimal),
+}
+
+impl<'a> Aggregator<'a> {
+    pub fn new(
+        schema: &'a Schema,
+        group_by: &[String],
+        aggregates: &[Aggregate],
+    ) -> Result<Self, PlanError> {
+        let field_index = |name: &str| {
+            schema
+                .fields
+                .iter()
+                .position(|field| field.name == name)
+                .ok_or_else(|| PlanError::FieldNotFound(name.to_string()))
+        };
+        let group_by = group_by
+            .iter()
+            .map(|name| field_index(name))
+            .collect::<Result<_, _>>()?;
+        let aggregates = aggregates
+            .iter()
+            .map(|aggregate| {
+                let (function, name) = match aggregate {
+                    // The field is not used.
+                    Aggregate::Count => return Ok((Function::Count, 0)),
+                    Aggregate::Sum(name) => (Function::Sum, name),
+                    Aggregate::Avg(name) => (Function::Avg, name),
+                    Aggregate::Min(name) => (Function::Min, name),
+                    Aggregate::Max(name) => (Function::Max, name),
+                };
+                let index = field_index(name)?;
+                let typ = schema.fields[index].typ;
+                if matches!(function, Function::Sum | Function::Avg)
+                    && !matches!(
+                        typ,
+                        FieldType::UInt | FieldType::Int | FieldType::Float | FieldType::Decimal
+                    )
+                {
+                    return Err(PlanError::NotNumeric(name.clone(), typ));
+                }
+                Ok((function, index))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self {
+            schema,
+            group_by,
+            aggregates,
+        })
+    }
+
+    /// Returns the groups sorted by their keys. Without `group_by` fields, there's exactly one
+    /// group, even if there's no record. Records are read one at a time, stopping at the first
+    /// error.
+    pub fn aggregate(
+        &self,
+        records: impl Iterator<Item = Result<Record, CacheError>>,
+    ) -> Result<Vec<AggregateGroup>, CacheError> {
+        let mut groups = BTreeMap::<Vec<Field>, Vec<Accumulator>>::new();
+        if self.group_by.is_empty() {
+            groups.insert(vec![], self.new_accumulators());
+        }
+        for record in records {
+            let record = record?;
+            let key = self
+                .group_by
+                .iter()
+                .map(|index| record.values[*index].clone())
+                .collect::<Vec<_>>();
+            let accumulators = groups.entry(key).or_insert_with(|| self.new_accumulators());
+            for (accumulator, (_, index)) in accumulators.iter_mut().zip(&self.aggregates) {
+                accumulator.add(&record.values[*index]).ok_or_else(|| {
+                    CacheError::AggregateOverflow(self.schema.fields[*index].name.clone())
+                })?;
+            }
+        }
+
+        groups
+            .into_iter()
+            .map(|(key, accumulators)| {
+                let values = accumulators
+                    .into_iter()
+                    .zip(&self.aggregates)
+                    .map(|(accumulator, (_, index))| {
+                        accumulator.finish().ok_or_else(|| {
+                            CacheError::AggregateOverflow(self.schema.fields[*index].name.clone())
+                        })
+                    })
+                    .collect::<Result<_, _>>()?;
+                Ok(AggregateGroup { key, values })
+            })
+            .collect()
+    }
+
+    fn new_accumulators(&self) -> Vec<Accumulator> {
+        self.aggregates
+            .iter()
+            .map(|(function, _)| match function {
+                Function::Count => Accumulator::Count(0),
+                Function::Sum => Accumulator::Sum(None),
+                Function::Avg => Accumulator::Avg(None, 0),
+                Function::Min => Accumulator::Min(None),
+                Function::Max => Accumulator::Max(None),
+            })
+            .collect()
+    }
+}
+
+impl Accumulator {
+    /// Returns `None` if the sum overflows.
+    fn add(&mut self, field: &Field) -> Option<()> {
+        match self {
+            Accumulator::Count(count) => *count += 1,
+            _ if field == &Field::Null => (),
+            Accumulator::Sum(sum) => add_to_sum(sum, field)?,
+            Accumulator::Avg(sum, count) => {
+                add_to_sum(sum, field)?;
+                *count += 1;
+            }
+            Accumulator::Min(min) => {
+                if min.as_ref().map_or(true, |min| field < min) {
+                    *min = Some(field.clone());
+                }
+            }
+            Accumulator::Max(max) => {
+                if max.as_ref().map_or(true, |max| field > max) {
+                    *max = Some(field.clone());
+                }
+            }
+        }
+        Some(())
+    }
+
+    /// Returns `None` if the result overflows.
+    fn finish(self) -> Option<Field> {
+        Some(match self {
+            Accumulator::Count(count) => Field::UInt(count),
+            Accumulator::Sum(None) | Accumulator::Avg(None, _) => Field::Null,
+            Accumulator::Sum(Some(sum)) => match sum {
+                Sum::UInt(sum) => Field::UInt(sum.try_into().ok()?),
+                Sum::Int(sum) => Field::Int(sum.try_into().ok()?),
+                Sum::Float(sum) => Field::Float(OrderedFloat(sum)),
+                Sum::Decimal(sum) => Field::Decimal(sum),
+            },
+            Accumulator::Avg(Some(sum), count) => match sum {
+                Sum::UInt(sum) => Field::Float(OrderedFloat(sum as f64 / count as f64)),
+                Sum::Int(sum) => Field::Float(OrderedFloat(sum as f64 / count as f64)),
+                Sum::Float(sum) => Field::Float(OrderedFloat(sum / count as f64)),
+                Sum::Decimal(sum) => Field::Decimal(sum / Decimal::from(count)),
+            },
+            Accumulator::Min(field) | Accumulator::Max(field) => field.unwrap_or(Field::Null),
+        })
+    }
+}
+
+/// Returns `None` if the sum overflows.
+fn add_to_sum(sum: &mut Option<Sum>, field: &Field) -> Option<()> {
+    let value = match field {
+        Field::UInt(value) => Sum::UInt(*value as u128),
+        Field::Int(value) => Sum::Int(*value as i128),
+        Field::Float(value) => Sum::Float(value.0),
+        Field::Decimal(value) => Sum::Decimal(*value),
+        // The field types are checked when creating the aggregator.
+        _ => return Some(()),
+    };
+    *sum = Some(match (*sum, value) {
+        (None, value) => value,
+        (Some(Sum::UInt(a)), Sum::UInt(b)) => Sum::UInt(a + b),
+        (Some(Sum::Int(a)), Sum::Int(b)) => Sum::Int(a + b),
+        (Some(Sum::Float(a)), Sum::Float(b)) => Sum::Float(a + b),
+        (Some(Sum::Decimal(a)), Sum::Decimal(b)) => Sum::Decimal(a.checked_add(b)?),
+        (Some(sum), _) => sum,
+    });
+    Some(())
+}
+
+#[cfg(test)]
+mod tests {
+    use dozer_types::types::{FieldDefinition, SourceDefinition};
+
+    use super::*;
+
+    #[test]
+    fn test_aggregate() {
+        let field = |name: &str, typ| FieldDefinition {
+            name: name.to_string(),
+            typ,
+            nullable: true,
+            source: SourceDefinition::Dynamic,
+        };
+        let schema = Schema {
+            identifier: None,
+            fields: vec![
+                field("group", FieldType::String),
+                field("int", FieldType::Int),
+                field("float", FieldType::Float),
+            ],
+            primary_index: vec![],
+        };
+        let records = [("a", Some(1), 1.5), ("b", None, 2.0), ("a", Some(4), 0.5)]
+            .into_iter()
+            .map(|(group, int, float)| {
+                Record::new(
+                    None,
+                    vec![
+                        Field::String(group.to_string()),
+                        int.map_or(Field::Null, Field::Int),
+                        Field::Float(OrderedFloat(float)),
+                    ],
+                    None,
+                )
+            })
+            .collect::<Vec<_>>();
+        let aggregates = [
+            Aggregate::Count,
+            Aggregate::Sum("int".to_string()),
+            Aggregate::Avg("int".to_string()),
+            Aggregate::Min("float".to_string()),
+            Aggregate::Max("group".to_string()),
+        ];
+
+        let groups = Aggregator::new(&schema, &["group".to_string()], &aggregates)
+            .unwrap()
+            .aggregate(records.into_iter().map(Ok))
+            .unwrap();
+        assert_eq!(
+            groups,
+            vec![
+                AggregateGroup {
+                    key: vec![Field::String("a".to_string())],
+                    values: vec![
+                        Field::UInt(2),
+                        Field::Int(5),
+                        Field::Float(OrderedFloat(2.5)),
+                        Field::Float(OrderedFloat(0.5)),
+                        Field::String("a".to_string()),
+                    ],
+                },
+                AggregateGroup {
+                    key: vec![Field::String("b".to_string())],
+                    values: vec![
+                        Field::UInt(1),
+                        Field::Null,
+                        Field::Null,
+                        Field::Float(OrderedFloat(2.0)),
+                        Field::String("b".to_string()),
+                    ],
+                },
+            ]
+        );
+
+        // Without `group_by`, there's one group even without records.
+        let groups = Aggregator::new(&schema, &[], &aggregates[..2])
+            .unwrap()
+            .aggregate(std::iter::empty())
+            .unwrap();
+        assert_eq!(
+            groups,
+            vec![AggregateGroup {
+                key: vec![],
+                values: vec![Field::UInt(0), Field::Null],
+            }]
+        );
+
+        assert!(matches!(
+            Aggregator::new(&schema, &[], &[Aggregate::Sum("group".to_string())]),
+            Err(PlanError::NotNumeric(_, FieldType::String))
+        ));
+        assert!(matches!(
+            Aggregator::new(&schema, &["unknown".to_string()], &[]),
+            Err(PlanError::FieldNotFound(_))
+        ));
+    }
+}
@@ -1,19 +1,29 @@
 use std::cmp::Ordering;
+use std::collections::BTreeSet;
 
+use super::aggregator::Aggregator;
 use super::intersection::intersection;
 use super::iterator::{CacheIterator, KeyEndpoint};
+use super::ranker::{self, ScoredTerm};
 use crate::cache::expression::Skip;
-use crate::cache::lmdb::cache::{id_from_bytes, id_to_bytes, LmdbCacheCommon};
-use crate::cache::RecordWithId;
+use crate::cache::lmdb::cache::{
+    id_from_bytes, id_to_bytes, LmdbCacheCommon, SecondaryIndexDatabase,
+};
 use crate::cache::{
-    expression::{Operator, QueryExpression, SortDirection},
+    expression::{Aggregate, Operator, QueryExpression, SortDirection},
     index,
-    plan::{IndexScan, IndexScanKind, Plan, QueryPlanner, SortedInvertedRangeQuery},
+    plan::{
+        FilteredScan, IndexFilter, IndexScan, IndexScanKind, Plan, QueryPlanner, RankedScan,
+        RecordFilter, ResidualIndexScans, ScanReason, SnapshotScan, SortedInvertedRangeQuery,
+        SpatialFilter, SpatialScan,
+    },
 };
-use crate::errors::{CacheError, IndexError};
+use crate::cache::{AggregateGroup, RecordWithId};
+use crate::errors::{CacheError, IndexError, PlanError};
 use dozer_storage::lmdb::Transaction;
-use dozer_types::types::{Field, IndexDefinition, Schema};
-use itertools::Either;
+use dozer_types::log::debug;
+use dozer_types::types::{Field, IndexDefinition, Record, Schema};
+use itertools::{Either, Itertools};
 
 pub struct LmdbQueryHandler<'a, T: Transaction> {
     common: &'a LmdbCacheCommon,
@@ -39,11 +49,22 @@ impl<'a, T: Transaction> LmdbQueryHandler<'a, T> {
         }
     }
 
-    pub fn count(&self) -> Result<usize, CacheError> {
+    pub fn plan(&self) -> Result<Plan, CacheError> {
         let planner = QueryPlanner::new(self.schema, self.secondary_indexes, self.query);
-        let execution = planner.plan()?;
-        match execution {
+        let plan = planner.plan()?;
+        debug!("Query {:?} is executed as {plan}", self.query);
+        Ok(plan)
+    }
+
+    pub fn count(&self) -> Result<usize, CacheError> {
+        match self.plan()? {
             Plan::IndexScans(index_scans) => Ok(self.build_index_scan(index_scans)?.count()),
+            Plan::ResidualIndexScans(scans) => Ok(self.residual_index_scans(scans)?.len()),
+            Plan::Union(union) => Ok(self.build_union(union)?.count()),
+            Plan::FilteredScan(filtered_scan) => Ok(self.filtered_scan(&filtered_scan)?.len()),
+            Plan::SpatialScan(spatial_scan) => Ok(self.spatial_scan(&spatial_scan)?.len()),
+            Plan::Ranked(ranked_scan) => Ok(self.ranked_scan(ranked_scan)?.len()),
+            Plan::SnapshotScan(snapshot_scan) => Ok(self.snapshot_scan(&snapshot_scan)?.len()),
             Plan::SeqScan(_) => Ok(match self.query.skip {
                 Skip::Skip(skip) => self
                     .common
@@ -58,28 +79,151 @@ impl<'a, T: Transaction> LmdbQueryHandler<'a, T> {
     }
 
     pub fn query(&self) -> Result<Vec<RecordWithId>, CacheError> {
-        let planner = QueryPlanner::new(self.schema, self.secondary_indexes, self.query);
-        let execution = planner.plan()?;
-        match execution {
+        self.execute(self.plan()?)
+    }
+
+    fn execute(&self, plan: Plan) -> Result<Vec<RecordWithId>, CacheError> {
+        match plan {
             Plan::IndexScans(index_scans) => {
                 self.collect_records(self.build_index_scan(index_scans)?)
             }
+            Plan::ResidualIndexScans(scans) => self.residual_index_scans(scans),
+            Plan::Union(union) => self.collect_records(self.build_union(union)?),
+            Plan::FilteredScan(filtered_scan) => self.filtered_scan(&filtered_scan),
+            Plan::SpatialScan(spatial_scan) => self.spatial_scan(&spatial_scan),
+            Plan::Ranked(ranked_scan) => self.ranked_scan(ranked_scan),
+            Plan::SnapshotScan(snapshot_scan) => self.snapshot_scan(&snapshot_scan),
             Plan::SeqScan(_seq_scan) => self.collect_records(self.all_ids()?),
             Plan::ReturnEmpty => Ok(vec![]),
         }
     }
 
+    pub fn aggregate(
+        &self,
+        group_by: &[String],
+        aggregates: &[Aggregate],
+    ) -> Result<Vec<AggregateGroup>, CacheError> {
+        let aggregator = Aggregator::new(self.schema, group_by, aggregates)?;
+        // Counting the ids found by index scans doesn't need to read the records.
+        if group_by.is_empty()
+            && aggregates
+                .iter()
+                .all(|aggregate| aggregate == &Aggregate::Count)
+        {
+            let count = Field::UInt(self.count()? as u64);
+            return Ok(vec![AggregateGroup {
+                key: vec![],
+                values: vec![count; aggregates.len()],
+            }]);
+        }
+        // Records are read one at a time, and no more of them than a scan would read.
+        let ids = match self.plan()? {
+            Plan::IndexScans(index_scans) => self.build_index_scan(index_scans)?.collect(),
+            Plan::Union(union) => self.build_union(union)?.collect::<Vec<_>>(),
+            Plan::SeqScan(_) => {
+                self.check_scan_limit(self.num_records()?, ScanReason::Aggregate)?;
+                // Records of all schemas are in the same databases.
+                return aggregator.aggregate(self.read_records(self.all_ids()?).filter(|record| {
+                    record
+                        .as_ref()
+                        .map_or(true, |record| record.schema_id == self.schema.identifier)
+                }));
+            }
+            plan => {
+                let records = self.execute(plan)?;
+                self.check_scan_limit(records.len(), ScanReason::Aggregate)?;
+                return aggregator.aggregate(records.into_iter().map(|record| Ok(record.record)));
+            }
+        };
+        self.check_scan_limit(ids.len(), ScanReason::Aggregate)?;
+        aggregator.aggregate(self.read_records(ids.into_iter()))
+    }
+
+    fn check_scan_limit(&self, num_records: usize, reason: ScanReason) -> Result<(), CacheError> {
+        let max_scan_records = self.common.cache_options.max_scan_records;
+        if num_records > max_scan_records {
+            return Err(PlanError::ScanLimitExceeded {
+                num_records,
+                max_scan_records,
+                reason: reason.to_string(),
+            }
+            .into());
+        }
+        Ok(())
+    }
+
+    /// Reads the records of `ids` lazily.
+    fn read_records(
+        &self,
+        ids: impl Iterator<Item = u64>,
+    ) -> impl Iterator<Item = Result<Record, CacheError>> + '_ {
+        ids.map(|id| self.common.db.get(self.txn, id_to_bytes(id)))
+    }
+
     pub fn all_ids(&self) -> Result<impl Iterator<Item = u64> + '_, CacheError> {
+        Ok(
+            skip(self.existing_ids()?, self.query.skip)
+                .take(self.query.limit.unwrap_or(usize::MAX)),
+        )
+    }
+
+    /// Ids of the records in the cache, in primary key order. Ids are never removed from the id
+    /// database, so the ones of deleted or evicted records are skipped.
+    fn existing_ids(&self) -> Result<impl Iterator<Item = u64> + '_, CacheError> {
         let cursor = self.common.id.open_ro_cursor(self.txn)?;
-        Ok(skip(
-            CacheIterator::new(cursor, None, SortDirection::Ascending)
-                .map(map_index_database_entry_to_id),
+        Ok(CacheIterator::new(cursor, None, SortDirection::Ascending)
+            .map(map_index_database_entry_to_id)
+            .filter(move |id| self.common.db.contains(self.txn, id_to_bytes(*id))))
+    }
+
+    fn build_index_scan(
+        &self,
+        index_scans: Vec<IndexScan>,
+    ) -> Result<impl Iterator<Item = u64> + '_, CacheError> {
+        Ok(skip(self.index_scan_ids(index_scans)?, self.query.skip)
+            .take(self.query.limit.unwrap_or(usize::MAX)))
+    }
+
+    /// Ids matching any of the index scan intersections, in ascending order.
+    fn build_union(
+        &self,
+        union: Vec<ResidualIndexScans>,
+    ) -> Result<impl Iterator<Item = u64> + '_, CacheError> {
+        let mut ids = BTreeSet::new();
+        for scans in union {
+            let scan_ids = self.index_scan_ids(scans.index_scans)?;
+            match &scans.residual {
+                None => ids.extend(scan_ids),
+                Some(residual) => {
+                    for id in scan_ids {
+                        if residual.matches(&self.common.db.get(self.txn, id_to_bytes(id))?) {
+                            ids.insert(id);
+                        }
+                    }
+                }
+            }
+        }
+        Ok(skip(ids.into_iter(), self.query.skip).take(self.query.limit.unwrap_or(usize::MAX)))
+    }
+
+    fn residual_index_scans(
+        &self,
+        scans: ResidualIndexScans,
+    ) -> Result<Vec<RecordWithId>, CacheError> {
+        let residual = scans.residual;
+        self.collect_matching_records(
+            self.index_scan_ids(scans.index_scans)?,
+            |record| {
+                residual
+                    .as_ref()
+                    .map_or(true, |filter| filter.matches(record))
+            },
             self.query.skip,
+            self.query.limit.unwrap_or(usize::MAX),
         )
-        .take(self.query.limit.unwrap_or(usize::MAX)))
     }
 
-    fn build_index_scan(
+    fn index_scan_ids(
         &self,
         index_scans: Vec<IndexScan>,
     ) -> Result<impl Iterator<Item = u64> + '_, CacheError> {
@@ -87,7 +231,7 @@ impl<'a, T: Transaction> LmdbQueryHandler<'a, T> {
             !index_scans.is_empty(),
             "Planner should not generate empty index scan"
         );
-        let full_scan = if index_scans.len() == 1 {
+        Ok(if index_scans.len() == 1 {
             // The fast path, without intersection calculation.
             Either::Left(self.query_with_secondary_index(&index_scans[0])?)
         } else {
@@ -100,45 +244,423 @@ impl<'a, T: Transaction> LmdbQueryHandler<'a, T> {
                 iterators,
                 self.common.cache_options.intersection_chunk_size,
             ))
+        })
+    }
+
+    /// Reads all records in the same order as a sequential scan, keeping the ones matching the
+    /// filter. Records are sorted in memory if the plan requires it.
+    fn filtered_scan(&self, filtered_scan: &FilteredScan) -> Result<Vec<RecordWithId>, CacheError> {
+        self.check_scan_limit(self.num_records()?, filtered_scan.reason)?;
+
+        let ids = self.existing_ids()?;
+        // Records of all schemas are in the same databases.
+        let matches = |record: &Record| {
+            record.schema_id == self.schema.identifier && filtered_scan.filter.matches(record)
         };
-        Ok(skip(full_scan, self.query.skip).take(self.query.limit.unwrap_or(usize::MAX)))
+        let limit = self.query.limit.unwrap_or(usize::MAX);
+        if filtered_scan.order_by.is_empty() {
+            return self.collect_matching_records(ids, matches, self.query.skip, limit);
+        }
+
+        let mut records = self.collect_matching_records(ids, matches, Skip::Skip(0), usize::MAX)?;
+        records.sort_by(|a, b| compare_records(&a.record, &b.record, &filtered_scan.order_by));
+        Ok(self.page(records))
     }
 
-    fn query_with_secondary_index(
-        &'a self,
-        index_scan: &IndexScan,
-    ) -> Result<impl Iterator<Item = u64> + 'a, CacheError> {
+    /// Reads the versions of the records current at the commit of the scan, keeping the ones
+    /// matching its filter. Records are sorted by the sort options if any, and by id otherwise.
+    fn snapshot_scan(&self, snapshot_scan: &SnapshotScan) -> Result<Vec<RecordWithId>, CacheError> {
         let schema_id = self
             .schema
             .identifier
             .ok_or(CacheError::SchemaHasNoIdentifier)?;
-        let index_db = *self
-            .common
-            .secondary_indexes
-            .get(&(schema_id, index_scan.index_id))
-            .ok_or(CacheError::SecondaryIndexDatabaseNotFound)?;
+        let history = self.common.history;
+        let since = history
+            .since(self.txn, schema_id)?
+            .ok_or(CacheError::HistoryNotKept)?;
+        if snapshot_scan.commit < since {
+            return Err(CacheError::HistoryNotAvailable {
+                since,
+                as_of: snapshot_scan.commit,
+            });
+        }
+
+        self.check_scan_limit(history.count(self.txn, schema_id)?, ScanReason::AsOfCommit)?;
+
+        let mut records = history.as_of(self.txn, schema_id, snapshot_scan.commit)?;
+        if let Some(filter) = &snapshot_scan.filter {
+            records.retain(|record| filter.matches(&record.record));
+        }
+        records.sort_by(|a, b| compare_records(&a.record, &b.record, &snapshot_scan.order_by));
+        Ok(self.page(records))
+    }
+
+    /// Reads the records found in the spatial index for the region of the filter, keeping the
+    /// ones matching it. Records are sorted by the sort options if any, and by id otherwise.
+    ///
+    /// `$nearest` searches regions of growing radius around its point, until one has enough
+    /// records to fill the page, and sorts them by distance.
+    fn spatial_scan(&self, spatial_scan: &SpatialScan) -> Result<Vec<RecordWithId>, CacheError> {
+        let index_db = self.secondary_index_db(spatial_scan.index_id)?;
+        let regions = spatial_scan.filter.nearest_regions();
+        let Some((world, smaller_regions)) = regions.split_last() else {
+            let mut records = self.spatial_records(index_db, spatial_scan, &spatial_scan.filter)?;
+            if !spatial_scan.order_by.is_empty() {
+                records
+                    .sort_by(|a, b| compare_records(&a.record, &b.record, &spatial_scan.order_by));
+            }
+            return Ok(self.page(records));
+        };
+
+        // Without limit, all the records are read anyway.
+        if let Some(limit) = self.query.limit {
+            for region in smaller_regions {
+                let records = self.nearest_records(index_db, spatial_scan, region)?;
+                if records.len() >= limit {
+                    return Ok(records);
+                }
+            }
+        }
+        self.nearest_records(index_db, spatial_scan, world)
+    }
+
+    /// The page of the records in the region nearest to the point of a `$nearest` scan. If it's
+    /// full, it's the page of all records, as the region contains all the nearer ones.
+    fn nearest_records(
+        &self,
+        index_db: SecondaryIndexDatabase,
+        spatial_scan: &SpatialScan,
+        region: &SpatialFilter,
+    ) -> Result<Vec<RecordWithId>, CacheError> {
+        let filter = &spatial_scan.filter;
+        let mut records = self.spatial_records(index_db, spatial_scan, region)?;
+        // The sort is stable, so records at the same distance stay ordered by id.
+        records.sort_by_cached_key(|record| {
+            filter.distance(&record.record.values[filter.field_index])
+        });
+        Ok(self.page(records))
+    }
+
+    /// Reads the records found in the spatial index for the region, by id, keeping the ones in
+    /// the region and matching the filter of the scan.
+    fn spatial_records(
+        &self,
+        index_db: SecondaryIndexDatabase,
+        spatial_scan: &SpatialScan,
+        region: &SpatialFilter,
+    ) -> Result<Vec<RecordWithId>, CacheError> {
+        let key_ranges = region
+            .bounding_boxes()
+            .iter()
+            .flat_map(index::get_spatial_key_ranges)
+            .collect::<Vec<_>>();
+        let spatial_ids = |(start, end): &(Vec<u8>, Vec<u8>)| {
+            let cursor = index_db.open_ro_cursor(self.txn)?;
+            let end = end.clone();
+            Ok::<_, CacheError>(
+                CacheIterator::new(
+                    cursor,
+                    Some(KeyEndpoint::Including(start.clone())),
+                    SortDirection::Ascending,
+                )
+                .take_while(move |(key, _)| *key <= end.as_slice())
+                .map(map_index_database_entry_to_id),
+            )
+        };
+
+        // The points are counted before their ids are collected.
+        let mut num_records = 0;
+        for key_range in &key_ranges {
+            num_records += spatial_ids(key_range)?.count();
+        }
+        self.check_scan_limit(num_records, ScanReason::SpatialRegion)?;
+
+        // The ranges of different boxes may overlap, so ids are deduplicated.
+        let mut ids = BTreeSet::new();
+        for key_range in &key_ranges {
+            ids.extend(spatial_ids(key_range)?);
+        }
+
+        let matches = |record: &Record| {
+            record
+                .values
+                .get(region.field_index)
+                .map_or(false, |field| region.matches(field))
+                && spatial_scan
+                    .residual
+                    .as_ref()
+                    .map_or(true, |residual| residual.matches(record))
+        };
+        self.collect_matching_records(ids.into_iter(), matches, Skip::Skip(0), usize::MAX)
+    }
+
+    /// Finds the records with the plan of the unsorted query, reading no more of them than a scan
+    /// would, and sorts them by their scores. Terms are weighted by the statistics of the full
+    /// text index of their field over all the records of the schema.
+    fn ranked_scan(&self, ranked_scan: RankedScan) -> Result<Vec<RecordWithId>, CacheError> {
+        let query = QueryExpression::new(self.query.filter.clone(), vec![], None, Skip::Skip(0));
+        let handler = LmdbQueryHandler::new(
+            self.common,
+            self.txn,
+            self.schema,
+            self.secondary_indexes,
+            &query,
+        );
+        let records = match *ranked_scan.plan {
+            Plan::IndexScans(index_scans) => {
+                let ids = handler.index_scan_ids(index_scans)?.collect::<Vec<_>>();
+                self.check_scan_limit(ids.len(), ScanReason::Ranked)?;
+                handler.collect_records(ids.into_iter())?
+            }
+            Plan::ResidualIndexScans(scans) => {
+                let ids = handler
+                    .index_scan_ids(scans.index_scans)?
+                    .collect::<Vec<_>>();
+                self.check_scan_limit(ids.len(), ScanReason::Ranked)?;
+                let residual = scans.residual;
+                handler.collect_matching_records(
+                    ids.into_iter(),
+                    |record| {
+                        residual
+                            .as_ref()
+                            .map_or(true, |filter| filter.matches(record))
+                    },
+                    Skip::Skip(0),
+                    usize::MAX,
+                )?
+            }
+            Plan::Union(union) => {
+                let ids = handler.build_union(union)?.collect::<Vec<_>>();
+                self.check_scan_limit(ids.len(), ScanReason::Ranked)?;
+                handler.collect_records(ids.into_iter())?
+            }
+            // Other plans check the scan limit before reading the records.
+            plan => handler.execute(plan)?,
+        };
+
+        let schema_id = self
+            .schema
+            .identifier
+            .ok_or(CacheError::SchemaHasNoIdentifier)?;
+        let num_records = self.num_records()?;
+        let mut terms = vec![];
+        for filter in &ranked_scan.filters {
+            // The planner only ranks fields with a full text index.
+            let index_id = self
+                .full_text_index_id(filter)
+                .ok_or(CacheError::SecondaryIndexDatabaseNotFound)?;
+            let index_db = self.secondary_index_db(index_id)?;
+            let total_length = self
+                .common
+                .full_text_lengths
+                .get(self.txn, schema_id, index_id)?;
+            let average_length = total_length as f64 / num_records.max(1) as f64;
+            for (term, is_prefix) in scored_terms(filter)? {
+                // A record may have several terms starting with a prefix.
+                let document_frequency = self
+                    .full_text_ids(index_db, &term, is_prefix)?
+                    .unique()
+                    .count();
+                terms.push(ScoredTerm {
+                    filter,
+                    term,
+                    is_prefix,
+                    document_frequency,
+                    average_length,
+                });
+            }
+        }
+        Ok(self.page(ranker::rank(
+            records,
+            &terms,
+            num_records,
+            ranked_scan.direction,
+        )))
+    }
 
+    /// Number of records of the queried schema.
+    fn num_records(&self) -> Result<usize, CacheError> {
+        let schema_id = self
+            .schema
+            .identifier
+            .ok_or(CacheError::SchemaHasNoIdentifier)?;
+        self.common.db.count_of(self.txn, schema_id)
+    }
+
+    /// Applies `skip` and `limit` to records sorted in memory.
+    fn page(&self, records: Vec<RecordWithId>) -> Vec<RecordWithId> {
+        let start = match self.query.skip {
+            Skip::Skip(skip) => skip,
+            Skip::After(after) => records
+                .iter()
+                .position(|record| record.id == after)
+                .map_or(records.len(), |position| position + 1),
+        };
+        records
+            .into_iter()
+            .skip(start)
+            .take(self.query.limit.unwrap_or(usize::MAX))
+            .collect()
+    }
+
+    /// Reads the records of `ids` matching `filter`, applying `skip` and `limit` to the matching
+    /// ones. Reading stops once `limit` records are found.
+    fn collect_matching_records(
+        &self,
+        ids: impl Iterator<Item = u64>,
+        filter: impl Fn(&Record) -> bool,
+        mut skip: Skip,
+        limit: usize,
+    ) -> Result<Vec<RecordWithId>, CacheError> {
+        let mut records = vec![];
+        for id in ids {
+            if records.len() >= limit {
+                break;
+            }
+            let record = self.common.db.get(self.txn, id_to_bytes(id))?;
+            if !filter(&record) {
+                continue;
+            }
+            match skip {
+                Skip::Skip(0) => records.push(RecordWithId::new(id, record)),
+                Skip::Skip(n) => skip = Skip::Skip(n - 1),
+                Skip::After(after) => {
+                    if id == after {
+                        skip = Skip::Skip(0);
+                    }
+                }
+            }
+        }
+        Ok(records)
+    }
+
+    fn query_with_secondary_index(
+        &'a self,
+        index_scan: &IndexScan,
+    ) -> Result<impl Iterator<Item = u64> + 'a, CacheError> {
+        let index_db = self.secondary_index_db(index_scan.index_id)?;
+
+        let (eq_filters, range_query) = match &index_scan.kind {
+            IndexScanKind::SortedInverted {
+                eq_filters,
+                range_query,
+            } => (eq_filters, range_query),
+            IndexScanKind::FullText { filter } => {
+                return Ok(Either::Right(
+                    self.full_text_scan_ids(index_db, filter)?.into_iter(),
+                ))
+            }
+        };
         let RangeSpec {
             start,
             end,
             direction,
-        } = get_range_spec(&index_scan.kind, index_scan.is_single_field_sorted_inverted)?;
+        } = get_range_spec(
+            eq_filters,
+            range_query.as_ref(),
+            index_scan.is_single_field_sorted_inverted,
+        );
 
         let cursor = index_db.open_ro_cursor(self.txn)?;
 
-        Ok(CacheIterator::new(cursor, start, direction)
-            .take_while(move |(key, _)| {
-                if let Some(end_key) = &end {
-                    match index_db.cmp(self.txn, key, end_key.key()) {
-                        Ordering::Less => matches!(direction, SortDirection::Ascending),
-                        Ordering::Equal => matches!(end_key, KeyEndpoint::Including(_)),
-                        Ordering::Greater => matches!(direction, SortDirection::Descending),
+        Ok(Either::Left(
+            CacheIterator::new(cursor, start, direction)
+                .take_while(move |(key, _)| {
+                    if let Some(end_key) = &end {
+                        match index_db.cmp(self.txn, key, end_key.key()) {
+                            Ordering::Less => matches!(direction, SortDirection::Ascending),
+                            Ordering::Equal => matches!(end_key, KeyEndpoint::Including(_)),
+                            Ordering::Greater => matches!(direction, SortDirection::Descending),
+                        }
+                    } else {
+                        true
                     }
-                } else {
-                    true
+                })
+                .map(map_index_database_entry_to_id),
+        ))
+    }
+
+    fn secondary_index_db(&self, index_id: usize) -> Result<SecondaryIndexDatabase, CacheError> {
+        let schema_id = self
+            .schema
+            .identifier
+            .ok_or(CacheError::SchemaHasNoIdentifier)?;
+        self.common
+            .secondary_indexes
+            .get(&(schema_id, index_id))
+            .copied()
+            .ok_or(CacheError::SecondaryIndexDatabaseNotFound)
+    }
+
+    /// Index of the full text index answering `filter`, if any.
+    fn full_text_index_id(&self, filter: &IndexFilter) -> Option<usize> {
+        let index = IndexDefinition::FullText(filter.field_index, filter.analyzer);
+        self.secondary_indexes
+            .iter()
+            .position(|definition| definition == &index)
+    }
+
+    /// Ids of the records matching a full text filter, in ascending order. The terms of the
+    /// filter are looked up separately, and the ids found are intersected or united.
+    fn full_text_scan_ids(
+        &self,
+        index_db: SecondaryIndexDatabase,
+        filter: &IndexFilter,
+    ) -> Result<Vec<u64>, CacheError> {
+        let mut ids: Option<BTreeSet<u64>> = None;
+        for (term, is_prefix) in scored_terms(filter)? {
+            let term_ids = self.full_text_ids(index_db, &term, is_prefix)?;
+            ids = Some(match ids {
+                None => term_ids.collect(),
+                Some(mut ids) if filter.op == Operator::MatchesAny => {
+                    ids.extend(term_ids);
+                    ids
+                }
+                Some(ids) => {
+                    let term_ids = term_ids.collect::<BTreeSet<_>>();
+                    ids.intersection(&term_ids).copied().collect()
                 }
-            })
-            .map(map_index_database_entry_to_id))
+            });
+        }
+        let ids = ids.unwrap_or_default();
+
+        if filter.op != Operator::MatchesPhrase {
+            return Ok(ids.into_iter().collect());
+        }
+        // The index doesn't store the positions of the terms, so phrases are checked on the
+        // records having all terms.
+        let phrase = RecordFilter::Simple(filter.clone());
+        let mut matching = vec![];
+        for id in ids {
+            if phrase.matches(&self.common.db.get(self.txn, id_to_bytes(id))?) {
+                matching.push(id);
+            }
+        }
+        Ok(matching)
+    }
+
+    /// Ids of the records having `term`, or a term starting with it if `is_prefix`.
+    fn full_text_ids(
+        &self,
+        index_db: SecondaryIndexDatabase,
+        term: &str,
+        is_prefix: bool,
+    ) -> Result<impl Iterator<Item = u64> + '_, CacheError> {
+        let key = index::get_full_text_secondary_index(term);
+        let cursor = index_db.open_ro_cursor(self.txn)?;
+        Ok(CacheIterator::new(
+            cursor,
+            Some(KeyEndpoint::Including(key.clone())),
+            SortDirection::Ascending,
+        )
+        .take_while(move |(index_key, _)| {
+            if is_prefix {
+                index_key.starts_with(&key)
+            } else {
+                *index_key == key.as_slice()
+            }
+        })
+        .map(map_index_database_entry_to_id))
     }
 
     fn collect_records(
@@ -162,113 +684,110 @@ struct RangeSpec {
     direction: SortDirection,
 }
 
+/// The distinct terms of a full text filter, with whether they're prefixes.
+fn scored_terms(filter: &IndexFilter) -> Result<Vec<(String, bool)>, CacheError> {
+    let text = match &filter.val {
+        Field::String(text) | Field::Text(text) => text,
+        _ => return Err(CacheError::Index(IndexError::ExpectedStringFullText)),
+    };
+    Ok(if filter.op == Operator::MatchesPrefix {
+        let prefix = index::analyze_prefix(&filter.analyzer, text);
+        if prefix.is_empty() {
+            vec![]
+        } else {
+            vec![(prefix, true)]
+        }
+    } else {
+        index::analyze(&filter.analyzer, text)
+            .into_iter()
+            .unique()
+            .map(|term| (term, false))
+            .collect()
+    })
+}
+
 fn get_range_spec(
-    index_scan_kind: &IndexScanKind,
+    eq_filters: &[(usize, Field)],
+    range_query: Option<&SortedInvertedRangeQuery>,
     is_single_field_sorted_inverted: bool,
-) -> Result<RangeSpec, CacheError> {
-    match &index_scan_kind {
-        IndexScanKind::SortedInverted {
-            eq_filters,
-            range_query,
-        } => {
-            let comparison_key = build_sorted_inverted_comparision_key(
-                eq_filters,
-                range_query.as_ref(),
-                is_single_field_sorted_inverted,
-            );
-            // There're 3 cases:
-            // 1. Range query with operator.
-            // 2. Range query without operator (only order by).
-            // 3. No range query.
-            Ok(if let Some(range_query) = range_query {
-                match range_query.operator_and_value {
-                    Some((operator, _)) => {
-                        // Here we respond to case 1, examples are `a = 1 && b > 2` or `b < 2`.
-                        let comparison_key = comparison_key.expect("here's at least a range query");
-                        let null_key = build_sorted_inverted_comparision_key(
-                            eq_filters,
-                            Some(&SortedInvertedRangeQuery {
-                                field_index: range_query.field_index,
-                                operator_and_value: Some((operator, Field::Null)),
-                                sort_direction: range_query.sort_direction,
-                            }),
-                            is_single_field_sorted_inverted,
-                        )
-                        .expect("we provided a range query");
-                        get_key_interval_from_range_query(
-                            comparison_key,
-                            null_key,
-                            operator,
-                            range_query.sort_direction,
-                        )
+) -> RangeSpec {
+    let comparison_key = build_sorted_inverted_comparision_key(
+        eq_filters,
+        range_query,
+        is_single_field_sorted_inverted,
+    );
+    // There're 3 cases:
+    // 1. Range query with operator.
+    // 2. Range query without operator (only order by).
+    // 3. No range query.
+    if let Some(range_query) = range_query {
+        match range_query.operator_and_value {
+            Some((operator, _)) => {
+                // Here we respond to case 1, examples are `a = 1 && b > 2` or `b < 2`.
+                let comparison_key = comparison_key.expect("here's at least a range query");
+                let null_key = build_sorted_inverted_comparision_key(
+                    eq_filters,
+                    Some(&SortedInvertedRangeQuery {
+                        field_index: range_query.field_index,
+                        operator_and_value: Some((operator, Field::Null)),
+                        sort_direction: range_query.sort_direction,
+                    }),
+                    is_single_field_sorted_inverted,
+                )
+                .expect("we provided a range query");
+                get_key_interval_from_range_query(
+                    comparison_key,
+                    null_key,
+                    operator,
+                    range_query.sort_direction,
+                )
+            }
+            None => {
+                // Here we respond to case 2, examples are `a = 1 && b asc` or `b desc`.
+                if let Some(comparison_key) = comparison_key {
+                    // This is the case like `a = 1 && b asc`. The comparison key is only built from `a = 1`.
+                    // We use `a = 1 && b = null` as a sentinel, using the invariant that `null` is greater than anything.
+                    let null_key = build_sorted_inverted_comparision_key(
+                        eq_filters,
+                        Some(&SortedInvertedRangeQuery {
+                            field_index: range_query.field_index,
+                            operator_and_value: Some((Operator::LT, Field::Null)),
+                            sort_direction: range_query.sort_direction,
+                        }),
+                        is_single_field_sorted_inverted,
+                    )
+                    .expect("we provided a range query");
+                    match range_query.sort_direction {
+                        SortDirection::Ascending => RangeSpec {
+                            start: Some(KeyEndpoint::Excluding(comparison_key)),
+                            end: Some(KeyEndpoint::Including(null_key)),
+                            direction: SortDirection::Ascending,
+                        },
+                        SortDirection::Descending => RangeSpec {
+                            start: Some(KeyEndpoint::Including(null_key)),
+                            end: Some(KeyEndpoint::Excluding(comparison_key)),
+                            direction: SortDirection::Descending,
+                        },
                     }
-                    None => {
-                        // Here we respond to case 2, examples are `a = 1 && b asc` or `b desc`.
-                        if let Some(comparison_key) = comparison_key {
-                            // This is the case like `a = 1 && b asc`. The comparison key is only built from `a = 1`.
-                            // We use `a = 1 && b = null` as a sentinel, using the invariant that `null` is greater than anything.
-                            let null_key = build_sorted_inverted_comparision_key(
-                                eq_filters,
-                                Some(&SortedInvertedRangeQuery {
-                                    field_index: range_query.field_index,
-                                    operator_and_value: Some((Operator::LT, Field::Null)),
-                                    sort_direction: range_query.sort_direction,
-                                }),
-                                is_single_field_sorted_inverted,
-                            )
-                            .expect("we provided a range query");
-                            match range_query.sort_direction {
-                                SortDirection::Ascending => RangeSpec {
-                                    start: Some(KeyEndpoint::Excluding(comparison_key)),
-                                    end: Some(KeyEndpoint::Including(null_key)),
-                                    direction: SortDirection::Ascending,
-                                },
-                                SortDirection::Descending => RangeSpec {
-                                    start: Some(KeyEndpoint::Including(null_key)),
-                                    end: Some(KeyEndpoint::Excluding(comparison_key)),
-                                    direction: SortDirection::Descending,
-                                },
-                            }
-                        } else {
-                            // Just all of them.
-                            RangeSpec {
-                                start: None,
-                                end: None,
-                                direction: range_query.sort_direction,
-                            }
-                        }
+                } else {
+                    // Just all of them.
+                    RangeSpec {
+                        start: None,
+                        end: None,
+                        direction: range_query.sort_direction,
                     }
                 }
-            } else {
-                // Here we respond to case 3, examples are `a = 1` or `a = 1 && b = 2`.
-                let comparison_key = comparison_key
-                    .expect("here's at least a eq filter because there's no range query");
-                RangeSpec {
-                    start: Some(KeyEndpoint::Including(comparison_key.clone())),
-                    end: Some(KeyEndpoint::Including(comparison_key)),
-                    direction: SortDirection::Ascending, // doesn't matter
-                }
-            })
-        }
-        IndexScanKind::FullText { filter } => match filter.op {
-            Operator::Contains => {
-                let token = match &filter.val {
-                    Field::String(token) => token,
-                    Field::Text(token) => token,
-                    _ => return Err(CacheError::Index(IndexError::ExpectedStringFullText)),
-                };
-                let key = index::get_full_text_secondary_index(token);
-                Ok(RangeSpec {
-                    start: Some(KeyEndpoint::Including(key.clone())),
-                    end: Some(KeyEndpoint::Including(key)),
-                    direction: SortDirection::Ascending, // doesn't matter
-                })
-            }
-            Operator::MatchesAll | Operator::MatchesAny => {
-                unimplemented!("matches all and matches any are not implemented")
             }
-            other => panic!("operator {other:?} is not supported by full text index"),
-        },
+        }
+    } else {
+        // Here we respond to case 3, examples are `a = 1` or `a = 1 && b = 2`.
+        let comparison_key =
+            comparison_key.expect("here's at least a eq filter because there's no range query");
+        RangeSpec {
+            start: Some(KeyEndpoint::Including(comparison_key.clone())),
+            end: Some(KeyEndpoint::Including(comparison_key)),
+            direction: SortDirection::Ascending, // doesn't matter
+        }
     }
 }
 
@@ -347,6 +866,21 @@ fn get_key_interval_from_range_query(
     }
 }
 
+/// Compares records by the sort options, `null` being greater than anything as in the indexes.
+fn compare_records(a: &Record, b: &Record, order_by: &[(usize, SortDirection)]) -> Ordering {
+    for (field_index, direction) in order_by {
+        let ordering = a.values[*field_index].cmp(&b.values[*field_index]);
+        let ordering = match direction {
+            SortDirection::Ascending => ordering,
+            SortDirection::Descending => ordering.reverse(),
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+    Ordering::Equal
+}
+
 fn map_index_database_entry_to_id((_, id): (&[u8], &[u8])) -> u64 {
     id_from_bytes(
         id.try_into()
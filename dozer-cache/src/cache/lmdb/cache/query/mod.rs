@@ -1,6 +1,8 @@
+mod aggregator;
 mod handler;
 mod intersection;
 mod iterator;
+mod ranker;
 
 pub use handler::LmdbQueryHandler;
 
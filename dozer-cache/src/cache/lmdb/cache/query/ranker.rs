@@ -0,0 +1,176 @@
+use dozer_types::types::{Field, TextAnalyzer};
+
+use crate::cache::expression::SortDirection;
+use crate::cache::index;
+use crate::cache::plan::IndexFilter;
+use crate::cache::RecordWithId;
+
+/// How quickly the score of a term saturates as it repeats.
+const K1: f64 = 1.2;
+/// How much long texts are penalized.
+const B: f64 = 0.75;
+
+/// A term of a full text filter that records are scored against.
+#[derive(Debug)]
+pub struct ScoredTerm<'a> {
+    pub filter: &'a IndexFilter,
+    pub term: String,
+    /// Whether the term matches all terms starting with it.
+    pub is_prefix: bool,
+    /// Number of records of the schema with the term.
+    pub document_frequency: usize,
+    /// Average number of terms of the field in the records of the schema.
+    pub average_length: f64,
+}
+
+/// Sorts records by the sum of the BM25 scores of the terms, records with the same score keeping
+/// their order.
+pub fn rank(
+    records: Vec<RecordWithId>,
+    terms: &[ScoredTerm],
+    num_records: usize,
+    direction: SortDirection,
+) -> Vec<RecordWithId> {
+    let mut fields: Vec<(usize, TextAnalyzer)> = vec![];
+    for term in terms {
+        let field = (term.filter.field_index, term.filter.analyzer);
+        if !fields.contains(&field) {
+            fields.push(field);
+        }
+    }
+    let field_position = |term: &ScoredTerm| {
+        fields
+            .iter()
+            .position(|field| *field == (term.filter.field_index, term.filter.analyzer))
+            .expect("all fields of the terms are collected")
+    };
+
+    // The terms of every ranked field of every record.
+    let documents = records
+        .iter()
+        .map(|record| {
+            fields
+                .iter()
+                .map(
+                    |(field_index, analyzer)| match record.record.values.get(*field_index) {
+                        Some(Field::String(text) | Field::Text(text)) => {
+                            index::analyze(analyzer, text)
+                        }
+                        _ => vec![],
+                    },
+                )
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    let mut scores = vec![0.0; records.len()];
+    for term in terms {
+        let position = field_position(term);
+        let frequencies = documents
+            .iter()
+            .map(|document| term_frequency(&document[position], term))
+            .collect::<Vec<_>>();
+        let idf = inverse_document_frequency(term.document_frequency, num_records);
+        for (i, frequency) in frequencies.into_iter().enumerate() {
+            if frequency == 0 {
+                continue;
+            }
+            let frequency = frequency as f64;
+            let length = relative_length(documents[i][position].len(), term.average_length);
+            scores[i] += idf * frequency * (K1 + 1.0) / (frequency + K1 * (1.0 - B + B * length));
+        }
+    }
+
+    let mut records = records.into_iter().zip(scores).collect::<Vec<_>>();
+    records.sort_by(|(_, a), (_, b)| match direction {
+        SortDirection::Ascending => a.total_cmp(b),
+        SortDirection::Descending => b.total_cmp(a),
+    });
+    records.into_iter().map(|(record, _)| record).collect()
+}
+
+fn term_frequency(document: &[String], term: &ScoredTerm) -> usize {
+    document
+        .iter()
+        .filter(|document_term| {
+            if term.is_prefix {
+                document_term.starts_with(&term.term)
+            } else {
+                **document_term == term.term
+            }
+        })
+        .count()
+}
+
+/// Length of a text compared to the average length, which is 0 if no record has any term.
+fn relative_length(length: usize, average_length: f64) -> f64 {
+    if average_length > 0.0 {
+        length as f64 / average_length
+    } else {
+        1.0
+    }
+}
+
+fn inverse_document_frequency(document_frequency: usize, num_records: usize) -> f64 {
+    let document_frequency = document_frequency as f64;
+    let num_records = (num_records as f64).max(document_frequency);
+    ((num_records - document_frequency + 0.5) / (document_frequency + 0.5) + 1.0).ln()
+}
+
+#[cfg(test)]
+mod tests {
+    use dozer_types::types::Record;
+
+    use crate::cache::expression::Operator;
+
+    use super::*;
+
+    #[test]
+    fn test_rank() {
+        let records = [
+            "a cat and a dog",
+            "dogs everywhere",
+            "dog dog dog",
+            "a very long text which mentions a dog once",
+        ]
+        .into_iter()
+        .enumerate()
+        .map(|(id, text)| {
+            RecordWithId::new(
+                id as u64,
+                Record::new(None, vec![Field::String(text.into())], None),
+            )
+        })
+        .collect::<Vec<_>>();
+        let filter = IndexFilter::new(0, Operator::MatchesAny, Field::String("dog".into()));
+        let terms = [ScoredTerm {
+            filter: &filter,
+            term: "dog".into(),
+            is_prefix: false,
+            document_frequency: 3,
+            average_length: 4.75,
+        }];
+        let ids = |records: Vec<RecordWithId>| {
+            records
+                .into_iter()
+                .map(|record| record.id)
+                .collect::<Vec<_>>()
+        };
+
+        // Repeated terms score higher, long texts lower, and texts without the term last.
+        assert_eq!(
+            ids(rank(records.clone(), &terms, 10, SortDirection::Descending)),
+            vec![2, 0, 3, 1]
+        );
+        assert_eq!(
+            ids(rank(records, &terms, 10, SortDirection::Ascending)),
+            vec![1, 3, 0, 2]
+        );
+    }
+
+    #[test]
+    fn test_inverse_document_frequency() {
+        assert!(inverse_document_frequency(1, 100) > inverse_document_frequency(10, 100));
+        assert!(inverse_document_frequency(100, 100) > 0.0);
+        assert!(inverse_document_frequency(10, 0) > 0.0);
+    }
+}
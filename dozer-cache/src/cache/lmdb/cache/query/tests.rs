@@ -1,15 +1,19 @@
 use crate::cache::{
-    expression::{FilterExpression, Operator, QueryExpression},
+    expression::{AggregateExpression, FilterExpression, Operator, QueryExpression},
     lmdb::{
-        cache::LmdbRwCache,
+        cache::{CacheCommonOptions, LmdbRwCache},
         tests::utils::{create_cache, insert_rec_1},
     },
-    test_utils::{query_from_filter, schema_1, schema_full_text, schema_multi_indices},
-    RecordWithId, RoCache, RwCache,
+    plan::ScanReason,
+    test_utils::{
+        query_from_filter, schema_1, schema_full_text, schema_multi_indices, schema_spatial,
+    },
+    AggregateGroup, RecordWithId, RoCache, RwCache,
 };
+use crate::errors::{CacheError, PlanError};
 use dozer_types::{
     serde_json::{from_value, json, Value},
-    types::{Field, Record, Schema},
+    types::{DozerPoint, Field, IndexDefinition, Record, Schema, TextAnalyzer},
 };
 
 #[test]
@@ -159,8 +163,31 @@ fn query_secondary_vars() {
         schema_name,
     );
 
-    // No compound index for a,c
-    test_query_err(json!({"$filter":{ "a": 1, "c": 521}}), &cache, schema_name);
+    // No compound index for a,c, so c is checked on the records found by a.
+    test_query(
+        json!({"$filter":{ "a": 1, "c": 521}}),
+        1,
+        &cache,
+        schema_name,
+    );
+
+    test_query(
+        json!({"$filter":{ "$and": [{ "c": {"$gt": 521}}, { "c": {"$lt": 527}}]}}),
+        3,
+        &cache,
+        schema_name,
+    );
+
+    test_query_record(
+        json!({
+            "$filter":{ "b": "james", "c": {"$gt": 523}},
+            "$skip": 1
+        }),
+        vec![(6, 7, "james".to_string(), 528)],
+        &schema,
+        &cache,
+        schema_name,
+    );
 
     test_query(
         json!({
@@ -267,6 +294,234 @@ fn query_secondary_vars() {
         &cache,
         schema_name,
     );
+
+    // Disjunctions
+    test_query(
+        json!({"$filter":{ "b": {"$in": ["mega", "ava"]}}}),
+        3,
+        &cache,
+        schema_name,
+    );
+
+    test_query(
+        json!({"$filter":{ "$or": [{ "a": {"$lt": 3}}, { "c": {"$gte": 527}}]}}),
+        4,
+        &cache,
+        schema_name,
+    );
+
+    // Overlapping disjuncts return the records once.
+    test_query(
+        json!({"$filter":{ "$or": [{ "c": 521}, { "b": "mega"}]}}),
+        3,
+        &cache,
+        schema_name,
+    );
+
+    // Filtered scans, `$ne` doesn't match nulls.
+    test_query(
+        json!({"$filter":{ "c": {"$ne": 521}}}),
+        5,
+        &cache,
+        schema_name,
+    );
+
+    test_query(
+        json!({"$filter":{ "c": {"$ne": null}}}),
+        0,
+        &cache,
+        schema_name,
+    );
+
+    test_query(
+        json!({"$filter":{ "$not": { "b": "james"}}}),
+        5,
+        &cache,
+        schema_name,
+    );
+
+    test_query(
+        json!({"$filter":{ "$or": [{ "a": 1, "c": 521}, { "b": "steff"}]}}),
+        2,
+        &cache,
+        schema_name,
+    );
+
+    test_query_record(
+        json!({
+            "$filter":{ "$or": [{ "b": "james"}, { "c": {"$lt": 522}}]},
+            "$order_by": { "c": "desc" },
+            "$skip": 1,
+            "$limit": 3
+        }),
+        vec![
+            (3, 4, "james".to_string(), 524),
+            (2, 3, "james".to_string(), 523),
+            (0, 1, "yuri".to_string(), 521),
+        ],
+        &schema,
+        &cache,
+        schema_name,
+    );
+}
+
+#[test]
+fn query_scan_limit() {
+    let schema_name = "sample";
+    let (schema, secondary_indexes) = schema_1();
+    let (other_schema, other_secondary_indexes) = schema_multi_indices();
+    let cache = LmdbRwCache::create(
+        [
+            (schema_name.to_string(), schema.clone(), secondary_indexes),
+            (
+                "other".to_string(),
+                other_schema.clone(),
+                other_secondary_indexes,
+            ),
+        ],
+        CacheCommonOptions {
+            max_scan_records: 2,
+            ..Default::default()
+        },
+        Default::default(),
+    )
+    .unwrap();
+
+    // Records of other schemas don't count towards the limit.
+    for id in 1..=3 {
+        let mut record = Record::new(
+            other_schema.identifier,
+            vec![Field::Int(id), Field::String("text".into())],
+            None,
+        );
+        cache.insert(&mut record).unwrap();
+    }
+    for val in [
+        (1, Some("yuri".to_string()), Some(521)),
+        (2, Some("mega".to_string()), Some(521)),
+    ] {
+        insert_rec_1(&cache, &schema, val);
+    }
+    test_query(
+        json!({"$filter":{ "c": {"$ne": 521}}}),
+        0,
+        &cache,
+        schema_name,
+    );
+    let aggregate = |expression: Value| {
+        cache.aggregate(
+            schema_name,
+            &from_value::<AggregateExpression>(expression).unwrap(),
+        )
+    };
+    assert_eq!(
+        aggregate(json!({"$aggregates": [{"$max": "a"}]})).unwrap()[0].values,
+        vec![Field::Int(2)]
+    );
+
+    insert_rec_1(&cache, &schema, (3, Some("james".to_string()), Some(523)));
+    test_query_err(json!({"$filter":{ "c": {"$ne": 521}}}), &cache, schema_name);
+    // Index scans are not limited.
+    test_query(
+        json!({"$filter":{ "a": {"$gt": 0}, "c": {"$ne": 521}}}),
+        1,
+        &cache,
+        schema_name,
+    );
+
+    // Aggregates read no more records than scans, but counting doesn't read them.
+    assert!(matches!(
+        aggregate(json!({"$aggregates": [{"$max": "a"}]})),
+        Err(CacheError::Plan(PlanError::ScanLimitExceeded { .. }))
+    ));
+    assert!(matches!(
+        aggregate(json!({"$filter": { "a": {"$gt": 0}}, "$aggregates": [{"$max": "a"}]})),
+        Err(CacheError::Plan(PlanError::ScanLimitExceeded { .. }))
+    ));
+    assert_eq!(
+        aggregate(json!({"$filter": { "a": {"$gt": 1}}, "$aggregates": [{"$max": "a"}]})).unwrap()
+            [0]
+        .values,
+        vec![Field::Int(3)]
+    );
+    assert_eq!(
+        aggregate(json!({"$aggregates": ["$count"]})).unwrap()[0].values,
+        vec![Field::UInt(3)]
+    );
+}
+
+#[test]
+fn query_aggregate() {
+    let schema_name = "sample";
+    let (cache, schema, _) = create_cache(schema_name, schema_1);
+    for val in [
+        (1, Some("yuri".to_string()), Some(521)),
+        (2, Some("mega".to_string()), Some(521)),
+        (3, Some("james".to_string()), Some(523)),
+        (4, Some("james".to_string()), Some(524)),
+        (5, Some("mega".to_string()), None),
+    ] {
+        insert_rec_1(&cache, &schema, val);
+    }
+    let aggregate = |expression: Value| {
+        cache
+            .aggregate(
+                schema_name,
+                &from_value::<AggregateExpression>(expression).unwrap(),
+            )
+            .unwrap()
+    };
+
+    assert_eq!(
+        aggregate(json!({"$filter": { "c": {"$gt": 521}}, "$aggregates": ["$count"]})),
+        vec![AggregateGroup {
+            key: vec![],
+            values: vec![Field::UInt(2)],
+        }]
+    );
+    assert_eq!(
+        aggregate(json!({
+            "$filter": { "a": {"$gt": 1}},
+            "$group_by": ["b"],
+            "$aggregates": ["$count", {"$sum": "c"}, {"$max": "a"}]
+        })),
+        vec![
+            AggregateGroup {
+                key: vec![Field::String("james".to_string())],
+                values: vec![Field::UInt(2), Field::Int(1047), Field::Int(4)],
+            },
+            AggregateGroup {
+                key: vec![Field::String("mega".to_string())],
+                values: vec![Field::UInt(2), Field::Int(521), Field::Int(5)],
+            },
+        ]
+    );
+    assert!(cache
+        .aggregate(
+            schema_name,
+            &from_value::<AggregateExpression>(json!({"$aggregates": [{"$avg": "b"}]})).unwrap(),
+        )
+        .is_err());
+}
+
+#[test]
+fn query_explain() {
+    let schema_name = "sample";
+    let (cache, _, _) = create_cache(schema_name, schema_1);
+    let explain = |query: Value| {
+        cache
+            .explain(schema_name, &from_value::<QueryExpression>(query).unwrap())
+            .unwrap()
+    };
+
+    assert_eq!(explain(json!({})), "sequential scan of all records");
+    assert_eq!(
+        explain(json!({"$filter":{ "a": 1}})),
+        "scan of secondary indexes [0]"
+    );
+    assert!(explain(json!({"$filter":{ "a": 1, "c": 521}})).contains("as no index covers it"));
+    assert!(explain(json!({"$filter":{ "$not": { "a": 1}}}))
+        .ends_with(&format!("because {}", ScanReason::NotIndexable)));
 }
 
 #[test]
@@ -322,6 +577,258 @@ fn query_secondary_multi_indices() {
     );
 }
 
+#[test]
+fn query_spatial() {
+    let schema_name = "sample";
+    let (cache, schema, _) = create_cache(schema_name, schema_spatial);
+
+    for (id, location) in [
+        (1, Some((-0.1276, 51.5072))),   // London
+        (2, Some((2.3522, 48.8566))),    // Paris
+        (3, Some((-74.006, 40.7128))),   // New York
+        (4, Some((178.4419, -18.1416))), // Suva
+        (5, Some((-0.1, 51.5))),         // Also London
+        (6, None),
+    ] {
+        let mut record = Record {
+            schema_id: schema.identifier,
+            values: vec![
+                Field::Int(id),
+                location.map_or(Field::Null, |location| {
+                    Field::Point(DozerPoint::from(location))
+                }),
+            ],
+            version: None,
+        };
+        cache.insert(&mut record).unwrap();
+    }
+    let ids = |query: Value| {
+        let query = from_value::<QueryExpression>(query).unwrap();
+        let records = cache.query(schema_name, &query).unwrap().1;
+        assert_eq!(cache.count(schema_name, &query).unwrap(), records.len());
+        records
+            .into_iter()
+            .map(|record| record.record.values[0].clone())
+            .collect::<Vec<_>>()
+    };
+    let london = json!({"x": -0.1276, "y": 51.5072});
+
+    assert_eq!(
+        ids(
+            json!({"$filter": {"location": {"$within_radius": {"center": london, "meters": 400_000}}}})
+        ),
+        vec![Field::Int(1), Field::Int(2), Field::Int(5)]
+    );
+    assert_eq!(
+        ids(json!({"$filter": {
+            "location": {"$within_radius": {"center": london, "meters": 400_000}},
+            "id": {"$gt": 1}
+        }})),
+        vec![Field::Int(2), Field::Int(5)]
+    );
+    assert_eq!(
+        ids(
+            json!({"$filter": {"location": {"$within_bbox": {"min": {"x": 170, "y": -20}, "max": {"x": -170, "y": -10}}}}})
+        ),
+        vec![Field::Int(4)]
+    );
+    assert_eq!(
+        ids(json!({"$filter": {"location": {"$nearest": london}}, "$limit": 3})),
+        vec![Field::Int(1), Field::Int(5), Field::Int(2)]
+    );
+    assert_eq!(
+        ids(json!({"$filter": {"location": {"$nearest": london}}, "$skip": 3})),
+        vec![Field::Int(3), Field::Int(4)]
+    );
+}
+
+#[test]
+fn query_spatial_scan_limit() {
+    let schema_name = "sample";
+    let (schema, secondary_indexes) = schema_spatial();
+    let cache = LmdbRwCache::create(
+        [(schema_name.to_string(), schema.clone(), secondary_indexes)],
+        CacheCommonOptions {
+            max_scan_records: 2,
+            ..Default::default()
+        },
+        Default::default(),
+    )
+    .unwrap();
+    for (id, location) in [
+        (1, (-0.1276, 51.5072)), // London
+        (2, (2.3522, 48.8566)),  // Paris
+        (3, (-74.006, 40.7128)), // New York
+        (5, (-0.1, 51.5)),       // Also London
+    ] {
+        let mut record = Record::new(
+            schema.identifier,
+            vec![Field::Int(id), Field::Point(DozerPoint::from(location))],
+            None,
+        );
+        cache.insert(&mut record).unwrap();
+    }
+    let query = |query: Value| {
+        let query = from_value::<QueryExpression>(query).unwrap();
+        cache.query(schema_name, &query).map(|(_, records)| {
+            records
+                .into_iter()
+                .map(|record| record.record.values[0].clone())
+                .collect::<Vec<_>>()
+        })
+    };
+    let london = json!({"x": -0.1276, "y": 51.5072});
+
+    // The records in the region of the filter are read.
+    assert!(matches!(
+        query(
+            json!({"$filter": {"location": {"$within_radius": {"center": london, "meters": 400_000}}}})
+        ),
+        Err(CacheError::Plan(PlanError::ScanLimitExceeded { .. }))
+    ));
+    // `$nearest` reads the records of the smallest region filling the page.
+    assert_eq!(
+        query(json!({"$filter": {"location": {"$nearest": london}}, "$limit": 2})).unwrap(),
+        vec![Field::Int(1), Field::Int(5)]
+    );
+    assert!(matches!(
+        query(json!({"$filter": {"location": {"$nearest": london}}, "$limit": 3})),
+        Err(CacheError::Plan(PlanError::ScanLimitExceeded { .. }))
+    ));
+}
+
+#[test]
+fn query_full_text_ranked() {
+    let schema_name = "sample";
+    let (cache, schema, _) = create_cache(schema_name, || {
+        let (schema, _) = schema_full_text();
+        let analyzer = TextAnalyzer {
+            lowercase: true,
+            stop_words: true,
+            stemming: true,
+        };
+        (schema, vec![IndexDefinition::FullText(0, analyzer)])
+    });
+
+    let mut ids = vec![];
+    for text in [
+        "The quick brown fox jumps",
+        "Running foxes and running dogs",
+        "A fox, a fox, everywhere a fox",
+        "Nothing to see",
+    ] {
+        let mut record = Record::new(
+            schema.identifier,
+            vec![Field::String(text.into()), Field::Text(text.into())],
+            None,
+        );
+        ids.push(cache.insert(&mut record).unwrap());
+    }
+    let query_ids = |query: Value| {
+        let query = from_value::<QueryExpression>(query).unwrap();
+        let records = cache.query(schema_name, &query).unwrap().1;
+        assert_eq!(cache.count(schema_name, &query).unwrap(), records.len());
+        records
+            .into_iter()
+            .map(|record| record.id)
+            .collect::<Vec<_>>()
+    };
+
+    // Queries are analyzed like the indexed text.
+    assert_eq!(
+        query_ids(json!({"$filter": {"foo": {"$matches_phrase": "brown FOXES"}}})),
+        vec![ids[0]]
+    );
+    assert_eq!(
+        query_ids(json!({"$filter": {"foo": {"$matches_phrase": "fox brown"}}})),
+        vec![]
+    );
+    assert_eq!(
+        query_ids(json!({"$filter": {"foo": {"$matches_prefix": "Run"}}})),
+        vec![ids[1]]
+    );
+
+    // Records with more occurrences of the terms rank higher.
+    assert_eq!(
+        query_ids(json!({
+            "$filter": {"foo": {"$matches_any": "fox"}},
+            "$order_by": {"$score": "desc"}
+        })),
+        vec![ids[2], ids[0], ids[1]]
+    );
+    assert_eq!(
+        query_ids(json!({
+            "$filter": {"foo": {"$matches_any": "fox"}},
+            "$order_by": {"$score": "asc"},
+            "$limit": 2
+        })),
+        vec![ids[0], ids[1]]
+    );
+    // Terms are weighted with the statistics of the full text index of their field.
+    test_query_err(
+        json!({
+            "$filter": {"bar": {"$contains": "fox"}},
+            "$order_by": {"$score": "desc"}
+        }),
+        &cache,
+        schema_name,
+    );
+
+    test_query_err(
+        json!({"$order_by": {"$score": "desc"}}),
+        &cache,
+        schema_name,
+    );
+}
+
+#[test]
+fn query_full_text_ranked_scan_limit() {
+    let schema_name = "sample";
+    let (schema, _) = schema_full_text();
+    let cache = LmdbRwCache::create(
+        [(
+            schema_name.to_string(),
+            schema.clone(),
+            vec![IndexDefinition::FullText(0, TextAnalyzer::default())],
+        )],
+        CacheCommonOptions {
+            max_scan_records: 2,
+            ..Default::default()
+        },
+        Default::default(),
+    )
+    .unwrap();
+    for text in ["fox", "quick fox", "quick brown fox"] {
+        let mut record = Record::new(
+            schema.identifier,
+            vec![Field::String(text.into()), Field::Text(text.into())],
+            None,
+        );
+        cache.insert(&mut record).unwrap();
+    }
+    let query = |filter: Value| {
+        let query = from_value::<QueryExpression>(json!({
+            "$filter": filter,
+            "$order_by": {"$score": "desc"},
+            "$limit": 1
+        }))
+        .unwrap();
+        cache.query(schema_name, &query).map(|(_, records)| records)
+    };
+
+    // All the matching records are ranked, whatever the limit.
+    assert!(matches!(
+        query(json!({"foo": {"$matches_any": "fox"}})),
+        Err(CacheError::Plan(PlanError::ScanLimitExceeded { .. }))
+    ));
+    assert_eq!(
+        query(json!({"foo": {"$matches_any": "quick"}}))
+            .unwrap()
+            .len(),
+        1
+    );
+}
+
 fn test_query_err(query: Value, cache: &dyn RwCache, schema_name: &str) {
     let query = from_value::<QueryExpression>(query).unwrap();
     let count_result = cache.count(schema_name, &query);
@@ -1,14 +1,30 @@
 use dozer_storage::{
-    lmdb::{Database, DatabaseFlags, RwTransaction, Transaction, WriteFlags},
+    lmdb::{Cursor, Database, DatabaseFlags, RwTransaction, Transaction, WriteFlags},
     lmdb_storage::LmdbEnvironmentManager,
 };
-use dozer_types::{bincode, types::Record};
+use dozer_types::{
+    bincode,
+    types::{Record, SchemaIdentifier},
+};
 
 use super::helper;
 use crate::errors::{CacheError, QueryError};
 
+/// Records by id, and for each schema, the ids of its records with their encoded sizes, and the
+/// number and total size of its records.
 #[derive(Debug, Clone, Copy)]
-pub struct RecordDatabase(Database);
+pub struct RecordDatabase {
+    records: Database,
+    schema_ids: Database,
+    totals: Database,
+}
+
+/// Number and total encoded size of the records of a schema.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RecordTotals {
+    pub count: u64,
+    pub bytes: u64,
+}
 
 impl RecordDatabase {
     pub fn new(
@@ -20,8 +36,15 @@ impl RecordDatabase {
         } else {
             None
         };
-        let db = env.create_database(Some("records"), flags)?;
-        Ok(Self(db))
+        let records = env.create_database(Some("records"), flags)?;
+        let flags = create_if_not_exist.then_some(DatabaseFlags::empty());
+        let schema_ids = env.create_database(Some("schema_record_ids"), flags)?;
+        let totals = env.create_database(Some("record_totals"), flags)?;
+        Ok(Self {
+            records,
+            schema_ids,
+            totals,
+        })
     }
 
     pub fn insert(
@@ -33,24 +56,186 @@ impl RecordDatabase {
         let encoded: Vec<u8> =
             bincode::serialize(&record).map_err(CacheError::map_serialization_error)?;
 
-        txn.put(self.0, &id, &encoded.as_slice(), WriteFlags::NO_OVERWRITE)
-            .map_err(|e| CacheError::Query(QueryError::InsertValue(e)))
+        txn.put(
+            self.records,
+            &id,
+            &encoded.as_slice(),
+            WriteFlags::NO_OVERWRITE,
+        )
+        .map_err(|e| CacheError::Query(QueryError::InsertValue(e)))?;
+
+        let Some(schema_id) = record.schema_id else {
+            return Ok(());
+        };
+        let size = encoded.len() as u64;
+        txn.put(
+            self.schema_ids,
+            &schema_id_key(schema_id, id),
+            &size.to_be_bytes(),
+            WriteFlags::empty(),
+        )
+        .map_err(|e| CacheError::Query(QueryError::InsertValue(e)))?;
+        let mut totals = self.totals_of(txn, schema_id)?;
+        totals.count += 1;
+        totals.bytes += size;
+        self.put_totals(txn, schema_id, totals)
     }
 
     pub fn get<T: Transaction>(&self, txn: &T, id: [u8; 8]) -> Result<Record, CacheError> {
-        helper::get(txn, self.0, &id)
+        helper::get(txn, self.records, &id)
+    }
+
+    /// Returns `None` if there's no record with this id, because it was deleted or evicted.
+    pub fn get_opt<T: Transaction>(
+        &self,
+        txn: &T,
+        id: [u8; 8],
+    ) -> Result<Option<Record>, CacheError> {
+        match txn.get(self.records, &id) {
+            Ok(encoded) => bincode::deserialize(encoded)
+                .map(Some)
+                .map_err(CacheError::map_deserialization_error),
+            Err(dozer_storage::lmdb::Error::NotFound) => Ok(None),
+            Err(e) => Err(CacheError::Query(QueryError::GetValue(e))),
+        }
+    }
+
+    pub fn contains<T: Transaction>(&self, txn: &T, id: [u8; 8]) -> bool {
+        txn.get(self.records, &id).is_ok()
     }
 
     pub fn delete(&self, txn: &mut RwTransaction, id: [u8; 8]) -> Result<(), CacheError> {
-        txn.del(self.0, &id, None)
-            .map_err(|e| CacheError::Query(QueryError::DeleteValue(e)))
+        let encoded = txn
+            .get(self.records, &id)
+            .map_err(|e| CacheError::Query(QueryError::GetValue(e)))?;
+        let size = encoded.len() as u64;
+        let record: Record =
+            bincode::deserialize(encoded).map_err(CacheError::map_deserialization_error)?;
+        txn.del(self.records, &id, None)
+            .map_err(|e| CacheError::Query(QueryError::DeleteValue(e)))?;
+
+        let Some(schema_id) = record.schema_id else {
+            return Ok(());
+        };
+        txn.del(self.schema_ids, &schema_id_key(schema_id, id), None)
+            .map_err(|e| CacheError::Query(QueryError::DeleteValue(e)))?;
+        let mut totals = self.totals_of(txn, schema_id)?;
+        totals.count -= 1;
+        totals.bytes -= size;
+        self.put_totals(txn, schema_id, totals)
+    }
+
+    /// Calls `f` with the ids of the records of a schema and their encoded sizes, by id, until
+    /// it returns `false`.
+    pub fn for_each_id_of<T: Transaction>(
+        &self,
+        txn: &T,
+        schema_id: SchemaIdentifier,
+        mut f: impl FnMut([u8; 8], u64) -> bool,
+    ) -> Result<(), CacheError> {
+        let prefix = helper::schema_key(schema_id);
+        let mut cursor = txn
+            .open_ro_cursor(self.schema_ids)
+            .map_err(|e| CacheError::Internal(Box::new(e)))?;
+        for entry in cursor.iter_from(prefix) {
+            let (key, size) = entry.map_err(|e| CacheError::Internal(Box::new(e)))?;
+            let Some(id) = key.strip_prefix(prefix.as_slice()) else {
+                break;
+            };
+            let id = id
+                .try_into()
+                .expect("All keys must be a schema and an id in this database");
+            let size = u64::from_be_bytes(
+                size.try_into()
+                    .expect("All values must be u64 sizes in this database"),
+            );
+            if !f(id, size) {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the records of a schema with their ids, by id.
+    pub fn records_of<T: Transaction>(
+        &self,
+        txn: &T,
+        schema_id: SchemaIdentifier,
+    ) -> Result<Vec<([u8; 8], Record)>, CacheError> {
+        let mut ids = vec![];
+        self.for_each_id_of(txn, schema_id, |id, _| {
+            ids.push(id);
+            true
+        })?;
+        ids.into_iter()
+            .map(|id| Ok((id, self.get(txn, id)?)))
+            .collect()
     }
 
     pub fn count(&self, txn: &impl Transaction) -> Result<usize, CacheError> {
-        helper::lmdb_stat(txn, self.0)
+        helper::lmdb_stat(txn, self.records)
             .map(|stat| stat.ms_entries)
             .map_err(|e| CacheError::Internal(Box::new(e)))
     }
+
+    /// Returns the number of records of a schema.
+    pub fn count_of<T: Transaction>(
+        &self,
+        txn: &T,
+        schema_id: SchemaIdentifier,
+    ) -> Result<usize, CacheError> {
+        Ok(self.totals_of(txn, schema_id)?.count as usize)
+    }
+
+    pub fn totals_of<T: Transaction>(
+        &self,
+        txn: &T,
+        schema_id: SchemaIdentifier,
+    ) -> Result<RecordTotals, CacheError> {
+        match txn.get(self.totals, &helper::schema_key(schema_id)) {
+            Ok(totals) => {
+                let (count, bytes) = totals.split_at(8);
+                let u64_from_bytes = |bytes: &[u8]| {
+                    u64::from_be_bytes(
+                        bytes
+                            .try_into()
+                            .expect("All values must be a count and a size in this database"),
+                    )
+                };
+                Ok(RecordTotals {
+                    count: u64_from_bytes(count),
+                    bytes: u64_from_bytes(bytes),
+                })
+            }
+            Err(dozer_storage::lmdb::Error::NotFound) => Ok(RecordTotals::default()),
+            Err(e) => Err(CacheError::Query(QueryError::GetValue(e))),
+        }
+    }
+
+    fn put_totals(
+        &self,
+        txn: &mut RwTransaction,
+        schema_id: SchemaIdentifier,
+        totals: RecordTotals,
+    ) -> Result<(), CacheError> {
+        let mut value = [0; 16];
+        value[..8].copy_from_slice(&totals.count.to_be_bytes());
+        value[8..].copy_from_slice(&totals.bytes.to_be_bytes());
+        txn.put(
+            self.totals,
+            &helper::schema_key(schema_id),
+            &value,
+            WriteFlags::empty(),
+        )
+        .map_err(|e| CacheError::Query(QueryError::InsertValue(e)))
+    }
+}
+
+fn schema_id_key(schema_id: SchemaIdentifier, id: [u8; 8]) -> [u8; 14] {
+    let mut key = [0; 14];
+    key[..6].copy_from_slice(&helper::schema_key(schema_id));
+    key[6..].copy_from_slice(&id);
+    key
 }
 
 #[cfg(test)]
@@ -72,14 +257,36 @@ mod tests {
 
         let id = 1u64;
         let id = id.to_be_bytes();
-        let record = Record::new(None, vec![], None);
+        let schema_id = SchemaIdentifier { id: 2, version: 1 };
+        let record = Record::new(Some(schema_id), vec![], None);
 
         writer.insert(txn.txn_mut(), id, &record).unwrap();
         txn.commit_and_renew().unwrap();
 
         assert_eq!(writer.count(txn.txn()).unwrap(), 1);
         assert_eq!(reader.count(txn.txn()).unwrap(), 1);
+        assert_eq!(reader.count_of(txn.txn(), schema_id).unwrap(), 1);
+        let size = bincode::serialize(&record).unwrap().len() as u64;
+        assert_eq!(
+            reader.totals_of(txn.txn(), schema_id).unwrap(),
+            RecordTotals {
+                count: 1,
+                bytes: size
+            }
+        );
+        assert_eq!(
+            reader.records_of(txn.txn(), schema_id).unwrap(),
+            vec![(id, record.clone())]
+        );
         assert_eq!(writer.get(txn.txn(), id).unwrap(), record);
+        assert_eq!(reader.get_opt(txn.txn(), id).unwrap(), Some(record.clone()));
+        assert!(reader.contains(txn.txn(), id));
+        assert_eq!(
+            reader
+                .records_of(txn.txn(), SchemaIdentifier { id: 1, version: 1 })
+                .unwrap(),
+            vec![]
+        );
         assert_eq!(reader.get(txn.txn(), id).unwrap(), record);
         txn.commit_and_renew().unwrap();
 
@@ -88,8 +295,15 @@ mod tests {
 
         assert_eq!(writer.count(txn.txn()).unwrap(), 0);
         assert_eq!(reader.count(txn.txn()).unwrap(), 0);
+        assert_eq!(
+            reader.totals_of(txn.txn(), schema_id).unwrap(),
+            RecordTotals::default()
+        );
+        assert_eq!(reader.records_of(txn.txn(), schema_id).unwrap(), vec![]);
         assert!(writer.get(txn.txn(), id).is_err());
         assert!(reader.get(txn.txn(), id).is_err());
+        assert_eq!(reader.get_opt(txn.txn(), id).unwrap(), None);
+        assert!(!reader.contains(txn.txn(), id));
         txn.commit_and_renew().unwrap();
     }
 }
@@ -0,0 +1,199 @@
+use dozer_storage::{
+    lmdb::{Cursor, Database, DatabaseFlags, RwTransaction, Transaction, WriteFlags},
+    lmdb_storage::LmdbEnvironmentManager,
+};
+use dozer_types::{
+    chrono::{DateTime, TimeZone, Utc},
+    types::{Field, SchemaIdentifier},
+};
+
+use super::helper;
+use crate::errors::{CacheError, QueryError};
+
+/// Ids of the records of the schemas with a time to live, keyed by schema and the value of their
+/// timestamp or date field, so the expired records of a schema are the first ones of the schema.
+///
+/// Records with a null value in that field never expire and are not indexed.
+#[derive(Debug, Clone, Copy)]
+pub struct TtlDatabase(Database);
+
+impl TtlDatabase {
+    pub fn new(env: &mut LmdbEnvironmentManager) -> Result<Self, CacheError> {
+        Ok(Self(env.create_database(
+            Some("ttl"),
+            Some(DatabaseFlags::empty()),
+        )?))
+    }
+
+    pub fn insert(
+        &self,
+        txn: &mut RwTransaction,
+        schema_id: SchemaIdentifier,
+        field: &Field,
+        id: [u8; 8],
+    ) -> Result<(), CacheError> {
+        let Some(time) = field_time(field) else {
+            return Ok(());
+        };
+        txn.put(self.0, &key(schema_id, time, id), &b"", WriteFlags::empty())
+            .map_err(|e| CacheError::Query(QueryError::InsertValue(e)))
+    }
+
+    pub fn delete(
+        &self,
+        txn: &mut RwTransaction,
+        schema_id: SchemaIdentifier,
+        field: &Field,
+        id: [u8; 8],
+    ) -> Result<(), CacheError> {
+        let Some(time) = field_time(field) else {
+            return Ok(());
+        };
+        match txn.del(self.0, &key(schema_id, time, id), None) {
+            Ok(()) | Err(dozer_storage::lmdb::Error::NotFound) => Ok(()),
+            Err(e) => Err(CacheError::Query(QueryError::DeleteValue(e))),
+        }
+    }
+
+    /// Returns the ids of the records of the schema whose time is not after `until`, earliest first.
+    pub fn until<T: Transaction>(
+        &self,
+        txn: &T,
+        schema_id: SchemaIdentifier,
+        until: DateTime<Utc>,
+    ) -> Result<Vec<[u8; 8]>, CacheError> {
+        let prefix = helper::schema_key(schema_id);
+        let until = sortable_time(until);
+        let mut cursor = txn
+            .open_ro_cursor(self.0)
+            .map_err(|e| CacheError::Internal(Box::new(e)))?;
+        let mut ids = vec![];
+        for entry in cursor.iter_from(prefix) {
+            let (key, _) = entry.map_err(|e| CacheError::Internal(Box::new(e)))?;
+            let Some(time_and_id) = key.strip_prefix(prefix.as_slice()) else {
+                break;
+            };
+            let (time, id) = time_and_id.split_at(8);
+            if time > until.as_slice() {
+                break;
+            }
+            ids.push(
+                id.try_into()
+                    .expect("All keys must be a schema, a time and an id in this database"),
+            );
+        }
+        Ok(ids)
+    }
+
+    /// Removes the entries of the schema.
+    pub fn clear(
+        &self,
+        txn: &mut RwTransaction,
+        schema_id: SchemaIdentifier,
+    ) -> Result<(), CacheError> {
+        for key in self.keys_of(txn, schema_id)? {
+            txn.del(self.0, &key, None)
+                .map_err(|e| CacheError::Query(QueryError::DeleteValue(e)))?;
+        }
+        Ok(())
+    }
+
+    fn keys_of<T: Transaction>(
+        &self,
+        txn: &T,
+        schema_id: SchemaIdentifier,
+    ) -> Result<Vec<Vec<u8>>, CacheError> {
+        let prefix = helper::schema_key(schema_id);
+        let mut cursor = txn
+            .open_ro_cursor(self.0)
+            .map_err(|e| CacheError::Internal(Box::new(e)))?;
+        let mut keys = vec![];
+        for entry in cursor.iter_from(prefix) {
+            let (key, _) = entry.map_err(|e| CacheError::Internal(Box::new(e)))?;
+            if !key.starts_with(&prefix) {
+                break;
+            }
+            keys.push(key.to_vec());
+        }
+        Ok(keys)
+    }
+}
+
+fn field_time(field: &Field) -> Option<DateTime<Utc>> {
+    match field {
+        Field::Timestamp(time) => Some(time.with_timezone(&Utc)),
+        Field::Date(date) => Some(
+            Utc.from_utc_datetime(
+                &date
+                    .and_hms_opt(0, 0, 0)
+                    .expect("Midnight is always a valid time"),
+            ),
+        ),
+        _ => None,
+    }
+}
+
+/// Big endian milliseconds with the sign bit flipped, which sort like the times.
+fn sortable_time(time: DateTime<Utc>) -> [u8; 8] {
+    ((time.timestamp_millis() as u64) ^ (1 << 63)).to_be_bytes()
+}
+
+fn key(schema_id: SchemaIdentifier, time: DateTime<Utc>, id: [u8; 8]) -> [u8; 22] {
+    let mut key = [0; 22];
+    key[..6].copy_from_slice(&helper::schema_key(schema_id));
+    key[6..14].copy_from_slice(&sortable_time(time));
+    key[14..].copy_from_slice(&id);
+    key
+}
+
+#[cfg(test)]
+mod tests {
+    use dozer_types::chrono::NaiveDate;
+
+    use crate::cache::lmdb::utils::{init_env, CacheOptions};
+
+    use super::*;
+
+    #[test]
+    fn test_ttl_database() {
+        let mut env = init_env(&CacheOptions::default()).unwrap().0;
+        let db = TtlDatabase::new(&mut env).unwrap();
+        let txn = env.create_txn().unwrap();
+        let mut txn = txn.write();
+
+        let schema_id = SchemaIdentifier { id: 1, version: 1 };
+        let other_id = SchemaIdentifier { id: 2, version: 1 };
+        let day = |n| Utc.timestamp_opt(n * 24 * 60 * 60, 0).unwrap();
+        let timestamp = |n| Field::Timestamp(day(n).into());
+        let ids = [1u64, 2, 3, 4].map(u64::to_be_bytes);
+
+        db.insert(txn.txn_mut(), schema_id, &timestamp(2), ids[0])
+            .unwrap();
+        db.insert(txn.txn_mut(), schema_id, &timestamp(-1), ids[1])
+            .unwrap();
+        db.insert(txn.txn_mut(), schema_id, &Field::Null, ids[2])
+            .unwrap();
+        let date = NaiveDate::from_ymd_opt(1970, 1, 2).unwrap();
+        db.insert(txn.txn_mut(), schema_id, &Field::Date(date), ids[3])
+            .unwrap();
+        db.insert(txn.txn_mut(), other_id, &timestamp(0), ids[2])
+            .unwrap();
+
+        assert_eq!(db.until(txn.txn(), schema_id, day(0)).unwrap(), [ids[1]]);
+        assert_eq!(
+            db.until(txn.txn(), schema_id, day(5)).unwrap(),
+            [ids[1], ids[3], ids[0]]
+        );
+
+        db.delete(txn.txn_mut(), schema_id, &timestamp(2), ids[0])
+            .unwrap();
+        assert_eq!(
+            db.until(txn.txn(), schema_id, day(5)).unwrap(),
+            [ids[1], ids[3]]
+        );
+
+        db.clear(txn.txn_mut(), schema_id).unwrap();
+        assert_eq!(db.until(txn.txn(), schema_id, day(5)).unwrap(), vec![]);
+        assert_eq!(db.until(txn.txn(), other_id, day(5)).unwrap(), [ids[2]]);
+    }
+}
@@ -25,6 +25,9 @@ pub struct CacheManagerOptions {
     /// The chunk size when calculating intersection of index queries.
     pub intersection_chunk_size: usize,
 
+    /// Maximum number of records a query may scan when no secondary index answers it.
+    pub max_scan_records: usize,
+
     // Total size allocated for data in a memory mapped file.
     // This size is allocated at initialization.
     pub max_size: usize,
@@ -41,6 +44,7 @@ impl Default for CacheManagerOptions {
             max_readers: cache_common_options.max_readers,
             max_db_size: cache_common_options.max_db_size,
             intersection_chunk_size: cache_common_options.intersection_chunk_size,
+            max_scan_records: cache_common_options.max_scan_records,
             max_size: cache_write_options.max_size,
             path: None,
         }
@@ -151,6 +155,7 @@ impl LmdbCacheManager {
             max_db_size: self.options.max_db_size,
             max_readers: self.options.max_readers,
             intersection_chunk_size: self.options.intersection_chunk_size,
+            max_scan_records: self.options.max_scan_records,
             path: Some((self.base_path.clone(), name)),
         }
     }
@@ -1,15 +1,15 @@
 use crate::errors::{CacheError, IndexError};
 use dozer_storage::lmdb::RwTransaction;
-use dozer_types::types::{Field, IndexDefinition, Record, Schema};
+use dozer_types::types::{Field, IndexDefinition, Record, Schema, TextAnalyzer};
 use itertools::Itertools;
-use unicode_segmentation::UnicodeSegmentation;
 
-use crate::cache::index::{self, get_full_text_secondary_index};
+use crate::cache::index::{self, get_full_text_secondary_index, get_spatial_secondary_index};
 
-use super::cache::SecondaryIndexDatabases;
+use super::cache::{FullTextLengthDatabase, SecondaryIndexDatabases};
 
 pub struct Indexer<'a> {
     pub secondary_indexes: &'a SecondaryIndexDatabases,
+    pub full_text_lengths: FullTextLengthDatabase,
 }
 impl<'a> Indexer<'a> {
     pub fn build_indexes(
@@ -36,9 +36,17 @@ impl<'a> Indexer<'a> {
                     let secondary_key = Self::_build_index_sorted_inverted(fields, &record.values);
                     db.insert(txn, &secondary_key, id)?;
                 }
-                IndexDefinition::FullText(field_index) => {
-                    for secondary_key in
-                        Self::_build_indices_full_text(*field_index, &record.values)?
+                IndexDefinition::FullText(field_index, analyzer) => {
+                    let (secondary_keys, length) =
+                        Self::_build_indices_full_text(*field_index, analyzer, &record.values)?;
+                    for secondary_key in secondary_keys {
+                        db.insert(txn, &secondary_key, id)?;
+                    }
+                    self.full_text_lengths.add(txn, schema_id, idx, length)?;
+                }
+                IndexDefinition::Spatial(field_index) => {
+                    if let Some(secondary_key) =
+                        Self::_build_index_spatial(*field_index, &record.values)?
                     {
                         db.insert(txn, &secondary_key, id)?;
                     }
@@ -68,9 +76,18 @@ impl<'a> Indexer<'a> {
                     let secondary_key = Self::_build_index_sorted_inverted(fields, &record.values);
                     db.delete(txn, &secondary_key, id)?;
                 }
-                IndexDefinition::FullText(field_index) => {
-                    for secondary_key in
-                        Self::_build_indices_full_text(*field_index, &record.values)?
+                IndexDefinition::FullText(field_index, analyzer) => {
+                    let (secondary_keys, length) =
+                        Self::_build_indices_full_text(*field_index, analyzer, &record.values)?;
+                    for secondary_key in secondary_keys {
+                        db.delete(txn, &secondary_key, id)?;
+                    }
+                    self.full_text_lengths
+                        .subtract(txn, schema_id, idx, length)?;
+                }
+                IndexDefinition::Spatial(field_index) => {
+                    if let Some(secondary_key) =
+                        Self::_build_index_spatial(*field_index, &record.values)?
                     {
                         db.delete(txn, &secondary_key, id)?;
                     }
@@ -91,10 +108,12 @@ impl<'a> Indexer<'a> {
         index::get_secondary_index(&values, values.len() == 1)
     }
 
+    /// Returns the keys of the distinct terms of the text, and its number of terms.
     fn _build_indices_full_text(
         field_index: usize,
+        analyzer: &TextAnalyzer,
         values: &[Field],
-    ) -> Result<Vec<Vec<u8>>, CacheError> {
+    ) -> Result<(Vec<Vec<u8>>, u64), CacheError> {
         let Some(field) = values.get(field_index) else {
             return Err(CacheError::Index(IndexError::FieldIndexOutOfRange));
         };
@@ -110,11 +129,28 @@ impl<'a> Indexer<'a> {
             }
         };
 
-        Ok(string
-            .unicode_words()
-            .map(get_full_text_secondary_index)
+        let terms = index::analyze(analyzer, string);
+        let secondary_keys = terms
+            .iter()
+            .map(|term| get_full_text_secondary_index(term))
             .unique()
-            .collect())
+            .collect();
+        Ok((secondary_keys, terms.len() as u64))
+    }
+
+    /// `null` points are not indexed.
+    fn _build_index_spatial(
+        field_index: usize,
+        values: &[Field],
+    ) -> Result<Option<Vec<u8>>, CacheError> {
+        match values.get(field_index) {
+            Some(Field::Point(point)) => Ok(Some(get_spatial_secondary_index(point))),
+            Some(Field::Null) => Ok(None),
+            Some(_) => Err(CacheError::Index(IndexError::FieldNotCompatibleIndex(
+                field_index,
+            ))),
+            None => Err(CacheError::Index(IndexError::FieldIndexOutOfRange)),
+        }
     }
 }
 
@@ -183,16 +219,39 @@ mod tests {
         assert_eq!(
             Indexer::_build_indices_full_text(
                 field_index,
+                &TextAnalyzer::default(),
                 &[Field::String("today is a good day".into())]
             )
             .unwrap(),
-            vec![
-                get_full_text_secondary_index("today"),
-                get_full_text_secondary_index("is"),
-                get_full_text_secondary_index("a"),
-                get_full_text_secondary_index("good"),
-                get_full_text_secondary_index("day"),
-            ]
+            (
+                vec![
+                    get_full_text_secondary_index("today"),
+                    get_full_text_secondary_index("is"),
+                    get_full_text_secondary_index("a"),
+                    get_full_text_secondary_index("good"),
+                    get_full_text_secondary_index("day"),
+                ],
+                5
+            )
+        );
+        assert_eq!(
+            Indexer::_build_indices_full_text(
+                field_index,
+                &TextAnalyzer {
+                    lowercase: true,
+                    stop_words: true,
+                    stemming: true,
+                },
+                &[Field::String("Days and days of running".into())]
+            )
+            .unwrap(),
+            (
+                vec![
+                    get_full_text_secondary_index("day"),
+                    get_full_text_secondary_index("run"),
+                ],
+                3
+            )
         );
     }
 
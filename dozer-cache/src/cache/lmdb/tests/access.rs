@@ -0,0 +1,223 @@
+use dozer_types::{
+    serde_json::Value,
+    types::{Field, Record},
+};
+
+use crate::{
+    cache::{
+        expression::{
+            Aggregate, AggregateExpression, FilterExpression, Operator, QueryExpression,
+            SortDirection, SortOption,
+        },
+        test_utils::{query_from_filter, schema_1},
+        RwCache,
+    },
+    errors::CacheError,
+    AccessFilter, CacheReader,
+};
+
+use super::utils::{create_cache, insert_rec_1};
+
+fn tenant(name: &str) -> AccessFilter {
+    AccessFilter {
+        filter: Some(FilterExpression::Simple(
+            "b".to_string(),
+            Operator::EQ,
+            Value::from(name),
+        )),
+        fields: vec![],
+    }
+}
+
+fn no_access_filter() -> AccessFilter {
+    AccessFilter {
+        filter: None,
+        fields: vec![],
+    }
+}
+
+#[test]
+fn get_checks_access_filter() {
+    let (cache, schema, _) = create_cache("sample", schema_1);
+    insert_rec_1(&cache, &schema, (1, Some("tenant_a".to_string()), Some(1)));
+    insert_rec_1(&cache, &schema, (2, Some("tenant_b".to_string()), Some(2)));
+    let reader = CacheReader::new(Box::new(cache));
+
+    let (_, record) = reader
+        .get(&Field::Int(1).encode(), &tenant("tenant_a"))
+        .unwrap();
+    assert_eq!(record.record.values[0], Field::Int(1));
+
+    assert!(matches!(
+        reader.get(&Field::Int(2).encode(), &tenant("tenant_a")),
+        Err(CacheError::RecordNotPermitted)
+    ));
+    assert!(reader
+        .get(&Field::Int(2).encode(), &no_access_filter())
+        .is_ok());
+}
+
+#[test]
+fn history_checks_access_filter() {
+    let (cache, schema, _) = create_cache("sample", schema_1);
+    cache.set_history("sample", true).unwrap();
+    insert_rec_1(&cache, &schema, (1, Some("tenant_a".to_string()), Some(1)));
+    insert_rec_1(&cache, &schema, (2, Some("tenant_b".to_string()), Some(2)));
+    let mut record = Record::new(
+        schema.identifier,
+        vec![
+            Field::Int(1),
+            Field::String("tenant_b".to_string()),
+            Field::Int(3),
+        ],
+        None,
+    );
+    cache.update(&Field::Int(1).encode(), &mut record).unwrap();
+    let reader = CacheReader::new(Box::new(cache));
+
+    let (_, versions) = reader
+        .history("sample", &Field::Int(1).encode(), &tenant("tenant_a"))
+        .unwrap();
+    assert_eq!(versions.len(), 1);
+    assert_eq!(versions[0].record.record.values[2], Field::Int(1));
+    assert_eq!(
+        reader
+            .history("sample", &Field::Int(1).encode(), &no_access_filter())
+            .unwrap()
+            .1
+            .len(),
+        2
+    );
+
+    assert!(matches!(
+        reader.history("sample", &Field::Int(2).encode(), &tenant("tenant_a")),
+        Err(CacheError::RecordNotPermitted)
+    ));
+}
+
+#[test]
+fn query_and_count_check_access_filter() {
+    let (cache, schema, _) = create_cache("sample", schema_1);
+    insert_rec_1(&cache, &schema, (1, Some("tenant_a".to_string()), Some(1)));
+    insert_rec_1(&cache, &schema, (2, Some("tenant_b".to_string()), Some(2)));
+    insert_rec_1(&cache, &schema, (3, Some("tenant_a".to_string()), Some(3)));
+    let reader = CacheReader::new(Box::new(cache));
+
+    let check = |mut query: QueryExpression, expected: Vec<i64>| {
+        let count = reader
+            .count("sample", &mut query.clone(), tenant("tenant_a"))
+            .unwrap();
+        let (_, records) = reader
+            .query("sample", &mut query, tenant("tenant_a"))
+            .unwrap();
+        let values = records
+            .into_iter()
+            .map(|record| record.record.values[0].clone())
+            .collect::<Vec<_>>();
+        assert_eq!(count, expected.len());
+        assert_eq!(
+            values,
+            expected.into_iter().map(Field::Int).collect::<Vec<_>>()
+        );
+    };
+
+    check(QueryExpression::with_no_limit(), vec![1, 3]);
+    check(
+        query_from_filter(FilterExpression::Simple(
+            "a".to_string(),
+            Operator::EQ,
+            Value::from(2),
+        )),
+        vec![],
+    );
+    // A query filter can't widen the access filter.
+    check(
+        query_from_filter(FilterExpression::Or(vec![
+            FilterExpression::Simple("a".to_string(), Operator::EQ, Value::from(2)),
+            FilterExpression::Simple("b".to_string(), Operator::EQ, Value::from("tenant_b")),
+        ])),
+        vec![],
+    );
+}
+
+fn not_permitted<T>(result: Result<T, CacheError>) -> bool {
+    matches!(result, Err(CacheError::FieldNotPermitted(name)) if name == "b")
+}
+
+#[test]
+fn queries_check_readable_fields() {
+    let (cache, schema, _) = create_cache("sample", schema_1);
+    insert_rec_1(&cache, &schema, (1, Some("tenant_a".to_string()), Some(1)));
+    insert_rec_1(&cache, &schema, (2, Some("tenant_b".to_string()), Some(2)));
+    let reader = CacheReader::new(Box::new(cache));
+    // The access filter can use fields that can't be read.
+    let access_filter = || AccessFilter {
+        fields: vec!["a".to_string(), "c".to_string()],
+        ..tenant("tenant_a")
+    };
+    let filter =
+        |name: &str| FilterExpression::Simple(name.to_string(), Operator::GT, Value::from(0));
+    let not_permitted = |result: Result<_, CacheError>| matches!(result, Err(CacheError::FieldNotPermitted(name)) if name == "b");
+
+    let (schema, records) = reader
+        .query(
+            "sample",
+            &mut query_from_filter(filter("a")),
+            access_filter(),
+        )
+        .unwrap();
+    assert_eq!(schema.fields.len(), 2);
+    assert_eq!(records.len(), 1);
+    assert!(not_permitted(reader.query(
+        "sample",
+        &mut query_from_filter(filter("b")),
+        access_filter()
+    )));
+    assert!(not_permitted(reader.count(
+        "sample",
+        &mut query_from_filter(filter("b")),
+        access_filter()
+    )));
+    let mut query = QueryExpression::with_no_limit();
+    query.order_by.0 = vec![SortOption::new("b".to_string(), SortDirection::Ascending)];
+    assert!(not_permitted(reader.query(
+        "sample",
+        &mut query.clone(),
+        access_filter()
+    )));
+    assert!(not_permitted(reader.explain(
+        "sample",
+        &mut query,
+        access_filter()
+    )));
+
+    let aggregate = |filter, group_by: &[&str], aggregates| AggregateExpression {
+        filter,
+        group_by: group_by.iter().map(|name| name.to_string()).collect(),
+        aggregates,
+    };
+    let groups = reader
+        .aggregate(
+            "sample",
+            &mut aggregate(
+                None,
+                &["a"],
+                vec![Aggregate::Count, Aggregate::Max("c".into())],
+            ),
+            access_filter(),
+        )
+        .unwrap();
+    assert_eq!(groups.len(), 1);
+    assert_eq!(groups[0].values, vec![Field::UInt(1), Field::Int(1)]);
+    for mut expression in [
+        aggregate(Some(filter("b")), &[], vec![Aggregate::Count]),
+        aggregate(None, &["b"], vec![Aggregate::Count]),
+        aggregate(None, &[], vec![Aggregate::Min("b".into())]),
+    ] {
+        assert!(not_permitted(reader.aggregate(
+            "sample",
+            &mut expression,
+            access_filter()
+        )));
+    }
+}
@@ -0,0 +1,198 @@
+use dozer_types::serde_json::Value;
+use dozer_types::types::{Field, Record, Schema};
+
+use crate::cache::expression::{
+    FilterExpression, Operator, QueryExpression, Skip, SortDirection, SortOption,
+};
+use crate::cache::lmdb::cache::{CacheCommonOptions, LmdbRwCache};
+use crate::cache::test_utils::{schema_1, schema_multi_indices};
+use crate::cache::{RoCache, RwCache};
+use crate::errors::{CacheError, PlanError};
+
+use super::utils::{create_cache, insert_rec_1};
+
+fn as_of(commit: u64) -> QueryExpression {
+    let mut query = QueryExpression::new(None, vec![], None, Skip::Skip(0));
+    query.as_of = Some(commit);
+    query
+}
+
+/// Values of fields `a` and `c` of the records found by `query`.
+fn values(cache: &LmdbRwCache, query: &QueryExpression) -> Vec<(Field, Field)> {
+    let (_, records) = cache.query("sample", query).unwrap();
+    records
+        .into_iter()
+        .map(|record| {
+            let values = record.record.values;
+            (values[0].clone(), values[2].clone())
+        })
+        .collect()
+}
+
+fn pairs(pairs: &[(i64, i64)]) -> Vec<(Field, Field)> {
+    pairs
+        .iter()
+        .map(|(a, c)| (Field::Int(*a), Field::Int(*c)))
+        .collect()
+}
+
+fn update(cache: &LmdbRwCache, schema: &Schema, a: i64, c: i64) {
+    let mut record = Record::new(
+        schema.identifier,
+        vec![Field::Int(a), Field::Null, Field::Int(c)],
+        None,
+    );
+    cache.update(&Field::Int(a).encode(), &mut record).unwrap();
+}
+
+/// Commit 1 inserts records 1 and 2, commit 2 updates 1, deletes 2 and inserts 3.
+fn create_history() -> (LmdbRwCache, Schema) {
+    let (cache, schema, _) = create_cache("sample", schema_1);
+    cache.set_history("sample", true).unwrap();
+
+    insert_rec_1(&cache, &schema, (1, None, Some(10)));
+    insert_rec_1(&cache, &schema, (2, None, Some(20)));
+    cache.commit(&Default::default()).unwrap();
+
+    update(&cache, &schema, 1, 30);
+    cache.delete(&Field::Int(2).encode()).unwrap();
+    insert_rec_1(&cache, &schema, (3, None, Some(5)));
+    cache.commit(&Default::default()).unwrap();
+    (cache, schema)
+}
+
+#[test]
+fn query_as_of_commits() {
+    let (cache, _) = create_history();
+
+    assert_eq!(values(&cache, &as_of(0)), vec![]);
+    assert_eq!(values(&cache, &as_of(1)), pairs(&[(1, 10), (2, 20)]));
+    assert_eq!(values(&cache, &as_of(2)), pairs(&[(1, 30), (3, 5)]));
+    assert_eq!(
+        values(&cache, &as_of(3)),
+        values(&cache, &QueryExpression::with_no_limit())
+    );
+
+    let mut query = as_of(1);
+    query.filter = Some(FilterExpression::Simple(
+        "c".to_string(),
+        Operator::GT,
+        Value::from(15),
+    ));
+    assert_eq!(values(&cache, &query), pairs(&[(2, 20)]));
+    assert_eq!(cache.count("sample", &query).unwrap(), 1);
+
+    let mut query = as_of(2);
+    query.order_by.0 = vec![SortOption::new("c".to_string(), SortDirection::Ascending)];
+    assert_eq!(values(&cache, &query), pairs(&[(3, 5), (1, 30)]));
+    query.skip = Skip::Skip(1);
+    assert_eq!(values(&cache, &query), pairs(&[(1, 30)]));
+}
+
+#[test]
+fn record_history() {
+    let (cache, _) = create_history();
+
+    let versions = cache.history("sample", &Field::Int(1).encode()).unwrap();
+    assert_eq!(
+        versions
+            .iter()
+            .map(|version| (
+                version.record.record.version,
+                version.record.record.values[2].clone(),
+                version.valid_from,
+                version.valid_to
+            ))
+            .collect::<Vec<_>>(),
+        vec![
+            (Some(1), Field::Int(10), 1, Some(2)),
+            (Some(2), Field::Int(30), 2, None)
+        ]
+    );
+
+    let versions = cache.history("sample", &Field::Int(2).encode()).unwrap();
+    assert_eq!(
+        versions
+            .iter()
+            .map(|version| (version.valid_from, version.valid_to))
+            .collect::<Vec<_>>(),
+        vec![(1, Some(2))]
+    );
+}
+
+#[test]
+fn keep_history_of_existing_records() {
+    let (cache, schema, _) = create_cache("sample", schema_1);
+    insert_rec_1(&cache, &schema, (1, None, Some(10)));
+    cache.commit(&Default::default()).unwrap();
+    assert!(matches!(
+        cache.query("sample", &as_of(1)),
+        Err(CacheError::HistoryNotKept)
+    ));
+
+    // Records already in the cache are history as of the latest commit.
+    cache.set_history("sample", true).unwrap();
+    assert_eq!(values(&cache, &as_of(1)), pairs(&[(1, 10)]));
+    assert!(matches!(
+        cache.query("sample", &as_of(0)),
+        Err(CacheError::HistoryNotAvailable { since: 1, as_of: 0 })
+    ));
+    assert_eq!(
+        cache
+            .history("sample", &Field::Int(1).encode())
+            .unwrap()
+            .len(),
+        1
+    );
+
+    cache.set_history("sample", false).unwrap();
+    assert!(matches!(
+        cache.history("sample", &Field::Int(1).encode()),
+        Err(CacheError::HistoryNotKept)
+    ));
+    assert!(matches!(
+        cache.set_history("missing", true),
+        Err(CacheError::SchemaNotFound(_))
+    ));
+}
+
+#[test]
+fn scan_limit_counts_versions_of_queried_schema() {
+    let (schema, secondary_indexes) = schema_1();
+    let (other_schema, other_secondary_indexes) = schema_multi_indices();
+    let cache = LmdbRwCache::create(
+        [
+            ("sample".to_string(), schema.clone(), secondary_indexes),
+            (
+                "other".to_string(),
+                other_schema.clone(),
+                other_secondary_indexes,
+            ),
+        ],
+        CacheCommonOptions {
+            max_scan_records: 2,
+            ..Default::default()
+        },
+        Default::default(),
+    )
+    .unwrap();
+    cache.set_history("sample", true).unwrap();
+    cache.set_history("other", true).unwrap();
+
+    insert_rec_1(&cache, &schema, (1, None, Some(10)));
+    for id in 1..=3 {
+        let mut record = Record::new(
+            other_schema.identifier,
+            vec![Field::Int(id), Field::String("text".into())],
+            None,
+        );
+        cache.insert(&mut record).unwrap();
+    }
+    cache.commit(&Default::default()).unwrap();
+
+    assert_eq!(values(&cache, &as_of(1)), pairs(&[(1, 10)]));
+    assert!(matches!(
+        cache.query("other", &as_of(1)),
+        Err(CacheError::Plan(PlanError::ScanLimitExceeded { .. }))
+    ));
+}
@@ -1,3 +1,6 @@
+mod access;
 mod basic;
+mod history;
 mod read_write;
+mod retention;
 pub mod utils;
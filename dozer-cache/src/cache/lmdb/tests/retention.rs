@@ -0,0 +1,227 @@
+use std::time::Duration;
+
+use dozer_types::bincode;
+use dozer_types::chrono::{DateTime, TimeZone, Utc};
+use dozer_types::types::{
+    Field, FieldDefinition, FieldType, IndexDefinition, Record, Schema, SchemaIdentifier,
+    SourceDefinition,
+};
+
+use crate::cache::expression::{QueryExpression, Skip};
+use crate::cache::lmdb::cache::{CacheCommonOptions, LmdbRoCache, LmdbRwCache};
+use crate::cache::{RetentionPolicy, RoCache, RwCache};
+use crate::errors::CacheError;
+
+use super::utils::{create_cache, get_indexes};
+use tempdir::TempDir;
+
+const DAY: i64 = 24 * 60 * 60;
+
+fn schema_events() -> (Schema, Vec<IndexDefinition>) {
+    (
+        Schema {
+            identifier: Some(SchemaIdentifier { id: 1, version: 1 }),
+            fields: vec![
+                FieldDefinition {
+                    name: "id".to_string(),
+                    typ: FieldType::Int,
+                    nullable: false,
+                    source: SourceDefinition::Dynamic,
+                },
+                FieldDefinition {
+                    name: "created_at".to_string(),
+                    typ: FieldType::Timestamp,
+                    nullable: true,
+                    source: SourceDefinition::Dynamic,
+                },
+            ],
+            primary_index: vec![0],
+        },
+        vec![
+            IndexDefinition::SortedInverted(vec![0]),
+            IndexDefinition::SortedInverted(vec![1]),
+        ],
+    )
+}
+
+fn day(n: i64) -> DateTime<Utc> {
+    Utc.timestamp_opt(n * DAY, 0).unwrap()
+}
+
+fn event(schema: &Schema, id: i64, created_at: Option<i64>) -> Record {
+    Record::new(
+        schema.identifier,
+        vec![
+            Field::Int(id),
+            created_at.map_or(Field::Null, |n| Field::Timestamp(day(n).into())),
+        ],
+        None,
+    )
+}
+
+/// Inserts events `0..num_events`, event `n` being created on day `n`.
+fn insert_events(cache: &LmdbRwCache, schema: &Schema, num_events: i64) {
+    for n in 0..num_events {
+        cache.insert(&mut event(schema, n, Some(n))).unwrap();
+    }
+}
+
+fn ids(cache: &impl RoCache) -> Vec<Field> {
+    let query = QueryExpression::new(None, vec![], None, Skip::Skip(0));
+    let (_, records) = cache.query("events", &query).unwrap();
+    records
+        .into_iter()
+        .map(|record| record.record.values[0].clone())
+        .collect()
+}
+
+fn num_index_entries(cache: &LmdbRwCache) -> Vec<usize> {
+    get_indexes(cache).iter().map(Vec::len).collect()
+}
+
+#[test]
+fn evict_expired_records() {
+    let (cache, schema, _) = create_cache("events", schema_events);
+    insert_events(&cache, &schema, 5);
+    cache.insert(&mut event(&schema, 5, None)).unwrap();
+
+    let policy = RetentionPolicy {
+        ttl: Some((
+            "created_at".to_string(),
+            Duration::from_secs(2 * DAY as u64),
+        )),
+        ..Default::default()
+    };
+    assert_eq!(cache.evict("events", &policy, day(4)).unwrap(), 3);
+    assert_eq!(
+        ids(&cache),
+        vec![Field::Int(3), Field::Int(4), Field::Int(5)]
+    );
+    let query = QueryExpression::new(None, vec![], None, Skip::Skip(0));
+    assert_eq!(cache.count("events", &query).unwrap(), 3);
+    assert_eq!(num_index_entries(&cache), vec![3, 3]);
+
+    assert_eq!(cache.evict("events", &policy, day(4)).unwrap(), 0);
+}
+
+#[test]
+fn evict_earliest_records_over_limits() {
+    let (cache, schema, _) = create_cache("events", schema_events);
+    insert_events(&cache, &schema, 5);
+
+    let policy = RetentionPolicy {
+        max_records: Some(3),
+        ..Default::default()
+    };
+    assert_eq!(cache.evict("events", &policy, day(0)).unwrap(), 2);
+    assert_eq!(
+        ids(&cache),
+        vec![Field::Int(2), Field::Int(3), Field::Int(4)]
+    );
+
+    let mut record = event(&schema, 0, Some(0));
+    record.version = Some(1);
+    let record_size = bincode::serialize(&record).unwrap().len() as u64;
+    let policy = RetentionPolicy {
+        max_bytes: Some(2 * record_size),
+        ..Default::default()
+    };
+    assert_eq!(cache.evict("events", &policy, day(0)).unwrap(), 1);
+    assert_eq!(ids(&cache), vec![Field::Int(3), Field::Int(4)]);
+    assert_eq!(num_index_entries(&cache), vec![2, 2]);
+}
+
+#[test]
+fn commit_evictions_without_uncommitted_writes() {
+    let path = TempDir::new("dozer").unwrap();
+    let path = (path.path().to_path_buf(), "cache".to_string());
+    let (schema, secondary_indexes) = schema_events();
+    let cache = LmdbRwCache::create(
+        [("events".to_string(), schema.clone(), secondary_indexes)],
+        CacheCommonOptions {
+            path: Some(path.clone()),
+            ..Default::default()
+        },
+        Default::default(),
+    )
+    .unwrap();
+    insert_events(&cache, &schema, 3);
+    cache.commit(&Default::default()).unwrap();
+    let reader = LmdbRoCache::new(CacheCommonOptions {
+        path: Some(path),
+        ..Default::default()
+    })
+    .unwrap();
+
+    // Evictions are left for the commit of the uncommitted writes.
+    cache.insert(&mut event(&schema, 3, Some(3))).unwrap();
+    let policy = |max_records| RetentionPolicy {
+        max_records: Some(max_records),
+        ..Default::default()
+    };
+    assert_eq!(cache.evict("events", &policy(2), day(0)).unwrap(), 2);
+    assert_eq!(
+        ids(&reader),
+        vec![Field::Int(0), Field::Int(1), Field::Int(2)]
+    );
+    cache.commit(&Default::default()).unwrap();
+    assert_eq!(ids(&reader), vec![Field::Int(2), Field::Int(3)]);
+
+    // Otherwise they're committed right away.
+    assert_eq!(cache.evict("events", &policy(1), day(0)).unwrap(), 1);
+    assert_eq!(ids(&reader), vec![Field::Int(3)]);
+}
+
+#[test]
+fn update_and_delete_evicted_records() {
+    let (cache, schema, _) = create_cache("events", schema_events);
+    insert_events(&cache, &schema, 3);
+    let mut record = event(&schema, 0, Some(10));
+    let key = Field::Int(0).encode();
+    assert_eq!(cache.update(&key, &mut record).unwrap(), 1);
+
+    let policy = RetentionPolicy {
+        max_records: Some(1),
+        ..Default::default()
+    };
+    assert_eq!(cache.evict("events", &policy, day(0)).unwrap(), 2);
+    assert!(cache.get(&key).is_err());
+
+    // Updating an evicted record inserts it again, continuing from its version.
+    assert_eq!(cache.update(&key, &mut record).unwrap(), 2);
+    let record = cache.get(&key).unwrap().record;
+    assert_eq!(record.version, Some(3));
+    assert_eq!(record.values[1], Field::Timestamp(day(10).into()));
+
+    let key = Field::Int(1).encode();
+    assert_eq!(cache.delete(&key).unwrap(), 1);
+    assert!(cache.delete(&key).is_err());
+}
+
+#[test]
+fn retention_policy_checks_ttl_field() {
+    let (mut cache, _, _) = create_cache("events", schema_events);
+    let policy = |field: &str| RetentionPolicy {
+        ttl: Some((field.to_string(), Duration::from_secs(DAY as u64))),
+        ..Default::default()
+    };
+
+    cache
+        .set_retention_policy("events", policy("created_at"), None)
+        .unwrap();
+    assert!(matches!(
+        cache.set_retention_policy("events", policy("id"), None),
+        Err(CacheError::InvalidRetentionField(_, FieldType::Int))
+    ));
+    assert!(matches!(
+        cache.set_retention_policy("events", policy("missing"), None),
+        Err(CacheError::RetentionFieldNotFound(_))
+    ));
+    assert!(matches!(
+        cache.set_retention_policy("missing", policy("created_at"), None),
+        Err(CacheError::SchemaNotFound(_))
+    ));
+    cache
+        .set_retention_policy("events", RetentionPolicy::default(), None)
+        .unwrap();
+}
@@ -1,17 +1,19 @@
 mod lmdb;
 use std::fmt::Debug;
+use std::time::Duration;
 
-use self::expression::QueryExpression;
+use self::expression::{AggregateExpression, QueryExpression};
 use crate::errors::CacheError;
 use dozer_types::{
     node::SourceStates,
     serde::{Deserialize, Serialize},
-    types::{IndexDefinition, Record, Schema, SchemaIdentifier},
+    types::{Field, IndexDefinition, Record, Schema, SchemaIdentifier},
 };
 pub use lmdb::cache_manager::{CacheManagerOptions, LmdbCacheManager};
 pub mod expression;
 pub mod index;
 mod plan;
+pub use plan::{RecordFilter, SpatialFilter, SpatialFilterKind};
 pub mod test_utils;
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -27,6 +29,42 @@ impl RecordWithId {
     }
 }
 
+/// A version of a record, with the commits it was current between.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(crate = "dozer_types::serde")]
+pub struct RecordVersion {
+    pub record: RecordWithId,
+    /// Commit that wrote this version.
+    pub valid_from: u64,
+    /// Commit that updated or deleted the record, `None` if this is the current version.
+    pub valid_to: Option<u64>,
+}
+
+/// The aggregates of a group of records.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(crate = "dozer_types::serde")]
+pub struct AggregateGroup {
+    /// Values of the `group_by` fields shared by the records of the group.
+    pub key: Vec<Field>,
+    /// Values of the aggregates, in the order they're requested.
+    pub values: Vec<Field>,
+}
+
+/// Limits on the records of a schema kept in the cache. Records over them are removed in the
+/// background.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RetentionPolicy {
+    /// Records whose value of this timestamp or date field is older than the period are removed.
+    pub ttl: Option<(String, Duration)>,
+    /// The earliest inserted records are removed when there are more.
+    pub max_records: Option<u64>,
+    /// The earliest inserted records are removed when the encoded records take more bytes.
+    pub max_bytes: Option<u64>,
+}
+
+/// Called with each record removed by a retention policy.
+pub type OnEvict = Box<dyn Fn(Record) + Send>;
+
 pub trait CacheManager: Send + Sync + Debug {
     /// Opens a cache in read-write mode with given name or an alias with that name.
     ///
@@ -60,6 +98,10 @@ pub trait RoCache: Send + Sync + Debug {
 
     // Schema Operations
     fn get_schema(&self, schema_identifier: SchemaIdentifier) -> Result<&Schema, CacheError>;
+    fn get_schema_and_indexes(
+        &self,
+        schema_identifier: SchemaIdentifier,
+    ) -> Result<&(Schema, Vec<IndexDefinition>), CacheError>;
     fn get_schema_and_indexes_by_name(
         &self,
         name: &str,
@@ -73,6 +115,18 @@ pub trait RoCache: Send + Sync + Debug {
         schema_name: &str,
         query: &QueryExpression,
     ) -> Result<(&Schema, Vec<RecordWithId>), CacheError>;
+    /// Aggregates the records matching the filter, one group per distinct value of the `group_by`
+    /// fields, sorted by these values.
+    fn aggregate(
+        &self,
+        schema_name: &str,
+        expression: &AggregateExpression,
+    ) -> Result<Vec<AggregateGroup>, CacheError>;
+    /// Describes how `query` would be executed, without executing it.
+    fn explain(&self, schema_name: &str, query: &QueryExpression) -> Result<String, CacheError>;
+    /// Returns the versions of the record with primary key `key`, oldest first. Only schemas
+    /// keeping history have versions.
+    fn history(&self, schema_name: &str, key: &[u8]) -> Result<Vec<RecordVersion>, CacheError>;
 }
 
 pub trait RwCache: RoCache {
@@ -87,4 +141,20 @@ pub trait RwCache: RoCache {
     fn commit(&self, checkpoint: &SourceStates) -> Result<(), CacheError>;
     /// Get the current checkpoint.
     fn get_checkpoint(&self) -> Result<SourceStates, CacheError>;
+    /// Starts removing the records of schema `schema_name` over the limits of `policy` in the background,
+    /// replacing the previous policy of the schema. `on_evict` is called with the removed records.
+    ///
+    /// Removals are persisted by the next `commit`, or right away if there are no uncommitted writes.
+    /// Removed records can still be updated, which inserts them again, or deleted.
+    fn set_retention_policy(
+        &mut self,
+        schema_name: &str,
+        policy: RetentionPolicy,
+        on_evict: Option<OnEvict>,
+    ) -> Result<(), CacheError>;
+    /// Starts or stops keeping the previous versions of the records of schema `schema_name`.
+    ///
+    /// Queries can be `$as_of` the commits since history is kept, the records already in the
+    /// cache being history as of the latest commit. Stopping removes the kept versions.
+    fn set_history(&self, schema_name: &str, keep: bool) -> Result<(), CacheError>;
 }
@@ -0,0 +1,115 @@
+use std::fmt::{Display, Formatter, Result};
+
+use super::{
+    FilteredScan, IndexScan, Plan, RankedScan, ResidualIndexScans, ScanReason, SnapshotScan,
+    SpatialFilterKind, SpatialScan,
+};
+
+impl Display for Plan {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            Plan::IndexScans(index_scans) => write_index_scans(f, index_scans),
+            Plan::ResidualIndexScans(scans) => write!(f, "{scans}"),
+            Plan::Union(union) => {
+                write!(f, "union of {} disjuncts: ", union.len())?;
+                for (i, scans) in union.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, "; ")?;
+                    }
+                    write!(f, "{scans}")?;
+                }
+                Ok(())
+            }
+            Plan::SeqScan(_) => write!(f, "sequential scan of all records"),
+            Plan::FilteredScan(FilteredScan {
+                filter,
+                order_by,
+                reason,
+            }) => {
+                write!(f, "scan of all records filtered by {filter:?}")?;
+                if !order_by.is_empty() {
+                    write!(f, ", sorted in memory by {order_by:?}")?;
+                }
+                write!(f, ", because {reason}")
+            }
+            Plan::SpatialScan(SpatialScan {
+                index_id,
+                filter,
+                residual,
+                order_by,
+            }) => {
+                write!(f, "scan of spatial index {index_id} for {:?}", filter.kind)?;
+                if let Some(residual) = residual {
+                    write!(f, ", filtering the records found by {residual:?}")?;
+                }
+                if matches!(filter.kind, SpatialFilterKind::Nearest { .. }) {
+                    write!(f, ", sorted in memory by distance")
+                } else if !order_by.is_empty() {
+                    write!(f, ", sorted in memory by {order_by:?}")
+                } else {
+                    Ok(())
+                }
+            }
+            Plan::Ranked(RankedScan { plan, .. }) => {
+                write!(f, "{plan}, sorted in memory by relevance")
+            }
+            Plan::SnapshotScan(SnapshotScan {
+                commit,
+                filter,
+                order_by,
+            }) => {
+                write!(f, "scan of the record versions current at commit {commit}")?;
+                if let Some(filter) = filter {
+                    write!(f, " filtered by {filter:?}")?;
+                }
+                if !order_by.is_empty() {
+                    write!(f, ", sorted in memory by {order_by:?}")?;
+                }
+                Ok(())
+            }
+            Plan::ReturnEmpty => write!(f, "empty result, as no record can match the filter"),
+        }
+    }
+}
+
+impl Display for ResidualIndexScans {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        write_index_scans(f, &self.index_scans)?;
+        if let Some(residual) = &self.residual {
+            write!(
+                f,
+                ", filtering the records found by {residual:?} as no index covers it"
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for ScanReason {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        f.write_str(match self {
+            ScanReason::MatchingIndexNotFound => {
+                "no secondary index matches the filter or sort options"
+            }
+            ScanReason::NotIndexable => {
+                "the filter has a negation, a spatial filter without spatial index, or too many disjunctions for index scans"
+            }
+            ScanReason::DisjunctNotIndexed => {
+                "some disjuncts of the filter are not covered by any secondary index"
+            }
+            ScanReason::SortedDisjunction => "index scans of several disjuncts can't be sorted",
+            ScanReason::AsOfCommit => "secondary indexes only cover the latest commit",
+            ScanReason::Aggregate => "aggregates read all the records matching their filter",
+            ScanReason::Ranked => "sorting by $score reads all the records matching the filter",
+            ScanReason::SpatialRegion => "spatial filters read all the records in their region",
+        })
+    }
+}
+
+fn write_index_scans(f: &mut Formatter<'_>, index_scans: &[IndexScan]) -> Result {
+    let index_ids = index_scans
+        .iter()
+        .map(|index_scan| index_scan.index_id)
+        .collect::<Vec<_>>();
+    write!(f, "scan of secondary indexes {index_ids:?}")
+}
@@ -0,0 +1,281 @@
+use dozer_types::types::{Field, IndexDefinition, Record, Schema, TextAnalyzer};
+
+use crate::cache::expression::{FilterExpression, Operator};
+use crate::cache::index;
+use crate::errors::PlanError;
+
+use super::{planner::resolve_filter, IndexFilter, SpatialFilter};
+
+/// Maximum number of conjunctions a filter is expanded into before giving up on index unions.
+const MAX_DISJUNCTS: usize = 64;
+
+/// A `FilterExpression` with field names resolved to indexes and values converted to fields.
+///
+/// `$in` is expanded to an `Or` of `Eq` filters, and `$ne` to a `Not` of an `Eq` filter.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RecordFilter {
+    Simple(IndexFilter),
+    Spatial(SpatialFilter),
+    And(Vec<RecordFilter>),
+    Or(Vec<RecordFilter>),
+    Not(Box<RecordFilter>),
+}
+
+impl RecordFilter {
+    /// Resolves `expression` against `schema`. Full text filters analyze text like the full text
+    /// index of their field.
+    pub fn new(
+        schema: &Schema,
+        secondary_indexes: &[IndexDefinition],
+        expression: &FilterExpression,
+    ) -> Result<Self, PlanError> {
+        resolve_filter(schema, secondary_indexes, expression)
+    }
+
+    pub fn matches(&self, record: &Record) -> bool {
+        match self {
+            RecordFilter::Simple(filter) => record
+                .values
+                .get(filter.field_index)
+                .map_or(false, |field| field_matches(field, filter)),
+            RecordFilter::Spatial(filter) => record
+                .values
+                .get(filter.field_index)
+                .map_or(false, |field| filter.matches(field)),
+            RecordFilter::And(filters) => filters.iter().all(|filter| filter.matches(record)),
+            RecordFilter::Or(filters) => filters.iter().any(|filter| filter.matches(record)),
+            RecordFilter::Not(filter) => !filter.matches(record),
+        }
+    }
+
+    /// Expands the filter to a disjunction of conjunctions of simple filters.
+    ///
+    /// Returns `None` if the filter contains a negation, which no index can answer, a spatial
+    /// filter, which is planned separately, or if the expansion is too large.
+    pub fn disjunctive_normal_form(&self) -> Option<Vec<Vec<IndexFilter>>> {
+        match self {
+            RecordFilter::Simple(filter) => Some(vec![vec![filter.clone()]]),
+            RecordFilter::And(filters) => {
+                let mut result = vec![vec![]];
+                for filter in filters {
+                    let disjuncts = filter.disjunctive_normal_form()?;
+                    if result.len() * disjuncts.len() > MAX_DISJUNCTS {
+                        return None;
+                    }
+                    result = result
+                        .iter()
+                        .flat_map(|conjunction| {
+                            disjuncts.iter().map(move |disjunct| {
+                                let mut conjunction = conjunction.clone();
+                                conjunction.extend(disjunct.iter().cloned());
+                                conjunction
+                            })
+                        })
+                        .collect();
+                }
+                Some(result)
+            }
+            RecordFilter::Or(filters) => {
+                let mut result = vec![];
+                for filter in filters {
+                    result.extend(filter.disjunctive_normal_form()?);
+                    if result.len() > MAX_DISJUNCTS {
+                        return None;
+                    }
+                }
+                Some(result)
+            }
+            RecordFilter::Not(_) | RecordFilter::Spatial(_) => None,
+        }
+    }
+
+    /// Full text filters outside negations, which are the ones records are ranked by.
+    pub fn full_text_filters(&self) -> Vec<IndexFilter> {
+        match self {
+            RecordFilter::Simple(filter) if filter.op.supported_by_full_text() => {
+                vec![filter.clone()]
+            }
+            RecordFilter::And(filters) | RecordFilter::Or(filters) => filters
+                .iter()
+                .flat_map(RecordFilter::full_text_filters)
+                .collect(),
+            RecordFilter::Simple(_) | RecordFilter::Spatial(_) | RecordFilter::Not(_) => vec![],
+        }
+    }
+}
+
+/// `null` only equals `null`, and never satisfies a range or text operator, consistently with
+/// what the secondary indexes return.
+fn field_matches(field: &Field, filter: &IndexFilter) -> bool {
+    let (operator, value) = (filter.op, &filter.val);
+    if operator != Operator::EQ && (field == &Field::Null || value == &Field::Null) {
+        return false;
+    }
+    match operator {
+        Operator::LT => field < value,
+        Operator::LTE => field <= value,
+        Operator::EQ => field == value,
+        Operator::GT => field > value,
+        Operator::GTE => field >= value,
+        Operator::NE => field != value,
+        Operator::In | Operator::WithinRadius | Operator::WithinBbox | Operator::Nearest => false,
+        Operator::Contains
+        | Operator::MatchesAny
+        | Operator::MatchesAll
+        | Operator::MatchesPhrase
+        | Operator::MatchesPrefix => {
+            let (Some(text), Some(query)) = (as_text(field), as_text(value)) else {
+                return false;
+            };
+            text_matches(text, operator, query, &filter.analyzer)
+        }
+    }
+}
+
+/// Compares the terms of `text` and `query`, as the full text index scans do. A query without
+/// terms matches nothing.
+fn text_matches(text: &str, operator: Operator, query: &str, analyzer: &TextAnalyzer) -> bool {
+    let terms = index::analyze(analyzer, text);
+    if operator == Operator::MatchesPrefix {
+        let prefix = index::analyze_prefix(analyzer, query);
+        return !prefix.is_empty() && terms.iter().any(|term| term.starts_with(&prefix));
+    }
+    let query = index::analyze(analyzer, query);
+    if query.is_empty() {
+        return false;
+    }
+    match operator {
+        Operator::MatchesAny => query.iter().any(|term| terms.contains(term)),
+        Operator::MatchesPhrase => terms.windows(query.len()).any(|window| window == query),
+        _ => query.iter().all(|term| terms.contains(term)),
+    }
+}
+
+fn as_text(field: &Field) -> Option<&str> {
+    match field {
+        Field::String(text) | Field::Text(text) => Some(text),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eq(field_index: usize, val: i64) -> RecordFilter {
+        RecordFilter::Simple(IndexFilter::new(field_index, Operator::EQ, Field::Int(val)))
+    }
+
+    fn index_filter(filter: &RecordFilter) -> IndexFilter {
+        match filter {
+            RecordFilter::Simple(filter) => filter.clone(),
+            _ => panic!("must be simple"),
+        }
+    }
+
+    #[test]
+    fn test_matches() {
+        let record = Record::new(
+            None,
+            vec![
+                Field::Int(1),
+                Field::String("mary has a little lamb".into()),
+                Field::Null,
+            ],
+            None,
+        );
+        let simple = |field_index, op, val| {
+            RecordFilter::Simple(IndexFilter::new(field_index, op, val)).matches(&record)
+        };
+
+        assert!(simple(0, Operator::LT, Field::Int(2)));
+        assert!(!simple(0, Operator::GT, Field::Int(1)));
+        assert!(simple(0, Operator::NE, Field::Int(2)));
+        assert!(simple(1, Operator::Contains, Field::String("lamb".into())));
+        assert!(!simple(1, Operator::Contains, Field::String("lam".into())));
+        assert!(simple(
+            1,
+            Operator::MatchesAny,
+            Field::String("big lamb".into())
+        ));
+        assert!(!simple(
+            1,
+            Operator::MatchesAll,
+            Field::String("big lamb".into())
+        ));
+        assert!(simple(
+            1,
+            Operator::MatchesPhrase,
+            Field::String("little lamb".into())
+        ));
+        assert!(!simple(
+            1,
+            Operator::MatchesPhrase,
+            Field::String("lamb little".into())
+        ));
+        assert!(simple(
+            1,
+            Operator::MatchesPrefix,
+            Field::String("lit".into())
+        ));
+        assert!(!simple(
+            1,
+            Operator::MatchesPrefix,
+            Field::String("".into())
+        ));
+        assert!(!simple(1, Operator::Contains, Field::String("Lamb".into())));
+        let analyzed = IndexFilter::new(
+            1,
+            Operator::MatchesPhrase,
+            Field::String("the Little Lambs".into()),
+        )
+        .with_analyzer(TextAnalyzer {
+            lowercase: true,
+            stop_words: true,
+            stemming: true,
+        });
+        assert!(RecordFilter::Simple(analyzed).matches(&record));
+        assert!(simple(2, Operator::EQ, Field::Null));
+        assert!(!simple(2, Operator::LT, Field::Int(1)));
+        assert!(!simple(0, Operator::GT, Field::Null));
+
+        assert!(RecordFilter::Or(vec![eq(0, 2), eq(0, 1)]).matches(&record));
+        assert!(!RecordFilter::And(vec![eq(0, 2), eq(0, 1)]).matches(&record));
+        assert!(RecordFilter::Not(Box::new(eq(0, 2))).matches(&record));
+    }
+
+    #[test]
+    fn test_disjunctive_normal_form() {
+        let (a, b, c) = (eq(0, 1), eq(1, 2), eq(2, 3));
+        let filter = RecordFilter::And(vec![
+            RecordFilter::Or(vec![a.clone(), b.clone()]),
+            c.clone(),
+        ]);
+        assert_eq!(
+            filter.disjunctive_normal_form(),
+            Some(vec![
+                vec![index_filter(&a), index_filter(&c)],
+                vec![index_filter(&b), index_filter(&c)],
+            ])
+        );
+
+        assert_eq!(
+            RecordFilter::Or(vec![]).disjunctive_normal_form(),
+            Some(vec![])
+        );
+        assert_eq!(
+            RecordFilter::And(vec![]).disjunctive_normal_form(),
+            Some(vec![vec![]])
+        );
+        assert_eq!(
+            RecordFilter::And(vec![a, RecordFilter::Not(Box::new(b))]).disjunctive_normal_form(),
+            None
+        );
+
+        let wide = RecordFilter::Or((0..10).map(|i| eq(0, i)).collect());
+        assert_eq!(
+            RecordFilter::And(vec![wide.clone(), wide]).disjunctive_normal_form(),
+            None
+        );
+    }
+}
@@ -1,7 +1,12 @@
+mod explain;
+mod filter;
 mod helper;
 mod planner;
-use dozer_types::types::Field;
+mod spatial;
+use dozer_types::types::{Field, TextAnalyzer};
+pub use filter::RecordFilter;
 pub use planner::QueryPlanner;
+pub use spatial::{SpatialFilter, SpatialFilterKind};
 
 use super::expression::{Operator, SortDirection};
 
@@ -11,9 +16,29 @@ mod tests;
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum Plan {
     IndexScans(Vec<IndexScan>),
+    /// Index scans answering part of the filter, the records found being checked against the rest.
+    ResidualIndexScans(ResidualIndexScans),
+    /// Union of the ids returned by the index scans of every disjunct of the filter.
+    Union(Vec<ResidualIndexScans>),
     SeqScan(SeqScan),
+    /// Scan of all records, keeping the ones matching the filter.
+    FilteredScan(FilteredScan),
+    /// Scan of a spatial index for the region of a spatial filter.
+    SpatialScan(SpatialScan),
+    /// The records found by another plan, sorted by their relevance to the full text filters.
+    Ranked(RankedScan),
+    /// Scan of the versions of the records current at a past commit.
+    SnapshotScan(SnapshotScan),
     ReturnEmpty,
 }
+
+/// Index scans whose results are intersected, and the part of the filter they don't answer.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ResidualIndexScans {
+    pub index_scans: Vec<IndexScan>,
+    pub residual: Option<RecordFilter>,
+}
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct IndexScan {
     pub index_id: usize,
@@ -44,11 +69,72 @@ pub struct SeqScan {
     pub direction: SortDirection,
 }
 
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FilteredScan {
+    pub filter: RecordFilter,
+    /// Field indexes and directions to sort the matching records by.
+    pub order_by: Vec<(usize, SortDirection)>,
+    pub reason: ScanReason,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SpatialScan {
+    pub index_id: usize,
+    pub filter: SpatialFilter,
+    /// The part of the filter checked on the records found.
+    pub residual: Option<RecordFilter>,
+    /// Field indexes and directions to sort the matching records by. Empty for `$nearest`, whose
+    /// records are sorted by distance.
+    pub order_by: Vec<(usize, SortDirection)>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RankedScan {
+    /// Plan finding the records, which is executed without skip and limit.
+    pub plan: Box<Plan>,
+    /// The filters the records are scored against.
+    pub filters: Vec<IndexFilter>,
+    pub direction: SortDirection,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SnapshotScan {
+    /// The commit the versions were current at.
+    pub commit: u64,
+    pub filter: Option<RecordFilter>,
+    /// Field indexes and directions to sort the matching records by.
+    pub order_by: Vec<(usize, SortDirection)>,
+}
+
+/// Why a query is answered by scanning all records.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScanReason {
+    /// No secondary index answers any part of the filter and sort options.
+    MatchingIndexNotFound,
+    /// The filter has a negation, a spatial filter without spatial index, or too many
+    /// disjunctions to scan them separately.
+    NotIndexable,
+    /// Some disjuncts of the filter can't be answered by secondary indexes.
+    DisjunctNotIndexed,
+    /// The results of the index scans of several disjuncts can't be sorted.
+    SortedDisjunction,
+    /// The query is as of a past commit, whose record versions aren't indexed.
+    AsOfCommit,
+    /// Aggregates read all the records matching their filter.
+    Aggregate,
+    /// Sorting by `$score` reads all the records matching the filter.
+    Ranked,
+    /// Spatial filters read all the records in their region.
+    SpatialRegion,
+}
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct IndexFilter {
     pub field_index: usize,
     pub op: Operator,
     pub val: Field,
+    /// How full text operators analyze the text of the field and the value.
+    pub analyzer: TextAnalyzer,
 }
 
 impl IndexFilter {
@@ -57,6 +143,11 @@ impl IndexFilter {
             field_index,
             op,
             val,
+            analyzer: TextAnalyzer::default(),
         }
     }
+
+    pub fn with_analyzer(self, analyzer: TextAnalyzer) -> Self {
+        Self { analyzer, ..self }
+    }
 }
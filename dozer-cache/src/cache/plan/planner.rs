@@ -1,13 +1,23 @@
-use crate::cache::expression::{FilterExpression, Operator, QueryExpression, SortDirection};
+use crate::cache::expression::{
+    FilterExpression, Operator, QueryExpression, Skip, SortDirection, SCORE_SORT_FIELD,
+};
 use crate::errors::PlanError;
 use dozer_types::json_value_to_field;
-use dozer_types::types::{Field, FieldDefinition, Schema};
+use dozer_types::serde_json::Value;
+use dozer_types::types::{Field, FieldDefinition, Schema, TextAnalyzer};
 use dozer_types::types::{FieldType, IndexDefinition};
+use itertools::Itertools;
 
 use super::helper::{RangeQuery, RangeQueryKind};
-use super::{helper, IndexScan, Plan, SeqScan};
+use super::{
+    helper, FilteredScan, IndexScan, Plan, RankedScan, RecordFilter, ResidualIndexScans,
+    ScanReason, SeqScan, SnapshotScan, SpatialFilter, SpatialFilterKind, SpatialScan,
+};
 use super::{IndexFilter, IndexScanKind};
 
+/// Maximum number of filters of a conjunction for which subsets answered by indexes are searched.
+const MAX_RESIDUAL_SEARCH_FILTERS: usize = 6;
+
 pub struct QueryPlanner<'a> {
     schema: &'a Schema,
     secondary_indexes: &'a [IndexDefinition],
@@ -27,12 +37,278 @@ impl<'a> QueryPlanner<'a> {
     }
 
     pub fn plan(&self) -> Result<Plan, PlanError> {
-        // Collect all the filters.
-        // TODO: Handle filters like And([a > 0, a < 10]).
-        let mut filters = vec![];
-        if let Some(expression) = &self.query.filter {
-            collect_filters(self.schema, expression, &mut filters)?;
+        if let Some(commit) = self.query.as_of {
+            return self.plan_snapshot(commit);
+        }
+        if self
+            .query
+            .order_by
+            .0
+            .iter()
+            .any(|order| order.field_name == SCORE_SORT_FIELD)
+        {
+            return self.plan_ranked();
+        }
+        let Some(expression) = &self.query.filter else {
+            return self.plan_conjunction_with_residual(vec![]);
+        };
+        let filter = resolve_filter(self.schema, self.secondary_indexes, expression)?;
+        if let Some(plan) = self.plan_spatial(&filter)? {
+            return Ok(plan);
+        }
+        match filter.disjunctive_normal_form() {
+            Some(mut conjunctions) if conjunctions.len() == 1 => {
+                self.plan_conjunction_with_residual(conjunctions.remove(0))
+            }
+            Some(conjunctions) if self.query.order_by.0.is_empty() => {
+                self.plan_union(conjunctions, filter)
+            }
+            Some(_) => self.plan_filtered_scan(filter, ScanReason::SortedDisjunction),
+            None => self.plan_with_residual(filter),
+        }
+    }
+
+    /// Plans a query as of a past commit. Indexes only have the latest versions of the records, so
+    /// the versions current at the commit are scanned and sorted in memory.
+    fn plan_snapshot(&self, commit: u64) -> Result<Plan, PlanError> {
+        if self
+            .query
+            .order_by
+            .0
+            .iter()
+            .any(|order| order.field_name == SCORE_SORT_FIELD)
+        {
+            return Err(PlanError::UnsupportedAsOf);
+        }
+        let filter = self
+            .query
+            .filter
+            .as_ref()
+            .map(|expression| resolve_filter(self.schema, self.secondary_indexes, expression))
+            .transpose()?;
+        if filter.as_ref().map_or(0, count_nearest) > 0 {
+            return Err(PlanError::UnsupportedAsOf);
+        }
+        Ok(Plan::SnapshotScan(SnapshotScan {
+            commit,
+            filter,
+            order_by: self.resolve_order_by()?,
+        }))
+    }
+
+    /// Plans a query sorted by `$score`, finding the records with the plan of the unsorted query
+    /// and sorting them in memory.
+    fn plan_ranked(&self) -> Result<Plan, PlanError> {
+        let [order] = &self.query.order_by.0[..] else {
+            return Err(PlanError::ConflictingSortOptions);
+        };
+        let Some(expression) = &self.query.filter else {
+            return Err(PlanError::ScoreWithoutFullText);
+        };
+        let filter = resolve_filter(self.schema, self.secondary_indexes, expression)?;
+        if count_nearest(&filter) > 0 {
+            return Err(PlanError::ConflictingSortOptions);
+        }
+        let filters = filter.full_text_filters();
+        if filters.is_empty() {
+            return Err(PlanError::ScoreWithoutFullText);
+        }
+        // Records are scored with the statistics of the full text index of each field.
+        for filter in &filters {
+            let index = IndexDefinition::FullText(filter.field_index, filter.analyzer);
+            if !self.secondary_indexes.contains(&index) {
+                return Err(PlanError::ScoreWithoutFullTextIndex(
+                    self.schema.fields[filter.field_index].name.clone(),
+                ));
+            }
+        }
+
+        let query = QueryExpression::new(Some(expression.clone()), vec![], None, Skip::Skip(0));
+        let plan = QueryPlanner::new(self.schema, self.secondary_indexes, &query).plan()?;
+        Ok(Plan::Ranked(RankedScan {
+            plan: Box::new(plan),
+            filters,
+            direction: order.direction,
+        }))
+    }
+
+    /// Plans a filter with a spatial conjunct on a field with a spatial index, scanning the index
+    /// for the region of that conjunct and checking the other conjuncts on the records found.
+    ///
+    /// `$nearest` can only be planned this way, as its records are sorted by distance.
+    fn plan_spatial(&self, filter: &RecordFilter) -> Result<Option<Plan>, PlanError> {
+        let conjuncts = match filter {
+            RecordFilter::And(conjuncts) => &conjuncts[..],
+            filter => std::slice::from_ref(filter),
+        };
+        let num_nearest = count_nearest(filter);
+        if num_nearest > 1 {
+            return Err(PlanError::UnsupportedNearest);
+        }
+        let found = conjuncts
+            .iter()
+            .enumerate()
+            .find_map(|(position, conjunct)| match conjunct {
+                RecordFilter::Spatial(spatial) if num_nearest == 0 || is_nearest(spatial) => self
+                    .secondary_indexes
+                    .iter()
+                    .position(|index| index == &IndexDefinition::Spatial(spatial.field_index))
+                    .map(|index_id| (position, spatial, index_id)),
+                _ => None,
+            });
+        let Some((position, spatial, index_id)) = found else {
+            return if num_nearest == 0 {
+                Ok(None)
+            } else {
+                Err(PlanError::UnsupportedNearest)
+            };
+        };
+
+        let order_by = self.resolve_order_by()?;
+        if num_nearest == 1 && !order_by.is_empty() {
+            return Err(PlanError::ConflictingSortOptions);
+        }
+        let mut residual = conjuncts.to_vec();
+        residual.remove(position);
+        Ok(Some(Plan::SpatialScan(SpatialScan {
+            index_id,
+            filter: spatial.clone(),
+            residual: (!residual.is_empty()).then_some(RecordFilter::And(residual)),
+            order_by,
+        })))
+    }
+
+    /// Plans a filter that index scans can't fully answer, scanning the indexes for the conjuncts
+    /// they can answer and checking the others on the records found.
+    fn plan_with_residual(&self, filter: RecordFilter) -> Result<Plan, PlanError> {
+        let RecordFilter::And(conjuncts) = &filter else {
+            return self.plan_filtered_scan(filter, ScanReason::NotIndexable);
+        };
+        let mut covered = vec![];
+        let mut residual = vec![];
+        for conjunct in conjuncts {
+            match conjunct.disjunctive_normal_form() {
+                Some(mut conjunctions) if conjunctions.len() == 1 => {
+                    covered.append(&mut conjunctions[0])
+                }
+                _ => residual.push(conjunct.clone()),
+            }
+        }
+        if covered.is_empty() {
+            return self.plan_filtered_scan(filter, ScanReason::NotIndexable);
+        }
+
+        match self.plan_conjunction_with_residual(covered)? {
+            Plan::IndexScans(index_scans) => Ok(Plan::ResidualIndexScans(ResidualIndexScans {
+                index_scans,
+                residual: Some(RecordFilter::And(residual)),
+            })),
+            Plan::ResidualIndexScans(mut scans) => {
+                residual.extend(scans.residual.take());
+                scans.residual = Some(RecordFilter::And(residual));
+                Ok(Plan::ResidualIndexScans(scans))
+            }
+            Plan::ReturnEmpty => Ok(Plan::ReturnEmpty),
+            _ => self.plan_filtered_scan(filter, ScanReason::NotIndexable),
         }
+    }
+
+    /// Plans a disjunction as the union of the index scans of its conjunctions, falling back to
+    /// a filtered scan if any of them can't be answered by the indexes.
+    fn plan_union(
+        &self,
+        conjunctions: Vec<Vec<IndexFilter>>,
+        filter: RecordFilter,
+    ) -> Result<Plan, PlanError> {
+        let mut union = vec![];
+        for filters in conjunctions {
+            match self.plan_conjunction_with_residual(filters)? {
+                Plan::IndexScans(index_scans) => union.push(ResidualIndexScans {
+                    index_scans,
+                    residual: None,
+                }),
+                Plan::ResidualIndexScans(scans) => union.push(scans),
+                Plan::ReturnEmpty => (),
+                _ => return self.plan_filtered_scan(filter, ScanReason::DisjunctNotIndexed),
+            }
+        }
+        Ok(if union.is_empty() {
+            Plan::ReturnEmpty
+        } else {
+            Plan::Union(union)
+        })
+    }
+
+    fn plan_filtered_scan(
+        &self,
+        filter: RecordFilter,
+        reason: ScanReason,
+    ) -> Result<Plan, PlanError> {
+        Ok(Plan::FilteredScan(FilteredScan {
+            filter,
+            order_by: self.resolve_order_by()?,
+            reason,
+        }))
+    }
+
+    fn resolve_order_by(&self) -> Result<Vec<(usize, SortDirection)>, PlanError> {
+        self.query
+            .order_by
+            .0
+            .iter()
+            .map(|order| {
+                get_field_index_and_type(&order.field_name, &self.schema.fields)
+                    .map(|(field_index, _, _)| (field_index, order.direction))
+                    .ok_or_else(|| PlanError::FieldNotFound(order.field_name.clone()))
+            })
+            .collect()
+    }
+
+    /// Plans a conjunction with the indexes answering the most filters, the others being checked
+    /// on the records found. Falls back to a filtered scan if no index can be used.
+    fn plan_conjunction_with_residual(&self, filters: Vec<IndexFilter>) -> Result<Plan, PlanError> {
+        match self.plan_conjunction(filters.clone()) {
+            Err(PlanError::MatchingIndexNotFound | PlanError::RangeQueryLimit) => (),
+            result => return result,
+        }
+
+        let to_record_filter = |filters: Vec<IndexFilter>| {
+            RecordFilter::And(filters.into_iter().map(RecordFilter::Simple).collect())
+        };
+        if filters.len() <= MAX_RESIDUAL_SEARCH_FILTERS {
+            for num_covered in (0..filters.len()).rev() {
+                for covered in (0..filters.len()).combinations(num_covered) {
+                    let (covered, residual): (Vec<_>, Vec<_>) = filters
+                        .iter()
+                        .cloned()
+                        .enumerate()
+                        .partition(|(i, _)| covered.contains(i));
+                    let covered = covered.into_iter().map(|(_, filter)| filter).collect();
+                    let residual = residual.into_iter().map(|(_, filter)| filter).collect();
+                    match self.plan_conjunction(covered) {
+                        Ok(Plan::IndexScans(index_scans)) => {
+                            return Ok(Plan::ResidualIndexScans(ResidualIndexScans {
+                                index_scans,
+                                residual: Some(to_record_filter(residual)),
+                            }));
+                        }
+                        Ok(Plan::ReturnEmpty) => return Ok(Plan::ReturnEmpty),
+                        Ok(_)
+                        | Err(PlanError::MatchingIndexNotFound | PlanError::RangeQueryLimit) => (),
+                        Err(e) => return Err(e),
+                    }
+                }
+            }
+        }
+        self.plan_filtered_scan(to_record_filter(filters), ScanReason::MatchingIndexNotFound)
+    }
+
+    fn plan_conjunction(&self, filters: Vec<IndexFilter>) -> Result<Plan, PlanError> {
+        // TODO: Handle filters like And([a > 0, a < 10]).
+        let mut filters = filters
+            .into_iter()
+            .map(|filter| (filter, None))
+            .collect::<Vec<_>>();
 
         // Filter the sort options.
         // TODO: Handle duplicate fields.
@@ -94,26 +370,109 @@ fn get_field_index_and_type(
         .map(|(i, f)| (i, f.typ, f.nullable))
 }
 
-fn collect_filters(
+pub(super) fn resolve_filter(
     schema: &Schema,
+    secondary_indexes: &[IndexDefinition],
     expression: &FilterExpression,
-    filters: &mut Vec<(IndexFilter, Option<SortDirection>)>,
-) -> Result<(), PlanError> {
-    match expression {
+) -> Result<RecordFilter, PlanError> {
+    let resolve_all = |expressions: &[FilterExpression]| {
+        expressions
+            .iter()
+            .map(|expression| resolve_filter(schema, secondary_indexes, expression))
+            .collect::<Result<Vec<_>, _>>()
+    };
+    Ok(match expression {
         FilterExpression::Simple(field_name, operator, value) => {
             let (field_index, field_type, nullable) =
                 get_field_index_and_type(field_name, &schema.fields)
                     .ok_or_else(|| PlanError::FieldNotFound(field_name.clone()))?;
-            let field = json_value_to_field(value.clone(), field_type, nullable)?;
-            filters.push((IndexFilter::new(field_index, *operator, field), None));
+            let eq_filter = |value: &Value| -> Result<RecordFilter, PlanError> {
+                let field = json_value_to_field(value.clone(), field_type, nullable)?;
+                Ok(RecordFilter::Simple(IndexFilter::new(
+                    field_index,
+                    Operator::EQ,
+                    field,
+                )))
+            };
+            match operator {
+                Operator::In => {
+                    let Value::Array(values) = value else {
+                        return Err(PlanError::InRequiresArray(field_name.clone()));
+                    };
+                    RecordFilter::Or(values.iter().map(eq_filter).collect::<Result<_, _>>()?)
+                }
+                // Like the other non-`Eq` operators, `$ne` never matches nulls.
+                Operator::NE => {
+                    let field = json_value_to_field(value.clone(), field_type, nullable)?;
+                    let filter =
+                        |op, field| RecordFilter::Simple(IndexFilter::new(field_index, op, field));
+                    if field == Field::Null {
+                        filter(Operator::NE, field)
+                    } else {
+                        RecordFilter::Not(Box::new(RecordFilter::Or(vec![
+                            filter(Operator::EQ, field),
+                            filter(Operator::EQ, Field::Null),
+                        ])))
+                    }
+                }
+                Operator::WithinRadius | Operator::WithinBbox | Operator::Nearest => {
+                    if field_type != FieldType::Point {
+                        return Err(PlanError::NotPoint(field_name.clone(), field_type));
+                    }
+                    let kind = SpatialFilterKind::from_operator_and_value(*operator, value)
+                        .ok_or_else(|| {
+                            PlanError::InvalidSpatialFilter(field_name.clone(), *operator)
+                        })?;
+                    RecordFilter::Spatial(SpatialFilter::new(field_index, kind))
+                }
+                _ => {
+                    let field = json_value_to_field(value.clone(), field_type, nullable)?;
+                    let filter = IndexFilter::new(field_index, *operator, field);
+                    if operator.supported_by_full_text() {
+                        let analyzer = full_text_analyzer(secondary_indexes, field_index);
+                        RecordFilter::Simple(filter.with_analyzer(analyzer))
+                    } else {
+                        RecordFilter::Simple(filter)
+                    }
+                }
+            }
         }
-        FilterExpression::And(expressions) => {
-            for expression in expressions {
-                collect_filters(schema, expression, filters)?;
+        FilterExpression::And(expressions) => RecordFilter::And(resolve_all(expressions)?),
+        FilterExpression::Or(expressions) => RecordFilter::Or(resolve_all(expressions)?),
+        FilterExpression::Not(expression) => RecordFilter::Not(Box::new(resolve_filter(
+            schema,
+            secondary_indexes,
+            expression,
+        )?)),
+    })
+}
+
+/// Full text filters analyze text like the full text index of their field, if any.
+fn full_text_analyzer(secondary_indexes: &[IndexDefinition], field_index: usize) -> TextAnalyzer {
+    secondary_indexes
+        .iter()
+        .find_map(|index| match index {
+            IndexDefinition::FullText(index_field, analyzer) if *index_field == field_index => {
+                Some(*analyzer)
             }
+            _ => None,
+        })
+        .unwrap_or_default()
+}
+
+fn is_nearest(filter: &SpatialFilter) -> bool {
+    matches!(filter.kind, SpatialFilterKind::Nearest { .. })
+}
+
+fn count_nearest(filter: &RecordFilter) -> usize {
+    match filter {
+        RecordFilter::Simple(_) => 0,
+        RecordFilter::Spatial(filter) => is_nearest(filter) as usize,
+        RecordFilter::And(filters) | RecordFilter::Or(filters) => {
+            filters.iter().map(count_nearest).sum()
         }
+        RecordFilter::Not(filter) => count_nearest(filter),
     }
-    Ok(())
 }
 
 fn seen_in_sorted_inverted_filter(
@@ -211,9 +570,10 @@ impl IndexScanKind {
                     fields.len() == eq_filters.len()
                 }
             }
-            (IndexScanKind::FullText { filter }, IndexDefinition::FullText(field_index)) => {
-                filter.field_index == *field_index
-            }
+            (
+                IndexScanKind::FullText { filter },
+                IndexDefinition::FullText(field_index, analyzer),
+            ) => filter.field_index == *field_index && filter.analyzer == *analyzer,
             _ => false,
         }
     }
@@ -291,20 +651,27 @@ mod tests {
         check_sorted_inverted(vec![0], Some(1), vec![0], false);
 
         let full_text_scan = IndexScanKind::FullText {
-            filter: IndexFilter {
-                field_index: 0,
-                op: Operator::Contains,
-                val: Field::Null,
-            },
+            filter: IndexFilter::new(0, Operator::Contains, Field::Null),
         };
-        assert!(full_text_scan.is_supported_by_index(&IndexDefinition::FullText(0)),);
-        assert!(!full_text_scan.is_supported_by_index(&IndexDefinition::FullText(1)));
+        let analyzer = TextAnalyzer::default();
+        assert!(full_text_scan.is_supported_by_index(&IndexDefinition::FullText(0, analyzer)),);
+        assert!(!full_text_scan.is_supported_by_index(&IndexDefinition::FullText(1, analyzer)));
+        // The filter's terms must be analyzed like the index's.
+        assert!(
+            !full_text_scan.is_supported_by_index(&IndexDefinition::FullText(
+                0,
+                TextAnalyzer {
+                    lowercase: true,
+                    ..Default::default()
+                }
+            ))
+        );
 
         assert!(!full_text_scan.is_supported_by_index(&IndexDefinition::SortedInverted(vec![0])),);
         assert!(!IndexScanKind::SortedInverted {
             eq_filters: vec![(0, Field::Null)],
             range_query: None
         }
-        .is_supported_by_index(&IndexDefinition::FullText(0)),);
+        .is_supported_by_index(&IndexDefinition::FullText(0, analyzer)),);
     }
 }
@@ -0,0 +1,287 @@
+use dozer_types::geo::GeodesicDistance;
+use dozer_types::ordered_float::OrderedFloat;
+use dozer_types::serde::Deserialize;
+use dozer_types::serde_json::{self, Value};
+use dozer_types::types::{DozerPoint, Field};
+
+use crate::cache::expression::Operator;
+use crate::cache::index::BoundingBox;
+
+/// Conservative lengths of a degree, so that the bounding box of a circle contains it.
+const METERS_PER_DEGREE_OF_LATITUDE: f64 = 110_000.0;
+const METERS_PER_DEGREE_OF_LONGITUDE_AT_EQUATOR: f64 = 111_000.0;
+
+/// Radius of the first region searched for the points nearest to a point.
+const NEAREST_INITIAL_METERS: f64 = 1_000.0;
+/// Factor the radius of the searched region grows by while too few points are found.
+const NEAREST_RADIUS_GROWTH: f64 = 4.0;
+/// Longer than any geodesic distance, so a circle of this radius covers the world.
+const WORLD_METERS: f64 = 20_100_000.0;
+
+/// A `$within_radius`, `$within_bbox` or `$nearest` filter on a point field. Points are
+/// longitude (`x`) and latitude (`y`) in degrees.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SpatialFilter {
+    pub field_index: usize,
+    pub kind: SpatialFilterKind,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SpatialFilterKind {
+    WithinRadius {
+        center: DozerPoint,
+        meters: OrderedFloat<f64>,
+    },
+    WithinBbox {
+        min: DozerPoint,
+        max: DozerPoint,
+    },
+    Nearest {
+        point: DozerPoint,
+    },
+}
+
+#[derive(Deserialize)]
+#[serde(crate = "dozer_types::serde")]
+struct WithinRadius {
+    center: DozerPoint,
+    meters: f64,
+}
+
+#[derive(Deserialize)]
+#[serde(crate = "dozer_types::serde")]
+struct WithinBbox {
+    min: DozerPoint,
+    max: DozerPoint,
+}
+
+impl SpatialFilterKind {
+    /// Returns `None` if the operator is not spatial or the value doesn't fit it.
+    pub fn from_operator_and_value(operator: Operator, value: &Value) -> Option<Self> {
+        Some(match operator {
+            Operator::WithinRadius => {
+                let WithinRadius { center, meters } = serde_json::from_value(value.clone()).ok()?;
+                if meters.is_nan() || meters < 0.0 {
+                    return None;
+                }
+                SpatialFilterKind::WithinRadius {
+                    center,
+                    meters: OrderedFloat(meters),
+                }
+            }
+            Operator::WithinBbox => {
+                let WithinBbox { min, max } = serde_json::from_value(value.clone()).ok()?;
+                SpatialFilterKind::WithinBbox { min, max }
+            }
+            Operator::Nearest => SpatialFilterKind::Nearest {
+                point: serde_json::from_value(value.clone()).ok()?,
+            },
+            _ => return None,
+        })
+    }
+}
+
+impl SpatialFilter {
+    pub fn new(field_index: usize, kind: SpatialFilterKind) -> Self {
+        Self { field_index, kind }
+    }
+
+    /// `null` never matches.
+    pub fn matches(&self, field: &Field) -> bool {
+        let Field::Point(point) = field else {
+            return false;
+        };
+        match &self.kind {
+            SpatialFilterKind::WithinRadius { center, meters } => {
+                center.geodesic_distance(point) <= *meters
+            }
+            SpatialFilterKind::WithinBbox { min, max } => {
+                let (x, y) = (point.0.x(), point.0.y());
+                let within_x = if min.0.x() <= max.0.x() {
+                    min.0.x() <= x && x <= max.0.x()
+                } else {
+                    min.0.x() <= x || x <= max.0.x()
+                };
+                within_x && min.0.y() <= y && y <= max.0.y()
+            }
+            SpatialFilterKind::Nearest { .. } => true,
+        }
+    }
+
+    /// Boxes containing all points matching the filter, split at the antimeridian.
+    pub fn bounding_boxes(&self) -> Vec<BoundingBox> {
+        match &self.kind {
+            SpatialFilterKind::WithinRadius { center, meters } => {
+                let (x, y) = (center.0.x().0, center.0.y().0);
+                let delta_y = meters.0 / METERS_PER_DEGREE_OF_LATITUDE;
+                let (min_y, max_y) = (y - delta_y, y + delta_y);
+                if min_y <= -90.0 || max_y >= 90.0 {
+                    // The circle contains a pole.
+                    return vec![BoundingBox {
+                        min: (-180.0, min_y.max(-90.0)),
+                        max: (180.0, max_y.min(90.0)),
+                    }];
+                }
+                let max_latitude = min_y.abs().max(max_y.abs()).to_radians();
+                let delta_x =
+                    meters.0 / (METERS_PER_DEGREE_OF_LONGITUDE_AT_EQUATOR * max_latitude.cos());
+                if delta_x >= 180.0 {
+                    return vec![BoundingBox {
+                        min: (-180.0, min_y),
+                        max: (180.0, max_y),
+                    }];
+                }
+                split_at_antimeridian(
+                    wrap_longitude(x - delta_x),
+                    min_y,
+                    wrap_longitude(x + delta_x),
+                    max_y,
+                )
+            }
+            SpatialFilterKind::WithinBbox { min, max } => {
+                split_at_antimeridian(min.0.x().0, min.0.y().0, max.0.x().0, max.0.y().0)
+            }
+            SpatialFilterKind::Nearest { .. } => vec![BoundingBox::WORLD],
+        }
+    }
+
+    /// The regions searched for the points nearest to the point of a `$nearest` filter, as
+    /// `$within_radius` filters of growing radius, the last one covering the world. Empty for
+    /// other filters.
+    pub fn nearest_regions(&self) -> Vec<SpatialFilter> {
+        let SpatialFilterKind::Nearest { point } = &self.kind else {
+            return vec![];
+        };
+        let mut regions = vec![];
+        let mut meters = NEAREST_INITIAL_METERS;
+        loop {
+            let meters_capped = meters.min(WORLD_METERS);
+            regions.push(SpatialFilter::new(
+                self.field_index,
+                SpatialFilterKind::WithinRadius {
+                    center: *point,
+                    meters: OrderedFloat(meters_capped),
+                },
+            ));
+            if meters_capped >= WORLD_METERS {
+                return regions;
+            }
+            meters *= NEAREST_RADIUS_GROWTH;
+        }
+    }
+
+    /// Geodesic distance from the point of a `$nearest` filter.
+    pub fn distance(&self, field: &Field) -> Option<OrderedFloat<f64>> {
+        match (&self.kind, field) {
+            (SpatialFilterKind::Nearest { point: from }, Field::Point(to)) => {
+                Some(from.geodesic_distance(to))
+            }
+            _ => None,
+        }
+    }
+}
+
+fn wrap_longitude(x: f64) -> f64 {
+    if x < -180.0 {
+        x + 360.0
+    } else if x > 180.0 {
+        x - 360.0
+    } else {
+        x
+    }
+}
+
+fn split_at_antimeridian(min_x: f64, min_y: f64, max_x: f64, max_y: f64) -> Vec<BoundingBox> {
+    if min_x <= max_x {
+        vec![BoundingBox {
+            min: (min_x, min_y),
+            max: (max_x, max_y),
+        }]
+    } else {
+        vec![
+            BoundingBox {
+                min: (min_x, min_y),
+                max: (180.0, max_y),
+            },
+            BoundingBox {
+                min: (-180.0, min_y),
+                max: (max_x, max_y),
+            },
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use dozer_types::serde_json::json;
+
+    use super::*;
+
+    fn point(x: f64, y: f64) -> Field {
+        Field::Point(DozerPoint::from((x, y)))
+    }
+
+    #[test]
+    fn test_spatial_filter() {
+        let parse = |operator, value| {
+            SpatialFilter::new(
+                0,
+                SpatialFilterKind::from_operator_and_value(operator, &value).unwrap(),
+            )
+        };
+
+        // London to Paris is about 344 km.
+        let london = json!({"x": -0.1276, "y": 51.5072});
+        let within_400km = parse(
+            Operator::WithinRadius,
+            json!({"center": london, "meters": 400_000}),
+        );
+        let within_300km = parse(
+            Operator::WithinRadius,
+            json!({"center": london, "meters": 300_000}),
+        );
+        let paris = point(2.3522, 48.8566);
+        assert!(within_400km.matches(&paris));
+        assert!(!within_300km.matches(&paris));
+        assert!(!within_400km.matches(&Field::Null));
+        assert!(within_400km
+            .bounding_boxes()
+            .iter()
+            .any(|bounding_box| bounding_box.min.0 < 2.3522 && 2.3522 < bounding_box.max.0));
+
+        // A box crossing the antimeridian.
+        let fiji = parse(
+            Operator::WithinBbox,
+            json!({"min": {"x": 177, "y": -19}, "max": {"x": -179, "y": -16}}),
+        );
+        assert!(fiji.matches(&point(178.0, -18.0)));
+        assert!(fiji.matches(&point(-179.5, -17.0)));
+        assert!(!fiji.matches(&point(0.0, -18.0)));
+        assert_eq!(fiji.bounding_boxes().len(), 2);
+
+        let nearest = parse(Operator::Nearest, london);
+        assert!(nearest.matches(&paris));
+        assert!(nearest.distance(&paris).unwrap() > OrderedFloat(300_000.0));
+        assert_eq!(nearest.bounding_boxes(), vec![BoundingBox::WORLD]);
+        let regions = nearest.nearest_regions();
+        assert!(regions[0].matches(&point(-0.1276, 51.5)));
+        assert!(!regions[0].matches(&paris));
+        let world = regions.last().unwrap();
+        assert_eq!(world.bounding_boxes(), vec![BoundingBox::WORLD]);
+        assert!(world.matches(&point(179.8724, -51.5072)));
+        assert!(within_400km.nearest_regions().is_empty());
+
+        for (operator, value) in [
+            (Operator::WithinRadius, json!({"center": {"x": 0, "y": 0}})),
+            (
+                Operator::WithinRadius,
+                json!({"center": {"x": 0, "y": 0}, "meters": -1}),
+            ),
+            (Operator::WithinBbox, json!({"min": {"x": 0, "y": 0}})),
+            (Operator::Nearest, json!(1)),
+            (Operator::EQ, json!({"x": 0, "y": 0})),
+        ] {
+            assert!(SpatialFilterKind::from_operator_and_value(operator, &value).is_none());
+        }
+    }
+}
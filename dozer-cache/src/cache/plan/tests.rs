@@ -1,4 +1,7 @@
-use super::{Plan, QueryPlanner};
+use super::{
+    FilteredScan, IndexFilter, Plan, QueryPlanner, RecordFilter, ScanReason, SnapshotScan,
+    SpatialScan,
+};
 use crate::cache::{
     expression::{
         self, FilterExpression, Operator, QueryExpression, Skip, SortDirection, SortOption,
@@ -7,7 +10,10 @@ use crate::cache::{
     test_utils::{self, query_from_filter},
 };
 
-use dozer_types::{serde_json::Value, types::Field};
+use dozer_types::{
+    serde_json::{json, Value},
+    types::{Field, IndexDefinition, Schema},
+};
 
 #[test]
 fn test_generate_plan_simple() {
@@ -124,3 +130,307 @@ fn test_generate_plan_empty() {
     let planner = QueryPlanner::new(&schema, &secondary_indexes, &query);
     assert!(matches!(planner.plan().unwrap(), Plan::ReturnEmpty));
 }
+
+#[test]
+fn test_generate_plan_union() {
+    let (schema, secondary_indexes) = test_utils::schema_1();
+
+    let query = query_from_filter(FilterExpression::Or(vec![
+        FilterExpression::Simple("a".into(), Operator::EQ, 1.into()),
+        FilterExpression::Simple("c".into(), Operator::GT, 2.into()),
+    ]));
+    let planner = QueryPlanner::new(&schema, &secondary_indexes, &query);
+    if let Plan::Union(union) = planner.plan().unwrap() {
+        assert_eq!(union.len(), 2);
+        assert_eq!(union[0].index_scans[0].index_id, 0);
+        assert_eq!(union[1].index_scans[0].index_id, 2);
+        assert!(union.iter().all(|scans| scans.residual.is_none()));
+    } else {
+        panic!("Union expected")
+    }
+
+    // `$in` is a disjunction of `Eq` filters.
+    let query = query_from_filter(FilterExpression::Simple(
+        "b".into(),
+        Operator::In,
+        Value::from(vec!["x", "y"]),
+    ));
+    let planner = QueryPlanner::new(&schema, &secondary_indexes, &query);
+    if let Plan::Union(union) = planner.plan().unwrap() {
+        assert_eq!(
+            union
+                .iter()
+                .map(|scans| scans.index_scans[0].kind.clone())
+                .collect::<Vec<_>>(),
+            vec![
+                IndexScanKind::SortedInverted {
+                    eq_filters: vec![(1, Field::String("x".into()))],
+                    range_query: None,
+                },
+                IndexScanKind::SortedInverted {
+                    eq_filters: vec![(1, Field::String("y".into()))],
+                    range_query: None,
+                },
+            ]
+        );
+    } else {
+        panic!("Union expected")
+    }
+
+    let query = query_from_filter(FilterExpression::Simple(
+        "b".into(),
+        Operator::In,
+        Value::Array(vec![]),
+    ));
+    let planner = QueryPlanner::new(&schema, &secondary_indexes, &query);
+    assert!(matches!(planner.plan().unwrap(), Plan::ReturnEmpty));
+
+    let query = query_from_filter(FilterExpression::Simple("b".into(), Operator::In, 1.into()));
+    let planner = QueryPlanner::new(&schema, &secondary_indexes, &query);
+    assert!(planner.plan().is_err());
+}
+
+#[test]
+fn test_generate_plan_residual() {
+    let (schema, secondary_indexes) = test_utils::schema_1();
+    let plan = |query: QueryExpression| {
+        QueryPlanner::new(&schema, &secondary_indexes, &query)
+            .plan()
+            .unwrap()
+    };
+
+    // No index on (a, c), so `c` is checked on the records found by `a`.
+    let Plan::ResidualIndexScans(scans) = plan(query_from_filter(FilterExpression::And(vec![
+        FilterExpression::Simple("a".into(), Operator::EQ, 1.into()),
+        FilterExpression::Simple("c".into(), Operator::EQ, 2.into()),
+    ]))) else {
+        panic!("ResidualIndexScans expected")
+    };
+    assert_eq!(scans.index_scans[0].index_id, 0);
+    assert_eq!(
+        scans.residual,
+        Some(RecordFilter::And(vec![RecordFilter::Simple(
+            IndexFilter::new(2, Operator::EQ, Field::Int(2))
+        )]))
+    );
+
+    // A negation is checked on the records found by the other conjuncts.
+    let Plan::ResidualIndexScans(scans) = plan(query_from_filter(FilterExpression::And(vec![
+        FilterExpression::Simple("a".into(), Operator::GT, 1.into()),
+        FilterExpression::Simple("c".into(), Operator::NE, 2.into()),
+    ]))) else {
+        panic!("ResidualIndexScans expected")
+    };
+    assert_eq!(scans.index_scans[0].index_id, 0);
+    assert!(matches!(
+        scans.residual,
+        Some(RecordFilter::And(residual)) if matches!(residual[..], [RecordFilter::Not(_)])
+    ));
+
+    // The sort is answered by the index on `b`.
+    let Plan::ResidualIndexScans(scans) = plan(QueryExpression::new(
+        Some(FilterExpression::Simple("c".into(), Operator::GT, 2.into())),
+        vec![SortOption::new("b".into(), SortDirection::Ascending)],
+        None,
+        Skip::Skip(0),
+    )) else {
+        panic!("ResidualIndexScans expected")
+    };
+    assert_eq!(scans.index_scans[0].index_id, 1);
+
+    // Disjuncts may have residuals.
+    let Plan::Union(union) = plan(query_from_filter(FilterExpression::Or(vec![
+        FilterExpression::Simple("b".into(), Operator::EQ, "x".into()),
+        FilterExpression::And(vec![
+            FilterExpression::Simple("a".into(), Operator::EQ, 1.into()),
+            FilterExpression::Simple("c".into(), Operator::EQ, 2.into()),
+        ]),
+    ]))) else {
+        panic!("Union expected")
+    };
+    assert!(union[0].residual.is_none());
+    assert!(union[1].residual.is_some());
+}
+
+#[test]
+fn test_generate_plan_filtered_scan() {
+    let check = |(schema, secondary_indexes): (Schema, Vec<IndexDefinition>),
+                 query: QueryExpression,
+                 expected_reason: ScanReason| {
+        let planner = QueryPlanner::new(&schema, &secondary_indexes, &query);
+        let Plan::FilteredScan(FilteredScan { reason, .. }) = planner.plan().unwrap() else {
+            panic!("FilteredScan expected")
+        };
+        assert_eq!(reason, expected_reason);
+    };
+
+    // `text` only has a full text index.
+    check(
+        test_utils::schema_multi_indices(),
+        query_from_filter(FilterExpression::Simple(
+            "text".into(),
+            Operator::EQ,
+            "x".into(),
+        )),
+        ScanReason::MatchingIndexNotFound,
+    );
+    check(
+        test_utils::schema_multi_indices(),
+        query_from_filter(FilterExpression::Or(vec![
+            FilterExpression::Simple("id".into(), Operator::EQ, 1.into()),
+            FilterExpression::Simple("text".into(), Operator::EQ, "x".into()),
+        ])),
+        ScanReason::DisjunctNotIndexed,
+    );
+    // Negations can't be answered by indexes.
+    check(
+        test_utils::schema_1(),
+        query_from_filter(FilterExpression::Simple("a".into(), Operator::NE, 1.into())),
+        ScanReason::NotIndexable,
+    );
+    // Disjunctions are not sorted by index scans.
+    check(
+        test_utils::schema_1(),
+        QueryExpression::new(
+            Some(FilterExpression::Or(vec![
+                FilterExpression::Simple("a".into(), Operator::EQ, 1.into()),
+                FilterExpression::Simple("a".into(), Operator::EQ, 2.into()),
+            ])),
+            vec![SortOption::new("c".into(), SortDirection::Ascending)],
+            None,
+            Skip::Skip(0),
+        ),
+        ScanReason::SortedDisjunction,
+    );
+}
+
+#[test]
+fn test_generate_plan_snapshot() {
+    let (schema, secondary_indexes) = test_utils::schema_1();
+    let query = QueryExpression {
+        as_of: Some(2),
+        ..QueryExpression::new(
+            Some(FilterExpression::Simple("a".into(), Operator::EQ, 1.into())),
+            vec![SortOption::new("c".into(), SortDirection::Descending)],
+            None,
+            Skip::Skip(0),
+        )
+    };
+    let planner = QueryPlanner::new(&schema, &secondary_indexes, &query);
+    // Indexes only have the latest versions, so they're not used.
+    assert_eq!(
+        planner.plan().unwrap(),
+        Plan::SnapshotScan(SnapshotScan {
+            commit: 2,
+            filter: Some(RecordFilter::Simple(IndexFilter::new(
+                0,
+                Operator::EQ,
+                Field::Int(1)
+            ))),
+            order_by: vec![(2, SortDirection::Descending)],
+        })
+    );
+
+    let (schema, secondary_indexes) = test_utils::schema_full_text();
+    let query = QueryExpression {
+        as_of: Some(2),
+        ..QueryExpression::new(
+            Some(FilterExpression::Simple(
+                "foo".into(),
+                Operator::Contains,
+                "x".into(),
+            )),
+            vec![SortOption::new(
+                expression::SCORE_SORT_FIELD.into(),
+                SortDirection::Descending,
+            )],
+            None,
+            Skip::Skip(0),
+        )
+    };
+    let planner = QueryPlanner::new(&schema, &secondary_indexes, &query);
+    assert!(planner.plan().is_err());
+}
+
+#[test]
+fn test_generate_plan_spatial() {
+    let (schema, secondary_indexes) = test_utils::schema_spatial();
+    let plan = |filter: FilterExpression, order_by: Vec<SortOption>| {
+        let query = QueryExpression::new(Some(filter), order_by, None, Skip::Skip(0));
+        QueryPlanner::new(&schema, &secondary_indexes, &query).plan()
+    };
+    let within_radius = || {
+        FilterExpression::Simple(
+            "location".into(),
+            Operator::WithinRadius,
+            json!({"center": {"x": 0, "y": 0}, "meters": 1000}),
+        )
+    };
+    let nearest = || {
+        FilterExpression::Simple(
+            "location".into(),
+            Operator::Nearest,
+            json!({"x": 0, "y": 0}),
+        )
+    };
+
+    let Plan::SpatialScan(SpatialScan {
+        index_id, residual, ..
+    }) = plan(
+        FilterExpression::And(vec![
+            within_radius(),
+            FilterExpression::Simple("id".into(), Operator::GT, 1.into()),
+        ]),
+        vec![],
+    )
+    .unwrap()
+    else {
+        panic!("SpatialScan expected")
+    };
+    assert_eq!(index_id, 1);
+    assert_eq!(
+        residual,
+        Some(RecordFilter::And(vec![RecordFilter::Simple(
+            IndexFilter::new(0, Operator::GT, Field::Int(1))
+        )]))
+    );
+
+    // Without spatial index, spatial filters are checked on all records.
+    let query = query_from_filter(within_radius());
+    let Plan::FilteredScan(FilteredScan { reason, .. }) =
+        QueryPlanner::new(&schema, &[], &query).plan().unwrap()
+    else {
+        panic!("FilteredScan expected")
+    };
+    assert_eq!(reason, ScanReason::NotIndexable);
+
+    // `$nearest` needs a spatial index, appears once and can't be sorted otherwise.
+    assert!(
+        QueryPlanner::new(&schema, &[], &query_from_filter(nearest()))
+            .plan()
+            .is_err()
+    );
+    assert!(plan(FilterExpression::And(vec![nearest(), nearest()]), vec![]).is_err());
+    assert!(plan(
+        FilterExpression::Or(vec![nearest(), within_radius()]),
+        vec![]
+    )
+    .is_err());
+    assert!(plan(
+        nearest(),
+        vec![SortOption::new("id".into(), SortDirection::Ascending)]
+    )
+    .is_err());
+
+    // Spatial operators only apply to points with valid values.
+    assert!(plan(
+        FilterExpression::Simple("id".into(), Operator::Nearest, json!({"x": 0, "y": 0})),
+        vec![]
+    )
+    .is_err());
+    assert!(plan(
+        FilterExpression::Simple("location".into(), Operator::WithinBbox, json!(1)),
+        vec![]
+    )
+    .is_err());
+}
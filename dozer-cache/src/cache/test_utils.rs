@@ -1,5 +1,5 @@
 use dozer_types::types::{
-    FieldDefinition, IndexDefinition, Schema, SchemaIdentifier, SourceDefinition,
+    FieldDefinition, IndexDefinition, Schema, SchemaIdentifier, SourceDefinition, TextAnalyzer,
 };
 
 use super::expression::{FilterExpression, QueryExpression, Skip};
@@ -76,7 +76,10 @@ pub fn schema_full_text() -> (Schema, Vec<IndexDefinition>) {
             ],
             primary_index: vec![0],
         },
-        vec![IndexDefinition::FullText(0), IndexDefinition::FullText(1)],
+        vec![
+            IndexDefinition::FullText(0, TextAnalyzer::default()),
+            IndexDefinition::FullText(1, TextAnalyzer::default()),
+        ],
     )
 }
 
@@ -119,7 +122,34 @@ pub fn schema_multi_indices() -> (Schema, Vec<IndexDefinition>) {
         },
         vec![
             IndexDefinition::SortedInverted(vec![0]),
-            IndexDefinition::FullText(1),
+            IndexDefinition::FullText(1, TextAnalyzer::default()),
+        ],
+    )
+}
+
+pub fn schema_spatial() -> (Schema, Vec<IndexDefinition>) {
+    (
+        Schema {
+            identifier: Some(SchemaIdentifier { id: 5, version: 1 }),
+            fields: vec![
+                FieldDefinition {
+                    name: "id".to_string(),
+                    typ: dozer_types::types::FieldType::Int,
+                    nullable: false,
+                    source: SourceDefinition::Dynamic,
+                },
+                FieldDefinition {
+                    name: "location".to_string(),
+                    typ: dozer_types::types::FieldType::Point,
+                    nullable: true,
+                    source: SourceDefinition::Dynamic,
+                },
+            ],
+            primary_index: vec![0],
+        },
+        vec![
+            IndexDefinition::SortedInverted(vec![0]),
+            IndexDefinition::Spatial(1),
         ],
     )
 }
@@ -3,7 +3,9 @@ use dozer_types::thiserror::Error;
 
 use dozer_types::errors::internal::BoxedError;
 use dozer_types::errors::types::{DeserializationError, SerializationError, TypeError};
-use dozer_types::types::SchemaIdentifier;
+use dozer_types::types::{FieldType, SchemaIdentifier};
+
+use crate::cache::expression::Operator;
 
 #[derive(Error, Debug)]
 pub enum CacheError {
@@ -31,6 +33,20 @@ pub enum CacheError {
     PathNotInitialized,
     #[error("Secondary index database is not found")]
     SecondaryIndexDatabaseNotFound,
+    #[error("Sum of field {0:?} overflows")]
+    AggregateOverflow(String),
+    #[error("Field {0:?} can't be read with this access")]
+    FieldNotPermitted(String),
+    #[error("Record can't be read with this access")]
+    RecordNotPermitted,
+    #[error("Retention field {0:?} not found")]
+    RetentionFieldNotFound(String),
+    #[error("Retention field {0:?} must be a timestamp or date, not {1:?}")]
+    InvalidRetentionField(String, FieldType),
+    #[error("History of the records isn't kept")]
+    HistoryNotKept,
+    #[error("History of the records is kept since commit {since}, not as of commit {as_of}")]
+    HistoryNotAvailable { since: u64, as_of: u64 },
 }
 
 impl CacheError {
@@ -106,4 +122,26 @@ pub enum PlanError {
     RangeQueryLimit,
     #[error("Matching index not found")]
     MatchingIndexNotFound,
+    #[error("Operator $in on field {0:?} requires an array of values")]
+    InRequiresArray(String),
+    #[error("Query needs to scan {num_records} records, more than the limit of {max_scan_records}, because {reason}")]
+    ScanLimitExceeded {
+        num_records: usize,
+        max_scan_records: usize,
+        reason: String,
+    },
+    #[error("Field {0:?} of type {1} can't be summed or averaged")]
+    NotNumeric(String, FieldType),
+    #[error("Field {0:?} of type {1} is not a point")]
+    NotPoint(String, FieldType),
+    #[error("Invalid value of operator {1:?} on field {0:?}")]
+    InvalidSpatialFilter(String, Operator),
+    #[error("$nearest must appear once, as a top level filter on a field with a spatial index")]
+    UnsupportedNearest,
+    #[error("Sorting by $score needs a full text filter")]
+    ScoreWithoutFullText,
+    #[error("Sorting by $score needs a full text index on field {0:?}")]
+    ScoreWithoutFullTextIndex(String),
+    #[error("Queries $as_of a commit can't be sorted by $score or use $nearest")]
+    UnsupportedAsOf,
 }
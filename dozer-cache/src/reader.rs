@@ -1,10 +1,13 @@
-use crate::cache::{expression::QueryExpression, RecordWithId, RoCache};
+use crate::cache::{
+    expression::{Aggregate, AggregateExpression, QueryExpression},
+    AggregateGroup, RecordFilter, RecordVersion, RecordWithId, RoCache,
+};
 
 use super::cache::expression::FilterExpression;
-use crate::errors::CacheError;
+use crate::errors::{CacheError, PlanError};
 use dozer_types::{
     serde,
-    types::{IndexDefinition, Record, Schema},
+    types::{Field, IndexDefinition, Record, Schema},
 };
 use serde::{Deserialize, Serialize};
 
@@ -16,10 +19,44 @@ pub struct AccessFilter {
     /// FilterExpression to evaluate access
     pub filter: Option<FilterExpression>,
 
-    /// Fields to be restricted
+    /// Fields that can be read. All fields can be read if it's empty.
     pub fields: Vec<String>,
 }
 
+impl AccessFilter {
+    /// Fails if the field named `name` can't be read.
+    pub fn check_field(&self, name: &str) -> Result<(), CacheError> {
+        if self.fields.is_empty() || self.fields.iter().any(|field| field == name) {
+            Ok(())
+        } else {
+            Err(CacheError::FieldNotPermitted(name.to_string()))
+        }
+    }
+
+    /// Fails if `filter` uses a field that can't be read, as its results would reveal the values.
+    pub fn check_filter(&self, filter: &FilterExpression) -> Result<(), CacheError> {
+        match filter {
+            FilterExpression::Simple(name, _, _) => self.check_field(name),
+            FilterExpression::And(filters) | FilterExpression::Or(filters) => filters
+                .iter()
+                .try_for_each(|filter| self.check_filter(filter)),
+            FilterExpression::Not(filter) => self.check_filter(filter),
+        }
+    }
+
+    /// Fails if the filter or sort options of `query` use a field that can't be read.
+    fn check_query(&self, query: &QueryExpression) -> Result<(), CacheError> {
+        if let Some(filter) = &query.filter {
+            self.check_filter(filter)?;
+        }
+        query
+            .order_by
+            .0
+            .iter()
+            .try_for_each(|option| self.check_field(&option.field_name))
+    }
+}
+
 #[derive(Debug)]
 /// CacheReader dynamically attaches permissions on top of queries
 pub struct CacheReader {
@@ -31,9 +68,22 @@ impl CacheReader {
         Self { cache }
     }
 
-    // TODO: Implement check_access
-    fn check_access(&self, _rec: &Record, _access_filter: &AccessFilter) -> Result<(), CacheError> {
-        Ok(())
+    /// Checks that `record` satisfies the filter of `access_filter`.
+    fn check_access(
+        &self,
+        record: &Record,
+        schema: &Schema,
+        secondary_indexes: &[IndexDefinition],
+        access_filter: &AccessFilter,
+    ) -> Result<(), CacheError> {
+        let Some(filter) = &access_filter.filter else {
+            return Ok(());
+        };
+        if RecordFilter::new(schema, secondary_indexes, filter)?.matches(record) {
+            Ok(())
+        } else {
+            Err(CacheError::RecordNotPermitted)
+        }
     }
 
     pub fn get_schema_and_indexes_by_name(
@@ -43,26 +93,39 @@ impl CacheReader {
         self.cache.get_schema_and_indexes_by_name(name)
     }
 
+    /// Returns the record with the fields that can be read, and their schema, if the record
+    /// satisfies the access filter.
     pub fn get(
         &self,
         key: &[u8],
         access_filter: &AccessFilter,
-    ) -> Result<RecordWithId, CacheError> {
+    ) -> Result<(Schema, RecordWithId), CacheError> {
         let record = self.cache.get(key)?;
-        match self.check_access(&record.record, access_filter) {
-            Ok(_) => Ok(record),
-            Err(e) => Err(e),
-        }
+        let (schema, secondary_indexes) = self.cache.get_schema_and_indexes(
+            record
+                .record
+                .schema_id
+                .ok_or(CacheError::SchemaHasNoIdentifier)?,
+        )?;
+        self.check_access(&record.record, schema, secondary_indexes, access_filter)?;
+        let projection = Projection::new(schema, None, access_filter)?;
+        let (schema, mut records) = projection.apply(schema, vec![record]);
+        Ok((schema, records.pop().expect("one record is projected")))
     }
 
+    /// Returns the records with the `$select` fields that can be read, and their schema.
     pub fn query(
         &self,
         schema_name: &str,
         query: &mut QueryExpression,
         access_filter: AccessFilter,
-    ) -> Result<(&Schema, Vec<RecordWithId>), CacheError> {
-        self.apply_access_filter(query, access_filter);
-        self.cache.query(schema_name, query)
+    ) -> Result<(Schema, Vec<RecordWithId>), CacheError> {
+        let (schema, _) = self.cache.get_schema_and_indexes_by_name(schema_name)?;
+        let projection = Projection::new(schema, query.select.as_deref(), &access_filter)?;
+        access_filter.check_query(query)?;
+        apply_access_filter(&mut query.filter, access_filter);
+        let (schema, records) = self.cache.query(schema_name, query)?;
+        Ok(projection.apply(schema, records))
     }
 
     pub fn count(
@@ -71,20 +134,160 @@ impl CacheReader {
         query: &mut QueryExpression,
         access_filter: AccessFilter,
     ) -> Result<usize, CacheError> {
-        self.apply_access_filter(query, access_filter);
+        access_filter.check_query(query)?;
+        apply_access_filter(&mut query.filter, access_filter);
         self.cache.count(schema_name, query)
     }
 
-    // Apply filter if specified in access
-    fn apply_access_filter(&self, query: &mut QueryExpression, access_filter: AccessFilter) {
-        // TODO: Use `fields` in `access_filter`.
-        if let Some(access_filter) = access_filter.filter {
-            let filter = match query.filter.take() {
-                Some(query_filter) => FilterExpression::And(vec![access_filter, query_filter]),
-                None => access_filter,
-            };
+    pub fn aggregate(
+        &self,
+        schema_name: &str,
+        expression: &mut AggregateExpression,
+        access_filter: AccessFilter,
+    ) -> Result<Vec<AggregateGroup>, CacheError> {
+        if let Some(filter) = &expression.filter {
+            access_filter.check_filter(filter)?;
+        }
+        for name in &expression.group_by {
+            access_filter.check_field(name)?;
+        }
+        for aggregate in &expression.aggregates {
+            match aggregate {
+                Aggregate::Count => (),
+                Aggregate::Sum(name)
+                | Aggregate::Avg(name)
+                | Aggregate::Min(name)
+                | Aggregate::Max(name) => access_filter.check_field(name)?,
+            }
+        }
+        apply_access_filter(&mut expression.filter, access_filter);
+        self.cache.aggregate(schema_name, expression)
+    }
+
+    /// Returns the versions of the record satisfying the access filter, oldest first, with the
+    /// fields that can be read, and their schema.
+    pub fn history(
+        &self,
+        schema_name: &str,
+        key: &[u8],
+        access_filter: &AccessFilter,
+    ) -> Result<(Schema, Vec<RecordVersion>), CacheError> {
+        let (schema, secondary_indexes) = self.cache.get_schema_and_indexes_by_name(schema_name)?;
+        let mut versions = self.cache.history(schema_name, key)?;
+        if let Some(filter) = &access_filter.filter {
+            let filter = RecordFilter::new(schema, secondary_indexes, filter)?;
+            let num_versions = versions.len();
+            versions.retain(|version| filter.matches(&version.record.record));
+            if versions.is_empty() && num_versions > 0 {
+                return Err(CacheError::RecordNotPermitted);
+            }
+        }
+
+        let projection = Projection::new(schema, None, access_filter)?;
+        let (validity, records): (Vec<_>, Vec<_>) = versions
+            .into_iter()
+            .map(|version| ((version.valid_from, version.valid_to), version.record))
+            .unzip();
+        let (schema, records) = projection.apply(schema, records);
+        let versions = records
+            .into_iter()
+            .zip(validity)
+            .map(|(record, (valid_from, valid_to))| RecordVersion {
+                record,
+                valid_from,
+                valid_to,
+            })
+            .collect();
+        Ok((schema, versions))
+    }
 
-            query.filter = Some(filter);
+    /// Describes how the query, with the access filter applied, would be executed.
+    pub fn explain(
+        &self,
+        schema_name: &str,
+        query: &mut QueryExpression,
+        access_filter: AccessFilter,
+    ) -> Result<String, CacheError> {
+        access_filter.check_query(query)?;
+        apply_access_filter(&mut query.filter, access_filter);
+        self.cache.explain(schema_name, query)
+    }
+}
+
+// Apply filter if specified in access. Records are matched against it like `check_access` does,
+// as the planner resolves it to the same `RecordFilter`.
+fn apply_access_filter(filter: &mut Option<FilterExpression>, access_filter: AccessFilter) {
+    if let Some(access_filter) = access_filter.filter {
+        *filter = Some(match filter.take() {
+            Some(query_filter) => FilterExpression::And(vec![access_filter, query_filter]),
+            None => access_filter,
+        });
+    }
+}
+
+/// Positions of the fields returned by a query, in schema order.
+struct Projection(Option<Vec<usize>>);
+
+impl Projection {
+    /// Selects `select`, or all fields if it's `None`. Selected fields must be readable with
+    /// `access_filter`, and only readable fields are selected by default.
+    fn new(
+        schema: &Schema,
+        select: Option<&[String]>,
+        access_filter: &AccessFilter,
+    ) -> Result<Self, CacheError> {
+        if let Some(select) = select {
+            for name in select {
+                if !schema.fields.iter().any(|field| field.name == *name) {
+                    return Err(PlanError::FieldNotFound(name.clone()).into());
+                }
+                access_filter.check_field(name)?;
+            }
+        } else if access_filter.fields.is_empty() {
+            return Ok(Self(None));
         }
+
+        let selected = select.unwrap_or(&access_filter.fields);
+        Ok(Self(Some(
+            schema
+                .fields
+                .iter()
+                .enumerate()
+                .filter(|(_, field)| selected.contains(&field.name))
+                .map(|(index, _)| index)
+                .collect(),
+        )))
+    }
+
+    /// Returns the schema of the selected fields, and the records with their values.
+    fn apply(&self, schema: &Schema, records: Vec<RecordWithId>) -> (Schema, Vec<RecordWithId>) {
+        let Some(positions) = &self.0 else {
+            return (schema.clone(), records);
+        };
+
+        let projected_schema = Schema {
+            identifier: schema.identifier,
+            fields: positions
+                .iter()
+                .map(|position| schema.fields[*position].clone())
+                .collect(),
+            primary_index: schema
+                .primary_index
+                .iter()
+                .filter_map(|index| positions.iter().position(|position| position == index))
+                .collect(),
+        };
+        let records = records
+            .into_iter()
+            .map(|mut record| {
+                let mut values = std::mem::take(&mut record.record.values);
+                record.record.values = positions
+                    .iter()
+                    .map(|position| std::mem::replace(&mut values[*position], Field::Null))
+                    .collect();
+                record
+            })
+            .collect();
+        (projected_schema, records)
     }
 }
@@ -70,6 +70,16 @@ pub enum ExecutionError {
         expected: Vec<String>,
         actual: Vec<String>,
     },
+    #[error("Invalid secondary index for `{endpoint_name}`: {reason}")]
+    InvalidSecondaryIndex {
+        endpoint_name: String,
+        reason: String,
+    },
+    #[error("Invalid cache retention for `{endpoint_name}`: {reason}")]
+    InvalidCacheRetention {
+        endpoint_name: String,
+        reason: String,
+    },
 
     // Error forwarders
     #[error(transparent)]
@@ -166,6 +176,15 @@ pub enum SinkError {
 
     #[error("Failed to count thre records during init in Cache: {0:?}, Error: {1:?}")]
     CacheCountFailed(String, #[source] BoxedError),
+
+    #[error("Failed to set retention policy of Cache: {0:?}, Error: {1:?}")]
+    CacheRetentionFailed(String, #[source] BoxedError),
+
+    #[error("Failed to set history of Cache: {0:?}, Error: {1:?}")]
+    CacheHistoryFailed(String, #[source] BoxedError),
+
+    #[error("Failed to rebuild Cache with the configured secondary indexes: {0:?}, Error: {1:?}")]
+    CacheRebuildFailed(String, #[source] BoxedError),
 }
 
 #[derive(Error, Debug)]
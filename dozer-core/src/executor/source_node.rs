@@ -35,7 +35,7 @@ pub struct SourceSenderNode {
     /// Node handle in description DAG.
     node_handle: NodeHandle,
     /// The source.
-    source: Box<dyn Source>,
+    source: Arc<dyn Source>,
     /// Last checkpointed output data sequence number.
     last_checkpoint: Option<OpIdentifier>,
     /// The forwarder that will be passed to the source for outputting data.
@@ -153,6 +153,7 @@ pub fn create_source_nodes(
     let NodeKind::Source(source, last_checkpoint) = node.kind else {
         panic!("Must pass in a source node");
     };
+    let source: Arc<dyn Source> = source.into();
 
     // Create channel between source sender and source listener.
     let (source_sender, source_receiver) = bounded(options.channel_buffer_sz);
@@ -162,7 +163,7 @@ pub fn create_source_nodes(
     let forwarder = InternalChannelSourceForwarder::new(source_sender);
     let source_sender_node = SourceSenderNode {
         node_handle: node_handle.clone(),
-        source,
+        source: source.clone(),
         last_checkpoint,
         forwarder,
     };
@@ -182,6 +183,7 @@ pub fn create_source_nodes(
         options.commit_sz,
         options.commit_time_threshold,
         dag.epoch_manager().clone(),
+        source,
     );
     let source_listener_node = SourceListenerNode {
         node_handle,
@@ -3,7 +3,7 @@ use crate::epoch::{Epoch, EpochManager};
 use crate::errors::ExecutionError;
 use crate::errors::ExecutionError::InvalidPortHandle;
 use crate::executor::ExecutorOperation;
-use crate::node::PortHandle;
+use crate::node::{PortHandle, Source};
 use crate::record_store::RecordWriter;
 use dozer_storage::common::Database;
 
@@ -153,6 +153,7 @@ pub(crate) struct SourceChannelManager {
     max_duration_between_commits: Duration,
     last_commit_instant: Instant,
     epoch_manager: Arc<EpochManager>,
+    source: Arc<dyn Source>,
 }
 
 impl SourceChannelManager {
@@ -165,6 +166,7 @@ impl SourceChannelManager {
         commit_sz: u32,
         max_duration_between_commits: Duration,
         epoch_manager: Arc<EpochManager>,
+        source: Arc<dyn Source>,
     ) -> Self {
         Self {
             manager: ChannelManager::new(owner.clone(), senders, state_writer, stateful),
@@ -177,6 +179,7 @@ impl SourceChannelManager {
             max_duration_between_commits,
             last_commit_instant: Instant::now(),
             epoch_manager,
+            source,
         }
     }
 
@@ -196,6 +199,10 @@ impl SourceChannelManager {
                 self.curr_txid,
                 self.curr_seq_in_tx,
             ))?;
+            // Before the first message of the source, the identifiers are not those of a message.
+            if self.num_uncommitted_ops > 0 {
+                self.source.commit((self.curr_txid, self.curr_seq_in_tx))?;
+            }
         }
         self.num_uncommitted_ops = 0;
         self.last_commit_instant = decision_instant;
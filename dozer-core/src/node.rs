@@ -63,6 +63,11 @@ pub trait Source: Send + Sync + Debug {
         fw: &mut dyn SourceChannelForwarder,
         last_checkpoint: Option<(u64, u64)>,
     ) -> Result<(), ExecutionError>;
+    /// Called once the messages up to `checkpoint` are committed by the source. The executor
+    /// will not start the source from an earlier checkpoint.
+    fn commit(&self, _checkpoint: (u64, u64)) -> Result<(), ExecutionError> {
+        Ok(())
+    }
 }
 
 pub trait ProcessorFactory<T>: Send + Sync + Debug {
@@ -0,0 +1,451 @@
+use std::collections::HashMap;
+
+use super::super::block_tracker::BlockTracker;
+use super::super::helper as conn_helper;
+use super::super::trace::BatchIterator;
+use super::helper::{self, ETH_BLOCKS_TABLE, ETH_RECEIPTS_TABLE, ETH_TRANSACTIONS_TABLE};
+use crate::connectors::ValidationResults;
+use crate::{
+    connectors::{Connector, TableInfo},
+    errors::ConnectorError,
+    ingestion::Ingestor,
+};
+use dozer_types::ingestion_types::{EthBlockConfig, IngestionMessage};
+use dozer_types::log::{debug, info};
+use dozer_types::types::{Operation, Record, ReplicationChangesTrackingType, SourceSchema};
+use futures::future::join_all;
+use futures::StreamExt;
+use tokio::runtime::Runtime;
+use web3::transports::{Batch, WebSocket};
+use web3::types::{Block, BlockId, BlockNumber, Transaction, TransactionReceipt};
+use web3::{Transport, Web3};
+
+#[derive(Debug)]
+pub struct EthBlockConnector {
+    pub id: u64,
+    pub config: EthBlockConfig,
+    pub conn_name: String,
+}
+
+impl EthBlockConnector {
+    pub fn new(id: u64, config: EthBlockConfig, conn_name: String) -> Self {
+        Self {
+            id,
+            config,
+            conn_name,
+        }
+    }
+}
+
+impl Connector for EthBlockConnector {
+    fn get_schemas(
+        &self,
+        tables: Option<Vec<TableInfo>>,
+    ) -> Result<Vec<SourceSchema>, ConnectorError> {
+        let schemas = vec![
+            SourceSchema::new(
+                ETH_BLOCKS_TABLE.to_string(),
+                helper::get_block_schema(),
+                ReplicationChangesTrackingType::FullChanges,
+            ),
+            SourceSchema::new(
+                ETH_TRANSACTIONS_TABLE.to_string(),
+                helper::get_transaction_schema(),
+                ReplicationChangesTrackingType::FullChanges,
+            ),
+            SourceSchema::new(
+                ETH_RECEIPTS_TABLE.to_string(),
+                helper::get_receipt_schema(),
+                ReplicationChangesTrackingType::FullChanges,
+            ),
+        ];
+
+        let schemas = if let Some(tables) = tables {
+            schemas
+                .into_iter()
+                .filter(|s| tables.iter().any(|t| t.table_name == s.name))
+                .collect()
+        } else {
+            schemas
+        };
+
+        Ok(schemas)
+    }
+
+    fn start(
+        &self,
+        from_seq: Option<(u64, u64)>,
+        ingestor: &Ingestor,
+        tables: Vec<TableInfo>,
+    ) -> Result<(), ConnectorError> {
+        let config = self.config.clone();
+        let conn_name = self.conn_name.clone();
+        Runtime::new().unwrap().block_on(async {
+            run(
+                ingestor,
+                config,
+                RequestedTables::new(&tables),
+                from_seq,
+                conn_name,
+            )
+            .await
+        })
+    }
+
+    fn validate(&self, _tables: Option<Vec<TableInfo>>) -> Result<(), ConnectorError> {
+        let wss_url = self.config.wss_url.clone();
+        Runtime::new().unwrap().block_on(async {
+            let client = conn_helper::get_wss_client(&wss_url)
+                .await
+                .map_err(ConnectorError::EthError)?;
+            client
+                .eth()
+                .block_number()
+                .await
+                .map_err(ConnectorError::EthError)?;
+            Ok(())
+        })
+    }
+
+    fn validate_schemas(&self, _tables: &[TableInfo]) -> Result<ValidationResults, ConnectorError> {
+        Ok(HashMap::new())
+    }
+
+    fn get_tables(&self, tables: Option<&[TableInfo]>) -> Result<Vec<TableInfo>, ConnectorError> {
+        self.get_tables_default(tables)
+    }
+
+    fn can_start_from(&self, (block_no, _): (u64, u64)) -> Result<bool, ConnectorError> {
+        // The checkpoint holds the block of the last ingested record, reading resumes in that
+        // block as long as it is in the configured range.
+        Ok(block_no >= self.config.from_block
+            && self
+                .config
+                .to_block
+                .map_or(true, |to_block| block_no <= to_block))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RequestedTables {
+    pub blocks: bool,
+    pub transactions: bool,
+    pub receipts: bool,
+}
+
+impl RequestedTables {
+    pub fn new(tables: &[TableInfo]) -> Self {
+        let is_requested = |name: &str| tables.iter().any(|t| t.table_name == name);
+        Self {
+            blocks: is_requested(ETH_BLOCKS_TABLE),
+            transactions: is_requested(ETH_TRANSACTIONS_TABLE),
+            receipts: is_requested(ETH_RECEIPTS_TABLE),
+        }
+    }
+}
+
+pub async fn run(
+    ingestor: &Ingestor,
+    config: EthBlockConfig,
+    tables: RequestedTables,
+    from_seq: Option<(u64, u64)>,
+    conn_name: String,
+) -> Result<(), ConnectorError> {
+    let (client, transport) = conn_helper::get_batch_wss_client(&config.wss_url)
+        .await
+        .map_err(ConnectorError::EthError)?;
+    let live_client = Web3::new(transport);
+
+    // On resume, reading starts again in the block of the checkpoint, skipping ingested records.
+    let mut next_block = from_seq.map_or(config.from_block, |(block_no, _)| block_no);
+    let mut tracker = BlockTracker::new(from_seq);
+
+    let latest_block_no = live_client
+        .eth()
+        .block_number()
+        .await
+        .map_err(ConnectorError::EthError)?
+        .as_u64();
+    let backfill_end = config
+        .to_block
+        .map_or(latest_block_no, |to_block| to_block.min(latest_block_no));
+
+    info!(
+        "[{}] Backfilling blocks {} to {}",
+        conn_name, next_block, backfill_end
+    );
+    if next_block <= backfill_end {
+        ingest_blocks(
+            &client,
+            ingestor,
+            &mut tracker,
+            tables,
+            (next_block, backfill_end),
+            config.batch_size,
+            from_seq,
+        )
+        .await?;
+        next_block = backfill_end + 1;
+    }
+
+    // Then follow the new blocks as they are mined.
+    let mut heads = live_client
+        .eth_subscribe()
+        .subscribe_new_heads()
+        .await
+        .map_err(ConnectorError::EthError)?;
+    loop {
+        if config
+            .to_block
+            .map_or(false, |to_block| next_block > to_block)
+        {
+            info!("[{}] Reading reached block_to limit", conn_name);
+            return Ok(());
+        }
+
+        let head = heads
+            .next()
+            .await
+            .map_or(Err(ConnectorError::EmptyMessage), Ok)?
+            .map_err(ConnectorError::EthError)?;
+        let Some(head_no) = head.number.map(|n| n.as_u64()) else {
+            continue;
+        };
+
+        // A head which does not extend the ingested chain replaced some of its blocks, whose
+        // records are retracted before the blocks are ingested again.
+        if let Some(last_block) = next_block.checked_sub(1) {
+            let last_block = last_block.min(head_no);
+            if let Some(first_orphaned) = first_orphaned(&live_client, &tracker, last_block).await?
+            {
+                info!(
+                    "[{}] Blocks from {} were reorganized",
+                    conn_name, first_orphaned
+                );
+                retract(ingestor, &mut tracker, first_orphaned)?;
+                next_block = first_orphaned;
+            }
+        }
+
+        let block_end = config
+            .to_block
+            .map_or(head_no, |to_block| to_block.min(head_no));
+        if block_end >= next_block {
+            debug!(
+                "[{}] Fetching blocks {} to {}",
+                conn_name, next_block, block_end
+            );
+            ingest_blocks(
+                &client,
+                ingestor,
+                &mut tracker,
+                tables,
+                (next_block, block_end),
+                config.batch_size,
+                from_seq,
+            )
+            .await?;
+            next_block = block_end + 1;
+        }
+    }
+}
+
+/// Returns the first block of the ingested chain up to `last_block` which is not in the
+/// canonical chain anymore, comparing the hashes of the tracked blocks from the latest.
+async fn first_orphaned<T: Transport>(
+    client: &Web3<T>,
+    tracker: &BlockTracker,
+    last_block: u64,
+) -> Result<Option<u64>, ConnectorError> {
+    let mut first_orphaned = None;
+    let mut block_no = last_block;
+    while let Some(hash) = tracker.hash(block_no) {
+        let canonical = client
+            .eth()
+            .block(BlockId::Number(BlockNumber::Number(block_no.into())))
+            .await
+            .map_err(ConnectorError::EthError)?
+            .and_then(|block| block.hash);
+        if canonical == Some(hash) {
+            break;
+        }
+        first_orphaned = Some(block_no);
+        match block_no.checked_sub(1) {
+            Some(previous) => block_no = previous,
+            None => break,
+        }
+    }
+    Ok(first_orphaned)
+}
+
+/// Deletes the records of the blocks from `first_orphaned`, latest first.
+fn retract(
+    ingestor: &Ingestor,
+    tracker: &mut BlockTracker,
+    first_orphaned: u64,
+) -> Result<(), ConnectorError> {
+    let messages = tracker
+        .orphaned_from(first_orphaned)
+        .into_iter()
+        .map(|old| {
+            let (block_no, seq_in_tx) = tracker.next_id((first_orphaned, 0));
+            IngestionMessage::new_op(block_no, seq_in_tx, Operation::Delete { old })
+        })
+        .collect::<Vec<_>>();
+    if messages.is_empty() {
+        return Ok(());
+    }
+    ingestor
+        .handle_batch(messages)
+        .map_err(ConnectorError::IngestorError)
+}
+
+/// Ingests the blocks from `range.0` to `range.1` included, requested in batches. The records of
+/// the blocks are tracked until they are final.
+#[allow(clippy::too_many_arguments)]
+async fn ingest_blocks(
+    client: &Web3<Batch<WebSocket>>,
+    ingestor: &Ingestor,
+    tracker: &mut BlockTracker,
+    tables: RequestedTables,
+    range: (u64, u64),
+    batch_size: u64,
+    from_seq: Option<(u64, u64)>,
+) -> Result<(), ConnectorError> {
+    for batch in BatchIterator::new(range.0, Some(range.1), batch_size.max(1)) {
+        let blocks = get_blocks(client, batch).await?;
+        let receipts = if tables.receipts {
+            get_receipts(client, &blocks).await?
+        } else {
+            vec![vec![]; blocks.len()]
+        };
+
+        let mut messages = vec![];
+        for (block, receipts) in blocks.iter().zip(receipts) {
+            let block_no = block.number.expect("expected for non pending").as_u64();
+            let records = map_block_records(block, &receipts, tables)?;
+            tracker.track(
+                block_no,
+                block.hash,
+                0,
+                records.iter().map(|(_, record)| record.clone()).collect(),
+            );
+            for (seq_in_tx, record) in records {
+                // Skip what was ingested before the checkpoint.
+                if from_seq.map_or(false, |from_seq| (block_no, seq_in_tx) <= from_seq) {
+                    continue;
+                }
+
+                let (block_no, seq_in_tx) = tracker.next_id((block_no, seq_in_tx));
+                messages.push(IngestionMessage::new_op(
+                    block_no,
+                    seq_in_tx,
+                    Operation::Insert { new: record },
+                ));
+            }
+        }
+        ingestor
+            .handle_batch(messages)
+            .map_err(ConnectorError::IngestorError)?;
+    }
+
+    Ok(())
+}
+
+/// Returns the requested records of a block, with their sequence number in the block: the block
+/// comes first, followed by each transaction and its receipt.
+pub fn map_block_records(
+    block: &Block<Transaction>,
+    receipts: &[TransactionReceipt],
+    tables: RequestedTables,
+) -> Result<Vec<(u64, Record)>, ConnectorError> {
+    let mut records = vec![];
+    if tables.blocks {
+        records.push((0, helper::map_block(block)?));
+    }
+    if tables.transactions {
+        for (idx, transaction) in block.transactions.iter().enumerate() {
+            records.push((1 + 2 * idx as u64, helper::map_transaction(transaction)?));
+        }
+    }
+    for receipt in receipts {
+        records.push((
+            2 + 2 * receipt.transaction_index.as_u64(),
+            helper::map_receipt(receipt)?,
+        ));
+    }
+
+    records.sort_by_key(|(seq_in_tx, _)| *seq_in_tx);
+    Ok(records)
+}
+
+/// Requests the blocks of `batch`, its end excluded, with their transactions in a single call.
+async fn get_blocks(
+    client: &Web3<Batch<WebSocket>>,
+    batch: (u64, u64),
+) -> Result<Vec<Block<Transaction>>, ConnectorError> {
+    let (from, to) = batch;
+    debug!("Getting eth blocks for block range: {:?}", batch);
+    let requests: Vec<_> = (from..to)
+        .map(|block_no| {
+            client
+                .eth()
+                .block_with_txs(BlockId::Number(BlockNumber::Number(block_no.into())))
+        })
+        .collect();
+    client
+        .transport()
+        .submit_batch()
+        .await
+        .map_err(ConnectorError::EthError)?;
+
+    join_all(requests)
+        .await
+        .into_iter()
+        .zip(from..to)
+        .map(|(block, block_no)| {
+            block
+                .map_err(ConnectorError::EthError)?
+                .ok_or(ConnectorError::EthBlockNotFound(block_no))
+        })
+        .collect()
+}
+
+/// Requests the receipts of the transactions of the blocks in a single call.
+async fn get_receipts(
+    client: &Web3<Batch<WebSocket>>,
+    blocks: &[Block<Transaction>],
+) -> Result<Vec<Vec<TransactionReceipt>>, ConnectorError> {
+    let requests: Vec<Vec<_>> = blocks
+        .iter()
+        .map(|block| {
+            block
+                .transactions
+                .iter()
+                .map(|t| client.eth().transaction_receipt(t.hash))
+                .collect()
+        })
+        .collect();
+    client
+        .transport()
+        .submit_batch()
+        .await
+        .map_err(ConnectorError::EthError)?;
+
+    let mut receipts = vec![];
+    for (block, requests) in blocks.iter().zip(requests) {
+        let block_receipts = join_all(requests)
+            .await
+            .into_iter()
+            .zip(&block.transactions)
+            .map(|(receipt, transaction)| {
+                receipt.map_err(ConnectorError::EthError)?.ok_or_else(|| {
+                    ConnectorError::EthReceiptNotFound(format!("{:?}", transaction.hash))
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        receipts.push(block_receipts);
+    }
+
+    Ok(receipts)
+}
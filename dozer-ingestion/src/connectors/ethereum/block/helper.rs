@@ -0,0 +1,215 @@
+use dozer_types::chrono::{DateTime, NaiveDateTime, Offset, Utc};
+use dozer_types::rust_decimal::Decimal;
+use dozer_types::types::{
+    Field, FieldDefinition, FieldType, Record, Schema, SchemaIdentifier, SourceDefinition,
+};
+use std::str::FromStr;
+use web3::types::{Block, Transaction, TransactionReceipt, U256};
+
+use crate::errors::ConnectorError;
+
+pub const ETH_BLOCKS_TABLE: &str = "eth_blocks";
+pub const ETH_TRANSACTIONS_TABLE: &str = "eth_transactions";
+pub const ETH_RECEIPTS_TABLE: &str = "eth_receipts";
+
+fn get_block_schema_id() -> SchemaIdentifier {
+    SchemaIdentifier { id: 1, version: 1 }
+}
+
+fn get_transaction_schema_id() -> SchemaIdentifier {
+    SchemaIdentifier { id: 2, version: 1 }
+}
+
+fn get_receipt_schema_id() -> SchemaIdentifier {
+    SchemaIdentifier { id: 3, version: 1 }
+}
+
+fn field(name: &str, typ: FieldType, nullable: bool) -> FieldDefinition {
+    FieldDefinition {
+        name: name.to_string(),
+        typ,
+        nullable,
+        source: SourceDefinition::Dynamic,
+    }
+}
+
+pub fn get_block_schema() -> Schema {
+    Schema {
+        identifier: Some(get_block_schema_id()),
+        fields: vec![
+            field("number", FieldType::UInt, false),
+            field("hash", FieldType::String, true),
+            field("parent_hash", FieldType::String, false),
+            field("timestamp", FieldType::Timestamp, true),
+            field("miner", FieldType::String, false),
+            field("gas_used", FieldType::UInt, false),
+            field("gas_limit", FieldType::UInt, false),
+            field("base_fee_per_gas", FieldType::Decimal, true),
+            field("difficulty", FieldType::Decimal, true),
+            field("size", FieldType::UInt, true),
+            field("transaction_count", FieldType::UInt, false),
+        ],
+        primary_index: vec![0],
+    }
+}
+
+pub fn get_transaction_schema() -> Schema {
+    Schema {
+        identifier: Some(get_transaction_schema_id()),
+        fields: vec![
+            field("hash", FieldType::String, false),
+            field("block_number", FieldType::UInt, true),
+            field("transaction_index", FieldType::UInt, true),
+            field("from", FieldType::String, true),
+            field("to", FieldType::String, true),
+            field("value", FieldType::Decimal, true),
+            field("gas", FieldType::UInt, false),
+            field("gas_price", FieldType::Decimal, true),
+            field("max_fee_per_gas", FieldType::Decimal, true),
+            field("max_priority_fee_per_gas", FieldType::Decimal, true),
+            field("nonce", FieldType::UInt, false),
+            field("input", FieldType::Binary, false),
+            field("transaction_type", FieldType::UInt, true),
+        ],
+        primary_index: vec![0],
+    }
+}
+
+pub fn get_receipt_schema() -> Schema {
+    Schema {
+        identifier: Some(get_receipt_schema_id()),
+        fields: vec![
+            field("transaction_hash", FieldType::String, false),
+            field("block_number", FieldType::UInt, true),
+            field("transaction_index", FieldType::UInt, false),
+            field("status", FieldType::UInt, true),
+            field("gas_used", FieldType::UInt, true),
+            field("cumulative_gas_used", FieldType::UInt, false),
+            field("effective_gas_price", FieldType::Decimal, true),
+            field("contract_address", FieldType::String, true),
+            field("log_count", FieldType::UInt, false),
+        ],
+        primary_index: vec![0],
+    }
+}
+
+/// Maps amounts in wei, which overflow 64 bits, to decimals. Amounts above the decimal range
+/// are mapped to null.
+fn map_amount(amount: U256) -> Field {
+    Decimal::from_str(&amount.to_string()).map_or(Field::Null, Field::Decimal)
+}
+
+fn out_of_range(name: &str, value: U256) -> ConnectorError {
+    ConnectorError::EthValueOutOfRange(name.to_string(), value.to_string())
+}
+
+/// Maps prices in wei, which may overflow 64 bits, to decimals.
+fn map_price(name: &str, price: U256) -> Result<Field, ConnectorError> {
+    Decimal::from_str(&price.to_string())
+        .map(Field::Decimal)
+        .map_err(|_| out_of_range(name, price))
+}
+
+/// Maps quantities the protocol bounds to 64 bits, like gas and nonces.
+fn map_quantity(name: &str, quantity: U256) -> Result<Field, ConnectorError> {
+    u64::try_from(quantity)
+        .map(Field::UInt)
+        .map_err(|_| out_of_range(name, quantity))
+}
+
+fn map_optional(
+    value: Option<U256>,
+    map: impl FnOnce(U256) -> Result<Field, ConnectorError>,
+) -> Result<Field, ConnectorError> {
+    value.map_or(Ok(Field::Null), map)
+}
+
+fn map_timestamp(timestamp: U256) -> Field {
+    NaiveDateTime::from_timestamp_opt(timestamp.low_u64() as i64, 0).map_or(Field::Null, |t| {
+        Field::Timestamp(DateTime::from_utc(t, Utc.fix()))
+    })
+}
+
+pub fn map_block(block: &Block<Transaction>) -> Result<Record, ConnectorError> {
+    Ok(Record {
+        schema_id: Some(get_block_schema_id()),
+        values: vec![
+            Field::UInt(block.number.expect("expected for non pending").as_u64()),
+            block
+                .hash
+                .map_or(Field::Null, |h| Field::String(format!("{h:?}"))),
+            Field::String(format!("{:?}", block.parent_hash)),
+            map_timestamp(block.timestamp),
+            Field::String(format!("{:?}", block.author)),
+            map_quantity("gas_used", block.gas_used)?,
+            map_quantity("gas_limit", block.gas_limit)?,
+            map_optional(block.base_fee_per_gas, |f| map_price("base_fee_per_gas", f))?,
+            map_amount(block.difficulty),
+            map_optional(block.size, |s| map_quantity("size", s))?,
+            Field::UInt(block.transactions.len() as u64),
+        ],
+        version: None,
+    })
+}
+
+pub fn map_transaction(transaction: &Transaction) -> Result<Record, ConnectorError> {
+    Ok(Record {
+        schema_id: Some(get_transaction_schema_id()),
+        values: vec![
+            Field::String(format!("{:?}", transaction.hash)),
+            transaction
+                .block_number
+                .map_or(Field::Null, |n| Field::UInt(n.as_u64())),
+            transaction
+                .transaction_index
+                .map_or(Field::Null, |i| Field::UInt(i.as_u64())),
+            transaction
+                .from
+                .map_or(Field::Null, |a| Field::String(format!("{a:?}"))),
+            transaction
+                .to
+                .map_or(Field::Null, |a| Field::String(format!("{a:?}"))),
+            map_amount(transaction.value),
+            map_quantity("gas", transaction.gas)?,
+            map_optional(transaction.gas_price, |p| map_price("gas_price", p))?,
+            map_optional(transaction.max_fee_per_gas, |p| {
+                map_price("max_fee_per_gas", p)
+            })?,
+            map_optional(transaction.max_priority_fee_per_gas, |p| {
+                map_price("max_priority_fee_per_gas", p)
+            })?,
+            map_quantity("nonce", transaction.nonce)?,
+            Field::Binary(transaction.input.0.clone()),
+            transaction
+                .transaction_type
+                .map_or(Field::Null, |t| Field::UInt(t.as_u64())),
+        ],
+        version: None,
+    })
+}
+
+pub fn map_receipt(receipt: &TransactionReceipt) -> Result<Record, ConnectorError> {
+    Ok(Record {
+        schema_id: Some(get_receipt_schema_id()),
+        values: vec![
+            Field::String(format!("{:?}", receipt.transaction_hash)),
+            receipt
+                .block_number
+                .map_or(Field::Null, |n| Field::UInt(n.as_u64())),
+            Field::UInt(receipt.transaction_index.as_u64()),
+            receipt
+                .status
+                .map_or(Field::Null, |s| Field::UInt(s.as_u64())),
+            map_optional(receipt.gas_used, |g| map_quantity("gas_used", g))?,
+            map_quantity("cumulative_gas_used", receipt.cumulative_gas_used)?,
+            map_optional(receipt.effective_gas_price, |p| {
+                map_price("effective_gas_price", p)
+            })?,
+            receipt
+                .contract_address
+                .map_or(Field::Null, |a| Field::String(format!("{a:?}"))),
+            Field::UInt(receipt.logs.len() as u64),
+        ],
+        version: None,
+    })
+}
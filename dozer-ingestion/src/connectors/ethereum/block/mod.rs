@@ -0,0 +1,5 @@
+mod connector;
+pub mod helper;
+pub use connector::EthBlockConnector;
+#[cfg(test)]
+mod tests;
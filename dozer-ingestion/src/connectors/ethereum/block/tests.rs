@@ -0,0 +1,151 @@
+use dozer_types::ingestion_types::EthBlockConfig;
+use dozer_types::rust_decimal::Decimal;
+use dozer_types::types::Field;
+use web3::types::{Block, Transaction, TransactionReceipt, H256, U256, U64};
+
+use crate::connectors::ethereum::block::helper;
+use crate::connectors::ethereum::EthBlockConnector;
+use crate::connectors::Connector;
+use crate::errors::ConnectorError;
+
+use super::connector::{map_block_records, RequestedTables};
+
+fn block() -> Block<Transaction> {
+    let transactions = (0..2)
+        .map(|idx| Transaction {
+            hash: H256::from_low_u64_be(idx + 1),
+            block_number: Some(U64::from(10)),
+            transaction_index: Some(U64::from(idx)),
+            value: U256::exp10(20),
+            ..Default::default()
+        })
+        .collect();
+    Block {
+        number: Some(U64::from(10)),
+        hash: Some(H256::from_low_u64_be(10)),
+        timestamp: U256::from(1_672_531_200),
+        transactions,
+        ..Default::default()
+    }
+}
+
+fn receipts() -> Vec<TransactionReceipt> {
+    (0..2)
+        .map(|idx| TransactionReceipt {
+            transaction_hash: H256::from_low_u64_be(idx + 1),
+            transaction_index: U64::from(idx),
+            block_number: Some(U64::from(10)),
+            status: Some(U64::from(1)),
+            ..Default::default()
+        })
+        .collect()
+}
+
+#[test]
+fn test_it_maps_records_to_schemas() {
+    let block = block();
+    let receipts = receipts();
+
+    let record = helper::map_block(&block).unwrap();
+    assert_eq!(record.values.len(), helper::get_block_schema().fields.len());
+    assert_eq!(record.values[0], Field::UInt(10));
+    assert_eq!(record.values[10], Field::UInt(2));
+
+    let record = helper::map_transaction(&block.transactions[0]).unwrap();
+    assert_eq!(
+        record.values.len(),
+        helper::get_transaction_schema().fields.len()
+    );
+    // 100 ether in wei does not fit in 64 bits.
+    assert_eq!(
+        record.values[5],
+        Field::Decimal(Decimal::from_i128_with_scale(
+            100_000_000_000_000_000_000,
+            0
+        ))
+    );
+
+    let record = helper::map_receipt(&receipts[1]).unwrap();
+    assert_eq!(
+        record.values.len(),
+        helper::get_receipt_schema().fields.len()
+    );
+    assert_eq!(record.values[2], Field::UInt(1));
+    assert_eq!(record.values[3], Field::UInt(1));
+}
+
+#[test]
+fn test_it_maps_large_quantities() {
+    // Prices in wei overflow 64 bits.
+    let transaction = Transaction {
+        max_fee_per_gas: Some(U256::exp10(21)),
+        ..Default::default()
+    };
+    let record = helper::map_transaction(&transaction).unwrap();
+    assert_eq!(
+        record.values[8],
+        Field::Decimal(Decimal::from_i128_with_scale(
+            1_000_000_000_000_000_000_000,
+            0
+        ))
+    );
+
+    // Gas is bounded to 64 bits, and is not truncated when it overflows.
+    let transaction = Transaction {
+        gas: U256::from(u64::MAX) + 1,
+        ..Default::default()
+    };
+    assert!(matches!(
+        helper::map_transaction(&transaction),
+        Err(ConnectorError::EthValueOutOfRange(name, _)) if name == "gas"
+    ));
+}
+
+#[test]
+fn test_it_orders_records_in_block() {
+    let block = block();
+    let receipts = receipts();
+
+    let all = RequestedTables {
+        blocks: true,
+        transactions: true,
+        receipts: true,
+    };
+    let seqs: Vec<u64> = map_block_records(&block, &receipts, all)
+        .unwrap()
+        .into_iter()
+        .map(|(seq, _)| seq)
+        .collect();
+    assert_eq!(seqs, vec![0, 1, 2, 3, 4]);
+
+    let transactions_only = RequestedTables {
+        blocks: false,
+        transactions: true,
+        receipts: false,
+    };
+    let seqs: Vec<u64> = map_block_records(&block, &[], transactions_only)
+        .unwrap()
+        .into_iter()
+        .map(|(seq, _)| seq)
+        .collect();
+    assert_eq!(seqs, vec![1, 3]);
+}
+
+#[test]
+fn test_it_resumes_from_blocks_in_range() {
+    let connector = EthBlockConnector::new(
+        1,
+        EthBlockConfig {
+            wss_url: "ws://localhost:8545".to_string(),
+            from_block: 100,
+            to_block: Some(200),
+            batch_size: 10,
+        },
+        "eth_blocks".to_string(),
+    );
+
+    assert!(!connector.can_start_from((99, 0)).unwrap());
+    assert!(connector.can_start_from((100, 0)).unwrap());
+    assert!(connector.can_start_from((200, 4)).unwrap());
+    assert!(!connector.can_start_from((201, 0)).unwrap());
+}
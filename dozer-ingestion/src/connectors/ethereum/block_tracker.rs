@@ -0,0 +1,189 @@
+use dozer_types::types::Record;
+use std::collections::BTreeMap;
+use web3::types::H256;
+
+/// Number of blocks behind the latest tracked block which can still be reorganized.
+pub const MAX_REORG_DEPTH: u64 = 64;
+
+#[derive(Debug)]
+struct TrackedBlock {
+    hash: Option<H256>,
+    // log_index -> records ingested for the log, or a single entry for the records of the block
+    logs: BTreeMap<u64, Vec<Record>>,
+}
+
+/// Keeps the records ingested for the latest blocks, so that they can be retracted when their
+/// block gets orphaned by a chain reorganization.
+#[derive(Debug, Default)]
+pub struct BlockTracker {
+    blocks: BTreeMap<u64, TrackedBlock>,
+    // Identifier of the last ingested message
+    last_id: Option<(u64, u64)>,
+}
+
+impl BlockTracker {
+    pub fn new(last_id: Option<(u64, u64)>) -> Self {
+        Self {
+            blocks: BTreeMap::new(),
+            last_id,
+        }
+    }
+
+    /// Returns the identifier of the next ingested message, `id` unless it is not after the last
+    /// one, as a reorganization goes back to earlier blocks. Those messages follow the last one.
+    pub fn next_id(&mut self, id: (u64, u64)) -> (u64, u64) {
+        let id = match self.last_id {
+            Some((block_no, seq_in_tx)) if id <= (block_no, seq_in_tx) => (block_no, seq_in_tx + 1),
+            _ => id,
+        };
+        self.last_id = Some(id);
+        id
+    }
+
+    /// Hash of a tracked block.
+    pub fn hash(&self, block_no: u64) -> Option<H256> {
+        self.blocks.get(&block_no).and_then(|block| block.hash)
+    }
+
+    pub fn contains(&self, block_no: u64, log_index: u64) -> bool {
+        self.blocks
+            .get(&block_no)
+            .map_or(false, |block| block.logs.contains_key(&log_index))
+    }
+
+    pub fn track(
+        &mut self,
+        block_no: u64,
+        hash: Option<H256>,
+        log_index: u64,
+        records: Vec<Record>,
+    ) {
+        self.blocks
+            .entry(block_no)
+            .or_insert_with(|| TrackedBlock {
+                hash,
+                logs: BTreeMap::new(),
+            })
+            .logs
+            .insert(log_index, records);
+
+        // Blocks buried deep enough are final.
+        let first_kept = block_no.saturating_sub(MAX_REORG_DEPTH);
+        self.blocks = self.blocks.split_off(&first_kept);
+    }
+
+    /// Forgets a log removed from the chain, returning the records ingested for it.
+    pub fn remove(&mut self, block_no: u64, log_index: u64) -> Option<Vec<Record>> {
+        let block = self.blocks.get_mut(&block_no)?;
+        let records = block.logs.remove(&log_index);
+        if block.logs.is_empty() {
+            self.blocks.remove(&block_no);
+        }
+        records
+    }
+
+    /// Returns the records of the logs orphaned by a log of the block `block_no` with `hash`,
+    /// latest first.
+    ///
+    /// Logs are received in block order, so a log of a block known with another hash orphans
+    /// that block and the following ones, and a log of an earlier block than the tracked ones
+    /// orphans the following blocks.
+    pub fn orphaned(&mut self, block_no: u64, hash: Option<H256>) -> Vec<Record> {
+        let first_orphaned = match self.blocks.get(&block_no) {
+            Some(block) if hash.is_some() && block.hash.is_some() && block.hash != hash => block_no,
+            _ => block_no + 1,
+        };
+        self.orphaned_from(first_orphaned)
+    }
+
+    /// Forgets the blocks from `block_no`, which were orphaned, returning their records latest
+    /// first.
+    pub fn orphaned_from(&mut self, block_no: u64) -> Vec<Record> {
+        let orphaned = self.blocks.split_off(&block_no);
+        orphaned
+            .into_values()
+            .rev()
+            .flat_map(|block| block.logs.into_values().rev())
+            .flat_map(|records| records.into_iter().rev())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::connectors::ethereum::block_tracker::{BlockTracker, MAX_REORG_DEPTH};
+    use dozer_types::types::{Field, Record, SchemaIdentifier};
+    use web3::types::H256;
+
+    fn record(id: u64) -> Record {
+        Record::new(
+            Some(SchemaIdentifier { id: 1, version: 1 }),
+            vec![Field::UInt(id)],
+            None,
+        )
+    }
+
+    fn hash(n: u64) -> Option<H256> {
+        Some(H256::from_low_u64_be(n))
+    }
+
+    #[test]
+    fn test_it_returns_logs_of_orphaned_blocks() {
+        let mut tracker = BlockTracker::default();
+        tracker.track(10, hash(10), 0, vec![record(1), record(2)]);
+        tracker.track(11, hash(11), 0, vec![record(3)]);
+        tracker.track(11, hash(11), 1, vec![record(4)]);
+        tracker.track(12, hash(12), 0, vec![record(5)]);
+
+        // Another log of a known block.
+        assert!(tracker.orphaned(12, hash(12)).is_empty());
+        assert!(tracker.contains(12, 0));
+
+        // Block 11 was replaced.
+        assert_eq!(
+            tracker.orphaned(11, hash(111)),
+            vec![record(5), record(4), record(3)]
+        );
+        assert!(!tracker.contains(11, 0));
+        assert!(tracker.contains(10, 0));
+
+        // A log of an earlier block orphans the following ones.
+        tracker.track(11, hash(111), 0, vec![record(6)]);
+        assert_eq!(tracker.orphaned(10, hash(10)), vec![record(6)]);
+
+        tracker.track(11, hash(211), 0, vec![record(7)]);
+        assert_eq!(tracker.hash(11), hash(211));
+        assert_eq!(
+            tracker.orphaned_from(10),
+            vec![record(7), record(2), record(1)]
+        );
+        assert_eq!(tracker.hash(10), None);
+    }
+
+    #[test]
+    fn test_it_returns_increasing_ids() {
+        let mut tracker = BlockTracker::new(Some((10, 3)));
+        assert_eq!(tracker.next_id((10, 3)), (10, 4));
+        assert_eq!(tracker.next_id((11, 1)), (11, 1));
+
+        // Logs of a reorganization follow the last message.
+        assert_eq!(tracker.next_id((11, 0)), (11, 2));
+        assert_eq!(tracker.next_id((10, 5)), (11, 3));
+        assert_eq!(tracker.next_id((12, 1)), (12, 1));
+    }
+
+    #[test]
+    fn test_it_removes_logs_and_forgets_final_blocks() {
+        let mut tracker = BlockTracker::default();
+        tracker.track(10, hash(10), 0, vec![record(1)]);
+        tracker.track(10, hash(10), 1, vec![record(2)]);
+
+        assert_eq!(tracker.remove(10, 1), Some(vec![record(2)]));
+        assert_eq!(tracker.remove(10, 1), None);
+        assert!(tracker.contains(10, 0));
+
+        tracker.track(10 + MAX_REORG_DEPTH + 1, hash(100), 0, vec![record(3)]);
+        assert!(!tracker.contains(10, 0));
+        assert_eq!(tracker.remove(10, 0), None);
+    }
+}
@@ -129,7 +129,8 @@ impl Connector for EthLogConnector {
         let mut schemas = vec![SourceSchema::new(
             ETH_LOGS_TABLE.to_string(),
             helper::get_eth_schema(),
-            ReplicationChangesTrackingType::Nothing,
+            // Logs orphaned by chain reorganizations are retracted with their full record.
+            ReplicationChangesTrackingType::FullChanges,
         )];
 
         let event_schemas = helper::get_contract_event_schemas(
@@ -171,6 +172,7 @@ impl Connector for EthLogConnector {
                 self.schema_map.to_owned(),
                 from_seq,
                 self.conn_name.clone(),
+                self.config.confirmations,
             ));
             run(details).await
         })
@@ -195,7 +197,16 @@ impl Connector for EthLogConnector {
         self.get_tables_default(tables)
     }
 
-    fn can_start_from(&self, _last_checkpoint: (u64, u64)) -> Result<bool, ConnectorError> {
-        Ok(false)
+    fn can_start_from(&self, (block_no, _): (u64, u64)) -> Result<bool, ConnectorError> {
+        // The checkpoint holds the block of the last ingested log, reading resumes in that block
+        // as long as it is in the range of the filter.
+        let filter = self.config.filter.to_owned().unwrap_or_default();
+        Ok(block_no > 0
+            && filter
+                .from_block
+                .map_or(true, |from_block| block_no >= from_block)
+            && filter
+                .to_block
+                .map_or(true, |to_block| block_no <= to_block))
     }
 }
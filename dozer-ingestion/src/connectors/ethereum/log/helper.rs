@@ -44,6 +44,21 @@ pub fn get_contract_event_schemas(
                 });
             }
 
+            // Logs are identified by their block and index, so that orphaned logs can be deleted.
+            let primary_index = vec![fields.len(), fields.len() + 1];
+            fields.push(FieldDefinition {
+                name: "block_hash".to_string(),
+                typ: FieldType::String,
+                nullable: false,
+                source: SourceDefinition::Dynamic,
+            });
+            fields.push(FieldDefinition {
+                name: "log_index".to_string(),
+                typ: FieldType::UInt,
+                nullable: false,
+                source: SourceDefinition::Dynamic,
+            });
+
             let schema_id = schema_map
                 .get(&event.signature())
                 .expect("schema is missing")
@@ -57,9 +72,9 @@ pub fn get_contract_event_schemas(
                         version: 1,
                     }),
                     fields,
-                    primary_index: vec![],
+                    primary_index,
                 },
-                ReplicationChangesTrackingType::Nothing,
+                ReplicationChangesTrackingType::FullChanges,
             ));
         }
     }
@@ -107,6 +122,8 @@ pub fn decode_event(
             let table_name = get_table_name(contract_tuple, &event.name);
             let is_table_required = tables.iter().any(|t| t.table_name == table_name);
             if is_table_required {
+                let block_hash = log.block_hash;
+                let log_index = log.log_index;
                 let parsed_event = event.parse_log(RawLog {
                     topics: log.topics,
                     data: log.data.0,
@@ -114,11 +131,15 @@ pub fn decode_event(
 
                 match parsed_event {
                     Ok(parsed_event) => {
-                        let values = parsed_event
+                        let mut values: Vec<Field> = parsed_event
                             .params
                             .into_iter()
                             .map(|p| map_abitype_to_field(p.value))
                             .collect();
+                        values.push(
+                            block_hash.map_or(Field::Null, |h| Field::String(format!("{h:?}"))),
+                        );
+                        values.push(log_index.map_or(Field::Null, |i| Field::UInt(i.as_u64())));
                         return Some(Operation::Insert {
                             new: Record {
                                 schema_id: Some(SchemaIdentifier {
@@ -225,7 +246,7 @@ pub fn map_log_to_values(log: Log) -> Vec<Field> {
         ),
         Field::Binary(log.data.0),
         log.block_hash
-            .map_or(Field::Null, |f| Field::String(f.to_string())),
+            .map_or(Field::Null, |f| Field::String(format!("{f:?}"))),
         Field::UInt(block_no),
         log.transaction_hash
             .map_or(Field::Null, |f| Field::String(f.to_string())),
@@ -271,7 +292,7 @@ pub fn get_eth_schema() -> Schema {
             FieldDefinition {
                 name: "block_hash".to_string(),
                 typ: FieldType::String,
-                nullable: true,
+                nullable: false,
                 source: SourceDefinition::Dynamic,
             },
             FieldDefinition {
@@ -294,8 +315,8 @@ pub fn get_eth_schema() -> Schema {
             },
             FieldDefinition {
                 name: "log_index".to_string(),
-                typ: FieldType::Int,
-                nullable: true,
+                typ: FieldType::UInt,
+                nullable: false,
                 source: SourceDefinition::Dynamic,
             },
             FieldDefinition {
@@ -318,6 +339,7 @@ pub fn get_eth_schema() -> Schema {
             },
         ],
 
-        primary_index: vec![0],
+        // The id of a log is reused when its block gets orphaned.
+        primary_index: vec![4, 8],
     }
 }
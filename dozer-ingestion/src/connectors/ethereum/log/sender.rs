@@ -10,6 +10,8 @@ use crate::{
 };
 use dozer_types::ingestion_types::{EthFilter, IngestionMessage};
 use dozer_types::log::{debug, info, trace, warn};
+use dozer_types::parking_lot::Mutex;
+use dozer_types::types::{Operation, Record};
 
 use futures::StreamExt;
 
@@ -19,11 +21,15 @@ use web3::transports::WebSocket;
 use web3::types::{Log, H256};
 use web3::Web3;
 
+use super::super::block_tracker::BlockTracker;
 use super::connector::ContractTuple;
 use super::helper;
 
 const MAX_RETRIES: usize = 3;
 
+/// Delay between two polls of the latest block, when waiting for confirmations.
+const POLL_INTERVAL: time::Duration = time::Duration::from_secs(1);
+
 pub struct EthDetails<'a> {
     wss_url: String,
     filter: EthFilter,
@@ -33,6 +39,8 @@ pub struct EthDetails<'a> {
     pub schema_map: HashMap<H256, usize>,
     from_seq: Option<(u64, u64)>,
     pub conn_name: String,
+    confirmations: u64,
+    tracker: Mutex<BlockTracker>,
 }
 
 impl<'a> EthDetails<'a> {
@@ -46,6 +54,7 @@ impl<'a> EthDetails<'a> {
         schema_map: HashMap<H256, usize>,
         from_seq: Option<(u64, u64)>,
         conn_name: String,
+        confirmations: u64,
     ) -> Self {
         EthDetails {
             wss_url,
@@ -56,6 +65,8 @@ impl<'a> EthDetails<'a> {
             schema_map,
             from_seq,
             conn_name,
+            confirmations,
+            tracker: Mutex::new(BlockTracker::new(from_seq)),
         }
     }
 }
@@ -74,27 +85,38 @@ pub async fn run(details: Arc<EthDetails<'_>>) -> Result<(), ConnectorError> {
         .map_err(ConnectorError::EthError)?
         .as_u64();
 
+    // Only blocks with enough confirmations are read.
+    let confirmed_block_no = latest_block_no.saturating_sub(details.confirmations);
+
     let block_end = match details.filter.to_block {
-        None => latest_block_no,
+        None => confirmed_block_no,
+        Some(block_no) if details.confirmations > 0 => block_no.min(confirmed_block_no),
         Some(block_no) => block_no,
     };
 
-    // Default to current block if from_block is not specified
+    // Default to current block if from_block is not specified.
+    // On resume, reading starts again in the block of the checkpoint, skipping ingested logs.
     let block_start = match (details.from_seq, details.filter.from_block) {
         (Some((0, _)), Some(block_no)) | (None, Some(block_no)) => block_no,
         (Some((0, _)), None) | (None, None) => block_end,
-        (Some((lsn, _)), _) => lsn + 1,
+        (Some((block_no, _)), _) => block_no,
     };
 
-    fetch_logs(
-        details.clone(),
-        client.clone(),
-        block_start,
-        block_end,
-        0,
-        MAX_RETRIES,
-    )
-    .await?;
+    if block_start <= block_end {
+        fetch_logs(
+            details.clone(),
+            client.clone(),
+            block_start,
+            block_end,
+            0,
+            MAX_RETRIES,
+        )
+        .await?;
+    }
+
+    if details.confirmations > 0 {
+        return poll_confirmed_logs(details, client, block_start.max(block_end + 1)).await;
+    }
 
     let changes_handler_filter = match details.filter.to_block {
         None => {
@@ -149,6 +171,50 @@ pub async fn run(details: Arc<EthDetails<'_>>) -> Result<(), ConnectorError> {
     Ok(())
 }
 
+/// Reads the logs of the following blocks as soon as they have enough confirmations.
+async fn poll_confirmed_logs(
+    details: Arc<EthDetails<'_>>,
+    client: Web3<WebSocket>,
+    mut next_block: u64,
+) -> Result<(), ConnectorError> {
+    loop {
+        if details
+            .filter
+            .to_block
+            .map_or(false, |to_block| next_block > to_block)
+        {
+            info!("[{}] Reading reached block_to limit", details.conn_name);
+            return Ok(());
+        }
+
+        let latest_block_no = client
+            .eth()
+            .block_number()
+            .await
+            .map_err(ConnectorError::EthError)?
+            .as_u64();
+        let mut block_end = latest_block_no.saturating_sub(details.confirmations);
+        if let Some(to_block) = details.filter.to_block {
+            block_end = block_end.min(to_block);
+        }
+
+        if block_end >= next_block {
+            fetch_logs(
+                details.clone(),
+                client.clone(),
+                next_block,
+                block_end,
+                0,
+                MAX_RETRIES,
+            )
+            .await?;
+            next_block = block_end + 1;
+        } else {
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+    }
+}
+
 pub fn fetch_logs(
     details: Arc<EthDetails>,
     client: Web3<WebSocket>,
@@ -233,42 +299,120 @@ pub fn fetch_logs(
 
 fn process_log(details: Arc<EthDetails>, msg: Log) -> Result<(), ConnectorError> {
     // Filter pending logs. log.log_index is None for pending State
-    if msg.log_index.is_none() {
-        Ok(())
-    } else {
-        if let Some(op) = helper::map_log_to_event(msg.to_owned(), details.clone()) {
-            trace!("Writing log : {:?}", op);
-            // Write eth_log record
-            details
-                .ingestor
-                .handle_message(IngestionMessage::new_op(
-                    msg.block_number.expect("expected for non pending").as_u64(),
-                    0,
-                    op,
-                ))
-                .map_err(ConnectorError::IngestorError)?;
-        } else {
-            trace!("Ignoring log : {:?}", msg);
+    let (Some(block_no), Some(log_idx)) = (msg.block_number, msg.log_index) else {
+        return Ok(());
+    };
+    let (block_no, log_idx) = (block_no.as_u64(), log_idx.as_u64());
+
+    let mut tracker = details.tracker.lock();
+
+    if msg.removed == Some(true) {
+        // The log was orphaned by a chain reorganization.
+        let records = match tracker.remove(block_no, log_idx) {
+            Some(records) => records,
+            // Logs ingested before a restart are not tracked.
+            None if details
+                .from_seq
+                .map_or(false, |(from_block, _)| block_no <= from_block) =>
+            {
+                let mut log = msg;
+                log.removed = Some(false);
+                log_records(&details, log)
+                    .into_iter()
+                    .map(|(_, record)| record)
+                    .collect()
+            }
+            None => vec![],
+        };
+        for record in records.into_iter().rev() {
+            retract(&details, &mut tracker, block_no, record)?;
         }
+        return Ok(());
+    }
 
-        // write event record optionally
+    for record in tracker.orphaned(block_no, msg.block_hash) {
+        retract(&details, &mut tracker, block_no, record)?;
+    }
 
-        let op = helper::decode_event(
-            msg,
-            details.contracts.to_owned(),
-            details.tables.clone(),
-            details.schema_map.clone(),
-        );
-        if let Some(op) = op {
-            trace!("Writing event : {:?}", op);
-            details
-                .ingestor
-                .handle_message(IngestionMessage::new_op(0, 0, op))
-                .map_err(ConnectorError::IngestorError)?;
-        } else {
-            trace!("Writing event : {:?}", op);
+    // Logs of the last fetched block are received again from the subscription.
+    if tracker.contains(block_no, log_idx) {
+        trace!("Ignoring already ingested log : {:?}", msg);
+        return Ok(());
+    }
+
+    let block_hash = msg.block_hash;
+    let records = log_records(&details, msg);
+    tracker.track(
+        block_no,
+        block_hash,
+        log_idx,
+        records.iter().map(|(_, record)| record.clone()).collect(),
+    );
+
+    for (seq_in_tx, record) in records {
+        // Skip what was ingested before the checkpoint.
+        if details
+            .from_seq
+            .map_or(false, |from_seq| (block_no, seq_in_tx) <= from_seq)
+        {
+            continue;
         }
 
-        Ok(())
+        trace!("Writing record : {:?}", record);
+        let (txid, seq_in_tx) = tracker.next_id((block_no, seq_in_tx));
+        details
+            .ingestor
+            .handle_message(IngestionMessage::new_op(
+                txid,
+                seq_in_tx,
+                Operation::Insert { new: record },
+            ))
+            .map_err(ConnectorError::IngestorError)?;
+    }
+
+    Ok(())
+}
+
+/// Returns the eth_logs record of a log and, optionally, the record of its decoded event, with
+/// their sequence number in the block.
+fn log_records(details: &Arc<EthDetails>, log: Log) -> Vec<(u64, Record)> {
+    let log_idx = log.log_index.expect("expected for non pending").as_u64();
+
+    let mut records = vec![];
+    if let Some(Operation::Insert { new }) = helper::map_log_to_event(log.clone(), details.clone())
+    {
+        records.push((log_idx * 2 + 1, new));
     }
+
+    let event = helper::decode_event(
+        log,
+        details.contracts.to_owned(),
+        details.tables.clone(),
+        details.schema_map.clone(),
+    );
+    if let Some(Operation::Insert { new }) = event {
+        records.push((log_idx * 2 + 2, new));
+    }
+
+    records
+}
+
+/// Retracts a record ingested for an orphaned log. Retractions follow the last ingested message,
+/// so that the checkpoint never goes back to the block of the log which revealed the reorganization.
+fn retract(
+    details: &EthDetails,
+    tracker: &mut BlockTracker,
+    block_no: u64,
+    record: Record,
+) -> Result<(), ConnectorError> {
+    trace!("Retracting record : {:?}", record);
+    let (txid, seq_in_tx) = tracker.next_id((block_no, 0));
+    details
+        .ingestor
+        .handle_message(IngestionMessage::new_op(
+            txid,
+            seq_in_tx,
+            Operation::Delete { old: record },
+        ))
+        .map_err(ConnectorError::IngestorError)
 }
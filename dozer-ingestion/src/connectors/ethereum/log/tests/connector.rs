@@ -2,6 +2,9 @@ use dozer_types::types::{Field, Operation};
 use hex_literal::hex;
 
 use super::helper::run_eth_sample;
+use crate::connectors::ethereum::EthLogConnector;
+use crate::connectors::Connector;
+use dozer_types::ingestion_types::{EthFilter, EthLogConfig};
 
 #[test]
 #[ignore]
@@ -28,3 +31,27 @@ fn test_eth_iterator() {
 
     validate(&msgs[2], 0, None);
 }
+
+#[test]
+fn test_it_resumes_from_blocks_in_filter_range() {
+    let connector = EthLogConnector::new(
+        1,
+        EthLogConfig {
+            wss_url: "ws://localhost:8545".to_string(),
+            filter: Some(EthFilter {
+                from_block: Some(100),
+                to_block: Some(200),
+                addresses: vec![],
+                topics: vec![],
+            }),
+            contracts: vec![],
+            confirmations: 12,
+        },
+        "eth_test".to_string(),
+    );
+
+    assert!(connector.can_start_from((150, 3)).unwrap());
+    assert!(connector.can_start_from((200, 0)).unwrap());
+    assert!(!connector.can_start_from((50, 0)).unwrap());
+    assert!(!connector.can_start_from((201, 0)).unwrap());
+}
@@ -1,5 +1,8 @@
+mod block;
+mod block_tracker;
 pub mod helper;
 mod log;
 mod trace;
+pub use block::EthBlockConnector;
 pub use log::EthLogConnector;
 pub use trace::EthTraceConnector;
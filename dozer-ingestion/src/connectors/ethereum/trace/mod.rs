@@ -1,5 +1,5 @@
 mod connector;
 pub mod helper;
-pub use connector::EthTraceConnector;
+pub use connector::{BatchIterator, EthTraceConnector};
 #[cfg(test)]
 mod tests;
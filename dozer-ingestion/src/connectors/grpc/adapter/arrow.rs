@@ -5,14 +5,13 @@ use dozer_types::{
     arrow_types::{self, from_arrow::map_record_batch_to_dozer_records},
     bytes::{Buf, Bytes},
     grpc_types::ingest::IngestArrowRequest,
-    ingestion_types::IngestionMessage,
     serde_json,
     types::{
         Operation, Record, ReplicationChangesTrackingType, Schema, SchemaIdentifier, SourceSchema,
     },
 };
 
-use crate::{errors::ConnectorError, ingestion::Ingestor};
+use crate::errors::ConnectorError;
 use dozer_types::serde::{self, Deserialize, Serialize};
 
 use super::{GrpcIngestMessage, IngestAdapter};
@@ -60,41 +59,33 @@ impl IngestAdapter for ArrowAdapter {
         }
         Ok(schemas)
     }
-    fn handle_message(
+    fn map_message(
         &self,
         msg: GrpcIngestMessage,
         schema_map: &'static HashMap<String, Schema>,
-        ingestor: &'static Ingestor,
-    ) -> Result<(), ConnectorError> {
+    ) -> Result<Vec<Operation>, ConnectorError> {
         match msg {
             GrpcIngestMessage::Default(_) => Err(ConnectorError::InitializationError(
                 "Wrong message format!".to_string(),
             )),
-            GrpcIngestMessage::Arrow(msg) => handle_message(msg, schema_map, ingestor),
+            GrpcIngestMessage::Arrow(msg) => map_message(msg, schema_map),
         }
     }
 }
 
-pub fn handle_message(
+pub fn map_message(
     req: IngestArrowRequest,
     schema_map: &'static HashMap<String, Schema>,
-    ingestor: &'static Ingestor,
-) -> Result<(), ConnectorError> {
+) -> Result<Vec<Operation>, ConnectorError> {
     let schema = schema_map.get(&req.schema_name).ok_or_else(|| {
         ConnectorError::InitializationError(format!("schema not found: {}", req.schema_name))
     })?;
 
-    let seq_no = req.seq_no;
     let records = map_record_batch(req, schema)?;
-
-    for r in records {
-        let op = Operation::Insert { new: r };
-        ingestor
-            .handle_message(IngestionMessage::new_op(0, seq_no as u64, op))
-            .map_err(|e| ConnectorError::InternalError(Box::new(e)))?;
-    }
-
-    Ok(())
+    Ok(records
+        .into_iter()
+        .map(|new| Operation::Insert { new })
+        .collect())
 }
 
 fn map_record_batch(
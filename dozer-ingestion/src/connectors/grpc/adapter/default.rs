@@ -8,15 +8,12 @@ use crate::errors::ConnectorError;
 use super::{GrpcIngestMessage, IngestAdapter};
 use dozer_types::{
     chrono,
-    ingestion_types::IngestionMessage,
     ordered_float::OrderedFloat,
     types::{Operation, Record, Schema},
 };
 
 use std::collections::HashMap;
 
-use crate::ingestion::Ingestor;
-
 use dozer_types::grpc_types;
 use dozer_types::grpc_types::ingest::IngestRequest;
 
@@ -45,14 +42,13 @@ impl IngestAdapter for DefaultAdapter {
             .collect();
         Ok(schemas)
     }
-    fn handle_message(
+    fn map_message(
         &self,
         msg: GrpcIngestMessage,
         schema_map: &'static HashMap<String, Schema>,
-        ingestor: &'static Ingestor,
-    ) -> Result<(), ConnectorError> {
+    ) -> Result<Vec<Operation>, ConnectorError> {
         match msg {
-            GrpcIngestMessage::Default(msg) => handle_message(msg, schema_map, ingestor),
+            GrpcIngestMessage::Default(msg) => map_message(msg, schema_map),
             GrpcIngestMessage::Arrow(_) => Err(ConnectorError::InitializationError(
                 "Wrong message format!".to_string(),
             )),
@@ -60,11 +56,10 @@ impl IngestAdapter for DefaultAdapter {
     }
 }
 
-pub fn handle_message(
+pub fn map_message(
     req: IngestRequest,
     schema_map: &'static HashMap<String, Schema>,
-    ingestor: &'static Ingestor,
-) -> Result<(), ConnectorError> {
+) -> Result<Vec<Operation>, ConnectorError> {
     let schema = schema_map.get(&req.schema_name).ok_or_else(|| {
         ConnectorError::InitializationError(format!("schema not found: {}", req.schema_name))
     })?;
@@ -81,9 +76,7 @@ pub fn handle_message(
             new: map_record(req.new.unwrap(), schema)?,
         },
     };
-    ingestor
-        .handle_message(IngestionMessage::new_op(0, req.seq_no as u64, op))
-        .map_err(ConnectorError::IngestorError)
+    Ok(vec![op])
 }
 
 fn map_record(rec: grpc_types::types::Record, schema: &Schema) -> Result<Record, ConnectorError> {
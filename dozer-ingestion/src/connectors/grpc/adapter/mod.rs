@@ -2,9 +2,10 @@ use std::collections::HashMap;
 
 use dozer_types::{
     grpc_types::ingest::{IngestArrowRequest, IngestRequest},
-    types::{Schema, SchemaIdentifier, SourceSchema},
+    types::{Operation, Schema, SchemaIdentifier, SourceSchema},
 };
 
+use super::sequencer::Sequencer;
 use crate::{errors::ConnectorError, ingestion::Ingestor};
 
 mod default;
@@ -19,18 +20,33 @@ where
 {
     fn new() -> Self;
     fn get_schemas(&self, schemas_str: &str) -> Result<Vec<SourceSchema>, ConnectorError>;
-    fn handle_message(
+    fn map_message(
         &self,
         msg: GrpcIngestMessage,
         schema_map: &'static HashMap<String, Schema>,
-        ingestor: &'static Ingestor,
-    ) -> Result<(), ConnectorError>;
+    ) -> Result<Vec<Operation>, ConnectorError>;
 }
 
 pub enum GrpcIngestMessage {
     Default(IngestRequest),
     Arrow(IngestArrowRequest),
 }
+
+impl GrpcIngestMessage {
+    pub fn producer_id(&self) -> &str {
+        match self {
+            GrpcIngestMessage::Default(req) => &req.producer_id,
+            GrpcIngestMessage::Arrow(req) => &req.producer_id,
+        }
+    }
+
+    pub fn seq_no(&self) -> u32 {
+        match self {
+            GrpcIngestMessage::Default(req) => req.seq_no,
+            GrpcIngestMessage::Arrow(req) => req.seq_no,
+        }
+    }
+}
 pub struct GrpcIngestor<A>
 where
     A: IngestAdapter,
@@ -79,11 +95,20 @@ where
         self.adapter.get_schemas(&self.schemas_str)
     }
 
+    /// Ingests a message, returning false if it was a duplicate.
     pub fn handle_message(
         &self,
         msg: GrpcIngestMessage,
+        sequencer: &Sequencer,
         ingestor: &'static Ingestor,
-    ) -> Result<(), ConnectorError> {
-        self.adapter.handle_message(msg, self.schema_map, ingestor)
+    ) -> Result<bool, ConnectorError> {
+        let producer_id = msg.producer_id().to_string();
+        let seq_no = msg.seq_no();
+        sequencer.ingest(
+            producer_id,
+            seq_no,
+            || self.adapter.map_message(msg, self.schema_map),
+            ingestor,
+        )
     }
 }
@@ -1,9 +1,11 @@
 use std::collections::HashMap;
 use std::fmt::Debug;
 use std::path::Path;
+use std::sync::Arc;
 
 use super::adapter::{GrpcIngestor, IngestAdapter};
 use super::ingest::IngestorServiceImpl;
+use super::sequencer::Sequencer;
 use crate::connectors::ValidationResults;
 use crate::{
     connectors::{Connector, TableInfo},
@@ -11,7 +13,7 @@ use crate::{
     ingestion::Ingestor,
 };
 use dozer_types::grpc_types::ingest::ingest_service_server::IngestServiceServer;
-use dozer_types::ingestion_types::GrpcConfig;
+use dozer_types::ingestion_types::{GrpcConfig, GrpcConfigSchemas};
 use dozer_types::log::info;
 use dozer_types::types::SourceSchema;
 use tonic::transport::Server;
@@ -24,6 +26,7 @@ where
     pub id: u64,
     pub name: String,
     pub config: GrpcConfig,
+    sequencer: Arc<Sequencer>,
     _phantom: std::marker::PhantomData<T>,
 }
 impl<T> Debug for GrpcConnector<T>
@@ -48,6 +51,7 @@ where
             id,
             name,
             config,
+            sequencer: Arc::new(Sequencer::default()),
             _phantom: std::marker::PhantomData,
         })
     }
@@ -56,29 +60,14 @@ where
     where
         T: IngestAdapter,
     {
-        let schemas = config.schemas.as_ref().map_or_else(
-            || {
-                Err(ConnectorError::InitializationError(
-                    "schemas not found".to_string(),
-                ))
-            },
-            Ok,
-        )?;
-        let schemas_str = match schemas {
-            dozer_types::ingestion_types::GrpcConfigSchemas::Inline(schemas_str) => {
-                schemas_str.clone()
-            }
-            dozer_types::ingestion_types::GrpcConfigSchemas::Path(path) => {
-                let path = Path::new(path);
-                std::fs::read_to_string(path)
-                    .map_err(|e| ConnectorError::InitializationError(e.to_string()))?
-            }
-        };
-
-        Ok(schemas_str)
+        read_schemas(config.schemas.as_ref())
     }
 
-    pub fn serve(&self, ingestor: &Ingestor) -> Result<(), ConnectorError> {
+    pub fn serve(
+        &self,
+        from_seq: Option<(u64, u64)>,
+        ingestor: &Ingestor,
+    ) -> Result<(), ConnectorError> {
         let host = &self.config.host;
         let port = self.config.port;
 
@@ -89,6 +78,11 @@ where
 
         let schemas_str = Self::parse_config(&self.config)?;
         let adapter = GrpcIngestor::<T>::new(schemas_str)?;
+        self.sequencer.open(
+            self.config.journal_path.as_deref().map(Path::new),
+            from_seq,
+            ingestor,
+        )?;
 
         rt.block_on(async {
             // Ingestor will live as long as the server
@@ -96,7 +90,8 @@ where
             let ingestor =
                 unsafe { std::mem::transmute::<&'_ Ingestor, &'static Ingestor>(ingestor) };
 
-            let ingest_service = IngestorServiceImpl::new(adapter, ingestor);
+            let ingest_service =
+                IngestorServiceImpl::new(adapter, self.sequencer.clone(), ingestor);
             let ingest_service = tonic_web::config()
                 .allow_all_origins()
                 .enable(IngestServiceServer::new(ingest_service));
@@ -120,6 +115,28 @@ where
     }
 }
 
+/// Reads the schemas declared inline or in a file.
+pub fn read_schemas(schemas: Option<&GrpcConfigSchemas>) -> Result<String, ConnectorError> {
+    let schemas = schemas.map_or_else(
+        || {
+            Err(ConnectorError::InitializationError(
+                "schemas not found".to_string(),
+            ))
+        },
+        Ok,
+    )?;
+    let schemas_str = match schemas {
+        GrpcConfigSchemas::Inline(schemas_str) => schemas_str.clone(),
+        GrpcConfigSchemas::Path(path) => {
+            let path = Path::new(path);
+            std::fs::read_to_string(path)
+                .map_err(|e| ConnectorError::InitializationError(e.to_string()))?
+        }
+    };
+
+    Ok(schemas_str)
+}
+
 impl<T> Connector for GrpcConnector<T>
 where
     T: IngestAdapter,
@@ -143,11 +160,16 @@ where
 
     fn start(
         &self,
-        _from_seq: Option<(u64, u64)>,
+        from_seq: Option<(u64, u64)>,
         ingestor: &Ingestor,
         _table_names: Vec<TableInfo>,
     ) -> Result<(), ConnectorError> {
-        self.serve(ingestor)
+        self.serve(from_seq, ingestor)
+    }
+
+    fn commit(&self, checkpoint: (u64, u64)) -> Result<(), ConnectorError> {
+        self.sequencer.commit(checkpoint);
+        Ok(())
     }
 
     fn validate(&self, table_names: Option<Vec<TableInfo>>) -> Result<(), ConnectorError> {
@@ -179,6 +201,7 @@ where
     }
 
     fn can_start_from(&self, _last_checkpoint: (u64, u64)) -> Result<bool, ConnectorError> {
-        Ok(false)
+        // Requests which were not committed can only be ingested again from the journal.
+        Ok(self.config.journal_path.is_some())
     }
 }
@@ -1,7 +1,10 @@
 use std::sync::Arc;
 
-use dozer_types::{grpc_types::ingest::IngestArrowRequest, log::error};
-use futures::StreamExt;
+use dozer_types::{
+    grpc_types::ingest::{GetCheckpointRequest, GetCheckpointResponse, IngestArrowRequest},
+    log::error,
+};
+use futures::{Stream, StreamExt};
 use tonic::Streaming;
 
 use dozer_types::grpc_types::ingest::{
@@ -11,25 +14,86 @@ use dozer_types::grpc_types::ingest::{
 use crate::ingestion::Ingestor;
 
 use super::adapter::{GrpcIngestMessage, GrpcIngestor, IngestAdapter};
+use super::sequencer::Sequencer;
 
 pub struct IngestorServiceImpl<T>
 where
     T: IngestAdapter,
 {
     adapter: Arc<GrpcIngestor<T>>,
+    sequencer: Arc<Sequencer>,
     ingestor: &'static Ingestor,
 }
 impl<T> IngestorServiceImpl<T>
 where
     T: IngestAdapter,
 {
-    pub fn new(adapter: GrpcIngestor<T>, ingestor: &'static Ingestor) -> Self {
+    pub fn new(
+        adapter: GrpcIngestor<T>,
+        sequencer: Arc<Sequencer>,
+        ingestor: &'static Ingestor,
+    ) -> Self {
         Self {
             adapter: Arc::new(adapter),
+            sequencer,
             ingestor,
         }
     }
+
+    /// Ingests a message, returning false if it was a duplicate. Requests are journaled and sent
+    /// to the pipeline synchronously, so this runs on a blocking thread.
+    async fn handle_message(&self, msg: GrpcIngestMessage) -> Result<bool, tonic::Status> {
+        let adapter = self.adapter.clone();
+        let sequencer = self.sequencer.clone();
+        let ingestor = self.ingestor;
+        tokio::task::spawn_blocking(move || adapter.handle_message(msg, &sequencer, ingestor))
+            .await
+            .map_err(|e| tonic::Status::internal(format!("ingestion stream error: {e}")))?
+            .map_err(|e| tonic::Status::internal(format!("ingestion stream error: {e}")))
+    }
+
+    /// Ingests the messages of a stream until it ends or fails, returning the sequence number of
+    /// the last ingested message and the ones of the duplicates.
+    async fn handle_stream(
+        &self,
+        mut in_stream: impl Stream<Item = Result<GrpcIngestMessage, tonic::Status>> + Unpin + Send,
+    ) -> IngestResponse {
+        let mut response = IngestResponse::default();
+        while let Some(result) = in_stream.next().await {
+            let msg = match result {
+                Ok(msg) => msg,
+                Err(e) => {
+                    error!("ingestion stream errored: {:#?}", e);
+                    break;
+                }
+            };
+            let seq_no = msg.seq_no();
+            match self.handle_message(msg).await {
+                Ok(ingested) => {
+                    response.seq_no = seq_no;
+                    if !ingested {
+                        response.duplicate = true;
+                        response.duplicate_seq_nos.push(seq_no);
+                    }
+                }
+                Err(e) => {
+                    error!("ingestion stream insertion errored: {:#?}", e);
+                    break;
+                }
+            }
+        }
+        response
+    }
+}
+
+fn response(seq_no: u32, ingested: bool) -> IngestResponse {
+    IngestResponse {
+        seq_no,
+        duplicate: !ingested,
+        duplicate_seq_nos: if ingested { vec![] } else { vec![seq_no] },
+    }
 }
+
 #[tonic::async_trait]
 impl<T> IngestService for IngestorServiceImpl<T>
 where
@@ -41,41 +105,18 @@ where
     ) -> Result<tonic::Response<IngestResponse>, tonic::Status> {
         let req = request.into_inner();
         let seq_no = req.seq_no;
-        self.adapter
-            .handle_message(GrpcIngestMessage::Default(req), self.ingestor)
-            .map_err(|e| tonic::Status::internal(format!("ingestion stream error: {e}")))?;
-
-        Ok(tonic::Response::new(IngestResponse { seq_no }))
+        let ingested = self.handle_message(GrpcIngestMessage::Default(req)).await?;
+        Ok(tonic::Response::new(response(seq_no, ingested)))
     }
 
     async fn ingest_stream(
         &self,
         req: tonic::Request<Streaming<IngestRequest>>,
     ) -> Result<tonic::Response<IngestResponse>, tonic::Status> {
-        let mut in_stream = req.into_inner();
-
-        let adapter = self.adapter.clone();
-        let ingestor = self.ingestor;
-        let seq_no = tokio::spawn(async move {
-            let mut seq_no = 0;
-            while let Some(result) = in_stream.next().await {
-                if let Ok(req) = result {
-                    seq_no = req.seq_no;
-                    let res = adapter.handle_message(GrpcIngestMessage::Default(req), ingestor);
-                    if let Err(e) = res {
-                        error!("ingestion stream insertion errored: {:#?}", e);
-                        break;
-                    }
-                } else {
-                    error!("ingestion stream errored: {:#?}", result);
-                    break;
-                }
-            }
-            seq_no
-        })
-        .await
-        .map_err(|e| tonic::Status::internal(format!("ingestion stream error: {e}")))?;
-        Ok(tonic::Response::new(IngestResponse { seq_no }))
+        let in_stream = req
+            .into_inner()
+            .map(|result| result.map(GrpcIngestMessage::Default));
+        Ok(tonic::Response::new(self.handle_stream(in_stream).await))
     }
 
     async fn ingest_arrow(
@@ -84,40 +125,30 @@ where
     ) -> Result<tonic::Response<IngestResponse>, tonic::Status> {
         let req = request.into_inner();
         let seq_no = req.seq_no;
-        self.adapter
-            .handle_message(GrpcIngestMessage::Arrow(req), self.ingestor)
-            .map_err(|e| tonic::Status::internal(format!("ingestion stream error: {e}")))?;
-
-        Ok(tonic::Response::new(IngestResponse { seq_no }))
+        let ingested = self.handle_message(GrpcIngestMessage::Arrow(req)).await?;
+        Ok(tonic::Response::new(response(seq_no, ingested)))
     }
 
     async fn ingest_arrow_stream(
         &self,
         req: tonic::Request<Streaming<IngestArrowRequest>>,
     ) -> Result<tonic::Response<IngestResponse>, tonic::Status> {
-        let mut in_stream = req.into_inner();
+        let in_stream = req
+            .into_inner()
+            .map(|result| result.map(GrpcIngestMessage::Arrow));
+        Ok(tonic::Response::new(self.handle_stream(in_stream).await))
+    }
 
-        let adapter = self.adapter.clone();
-        let ingestor = self.ingestor;
-        let seq_no = tokio::spawn(async move {
-            let mut seq_no = 0;
-            while let Some(result) = in_stream.next().await {
-                if let Ok(req) = result {
-                    seq_no = req.seq_no;
-                    let res = adapter.handle_message(GrpcIngestMessage::Arrow(req), ingestor);
-                    if let Err(e) = res {
-                        error!("ingestion stream insertion errored: {:#?}", e);
-                        break;
-                    }
-                } else {
-                    error!("ingestion stream errored: {:#?}", result);
-                    break;
-                }
-            }
-            seq_no
-        })
-        .await
-        .map_err(|e| tonic::Status::internal(format!("ingestion stream error: {e}")))?;
-        Ok(tonic::Response::new(IngestResponse { seq_no }))
+    async fn get_checkpoint(
+        &self,
+        request: tonic::Request<GetCheckpointRequest>,
+    ) -> Result<tonic::Response<GetCheckpointResponse>, tonic::Status> {
+        let req = request.into_inner();
+        // The sequencer is locked while requests are journaled.
+        let sequencer = self.sequencer.clone();
+        let seq_no = tokio::task::spawn_blocking(move || sequencer.checkpoint(&req.producer_id))
+            .await
+            .map_err(|e| tonic::Status::internal(format!("checkpoint error: {e}")))?;
+        Ok(tonic::Response::new(GetCheckpointResponse { seq_no }))
     }
 }
@@ -1,6 +1,7 @@
 #[allow(dead_code)]
 pub mod connector;
 mod ingest;
+mod sequencer;
 
 mod adapter;
 pub use adapter::{ArrowAdapter, DefaultAdapter, GrpcIngestMessage, GrpcIngestor, IngestAdapter};
@@ -0,0 +1,442 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use dozer_types::ingestion_types::IngestionMessage;
+use dozer_types::log::info;
+use dozer_types::parking_lot::Mutex;
+use dozer_types::serde::{self, Deserialize, Serialize};
+use dozer_types::types::Operation;
+
+use crate::connectors::journal::Journal;
+use crate::errors::ConnectorError;
+use crate::ingestion::Ingestor;
+
+/// Requests appended to the journal before it is compacted, unless its last compaction left
+/// more entries.
+const MIN_TRANSACTIONS_BEFORE_COMPACTION: usize = 1000;
+
+/// A request accepted by the connector.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[serde(crate = "self::serde")]
+pub struct JournalEntry {
+    pub txid: u64,
+    pub producer_id: String,
+    pub seq_no: u32,
+    pub operations: Vec<Operation>,
+}
+
+/// Orders the requests of all producers, and ignores the ones which were already acknowledged.
+///
+/// Each request is a transaction of the source, and its operations are identified by their
+/// index in it. With a journal, requests are persisted before being acknowledged, and the ones
+/// the pipeline had not committed are ingested again on restart. The journal is compacted to
+/// the committed checkpoint, only keeping the sequence numbers of the producers.
+#[derive(Debug, Default)]
+pub struct Sequencer {
+    state: Mutex<SequencerState>,
+    // Last committed checkpoint, which is only recorded so that committing never waits for a
+    // request to be ingested. The journal is compacted to it by the next request.
+    committed: Mutex<Option<(u64, u64)>>,
+}
+
+#[derive(Debug)]
+struct SequencerState {
+    // Last acknowledged sequence number, by producer
+    producers: HashMap<String, u32>,
+    next_txid: u64,
+    journal: Option<Journal<JournalEntry>>,
+    // Checkpoint the journal was last compacted at
+    compacted_at: Option<(u64, u64)>,
+    // Entries left by the last compaction
+    num_compacted: usize,
+    // Requests appended since the last compaction
+    num_appended: usize,
+}
+
+impl Default for SequencerState {
+    fn default() -> Self {
+        Self {
+            producers: HashMap::new(),
+            next_txid: 1,
+            journal: None,
+            compacted_at: None,
+            num_compacted: 0,
+            num_appended: 0,
+        }
+    }
+}
+
+impl Sequencer {
+    /// Opens the journal at `journal_path` if any, ingesting the requests following
+    /// `last_checkpoint`. The entries committed by the pipeline are removed, only keeping the
+    /// sequence numbers of their producers.
+    pub fn open(
+        &self,
+        journal_path: Option<&Path>,
+        last_checkpoint: Option<(u64, u64)>,
+        ingestor: &Ingestor,
+    ) -> Result<(), ConnectorError> {
+        let mut state = self.state.lock();
+        *state = SequencerState {
+            next_txid: last_checkpoint.map_or(1, |(txid, _)| txid + 1),
+            ..SequencerState::default()
+        };
+        *self.committed.lock() = None;
+        let Some(path) = journal_path else {
+            return Ok(());
+        };
+        let (mut journal, entries) = Journal::open(path)?;
+
+        for entry in &entries {
+            if !entry.producer_id.is_empty() {
+                state
+                    .producers
+                    .insert(entry.producer_id.clone(), entry.seq_no);
+            }
+            state.next_txid = state.next_txid.max(entry.txid + 1);
+        }
+
+        let (mut compacted, pending) = split(entries, last_checkpoint);
+        state.compacted_at = last_checkpoint;
+        state.num_compacted = compacted.len();
+        state.num_appended = pending.len();
+        compacted.extend(pending.iter().cloned());
+        journal.compact(&compacted)?;
+        state.journal = Some(journal);
+
+        info!(
+            "Replaying {} requests from gRPC journal {}",
+            pending.len(),
+            path.display()
+        );
+        for entry in pending {
+            let skip = match last_checkpoint {
+                Some((txid, seq_in_tx)) if entry.txid == txid => seq_in_tx as usize + 1,
+                _ => 0,
+            };
+            send(entry.txid, entry.operations, skip, ingestor)?;
+        }
+        Ok(())
+    }
+
+    /// Records that the messages up to `checkpoint` were committed.
+    pub fn commit(&self, checkpoint: (u64, u64)) {
+        *self.committed.lock() = Some(checkpoint);
+    }
+
+    /// Last sequence number acknowledged for a producer.
+    pub fn checkpoint(&self, producer_id: &str) -> Option<u32> {
+        self.state.lock().producers.get(producer_id).copied()
+    }
+
+    /// Ingests the operations of a request, which is persisted first if there is a journal.
+    /// Returns false if the request was already acknowledged, in which case its operations are
+    /// not mapped, as they may not be valid anymore.
+    pub fn ingest(
+        &self,
+        producer_id: String,
+        seq_no: u32,
+        operations: impl FnOnce() -> Result<Vec<Operation>, ConnectorError>,
+        ingestor: &Ingestor,
+    ) -> Result<bool, ConnectorError> {
+        let committed = *self.committed.lock();
+        let mut state = self.state.lock();
+        if state.is_duplicate(&producer_id, seq_no) {
+            return Ok(false);
+        }
+        let operations = operations()?;
+        if let Some(checkpoint) = committed {
+            state.compact(checkpoint)?;
+        }
+
+        let txid = state.next_txid;
+        if let Some(journal) = &mut state.journal {
+            journal.append(&JournalEntry {
+                txid,
+                producer_id: producer_id.clone(),
+                seq_no,
+                operations: operations.clone(),
+            })?;
+            state.num_appended += 1;
+        }
+        state.next_txid += 1;
+        if !producer_id.is_empty() {
+            state.producers.insert(producer_id, seq_no);
+        }
+
+        // Sending under the lock keeps the requests in order.
+        send(txid, operations, 0, ingestor)?;
+        Ok(true)
+    }
+}
+
+impl SequencerState {
+    /// Whether a request of a producer was already acknowledged.
+    fn is_duplicate(&self, producer_id: &str, seq_no: u32) -> bool {
+        !producer_id.is_empty()
+            && self
+                .producers
+                .get(producer_id)
+                .map_or(false, |last| seq_no <= *last)
+    }
+
+    /// Compacts the journal to `checkpoint`, keeping the requests following it. Compaction
+    /// waits for as many requests as the last one left entries.
+    fn compact(&mut self, checkpoint: (u64, u64)) -> Result<(), ConnectorError> {
+        let Some(journal) = &mut self.journal else {
+            return Ok(());
+        };
+        if self.compacted_at == Some(checkpoint)
+            || self.num_appended < self.num_compacted.max(MIN_TRANSACTIONS_BEFORE_COMPACTION)
+        {
+            return Ok(());
+        }
+
+        let (mut compacted, pending) = split(Journal::read(journal.path())?, Some(checkpoint));
+        self.compacted_at = Some(checkpoint);
+        self.num_compacted = compacted.len();
+        self.num_appended = pending.len();
+        compacted.extend(pending);
+        journal.compact(&compacted)?;
+        Ok(())
+    }
+}
+
+/// Splits journal entries at `checkpoint`, returning the last sequence number of the producers
+/// which only have committed requests, and the requests following the checkpoint.
+fn split(
+    entries: Vec<JournalEntry>,
+    checkpoint: Option<(u64, u64)>,
+) -> (Vec<JournalEntry>, Vec<JournalEntry>) {
+    let (committed, pending): (Vec<_>, Vec<_>) = match checkpoint {
+        Some((txid, _)) => entries.into_iter().partition(|e| e.txid < txid),
+        None => (vec![], entries),
+    };
+
+    let mut markers: HashMap<String, JournalEntry> = HashMap::new();
+    for entry in committed {
+        if !entry.producer_id.is_empty() {
+            markers.insert(
+                entry.producer_id.clone(),
+                JournalEntry {
+                    operations: vec![],
+                    ..entry
+                },
+            );
+        }
+    }
+    for entry in &pending {
+        markers.remove(&entry.producer_id);
+    }
+    let mut compacted = markers.into_values().collect::<Vec<_>>();
+    compacted.sort_by_key(|e| e.txid);
+    (compacted, pending)
+}
+
+fn send(
+    txid: u64,
+    operations: Vec<Operation>,
+    skip: usize,
+    ingestor: &Ingestor,
+) -> Result<(), ConnectorError> {
+    let messages = operations
+        .into_iter()
+        .enumerate()
+        .skip(skip)
+        .map(|(seq_in_tx, op)| IngestionMessage::new_op(txid, seq_in_tx as u64, op))
+        .collect::<Vec<_>>();
+    if messages.is_empty() {
+        return Ok(());
+    }
+    ingestor
+        .handle_batch(messages)
+        .map_err(ConnectorError::IngestorError)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::OpenOptions;
+    use std::io::Write;
+    use std::path::Path;
+
+    use dozer_types::ingestion_types::{IngestionMessage, IngestionMessageKind};
+    use dozer_types::types::{Field, Operation, Record};
+    use tempdir::TempDir;
+
+    use super::Sequencer;
+    use crate::connectors::journal::Journal;
+    use crate::ingestion::{IngestionConfig, IngestionIterator, Ingestor};
+
+    fn insert(id: i64) -> Operation {
+        Operation::Insert {
+            new: Record::new(None, vec![Field::Int(id)], None),
+        }
+    }
+
+    fn open(path: Option<&Path>, checkpoint: Option<(u64, u64)>, ingestor: &Ingestor) -> Sequencer {
+        let sequencer = Sequencer::default();
+        sequencer.open(path, checkpoint, ingestor).unwrap();
+        sequencer
+    }
+
+    fn ingest(
+        sequencer: &Sequencer,
+        producer_id: &str,
+        seq_no: u32,
+        ids: &[i64],
+        ingestor: &Ingestor,
+    ) -> bool {
+        sequencer
+            .ingest(
+                producer_id.to_string(),
+                seq_no,
+                || Ok(ids.iter().map(|id| insert(*id)).collect()),
+                ingestor,
+            )
+            .unwrap()
+    }
+
+    fn received(iterator: &mut IngestionIterator, count: usize) -> Vec<(u64, u64, i64)> {
+        (0..count)
+            .map(|_| {
+                let IngestionMessage { identifier, kind } = iterator.next().unwrap();
+                let IngestionMessageKind::OperationEvent(Operation::Insert { new }) = kind else {
+                    panic!("wrong message kind");
+                };
+                (
+                    identifier.txid,
+                    identifier.seq_in_tx,
+                    new.values[0].as_int().unwrap(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_ignores_acknowledged_requests() {
+        let (ingestor, mut iterator) = Ingestor::initialize_channel(IngestionConfig::default());
+        let sequencer = open(None, None, &ingestor);
+
+        assert!(ingest(&sequencer, "a", 1, &[1, 2], &ingestor));
+        assert!(ingest(&sequencer, "", 1, &[3], &ingestor));
+
+        // Duplicates are not mapped.
+        let result = sequencer.ingest(
+            "a".to_string(),
+            1,
+            || panic!("duplicate request mapped"),
+            &ingestor,
+        );
+        assert!(!result.unwrap());
+        assert!(ingest(&sequencer, "a", 2, &[4], &ingestor));
+        assert!(ingest(&sequencer, "b", 1, &[5], &ingestor));
+        assert!(ingest(&sequencer, "", 1, &[6], &ingestor));
+        assert_eq!(sequencer.checkpoint("a"), Some(2));
+        assert_eq!(
+            received(&mut iterator, 6),
+            vec![
+                (1, 0, 1),
+                (1, 1, 2),
+                (2, 0, 3),
+                (3, 0, 4),
+                (4, 0, 5),
+                (5, 0, 6)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_replays_uncommitted_requests() {
+        let dir = TempDir::new("grpc_journal").unwrap();
+        let path = dir.path().join("journal");
+
+        let (ingestor, mut iterator) = Ingestor::initialize_channel(IngestionConfig::default());
+        let sequencer = open(Some(&path), None, &ingestor);
+        ingest(&sequencer, "a", 1, &[1], &ingestor);
+        ingest(&sequencer, "b", 5, &[2, 3], &ingestor);
+        ingest(&sequencer, "b", 6, &[4], &ingestor);
+        received(&mut iterator, 4);
+        drop(sequencer);
+
+        // The pipeline committed the first operation of the second request.
+        let (ingestor, mut iterator) = Ingestor::initialize_channel(IngestionConfig::default());
+        let sequencer = open(Some(&path), Some((2, 0)), &ingestor);
+        assert_eq!(received(&mut iterator, 2), vec![(2, 1, 3), (3, 0, 4)]);
+        assert_eq!(sequencer.checkpoint("a"), Some(1));
+        assert_eq!(sequencer.checkpoint("b"), Some(6));
+
+        ingest(&sequencer, "a", 2, &[5], &ingestor);
+        assert_eq!(received(&mut iterator, 1), vec![(4, 0, 5)]);
+        drop(sequencer);
+
+        // Committed requests are compacted, but their producers are still deduplicated.
+        let (ingestor, mut iterator) = Ingestor::initialize_channel(IngestionConfig::default());
+        let sequencer = open(Some(&path), Some((4, 0)), &ingestor);
+        assert_eq!(sequencer.checkpoint("a"), Some(2));
+        assert_eq!(sequencer.checkpoint("b"), Some(6));
+        drop(sequencer);
+        drop(ingestor);
+        assert!(iterator.next().is_none());
+    }
+
+    #[test]
+    fn test_it_compacts_on_commit() {
+        let dir = TempDir::new("grpc_journal").unwrap();
+        let path = dir.path().join("journal");
+        let (ingestor, _iterator) = Ingestor::initialize_channel(IngestionConfig::default());
+
+        let sequencer = open(Some(&path), None, &ingestor);
+        let num_requests = super::MIN_TRANSACTIONS_BEFORE_COMPACTION as u32 + 1;
+        for seq_no in 1..=num_requests {
+            ingest(&sequencer, "a", seq_no, &[seq_no as i64], &ingestor);
+        }
+        sequencer.commit((num_requests as u64, 0));
+        assert_eq!(
+            Journal::<super::JournalEntry>::read(&path).unwrap().len(),
+            num_requests as usize
+        );
+
+        // The next request compacts the journal, leaving the request at the checkpoint and the
+        // following one.
+        ingest(&sequencer, "b", 1, &[0], &ingestor);
+        let entries = Journal::<super::JournalEntry>::read(&path).unwrap();
+        assert_eq!(
+            entries
+                .iter()
+                .map(|e| (e.txid, e.producer_id.as_str(), e.seq_no))
+                .collect::<Vec<_>>(),
+            vec![
+                (num_requests as u64, "a", num_requests),
+                (num_requests as u64 + 1, "b", 1)
+            ]
+        );
+        drop(sequencer);
+
+        let sequencer = open(Some(&path), Some((num_requests as u64 + 1, 0)), &ingestor);
+        assert_eq!(sequencer.checkpoint("a"), Some(num_requests));
+        assert_eq!(sequencer.checkpoint("b"), Some(1));
+    }
+
+    #[test]
+    fn test_drops_partially_written_request() {
+        let dir = TempDir::new("grpc_journal").unwrap();
+        let path = dir.path().join("journal");
+
+        let (ingestor, _iterator) = Ingestor::initialize_channel(IngestionConfig::default());
+        let sequencer = open(Some(&path), None, &ingestor);
+        ingest(&sequencer, "a", 1, &[1], &ingestor);
+        drop(sequencer);
+
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(&100u32.to_le_bytes()).unwrap();
+        file.write_all(&[0; 10]).unwrap();
+        drop(file);
+
+        let (ingestor, mut iterator) = Ingestor::initialize_channel(IngestionConfig::default());
+        let sequencer = open(Some(&path), Some((1, 0)), &ingestor);
+        assert_eq!(sequencer.checkpoint("a"), Some(1));
+        drop(sequencer);
+        drop(ingestor);
+        assert!(iterator.next().is_none());
+    }
+}
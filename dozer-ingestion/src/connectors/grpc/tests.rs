@@ -5,7 +5,10 @@ use dozer_orchestrator::Connection;
 use dozer_types::{
     arrow::array::{Int32Array, StringArray},
     grpc_types::{
-        ingest::{ingest_service_client::IngestServiceClient, IngestArrowRequest, IngestRequest},
+        ingest::{
+            ingest_service_client::IngestServiceClient, GetCheckpointRequest, IngestArrowRequest,
+            IngestRequest,
+        },
         types,
     },
     ingestion_types::IngestionMessageKind,
@@ -109,7 +112,11 @@ async fn ingest_grpc_default() {
         .unwrap();
 
     let msg = iterator.next().unwrap();
-    assert!(msg.identifier.seq_in_tx == 1, "seq_no should be 1");
+    assert_eq!(
+        msg.identifier.txid, 1,
+        "each request should be a transaction"
+    );
+    assert_eq!(msg.identifier.seq_in_tx, 0);
 
     if let IngestionMessageKind::OperationEvent(op) = msg.kind {
         if let Operation::Insert { new: record } = op {
@@ -175,7 +182,11 @@ async fn ingest_grpc_arrow() {
         .unwrap();
 
     let msg = iterator.next().unwrap();
-    assert!(msg.identifier.seq_in_tx == 1, "seq_no should be 1");
+    assert_eq!(
+        msg.identifier.txid, 1,
+        "each request should be a transaction"
+    );
+    assert_eq!(msg.identifier.seq_in_tx, 0);
 
     if let IngestionMessageKind::OperationEvent(op) = msg.kind {
         if let Operation::Insert { new: record } = op {
@@ -188,3 +199,76 @@ async fn ingest_grpc_arrow() {
         panic!("wrong message kind");
     }
 }
+
+#[tokio::test]
+async fn ingest_grpc_duplicates() {
+    let schemas = json!([{
+      "name": "users",
+      "schema": {
+        "fields": [
+          {
+            "name": "id",
+            "typ": "Int",
+            "nullable": false
+          }
+        ]
+      }
+    }]);
+
+    let (mut ingest_client, mut iterator) =
+        ingest_grpc(schemas, "default".to_string(), 45680).await;
+
+    let request = |id: i64, seq_no: u32| IngestRequest {
+        schema_name: "users".to_string(),
+        new: Some(types::Record {
+            values: vec![types::Value {
+                value: Some(types::value::Value::IntValue(id)),
+            }],
+            version: 1,
+        }),
+        seq_no,
+        producer_id: "producer".to_string(),
+        ..Default::default()
+    };
+    let checkpoint = GetCheckpointRequest {
+        producer_id: "producer".to_string(),
+    };
+
+    let res = ingest_client.get_checkpoint(checkpoint.clone()).await;
+    assert_eq!(res.unwrap().into_inner().seq_no, None);
+
+    let res = ingest_client.ingest(request(1, 1)).await.unwrap();
+    assert!(!res.into_inner().duplicate);
+    let res = ingest_client
+        .ingest(request(1, 1))
+        .await
+        .unwrap()
+        .into_inner();
+    assert!(res.duplicate);
+    assert_eq!(res.duplicate_seq_nos, vec![1]);
+    let res = ingest_client.ingest(request(2, 2)).await.unwrap();
+    assert!(!res.into_inner().duplicate);
+
+    let res = ingest_client.get_checkpoint(checkpoint).await;
+    assert_eq!(res.unwrap().into_inner().seq_no, Some(2));
+
+    // Duplicates are reported per request in streams.
+    let requests = vec![request(2, 2), request(3, 3), request(3, 3)];
+    let res = ingest_client
+        .ingest_stream(futures::stream::iter(requests))
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(res.seq_no, 3);
+    assert!(res.duplicate);
+    assert_eq!(res.duplicate_seq_nos, vec![2, 3]);
+
+    for (txid, id) in [(1, 1), (2, 2), (3, 3)] {
+        let msg = iterator.next().unwrap();
+        assert_eq!(msg.identifier.txid, txid);
+        let IngestionMessageKind::OperationEvent(Operation::Insert { new }) = msg.kind else {
+            panic!("wrong message kind");
+        };
+        assert_eq!(new.values[0].as_int(), Some(id));
+    }
+}
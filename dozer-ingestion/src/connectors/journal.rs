@@ -0,0 +1,137 @@
+use std::fs::{File, OpenOptions};
+use std::io::{BufReader, BufWriter, ErrorKind, Read, Write};
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+
+use dozer_types::bincode;
+use dozer_types::log::warn;
+use dozer_types::serde::{de::DeserializeOwned, Serialize};
+
+use crate::errors::JournalError;
+
+/// Append only file of the entries a connector needs to resume from a checkpoint. Entries are
+/// written as their length followed by their bincode encoding.
+#[derive(Debug)]
+pub struct Journal<T> {
+    path: PathBuf,
+    file: File,
+    entry: PhantomData<T>,
+}
+
+impl<T: Serialize + DeserializeOwned> Journal<T> {
+    /// Opens or creates the journal at `path`, returning its entries. An entry which was not
+    /// fully written is dropped, as entries are written before being acted upon.
+    pub fn open(path: &Path) -> Result<(Self, Vec<T>), JournalError> {
+        let entries = Self::read(path)?;
+
+        // Rewriting drops any partially written entry.
+        let journal = Self::create(path, &entries)?;
+        Ok((journal, entries))
+    }
+
+    /// Reads the entries of the journal at `path`, if any, without opening it.
+    pub fn read(path: &Path) -> Result<Vec<T>, JournalError> {
+        if !path.exists() {
+            return Ok(vec![]);
+        }
+        let file = File::open(path).map_err(|e| io_error(path, e))?;
+        read_entries(path, BufReader::new(file))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Replaces the entries of the journal.
+    pub fn compact(&mut self, entries: &[T]) -> Result<(), JournalError> {
+        *self = Self::create(&self.path, entries)?;
+        Ok(())
+    }
+
+    /// Appends an entry, which is persisted when this returns.
+    pub fn append(&mut self, entry: &T) -> Result<(), JournalError> {
+        self.file
+            .write_all(&encode(entry)?)
+            .and_then(|_| self.file.sync_data())
+            .map_err(|e| io_error(&self.path, e))
+    }
+
+    fn create(path: &Path, entries: &[T]) -> Result<Self, JournalError> {
+        let tmp_path = path.with_extension("tmp");
+        let file = File::create(&tmp_path).map_err(|e| io_error(&tmp_path, e))?;
+        let mut writer = BufWriter::new(file);
+        for entry in entries {
+            writer
+                .write_all(&encode(entry)?)
+                .map_err(|e| io_error(&tmp_path, e))?;
+        }
+        writer
+            .into_inner()
+            .map_err(|e| io_error(&tmp_path, e.into_error()))?
+            .sync_all()
+            .map_err(|e| io_error(&tmp_path, e))?;
+        std::fs::rename(&tmp_path, path).map_err(|e| io_error(path, e))?;
+
+        let file = OpenOptions::new()
+            .append(true)
+            .open(path)
+            .map_err(|e| io_error(path, e))?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            file,
+            entry: PhantomData,
+        })
+    }
+}
+
+fn io_error(path: &Path, e: std::io::Error) -> JournalError {
+    JournalError::IoError(path.display().to_string(), e)
+}
+
+fn encode(entry: &impl Serialize) -> Result<Vec<u8>, JournalError> {
+    let data = bincode::serialize(entry).map_err(JournalError::SerializationError)?;
+    let mut buf = Vec::with_capacity(data.len() + 4);
+    buf.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    buf.extend_from_slice(&data);
+    Ok(buf)
+}
+
+fn read_entries<T: DeserializeOwned>(
+    path: &Path,
+    mut reader: impl Read,
+) -> Result<Vec<T>, JournalError> {
+    let mut entries = vec![];
+    loop {
+        let mut len = [0; 4];
+        match read_exact(&mut reader, &mut len) {
+            Ok(true) => (),
+            Ok(false) => break,
+            Err(e) => return Err(io_error(path, e)),
+        }
+        let mut data = vec![0; u32::from_le_bytes(len) as usize];
+        match read_exact(&mut reader, &mut data) {
+            Ok(true) => (),
+            Ok(false) => {
+                warn!(
+                    "Dropping partially written entry of journal {}",
+                    path.display()
+                );
+                break;
+            }
+            Err(e) => return Err(io_error(path, e)),
+        }
+        let entry = bincode::deserialize(&data)
+            .map_err(|e| JournalError::CorruptedEntry(path.display().to_string(), e))?;
+        entries.push(entry);
+    }
+    Ok(entries)
+}
+
+/// Returns false if the reader ended before `buf` was filled.
+fn read_exact(reader: &mut impl Read, buf: &mut [u8]) -> Result<bool, std::io::Error> {
+    match reader.read_exact(buf) {
+        Ok(()) => Ok(true),
+        Err(e) if e.kind() == ErrorKind::UnexpectedEof => Ok(false),
+        Err(e) => Err(e),
+    }
+}
@@ -3,25 +3,85 @@ use crate::ingestion::Ingestor;
 use crate::{connectors::TableInfo, errors::ConnectorError};
 use dozer_types::ingestion_types::KafkaConfig;
 
-use dozer_types::types::SourceSchema;
+use dozer_types::log::info;
+use dozer_types::types::{ReplicationChangesTrackingType, SchemaIdentifier, SourceSchema};
+use kafka::client::KafkaClient;
 use kafka::consumer::{Consumer, FetchOffset, GroupOffsetStorage};
-use tokio::runtime::Runtime;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
 
 use crate::connectors::kafka::debezium::no_schema_registry::NoSchemaRegistry;
 use crate::connectors::kafka::debezium::schema_registry::SchemaRegistry;
 use crate::connectors::kafka::debezium::stream_consumer::DebeziumStreamConsumer;
+use crate::connectors::kafka::generic::decoder::{get_decoder, ValueDecoder};
+use crate::connectors::kafka::generic::stream_consumer::{GenericStreamConsumer, UpsertState};
+use crate::connectors::kafka::key_store::KeyStore;
+use crate::connectors::kafka::sequencer::{Partitions, Sequencer};
 use crate::connectors::kafka::stream_consumer::StreamConsumer;
 use crate::errors::DebeziumError::{DebeziumConnectionError, TopicNotDefined};
+use crate::errors::KafkaError::{MetadataFetchError, OffsetCommitError};
+use crate::errors::{DebeziumError, DebeziumStreamError};
 
 #[derive(Debug)]
 pub struct KafkaConnector {
     pub id: u64,
+    name: String,
     config: KafkaConfig,
+    sequencer: Sequencer,
 }
 
 impl KafkaConnector {
-    pub fn new(id: u64, config: KafkaConfig) -> Self {
-        Self { id, config }
+    pub fn new(id: u64, name: String, config: KafkaConfig) -> Self {
+        Self {
+            id,
+            name,
+            config,
+            sequencer: Sequencer::default(),
+        }
+    }
+
+    /// Consumer group through which the partition consumers are given their starting offsets.
+    fn group(&self) -> String {
+        format!("dozer-{}", self.name)
+    }
+
+    fn state_path(&self) -> Option<&Path> {
+        self.config.state_path.as_deref().map(Path::new)
+    }
+
+    fn is_debezium(&self) -> bool {
+        self.config.format == "debezium"
+    }
+
+    fn get_generic_schemas(
+        decoder: &dyn ValueDecoder,
+        tables: &[TableInfo],
+    ) -> Result<Vec<SourceSchema>, ConnectorError> {
+        tables
+            .iter()
+            .enumerate()
+            .map(|(id, table)| {
+                let mut schema = decoder.get_schema(&table.table_name)?;
+                schema.identifier = Some(SchemaIdentifier {
+                    id: id as u32,
+                    version: 1,
+                });
+                // Plain topics only carry the new value, so old records of updates and deletes
+                // only hold the primary key.
+                let replication_type = if schema.primary_index.is_empty() {
+                    ReplicationChangesTrackingType::Nothing
+                } else {
+                    ReplicationChangesTrackingType::OnlyPK
+                };
+                Ok(SourceSchema::new(
+                    table.table_name.clone(),
+                    schema,
+                    replication_type,
+                ))
+            })
+            .collect()
     }
 }
 
@@ -30,6 +90,11 @@ impl Connector for KafkaConnector {
         &self,
         table_names: Option<Vec<TableInfo>>,
     ) -> Result<Vec<SourceSchema>, ConnectorError> {
+        if !self.is_debezium() {
+            let decoder = get_decoder(&self.config)?;
+            return Self::get_generic_schemas(decoder.as_ref(), &table_names.unwrap_or_default());
+        }
+
         self.config.schema_registry_url.clone().map_or(
             NoSchemaRegistry::get_schema(table_names.clone(), self.config.clone()),
             |_| SchemaRegistry::get_schema(table_names, self.config.clone()),
@@ -38,18 +103,48 @@ impl Connector for KafkaConnector {
 
     fn start(
         &self,
-        _from_seq: Option<(u64, u64)>,
+        from_seq: Option<(u64, u64)>,
         ingestor: &Ingestor,
         tables: Vec<TableInfo>,
     ) -> Result<(), ConnectorError> {
-        let topic = tables
-            .get(0)
-            .map_or(Err(TopicNotDefined), |table| Ok(&table.table_name))?;
-
-        let broker = self.config.broker.to_owned();
-        Runtime::new()
-            .unwrap()
-            .block_on(async { run(broker, topic, ingestor).await })
+        if tables.is_empty() {
+            return Err(TopicNotDefined.into());
+        }
+
+        let consumer: Box<dyn StreamConsumer> = if self.is_debezium() {
+            Box::<DebeziumStreamConsumer>::default()
+        } else {
+            let decoder = get_decoder(&self.config)?;
+            let schemas = Self::get_generic_schemas(decoder.as_ref(), &tables)?
+                .into_iter()
+                .map(|s| (s.name, s.schema))
+                .collect();
+            Box::new(GenericStreamConsumer::new(decoder, schemas))
+        };
+
+        // Debezium envelopes carry a single table, so only the first topic is consumed.
+        let topics = if self.is_debezium() {
+            vec![tables[0].table_name.clone()]
+        } else {
+            tables.into_iter().map(|t| t.table_name).collect()
+        };
+
+        let (partitions, keys) = self.sequencer.open(self.state_path(), from_seq)?;
+        run(
+            &self.config.broker,
+            &self.group(),
+            &topics,
+            partitions,
+            &keys,
+            consumer.as_ref(),
+            &self.sequencer,
+            ingestor,
+        )
+    }
+
+    fn commit(&self, checkpoint: (u64, u64)) -> Result<(), ConnectorError> {
+        self.sequencer.commit(checkpoint);
+        Ok(())
     }
 
     fn validate(&self, _tables: Option<Vec<TableInfo>>) -> Result<(), ConnectorError> {
@@ -64,19 +159,114 @@ impl Connector for KafkaConnector {
         self.get_tables_default(tables)
     }
 
-    fn can_start_from(&self, _last_checkpoint: (u64, u64)) -> Result<bool, ConnectorError> {
-        Ok(false)
+    fn can_start_from(&self, last_checkpoint: (u64, u64)) -> Result<bool, ConnectorError> {
+        // The offsets of the partitions at a checkpoint are kept in the journal, with the key
+        // changes rolling the key store back to it.
+        match self.state_path() {
+            Some(path) => Sequencer::can_resume(path, last_checkpoint),
+            None => Ok(false),
+        }
     }
 }
 
-async fn run(broker: String, topic: &str, ingestor: &Ingestor) -> Result<(), ConnectorError> {
-    let con = Consumer::from_hosts(vec![broker])
-        .with_topic(topic.to_string())
-        .with_fallback_offset(FetchOffset::Earliest)
-        .with_offset_storage(GroupOffsetStorage::Kafka)
-        .create()
-        .map_err(DebeziumConnectionError)?;
+/// Consumes every partition of `topics` in its own thread, from the offsets in `partitions`
+/// or from the start, reading the upserted keys from `keys`. All consumers stop once one of
+/// them fails.
+fn run(
+    broker: &str,
+    group: &str,
+    topics: &[String],
+    mut partitions: Partitions,
+    keys: &Arc<KeyStore>,
+    consumer: &dyn StreamConsumer,
+    sequencer: &Sequencer,
+    ingestor: &Ingestor,
+) -> Result<(), ConnectorError> {
+    let mut client = KafkaClient::new(vec![broker.to_owned()]);
+    client.set_group_offset_storage(GroupOffsetStorage::Kafka);
+    client.load_metadata(topics).map_err(MetadataFetchError)?;
+
+    // Consumers start from the offsets committed to the group here, and never commit any.
+    let mut consumers = vec![];
+    for topic in topics {
+        let offsets = client
+            .fetch_topic_offsets(topic, FetchOffset::Earliest)
+            .map_err(MetadataFetchError)?;
+        for partition_offset in offsets {
+            let partition = partition_offset.partition;
+            let state = partitions
+                .remove(&(topic.clone(), partition))
+                .unwrap_or_default();
+            let offset = state.next_offset.unwrap_or(partition_offset.offset);
+            info!("[kafka] Consuming {topic}/{partition} from offset {offset}");
+            client
+                .commit_offset(group, topic, partition, offset)
+                .map_err(OffsetCommitError)?;
 
-    let consumer = DebeziumStreamConsumer::default();
-    consumer.run(con, ingestor)
+            let con = Consumer::from_hosts(vec![broker.to_owned()])
+                .with_topic_partitions(topic.clone(), &[partition])
+                .with_group(group.to_owned())
+                .with_fallback_offset(FetchOffset::Earliest)
+                .with_offset_storage(GroupOffsetStorage::Kafka)
+                .create()
+                .map_err(DebeziumConnectionError)?;
+            consumers.push((
+                con,
+                UpsertState::new(keys.clone(), topic.clone(), partition),
+            ));
+        }
+    }
+
+    let running = AtomicBool::new(true);
+    thread::scope(|scope| {
+        let handles = consumers
+            .into_iter()
+            .map(|(con, keys)| {
+                let running = &running;
+                scope.spawn(move || {
+                    let result = consume(con, keys, consumer, sequencer, ingestor, running);
+                    running.store(false, Ordering::Relaxed);
+                    result
+                })
+            })
+            .collect::<Vec<_>>();
+
+        handles
+            .into_iter()
+            .map(|handle| {
+                handle
+                    .join()
+                    .unwrap_or_else(|e| std::panic::resume_unwind(e))
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map(|_| ())
+    })
+}
+
+/// Consumes a partition until `running` is unset, ingesting every message set as a
+/// transaction.
+fn consume(
+    mut con: Consumer,
+    mut keys: UpsertState,
+    consumer: &dyn StreamConsumer,
+    sequencer: &Sequencer,
+    ingestor: &Ingestor,
+    running: &AtomicBool,
+) -> Result<(), ConnectorError> {
+    while running.load(Ordering::Relaxed) {
+        let mss = con.poll().map_err(|e| {
+            DebeziumError::DebeziumStreamError(DebeziumStreamError::PollingError(e))
+        })?;
+        for ms in mss.iter() {
+            let mut messages = vec![];
+            for m in ms.messages() {
+                if let Some((op, change)) = consumer.map_message(ms.topic(), m, &mut keys)? {
+                    messages.push((m.offset, op, change));
+                }
+            }
+            sequencer.ingest(ms.topic(), ms.partition(), messages, ingestor)?;
+            keys.clear_pending();
+        }
+    }
+    Ok(())
 }
@@ -0,0 +1,228 @@
+use crate::connectors::kafka::generic::decoder::ValueDecoder;
+use crate::errors::KafkaError;
+use crate::errors::KafkaError::{
+    AvroDecodeError, AvroSchemaError, FieldValueMismatch, SchemaRegistryFetchError,
+    TypeNotSupported,
+};
+use apache_avro::types::Value;
+use apache_avro::Schema as AvroSchema;
+use dozer_types::chrono::{NaiveDate, NaiveDateTime};
+use dozer_types::types::{Field, FieldDefinition, FieldType, Schema, SourceDefinition};
+use schema_registry_converter::blocking::avro::AvroDecoder as RegistryAvroDecoder;
+use schema_registry_converter::blocking::schema_registry::{get_schema_by_subject, SrSettings};
+use schema_registry_converter::schema_registry_common::SubjectNameStrategy;
+
+// Days between 0001-01-01 and 1970-01-01, avro dates are counted from the unix epoch.
+const UNIX_EPOCH_DAYS_FROM_CE: i32 = 719_163;
+
+/// Decodes avro values written in the schema registry wire format.
+pub struct AvroDecoder {
+    sr_settings: SrSettings,
+    decoder: RegistryAvroDecoder,
+}
+
+impl AvroDecoder {
+    pub fn new(schema_registry_url: String) -> Self {
+        Self {
+            sr_settings: SrSettings::new(schema_registry_url.clone()),
+            decoder: RegistryAvroDecoder::new(SrSettings::new(schema_registry_url)),
+        }
+    }
+
+    fn fetch_schema(&self, topic: &str, is_key: bool) -> Result<AvroSchema, KafkaError> {
+        let registered_schema = get_schema_by_subject(
+            &self.sr_settings,
+            &SubjectNameStrategy::TopicNameStrategy(topic.to_string(), is_key),
+        )
+        .map_err(SchemaRegistryFetchError)?;
+
+        AvroSchema::parse_str(&registered_schema.schema).map_err(AvroSchemaError)
+    }
+}
+
+impl ValueDecoder for AvroDecoder {
+    fn get_schema(&self, topic: &str) -> Result<Schema, KafkaError> {
+        let value_schema = self.fetch_schema(topic, false)?;
+        // Topics without a registered key schema, or with a primitive key, have no primary index.
+        let pk_fields = match self.fetch_schema(topic, true) {
+            Ok(AvroSchema::Record { fields, .. }) => fields.into_iter().map(|f| f.name).collect(),
+            _ => vec![],
+        };
+
+        let fields = match value_schema {
+            AvroSchema::Record { fields, .. } => fields,
+            _ => return Err(TypeNotSupported("Non record value".to_string())),
+        };
+
+        let mut primary_index = vec![];
+        let fields = fields
+            .iter()
+            .enumerate()
+            .map(|(idx, f)| {
+                let (typ, nullable) = map_type(&f.schema)?;
+                if pk_fields.contains(&f.name) {
+                    primary_index.push(idx);
+                }
+                Ok(FieldDefinition {
+                    name: f.name.clone(),
+                    typ,
+                    nullable,
+                    source: SourceDefinition::Dynamic,
+                })
+            })
+            .collect::<Result<Vec<_>, KafkaError>>()?;
+
+        Ok(Schema {
+            identifier: None,
+            fields,
+            primary_index,
+        })
+    }
+
+    fn decode(
+        &self,
+        _topic: &str,
+        schema: &Schema,
+        value: &[u8],
+    ) -> Result<Vec<Field>, KafkaError> {
+        let decoded = self.decoder.decode(Some(value)).map_err(AvroDecodeError)?;
+        let values = match decoded.value {
+            Value::Record(values) => values,
+            _ => return Err(TypeNotSupported("Non record value".to_string())),
+        };
+
+        schema
+            .fields
+            .iter()
+            .map(|f| {
+                values
+                    .iter()
+                    .find(|(name, _)| *name == f.name)
+                    .map_or(Ok(Field::Null), |(name, v)| convert_value(name, v, f.typ))
+            })
+            .collect()
+    }
+}
+
+pub fn map_type(schema: &AvroSchema) -> Result<(FieldType, bool), KafkaError> {
+    match schema {
+        AvroSchema::Union(union) => {
+            let nullable = union.is_nullable();
+            let schema = union
+                .variants()
+                .iter()
+                .find(|s| **s != AvroSchema::Null)
+                .ok_or_else(|| TypeNotSupported("Null".to_string()))?;
+            map_type(schema).map(|(typ, _)| (typ, nullable))
+        }
+        AvroSchema::Boolean => Ok((FieldType::Boolean, false)),
+        AvroSchema::Int | AvroSchema::Long => Ok((FieldType::Int, false)),
+        AvroSchema::Float | AvroSchema::Double => Ok((FieldType::Float, false)),
+        AvroSchema::String | AvroSchema::Enum { .. } | AvroSchema::Uuid => {
+            Ok((FieldType::String, false))
+        }
+        AvroSchema::Bytes | AvroSchema::Fixed { .. } => Ok((FieldType::Binary, false)),
+        AvroSchema::Date => Ok((FieldType::Date, false)),
+        AvroSchema::TimestampMillis | AvroSchema::TimestampMicros => {
+            Ok((FieldType::Timestamp, false))
+        }
+        _ => Err(TypeNotSupported(format!("{schema:?}"))),
+    }
+}
+
+pub fn convert_value(name: &str, value: &Value, typ: FieldType) -> Result<Field, KafkaError> {
+    let mismatch = || FieldValueMismatch(name.to_string());
+    match (value, typ) {
+        (Value::Null, _) => Ok(Field::Null),
+        (Value::Union(_, value), _) => convert_value(name, value, typ),
+        (Value::Boolean(b), FieldType::Boolean) => Ok(Field::Boolean(*b)),
+        (Value::Int(i), FieldType::Int) => Ok(Field::from(*i)),
+        (Value::Long(l), FieldType::Int) => Ok(Field::from(*l)),
+        (Value::Float(f), FieldType::Float) => Ok(Field::from(*f as f64)),
+        (Value::Double(d), FieldType::Float) => Ok(Field::from(*d)),
+        (Value::String(s), FieldType::String) | (Value::Enum(_, s), FieldType::String) => {
+            Ok(Field::String(s.clone()))
+        }
+        (Value::Uuid(u), FieldType::String) => Ok(Field::String(u.to_string())),
+        (Value::Bytes(b), FieldType::Binary) | (Value::Fixed(_, b), FieldType::Binary) => {
+            Ok(Field::Binary(b.clone()))
+        }
+        (Value::Date(days), FieldType::Date) => {
+            NaiveDate::from_num_days_from_ce_opt(days + UNIX_EPOCH_DAYS_FROM_CE)
+                .map(Field::from)
+                .ok_or_else(mismatch)
+        }
+        (Value::TimestampMillis(ms), FieldType::Timestamp) => {
+            NaiveDateTime::from_timestamp_millis(*ms)
+                .map(Field::from)
+                .ok_or_else(mismatch)
+        }
+        (Value::TimestampMicros(us), FieldType::Timestamp) => {
+            NaiveDateTime::from_timestamp_opt(us / 1_000_000, ((us % 1_000_000) * 1000) as u32)
+                .map(Field::from)
+                .ok_or_else(mismatch)
+        }
+        _ => Err(mismatch()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::connectors::kafka::generic::avro::{convert_value, map_type};
+    use apache_avro::types::Value;
+    use apache_avro::Schema as AvroSchema;
+    use dozer_types::chrono::{NaiveDate, NaiveDateTime};
+    use dozer_types::types::{Field, FieldType};
+
+    #[test]
+    fn test_it_maps_avro_types() {
+        let schema = AvroSchema::parse_str(r#"["null", "long"]"#).unwrap();
+        assert_eq!(map_type(&schema).unwrap(), (FieldType::Int, true));
+
+        let schema = AvroSchema::parse_str(r#""string""#).unwrap();
+        assert_eq!(map_type(&schema).unwrap(), (FieldType::String, false));
+
+        let schema = AvroSchema::parse_str(r#"{"type": "int", "logicalType": "date"}"#).unwrap();
+        assert_eq!(map_type(&schema).unwrap(), (FieldType::Date, false));
+
+        let schema = AvroSchema::parse_str(r#"{"type": "array", "items": "int"}"#).unwrap();
+        assert!(map_type(&schema).is_err());
+    }
+
+    #[test]
+    fn test_it_converts_avro_values() {
+        assert_eq!(
+            convert_value("a", &Value::Long(5), FieldType::Int).unwrap(),
+            Field::Int(5)
+        );
+        assert_eq!(
+            convert_value(
+                "a",
+                &Value::Union(1, Box::new(Value::String("b".to_string()))),
+                FieldType::String
+            )
+            .unwrap(),
+            Field::String("b".to_string())
+        );
+        assert_eq!(
+            convert_value("a", &Value::Union(0, Box::new(Value::Null)), FieldType::Int).unwrap(),
+            Field::Null
+        );
+        assert_eq!(
+            convert_value("a", &Value::Date(19000), FieldType::Date).unwrap(),
+            Field::from(NaiveDate::from_ymd_opt(2022, 1, 8).unwrap())
+        );
+        assert_eq!(
+            convert_value(
+                "a",
+                &Value::TimestampMillis(1669654543000),
+                FieldType::Timestamp
+            )
+            .unwrap(),
+            Field::from(
+                NaiveDateTime::parse_from_str("2022-11-28 16:55:43", "%Y-%m-%d %H:%M:%S").unwrap()
+            )
+        );
+        assert!(convert_value("a", &Value::Boolean(true), FieldType::Int).is_err());
+    }
+}
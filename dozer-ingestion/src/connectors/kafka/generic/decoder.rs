@@ -0,0 +1,31 @@
+use crate::connectors::kafka::generic::avro::AvroDecoder;
+use crate::connectors::kafka::generic::json::JsonDecoder;
+use crate::connectors::kafka::generic::protobuf::ProtobufDecoder;
+use crate::errors::KafkaError;
+use dozer_types::ingestion_types::KafkaConfig;
+use dozer_types::types::{Field, Schema};
+
+/// Decodes the values of plain (non Debezium) topics into dozer fields.
+pub trait ValueDecoder: Send + Sync {
+    /// Returns the schema of the topic. Fields of the message key form the primary index.
+    fn get_schema(&self, topic: &str) -> Result<Schema, KafkaError>;
+
+    /// Decodes a message value into fields, following the order of `schema` fields.
+    fn decode(&self, topic: &str, schema: &Schema, value: &[u8]) -> Result<Vec<Field>, KafkaError>;
+}
+
+pub fn get_decoder(config: &KafkaConfig) -> Result<Box<dyn ValueDecoder>, KafkaError> {
+    match config.format.as_str() {
+        "json" => Ok(Box::new(JsonDecoder::new(config)?)),
+        "avro" => Ok(Box::new(AvroDecoder::new(get_registry_url(config)?))),
+        "protobuf" => Ok(Box::new(ProtobufDecoder::new(get_registry_url(config)?))),
+        _ => Err(KafkaError::UnsupportedFormat(config.format.clone())),
+    }
+}
+
+fn get_registry_url(config: &KafkaConfig) -> Result<String, KafkaError> {
+    config
+        .schema_registry_url
+        .clone()
+        .ok_or(KafkaError::SchemaRegistryUrlNotDefined)
+}
@@ -0,0 +1,120 @@
+use crate::connectors::kafka::generic::decoder::ValueDecoder;
+use crate::errors::KafkaError;
+use crate::errors::KafkaError::{JsonDecodeError, SchemaNotFound, SchemasNotDefined};
+use dozer_types::ingestion_types::{KafkaConfig, KafkaConfigSchemas};
+use dozer_types::json_value_to_field;
+use dozer_types::serde_json;
+use dozer_types::serde_json::Value;
+use dozer_types::types::{Field, Schema, SourceSchema};
+use std::collections::HashMap;
+
+/// Decodes schemaless JSON values, using the schemas declared in the connection config.
+pub struct JsonDecoder {
+    schemas: HashMap<String, Schema>,
+}
+
+impl JsonDecoder {
+    pub fn new(config: &KafkaConfig) -> Result<Self, KafkaError> {
+        let schemas_str = match config.schemas.as_ref().ok_or(SchemasNotDefined)? {
+            KafkaConfigSchemas::Inline(schemas_str) => schemas_str.clone(),
+            KafkaConfigSchemas::Path(path) => {
+                std::fs::read_to_string(path).map_err(KafkaError::SchemasReadError)?
+            }
+        };
+
+        let schemas: Vec<SourceSchema> =
+            serde_json::from_str(&schemas_str).map_err(JsonDecodeError)?;
+
+        Ok(Self {
+            schemas: schemas.into_iter().map(|s| (s.name, s.schema)).collect(),
+        })
+    }
+}
+
+impl ValueDecoder for JsonDecoder {
+    fn get_schema(&self, topic: &str) -> Result<Schema, KafkaError> {
+        self.schemas
+            .get(topic)
+            .cloned()
+            .ok_or_else(|| SchemaNotFound(topic.to_string()))
+    }
+
+    fn decode(
+        &self,
+        _topic: &str,
+        schema: &Schema,
+        value: &[u8],
+    ) -> Result<Vec<Field>, KafkaError> {
+        let value: Value = serde_json::from_slice(value).map_err(JsonDecodeError)?;
+
+        schema
+            .fields
+            .iter()
+            .map(|f| {
+                let field_value = value.get(&f.name).cloned().unwrap_or(Value::Null);
+                json_value_to_field(field_value, f.typ, f.nullable).map_err(KafkaError::TypeError)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::connectors::kafka::generic::decoder::ValueDecoder;
+    use crate::connectors::kafka::generic::json::JsonDecoder;
+    use dozer_types::ingestion_types::{KafkaConfig, KafkaConfigSchemas};
+    use dozer_types::serde_json::json;
+    use dozer_types::types::Field;
+
+    fn get_decoder() -> JsonDecoder {
+        let schemas = json!([{
+          "name": "users",
+          "schema": {
+            "fields": [
+              { "name": "id", "typ": "Int", "nullable": false },
+              { "name": "name", "typ": "String", "nullable": true }
+            ],
+            "primary_index": [0]
+          }
+        }]);
+
+        JsonDecoder::new(&KafkaConfig {
+            broker: "localhost:9092".to_string(),
+            schema_registry_url: None,
+            format: "json".to_string(),
+            schemas: Some(KafkaConfigSchemas::Inline(schemas.to_string())),
+            state_path: None,
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_it_decodes_json_value() {
+        let decoder = get_decoder();
+        let schema = decoder.get_schema("users").unwrap();
+        assert_eq!(schema.primary_index, vec![0]);
+
+        let fields = decoder
+            .decode("users", &schema, br#"{"id": 1, "name": "John", "age": 42}"#)
+            .unwrap();
+        assert_eq!(
+            fields,
+            vec![Field::Int(1), Field::String("John".to_string())]
+        );
+
+        let fields = decoder.decode("users", &schema, br#"{"id": 2}"#).unwrap();
+        assert_eq!(fields, vec![Field::Int(2), Field::Null]);
+    }
+
+    #[test]
+    fn test_it_fails_on_missing_schema_or_wrong_value() {
+        let decoder = get_decoder();
+        assert!(decoder.get_schema("orders").is_err());
+
+        let schema = decoder.get_schema("users").unwrap();
+        assert!(decoder
+            .decode("users", &schema, br#"{"id": "abc"}"#)
+            .is_err());
+        assert!(decoder.decode("users", &schema, b"not json").is_err());
+    }
+}
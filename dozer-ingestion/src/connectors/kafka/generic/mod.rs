@@ -0,0 +1,5 @@
+pub mod avro;
+pub mod decoder;
+pub mod json;
+pub mod protobuf;
+pub mod stream_consumer;
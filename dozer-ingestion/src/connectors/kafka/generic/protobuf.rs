@@ -0,0 +1,312 @@
+use crate::connectors::kafka::generic::decoder::ValueDecoder;
+use crate::errors::KafkaError;
+use crate::errors::KafkaError::{
+    FieldValueMismatch, InvalidWireFormat, ProtobufDecodeError, ProtobufDescriptorError,
+    ProtobufSchemaError, ProtobufSchemaWriteError, SchemaNotFound, SchemaRegistryFetchError,
+    TypeNotSupported,
+};
+use dozer_types::chrono::NaiveDateTime;
+use dozer_types::parking_lot::Mutex;
+use dozer_types::types::{Field, FieldDefinition, FieldType, Schema, SourceDefinition};
+use prost_reflect::{
+    DescriptorPool, DynamicMessage, FieldDescriptor, Kind, MessageDescriptor, Value,
+};
+use protobuf::descriptor::FileDescriptorSet;
+use protobuf::Message;
+use schema_registry_converter::blocking::schema_registry::{
+    get_referenced_schema, get_schema_by_id, get_schema_by_subject, SrSettings,
+};
+use schema_registry_converter::schema_registry_common::{RegisteredSchema, SubjectNameStrategy};
+use std::collections::HashMap;
+use std::path::Path;
+use tempdir::TempDir;
+
+const TIMESTAMP_MESSAGE: &str = "google.protobuf.Timestamp";
+
+/// Decodes protobuf values written in the schema registry wire format.
+///
+/// The registry returns `.proto` files, which are compiled with their references to
+/// descriptors used for dynamic decoding.
+pub struct ProtobufDecoder {
+    sr_settings: SrSettings,
+    descriptors: Mutex<HashMap<(u32, Vec<i64>), MessageDescriptor>>,
+}
+
+impl ProtobufDecoder {
+    pub fn new(schema_registry_url: String) -> Self {
+        Self {
+            sr_settings: SrSettings::new(schema_registry_url),
+            descriptors: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Writes the `.proto` file of `schema` to `path` under `dir`, with all its references under their import paths.
+    fn write_schema(
+        &self,
+        dir: &Path,
+        path: &str,
+        schema: &RegisteredSchema,
+    ) -> Result<(), KafkaError> {
+        for reference in &schema.references {
+            let referenced = get_referenced_schema(&self.sr_settings, reference)
+                .map_err(SchemaRegistryFetchError)?;
+            self.write_schema(dir, &reference.name, &referenced)?;
+        }
+
+        let file_path = dir.join(path);
+        if let Some(parent) = file_path.parent() {
+            std::fs::create_dir_all(parent).map_err(ProtobufSchemaWriteError)?;
+        }
+        std::fs::write(file_path, &schema.schema).map_err(ProtobufSchemaWriteError)
+    }
+
+    fn get_message_descriptor(
+        &self,
+        schema: &RegisteredSchema,
+        indexes: &[i64],
+    ) -> Result<MessageDescriptor, KafkaError> {
+        let dir = TempDir::new("protobuf").map_err(ProtobufSchemaWriteError)?;
+        let file_name = format!("schema_{}.proto", schema.id);
+        self.write_schema(dir.path(), &file_name, schema)?;
+
+        let parsed = protobuf_parse::Parser::new()
+            .pure()
+            .include(dir.path())
+            .input(dir.path().join(&file_name))
+            .parse_and_typecheck()
+            .map_err(|e| ProtobufSchemaError(format!("{e:#}")))?;
+        let mut files = FileDescriptorSet::new();
+        files.file = parsed.file_descriptors;
+        let bytes = files
+            .write_to_bytes()
+            .map_err(|e| ProtobufSchemaError(e.to_string()))?;
+        let pool = DescriptorPool::decode(bytes.as_slice()).map_err(ProtobufDescriptorError)?;
+        let file = pool
+            .get_file_by_name(&file_name)
+            .ok_or_else(|| SchemaNotFound(file_name.clone()))?;
+
+        let mut messages: Vec<MessageDescriptor> = file.messages().collect();
+        let mut descriptor = None;
+        for index in indexes {
+            let message = messages
+                .get(*index as usize)
+                .cloned()
+                .ok_or(InvalidWireFormat)?;
+            messages = message.child_messages().collect();
+            descriptor = Some(message);
+        }
+        descriptor.ok_or(InvalidWireFormat)
+    }
+
+    fn get_subject_descriptor(
+        &self,
+        topic: &str,
+        is_key: bool,
+    ) -> Result<MessageDescriptor, KafkaError> {
+        let schema = get_schema_by_subject(
+            &self.sr_settings,
+            &SubjectNameStrategy::TopicNameStrategy(topic.to_string(), is_key),
+        )
+        .map_err(SchemaRegistryFetchError)?;
+        self.get_message_descriptor(&schema, &[0])
+    }
+}
+
+impl ValueDecoder for ProtobufDecoder {
+    fn get_schema(&self, topic: &str) -> Result<Schema, KafkaError> {
+        let value_descriptor = self.get_subject_descriptor(topic, false)?;
+        // Topics without a registered key schema have no primary index.
+        let pk_fields: Vec<String> = self
+            .get_subject_descriptor(topic, true)
+            .map_or(vec![], |key| {
+                key.fields().map(|f| f.name().to_string()).collect()
+            });
+
+        let mut primary_index = vec![];
+        let fields = value_descriptor
+            .fields()
+            .enumerate()
+            .map(|(idx, f)| {
+                if pk_fields.iter().any(|name| name == f.name()) {
+                    primary_index.push(idx);
+                }
+                Ok(FieldDefinition {
+                    name: f.name().to_string(),
+                    typ: map_type(&f)?,
+                    nullable: f.supports_presence(),
+                    source: SourceDefinition::Dynamic,
+                })
+            })
+            .collect::<Result<Vec<_>, KafkaError>>()?;
+
+        Ok(Schema {
+            identifier: None,
+            fields,
+            primary_index,
+        })
+    }
+
+    fn decode(
+        &self,
+        _topic: &str,
+        schema: &Schema,
+        value: &[u8],
+    ) -> Result<Vec<Field>, KafkaError> {
+        let (schema_id, indexes, payload) = parse_wire_format(value)?;
+
+        let key = (schema_id, indexes);
+        let cached = self.descriptors.lock().get(&key).cloned();
+        let descriptor = match cached {
+            Some(descriptor) => descriptor,
+            None => {
+                let registered_schema = get_schema_by_id(schema_id, &self.sr_settings)
+                    .map_err(SchemaRegistryFetchError)?;
+                let descriptor = self.get_message_descriptor(&registered_schema, &key.1)?;
+                self.descriptors.lock().insert(key, descriptor.clone());
+                descriptor
+            }
+        };
+
+        let message = DynamicMessage::decode(descriptor, payload).map_err(ProtobufDecodeError)?;
+        schema
+            .fields
+            .iter()
+            .map(|f| match message.descriptor().get_field_by_name(&f.name) {
+                Some(field) if !field.supports_presence() || message.has_field(&field) => {
+                    convert_value(&f.name, &message.get_field(&field), f.typ)
+                }
+                _ => Ok(Field::Null),
+            })
+            .collect()
+    }
+}
+
+/// Splits a message into schema id, message indexes and payload.
+pub fn parse_wire_format(bytes: &[u8]) -> Result<(u32, Vec<i64>, &[u8]), KafkaError> {
+    if bytes.len() < 5 || bytes[0] != 0 {
+        return Err(InvalidWireFormat);
+    }
+    let schema_id = u32::from_be_bytes(bytes[1..5].try_into().map_err(|_| InvalidWireFormat)?);
+
+    let mut buf = &bytes[5..];
+    let count = read_zigzag_varint(&mut buf)?;
+    // A single zero byte is the shortcut for the first message in the file.
+    let indexes = if count == 0 {
+        vec![0]
+    } else {
+        (0..count)
+            .map(|_| read_zigzag_varint(&mut buf))
+            .collect::<Result<Vec<_>, _>>()?
+    };
+    Ok((schema_id, indexes, buf))
+}
+
+fn read_zigzag_varint(buf: &mut &[u8]) -> Result<i64, KafkaError> {
+    let mut value: u64 = 0;
+    for shift in (0..64).step_by(7) {
+        let (byte, rest) = buf.split_first().ok_or(InvalidWireFormat)?;
+        *buf = rest;
+        value |= ((byte & 0x7f) as u64) << shift;
+        if byte & 0x80 == 0 {
+            return Ok((value >> 1) as i64 ^ -((value & 1) as i64));
+        }
+    }
+    Err(InvalidWireFormat)
+}
+
+pub fn map_type(field: &FieldDescriptor) -> Result<FieldType, KafkaError> {
+    if field.is_list() || field.is_map() {
+        return Err(TypeNotSupported(format!("repeated {}", field.name())));
+    }
+    match field.kind() {
+        Kind::Double | Kind::Float => Ok(FieldType::Float),
+        Kind::Int32
+        | Kind::Int64
+        | Kind::Sint32
+        | Kind::Sint64
+        | Kind::Sfixed32
+        | Kind::Sfixed64
+        | Kind::Enum(_) => Ok(FieldType::Int),
+        Kind::Uint32 | Kind::Uint64 | Kind::Fixed32 | Kind::Fixed64 => Ok(FieldType::UInt),
+        Kind::Bool => Ok(FieldType::Boolean),
+        Kind::String => Ok(FieldType::String),
+        Kind::Bytes => Ok(FieldType::Binary),
+        Kind::Message(message) if message.full_name() == TIMESTAMP_MESSAGE => {
+            Ok(FieldType::Timestamp)
+        }
+        Kind::Message(message) => Err(TypeNotSupported(message.full_name().to_string())),
+    }
+}
+
+pub fn convert_value(name: &str, value: &Value, typ: FieldType) -> Result<Field, KafkaError> {
+    let mismatch = || FieldValueMismatch(name.to_string());
+    match (value, typ) {
+        (Value::Bool(b), FieldType::Boolean) => Ok(Field::Boolean(*b)),
+        (Value::I32(i), FieldType::Int) | (Value::EnumNumber(i), FieldType::Int) => {
+            Ok(Field::from(*i))
+        }
+        (Value::I64(i), FieldType::Int) => Ok(Field::from(*i)),
+        (Value::U32(u), FieldType::UInt) => Ok(Field::UInt(*u as u64)),
+        (Value::U64(u), FieldType::UInt) => Ok(Field::UInt(*u)),
+        (Value::F32(f), FieldType::Float) => Ok(Field::from(*f as f64)),
+        (Value::F64(f), FieldType::Float) => Ok(Field::from(*f)),
+        (Value::String(s), FieldType::String) => Ok(Field::String(s.clone())),
+        (Value::Bytes(b), FieldType::Binary) => Ok(Field::Binary(b.to_vec())),
+        (Value::Message(message), FieldType::Timestamp) => {
+            let seconds = message
+                .get_field_by_name("seconds")
+                .and_then(|v| v.as_i64())
+                .unwrap_or_default();
+            let nanos = message
+                .get_field_by_name("nanos")
+                .and_then(|v| v.as_i32())
+                .unwrap_or_default();
+            NaiveDateTime::from_timestamp_opt(seconds, nanos as u32)
+                .map(Field::from)
+                .ok_or_else(mismatch)
+        }
+        _ => Err(mismatch()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::connectors::kafka::generic::protobuf::{convert_value, parse_wire_format};
+    use dozer_types::types::{Field, FieldType};
+    use prost_reflect::Value;
+
+    #[test]
+    fn test_it_parses_wire_format() {
+        let (schema_id, indexes, payload) = parse_wire_format(&[0, 0, 0, 0, 7, 0, 8, 1]).unwrap();
+        assert_eq!(schema_id, 7);
+        assert_eq!(indexes, vec![0]);
+        assert_eq!(payload, &[8, 1]);
+
+        // Two indexes: 1 and 2, zigzag encoded.
+        let (schema_id, indexes, payload) =
+            parse_wire_format(&[0, 0, 0, 1, 0, 4, 2, 4, 8, 1]).unwrap();
+        assert_eq!(schema_id, 256);
+        assert_eq!(indexes, vec![1, 2]);
+        assert_eq!(payload, &[8, 1]);
+
+        assert!(parse_wire_format(&[1, 0, 0, 0, 7, 0]).is_err());
+        assert!(parse_wire_format(&[0, 0, 0]).is_err());
+    }
+
+    #[test]
+    fn test_it_converts_protobuf_values() {
+        assert_eq!(
+            convert_value("a", &Value::I32(3), FieldType::Int).unwrap(),
+            Field::Int(3)
+        );
+        assert_eq!(
+            convert_value("a", &Value::U64(3), FieldType::UInt).unwrap(),
+            Field::UInt(3)
+        );
+        assert_eq!(
+            convert_value("a", &Value::String("b".to_string()), FieldType::String).unwrap(),
+            Field::String("b".to_string())
+        );
+        assert!(convert_value("a", &Value::Bool(true), FieldType::String).is_err());
+    }
+}
@@ -0,0 +1,274 @@
+use crate::connectors::kafka::generic::decoder::ValueDecoder;
+use crate::connectors::kafka::key_store::KeyStore;
+use crate::connectors::kafka::stream_consumer::StreamConsumer;
+use crate::errors::{ConnectorError, KafkaError};
+use dozer_types::serde::{Deserialize, Serialize};
+use dozer_types::types::{Field, Operation, Record, Schema};
+use kafka::consumer::Message;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Change of the primary key stored for a message key, `None` once the key is deleted. The
+/// previous primary key is kept so that the change can be rolled back.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(crate = "dozer_types::serde")]
+pub struct KeyChange {
+    pub key: Vec<u8>,
+    pub record: Option<Record>,
+    pub old: Option<Record>,
+}
+
+/// Tracks the primary key of every message key seen on a partition, so that messages map to
+/// upserts and tombstones (messages with an empty value) map to deletes.
+///
+/// Keys are read from the `KeyStore`, which is written once the messages are ingested. Only the
+/// changes of the messages mapped since then are kept in memory.
+#[derive(Debug)]
+pub struct UpsertState {
+    store: Arc<KeyStore>,
+    topic: String,
+    partition: i32,
+    // Keys changed by the messages which were not ingested yet
+    pending: HashMap<Vec<u8>, Option<Record>>,
+}
+
+impl UpsertState {
+    pub fn new(store: Arc<KeyStore>, topic: String, partition: i32) -> Self {
+        Self {
+            store,
+            topic,
+            partition,
+            pending: HashMap::new(),
+        }
+    }
+
+    fn get(&self, key: &[u8]) -> Result<Option<Record>, ConnectorError> {
+        match self.pending.get(key) {
+            Some(record) => Ok(record.clone()),
+            None => self.store.get(&self.topic, self.partition, key),
+        }
+    }
+
+    /// Returns the operation of a message, with the change of its key which was applied.
+    pub fn map_message(
+        &mut self,
+        schema: &Schema,
+        key: &[u8],
+        values: Option<Vec<Field>>,
+    ) -> Result<Option<(Operation, Option<KeyChange>)>, ConnectorError> {
+        match values {
+            Some(values) => {
+                let new = Record::new(schema.identifier, values, None);
+                if schema.primary_index.is_empty() || key.is_empty() {
+                    return Ok(Some((Operation::Insert { new }, None)));
+                }
+
+                let mut key_record = Record::nulls(schema.identifier, schema.fields.len(), None);
+                for idx in &schema.primary_index {
+                    key_record.set_value(*idx, new.values[*idx].clone());
+                }
+
+                let old = self.get(key)?;
+                self.pending.insert(key.to_vec(), Some(key_record.clone()));
+                let change = KeyChange {
+                    key: key.to_vec(),
+                    record: Some(key_record),
+                    old: old.clone(),
+                };
+                let op = match old {
+                    Some(old) => Operation::Update { old, new },
+                    None => Operation::Insert { new },
+                };
+                Ok(Some((op, Some(change))))
+            }
+            None => {
+                let Some(old) = self.get(key)? else {
+                    return Ok(None);
+                };
+                self.pending.insert(key.to_vec(), None);
+                let change = KeyChange {
+                    key: key.to_vec(),
+                    record: None,
+                    old: Some(old.clone()),
+                };
+                Ok(Some((Operation::Delete { old }, Some(change))))
+            }
+        }
+    }
+
+    /// Forgets the changes of the mapped messages, once they were ingested and written to the
+    /// store.
+    pub fn clear_pending(&mut self) {
+        self.pending.clear();
+    }
+}
+
+pub struct GenericStreamConsumer {
+    decoder: Box<dyn ValueDecoder>,
+    schemas: HashMap<String, Schema>,
+}
+
+impl GenericStreamConsumer {
+    pub fn new(decoder: Box<dyn ValueDecoder>, schemas: HashMap<String, Schema>) -> Self {
+        Self { decoder, schemas }
+    }
+}
+
+impl StreamConsumer for GenericStreamConsumer {
+    fn map_message(
+        &self,
+        topic: &str,
+        message: &Message,
+        keys: &mut UpsertState,
+    ) -> Result<Option<(Operation, Option<KeyChange>)>, ConnectorError> {
+        let schema = self
+            .schemas
+            .get(topic)
+            .ok_or_else(|| KafkaError::SchemaNotFound(topic.to_string()))?;
+
+        let values = if message.value.is_empty() {
+            None
+        } else {
+            Some(self.decoder.decode(topic, schema, message.value)?)
+        };
+        keys.map_message(schema, message.key, values)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::connectors::kafka::generic::stream_consumer::{KeyChange, UpsertState};
+    use crate::connectors::kafka::key_store::KeyStore;
+    use dozer_types::types::{
+        Field, FieldDefinition, FieldType, Operation, Record, Schema, SchemaIdentifier,
+        SourceDefinition,
+    };
+    use std::sync::Arc;
+
+    fn get_schema(primary_index: Vec<usize>) -> Schema {
+        Schema {
+            identifier: Some(SchemaIdentifier { id: 0, version: 1 }),
+            fields: vec![
+                FieldDefinition::new(
+                    "id".to_string(),
+                    FieldType::Int,
+                    false,
+                    SourceDefinition::Dynamic,
+                ),
+                FieldDefinition::new(
+                    "name".to_string(),
+                    FieldType::String,
+                    true,
+                    SourceDefinition::Dynamic,
+                ),
+            ],
+            primary_index,
+        }
+    }
+
+    fn get_state(store: &Arc<KeyStore>, partition: i32) -> UpsertState {
+        UpsertState::new(store.clone(), "users".to_string(), partition)
+    }
+
+    #[test]
+    fn test_it_maps_messages_to_upserts_and_deletes() {
+        let schema = get_schema(vec![0]);
+        let store = Arc::new(KeyStore::open(None).unwrap());
+        let mut state = get_state(&store, 0);
+        let key_record = Record::new(schema.identifier, vec![Field::Int(1), Field::Null], None);
+
+        let first = vec![Field::Int(1), Field::String("a".to_string())];
+        assert_eq!(
+            state
+                .map_message(&schema, b"1", Some(first.clone()))
+                .unwrap(),
+            Some((
+                Operation::Insert {
+                    new: Record::new(schema.identifier, first, None)
+                },
+                Some(KeyChange {
+                    key: b"1".to_vec(),
+                    record: Some(key_record.clone()),
+                    old: None,
+                })
+            ))
+        );
+
+        let second = vec![Field::Int(1), Field::String("b".to_string())];
+        assert_eq!(
+            state
+                .map_message(&schema, b"1", Some(second.clone()))
+                .unwrap(),
+            Some((
+                Operation::Update {
+                    old: key_record.clone(),
+                    new: Record::new(schema.identifier, second, None)
+                },
+                Some(KeyChange {
+                    key: b"1".to_vec(),
+                    record: Some(key_record.clone()),
+                    old: Some(key_record.clone()),
+                })
+            ))
+        );
+
+        let delete = KeyChange {
+            key: b"1".to_vec(),
+            record: None,
+            old: Some(key_record.clone()),
+        };
+        assert_eq!(
+            state.map_message(&schema, b"1", None).unwrap(),
+            Some((
+                Operation::Delete {
+                    old: key_record.clone()
+                },
+                Some(delete.clone())
+            ))
+        );
+
+        // Tombstone of an unknown key is ignored.
+        assert_eq!(state.map_message(&schema, b"1", None).unwrap(), None);
+
+        // Once the messages are ingested, their keys are read from the store.
+        store
+            .set("users", 0, [(b"1".as_slice(), Some(&key_record))])
+            .unwrap();
+        state.clear_pending();
+        assert_eq!(
+            state.map_message(&schema, b"1", None).unwrap(),
+            Some((Operation::Delete { old: key_record }, Some(delete)))
+        );
+
+        // Keys of other partitions are not shared.
+        assert_eq!(
+            get_state(&store, 1)
+                .map_message(&schema, b"1", None)
+                .unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_it_inserts_without_primary_key() {
+        let schema = get_schema(vec![]);
+        let store = Arc::new(KeyStore::open(None).unwrap());
+        let mut state = get_state(&store, 0);
+
+        let values = vec![Field::Int(1), Field::Null];
+        for _ in 0..2 {
+            assert_eq!(
+                state
+                    .map_message(&schema, b"1", Some(values.clone()))
+                    .unwrap(),
+                Some((
+                    Operation::Insert {
+                        new: Record::new(schema.identifier, values.clone(), None)
+                    },
+                    None
+                ))
+            );
+        }
+        assert_eq!(state.map_message(&schema, b"1", None).unwrap(), None);
+    }
+}
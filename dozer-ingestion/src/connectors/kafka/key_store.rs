@@ -0,0 +1,126 @@
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+use dozer_types::bincode;
+use dozer_types::parking_lot::Mutex;
+use dozer_types::types::Record;
+use rusqlite::{params, Connection, OptionalExtension};
+use tempdir::TempDir;
+
+use crate::errors::{ConnectorError, KafkaError};
+
+/// Primary key of the upserted message keys of the consumed partitions, kept on disk in a
+/// SQLite database so that the number of keys is not bounded by memory.
+///
+/// The store is written when the messages are ingested, after their key changes were
+/// journaled, so that the changes of the messages which were not committed can be rolled back.
+#[derive(Debug)]
+pub struct KeyStore {
+    conn: Mutex<Connection>,
+    // Directory of the database when there is no state path, removed with the store
+    _temp_dir: Option<TempDir>,
+}
+
+impl KeyStore {
+    /// Opens the store next to the journal at `state_path`, or in a temporary directory.
+    pub fn open(state_path: Option<&Path>) -> Result<Self, ConnectorError> {
+        let (path, temp_dir) = match state_path {
+            Some(state_path) => (Self::path(state_path), None),
+            None => {
+                let dir = TempDir::new("kafka_keys").map_err(KafkaError::KeyStoreIoError)?;
+                (dir.path().join("keys"), Some(dir))
+            }
+        };
+        let conn = Connection::open(&path).map_err(KafkaError::KeyStoreError)?;
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS keys (
+                topic TEXT NOT NULL,
+                partition INTEGER NOT NULL,
+                key BLOB NOT NULL,
+                record BLOB NOT NULL,
+                PRIMARY KEY (topic, partition, key)
+            )",
+            [],
+        )
+        .map_err(KafkaError::KeyStoreError)?;
+        Ok(Self {
+            conn: Mutex::new(conn),
+            _temp_dir: temp_dir,
+        })
+    }
+
+    /// Path of the store kept with the journal at `state_path`.
+    pub fn path(state_path: &Path) -> PathBuf {
+        let mut path = OsString::from(state_path);
+        path.push(".keys");
+        path.into()
+    }
+
+    pub fn get(
+        &self,
+        topic: &str,
+        partition: i32,
+        key: &[u8],
+    ) -> Result<Option<Record>, ConnectorError> {
+        let record: Option<Vec<u8>> = self
+            .conn
+            .lock()
+            .prepare_cached(
+                "SELECT record FROM keys WHERE topic = ?1 AND partition = ?2 AND key = ?3",
+            )
+            .and_then(|mut statement| {
+                statement
+                    .query_row(params![topic, partition, key], |row| row.get(0))
+                    .optional()
+            })
+            .map_err(KafkaError::KeyStoreError)?;
+        record
+            .map(|record| bincode::deserialize(&record))
+            .transpose()
+            .map_err(ConnectorError::map_bincode_serialization_error)
+    }
+
+    /// Sets the primary key of message keys of a partition in one transaction, removing the
+    /// keys set to `None`.
+    pub fn set<'a>(
+        &self,
+        topic: &str,
+        partition: i32,
+        keys: impl IntoIterator<Item = (&'a [u8], Option<&'a Record>)>,
+    ) -> Result<(), ConnectorError> {
+        let mut conn = self.conn.lock();
+        let tx = conn.transaction().map_err(KafkaError::KeyStoreError)?;
+        for (key, record) in keys {
+            let result = match record {
+                Some(record) => {
+                    let record = bincode::serialize(record)
+                        .map_err(ConnectorError::map_bincode_serialization_error)?;
+                    tx.prepare_cached(
+                        "INSERT OR REPLACE INTO keys (topic, partition, key, record)
+                        VALUES (?1, ?2, ?3, ?4)",
+                    )
+                    .and_then(|mut statement| {
+                        statement.execute(params![topic, partition, key, record])
+                    })
+                }
+                None => tx
+                    .prepare_cached(
+                        "DELETE FROM keys WHERE topic = ?1 AND partition = ?2 AND key = ?3",
+                    )
+                    .and_then(|mut statement| statement.execute(params![topic, partition, key])),
+            };
+            result.map_err(KafkaError::KeyStoreError)?;
+        }
+        tx.commit().map_err(KafkaError::KeyStoreError)?;
+        Ok(())
+    }
+
+    /// Removes the keys of all partitions.
+    pub fn clear(&self) -> Result<(), ConnectorError> {
+        self.conn
+            .lock()
+            .execute("DELETE FROM keys", [])
+            .map_err(KafkaError::KeyStoreError)?;
+        Ok(())
+    }
+}
@@ -1,5 +1,8 @@
 pub mod connector;
 pub mod debezium;
+pub mod generic;
+pub mod key_store;
+pub mod sequencer;
 pub mod stream_consumer;
 #[cfg(any(test, feature = "debezium_bench"))]
 pub mod test_utils;
@@ -0,0 +1,467 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+
+use dozer_types::ingestion_types::IngestionMessage;
+use dozer_types::parking_lot::Mutex;
+use dozer_types::serde::{Deserialize, Serialize};
+use dozer_types::types::Operation;
+
+use crate::connectors::journal::Journal;
+use crate::connectors::kafka::generic::stream_consumer::KeyChange;
+use crate::connectors::kafka::key_store::KeyStore;
+use crate::errors::{ConnectorError, KafkaError};
+use crate::ingestion::Ingestor;
+
+/// Transactions appended to the journal before it is compacted, unless its last compaction
+/// left more entries.
+const MIN_TRANSACTIONS_BEFORE_COMPACTION: usize = 1000;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(crate = "dozer_types::serde")]
+pub enum JournalEntry {
+    /// Checkpoint the journal was compacted at, followed by the state of the partitions at it.
+    Checkpoint(u64, u64),
+    Partition {
+        topic: String,
+        partition: i32,
+        next_offset: i64,
+    },
+    /// Messages of a partition ingested as a transaction, from its operation `first_seq` on.
+    /// Every message holds its offset and the change of its key, which is rolled back if the
+    /// message was not committed.
+    Transaction {
+        txid: u64,
+        first_seq: u64,
+        topic: String,
+        partition: i32,
+        messages: Vec<(i64, Option<KeyChange>)>,
+    },
+}
+
+/// State of a partition, from which it is consumed again.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct PartitionState {
+    /// Offset following the last ingested message, or `None` to consume from the start
+    pub next_offset: Option<i64>,
+}
+
+pub type Partitions = HashMap<(String, i32), PartitionState>;
+
+/// Orders the messages of all partitions, giving every message set a transaction id.
+///
+/// The key changes of a transaction are written to the `KeyStore` before it is ingested. With a
+/// journal, its offsets and key changes are persisted first, so that the state of the
+/// partitions at a checkpoint can be restored, rolling back the key changes following it. The
+/// journal is compacted to the state at the committed checkpoint.
+#[derive(Debug, Default)]
+pub struct Sequencer {
+    state: Mutex<SequencerState>,
+    // Last committed checkpoint, which is only recorded so that committing never waits for a
+    // transaction to be ingested. The journal is compacted to it by the next transaction.
+    committed: Mutex<Option<(u64, u64)>>,
+}
+
+#[derive(Debug)]
+struct SequencerState {
+    next_txid: u64,
+    keys: Option<Arc<KeyStore>>,
+    journal: Option<Journal<JournalEntry>>,
+    // Checkpoint the journal was last compacted at
+    compacted_at: Option<(u64, u64)>,
+    // Entries left by the last compaction
+    num_compacted: usize,
+    // Transactions appended since the last compaction
+    num_appended: usize,
+}
+
+impl Default for SequencerState {
+    fn default() -> Self {
+        Self {
+            next_txid: 1,
+            keys: None,
+            journal: None,
+            compacted_at: None,
+            num_compacted: 0,
+            num_appended: 0,
+        }
+    }
+}
+
+impl Sequencer {
+    /// Whether the journal at `path` holds the state of the partitions at `checkpoint`.
+    pub fn can_resume(path: &Path, checkpoint: (u64, u64)) -> Result<bool, ConnectorError> {
+        Ok(KeyStore::path(path).exists() && replay(Journal::read(path)?, checkpoint).is_some())
+    }
+
+    /// Opens the journal at `path` and the key store kept with it if any, returning the state
+    /// of the partitions at `last_checkpoint` and the store. Transactions which were not
+    /// committed are dropped and their key changes rolled back, as their messages are consumed
+    /// again.
+    pub fn open(
+        &self,
+        path: Option<&Path>,
+        last_checkpoint: Option<(u64, u64)>,
+    ) -> Result<(Partitions, Arc<KeyStore>), ConnectorError> {
+        let mut state = self.state.lock();
+        *state = SequencerState {
+            next_txid: last_checkpoint.map_or(1, |(txid, _)| txid + 1),
+            ..SequencerState::default()
+        };
+        *self.committed.lock() = None;
+        let keys = Arc::new(KeyStore::open(path)?);
+        state.keys = Some(keys.clone());
+        let Some(path) = path else {
+            return Ok((Partitions::new(), keys));
+        };
+
+        let (mut journal, entries) = Journal::open(path)?;
+        let partitions = match last_checkpoint {
+            Some(checkpoint) => {
+                let (partitions, pending) = replay(entries, checkpoint)
+                    .ok_or(KafkaError::CheckpointNotFound(checkpoint))?;
+                for entry in pending.iter().rev() {
+                    write_keys(&keys, entry, true)?;
+                }
+                partitions
+            }
+            None => {
+                keys.clear()?;
+                Partitions::new()
+            }
+        };
+        let compacted = snapshot(last_checkpoint, &partitions);
+        journal.compact(&compacted)?;
+        state.journal = Some(journal);
+        state.compacted_at = last_checkpoint;
+        state.num_compacted = compacted.len();
+        Ok((partitions, keys))
+    }
+
+    /// Records that the messages up to `checkpoint` were committed.
+    pub fn commit(&self, checkpoint: (u64, u64)) {
+        *self.committed.lock() = Some(checkpoint);
+    }
+
+    /// Ingests the operations of consecutive messages of a partition as a transaction, which
+    /// is persisted first if there is a journal. Every message holds its offset and the change
+    /// of its key, which is written to the key store before the operations are sent.
+    pub fn ingest(
+        &self,
+        topic: &str,
+        partition: i32,
+        messages: Vec<(i64, Operation, Option<KeyChange>)>,
+        ingestor: &Ingestor,
+    ) -> Result<(), ConnectorError> {
+        if messages.is_empty() {
+            return Ok(());
+        }
+
+        let committed = *self.committed.lock();
+        let mut state = self.state.lock();
+        if let Some(checkpoint) = committed {
+            state.compact(checkpoint)?;
+        }
+
+        let txid = state.next_txid;
+        state.next_txid += 1;
+        let (positions, operations): (Vec<_>, Vec<_>) = messages
+            .into_iter()
+            .map(|(offset, op, change)| ((offset, change), op))
+            .unzip();
+        let entry = JournalEntry::Transaction {
+            txid,
+            first_seq: 0,
+            topic: topic.to_string(),
+            partition,
+            messages: positions,
+        };
+        if let Some(journal) = &mut state.journal {
+            journal.append(&entry)?;
+            state.num_appended += 1;
+        }
+        if let Some(keys) = &state.keys {
+            write_keys(keys, &entry, false)?;
+        }
+
+        // Sending under the lock keeps the transactions in order.
+        ingestor
+            .handle_batch(
+                operations
+                    .into_iter()
+                    .enumerate()
+                    .map(|(seq_in_tx, op)| IngestionMessage::new_op(txid, seq_in_tx as u64, op))
+                    .collect(),
+            )
+            .map_err(ConnectorError::IngestorError)
+    }
+}
+
+impl SequencerState {
+    /// Compacts the journal to the state at `checkpoint`, keeping the transactions following
+    /// it. Compaction waits for as many transactions as the last one left entries.
+    fn compact(&mut self, checkpoint: (u64, u64)) -> Result<(), ConnectorError> {
+        let Some(journal) = &mut self.journal else {
+            return Ok(());
+        };
+        if self.compacted_at == Some(checkpoint)
+            || self.num_appended < self.num_compacted.max(MIN_TRANSACTIONS_BEFORE_COMPACTION)
+        {
+            return Ok(());
+        }
+
+        let (partitions, pending) = replay(Journal::read(journal.path())?, checkpoint)
+            .ok_or(KafkaError::CheckpointNotFound(checkpoint))?;
+        let mut compacted = snapshot(Some(checkpoint), &partitions);
+        self.compacted_at = Some(checkpoint);
+        self.num_compacted = compacted.len();
+        self.num_appended = pending.len();
+        compacted.extend(pending);
+        journal.compact(&compacted)?;
+        Ok(())
+    }
+}
+
+/// Replays the journal entries up to `checkpoint`, returning the state of the partitions at it
+/// and the transactions following it. Returns `None` if the journal has no message at the
+/// checkpoint, or was compacted past it.
+fn replay(
+    entries: Vec<JournalEntry>,
+    checkpoint: (u64, u64),
+) -> Option<(Partitions, Vec<JournalEntry>)> {
+    let (txid, seq_in_tx) = checkpoint;
+    let mut partitions = Partitions::new();
+    let mut pending = vec![];
+    let mut found = false;
+    for entry in entries {
+        match entry {
+            JournalEntry::Checkpoint(compacted_txid, compacted_seq) => {
+                let compacted = (compacted_txid, compacted_seq);
+                if compacted > checkpoint {
+                    return None;
+                }
+                found |= compacted == checkpoint;
+            }
+            JournalEntry::Partition {
+                topic,
+                partition,
+                next_offset,
+            } => {
+                partitions.insert(
+                    (topic, partition),
+                    PartitionState {
+                        next_offset: Some(next_offset),
+                    },
+                );
+            }
+            JournalEntry::Transaction {
+                txid: entry_txid,
+                first_seq,
+                topic,
+                partition,
+                mut messages,
+            } => {
+                let num_committed = if entry_txid < txid {
+                    messages.len()
+                } else if entry_txid == txid {
+                    found |=
+                        first_seq <= seq_in_tx && seq_in_tx < first_seq + messages.len() as u64;
+                    ((seq_in_tx + 1).saturating_sub(first_seq) as usize).min(messages.len())
+                } else {
+                    0
+                };
+
+                if num_committed > 0 {
+                    let state = partitions.entry((topic.clone(), partition)).or_default();
+                    if let Some((offset, _)) = messages.drain(..num_committed).last() {
+                        state.next_offset = Some(offset + 1);
+                    }
+                }
+                if !messages.is_empty() {
+                    pending.push(JournalEntry::Transaction {
+                        txid: entry_txid,
+                        first_seq: first_seq + num_committed as u64,
+                        topic,
+                        partition,
+                        messages,
+                    });
+                }
+            }
+        }
+    }
+    found.then_some((partitions, pending))
+}
+
+/// Entries holding the state of the partitions at `checkpoint`.
+fn snapshot(checkpoint: Option<(u64, u64)>, partitions: &Partitions) -> Vec<JournalEntry> {
+    let Some((txid, seq_in_tx)) = checkpoint else {
+        return vec![];
+    };
+    let mut entries = vec![JournalEntry::Checkpoint(txid, seq_in_tx)];
+    for ((topic, partition), state) in partitions {
+        if let Some(next_offset) = state.next_offset {
+            entries.push(JournalEntry::Partition {
+                topic: topic.clone(),
+                partition: *partition,
+                next_offset,
+            });
+        }
+    }
+    entries
+}
+
+/// Writes the key changes of a transaction to the store, or rolls them back in reverse order.
+fn write_keys(keys: &KeyStore, entry: &JournalEntry, rollback: bool) -> Result<(), ConnectorError> {
+    let JournalEntry::Transaction {
+        topic,
+        partition,
+        messages,
+        ..
+    } = entry
+    else {
+        return Ok(());
+    };
+    let partition = *partition;
+    let changes = messages.iter().filter_map(|(_, change)| change.as_ref());
+    if rollback {
+        keys.set(
+            topic,
+            partition,
+            changes.rev().map(|c| (c.key.as_slice(), c.old.as_ref())),
+        )
+    } else {
+        keys.set(
+            topic,
+            partition,
+            changes.map(|c| (c.key.as_slice(), c.record.as_ref())),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::connectors::kafka::generic::stream_consumer::KeyChange;
+    use crate::connectors::kafka::key_store::KeyStore;
+    use crate::connectors::kafka::sequencer::{PartitionState, Sequencer};
+    use crate::ingestion::{IngestionConfig, Ingestor};
+    use dozer_types::types::{Field, Operation, Record};
+    use tempdir::TempDir;
+
+    fn upsert(key: &str) -> (Operation, Option<KeyChange>) {
+        let record = Record::new(None, vec![Field::String(key.to_string())], None);
+        (
+            Operation::Insert {
+                new: record.clone(),
+            },
+            Some(KeyChange {
+                key: key.as_bytes().to_vec(),
+                record: Some(record),
+                old: None,
+            }),
+        )
+    }
+
+    fn ingest(sequencer: &Sequencer, ingestor: &Ingestor, partition: i32, keys: &[(i64, &str)]) {
+        let messages = keys
+            .iter()
+            .map(|(offset, key)| {
+                let (op, change) = upsert(key);
+                (*offset, op, change)
+            })
+            .collect();
+        sequencer
+            .ingest("users", partition, messages, ingestor)
+            .unwrap();
+    }
+
+    fn has_key(keys: &KeyStore, partition: i32, key: &str) -> bool {
+        keys.get("users", partition, key.as_bytes())
+            .unwrap()
+            .is_some()
+    }
+
+    #[test]
+    fn test_it_restores_partitions_at_checkpoint() {
+        let dir = TempDir::new("kafka_sequencer").unwrap();
+        let path = dir.path().join("state");
+        let (ingestor, mut iterator) = Ingestor::initialize_channel(IngestionConfig::default());
+
+        let sequencer = Sequencer::default();
+        assert!(sequencer.open(Some(&path), None).unwrap().0.is_empty());
+        ingest(&sequencer, &ingestor, 0, &[(10, "a"), (11, "b")]);
+        ingest(&sequencer, &ingestor, 1, &[(5, "c")]);
+        ingest(&sequencer, &ingestor, 0, &[(12, "d")]);
+
+        let identifiers = (0..4)
+            .map(|_| {
+                let identifier = iterator.next().unwrap().identifier;
+                (identifier.txid, identifier.seq_in_tx)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(identifiers, vec![(1, 0), (1, 1), (2, 0), (3, 0)]);
+
+        assert!(Sequencer::can_resume(&path, (1, 0)).unwrap());
+        assert!(Sequencer::can_resume(&path, (3, 0)).unwrap());
+        assert!(!Sequencer::can_resume(&path, (1, 2)).unwrap());
+        assert!(!Sequencer::can_resume(&path, (4, 0)).unwrap());
+
+        // Partition 1 and the second message of partition 0 were not committed, so their keys
+        // are rolled back.
+        let (partitions, keys) = Sequencer::default()
+            .open(Some(&path), Some((1, 0)))
+            .unwrap();
+        assert_eq!(partitions.len(), 1);
+        assert_eq!(
+            partitions[&("users".to_string(), 0)],
+            PartitionState {
+                next_offset: Some(11),
+            }
+        );
+        assert!(has_key(&keys, 0, "a"));
+        assert!(!has_key(&keys, 0, "b"));
+        assert!(!has_key(&keys, 1, "c"));
+        assert!(!has_key(&keys, 0, "d"));
+
+        // The journal was compacted at the checkpoint.
+        assert!(Sequencer::can_resume(&path, (1, 0)).unwrap());
+        assert!(!Sequencer::can_resume(&path, (0, 0)).unwrap());
+        assert!(!Sequencer::can_resume(&path, (3, 0)).unwrap());
+    }
+
+    #[test]
+    fn test_it_compacts_on_commit() {
+        let dir = TempDir::new("kafka_sequencer").unwrap();
+        let path = dir.path().join("state");
+        let (ingestor, _iterator) = Ingestor::initialize_channel(IngestionConfig::default());
+
+        let sequencer = Sequencer::default();
+        sequencer.open(Some(&path), None).unwrap();
+        let offsets = 0..super::MIN_TRANSACTIONS_BEFORE_COMPACTION as i64 + 1;
+        for offset in offsets.clone() {
+            ingest(&sequencer, &ingestor, 0, &[(offset, "a")]);
+        }
+        let last_txid = offsets.end as u64;
+        sequencer.commit((last_txid - 1, 0));
+        assert!(Sequencer::can_resume(&path, (1, 0)).unwrap());
+
+        // The next transaction compacts the journal, leaving the transactions following the
+        // state at the checkpoint.
+        ingest(&sequencer, &ingestor, 0, &[(offsets.end, "b")]);
+        assert!(!Sequencer::can_resume(&path, (1, 0)).unwrap());
+        assert!(Sequencer::can_resume(&path, (last_txid - 1, 0)).unwrap());
+        assert!(Sequencer::can_resume(&path, (last_txid, 0)).unwrap());
+        assert!(Sequencer::can_resume(&path, (last_txid + 1, 0)).unwrap());
+
+        let (partitions, keys) = Sequencer::default()
+            .open(Some(&path), Some((last_txid, 0)))
+            .unwrap();
+        assert_eq!(
+            partitions[&("users".to_string(), 0)],
+            PartitionState {
+                next_offset: Some(offsets.end),
+            }
+        );
+        assert!(has_key(&keys, 0, "a"));
+        assert!(!has_key(&keys, 0, "b"));
+    }
+}
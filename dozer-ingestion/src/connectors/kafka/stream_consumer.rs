@@ -1,7 +1,17 @@
+use crate::connectors::kafka::generic::stream_consumer::{KeyChange, UpsertState};
 use crate::errors::ConnectorError;
-use crate::ingestion::Ingestor;
-use kafka::consumer::Consumer;
+use dozer_types::types::Operation;
+use kafka::consumer::Message;
 
-pub trait StreamConsumer {
-    fn run(&self, con: Consumer, ingestor: &Ingestor) -> Result<(), ConnectorError>;
+/// Maps the messages of the consumed topics to operations. Partitions are consumed in parallel,
+/// so the messages of a key, which always land on the same partition, keep their order.
+pub trait StreamConsumer: Send + Sync {
+    /// Returns the operation of a message of `topic` if any, with the change of its key when
+    /// the topic is upserted. `keys` holds the upserted keys of the partition of the message.
+    fn map_message(
+        &self,
+        topic: &str,
+        message: &Message,
+        keys: &mut UpsertState,
+    ) -> Result<Option<(Operation, Option<KeyChange>)>, ConnectorError>;
 }
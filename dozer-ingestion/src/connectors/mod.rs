@@ -1,8 +1,12 @@
 pub mod ethereum;
 pub mod grpc;
+pub mod journal;
 pub mod kafka;
 pub mod object_store;
+pub mod odbc;
 pub mod postgres;
+pub mod sqlite;
+pub mod webhook;
 
 use crate::connectors::postgres::connection::helper::map_connection_config;
 use std::collections::HashMap;
@@ -24,10 +28,13 @@ use dozer_types::types::SourceSchema;
 
 pub mod snowflake;
 
-use self::ethereum::{EthLogConnector, EthTraceConnector};
+use self::ethereum::{EthBlockConnector, EthLogConnector, EthTraceConnector};
 use self::grpc::connector::GrpcConnector;
 use self::grpc::{ArrowAdapter, DefaultAdapter};
+use crate::connectors::odbc::connector::OdbcConnector;
 use crate::connectors::snowflake::connector::SnowflakeConnector;
+use crate::connectors::sqlite::connector::SqliteConnector;
+use crate::connectors::webhook::connector::WebhookConnector;
 
 pub type ValidationResults = HashMap<String, Vec<(Option<String>, Result<(), ConnectorError>)>>;
 
@@ -47,6 +54,11 @@ pub trait Connector: Send + Sync + Debug {
         ingestor: &Ingestor,
         tables: Vec<TableInfo>,
     ) -> Result<(), ConnectorError>;
+    /// Called once the pipeline committed the messages up to `checkpoint`. The connector will
+    /// not be started from an earlier checkpoint.
+    fn commit(&self, _checkpoint: (u64, u64)) -> Result<(), ConnectorError> {
+        Ok(())
+    }
     fn get_tables(&self, tables: Option<&[TableInfo]>) -> Result<Vec<TableInfo>, ConnectorError>;
 
     // This is a default table mapping from schemas. It will result in errors if connector has unsupported data types.
@@ -124,6 +136,9 @@ pub fn get_connector(connection: Connection) -> Result<Box<dyn Connector>, Conne
             dozer_types::ingestion_types::EthProviderConfig::Trace(trace_config) => Ok(Box::new(
                 EthTraceConnector::new(2, trace_config, connection.name),
             )),
+            dozer_types::ingestion_types::EthProviderConfig::Block(block_config) => Ok(Box::new(
+                EthBlockConnector::new(2, block_config, connection.name),
+            )),
         },
         ConnectionConfig::Grpc(grpc_config) => match grpc_config.adapter.as_str() {
             "arrow" => Ok(Box::new(GrpcConnector::<ArrowAdapter>::new(
@@ -149,13 +164,32 @@ pub fn get_connector(connection: Connection) -> Result<Box<dyn Connector>, Conne
                 snowflake_config,
             )))
         }
-        ConnectionConfig::Kafka(kafka_config) => Ok(Box::new(KafkaConnector::new(5, kafka_config))),
+        ConnectionConfig::Kafka(kafka_config) => Ok(Box::new(KafkaConnector::new(
+            5,
+            connection.name,
+            kafka_config,
+        ))),
         ConnectionConfig::S3Storage(object_store_config) => {
             Ok(Box::new(ObjectStoreConnector::new(5, object_store_config)))
         }
         ConnectionConfig::LocalStorage(object_store_config) => {
             Ok(Box::new(ObjectStoreConnector::new(5, object_store_config)))
         }
+        ConnectionConfig::Webhook(webhook_config) => Ok(Box::new(WebhookConnector::new(
+            6,
+            connection.name,
+            webhook_config,
+        ))),
+        ConnectionConfig::Sqlite(sqlite_config) => Ok(Box::new(SqliteConnector::new(
+            7,
+            connection.name,
+            sqlite_config,
+        ))),
+        ConnectionConfig::Odbc(odbc_config) => Ok(Box::new(OdbcConnector::new(
+            8,
+            connection.name,
+            odbc_config,
+        ))),
     }
 }
 
@@ -167,6 +201,9 @@ pub fn get_connector_info_table(connection: &Connection) -> Option<Table> {
         Some(ConnectionConfig::Kafka(config)) => Some(config.convert_to_table()),
         Some(ConnectionConfig::S3Storage(config)) => Some(config.convert_to_table()),
         Some(ConnectionConfig::LocalStorage(config)) => Some(config.convert_to_table()),
+        Some(ConnectionConfig::Webhook(config)) => Some(config.convert_to_table()),
+        Some(ConnectionConfig::Sqlite(config)) => Some(config.convert_to_table()),
+        Some(ConnectionConfig::Odbc(config)) => Some(config.convert_to_table()),
         _ => None,
     }
 }
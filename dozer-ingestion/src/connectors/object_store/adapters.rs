@@ -30,12 +30,15 @@ pub trait DozerObjectStore: Clone + Send + Sync + Debug {
     ) -> Result<DozerObjectStoreParams<'a, Self::ObjectStore>, ConnectorError>;
 
     fn tables(&self) -> &[Table];
+
+    fn state_path(&self) -> Option<&str>;
 }
 
 pub struct DozerObjectStoreParams<'a, T: ObjectStore> {
     pub scheme: &'static str,
     pub host: &'a str,
     pub object_store: T,
+    pub base_path: String,
     pub table_path: String,
     pub data_fusion_table: &'a Table,
 }
@@ -61,6 +64,7 @@ impl DozerObjectStore for S3Storage {
             scheme: "s3",
             host: &details.bucket_name,
             object_store,
+            base_path: format!("s3://{}", details.bucket_name),
             table_path: format!("s3://{}/{}/", details.bucket_name, table.prefix),
             data_fusion_table: table,
         })
@@ -69,6 +73,10 @@ impl DozerObjectStore for S3Storage {
     fn tables(&self) -> &[Table] {
         &self.tables
     }
+
+    fn state_path(&self) -> Option<&str> {
+        self.state_path.as_deref()
+    }
 }
 
 impl DozerObjectStore for LocalStorage {
@@ -87,6 +95,7 @@ impl DozerObjectStore for LocalStorage {
             scheme: "local",
             host: path,
             object_store,
+            base_path: path.to_string(),
             table_path: format!("{path}/{}/", table.prefix),
             data_fusion_table: table,
         })
@@ -95,6 +104,10 @@ impl DozerObjectStore for LocalStorage {
     fn tables(&self) -> &[Table] {
         &self.tables
     }
+
+    fn state_path(&self) -> Option<&str> {
+        self.state_path.as_deref()
+    }
 }
 
 fn get_details<T>(details: &Option<T>) -> Result<&T, ObjectStoreConnectorError> {
@@ -1,10 +1,13 @@
 use dozer_types::types::SourceSchema;
 use std::collections::HashMap;
 use std::fmt::Debug;
+use std::path::Path;
+use std::sync::Arc;
 
 use crate::connectors::object_store::adapters::DozerObjectStore;
+use crate::connectors::object_store::partition::PartitionMatchers;
 use crate::connectors::object_store::schema_mapper::{Mapper, SchemaMapper};
-use crate::connectors::object_store::table_reader::{Reader, TableReader};
+use crate::connectors::object_store::table_reader::{FileTracker, Reader, TableReader};
 use crate::connectors::{Connector, TableInfo, ValidationResults};
 use crate::errors::ConnectorError;
 use crate::ingestion::Ingestor;
@@ -15,11 +18,17 @@ type ConnectorResult<T> = Result<T, ConnectorError>;
 pub struct ObjectStoreConnector<T: Clone> {
     pub id: u64,
     config: T,
+    // Partition columns inferred when getting the schemas, reused to read the tables
+    matchers: Arc<PartitionMatchers>,
 }
 
 impl<T: DozerObjectStore> ObjectStoreConnector<T> {
     pub fn new(id: u64, config: T) -> Self {
-        Self { id, config }
+        Self {
+            id,
+            config,
+            matchers: Arc::new(PartitionMatchers::default()),
+        }
     }
 }
 
@@ -36,21 +45,26 @@ impl<T: DozerObjectStore> Connector for ObjectStoreConnector<T> {
         &self,
         table_names: Option<Vec<TableInfo>>,
     ) -> ConnectorResult<Vec<SourceSchema>> {
-        let mapper = SchemaMapper::new(self.config.clone());
+        let mapper = SchemaMapper::new(self.config.clone(), self.matchers.clone());
         mapper.get_schema(table_names)
     }
 
-    fn can_start_from(&self, _last_checkpoint: (u64, u64)) -> Result<bool, ConnectorError> {
-        Ok(false)
+    fn can_start_from(&self, last_checkpoint: (u64, u64)) -> Result<bool, ConnectorError> {
+        // The files ingested at a checkpoint are kept in the journal.
+        match self.config.state_path() {
+            Some(path) => FileTracker::can_resume(Path::new(path), last_checkpoint),
+            None => Ok(false),
+        }
     }
 
     fn start(
         &self,
-        _from_seq: Option<(u64, u64)>,
+        from_seq: Option<(u64, u64)>,
         ingestor: &Ingestor,
         tables: Vec<TableInfo>,
     ) -> ConnectorResult<()> {
-        TableReader::new(self.config.clone()).read_tables(&tables, ingestor)
+        TableReader::new(self.config.clone(), self.matchers.clone())
+            .read_tables(&tables, from_seq, ingestor)
     }
 
     fn get_tables(&self, tables: Option<&[TableInfo]>) -> ConnectorResult<Vec<TableInfo>> {
@@ -0,0 +1,546 @@
+use crate::errors::ObjectStoreDeltaError;
+use crate::errors::ObjectStoreDeltaError::{
+    ChangeTypeNotSupported, CheckpointParseError, CommitParseError, LogReadError, MetadataNotFound,
+    PartitionedTableNotSupported, RemovedFileVacuumed, UnmatchedUpdatePostimage,
+};
+use crate::errors::ObjectStoreSchemaError::FieldTypeNotSupported;
+use datafusion::arrow::array::{Array, BooleanArray, ListArray, StringArray, StructArray};
+use datafusion::arrow::record_batch::RecordBatch;
+use datafusion::parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use datafusion::parquet::file::reader::ChunkReader;
+use dozer_types::serde::Deserialize;
+use dozer_types::serde_json;
+use dozer_types::serde_json::Value;
+use dozer_types::types::{
+    Field, FieldDefinition, FieldType, Operation, Record, SchemaIdentifier, SourceDefinition,
+};
+use futures::TryStreamExt;
+use object_store::path::Path;
+use object_store::{ObjectMeta, ObjectStore};
+use std::collections::{HashMap, VecDeque};
+
+/// Directory of the transaction log, under the root of a Delta table.
+pub const DELTA_LOG_DIR: &str = "_delta_log";
+
+/// Column of the change data files holding the kind of change.
+pub const CHANGE_TYPE_COLUMN: &str = "_change_type";
+
+/// File of the transaction log pointing to its last checkpoint.
+const LAST_CHECKPOINT_FILE: &str = "_last_checkpoint";
+
+/// File of the transaction log, named after the version of the table it leads to, see
+/// https://github.com/delta-io/delta/blob/master/PROTOCOL.md#delta-log-entries
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogFile {
+    Commit(u64),
+    /// Part of a checkpoint holding the state of the table at `version`, which is written in
+    /// `parts` files.
+    Checkpoint {
+        version: u64,
+        parts: u64,
+    },
+}
+
+impl LogFile {
+    /// Parses the name of the file at `location`, returning `None` for the files of the log which
+    /// are neither commits nor checkpoints.
+    pub fn parse(location: &str) -> Option<Self> {
+        let name = location.rsplit('/').next()?;
+        if let Some(version) = name.strip_suffix(".json") {
+            return version.parse().ok().map(LogFile::Commit);
+        }
+
+        let (version, part) = name.strip_suffix(".parquet")?.split_once(".checkpoint")?;
+        let parts = match part.split('.').collect::<Vec<_>>().as_slice() {
+            [""] => 1,
+            ["", _, parts] => parts.parse().ok()?,
+            _ => return None,
+        };
+        Some(LogFile::Checkpoint {
+            version: version.parse().ok()?,
+            parts,
+        })
+    }
+
+    pub fn version(&self) -> u64 {
+        match self {
+            LogFile::Commit(version) | LogFile::Checkpoint { version, .. } => *version,
+        }
+    }
+
+    /// Order of the file in the log. Checkpoints follow the commit of their version, whose
+    /// changes they hold.
+    pub fn order(&self) -> (u64, bool) {
+        (self.version(), matches!(self, LogFile::Checkpoint { .. }))
+    }
+}
+
+/// Returns the version of the latest checkpoint of which `log` holds every part.
+pub fn checkpoint_version<'a>(log: impl IntoIterator<Item = &'a LogFile>) -> Option<u64> {
+    let mut checkpoints: HashMap<u64, (u64, u64)> = HashMap::new();
+    for file in log {
+        if let LogFile::Checkpoint { version, parts } = file {
+            checkpoints.entry(*version).or_insert((0, *parts)).0 += 1;
+        }
+    }
+    checkpoints
+        .into_iter()
+        .filter(|(_, (listed, parts))| listed >= parts)
+        .map(|(version, _)| version)
+        .max()
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(crate = "dozer_types::serde")]
+struct LastCheckpoint {
+    version: u64,
+}
+
+/// Action of a Delta commit. Only the actions needed to follow data changes are mapped,
+/// see https://github.com/delta-io/delta/blob/master/PROTOCOL.md#actions
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(crate = "dozer_types::serde")]
+pub struct DeltaAction {
+    pub add: Option<DeltaFile>,
+    pub remove: Option<DeltaFile>,
+    pub cdc: Option<DeltaFile>,
+    #[serde(rename = "metaData")]
+    pub metadata: Option<DeltaMetadata>,
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(crate = "dozer_types::serde")]
+pub struct DeltaFile {
+    pub path: String,
+    #[serde(rename = "dataChange", default = "default_data_change")]
+    pub data_change: bool,
+}
+
+fn default_data_change() -> bool {
+    true
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(crate = "dozer_types::serde")]
+pub struct DeltaMetadata {
+    #[serde(rename = "schemaString")]
+    pub schema_string: String,
+    #[serde(rename = "partitionColumns", default)]
+    pub partition_columns: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(crate = "dozer_types::serde")]
+struct DeltaStruct {
+    fields: Vec<DeltaStructField>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(crate = "dozer_types::serde")]
+struct DeltaStructField {
+    name: String,
+    #[serde(rename = "type")]
+    typ: Value,
+    nullable: bool,
+}
+
+/// Parses a commit file, which holds one action per line.
+pub fn parse_commit(commit: &[u8]) -> Result<Vec<DeltaAction>, ObjectStoreDeltaError> {
+    commit
+        .split(|b| *b == b'\n')
+        .filter(|line| !line.iter().all(u8::is_ascii_whitespace))
+        .map(|line| serde_json::from_slice(line).map_err(CommitParseError))
+        .collect()
+}
+
+pub async fn read_commit<S: ObjectStore>(
+    object_store: &S,
+    location: &Path,
+) -> Result<Vec<DeltaAction>, ObjectStoreDeltaError> {
+    let commit = object_store
+        .get(location)
+        .await
+        .map_err(LogReadError)?
+        .bytes()
+        .await
+        .map_err(LogReadError)?;
+    parse_commit(&commit)
+}
+
+/// Parses a checkpoint file, which holds one action per row.
+pub fn parse_checkpoint<R: ChunkReader + 'static>(
+    checkpoint: R,
+) -> Result<Vec<DeltaAction>, ObjectStoreDeltaError> {
+    let reader = ParquetRecordBatchReaderBuilder::try_new(checkpoint)
+        .and_then(|builder| builder.build())
+        .map_err(CheckpointParseError)?;
+
+    let mut actions = vec![];
+    for batch in reader {
+        let batch = batch.map_err(|e| CheckpointParseError(e.into()))?;
+        let add = struct_column(&batch, "add");
+        let remove = struct_column(&batch, "remove");
+        let metadata = struct_column(&batch, "metaData");
+        for row in 0..batch.num_rows() {
+            actions.push(DeltaAction {
+                add: add.and_then(|add| checkpoint_file(add, row)),
+                remove: remove.and_then(|remove| checkpoint_file(remove, row)),
+                cdc: None,
+                metadata: metadata.and_then(|metadata| checkpoint_metadata(metadata, row)),
+            });
+        }
+    }
+    Ok(actions)
+}
+
+fn struct_column<'a>(batch: &'a RecordBatch, name: &str) -> Option<&'a StructArray> {
+    let idx = batch.schema().index_of(name).ok()?;
+    batch.column(idx).as_any().downcast_ref()
+}
+
+fn child<'a, T: 'static>(array: &'a StructArray, name: &str, row: usize) -> Option<&'a T> {
+    let child = array.column_by_name(name)?;
+    if child.is_null(row) {
+        return None;
+    }
+    child.as_any().downcast_ref()
+}
+
+fn checkpoint_file(array: &StructArray, row: usize) -> Option<DeltaFile> {
+    if array.is_null(row) {
+        return None;
+    }
+    Some(DeltaFile {
+        path: child::<StringArray>(array, "path", row)?
+            .value(row)
+            .to_string(),
+        data_change: child::<BooleanArray>(array, "dataChange", row)
+            .map_or(default_data_change(), |data_change| data_change.value(row)),
+    })
+}
+
+fn checkpoint_metadata(array: &StructArray, row: usize) -> Option<DeltaMetadata> {
+    if array.is_null(row) {
+        return None;
+    }
+    let partition_columns = child::<ListArray>(array, "partitionColumns", row)
+        .map(|columns| columns.value(row))
+        .map_or(vec![], |columns| {
+            columns
+                .as_any()
+                .downcast_ref::<StringArray>()
+                .map_or(vec![], |columns| {
+                    columns.iter().flatten().map(str::to_string).collect()
+                })
+        });
+    Some(DeltaMetadata {
+        schema_string: child::<StringArray>(array, "schemaString", row)?
+            .value(row)
+            .to_string(),
+        partition_columns,
+    })
+}
+
+pub async fn read_checkpoint<S: ObjectStore>(
+    object_store: &S,
+    location: &Path,
+) -> Result<Vec<DeltaAction>, ObjectStoreDeltaError> {
+    let checkpoint = object_store
+        .get(location)
+        .await
+        .map_err(LogReadError)?
+        .bytes()
+        .await
+        .map_err(LogReadError)?;
+    parse_checkpoint(checkpoint)
+}
+
+/// Returns the version of the last checkpoint of the log, as recorded once all its parts are
+/// written.
+pub async fn read_last_checkpoint<S: ObjectStore>(
+    object_store: &S,
+    log_path: &Path,
+) -> Result<Option<u64>, ObjectStoreDeltaError> {
+    let last_checkpoint = match object_store
+        .get(&log_path.child(LAST_CHECKPOINT_FILE))
+        .await
+    {
+        Ok(result) => result.bytes().await.map_err(LogReadError)?,
+        Err(object_store::Error::NotFound { .. }) => return Ok(None),
+        Err(e) => return Err(LogReadError(e)),
+    };
+    let last_checkpoint: LastCheckpoint =
+        serde_json::from_slice(&last_checkpoint).map_err(CommitParseError)?;
+    Ok(Some(last_checkpoint.version))
+}
+
+/// Lists the commits and checkpoints of the transaction log, leaving out the checkpoints
+/// following the last one, which may not be completely written.
+pub async fn list_log<S: ObjectStore>(
+    object_store: &S,
+    prefix: &str,
+) -> Result<Vec<(ObjectMeta, LogFile)>, ObjectStoreDeltaError> {
+    let log_path = Path::from(format!("{prefix}/{DELTA_LOG_DIR}"));
+    let last_checkpoint = read_last_checkpoint(object_store, &log_path).await?;
+    let objects: Vec<ObjectMeta> = object_store
+        .list(Some(&log_path))
+        .await
+        .map_err(LogReadError)?
+        .try_collect()
+        .await
+        .map_err(LogReadError)?;
+
+    Ok(objects
+        .into_iter()
+        .filter_map(|object| {
+            let file = LogFile::parse(object.location.as_ref())?;
+            match file {
+                LogFile::Checkpoint { version, .. }
+                    if last_checkpoint.map_or(false, |last| version > last) =>
+                {
+                    None
+                }
+                file => Some((object, file)),
+            }
+        })
+        .collect())
+}
+
+/// Checks that a data file removed by a commit can be read again to delete its rows, which is
+/// not the case once it was vacuumed.
+pub async fn check_removed_file<S: ObjectStore>(
+    object_store: &S,
+    prefix: &str,
+    path: &str,
+) -> Result<(), ObjectStoreDeltaError> {
+    if path.contains("://") {
+        return Ok(());
+    }
+    match object_store
+        .head(&Path::from(format!("{prefix}/{path}")))
+        .await
+    {
+        Ok(_) => Ok(()),
+        Err(object_store::Error::NotFound { .. }) => Err(RemovedFileVacuumed(path.to_string())),
+        Err(e) => Err(LogReadError(e)),
+    }
+}
+
+/// Returns the latest metadata of the table, replaying the commits of its transaction log from
+/// its last checkpoint, in the order of their versions.
+pub async fn read_metadata<S: ObjectStore>(
+    object_store: &S,
+    prefix: &str,
+) -> Result<DeltaMetadata, ObjectStoreDeltaError> {
+    let mut log = list_log(object_store, prefix).await?;
+    let checkpoint = checkpoint_version(log.iter().map(|(_, file)| file));
+    log.retain(|(_, file)| match (file, checkpoint) {
+        (LogFile::Checkpoint { version, .. }, _) => Some(*version) == checkpoint,
+        (LogFile::Commit(version), Some(checkpoint)) => *version > checkpoint,
+        (LogFile::Commit(_), None) => true,
+    });
+    log.sort_by_key(|(_, file)| file.order());
+
+    let mut metadata = None;
+    for (object, file) in log {
+        let actions = match file {
+            LogFile::Commit(_) => read_commit(object_store, &object.location).await?,
+            LogFile::Checkpoint { .. } => read_checkpoint(object_store, &object.location).await?,
+        };
+        if let Some(last) = actions.into_iter().filter_map(|a| a.metadata).last() {
+            metadata = Some(last);
+        }
+    }
+    metadata.ok_or(MetadataNotFound)
+}
+
+pub fn map_schema(metadata: &DeltaMetadata) -> Result<Vec<FieldDefinition>, ObjectStoreDeltaError> {
+    if !metadata.partition_columns.is_empty() {
+        return Err(PartitionedTableNotSupported);
+    }
+
+    let schema: DeltaStruct =
+        serde_json::from_str(&metadata.schema_string).map_err(CommitParseError)?;
+
+    schema
+        .fields
+        .into_iter()
+        .map(|field| {
+            let typ = match field.typ.as_str() {
+                Some("string") => FieldType::String,
+                Some("long" | "integer" | "short" | "byte") => FieldType::Int,
+                Some("float" | "double") => FieldType::Float,
+                Some("boolean") => FieldType::Boolean,
+                Some("binary") => FieldType::Binary,
+                Some("date") => FieldType::Date,
+                Some("timestamp") => FieldType::Timestamp,
+                _ => return Err(FieldTypeNotSupported(field.name).into()),
+            };
+
+            Ok(FieldDefinition::new(
+                field.name,
+                typ,
+                field.nullable,
+                SourceDefinition::Dynamic,
+            ))
+        })
+        .collect()
+}
+
+/// Maps the rows of change data files to operations.
+///
+/// Delta writes the pre-image and post-image of updated rows in the same order, so they are
+/// paired as they come.
+#[derive(Debug, Default)]
+pub struct ChangeMapper {
+    preimages: VecDeque<Record>,
+}
+
+impl ChangeMapper {
+    pub fn map_change(
+        &mut self,
+        schema_id: SchemaIdentifier,
+        change_type: &Field,
+        values: Vec<Field>,
+    ) -> Result<Option<Operation>, ObjectStoreDeltaError> {
+        let record = Record::new(Some(schema_id), values, None);
+        let change_type = match change_type {
+            Field::String(change_type) => change_type.as_str(),
+            _ => return Err(ChangeTypeNotSupported(format!("{change_type:?}"))),
+        };
+
+        match change_type {
+            "insert" => Ok(Some(Operation::Insert { new: record })),
+            "delete" => Ok(Some(Operation::Delete { old: record })),
+            "update_preimage" => {
+                self.preimages.push_back(record);
+                Ok(None)
+            }
+            "update_postimage" => {
+                let old = self.preimages.pop_front().ok_or(UnmatchedUpdatePostimage)?;
+                Ok(Some(Operation::Update { old, new: record }))
+            }
+            typ => Err(ChangeTypeNotSupported(typ.to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::connectors::object_store::delta::{
+        checkpoint_version, map_schema, parse_commit, ChangeMapper, DeltaFile, LogFile,
+    };
+    use dozer_types::types::{Field, FieldType, Operation, Record, SchemaIdentifier};
+
+    #[test]
+    fn test_it_parses_commit() {
+        let commit = br#"{"commitInfo":{"timestamp":1677000000000,"operation":"WRITE"}}
+{"metaData":{"id":"1","schemaString":"{\"type\":\"struct\",\"fields\":[{\"name\":\"id\",\"type\":\"long\",\"nullable\":false,\"metadata\":{}},{\"name\":\"amount\",\"type\":\"double\",\"nullable\":true,\"metadata\":{}}]}","partitionColumns":[]}}
+{"add":{"path":"part-0.parquet","partitionValues":{},"size":10,"dataChange":true}}
+{"remove":{"path":"part-1.parquet","dataChange":false}}
+"#;
+        let actions = parse_commit(commit).unwrap();
+        assert_eq!(actions.len(), 4);
+        assert_eq!(
+            actions[2].add,
+            Some(DeltaFile {
+                path: "part-0.parquet".to_string(),
+                data_change: true
+            })
+        );
+        assert!(!actions[3].remove.as_ref().unwrap().data_change);
+
+        let fields = map_schema(actions[1].metadata.as_ref().unwrap()).unwrap();
+        assert_eq!(fields[0].name, "id");
+        assert_eq!(fields[0].typ, FieldType::Int);
+        assert!(!fields[0].nullable);
+        assert_eq!(fields[1].typ, FieldType::Float);
+    }
+
+    #[test]
+    fn test_it_parses_log_files() {
+        let log = "table/_delta_log";
+        assert_eq!(
+            LogFile::parse(&format!("{log}/00000000000000000010.json")),
+            Some(LogFile::Commit(10))
+        );
+        assert_eq!(
+            LogFile::parse(&format!("{log}/00000000000000000010.checkpoint.parquet")),
+            Some(LogFile::Checkpoint {
+                version: 10,
+                parts: 1
+            })
+        );
+        assert_eq!(
+            LogFile::parse(&format!(
+                "{log}/00000000000000000020.checkpoint.0000000002.0000000002.parquet"
+            )),
+            Some(LogFile::Checkpoint {
+                version: 20,
+                parts: 2
+            })
+        );
+        assert_eq!(LogFile::parse(&format!("{log}/_last_checkpoint")), None);
+        assert_eq!(
+            LogFile::parse(&format!("{log}/00000000000000000010.json.crc")),
+            None
+        );
+
+        // The checkpoint of version 20 misses a part.
+        let files = [
+            LogFile::Commit(10),
+            LogFile::Checkpoint {
+                version: 10,
+                parts: 1,
+            },
+            LogFile::Checkpoint {
+                version: 20,
+                parts: 2,
+            },
+        ];
+        assert_eq!(checkpoint_version(&files), Some(10));
+        assert_eq!(checkpoint_version(&files[..1]), None);
+    }
+
+    #[test]
+    fn test_it_maps_changes() {
+        let schema_id = SchemaIdentifier { id: 0, version: 0 };
+        let mut mapper = ChangeMapper::default();
+        let change = |typ: &str| Field::String(typ.to_string());
+        let record = |id: i64| Record::new(Some(schema_id), vec![Field::Int(id)], None);
+
+        assert_eq!(
+            mapper
+                .map_change(schema_id, &change("insert"), vec![Field::Int(1)])
+                .unwrap(),
+            Some(Operation::Insert { new: record(1) })
+        );
+        assert_eq!(
+            mapper
+                .map_change(schema_id, &change("update_preimage"), vec![Field::Int(1)])
+                .unwrap(),
+            None
+        );
+        assert_eq!(
+            mapper
+                .map_change(schema_id, &change("update_postimage"), vec![Field::Int(2)])
+                .unwrap(),
+            Some(Operation::Update {
+                old: record(1),
+                new: record(2)
+            })
+        );
+        assert_eq!(
+            mapper
+                .map_change(schema_id, &change("delete"), vec![Field::Int(2)])
+                .unwrap(),
+            Some(Operation::Delete { old: record(2) })
+        );
+        assert!(mapper
+            .map_change(schema_id, &change("update_postimage"), vec![Field::Int(3)])
+            .is_err());
+        assert!(mapper
+            .map_change(schema_id, &change("merge"), vec![Field::Int(3)])
+            .is_err());
+    }
+}
@@ -1,8 +1,15 @@
-use crate::errors::ObjectStoreObjectError;
+use crate::errors::ObjectStoreConnectorError::TableReaderError;
+use crate::errors::ObjectStoreTableReaderError::FilesListingFailed;
+use crate::errors::{ObjectStoreConnectorError, ObjectStoreObjectError};
+use datafusion::datasource::file_format::avro::AvroFormat;
 use datafusion::datasource::file_format::csv::CsvFormat;
+use datafusion::datasource::file_format::json::JsonFormat;
 use datafusion::datasource::file_format::parquet::ParquetFormat;
 use datafusion::datasource::listing::ListingOptions;
 use dozer_types::ingestion_types::Table;
+use futures::TryStreamExt;
+use object_store::path::Path;
+use object_store::{ObjectMeta, ObjectStore};
 use std::sync::Arc;
 
 pub fn map_listing_options(
@@ -19,8 +26,52 @@ pub fn map_listing_options(
             Ok(ListingOptions::new(Arc::new(format))
                 .with_file_extension(data_fusion_table.extension.clone()))
         }
+        "json" | "jsonl" | "ndjson" => {
+            let format = JsonFormat::default();
+            Ok(ListingOptions::new(Arc::new(format))
+                .with_file_extension(data_fusion_table.extension.clone()))
+        }
+        "avro" => {
+            let format = AvroFormat::default();
+            Ok(ListingOptions::new(Arc::new(format))
+                .with_file_extension(data_fusion_table.extension.clone()))
+        }
+        // Data and change data files of delta tables are parquet files.
+        "delta" => {
+            let format = ParquetFormat::new();
+            Ok(ListingOptions::new(Arc::new(format)).with_file_extension(".parquet"))
+        }
         format => Err(ObjectStoreObjectError::FileFormatUnsupportedError(
             format.to_string(),
         )),
     }
 }
+
+pub fn is_delta_table(data_fusion_table: &Table) -> bool {
+    data_fusion_table.file_type == "delta"
+}
+
+/// Lists the objects found under `prefix` whose name ends with `extension`.
+pub async fn list_objects<S: ObjectStore>(
+    object_store: &S,
+    prefix: &Path,
+    extension: &str,
+) -> Result<Vec<ObjectMeta>, ObjectStoreConnectorError> {
+    let mut objects: Vec<ObjectMeta> = object_store
+        .list(Some(prefix))
+        .await
+        .map_err(|e| TableReaderError(FilesListingFailed(e)))?
+        .try_collect()
+        .await
+        .map_err(|e| TableReaderError(FilesListingFailed(e)))?;
+    objects.retain(|object| object.location.as_ref().ends_with(extension));
+    Ok(objects)
+}
+
+/// Returns the path of an object relative to the prefix of its table.
+pub fn relative_path<'a>(prefix: &str, location: &'a str) -> &'a str {
+    location
+        .strip_prefix(prefix.trim_matches('/'))
+        .unwrap_or(location)
+        .trim_start_matches('/')
+}
@@ -1,6 +1,8 @@
 mod adapters;
 pub mod connector;
+mod delta;
 mod helper;
+mod partition;
 mod schema_helper;
 mod schema_mapper;
 mod table_reader;
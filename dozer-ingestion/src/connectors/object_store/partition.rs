@@ -0,0 +1,363 @@
+use crate::errors::ObjectStorePartitionError;
+use crate::errors::ObjectStorePartitionError::{
+    FilterOperatorNotSupported, InvalidPattern, PartitionValueParseError, UnknownPartitionColumn,
+};
+use dozer_types::chrono::NaiveDate;
+use dozer_types::ingestion_types::Table;
+use dozer_types::ordered_float::OrderedFloat;
+use dozer_types::parking_lot::Mutex;
+use dozer_types::types::{Field, FieldDefinition, FieldType, SourceDefinition, DATE_FORMAT};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+/// Value written by Hive for the partitions of null values.
+const HIVE_DEFAULT_PARTITION: &str = "__HIVE_DEFAULT_PARTITION__";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PartitionColumn {
+    pub name: String,
+    pub typ: FieldType,
+}
+
+#[derive(Debug, Clone)]
+struct PartitionFilter {
+    column_idx: usize,
+    accepted: Vec<Ordering>,
+    value: Field,
+}
+
+/// Selects the files of a table by glob patterns and partition filters, and extracts the values
+/// of the partition columns from their path.
+#[derive(Debug, Clone, Default)]
+pub struct PartitionMatcher {
+    globs: Option<GlobSet>,
+    columns: Vec<PartitionColumn>,
+    filters: Vec<PartitionFilter>,
+}
+
+/// Matchers of the tables, by name. The partition columns of a table are inferred once and
+/// reused, so that the values ingested from files listed later match the types of its schema.
+#[derive(Debug, Default)]
+pub struct PartitionMatchers(Mutex<HashMap<String, PartitionMatcher>>);
+
+impl PartitionMatchers {
+    /// Returns the matcher of a table, built from the `paths` of its files the first time.
+    pub fn get_or_infer(
+        &self,
+        table: &Table,
+        paths: &[String],
+    ) -> Result<PartitionMatcher, ObjectStorePartitionError> {
+        let mut matchers = self.0.lock();
+        if let Some(matcher) = matchers.get(&table.name) {
+            return Ok(matcher.clone());
+        }
+        let matcher = PartitionMatcher::new(table, paths)?;
+        matchers.insert(table.name.clone(), matcher.clone());
+        Ok(matcher)
+    }
+}
+
+impl PartitionMatcher {
+    /// Builds the matcher of a table. The partition columns, and their types, are inferred from
+    /// the paths of the files found under the prefix of the table, relative to that prefix.
+    pub fn new(table: &Table, paths: &[String]) -> Result<Self, ObjectStorePartitionError> {
+        let globs = if table.patterns.is_empty() {
+            None
+        } else {
+            let mut builder = GlobSetBuilder::new();
+            for pattern in &table.patterns {
+                builder.add(Glob::new(pattern).map_err(InvalidPattern)?);
+            }
+            Some(builder.build().map_err(InvalidPattern)?)
+        };
+
+        let mut matcher = Self {
+            globs,
+            columns: vec![],
+            filters: vec![],
+        };
+
+        if table.hive_partitioning {
+            let paths: Vec<&String> = paths.iter().filter(|p| matcher.is_selected(p)).collect();
+            matcher.columns = infer_columns(&paths);
+        }
+
+        for filter in &table.partition_filters {
+            let column_idx = matcher
+                .columns
+                .iter()
+                .position(|c| c.name == filter.column)
+                .ok_or_else(|| UnknownPartitionColumn(filter.column.clone()))?;
+            let accepted = match filter.operator.as_str() {
+                "=" | "==" => vec![Ordering::Equal],
+                "!=" | "<>" => vec![Ordering::Less, Ordering::Greater],
+                "<" => vec![Ordering::Less],
+                "<=" => vec![Ordering::Less, Ordering::Equal],
+                ">" => vec![Ordering::Greater],
+                ">=" => vec![Ordering::Greater, Ordering::Equal],
+                op => return Err(FilterOperatorNotSupported(op.to_string())),
+            };
+            let value = parse_value(&matcher.columns[column_idx], &filter.value)?;
+
+            matcher.filters.push(PartitionFilter {
+                column_idx,
+                accepted,
+                value,
+            });
+        }
+
+        Ok(matcher)
+    }
+
+    pub fn columns(&self) -> &[PartitionColumn] {
+        &self.columns
+    }
+
+    pub fn field_definitions(&self) -> Vec<FieldDefinition> {
+        self.columns
+            .iter()
+            .map(|c| FieldDefinition::new(c.name.clone(), c.typ, true, SourceDefinition::Dynamic))
+            .collect()
+    }
+
+    fn is_selected(&self, path: &str) -> bool {
+        self.globs
+            .as_ref()
+            .map_or(true, |globs| globs.is_match(path))
+    }
+
+    /// Returns the values of the partition columns of the file at `path`, relative to the table
+    /// prefix, or `None` when the file is not selected by the patterns and filters. Values which
+    /// do not match the type of their column are null.
+    pub fn matches(&self, path: &str) -> Option<Vec<Field>> {
+        if !self.is_selected(path) {
+            return None;
+        }
+
+        let values: Vec<Field> = self
+            .parse_values(path)
+            .into_iter()
+            .map(|value| value.unwrap_or(Field::Null))
+            .collect();
+
+        let selected = self.filters.iter().all(|filter| {
+            let value = &values[filter.column_idx];
+            // Files without a value for the filtered column never match.
+            value != &Field::Null && filter.accepted.contains(&value.cmp(&filter.value))
+        });
+
+        selected.then_some(values)
+    }
+
+    /// Returns the errors of the partition values of the file at `path` which do not match the
+    /// type of their column.
+    pub fn invalid_values(&self, path: &str) -> Vec<ObjectStorePartitionError> {
+        self.parse_values(path)
+            .into_iter()
+            .filter_map(Result::err)
+            .collect()
+    }
+
+    fn parse_values(&self, path: &str) -> Vec<Result<Field, ObjectStorePartitionError>> {
+        let raw_values = partition_values(path);
+        self.columns
+            .iter()
+            .map(
+                |column| match raw_values.iter().find(|(key, _)| *key == column.name) {
+                    Some((_, value)) => parse_value(column, value),
+                    None => Ok(Field::Null),
+                },
+            )
+            .collect()
+    }
+}
+
+/// Returns the `key=value` directories of a path, in order.
+pub fn partition_values(path: &str) -> Vec<(&str, &str)> {
+    let mut segments: Vec<&str> = path.split('/').collect();
+    // The last segment is the file name.
+    segments.pop();
+    segments
+        .into_iter()
+        .filter_map(|segment| segment.split_once('='))
+        .filter(|(key, _)| !key.is_empty())
+        .collect()
+}
+
+/// Infers the partition columns, in the order they are nested, with the narrowest type that
+/// fits all their values.
+fn infer_columns(paths: &[&String]) -> Vec<PartitionColumn> {
+    let mut columns: Vec<(String, Vec<&str>)> = vec![];
+    for path in paths {
+        for (key, value) in partition_values(path) {
+            match columns.iter_mut().find(|(name, _)| name == key) {
+                Some((_, values)) => values.push(value),
+                None => columns.push((key.to_string(), vec![value])),
+            }
+        }
+    }
+
+    columns
+        .into_iter()
+        .map(|(name, values)| {
+            let values: Vec<&str> = values
+                .into_iter()
+                .filter(|v| *v != HIVE_DEFAULT_PARTITION)
+                .collect();
+            let typ = if values.iter().all(|v| v.parse::<i64>().is_ok()) {
+                FieldType::Int
+            } else if values.iter().all(|v| v.parse::<f64>().is_ok()) {
+                FieldType::Float
+            } else if values
+                .iter()
+                .all(|v| NaiveDate::parse_from_str(v, DATE_FORMAT).is_ok())
+            {
+                FieldType::Date
+            } else {
+                FieldType::String
+            };
+
+            PartitionColumn { name, typ }
+        })
+        .collect()
+}
+
+fn parse_value(column: &PartitionColumn, value: &str) -> Result<Field, ObjectStorePartitionError> {
+    if value == HIVE_DEFAULT_PARTITION {
+        return Ok(Field::Null);
+    }
+
+    let error = || PartitionValueParseError(column.name.clone(), value.to_string());
+    match column.typ {
+        FieldType::Int => value.parse().map(Field::Int).map_err(|_| error()),
+        FieldType::Float => value
+            .parse()
+            .map(|v| Field::Float(OrderedFloat(v)))
+            .map_err(|_| error()),
+        FieldType::Date => NaiveDate::parse_from_str(value, DATE_FORMAT)
+            .map(Field::Date)
+            .map_err(|_| error()),
+        _ => Ok(Field::String(value.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::connectors::object_store::partition::{
+        partition_values, PartitionColumn, PartitionMatcher, PartitionMatchers,
+    };
+    use dozer_types::ingestion_types::{PartitionFilter, Table};
+    use dozer_types::types::{Field, FieldType};
+
+    fn table(patterns: Vec<&str>, filters: Vec<(&str, &str, &str)>) -> Table {
+        Table {
+            name: "trips".to_string(),
+            prefix: "trips".to_string(),
+            file_type: "parquet".to_string(),
+            extension: ".parquet".to_string(),
+            watch: false,
+            patterns: patterns.into_iter().map(String::from).collect(),
+            hive_partitioning: true,
+            partition_filters: filters
+                .into_iter()
+                .map(|(column, operator, value)| PartitionFilter {
+                    column: column.to_string(),
+                    operator: operator.to_string(),
+                    value: value.to_string(),
+                })
+                .collect(),
+        }
+    }
+
+    fn paths() -> Vec<String> {
+        vec![
+            "year=2022/month=12/city=Paris/part-0.parquet".to_string(),
+            "year=2023/month=01/city=Hanoi/part-0.parquet".to_string(),
+            "year=2023/month=02/city=__HIVE_DEFAULT_PARTITION__/part-0.parquet".to_string(),
+            "year=2023/month=02/city=Hanoi/_SUCCESS".to_string(),
+        ]
+    }
+
+    #[test]
+    fn test_it_extracts_partition_values() {
+        assert_eq!(
+            partition_values("year=2023/month=01/part-0.parquet"),
+            vec![("year", "2023"), ("month", "01")]
+        );
+        assert_eq!(partition_values("data/part=0.parquet"), vec![]);
+    }
+
+    #[test]
+    fn test_it_infers_partition_columns() {
+        let matcher =
+            PartitionMatcher::new(&table(vec!["**/*.parquet"], vec![]), &paths()).unwrap();
+        assert_eq!(
+            matcher.columns(),
+            &[
+                PartitionColumn {
+                    name: "year".to_string(),
+                    typ: FieldType::Int
+                },
+                PartitionColumn {
+                    name: "month".to_string(),
+                    typ: FieldType::Int
+                },
+                PartitionColumn {
+                    name: "city".to_string(),
+                    typ: FieldType::String
+                },
+            ]
+        );
+
+        assert_eq!(
+            matcher.matches(&paths()[2]),
+            Some(vec![Field::Int(2023), Field::Int(2), Field::Null])
+        );
+        assert_eq!(matcher.matches(&paths()[3]), None);
+    }
+
+    #[test]
+    fn test_it_infers_partition_columns_once() {
+        let matchers = PartitionMatchers::default();
+        let table = table(vec![], vec![]);
+        matchers.get_or_infer(&table, &paths()).unwrap();
+
+        // A file added later with a value of another type doesn't change the inferred types.
+        let path = "year=next/month=03/city=Lima/part-0.parquet".to_string();
+        let matcher = matchers.get_or_infer(&table, &[path.clone()]).unwrap();
+        assert_eq!(matcher.columns()[0].typ, FieldType::Int);
+        assert_eq!(
+            matcher.matches(&path),
+            Some(vec![
+                Field::Null,
+                Field::Int(3),
+                Field::String("Lima".to_string())
+            ])
+        );
+        assert_eq!(matcher.invalid_values(&path).len(), 1);
+    }
+
+    #[test]
+    fn test_it_prunes_partitions() {
+        let matcher = PartitionMatcher::new(
+            &table(
+                vec![],
+                vec![("year", ">=", "2023"), ("city", "!=", "Paris")],
+            ),
+            &paths(),
+        )
+        .unwrap();
+        let selected: Vec<bool> = paths()
+            .iter()
+            .map(|p| matcher.matches(p).is_some())
+            .collect();
+        assert_eq!(selected, vec![false, true, false, true]);
+
+        assert!(PartitionMatcher::new(&table(vec![], vec![("day", "=", "1")]), &paths()).is_err());
+        assert!(PartitionMatcher::new(&table(vec![], vec![("year", "~", "1")]), &paths()).is_err());
+        assert!(
+            PartitionMatcher::new(&table(vec![], vec![("year", "=", "abc")]), &paths()).is_err()
+        );
+    }
+}
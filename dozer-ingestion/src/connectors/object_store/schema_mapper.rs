@@ -1,5 +1,9 @@
 use crate::connectors::object_store::adapters::DozerObjectStore;
-use crate::connectors::object_store::helper::map_listing_options;
+use crate::connectors::object_store::delta;
+use crate::connectors::object_store::helper::{
+    is_delta_table, list_objects, map_listing_options, relative_path,
+};
+use crate::connectors::object_store::partition::PartitionMatchers;
 use crate::connectors::object_store::schema_helper::map_schema_to_dozer;
 use crate::connectors::TableInfo;
 use crate::errors::ObjectStoreObjectError::ListingPathParsingError;
@@ -8,44 +12,73 @@ use datafusion::arrow::datatypes::SchemaRef;
 use datafusion::datasource::listing::ListingTableUrl;
 use datafusion::prelude::SessionContext;
 use dozer_types::log::error;
-use dozer_types::types::ReplicationChangesTrackingType::Nothing;
-use dozer_types::types::{Schema, SchemaIdentifier, SourceSchema};
+use dozer_types::types::ReplicationChangesTrackingType::{FullChanges, Nothing};
+use dozer_types::types::{FieldDefinition, Schema, SchemaIdentifier, SourceSchema};
+use object_store::path::Path;
 use std::sync::Arc;
 use tokio::runtime::Runtime;
 
 pub struct SchemaMapper<T: Clone + Send + Sync> {
     config: T,
+    matchers: Arc<PartitionMatchers>,
 }
 
 impl<T: Clone + Send + Sync> SchemaMapper<T> {
-    pub fn new(config: T) -> SchemaMapper<T> {
-        Self { config }
+    pub fn new(config: T, matchers: Arc<PartitionMatchers>) -> SchemaMapper<T> {
+        Self { config, matchers }
     }
 
     fn map_schema(
         &self,
         id: u32,
         resolved_schema: SchemaRef,
+        partition_fields: Vec<FieldDefinition>,
         table: &TableInfo,
     ) -> Result<Schema, ConnectorError> {
         let fields_list = resolved_schema.fields().iter();
 
-        let fields = match &table.columns {
+        let (fields, partition_fields) = match &table.columns {
             Some(columns) if !columns.is_empty() => {
                 let fields_list =
                     fields_list.filter(|f| columns.iter().any(|c| &c.name == f.name()));
+                let partition_fields = partition_fields
+                    .into_iter()
+                    .filter(|f| columns.iter().any(|c| c.name == f.name))
+                    .collect();
 
-                map_schema_to_dozer(fields_list)
+                (map_schema_to_dozer(fields_list), partition_fields)
             }
-            _ => map_schema_to_dozer(fields_list),
+            _ => (map_schema_to_dozer(fields_list), partition_fields),
         };
 
+        // Values of the partition columns are appended to the values read from the files.
+        let mut fields = fields.map_err(ObjectStoreConnectorError::DataFusionSchemaError)?;
+        fields.extend(partition_fields);
+
         Ok(Schema {
             identifier: Some(SchemaIdentifier { id, version: 0 }),
-            fields: fields.map_err(ObjectStoreConnectorError::DataFusionSchemaError)?,
+            fields,
             primary_index: vec![],
         })
     }
+
+    /// Rows of delta tables have no key, and their changes are matched by the values of all their
+    /// columns, which must identify them.
+    fn map_delta_schema(&self, id: u32, fields: Vec<FieldDefinition>, table: &TableInfo) -> Schema {
+        let fields = match &table.columns {
+            Some(columns) if !columns.is_empty() => fields
+                .into_iter()
+                .filter(|f| columns.iter().any(|c| c.name == f.name))
+                .collect(),
+            _ => fields,
+        };
+
+        Schema {
+            identifier: Some(SchemaIdentifier { id, version: 0 }),
+            primary_index: (0..fields.len()).collect(),
+            fields,
+        }
+    }
 }
 
 pub trait Mapper<T> {
@@ -83,7 +116,51 @@ impl<T: DozerObjectStore> Mapper<T> for SchemaMapper<T> {
 
                 let params = self.config.table_params(&table_name)?;
 
-                let table_path = ListingTableUrl::parse(params.table_path).map_err(|e| {
+                // Delta tables declare their schema in the transaction log, and their changes
+                // carry the full old records.
+                if is_delta_table(params.data_fusion_table) {
+                    let metadata = rt
+                        .block_on(delta::read_metadata(
+                            &params.object_store,
+                            &params.data_fusion_table.prefix,
+                        ))
+                        .map_err(ObjectStoreConnectorError::DeltaError)?;
+                    let fields = delta::map_schema(&metadata)
+                        .map_err(ObjectStoreConnectorError::DeltaError)?;
+                    let schema = self.map_delta_schema(id as u32, fields, table);
+
+                    return Ok(SourceSchema::new(table_name, schema, FullChanges));
+                }
+
+                let table_config = params.data_fusion_table;
+                let objects = rt.block_on(list_objects(
+                    &params.object_store,
+                    &Path::from(table_config.prefix.as_str()),
+                    &table_config.extension,
+                ))?;
+                let paths: Vec<String> = objects
+                    .iter()
+                    .map(|o| relative_path(&table_config.prefix, o.location.as_ref()).to_string())
+                    .collect();
+                let matcher = self
+                    .matchers
+                    .get_or_infer(table_config, &paths)
+                    .map_err(ObjectStoreConnectorError::PartitionError)?;
+
+                // When only some of the files are selected, the schema is inferred from the first
+                // of them, not from all the files under the prefix.
+                let mut table_path = params.table_path;
+                if !table_config.patterns.is_empty() || !table_config.partition_filters.is_empty() {
+                    for path in &paths {
+                        if matcher.matches(path).is_some() {
+                            table_path =
+                                format!("{}/{}/{path}", params.base_path, table_config.prefix);
+                            break;
+                        }
+                    }
+                }
+
+                let table_path = ListingTableUrl::parse(table_path).map_err(|e| {
                     ObjectStoreConnectorError::DataFusionStorageObjectError(
                         ListingPathParsingError(e),
                     )
@@ -107,7 +184,12 @@ impl<T: DozerObjectStore> Mapper<T> for SchemaMapper<T> {
                         ConnectorError::WrongConnectionConfiguration
                     })?;
 
-                let schema = self.map_schema(id as u32, resolved_schema, table)?;
+                let schema = self.map_schema(
+                    id as u32,
+                    resolved_schema,
+                    matcher.field_definitions(),
+                    table,
+                )?;
 
                 Ok(SourceSchema::new(table_name, schema, Nothing))
             })
@@ -1,9 +1,19 @@
+use crate::connectors::journal::Journal;
 use crate::connectors::object_store::adapters::DozerObjectStore;
-use crate::connectors::object_store::helper::map_listing_options;
+use crate::connectors::object_store::delta;
+use crate::connectors::object_store::delta::{
+    ChangeMapper, DeltaAction, DeltaFile, LogFile, CHANGE_TYPE_COLUMN,
+};
+use crate::connectors::object_store::helper::{
+    is_delta_table, list_objects, map_listing_options, relative_path,
+};
+use crate::connectors::object_store::partition::{
+    PartitionColumn, PartitionMatcher, PartitionMatchers,
+};
 use crate::connectors::object_store::schema_helper::map_value_to_dozer_field;
-use crate::connectors::{ColumnInfo, TableInfo};
+use crate::connectors::TableInfo;
 use crate::errors::ObjectStoreConnectorError::TableReaderError;
-use crate::errors::ObjectStoreObjectError::ListingPathParsingError;
+use crate::errors::ObjectStoreObjectError::{ListingPathParsingError, TableDefinitionNotFound};
 use crate::errors::ObjectStoreTableReaderError::{
     ColumnsSelectFailed, StreamExecutionError, TableReadFailed,
 };
@@ -13,35 +23,273 @@ use datafusion::datasource::listing::{
     ListingOptions, ListingTable, ListingTableConfig, ListingTableUrl,
 };
 use datafusion::prelude::SessionContext;
-use dozer_types::ingestion_types::IngestionMessage;
-use dozer_types::types::{Operation, Record, SchemaIdentifier};
+use dozer_types::ingestion_types::{IngestionMessage, Table};
+use dozer_types::log::{info, warn};
+use dozer_types::serde::{Deserialize, Serialize};
+use dozer_types::types::{Field, Operation, Record, SchemaIdentifier};
 use futures::StreamExt;
+use object_store::path::Path;
+use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::runtime::Runtime;
 
+/// Delay between two listings of the watched tables.
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Position of an ingested row, stored in the checkpoint as the message identifier. Every
+/// ingested file is a transaction, numbered in ingestion order, and rows are numbered in their
+/// file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct FilePosition {
+    pub txid: u64,
+    pub row: u64,
+}
+
+/// File listed under the prefix of a table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileInfo {
+    pub table_idx: usize,
+    pub location: String,
+    pub modified: u64,
+    pub size: usize,
+    pub partition_values: Vec<Field>,
+    // Commit or checkpoint, for the files of the transaction log of delta tables
+    pub log_file: Option<LogFile>,
+}
+
+/// File whose ingestion started as transaction `txid`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(crate = "dozer_types::serde")]
+pub struct JournalEntry {
+    pub txid: u64,
+    pub table: String,
+    pub location: String,
+    pub modified: u64,
+    pub size: usize,
+}
+
+/// Keeps track of the ingested files by table and path, so that every listing only yields the
+/// files which were not ingested yet, whatever their modification time.
+///
+/// With a journal, files are persisted before being ingested, so that the files ingested at a
+/// checkpoint can be restored.
+#[derive(Debug)]
+pub struct FileTracker {
+    // Modification time, size and transaction of the ingested files, by table and path
+    ingested: HashMap<(String, String), (u64, usize, u64)>,
+    next_txid: u64,
+    // Partially ingested file at the checkpoint, with the position to resume it from
+    resume: Option<FilePosition>,
+    journal: Option<Journal<JournalEntry>>,
+}
+
+impl FileTracker {
+    /// Whether the journal at `path` holds the files ingested at `checkpoint`.
+    pub fn can_resume(
+        path: &std::path::Path,
+        checkpoint: (u64, u64),
+    ) -> Result<bool, ConnectorError> {
+        Ok(Journal::<JournalEntry>::read(path)?
+            .iter()
+            .any(|entry| entry.txid == checkpoint.0))
+    }
+
+    /// Opens the journal at `path` if any, restoring the files ingested at `last_checkpoint`.
+    /// Files which were not committed are dropped, as they are ingested again.
+    pub fn open(
+        path: Option<&std::path::Path>,
+        last_checkpoint: Option<(u64, u64)>,
+    ) -> Result<Self, ConnectorError> {
+        let mut tracker = Self {
+            ingested: HashMap::new(),
+            next_txid: last_checkpoint.map_or(1, |(txid, _)| txid + 1),
+            resume: last_checkpoint.map(|(txid, row)| FilePosition { txid, row: row + 1 }),
+            journal: None,
+        };
+        let Some(path) = path else {
+            return Ok(tracker);
+        };
+
+        let (mut journal, mut entries) = Journal::open(path)?;
+        let last_txid = last_checkpoint.map_or(0, |(txid, _)| txid);
+        entries.retain(|entry| entry.txid <= last_txid);
+        journal.compact(&entries)?;
+        for entry in entries {
+            tracker.ingested.insert(
+                (entry.table, entry.location),
+                (entry.modified, entry.size, entry.txid),
+            );
+        }
+        tracker.journal = Some(journal);
+        Ok(tracker)
+    }
+
+    /// Returns the version of the checkpoint the delta tables are read from, by table. A table is
+    /// read from its latest checkpoint, unless its commits were already ingested.
+    fn delta_checkpoints(
+        &self,
+        tables: &[TableInfo],
+        files: &[FileInfo],
+    ) -> HashMap<usize, Option<u64>> {
+        let mut checkpoints = HashMap::new();
+        for file in files.iter().filter(|file| file.log_file.is_some()) {
+            if checkpoints.contains_key(&file.table_idx) {
+                continue;
+            }
+
+            let table = &tables[file.table_idx].name;
+            let ingested: Vec<LogFile> = self
+                .ingested
+                .keys()
+                .filter(|(name, _)| name == table)
+                .filter_map(|(_, location)| LogFile::parse(location))
+                .collect();
+            let checkpoint = if ingested.is_empty() {
+                let log = files
+                    .iter()
+                    .filter(|f| f.table_idx == file.table_idx)
+                    .filter_map(|f| f.log_file.as_ref());
+                delta::checkpoint_version(log)
+            } else {
+                ingested
+                    .iter()
+                    .filter(|f| matches!(f, LogFile::Checkpoint { .. }))
+                    .map(LogFile::version)
+                    .max()
+            };
+            checkpoints.insert(file.table_idx, checkpoint);
+        }
+        checkpoints
+    }
+
+    /// Returns the files to ingest in ingestion order, with the position of their first row.
+    /// Files are ingested once, so overwriting an ingested file is an error.
+    ///
+    /// Delta tables are read from the commits following their checkpoint, in the order of their
+    /// versions: a commit is not ingested before the ones preceding it, even if it was modified
+    /// earlier.
+    pub fn new_files(
+        &mut self,
+        tables: &[TableInfo],
+        mut files: Vec<FileInfo>,
+    ) -> Result<Vec<(FileInfo, FilePosition)>, ConnectorError> {
+        let checkpoints = self.delta_checkpoints(tables, &files);
+        files.retain(
+            |file| match (file.log_file, checkpoints.get(&file.table_idx)) {
+                (Some(LogFile::Checkpoint { version, .. }), Some(checkpoint)) => {
+                    Some(version) == *checkpoint
+                }
+                (Some(LogFile::Commit(version)), Some(Some(checkpoint))) => version > *checkpoint,
+                _ => true,
+            },
+        );
+
+        // Log files take the latest modification time of the files preceding them in the log.
+        files.sort_by_key(|file| (file.table_idx, file.log_file.map(|f| f.order())));
+        let mut previous: Option<(usize, u64)> = None;
+        let mut files: Vec<(u64, FileInfo)> = files
+            .into_iter()
+            .map(|file| {
+                let modified = match previous {
+                    Some((table_idx, modified))
+                        if file.log_file.is_some() && table_idx == file.table_idx =>
+                    {
+                        modified.max(file.modified)
+                    }
+                    _ => file.modified,
+                };
+                previous = Some((file.table_idx, modified));
+                (modified, file)
+            })
+            .collect();
+        files.sort_by(|(a_modified, a), (b_modified, b)| {
+            (
+                a_modified,
+                a.table_idx,
+                a.log_file.map(|f| f.order()),
+                &a.location,
+            )
+                .cmp(&(
+                    b_modified,
+                    b.table_idx,
+                    b.log_file.map(|f| f.order()),
+                    &b.location,
+                ))
+        });
+
+        let mut result = vec![];
+        for (_, file) in files {
+            let table = &tables[file.table_idx].name;
+            match self.ingested.get(&(table.clone(), file.location.clone())) {
+                Some(&(modified, size, txid)) => {
+                    if (modified, size) != (file.modified, file.size) {
+                        return Err(
+                            ObjectStoreConnectorError::FileOverwritten(file.location).into()
+                        );
+                    }
+                    // The file was partially ingested before the checkpoint, and is finished
+                    // before any other file so that positions keep increasing.
+                    if let Some(position) = self.resume.filter(|p| p.txid == txid) {
+                        result.insert(0, (file, position));
+                    }
+                }
+                None => {
+                    let entry = JournalEntry {
+                        txid: self.next_txid,
+                        table: table.clone(),
+                        location: file.location.clone(),
+                        modified: file.modified,
+                        size: file.size,
+                    };
+                    if let Some(journal) = &mut self.journal {
+                        journal.append(&entry)?;
+                    }
+                    self.next_txid += 1;
+                    self.ingested.insert(
+                        (entry.table, entry.location),
+                        (entry.modified, entry.size, entry.txid),
+                    );
+                    result.push((
+                        file,
+                        FilePosition {
+                            txid: entry.txid,
+                            row: 0,
+                        },
+                    ));
+                }
+            }
+        }
+
+        self.resume = None;
+        Ok(result)
+    }
+}
+
 pub struct TableReader<T: Clone + Send + Sync> {
     config: T,
+    matchers: Arc<PartitionMatchers>,
 }
 
 impl<T: Clone + Send + Sync> TableReader<T> {
-    pub fn new(config: T) -> TableReader<T> {
-        Self { config }
+    pub fn new(config: T, matchers: Arc<PartitionMatchers>) -> TableReader<T> {
+        Self { config, matchers }
     }
 
-    pub async fn read(
-        id: u32,
-        ctx: SessionContext,
+    /// Reads the rows of the files under `table_path`, with the values of `columns`, or of all
+    /// the columns when `columns` is empty.
+    async fn read_rows(
+        ctx: &SessionContext,
         table_path: ListingTableUrl,
         listing_options: ListingOptions,
-        ingestor: &Ingestor,
-        table: &TableInfo,
+        columns: &[String],
+        mut on_row: impl FnMut(Vec<Field>) -> Result<(), ObjectStoreConnectorError>,
     ) -> Result<(), ObjectStoreConnectorError> {
         let resolved_schema = listing_options
             .infer_schema(&ctx.state(), &table_path)
             .await
             .map_err(ObjectStoreConnectorError::InternalDataFusionError)?;
 
-        let mut idx = 0;
         let fields = resolved_schema.all_fields();
 
         let config = ListingTableConfig::new(table_path)
@@ -53,18 +301,11 @@ impl<T: Clone + Send + Sync> TableReader<T> {
                 .map_err(ObjectStoreConnectorError::InternalDataFusionError)?,
         );
 
-        let columns: Vec<ColumnInfo> = match &table.columns {
-            Some(columns_list) if !columns_list.is_empty() => columns_list.clone(),
-            _ => fields
-                .iter()
-                .map(|f| ColumnInfo {
-                    name: f.name().to_string(),
-                    data_type: Some(f.data_type().to_string()),
-                })
-                .collect(),
+        let cols: Vec<&str> = if columns.is_empty() {
+            fields.iter().map(|f| f.name().as_str()).collect()
+        } else {
+            columns.iter().map(|c| c.as_str()).collect()
         };
-
-        let cols: Vec<&str> = columns.iter().map(|c| c.name.as_str()).collect();
         let data = ctx
             .read_table(provider.clone())
             .map_err(|e| TableReaderError(TableReadFailed(e)))?
@@ -77,19 +318,44 @@ impl<T: Clone + Send + Sync> TableReader<T> {
         tokio::pin!(data);
 
         while let Some(Ok(batch)) = data.next().await {
+            let batch_schema = batch.schema();
             for row in 0..batch.num_rows() {
                 let fields = batch
                     .columns()
                     .iter()
                     .enumerate()
                     .map(|(col, column)| {
-                        map_value_to_dozer_field(column, &row, resolved_schema.field(col).name())
+                        map_value_to_dozer_field(column, &row, batch_schema.field(col).name())
                     })
                     .collect::<Result<Vec<_>, _>>()?;
 
+                on_row(fields)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Reads the rows of the files under `table_path` as inserts, appending the values of the
+    /// partition columns of the files.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn read(
+        id: u32,
+        ctx: SessionContext,
+        table_path: ListingTableUrl,
+        listing_options: ListingOptions,
+        ingestor: &Ingestor,
+        columns: &[String],
+        partition_values: &[Field],
+        from_position: FilePosition,
+    ) -> Result<(), ObjectStoreConnectorError> {
+        let mut idx = 0;
+        Self::read_rows(&ctx, table_path, listing_options, columns, |mut fields| {
+            if idx >= from_position.row {
+                fields.extend_from_slice(partition_values);
                 ingestor
                     .handle_message(IngestionMessage::new_op(
-                        0_u64,
+                        from_position.txid,
                         idx,
                         Operation::Insert {
                             new: Record {
@@ -100,51 +366,435 @@ impl<T: Clone + Send + Sync> TableReader<T> {
                         },
                     ))
                     .map_err(ObjectStoreConnectorError::IngestorError)?;
+            }
+
+            idx += 1;
+            Ok(())
+        })
+        .await
+    }
+
+    /// Reads the changes of a delta commit, from its change data files when the table has
+    /// change data feed enabled, or else from the data files it removed and added.
+    #[allow(clippy::too_many_arguments)]
+    async fn read_delta_commit(
+        ctx: &SessionContext,
+        table_url: &str,
+        data_fusion_table: &Table,
+        actions: Vec<DeltaAction>,
+        id: u32,
+        ingestor: &Ingestor,
+        table: &TableInfo,
+        from_position: FilePosition,
+    ) -> Result<(), ObjectStoreConnectorError> {
+        let schema_id = SchemaIdentifier { id, version: 0 };
+        let columns = column_names(table);
+        let listing_options = || {
+            map_listing_options(data_fusion_table)
+                .map_err(ObjectStoreConnectorError::DataFusionStorageObjectError)
+        };
+
+        let mut idx = 0;
+        let mut emit = |op: Operation| -> Result<(), ObjectStoreConnectorError> {
+            if idx >= from_position.row {
+                ingestor
+                    .handle_message(IngestionMessage::new_op(from_position.txid, idx, op))
+                    .map_err(ObjectStoreConnectorError::IngestorError)?;
+            }
+            idx += 1;
+            Ok(())
+        };
+
+        let changes: Vec<&DeltaFile> = actions.iter().filter_map(|a| a.cdc.as_ref()).collect();
+        if !changes.is_empty() {
+            let mut mapper = ChangeMapper::default();
+            for change in changes {
+                let file_path = delta_file_url(table_url, &change.path)?;
 
-                idx += 1;
+                let mut read_columns = if columns.is_empty() {
+                    listing_options()?
+                        .infer_schema(&ctx.state(), &file_path)
+                        .await
+                        .map_err(ObjectStoreConnectorError::InternalDataFusionError)?
+                        .fields()
+                        .iter()
+                        .map(|f| f.name().clone())
+                        .filter(|name| name != CHANGE_TYPE_COLUMN)
+                        .collect()
+                } else {
+                    columns.clone()
+                };
+                read_columns.push(CHANGE_TYPE_COLUMN.to_string());
+
+                Self::read_rows(
+                    ctx,
+                    file_path,
+                    listing_options()?,
+                    &read_columns,
+                    |mut fields| {
+                        let change_type = fields.pop().unwrap_or(Field::Null);
+                        if let Some(op) = mapper.map_change(schema_id, &change_type, fields)? {
+                            emit(op)?;
+                        }
+                        Ok(())
+                    },
+                )
+                .await?;
             }
+
+            return Ok(());
+        }
+
+        // Without change data, rows of removed files are deleted and rows of added files inserted.
+        let removed = actions
+            .iter()
+            .filter_map(|a| a.remove.as_ref())
+            .filter(|f| f.data_change);
+        for file in removed {
+            let file_path = delta_file_url(table_url, &file.path)?;
+            Self::read_rows(ctx, file_path, listing_options()?, &columns, |fields| {
+                emit(Operation::Delete {
+                    old: Record::new(Some(schema_id), fields, None),
+                })
+            })
+            .await?;
+        }
+
+        let added = actions
+            .iter()
+            .filter_map(|a| a.add.as_ref())
+            .filter(|f| f.data_change);
+        for file in added {
+            let file_path = delta_file_url(table_url, &file.path)?;
+            Self::read_rows(ctx, file_path, listing_options()?, &columns, |fields| {
+                emit(Operation::Insert {
+                    new: Record::new(Some(schema_id), fields, None),
+                })
+            })
+            .await?;
         }
 
         Ok(())
     }
 }
 
-pub trait Reader<T> {
-    fn read_tables(&self, tables: &[TableInfo], ingestor: &Ingestor) -> Result<(), ConnectorError>;
+fn column_names(table: &TableInfo) -> Vec<String> {
+    table.columns.as_ref().map_or(vec![], |columns| {
+        columns.iter().map(|c| c.name.clone()).collect()
+    })
 }
 
-impl<T: DozerObjectStore> Reader<T> for TableReader<T> {
-    fn read_tables(&self, tables: &[TableInfo], ingestor: &Ingestor) -> Result<(), ConnectorError> {
-        for (id, table) in tables.iter().enumerate() {
-            let params = self.config.table_params(&table.name)?;
+/// Splits the selected columns of a table into the columns read from its files and the values of
+/// the selected partition columns, which are appended to them.
+fn select_columns(
+    table: &TableInfo,
+    partition_columns: &[PartitionColumn],
+    partition_values: &[Field],
+) -> (Vec<String>, Vec<Field>) {
+    let columns = column_names(table);
+    if columns.is_empty() {
+        return (columns, partition_values.to_vec());
+    }
 
-            let table_path = ListingTableUrl::parse(params.table_path).map_err(|e| {
-                ObjectStoreConnectorError::DataFusionStorageObjectError(ListingPathParsingError(e))
-            })?;
+    let is_partition_column = |name: &String| partition_columns.iter().any(|c| &c.name == name);
+    let values = partition_columns
+        .iter()
+        .zip(partition_values)
+        .filter(|(column, _)| columns.contains(&column.name))
+        .map(|(_, value)| value.clone())
+        .collect();
+    let columns = columns
+        .into_iter()
+        .filter(|name| !is_partition_column(name))
+        .collect();
+    (columns, values)
+}
 
-            let listing_options = map_listing_options(params.data_fusion_table)
-                .map_err(ObjectStoreConnectorError::DataFusionStorageObjectError)?;
+/// Data files of delta tables are referenced relatively to the table root, or by absolute urls.
+fn delta_file_url(
+    table_url: &str,
+    path: &str,
+) -> Result<ListingTableUrl, ObjectStoreConnectorError> {
+    let url = if path.contains("://") {
+        path.to_string()
+    } else {
+        format!("{table_url}/{path}")
+    };
 
-            let rt = Runtime::new().map_err(|_| ObjectStoreConnectorError::RuntimeCreationError)?;
+    ListingTableUrl::parse(url).map_err(|e| {
+        ObjectStoreConnectorError::DataFusionStorageObjectError(ListingPathParsingError(e))
+    })
+}
 
-            let ctx = SessionContext::new();
+impl<T: DozerObjectStore> TableReader<T> {
+    /// Returns the matchers selecting the files of the tables, inferred from a listing of their
+    /// files unless the schemas were already read. Files of delta tables are all selected.
+    async fn partition_matchers(
+        &self,
+        tables: &[TableInfo],
+    ) -> Result<Vec<PartitionMatcher>, ConnectorError> {
+        let mut matchers = vec![];
+        for table in tables {
+            let params = self.config.table_params(&table.name)?;
+            let table_config = params.data_fusion_table;
+            if is_delta_table(table_config) {
+                matchers.push(PartitionMatcher::default());
+                continue;
+            }
 
-            ctx.runtime_env().register_object_store(
-                params.scheme,
-                params.host,
-                Arc::new(params.object_store),
+            let objects = list_objects(
+                &params.object_store,
+                &Path::from(table_config.prefix.as_str()),
+                &table_config.extension,
+            )
+            .await?;
+            let paths: Vec<String> = objects
+                .iter()
+                .map(|o| relative_path(&table_config.prefix, o.location.as_ref()).to_string())
+                .collect();
+
+            matchers.push(
+                self.matchers
+                    .get_or_infer(table_config, &paths)
+                    .map_err(ObjectStoreConnectorError::PartitionError)?,
             );
+        }
+
+        Ok(matchers)
+    }
+
+    async fn list_files(
+        &self,
+        tables: &[TableInfo],
+        matchers: &[PartitionMatcher],
+    ) -> Result<Vec<FileInfo>, ConnectorError> {
+        let mut files = vec![];
+        for (table_idx, table) in tables.iter().enumerate() {
+            let params = self.config.table_params(&table.name)?;
+            // Changes of delta tables are followed through the commits of their transaction log.
+            if is_delta_table(params.data_fusion_table) {
+                let log = delta::list_log(&params.object_store, &params.data_fusion_table.prefix)
+                    .await
+                    .map_err(ObjectStoreConnectorError::DeltaError)?;
+                for (object, log_file) in log {
+                    files.push(FileInfo {
+                        table_idx,
+                        location: object.location.to_string(),
+                        modified: object.last_modified.timestamp_millis() as u64,
+                        size: object.size,
+                        partition_values: vec![],
+                        log_file: Some(log_file),
+                    });
+                }
+                continue;
+            }
+
+            let objects = list_objects(
+                &params.object_store,
+                &Path::from(params.data_fusion_table.prefix.as_str()),
+                &params.data_fusion_table.extension,
+            )
+            .await?;
+            for object in objects {
+                let location = object.location.to_string();
+                let path = relative_path(&params.data_fusion_table.prefix, &location);
+                let partition_values = match matchers[table_idx].matches(path) {
+                    Some(values) => values,
+                    None => continue,
+                };
+
+                files.push(FileInfo {
+                    table_idx,
+                    location,
+                    modified: object.last_modified.timestamp_millis() as u64,
+                    size: object.size,
+                    partition_values,
+                    log_file: None,
+                });
+            }
+        }
 
-            rt.block_on(Self::read(
-                id as u32,
-                ctx,
-                table_path,
-                listing_options,
+        Ok(files)
+    }
+
+    async fn read_file(
+        &self,
+        table: &TableInfo,
+        matcher: &PartitionMatcher,
+        file: &FileInfo,
+        from_position: FilePosition,
+        ingestor: &Ingestor,
+    ) -> Result<(), ConnectorError> {
+        let params = self.config.table_params(&table.name)?;
+
+        // Files listed for delta tables are the commits and checkpoints of their transaction log.
+        let location = Path::from(file.location.as_str());
+        let delta_actions = match file.log_file {
+            Some(LogFile::Commit(_)) => {
+                let actions = delta::read_commit(&params.object_store, &location)
+                    .await
+                    .map_err(ObjectStoreConnectorError::DeltaError)?;
+                // Without change data, the rows of the removed files are read again to delete
+                // them.
+                if actions.iter().all(|a| a.cdc.is_none()) {
+                    let removed = actions
+                        .iter()
+                        .filter_map(|a| a.remove.as_ref())
+                        .filter(|f| f.data_change);
+                    for removed in removed {
+                        delta::check_removed_file(
+                            &params.object_store,
+                            &params.data_fusion_table.prefix,
+                            &removed.path,
+                        )
+                        .await
+                        .map_err(ObjectStoreConnectorError::DeltaError)?;
+                    }
+                }
+                Some(actions)
+            }
+            // The data files of a checkpoint are the rows of the table at its version.
+            Some(LogFile::Checkpoint { .. }) => Some(
+                delta::read_checkpoint(&params.object_store, &location)
+                    .await
+                    .map_err(ObjectStoreConnectorError::DeltaError)?
+                    .into_iter()
+                    .filter_map(|a| a.add)
+                    .map(|add| DeltaAction {
+                        add: Some(DeltaFile {
+                            path: add.path,
+                            data_change: true,
+                        }),
+                        remove: None,
+                        cdc: None,
+                        metadata: None,
+                    })
+                    .collect(),
+            ),
+            None => None,
+        };
+
+        let ctx = SessionContext::new();
+
+        ctx.runtime_env().register_object_store(
+            params.scheme,
+            params.host,
+            Arc::new(params.object_store),
+        );
+
+        if let Some(actions) = delta_actions {
+            let table_url = format!("{}/{}", params.base_path, params.data_fusion_table.prefix);
+            Self::read_delta_commit(
+                &ctx,
+                &table_url,
+                params.data_fusion_table,
+                actions,
+                file.table_idx as u32,
                 ingestor,
                 table,
-            ))?;
+                from_position,
+            )
+            .await?;
+
+            return Ok(());
+        }
+
+        let file_path = ListingTableUrl::parse(format!("{}/{}", params.base_path, file.location))
+            .map_err(|e| {
+            ObjectStoreConnectorError::DataFusionStorageObjectError(ListingPathParsingError(e))
+        })?;
+
+        let listing_options = map_listing_options(params.data_fusion_table)
+            .map_err(ObjectStoreConnectorError::DataFusionStorageObjectError)?;
+
+        let path = relative_path(&params.data_fusion_table.prefix, &file.location);
+        for error in matcher.invalid_values(path) {
+            warn!(
+                "[object_store][{}] {error}, it is ingested as null",
+                table.name
+            );
         }
+        let (columns, partition_values) =
+            select_columns(table, matcher.columns(), &file.partition_values);
+
+        Self::read(
+            file.table_idx as u32,
+            ctx,
+            file_path,
+            listing_options,
+            ingestor,
+            &columns,
+            &partition_values,
+            from_position,
+        )
+        .await?;
 
         Ok(())
     }
 }
+
+pub trait Reader<T> {
+    fn read_tables(
+        &self,
+        tables: &[TableInfo],
+        from_seq: Option<(u64, u64)>,
+        ingestor: &Ingestor,
+    ) -> Result<(), ConnectorError>;
+}
+
+impl<T: DozerObjectStore> Reader<T> for TableReader<T> {
+    fn read_tables(
+        &self,
+        tables: &[TableInfo],
+        from_seq: Option<(u64, u64)>,
+        ingestor: &Ingestor,
+    ) -> Result<(), ConnectorError> {
+        let mut watched = vec![];
+        for table in tables {
+            let table_config = self
+                .config
+                .tables()
+                .iter()
+                .find(|t| t.name == table.name)
+                .ok_or(ObjectStoreConnectorError::DataFusionStorageObjectError(
+                    TableDefinitionNotFound,
+                ))?;
+            watched.push(table_config.watch);
+        }
+
+        let rt = Runtime::new().map_err(|_| ObjectStoreConnectorError::RuntimeCreationError)?;
+
+        rt.block_on(async {
+            let matchers = self.partition_matchers(tables).await?;
+            let state_path = self.config.state_path().map(std::path::Path::new);
+            let mut tracker = FileTracker::open(state_path, from_seq)?;
+            let mut first_listing = true;
+            loop {
+                let mut files = self.list_files(tables, &matchers).await?;
+                // After the first listing, only files of watched tables are ingested.
+                files.retain(|file| first_listing || watched[file.table_idx]);
+                for (file, position) in tracker.new_files(tables, files)? {
+                    info!(
+                        "[object_store][{}] Ingesting {}",
+                        tables[file.table_idx].name, file.location
+                    );
+                    self.read_file(
+                        &tables[file.table_idx],
+                        &matchers[file.table_idx],
+                        &file,
+                        position,
+                        ingestor,
+                    )
+                    .await?;
+                }
+
+                if !watched.contains(&true) {
+                    return Ok(());
+                }
+
+                first_listing = false;
+                tokio::time::sleep(POLL_INTERVAL).await;
+            }
+        })
+    }
+}
@@ -4,15 +4,23 @@ use crate::connectors::TableInfo;
 use crate::ingestion::{IngestionConfig, Ingestor};
 use dozer_types::ingestion_types::IngestionMessage;
 use dozer_types::ingestion_types::IngestionMessageKind;
-use dozer_types::ingestion_types::LocalDetails;
+use dozer_types::ingestion_types::{LocalDetails, LocalStorage};
+use dozer_types::ingestion_types::{PartitionFilter, Table};
 use dozer_types::node::OpIdentifier;
+use dozer_types::ordered_float::OrderedFloat;
+use std::io::Cursor;
 use std::thread;
+use std::time::Duration;
+use tempdir::TempDir;
 
 use crate::connectors::object_store::helper::map_listing_options;
 use crate::connectors::object_store::tests::test_utils::get_local_storage_config;
-use crate::errors::ConnectorError::InitializationError;
+use crate::errors::ConnectorError::{InitializationError, ObjectStoreConnectorError};
+use crate::errors::ObjectStoreConnectorError as ObjectStoreConnectorErrorKind;
 use crate::errors::ObjectStoreObjectError;
-use dozer_types::types::{Field, FieldType, Operation};
+use datafusion::arrow::json::ReaderBuilder;
+use datafusion::parquet::arrow::ArrowWriter;
+use dozer_types::types::{Field, FieldType, Operation, ReplicationChangesTrackingType};
 
 #[macro_export]
 macro_rules! test_type_conversion {
@@ -174,6 +182,283 @@ fn test_csv_read() {
     }
 }
 
+fn read_products(typ: &str) {
+    let local_storage = get_local_storage_config(typ);
+
+    let connector = ObjectStoreConnector::new(1, local_storage);
+    let schemas = connector.get_schemas(None).unwrap();
+    let fields = schemas.get(0).unwrap().schema.fields.clone();
+    assert_eq!(
+        fields.iter().map(|f| f.typ).collect::<Vec<_>>(),
+        vec![
+            FieldType::Int,
+            FieldType::String,
+            FieldType::Float,
+            FieldType::Boolean
+        ]
+    );
+
+    let config = IngestionConfig::default();
+    let (ingestor, mut iterator) = Ingestor::initialize_channel(config);
+
+    let table = TableInfo {
+        name: format!("all_types_{typ}"),
+        table_name: format!("all_types_{typ}"),
+        id: 0,
+        columns: None,
+    };
+    thread::spawn(move || {
+        let _ = connector.start(None, &ingestor, vec![table]);
+    });
+
+    for i in 0..5 {
+        if let Some(IngestionMessage {
+            kind: IngestionMessageKind::OperationEvent(Operation::Insert { new }),
+            ..
+        }) = iterator.next()
+        {
+            assert_eq!(new.values[0], Field::Int(i + 1));
+            test_type_conversion!(new.values, 1, Field::String(_));
+            test_type_conversion!(new.values, 2, Field::Float(_));
+            test_type_conversion!(new.values, 3, Field::Boolean(_));
+        } else {
+            panic!("Unexpected message");
+        }
+    }
+}
+
+#[test]
+fn test_json_read() {
+    read_products("json");
+}
+
+#[test]
+fn test_avro_read() {
+    read_products("avro");
+}
+
+#[test]
+fn test_get_schema_of_delta() {
+    let local_storage = get_local_storage_config("delta");
+
+    let connector = ObjectStoreConnector::new(1, local_storage);
+    let schemas = connector.get_schemas(None).unwrap();
+    let schema = schemas.get(0).unwrap();
+    assert!(matches!(
+        schema.replication_type,
+        ReplicationChangesTrackingType::FullChanges
+    ));
+
+    // Rows are identified by the values of all their columns.
+    assert_eq!(schema.schema.primary_index, (0..11).collect::<Vec<_>>());
+
+    let fields = schema.schema.fields.clone();
+    assert_eq!(fields.len(), 11);
+    assert_eq!(fields.get(0).unwrap().name, "id");
+    assert_eq!(fields.get(0).unwrap().typ, FieldType::Int);
+    assert_eq!(fields.get(6).unwrap().typ, FieldType::Float);
+    assert_eq!(fields.get(8).unwrap().typ, FieldType::Binary);
+    assert_eq!(fields.get(10).unwrap().typ, FieldType::Timestamp);
+}
+
+fn read_delta(local_storage: LocalStorage, count: usize) -> Vec<&'static str> {
+    let connector = ObjectStoreConnector::new(1, local_storage);
+
+    let config = IngestionConfig::default();
+    let (ingestor, mut iterator) = Ingestor::initialize_channel(config);
+
+    let table = TableInfo {
+        name: "all_types_delta".to_string(),
+        table_name: "all_types_delta".to_string(),
+        id: 0,
+        columns: None,
+    };
+    thread::spawn(move || {
+        let _ = connector.start(None, &ingestor, vec![table]);
+    });
+
+    let mut kinds = vec![];
+    for _ in 0..count {
+        match iterator.next() {
+            Some(IngestionMessage {
+                kind: IngestionMessageKind::OperationEvent(op),
+                ..
+            }) => kinds.push(match op {
+                Operation::Insert { new } => {
+                    test_type_conversion!(new.values, 10, Field::Timestamp(_));
+                    "insert"
+                }
+                Operation::Delete { .. } => "delete",
+                Operation::Update { .. } => "update",
+            }),
+            _ => panic!("Unexpected message"),
+        }
+    }
+    kinds
+}
+
+/// Copies the data files of the delta test table to `dir`, and writes the files of its log in
+/// the given order, so that their modification times follow it.
+fn write_delta_table(dir: &TempDir, log: &[(&str, Vec<u8>)]) -> LocalStorage {
+    let source = std::path::Path::new("src/connectors/object_store/tests/files/all_types_delta");
+    let table_dir = dir.path().join("all_types_delta");
+    std::fs::create_dir_all(table_dir.join("_delta_log")).unwrap();
+    for file in ["part-00000.parquet", "part-00001.parquet"] {
+        std::fs::copy(source.join(file), table_dir.join(file)).unwrap();
+    }
+    for (name, content) in log {
+        std::fs::write(table_dir.join("_delta_log").join(name), content).unwrap();
+        thread::sleep(Duration::from_millis(20));
+    }
+
+    let mut local_storage = get_local_storage_config("delta");
+    local_storage.details = Some(LocalDetails {
+        path: dir.path().to_str().unwrap().to_string(),
+    });
+    local_storage
+}
+
+fn read_delta_commit(version: u64) -> Vec<u8> {
+    std::fs::read(format!(
+        "src/connectors/object_store/tests/files/all_types_delta/_delta_log/{version:020}.json"
+    ))
+    .unwrap()
+}
+
+/// Checkpoint of the delta test table at version 1, written from its actions in json.
+fn write_delta_checkpoint(actions: &[String]) -> Vec<u8> {
+    let mut reader = ReaderBuilder::new()
+        .infer_schema(None)
+        .build(Cursor::new(actions.join("\n")))
+        .unwrap();
+    let batch = reader.next().unwrap().unwrap();
+
+    let mut checkpoint = vec![];
+    let mut writer = ArrowWriter::try_new(&mut checkpoint, batch.schema(), None).unwrap();
+    writer.write(&batch).unwrap();
+    writer.close().unwrap();
+    checkpoint
+}
+
+#[test]
+fn test_delta_read_follows_commits() {
+    // The first commit adds a file, the second one overwrites it with another file.
+    let mut expected = vec!["insert"; 8];
+    expected.extend(vec!["delete"; 8]);
+    expected.extend(vec!["insert"; 8]);
+
+    let local_storage = get_local_storage_config("delta");
+    assert_eq!(read_delta(local_storage, 24), expected);
+
+    // Commits are read in the order of their versions, whatever their modification times.
+    let dir = TempDir::new("object_store").unwrap();
+    let local_storage = write_delta_table(
+        &dir,
+        &[
+            ("00000000000000000001.json", read_delta_commit(1)),
+            ("00000000000000000000.json", read_delta_commit(0)),
+        ],
+    );
+    assert_eq!(read_delta(local_storage, 24), expected);
+
+    // The table is read from its last checkpoint, holding the file added by the second commit,
+    // and the commits preceding it are skipped.
+    let metadata = String::from_utf8(read_delta_commit(0))
+        .unwrap()
+        .lines()
+        .find(|line| line.contains("schemaString"))
+        .unwrap()
+        .replace(",\"partitionColumns\":[],\"configuration\":{}", "")
+        .replace("\"format\":{\"provider\":\"parquet\",\"options\":{}},", "");
+    let checkpoint = write_delta_checkpoint(&[
+        metadata,
+        r#"{"remove":{"path":"part-00000.parquet","deletionTimestamp":1677900060000,"dataChange":true}}"#.to_string(),
+        r#"{"add":{"path":"part-00001.parquet","size":1851,"modificationTime":1677900060000,"dataChange":false}}"#.to_string(),
+    ]);
+    let dir = TempDir::new("object_store").unwrap();
+    let local_storage = write_delta_table(
+        &dir,
+        &[
+            ("00000000000000000000.json", read_delta_commit(0)),
+            ("00000000000000000001.json", read_delta_commit(1)),
+            ("00000000000000000001.checkpoint.parquet", checkpoint),
+            ("_last_checkpoint", br#"{"version":1,"size":3}"#.to_vec()),
+            (
+                "00000000000000000002.json",
+                br#"{"add":{"path":"part-00000.parquet","partitionValues":{},"size":1851,"modificationTime":1677900120000,"dataChange":true}}"#.to_vec(),
+            ),
+        ],
+    );
+    assert_eq!(read_delta(local_storage, 16), vec!["insert"; 16]);
+}
+
+#[test]
+fn test_read_hive_partitioned_files() {
+    let mut local_storage = get_local_storage_config("csv");
+    local_storage.tables[0] = Table {
+        name: "partitioned_csv".to_string(),
+        prefix: "partitioned_csv".to_string(),
+        file_type: "csv".to_string(),
+        extension: ".csv".to_string(),
+        watch: false,
+        patterns: vec!["**/part-*.csv".to_string()],
+        hive_partitioning: true,
+        partition_filters: vec![PartitionFilter {
+            column: "year".to_string(),
+            operator: ">=".to_string(),
+            value: "2023".to_string(),
+        }],
+    };
+
+    let connector = ObjectStoreConnector::new(1, local_storage);
+    let schemas = connector.get_schemas(None).unwrap();
+    let fields = schemas.get(0).unwrap().schema.fields.clone();
+    assert_eq!(
+        fields
+            .iter()
+            .map(|f| (f.name.as_str(), f.typ))
+            .collect::<Vec<_>>(),
+        vec![
+            ("id", FieldType::Int),
+            ("amount", FieldType::Float),
+            ("year", FieldType::Int),
+            ("month", FieldType::Int)
+        ]
+    );
+
+    let config = IngestionConfig::default();
+    let (ingestor, mut iterator) = Ingestor::initialize_channel(config);
+
+    let table = TableInfo {
+        name: "partitioned_csv".to_string(),
+        table_name: "partitioned_csv".to_string(),
+        id: 0,
+        columns: None,
+    };
+    thread::spawn(move || {
+        let _ = connector.start(None, &ingestor, vec![table]);
+    });
+
+    // Only the part file of the 2023 partition is read.
+    if let Some(IngestionMessage {
+        kind: IngestionMessageKind::OperationEvent(Operation::Insert { new }),
+        ..
+    }) = iterator.next()
+    {
+        assert_eq!(
+            new.values,
+            vec![
+                Field::Int(3),
+                Field::Float(OrderedFloat(5.5)),
+                Field::Int(2023),
+                Field::Int(1)
+            ]
+        );
+    } else {
+        panic!("Unexpected message");
+    }
+}
+
 #[test]
 fn test_unsupported_format() {
     let local_storage = get_local_storage_config("unsupported");
@@ -214,3 +499,86 @@ fn test_missing_directory() {
 
     assert!(matches!(result, Err(InitializationError(_))));
 }
+
+fn get_state_config(dir: &TempDir) -> LocalStorage {
+    let mut local_storage = get_local_storage_config("csv");
+    local_storage.details = Some(LocalDetails {
+        path: dir.path().to_str().unwrap().to_string(),
+    });
+    local_storage.state_path = Some(dir.path().join("state").to_str().unwrap().to_string());
+    local_storage
+}
+
+fn write_csv(dir: &TempDir, name: &str, rows: &[&str]) {
+    let table_dir = dir.path().join("all_types_csv");
+    std::fs::create_dir_all(&table_dir).unwrap();
+    std::fs::write(
+        table_dir.join(name),
+        format!("id,name\n{}\n", rows.join("\n")),
+    )
+    .unwrap();
+}
+
+fn ingest(local_storage: LocalStorage, from_seq: Option<(u64, u64)>) -> Vec<(u64, u64)> {
+    let connector = ObjectStoreConnector::new(1, local_storage);
+
+    let config = IngestionConfig::default();
+    let (ingestor, iterator) = Ingestor::initialize_channel(config);
+
+    let table = TableInfo {
+        name: "all_types_csv".to_string(),
+        table_name: "all_types_csv".to_string(),
+        id: 0,
+        columns: None,
+    };
+
+    thread::spawn(move || {
+        connector.start(from_seq, &ingestor, vec![table]).unwrap();
+    });
+
+    iterator
+        .map(|message| (message.identifier.txid, message.identifier.seq_in_tx))
+        .collect()
+}
+
+#[test]
+fn test_csv_read_resumes_from_checkpoint() {
+    let dir = TempDir::new("object_store").unwrap();
+    write_csv(&dir, "a.csv", &["1,a", "2,b", "3,c"]);
+
+    let identifiers = ingest(get_state_config(&dir), None);
+    assert_eq!(identifiers, vec![(1, 0), (1, 1), (1, 2)]);
+
+    // The checkpoint is in the middle of the first file, and a file was added since.
+    write_csv(&dir, "b.csv", &["4,d", "5,e"]);
+    let connector = ObjectStoreConnector::new(1, get_state_config(&dir));
+    assert!(connector.can_start_from((1, 1)).unwrap());
+    assert!(!connector.can_start_from((2, 0)).unwrap());
+
+    let identifiers = ingest(get_state_config(&dir), Some((1, 1)));
+    assert_eq!(identifiers, vec![(1, 2), (2, 0), (2, 1)]);
+}
+
+#[test]
+fn test_overwritten_file_is_rejected() {
+    let dir = TempDir::new("object_store").unwrap();
+    write_csv(&dir, "a.csv", &["1,a"]);
+    assert_eq!(ingest(get_state_config(&dir), None), vec![(1, 0)]);
+
+    write_csv(&dir, "a.csv", &["1,a", "2,b"]);
+    let connector = ObjectStoreConnector::new(1, get_state_config(&dir));
+    let (ingestor, _iterator) = Ingestor::initialize_channel(IngestionConfig::default());
+    let table = TableInfo {
+        name: "all_types_csv".to_string(),
+        table_name: "all_types_csv".to_string(),
+        id: 0,
+        columns: None,
+    };
+    let result = connector.start(Some((1, 0)), &ingestor, vec![table]);
+    assert!(matches!(
+        result,
+        Err(ObjectStoreConnectorError(
+            ObjectStoreConnectorErrorKind::FileOverwritten(_)
+        ))
+    ));
+}
@@ -12,6 +12,11 @@ pub fn get_local_storage_config(typ: &str) -> LocalStorage {
             prefix: format!("all_types_{typ}"),
             file_type: typ.to_string(),
             extension: typ.to_string(),
+            watch: false,
+            patterns: vec![],
+            hive_partitioning: false,
+            partition_filters: vec![],
         }],
+        state_path: None,
     }
 }
@@ -0,0 +1,314 @@
+#[cfg(feature = "odbc")]
+use std::collections::HashMap;
+use std::path::Path;
+#[cfg(feature = "odbc")]
+use std::thread;
+#[cfg(feature = "odbc")]
+use std::time::Duration;
+
+use dozer_types::ingestion_types::OdbcConfig;
+#[cfg(feature = "odbc")]
+use dozer_types::ingestion_types::OdbcQuery;
+#[cfg(feature = "odbc")]
+use dozer_types::log::info;
+use dozer_types::types::SourceSchema;
+#[cfg(feature = "odbc")]
+use dozer_types::types::{FieldDefinition, SourceDefinition};
+#[cfg(feature = "odbc")]
+use odbc::odbc_safe::AutocommitOn;
+#[cfg(feature = "odbc")]
+use odbc::{create_environment_v3, Connection, Data, Environment, NoData, Statement, Version3};
+
+#[cfg(feature = "odbc")]
+use crate::connectors::odbc::helper::{map_column_type, ResultIterator};
+#[cfg(feature = "odbc")]
+use crate::connectors::odbc::query::{map_schema, replication_type, QueryState};
+use crate::connectors::odbc::sequencer::Sequencer;
+use crate::connectors::{Connector, TableInfo, ValidationResults};
+use crate::errors::{ConnectorError, OdbcError};
+use crate::ingestion::Ingestor;
+
+/// Rows of a poll ingested as a transaction.
+#[cfg(feature = "odbc")]
+const TRANSACTION_SIZE: usize = 1000;
+
+#[derive(Debug)]
+pub struct OdbcConnector {
+    pub id: u64,
+    name: String,
+    config: OdbcConfig,
+    sequencer: Sequencer,
+}
+
+impl OdbcConnector {
+    pub fn new(id: u64, name: String, config: OdbcConfig) -> Self {
+        Self {
+            id,
+            name,
+            config,
+            sequencer: Sequencer::default(),
+        }
+    }
+
+    fn state_path(&self) -> Option<&Path> {
+        self.config.state_path.as_deref().map(Path::new)
+    }
+}
+
+#[cfg(feature = "odbc")]
+impl OdbcConnector {
+    /// Tables exposing all the configured queries.
+    fn get_tables_info(&self) -> Vec<TableInfo> {
+        self.config
+            .queries
+            .iter()
+            .enumerate()
+            .map(|(id, query)| TableInfo {
+                name: query.name.clone(),
+                table_name: query.name.clone(),
+                id: id as u32,
+                columns: None,
+            })
+            .collect()
+    }
+
+    /// Returns the configured query behind a table, with its index.
+    fn get_query(&self, table: &TableInfo) -> Result<(u32, &OdbcQuery), OdbcError> {
+        self.config
+            .queries
+            .iter()
+            .enumerate()
+            .find(|(_, query)| query.name == table.table_name)
+            .map(|(idx, query)| (idx as u32, query))
+            .ok_or_else(|| OdbcError::QueryNotFound(table.table_name.clone()))
+    }
+
+    fn get_states(
+        &self,
+        conn: &Connection<AutocommitOn>,
+        tables: &[TableInfo],
+    ) -> Result<Vec<QueryState>, OdbcError> {
+        tables
+            .iter()
+            .enumerate()
+            .map(|(id, table)| {
+                let (idx, query) = self.get_query(table)?;
+                let fields = describe_query(conn, query)?;
+                let schema = map_schema(id as u32, query, fields)?;
+                QueryState::new(idx, query, schema)
+            })
+            .collect()
+    }
+}
+
+impl Connector for OdbcConnector {
+    fn validate(&self, tables: Option<Vec<TableInfo>>) -> Result<(), ConnectorError> {
+        self.get_schemas(tables).map(|_| ())
+    }
+
+    #[cfg(feature = "odbc")]
+    fn validate_schemas(&self, tables: &[TableInfo]) -> Result<ValidationResults, ConnectorError> {
+        let env = create_environment()?;
+        let conn = connect(&env, &self.config)?;
+        let mut results = HashMap::new();
+        for table in tables {
+            let r = self
+                .get_states(&conn, std::slice::from_ref(table))
+                .map(|_| ())
+                .map_err(ConnectorError::OdbcError);
+            results.insert(table.name.clone(), vec![(None, r)]);
+        }
+        Ok(results)
+    }
+
+    #[cfg(not(feature = "odbc"))]
+    fn validate_schemas(&self, _tables: &[TableInfo]) -> Result<ValidationResults, ConnectorError> {
+        Err(OdbcError::NotEnabled(self.name.clone()).into())
+    }
+
+    #[cfg(feature = "odbc")]
+    fn get_schemas(
+        &self,
+        table_names: Option<Vec<TableInfo>>,
+    ) -> Result<Vec<SourceSchema>, ConnectorError> {
+        let env = create_environment()?;
+        let conn = connect(&env, &self.config)?;
+        let tables = table_names.unwrap_or_else(|| self.get_tables_info());
+
+        let states = self.get_states(&conn, &tables)?;
+        Ok(states
+            .into_iter()
+            .zip(tables)
+            .map(|(state, table)| {
+                let query = &self.config.queries[state.idx as usize];
+                SourceSchema::new(table.name, state.schema, replication_type(query))
+            })
+            .collect())
+    }
+
+    #[cfg(not(feature = "odbc"))]
+    fn get_schemas(
+        &self,
+        _table_names: Option<Vec<TableInfo>>,
+    ) -> Result<Vec<SourceSchema>, ConnectorError> {
+        Err(OdbcError::NotEnabled(self.name.clone()).into())
+    }
+
+    fn can_start_from(&self, last_checkpoint: (u64, u64)) -> Result<bool, ConnectorError> {
+        // The cursors and primary keys of the queries at a checkpoint are kept in the journal.
+        match self.state_path() {
+            Some(path) => Sequencer::can_resume(path, last_checkpoint),
+            None => Ok(false),
+        }
+    }
+
+    fn commit(&self, checkpoint: (u64, u64)) -> Result<(), ConnectorError> {
+        self.sequencer.commit(checkpoint);
+        Ok(())
+    }
+
+    #[cfg(feature = "odbc")]
+    fn start(
+        &self,
+        from_seq: Option<(u64, u64)>,
+        ingestor: &Ingestor,
+        tables: Vec<TableInfo>,
+    ) -> Result<(), ConnectorError> {
+        let env = create_environment()?;
+        let conn = connect(&env, &self.config)?;
+        let mut states = self.get_states(&conn, &tables)?;
+        let poll_interval = Duration::from_millis(self.config.poll_interval_ms);
+
+        let mut queries = self.sequencer.open(self.state_path(), from_seq)?;
+        for state in states.iter_mut() {
+            if let Some(poll_state) = queries.remove(state.name()) {
+                info!(
+                    "[{}] Resuming query {} from cursor {:?}",
+                    self.name,
+                    state.name(),
+                    poll_state.cursor
+                );
+                state.resume(poll_state);
+            }
+        }
+
+        info!("[{}] Polling {} queries", self.name, states.len());
+        loop {
+            let mut read = 0;
+            for state in states.iter_mut() {
+                read += poll_query(&conn, state, &self.sequencer, ingestor)?;
+            }
+            if read == 0 {
+                thread::sleep(poll_interval);
+            }
+        }
+    }
+
+    #[cfg(not(feature = "odbc"))]
+    fn start(
+        &self,
+        _from_seq: Option<(u64, u64)>,
+        _ingestor: &Ingestor,
+        _tables: Vec<TableInfo>,
+    ) -> Result<(), ConnectorError> {
+        Err(OdbcError::NotEnabled(self.name.clone()).into())
+    }
+
+    fn get_tables(&self, tables: Option<&[TableInfo]>) -> Result<Vec<TableInfo>, ConnectorError> {
+        self.get_tables_default(tables)
+    }
+}
+
+#[cfg(feature = "odbc")]
+fn create_environment() -> Result<Environment<Version3>, OdbcError> {
+    create_environment_v3().map_err(|e| match e {
+        Some(e) => OdbcError::ConnectionError(Box::new(e)),
+        None => OdbcError::EnvironmentError,
+    })
+}
+
+#[cfg(feature = "odbc")]
+fn connect<'env>(
+    env: &'env Environment<Version3>,
+    config: &OdbcConfig,
+) -> Result<Connection<'env, AutocommitOn>, OdbcError> {
+    env.connect_with_connection_string(&config.connection_string)
+        .map_err(|e| OdbcError::ConnectionError(Box::new(e)))
+}
+
+/// Reads the columns of the result of a query, without reading any row.
+#[cfg(feature = "odbc")]
+fn describe_query(
+    conn: &Connection<AutocommitOn>,
+    query: &OdbcQuery,
+) -> Result<Vec<FieldDefinition>, OdbcError> {
+    let stmt = Statement::with_parent(conn).map_err(|e| OdbcError::QueryError(Box::new(e)))?;
+    let sql = format!("SELECT * FROM ({}) dozer_query WHERE 1 = 0", query.query);
+    match stmt
+        .exec_direct(&sql)
+        .map_err(|e| OdbcError::QueryError(Box::new(e)))?
+    {
+        Data(stmt) => {
+            let cols = stmt
+                .num_result_cols()
+                .map_err(|e| OdbcError::QueryError(Box::new(e)))?;
+            (1..(cols + 1))
+                .map(|i| {
+                    let column = stmt
+                        .describe_col(i as u16)
+                        .map_err(|e| OdbcError::QueryError(Box::new(e)))?;
+                    Ok(FieldDefinition::new(
+                        column.name.clone(),
+                        map_column_type(&column)?,
+                        column.nullable.unwrap_or(true),
+                        SourceDefinition::Dynamic,
+                    ))
+                })
+                .collect()
+        }
+        NoData(_) => Ok(vec![]),
+    }
+}
+
+/// Ingests the rows read since the last poll, returning their count.
+#[cfg(feature = "odbc")]
+fn poll_query(
+    conn: &Connection<AutocommitOn>,
+    state: &mut QueryState,
+    sequencer: &Sequencer,
+    ingestor: &Ingestor,
+) -> Result<usize, ConnectorError> {
+    let sql = state.start_poll()?;
+    let stmt = Statement::with_parent(conn).map_err(|e| OdbcError::QueryError(Box::new(e)))?;
+    let stmt = match stmt
+        .exec_direct(&sql)
+        .map_err(|e| OdbcError::QueryError(Box::new(e)))?
+    {
+        Data(stmt) => stmt,
+        NoData(_) => return Ok(0),
+    };
+
+    let cols = stmt
+        .num_result_cols()
+        .map_err(|e| OdbcError::QueryError(Box::new(e)))?;
+    let schema = (1..(cols + 1))
+        .map(|i| stmt.describe_col(i as u16))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| OdbcError::QueryError(Box::new(e)))?;
+
+    let mut rows = ResultIterator::new(stmt, cols, schema);
+    let mut read = 0;
+    let mut transaction = vec![];
+    while let Some(values) = rows.try_next()? {
+        if let Some(row) = state.read_row(values)? {
+            transaction.push(row);
+            if transaction.len() == TRANSACTION_SIZE {
+                read += transaction.len();
+                sequencer.ingest(state.name(), std::mem::take(&mut transaction), ingestor)?;
+            }
+        }
+    }
+    read += transaction.len();
+    sequencer.ingest(state.name(), transaction, ingestor)?;
+    Ok(read)
+}
@@ -0,0 +1,49 @@
+use dozer_types::chrono::{Datelike, NaiveDate, NaiveDateTime};
+use dozer_types::rust_decimal::prelude::ToPrimitive;
+use dozer_types::types::{Field, FieldType};
+
+pub fn is_cursor_type(typ: FieldType) -> bool {
+    matches!(
+        typ,
+        FieldType::Int
+            | FieldType::UInt
+            | FieldType::Decimal
+            | FieldType::Timestamp
+            | FieldType::Date
+    )
+}
+
+/// Maps a cursor value to an integer with the same order. Timestamps are counted in
+/// microseconds and dates in days, decimals must not have a fractional part.
+pub fn cursor_value(value: &Field) -> Option<i64> {
+    match value {
+        Field::Int(v) => Some(*v),
+        Field::UInt(v) => i64::try_from(*v).ok(),
+        Field::Decimal(v) if v.fract().is_zero() => v.to_i64(),
+        Field::Timestamp(v) => Some(v.timestamp_micros()),
+        Field::Date(v) => Some(v.num_days_from_ce() as i64),
+        _ => None,
+    }
+}
+
+/// Writes a cursor value as a literal of the cursor column type. Timestamps and dates are
+/// written as ODBC escape sequences, which drivers translate to their own syntax.
+pub fn cursor_literal(value: i64, typ: FieldType) -> Option<String> {
+    match typ {
+        FieldType::Timestamp => {
+            let timestamp = NaiveDateTime::from_timestamp_opt(
+                value.div_euclid(1_000_000),
+                (value.rem_euclid(1_000_000) * 1_000) as u32,
+            )?;
+            Some(format!(
+                "{{ts '{}'}}",
+                timestamp.format("%Y-%m-%d %H:%M:%S%.6f")
+            ))
+        }
+        FieldType::Date => {
+            let date = NaiveDate::from_num_days_from_ce_opt(i32::try_from(value).ok()?)?;
+            Some(format!("{{d '{}'}}", date.format("%Y-%m-%d")))
+        }
+        _ => Some(value.to_string()),
+    }
+}
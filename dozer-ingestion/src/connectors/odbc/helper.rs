@@ -0,0 +1,238 @@
+use crate::errors::OdbcError;
+use crate::errors::OdbcError::{DecimalConvertError, InvalidDateError, InvalidTimeError};
+use dozer_types::chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+use dozer_types::rust_decimal::Decimal;
+use dozer_types::types::{Field, FieldType};
+use odbc::ffi::{SqlDataType, SQL_DATE_STRUCT, SQL_TIMESTAMP_STRUCT};
+use odbc::odbc_safe::AutocommitOn;
+use odbc::{ColumnDescriptor, Cursor, Executed, HasResult, Statement};
+
+fn convert_decimal(bytes: &[u8], scale: u16) -> Result<Field, OdbcError> {
+    let is_negative = bytes[bytes.len() - 4] == 255;
+    let mut multiplier: i64 = 1;
+    let mut result: i64 = 0;
+    let bytes: &[u8] = &bytes[4..11];
+    bytes.iter().for_each(|w| {
+        let number = *w as i64;
+        result += number * multiplier;
+        multiplier *= 256;
+    });
+
+    if is_negative {
+        result = -result;
+    }
+
+    Ok(Field::from(
+        Decimal::try_new(result, scale as u32).map_err(DecimalConvertError)?,
+    ))
+}
+
+/// Maps a result column to the type of the field `convert_data` reads from it.
+pub fn map_column_type(column_descriptor: &ColumnDescriptor) -> Result<FieldType, OdbcError> {
+    match column_descriptor.data_type {
+        SqlDataType::SQL_CHAR | SqlDataType::SQL_VARCHAR => Ok(FieldType::String),
+        SqlDataType::SQL_DECIMAL
+        | SqlDataType::SQL_NUMERIC
+        | SqlDataType::SQL_INTEGER
+        | SqlDataType::SQL_SMALLINT => match column_descriptor.decimal_digits {
+            None => Ok(FieldType::Int),
+            Some(_) => Ok(FieldType::Decimal),
+        },
+        SqlDataType::SQL_EXT_BIGINT | SqlDataType::SQL_EXT_TINYINT => Ok(FieldType::Int),
+        SqlDataType::SQL_FLOAT | SqlDataType::SQL_REAL | SqlDataType::SQL_DOUBLE => {
+            Ok(FieldType::Float)
+        }
+        SqlDataType::SQL_TIMESTAMP => Ok(FieldType::Timestamp),
+        SqlDataType::SQL_DATE => Ok(FieldType::Date),
+        SqlDataType::SQL_EXT_BIT => Ok(FieldType::Boolean),
+        _ => Err(OdbcError::ColumnTypeNotSupported(format!(
+            "{:?}",
+            &column_descriptor.data_type
+        ))),
+    }
+}
+
+pub fn convert_data(
+    cursor: &mut Cursor<Executed, AutocommitOn>,
+    i: u16,
+    column_descriptor: &ColumnDescriptor,
+) -> Result<Field, OdbcError> {
+    match column_descriptor.data_type {
+        SqlDataType::SQL_CHAR | SqlDataType::SQL_VARCHAR => {
+            match cursor
+                .get_data::<String>(i)
+                .map_err(|e| OdbcError::ValueConversionError(Box::new(e)))?
+            {
+                None => Ok(Field::Null),
+                Some(value) => Ok(Field::from(value)),
+            }
+        }
+        SqlDataType::SQL_DECIMAL
+        | SqlDataType::SQL_NUMERIC
+        | SqlDataType::SQL_INTEGER
+        | SqlDataType::SQL_SMALLINT => match column_descriptor.decimal_digits {
+            None => {
+                match cursor
+                    .get_data::<i64>(i)
+                    .map_err(|e| OdbcError::ValueConversionError(Box::new(e)))?
+                {
+                    None => Ok(Field::Null),
+                    Some(value) => Ok(Field::from(value)),
+                }
+            }
+            Some(digits) => {
+                match cursor
+                    .get_data::<&[u8]>(i)
+                    .map_err(|e| OdbcError::ValueConversionError(Box::new(e)))?
+                {
+                    None => Ok(Field::Null),
+                    Some(value) => convert_decimal(value, digits),
+                }
+            }
+        },
+        SqlDataType::SQL_EXT_BIGINT | SqlDataType::SQL_EXT_TINYINT => {
+            match cursor
+                .get_data::<i64>(i)
+                .map_err(|e| OdbcError::ValueConversionError(Box::new(e)))?
+            {
+                None => Ok(Field::Null),
+                Some(value) => Ok(Field::from(value)),
+            }
+        }
+        SqlDataType::SQL_FLOAT | SqlDataType::SQL_REAL | SqlDataType::SQL_DOUBLE => {
+            match cursor
+                .get_data::<f64>(i)
+                .map_err(|e| OdbcError::ValueConversionError(Box::new(e)))?
+            {
+                None => Ok(Field::Null),
+                Some(value) => Ok(Field::from(value)),
+            }
+        }
+        SqlDataType::SQL_TIMESTAMP => {
+            match cursor
+                .get_data::<SQL_TIMESTAMP_STRUCT>(i)
+                .map_err(|e| OdbcError::ValueConversionError(Box::new(e)))?
+            {
+                None => Ok(Field::Null),
+                Some(value) => {
+                    let date = NaiveDate::from_ymd_opt(
+                        value.year as i32,
+                        value.month as u32,
+                        value.day as u32,
+                    )
+                    .map_or_else(|| Err(InvalidDateError), Ok)?;
+                    let time = NaiveTime::from_hms_nano_opt(
+                        value.hour as u32,
+                        value.minute as u32,
+                        value.second as u32,
+                        value.fraction,
+                    )
+                    .map_or_else(|| Err(InvalidTimeError), Ok)?;
+                    Ok(Field::from(NaiveDateTime::new(date, time)))
+                }
+            }
+        }
+        SqlDataType::SQL_DATE => {
+            match cursor
+                .get_data::<SQL_DATE_STRUCT>(i)
+                .map_err(|e| OdbcError::ValueConversionError(Box::new(e)))?
+            {
+                None => Ok(Field::Null),
+                Some(value) => {
+                    let date = NaiveDate::from_ymd_opt(
+                        value.year as i32,
+                        value.month as u32,
+                        value.day as u32,
+                    )
+                    .map_or_else(|| Err(InvalidDateError), Ok)?;
+                    Ok(Field::from(date))
+                }
+            }
+        }
+        SqlDataType::SQL_EXT_BIT => {
+            match cursor
+                .get_data::<bool>(i)
+                .map_err(|e| OdbcError::ValueConversionError(Box::new(e)))?
+            {
+                None => Ok(Field::Null),
+                Some(v) => Ok(Field::from(v)),
+            }
+        }
+        _ => Err(OdbcError::ColumnTypeNotSupported(format!(
+            "{:?}",
+            &column_descriptor.data_type
+        ))),
+    }
+}
+
+pub struct ResultIterator<'a, 'b> {
+    stmt: Option<Statement<'a, 'b, Executed, HasResult, AutocommitOn>>,
+    cols: i16,
+    schema: Vec<ColumnDescriptor>,
+}
+
+impl<'a, 'b> ResultIterator<'a, 'b> {
+    pub fn new(
+        stmt: Statement<'a, 'b, Executed, HasResult, AutocommitOn>,
+        cols: i16,
+        schema: Vec<ColumnDescriptor>,
+    ) -> Self {
+        Self {
+            stmt: Some(stmt),
+            cols,
+            schema,
+        }
+    }
+
+    pub fn close_cursor(&mut self) -> Result<(), OdbcError> {
+        self.stmt
+            .take()
+            .unwrap()
+            .close_cursor()
+            .map_or_else(|e| Err(OdbcError::QueryError(Box::new(e))), |_| Ok(()))
+    }
+
+    /// Reads the next row, returning conversion errors instead of panicking on them.
+    pub fn try_next(&mut self) -> Result<Option<Vec<Field>>, OdbcError> {
+        let Some(stmt) = self.stmt.as_mut() else {
+            return Ok(None);
+        };
+        match stmt
+            .fetch()
+            .map_err(|e| OdbcError::QueryError(Box::new(e)))?
+        {
+            None => Ok(None),
+            Some(mut cursor) => {
+                let mut values = vec![];
+                for (i, descriptor) in self.schema.iter().enumerate() {
+                    values.push(convert_data(&mut cursor, i as u16 + 1, descriptor)?);
+                }
+                Ok(Some(values))
+            }
+        }
+    }
+}
+
+impl Iterator for ResultIterator<'_, '_> {
+    type Item = Vec<Field>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        return if let Some(ref mut stmt) = self.stmt {
+            match stmt.fetch().unwrap() {
+                None => None,
+                Some(mut cursor) => {
+                    let mut values = vec![];
+                    for i in 1..(self.cols + 1) {
+                        let descriptor = self.schema.get((i - 1) as usize)?;
+                        let value = convert_data(&mut cursor, i as u16, descriptor).unwrap();
+                        values.push(value);
+                    }
+
+                    Some(values)
+                }
+            }
+        } else {
+            None
+        };
+    }
+}
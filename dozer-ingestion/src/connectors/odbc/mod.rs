@@ -0,0 +1,12 @@
+pub mod connector;
+#[cfg_attr(not(feature = "odbc"), allow(dead_code))]
+mod cursor;
+#[cfg(feature = "odbc")]
+pub mod helper;
+#[cfg_attr(not(feature = "odbc"), allow(dead_code))]
+pub mod query;
+#[cfg_attr(not(feature = "odbc"), allow(dead_code))]
+mod sequencer;
+
+#[cfg(test)]
+mod tests;
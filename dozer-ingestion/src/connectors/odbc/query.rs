@@ -0,0 +1,271 @@
+use std::collections::HashMap;
+
+use dozer_types::ingestion_types::OdbcQuery;
+use dozer_types::serde::{Deserialize, Serialize};
+use dozer_types::types::{
+    Field, FieldDefinition, FieldType, Operation, Record, ReplicationChangesTrackingType, Schema,
+    SchemaIdentifier,
+};
+
+use crate::connectors::odbc::cursor::{cursor_literal, cursor_value, is_cursor_type};
+use crate::errors::OdbcError;
+
+/// Builds the schema of a query from the columns of its result.
+pub fn map_schema(
+    id: u32,
+    query: &OdbcQuery,
+    fields: Vec<FieldDefinition>,
+) -> Result<Schema, OdbcError> {
+    let primary_index = query
+        .primary_key
+        .iter()
+        .map(|column| find_column(&fields, column, query))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(Schema {
+        identifier: Some(SchemaIdentifier { id, version: 1 }),
+        fields,
+        primary_index,
+    })
+}
+
+pub fn replication_type(query: &OdbcQuery) -> ReplicationChangesTrackingType {
+    if query.primary_key.is_empty() {
+        ReplicationChangesTrackingType::Nothing
+    } else {
+        ReplicationChangesTrackingType::FullChanges
+    }
+}
+
+/// Column names are matched ignoring case, as some databases change it.
+fn find_column(
+    fields: &[FieldDefinition],
+    column: &str,
+    query: &OdbcQuery,
+) -> Result<usize, OdbcError> {
+    fields
+        .iter()
+        .position(|f| f.name.eq_ignore_ascii_case(column))
+        .ok_or_else(|| OdbcError::ColumnNotFound(column.to_string(), query.name.clone()))
+}
+
+fn is_deleted(value: &Field) -> bool {
+    match value {
+        Field::Boolean(v) => *v,
+        Field::Int(v) => *v != 0,
+        Field::UInt(v) => *v != 0,
+        Field::Decimal(v) => !v.is_zero(),
+        Field::String(v) => matches!(v.to_lowercase().as_str(), "true" | "t" | "yes" | "y" | "1"),
+        _ => false,
+    }
+}
+
+/// Change of the key of an ingested row.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(crate = "dozer_types::serde")]
+pub enum KeyChange {
+    /// The query has no primary key
+    Unkeyed,
+    /// The row was inserted or updated, holding its primary key
+    Upserted(Record),
+    Deleted,
+}
+
+/// Change of the state of a query made by an ingested row.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(crate = "dozer_types::serde")]
+pub struct RowChange {
+    pub cursor: i64,
+    /// Primary key of the row, or all its values without primary key
+    pub key: Vec<u8>,
+    pub key_change: KeyChange,
+}
+
+/// State of a query after its last ingested row, from which it is polled again.
+///
+/// Rows read again with the last cursor value are identified by their key, so that they are
+/// skipped whatever their order. Only the primary key of the ingested rows is kept, to tell
+/// inserts from updates.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(crate = "dozer_types::serde")]
+pub struct PollState {
+    /// Cursor value of the last ingested row
+    pub cursor: Option<i64>,
+    // Rows ingested with this cursor value, by key
+    read_at_cursor: HashMap<Vec<u8>, u32>,
+    // Primary key of the ingested rows which were not deleted, by key
+    keys: HashMap<Vec<u8>, Record>,
+}
+
+impl PollState {
+    pub fn apply(&mut self, change: RowChange) {
+        if self.cursor != Some(change.cursor) {
+            self.cursor = Some(change.cursor);
+            self.read_at_cursor.clear();
+        }
+        *self.read_at_cursor.entry(change.key.clone()).or_default() += 1;
+        match change.key_change {
+            KeyChange::Unkeyed => {}
+            KeyChange::Upserted(record) => {
+                self.keys.insert(change.key, record);
+            }
+            KeyChange::Deleted => {
+                self.keys.remove(&change.key);
+            }
+        }
+    }
+}
+
+/// A query being polled.
+#[derive(Debug)]
+pub struct QueryState {
+    pub idx: u32,
+    pub schema: Schema,
+    query: OdbcQuery,
+    cursor_idx: usize,
+    cursor_type: FieldType,
+    soft_delete_idx: Option<usize>,
+    state: PollState,
+    // Rows of the current poll having the last cursor value, by key
+    seen: HashMap<Vec<u8>, u32>,
+}
+
+impl QueryState {
+    pub fn new(idx: u32, query: &OdbcQuery, schema: Schema) -> Result<Self, OdbcError> {
+        let cursor_idx = find_column(&schema.fields, &query.cursor_column, query)?;
+        let cursor_type = schema.fields[cursor_idx].typ;
+        if !is_cursor_type(cursor_type) {
+            return Err(OdbcError::UnsupportedCursorType(
+                query.cursor_column.clone(),
+                cursor_type,
+            ));
+        }
+
+        let soft_delete_idx = match &query.soft_delete_column {
+            Some(_) if query.primary_key.is_empty() => {
+                return Err(OdbcError::SoftDeleteWithoutPrimaryKey(query.name.clone()));
+            }
+            Some(column) => Some(find_column(&schema.fields, column, query)?),
+            None => None,
+        };
+
+        Ok(Self {
+            idx,
+            schema,
+            query: query.clone(),
+            cursor_idx,
+            cursor_type,
+            soft_delete_idx,
+            state: PollState::default(),
+            seen: HashMap::new(),
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.query.name
+    }
+
+    /// Continues from the state of the query at a checkpoint.
+    pub fn resume(&mut self, state: PollState) {
+        self.state = state;
+    }
+
+    /// Returns the statement reading the rows from the last cursor value. Rows having this value
+    /// are read again, as rows with the same value may have been added since the last poll.
+    pub fn start_poll(&mut self) -> Result<String, OdbcError> {
+        let cursor = &self.query.cursor_column;
+        let condition = match self.state.cursor {
+            None => format!("{cursor} IS NOT NULL"),
+            Some(value) => {
+                let literal = cursor_literal(value, self.cursor_type).ok_or_else(|| {
+                    OdbcError::UnsupportedCursorType(cursor.clone(), self.cursor_type)
+                })?;
+                format!("{cursor} >= {literal}")
+            }
+        };
+        let order = std::iter::once(cursor)
+            .chain(
+                self.query
+                    .primary_key
+                    .iter()
+                    .filter(|column| !column.eq_ignore_ascii_case(cursor)),
+            )
+            .cloned()
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        self.seen.clear();
+        Ok(format!(
+            "SELECT * FROM ({}) dozer_query WHERE {condition} ORDER BY {order}",
+            self.query.query
+        ))
+    }
+
+    /// Maps a row to the operation to ingest, if any, with the change it makes to the state of
+    /// the query, which is applied.
+    ///
+    /// Updates and deletes only hold the primary key of the old row.
+    pub fn read_row(
+        &mut self,
+        values: Vec<Field>,
+    ) -> Result<Option<(RowChange, Operation)>, OdbcError> {
+        let cursor = cursor_value(&values[self.cursor_idx]).ok_or_else(|| {
+            OdbcError::InvalidCursorValue(
+                self.query.cursor_column.clone(),
+                values[self.cursor_idx].clone(),
+            )
+        })?;
+        let new = Record::new(self.schema.identifier, values, None);
+        let key = if self.schema.primary_index.is_empty() {
+            new.get_key(&(0..new.values.len()).collect::<Vec<_>>())
+        } else {
+            new.get_key(&self.schema.primary_index)
+        };
+
+        // Skips the rows with the last cursor value which were ingested by a previous poll.
+        if self.state.cursor != Some(cursor) {
+            self.seen.clear();
+        }
+        let seen = self.seen.entry(key.clone()).or_default();
+        *seen += 1;
+        let read = match self.state.cursor {
+            Some(last) if last == cursor => self.state.read_at_cursor.get(&key).copied(),
+            _ => None,
+        };
+        if *seen <= read.unwrap_or(0) {
+            return Ok(None);
+        }
+
+        let (op, key_change) = if self.schema.primary_index.is_empty() {
+            (Operation::Insert { new }, KeyChange::Unkeyed)
+        } else if matches!(self.soft_delete_idx, Some(idx) if is_deleted(&new.values[idx])) {
+            match self.state.keys.get(&key) {
+                Some(old) => (Operation::Delete { old: old.clone() }, KeyChange::Deleted),
+                // Rows deleted before being read are not ingested
+                None => return Ok(None),
+            }
+        } else {
+            let mut key_record =
+                Record::nulls(self.schema.identifier, self.schema.fields.len(), None);
+            for idx in &self.schema.primary_index {
+                key_record.values[*idx] = new.values[*idx].clone();
+            }
+            let op = match self.state.keys.get(&key) {
+                Some(old) => Operation::Update {
+                    old: old.clone(),
+                    new,
+                },
+                None => Operation::Insert { new },
+            };
+            (op, KeyChange::Upserted(key_record))
+        };
+
+        let change = RowChange {
+            cursor,
+            key,
+            key_change,
+        };
+        self.state.apply(change.clone());
+        Ok(Some((change, op)))
+    }
+}
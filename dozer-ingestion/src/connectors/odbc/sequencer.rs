@@ -0,0 +1,261 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use dozer_types::ingestion_types::IngestionMessage;
+use dozer_types::parking_lot::Mutex;
+use dozer_types::serde::{Deserialize, Serialize};
+use dozer_types::types::Operation;
+
+use crate::connectors::journal::Journal;
+use crate::connectors::odbc::query::{PollState, RowChange};
+use crate::errors::{ConnectorError, OdbcError};
+use crate::ingestion::Ingestor;
+
+/// Transactions appended to the journal before it is compacted, unless its last compaction
+/// left more entries.
+const MIN_TRANSACTIONS_BEFORE_COMPACTION: usize = 1000;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(crate = "dozer_types::serde")]
+pub enum JournalEntry {
+    /// Checkpoint the journal was compacted at, followed by the state of the queries at it.
+    Checkpoint(u64, u64),
+    Query {
+        name: String,
+        state: PollState,
+    },
+    /// Rows of a query ingested as a transaction, from its operation `first_seq` on.
+    Transaction {
+        txid: u64,
+        first_seq: u64,
+        query: String,
+        changes: Vec<RowChange>,
+    },
+}
+
+/// State of the queries, by name.
+pub type Queries = HashMap<String, PollState>;
+
+/// Orders the rows of all queries, giving every set of rows a transaction id.
+///
+/// With a journal, the changes a transaction makes to the state of its query are persisted
+/// before it is ingested, so that the state of the queries at a checkpoint can be restored. The
+/// journal is compacted to the state at the committed checkpoint.
+#[derive(Debug, Default)]
+pub struct Sequencer {
+    state: Mutex<SequencerState>,
+    // Last committed checkpoint, which is only recorded so that committing never waits for a
+    // transaction to be ingested. The journal is compacted to it by the next transaction.
+    committed: Mutex<Option<(u64, u64)>>,
+}
+
+#[derive(Debug)]
+struct SequencerState {
+    next_txid: u64,
+    journal: Option<Journal<JournalEntry>>,
+    // Checkpoint the journal was last compacted at
+    compacted_at: Option<(u64, u64)>,
+    // Entries left by the last compaction
+    num_compacted: usize,
+    // Transactions appended since the last compaction
+    num_appended: usize,
+}
+
+impl Default for SequencerState {
+    fn default() -> Self {
+        Self {
+            next_txid: 1,
+            journal: None,
+            compacted_at: None,
+            num_compacted: 0,
+            num_appended: 0,
+        }
+    }
+}
+
+impl Sequencer {
+    /// Whether the journal at `path` holds the state of the queries at `checkpoint`.
+    pub fn can_resume(path: &Path, checkpoint: (u64, u64)) -> Result<bool, ConnectorError> {
+        Ok(replay(Journal::read(path)?, checkpoint).is_some())
+    }
+
+    /// Opens the journal at `path` if any, returning the state of the queries at
+    /// `last_checkpoint`. Transactions which were not committed are dropped, as their rows are
+    /// read again.
+    pub fn open(
+        &self,
+        path: Option<&Path>,
+        last_checkpoint: Option<(u64, u64)>,
+    ) -> Result<Queries, ConnectorError> {
+        let mut state = self.state.lock();
+        *state = SequencerState {
+            next_txid: last_checkpoint.map_or(1, |(txid, _)| txid + 1),
+            ..SequencerState::default()
+        };
+        *self.committed.lock() = None;
+        let Some(path) = path else {
+            return Ok(Queries::new());
+        };
+
+        let (mut journal, entries) = Journal::open(path)?;
+        let queries = match last_checkpoint {
+            Some(checkpoint) => {
+                replay(entries, checkpoint)
+                    .ok_or(OdbcError::CheckpointNotFound(checkpoint))?
+                    .0
+            }
+            None => Queries::new(),
+        };
+        let compacted = snapshot(last_checkpoint, &queries);
+        journal.compact(&compacted)?;
+        state.journal = Some(journal);
+        state.compacted_at = last_checkpoint;
+        state.num_compacted = compacted.len();
+        Ok(queries)
+    }
+
+    /// Records that the messages up to `checkpoint` were committed.
+    pub fn commit(&self, checkpoint: (u64, u64)) {
+        *self.committed.lock() = Some(checkpoint);
+    }
+
+    /// Ingests the operations of rows of a query as a transaction, which is persisted first if
+    /// there is a journal. Every row holds the change it made to the state of the query.
+    pub fn ingest(
+        &self,
+        query: &str,
+        rows: Vec<(RowChange, Operation)>,
+        ingestor: &Ingestor,
+    ) -> Result<(), ConnectorError> {
+        if rows.is_empty() {
+            return Ok(());
+        }
+
+        let committed = *self.committed.lock();
+        let mut state = self.state.lock();
+        if let Some(checkpoint) = committed {
+            state.compact(checkpoint)?;
+        }
+
+        let txid = state.next_txid;
+        state.next_txid += 1;
+        let (changes, operations): (Vec<_>, Vec<_>) = rows.into_iter().unzip();
+        if let Some(journal) = &mut state.journal {
+            journal.append(&JournalEntry::Transaction {
+                txid,
+                first_seq: 0,
+                query: query.to_string(),
+                changes,
+            })?;
+            state.num_appended += 1;
+        }
+
+        ingestor
+            .handle_batch(
+                operations
+                    .into_iter()
+                    .enumerate()
+                    .map(|(seq_in_tx, op)| IngestionMessage::new_op(txid, seq_in_tx as u64, op))
+                    .collect(),
+            )
+            .map_err(ConnectorError::IngestorError)
+    }
+}
+
+impl SequencerState {
+    /// Compacts the journal to the state at `checkpoint`, keeping the transactions following
+    /// it. Compaction waits for as many transactions as the last one left entries.
+    fn compact(&mut self, checkpoint: (u64, u64)) -> Result<(), ConnectorError> {
+        let Some(journal) = &mut self.journal else {
+            return Ok(());
+        };
+        if self.compacted_at == Some(checkpoint)
+            || self.num_appended < self.num_compacted.max(MIN_TRANSACTIONS_BEFORE_COMPACTION)
+        {
+            return Ok(());
+        }
+
+        let (queries, pending) = replay(Journal::read(journal.path())?, checkpoint)
+            .ok_or(OdbcError::CheckpointNotFound(checkpoint))?;
+        let mut compacted = snapshot(Some(checkpoint), &queries);
+        self.compacted_at = Some(checkpoint);
+        self.num_compacted = compacted.len();
+        self.num_appended = pending.len();
+        compacted.extend(pending);
+        journal.compact(&compacted)?;
+        Ok(())
+    }
+}
+
+/// Replays the journal entries up to `checkpoint`, returning the state of the queries at it and
+/// the transactions following it. Returns `None` if the journal has no message at the
+/// checkpoint, or was compacted past it.
+fn replay(
+    entries: Vec<JournalEntry>,
+    checkpoint: (u64, u64),
+) -> Option<(Queries, Vec<JournalEntry>)> {
+    let (txid, seq_in_tx) = checkpoint;
+    let mut queries = Queries::new();
+    let mut pending = vec![];
+    let mut found = false;
+    for entry in entries {
+        match entry {
+            JournalEntry::Checkpoint(compacted_txid, compacted_seq) => {
+                let compacted = (compacted_txid, compacted_seq);
+                if compacted > checkpoint {
+                    return None;
+                }
+                found |= compacted == checkpoint;
+            }
+            JournalEntry::Query { name, state } => {
+                queries.insert(name, state);
+            }
+            JournalEntry::Transaction {
+                txid: entry_txid,
+                first_seq,
+                query,
+                mut changes,
+            } => {
+                let num_committed = if entry_txid < txid {
+                    changes.len()
+                } else if entry_txid == txid {
+                    found |= first_seq <= seq_in_tx && seq_in_tx < first_seq + changes.len() as u64;
+                    ((seq_in_tx + 1).saturating_sub(first_seq) as usize).min(changes.len())
+                } else {
+                    0
+                };
+
+                if num_committed > 0 {
+                    let state = queries.entry(query.clone()).or_default();
+                    for change in changes.drain(..num_committed) {
+                        state.apply(change);
+                    }
+                }
+                if !changes.is_empty() {
+                    pending.push(JournalEntry::Transaction {
+                        txid: entry_txid,
+                        first_seq: first_seq + num_committed as u64,
+                        query,
+                        changes,
+                    });
+                }
+            }
+        }
+    }
+    found.then_some((queries, pending))
+}
+
+/// Entries holding the state of the queries at `checkpoint`.
+fn snapshot(checkpoint: Option<(u64, u64)>, queries: &Queries) -> Vec<JournalEntry> {
+    let Some((txid, seq_in_tx)) = checkpoint else {
+        return vec![];
+    };
+    let mut entries = vec![JournalEntry::Checkpoint(txid, seq_in_tx)];
+    for (name, state) in queries {
+        entries.push(JournalEntry::Query {
+            name: name.clone(),
+            state: state.clone(),
+        });
+    }
+    entries
+}
@@ -0,0 +1,299 @@
+use crate::connectors::odbc::connector::OdbcConnector;
+use crate::connectors::odbc::cursor::{cursor_literal, cursor_value};
+use crate::connectors::odbc::query::{map_schema, QueryState, RowChange};
+use crate::connectors::odbc::sequencer::Sequencer;
+use crate::connectors::Connector;
+use crate::errors::OdbcError;
+use crate::ingestion::{IngestionConfig, Ingestor};
+use dozer_types::chrono::{NaiveDate, NaiveDateTime};
+use dozer_types::ingestion_types::{OdbcConfig, OdbcQuery};
+use dozer_types::rust_decimal::Decimal;
+use dozer_types::types::{Field, FieldDefinition, FieldType, Operation, SourceDefinition};
+use tempdir::TempDir;
+
+fn query(primary_key: &[&str], soft_delete_column: Option<&str>) -> OdbcQuery {
+    OdbcQuery {
+        name: "users".to_string(),
+        query: "SELECT id, name, deleted FROM users".to_string(),
+        cursor_column: "id".to_string(),
+        primary_key: primary_key.iter().map(|c| c.to_string()).collect(),
+        soft_delete_column: soft_delete_column.map(|c| c.to_string()),
+    }
+}
+
+fn fields() -> Vec<FieldDefinition> {
+    vec![
+        FieldDefinition::new(
+            "ID".to_string(),
+            FieldType::Int,
+            false,
+            SourceDefinition::Dynamic,
+        ),
+        FieldDefinition::new(
+            "NAME".to_string(),
+            FieldType::String,
+            true,
+            SourceDefinition::Dynamic,
+        ),
+        FieldDefinition::new(
+            "DELETED".to_string(),
+            FieldType::Boolean,
+            true,
+            SourceDefinition::Dynamic,
+        ),
+    ]
+}
+
+fn query_state(query: &OdbcQuery) -> QueryState {
+    let schema = map_schema(0, query, fields()).unwrap();
+    QueryState::new(0, query, schema).unwrap()
+}
+
+fn row(id: i64, name: &str, deleted: bool) -> Vec<Field> {
+    vec![
+        Field::Int(id),
+        Field::String(name.to_string()),
+        Field::Boolean(deleted),
+    ]
+}
+
+#[test]
+fn test_it_maps_cursor_values() {
+    let timestamp =
+        NaiveDateTime::parse_from_str("2023-01-02 10:20:30.123456", "%Y-%m-%d %H:%M:%S%.f")
+            .unwrap();
+    let value = cursor_value(&Field::from(timestamp)).unwrap();
+    assert_eq!(
+        cursor_literal(value, FieldType::Timestamp).unwrap(),
+        "{ts '2023-01-02 10:20:30.123456'}"
+    );
+
+    let date = NaiveDate::from_ymd_opt(2023, 1, 2).unwrap();
+    let value = cursor_value(&Field::Date(date)).unwrap();
+    assert_eq!(
+        cursor_literal(value, FieldType::Date).unwrap(),
+        "{d '2023-01-02'}"
+    );
+
+    assert_eq!(
+        cursor_value(&Field::Decimal(Decimal::new(420, 1))),
+        Some(42)
+    );
+    assert_eq!(cursor_value(&Field::Decimal(Decimal::new(425, 1))), None);
+    assert_eq!(cursor_value(&Field::Null), None);
+}
+
+#[test]
+fn test_it_validates_queries() {
+    let schema = map_schema(0, &query(&["id"], None), fields()).unwrap();
+    assert_eq!(schema.primary_index, vec![0]);
+
+    assert!(matches!(
+        map_schema(0, &query(&["missing"], None), fields()),
+        Err(OdbcError::ColumnNotFound(_, _))
+    ));
+
+    let mut string_cursor = query(&[], None);
+    string_cursor.cursor_column = "name".to_string();
+    let schema = map_schema(0, &string_cursor, fields()).unwrap();
+    assert!(matches!(
+        QueryState::new(0, &string_cursor, schema),
+        Err(OdbcError::UnsupportedCursorType(_, FieldType::String))
+    ));
+
+    let soft_delete = query(&[], Some("deleted"));
+    let schema = map_schema(0, &soft_delete, fields()).unwrap();
+    assert!(matches!(
+        QueryState::new(0, &soft_delete, schema),
+        Err(OdbcError::SoftDeleteWithoutPrimaryKey(_))
+    ));
+}
+
+#[test]
+fn test_it_polls_from_last_cursor() {
+    let mut state = query_state(&query(&["id"], None));
+    assert_eq!(
+        state.start_poll().unwrap(),
+        "SELECT * FROM (SELECT id, name, deleted FROM users) dozer_query \
+         WHERE id IS NOT NULL ORDER BY id"
+    );
+
+    state.read_row(row(1, "alice", false)).unwrap();
+    assert_eq!(
+        state.start_poll().unwrap(),
+        "SELECT * FROM (SELECT id, name, deleted FROM users) dozer_query \
+         WHERE id >= 1 ORDER BY id"
+    );
+}
+
+#[test]
+fn test_it_skips_rows_read_by_previous_poll() {
+    let mut state = query_state(&query(&[], None));
+    state.start_poll().unwrap();
+    assert!(state.read_row(row(1, "alice", false)).unwrap().is_some());
+    assert!(state.read_row(row(1, "bob", false)).unwrap().is_some());
+
+    // Rows having the last cursor value are read again, in any order
+    state.start_poll().unwrap();
+    assert!(state.read_row(row(1, "carol", false)).unwrap().is_some());
+    assert!(state.read_row(row(1, "bob", false)).unwrap().is_none());
+    assert!(state.read_row(row(1, "alice", false)).unwrap().is_none());
+    assert!(state.read_row(row(2, "dave", false)).unwrap().is_some());
+
+    // Identical rows are told apart by their count
+    state.start_poll().unwrap();
+    assert!(state.read_row(row(2, "dave", false)).unwrap().is_none());
+    assert!(state.read_row(row(2, "dave", false)).unwrap().is_some());
+}
+
+#[test]
+fn test_it_maps_updates_and_soft_deletes() {
+    let mut state = query_state(&query(&["name"], Some("deleted")));
+    state.start_poll().unwrap();
+
+    let (_, op) = state.read_row(row(1, "alice", false)).unwrap().unwrap();
+    assert!(matches!(op, Operation::Insert { .. }));
+
+    // Old rows only hold their primary key
+    let (_, op) = state.read_row(row(2, "alice", false)).unwrap().unwrap();
+    let Operation::Update { old, new } = op else {
+        panic!("expected an update");
+    };
+    assert_eq!(
+        old.values,
+        vec![Field::Null, Field::String("alice".to_string()), Field::Null]
+    );
+    assert_eq!(new.values[0], Field::Int(2));
+
+    let (_, op) = state.read_row(row(3, "alice", true)).unwrap().unwrap();
+    let Operation::Delete { old } = op else {
+        panic!("expected a delete");
+    };
+    assert_eq!(old.values[1], Field::String("alice".to_string()));
+
+    // Rows deleted before being read are not ingested
+    assert!(state.read_row(row(4, "bob", true)).unwrap().is_none());
+}
+
+fn read(state: &mut QueryState, rows: Vec<Vec<Field>>) -> Vec<(RowChange, Operation)> {
+    state.start_poll().unwrap();
+    rows.into_iter()
+        .filter_map(|row| state.read_row(row).unwrap())
+        .collect()
+}
+
+#[test]
+fn test_it_resumes_queries_from_checkpoint() {
+    let dir = TempDir::new("odbc").unwrap();
+    let path = dir.path().join("state");
+    let (ingestor, _iterator) = Ingestor::initialize_channel(IngestionConfig::default());
+
+    let users = query(&["name"], None);
+    let mut orders = query(&[], None);
+    orders.name = "orders".to_string();
+
+    let sequencer = Sequencer::default();
+    assert!(sequencer.open(Some(&path), None).unwrap().is_empty());
+    let mut users_state = query_state(&users);
+    let mut orders_state = query_state(&orders);
+    let rows = read(
+        &mut users_state,
+        vec![row(1, "alice", false), row(1, "bob", false)],
+    );
+    sequencer.ingest("users", rows, &ingestor).unwrap();
+    let rows = read(&mut orders_state, vec![row(5, "first", false)]);
+    sequencer.ingest("orders", rows, &ingestor).unwrap();
+    let rows = read(
+        &mut users_state,
+        vec![row(2, "alice", false), row(3, "carol", false)],
+    );
+    sequencer.ingest("users", rows, &ingestor).unwrap();
+
+    // The pipeline committed the first row of the third transaction.
+    assert!(Sequencer::can_resume(&path, (3, 0)).unwrap());
+    assert!(!Sequencer::can_resume(&path, (4, 0)).unwrap());
+    let sequencer = Sequencer::default();
+    let mut queries = sequencer.open(Some(&path), Some((3, 0))).unwrap();
+
+    let mut state = query_state(&users);
+    state.resume(queries.remove("users").unwrap());
+    let rows = read(
+        &mut state,
+        vec![row(2, "alice", false), row(3, "carol", false)],
+    );
+    assert_eq!(rows.len(), 1);
+    assert!(matches!(rows[0].1, Operation::Insert { .. }));
+
+    let mut state = query_state(&orders);
+    state.resume(queries.remove("orders").unwrap());
+    assert!(read(&mut state, vec![row(5, "first", false)]).is_empty());
+}
+
+#[test]
+fn test_it_compacts_on_commit() {
+    let dir = TempDir::new("odbc").unwrap();
+    let path = dir.path().join("state");
+    let (ingestor, _iterator) = Ingestor::initialize_channel(IngestionConfig::default());
+
+    let users = query(&["name"], None);
+    let mut state = query_state(&users);
+    let sequencer = Sequencer::default();
+    sequencer.open(Some(&path), None).unwrap();
+    for id in 0..1001 {
+        let rows = read(&mut state, vec![row(id, &id.to_string(), false)]);
+        sequencer.ingest("users", rows, &ingestor).unwrap();
+    }
+    sequencer.commit((1000, 0));
+    assert!(Sequencer::can_resume(&path, (1, 0)).unwrap());
+
+    // The next transaction compacts the journal to the state at the checkpoint.
+    let rows = read(&mut state, vec![row(1001, "0", false)]);
+    assert!(matches!(rows[0].1, Operation::Update { .. }));
+    sequencer.ingest("users", rows, &ingestor).unwrap();
+    assert!(!Sequencer::can_resume(&path, (1, 0)).unwrap());
+    assert!(Sequencer::can_resume(&path, (1000, 0)).unwrap());
+    assert!(Sequencer::can_resume(&path, (1002, 0)).unwrap());
+
+    let queries = Sequencer::default()
+        .open(Some(&path), Some((1000, 0)))
+        .unwrap();
+    let mut state = query_state(&users);
+    state.resume(queries["users"].clone());
+    let rows = read(
+        &mut state,
+        vec![row(999, "999", false), row(1000, "0", false)],
+    );
+    assert_eq!(rows.len(), 1);
+    assert!(matches!(rows[0].1, Operation::Update { .. }));
+}
+
+#[test]
+fn test_it_resumes_with_state_path() {
+    let config = |state_path: Option<String>| OdbcConfig {
+        connection_string: "DSN=test".to_string(),
+        queries: vec![query(&["id"], None)],
+        poll_interval_ms: 10,
+        state_path,
+    };
+
+    let connector = OdbcConnector::new(1, "odbc".to_string(), config(None));
+    assert!(!connector.can_start_from((1, 0)).unwrap());
+
+    let dir = TempDir::new("odbc").unwrap();
+    let path = dir.path().join("state");
+    let connector = OdbcConnector::new(
+        1,
+        "odbc".to_string(),
+        config(Some(path.to_str().unwrap().to_string())),
+    );
+    assert!(!connector.can_start_from((1, 0)).unwrap());
+
+    let (ingestor, _iterator) = Ingestor::initialize_channel(IngestionConfig::default());
+    let sequencer = Sequencer::default();
+    sequencer.open(Some(&path), None).unwrap();
+    let mut state = query_state(&query(&["id"], None));
+    let rows = read(&mut state, vec![row(1, "alice", false)]);
+    sequencer.ingest("users", rows, &ingestor).unwrap();
+    assert!(connector.can_start_from((1, 0)).unwrap());
+    assert!(!connector.can_start_from((2, 0)).unwrap());
+}
@@ -1,192 +1,23 @@
 use dozer_types::ingestion_types::SnowflakeConfig;
 use dozer_types::log::debug;
 
-use crate::errors::{ConnectorError, SnowflakeError, SnowflakeSchemaError};
+use crate::errors::{ConnectorError, SnowflakeError};
 
+use crate::connectors::odbc::helper::ResultIterator;
 use crate::connectors::snowflake::schema_helper::SchemaHelper;
 use crate::connectors::TableInfo;
 use crate::errors::SnowflakeError::{QueryError, SnowflakeStreamError};
 use crate::errors::SnowflakeSchemaError::SchemaConversionError;
-use crate::errors::SnowflakeSchemaError::{
-    DecimalConvertError, InvalidDateError, InvalidTimeError,
-};
 use crate::errors::SnowflakeStreamError::TimeTravelNotAvailableError;
-use dozer_types::chrono::{NaiveDate, NaiveDateTime, NaiveTime};
-use dozer_types::rust_decimal::Decimal;
 use dozer_types::types::*;
-use odbc::ffi::{SqlDataType, SQL_DATE_STRUCT, SQL_TIMESTAMP_STRUCT};
 use odbc::odbc_safe::AutocommitOn;
 use odbc::{
-    ColumnDescriptor, Connection, Cursor, Data, DiagnosticRecord, Executed, HasResult, NoData,
-    ResultSetState, Statement,
+    ColumnDescriptor, Connection, Data, DiagnosticRecord, Executed, NoData, ResultSetState,
+    Statement,
 };
 use std::collections::HashMap;
 use std::fmt::Write;
 
-fn convert_decimal(bytes: &[u8], scale: u16) -> Result<Field, SnowflakeSchemaError> {
-    let is_negative = bytes[bytes.len() - 4] == 255;
-    let mut multiplier: i64 = 1;
-    let mut result: i64 = 0;
-    let bytes: &[u8] = &bytes[4..11];
-    bytes.iter().for_each(|w| {
-        let number = *w as i64;
-        result += number * multiplier;
-        multiplier *= 256;
-    });
-
-    if is_negative {
-        result = -result;
-    }
-
-    Ok(Field::from(
-        Decimal::try_new(result, scale as u32).map_err(DecimalConvertError)?,
-    ))
-}
-
-pub fn convert_data(
-    cursor: &mut Cursor<Executed, AutocommitOn>,
-    i: u16,
-    column_descriptor: &ColumnDescriptor,
-) -> Result<Field, SnowflakeSchemaError> {
-    match column_descriptor.data_type {
-        SqlDataType::SQL_CHAR | SqlDataType::SQL_VARCHAR => {
-            match cursor
-                .get_data::<String>(i)
-                .map_err(|e| SnowflakeSchemaError::ValueConversionError(Box::new(e)))?
-            {
-                None => Ok(Field::Null),
-                Some(value) => Ok(Field::from(value)),
-            }
-        }
-        SqlDataType::SQL_DECIMAL
-        | SqlDataType::SQL_NUMERIC
-        | SqlDataType::SQL_INTEGER
-        | SqlDataType::SQL_SMALLINT => match column_descriptor.decimal_digits {
-            None => {
-                match cursor
-                    .get_data::<i64>(i)
-                    .map_err(|e| SnowflakeSchemaError::ValueConversionError(Box::new(e)))?
-                {
-                    None => Ok(Field::Null),
-                    Some(value) => Ok(Field::from(value)),
-                }
-            }
-            Some(digits) => {
-                match cursor
-                    .get_data::<&[u8]>(i)
-                    .map_err(|e| SnowflakeSchemaError::ValueConversionError(Box::new(e)))?
-                {
-                    None => Ok(Field::Null),
-                    Some(value) => convert_decimal(value, digits),
-                }
-            }
-        },
-        SqlDataType::SQL_FLOAT | SqlDataType::SQL_REAL | SqlDataType::SQL_DOUBLE => {
-            match cursor
-                .get_data::<f64>(i)
-                .map_err(|e| SnowflakeSchemaError::ValueConversionError(Box::new(e)))?
-            {
-                None => Ok(Field::Null),
-                Some(value) => Ok(Field::from(value)),
-            }
-        }
-        SqlDataType::SQL_TIMESTAMP => {
-            match cursor
-                .get_data::<SQL_TIMESTAMP_STRUCT>(i)
-                .map_err(|e| SnowflakeSchemaError::ValueConversionError(Box::new(e)))?
-            {
-                None => Ok(Field::Null),
-                Some(value) => {
-                    let date = NaiveDate::from_ymd_opt(
-                        value.year as i32,
-                        value.month as u32,
-                        value.day as u32,
-                    )
-                    .map_or_else(|| Err(InvalidDateError), Ok)?;
-                    let time = NaiveTime::from_hms_nano_opt(
-                        value.hour as u32,
-                        value.minute as u32,
-                        value.second as u32,
-                        value.fraction,
-                    )
-                    .map_or_else(|| Err(InvalidTimeError), Ok)?;
-                    Ok(Field::from(NaiveDateTime::new(date, time)))
-                }
-            }
-        }
-        SqlDataType::SQL_DATE => {
-            match cursor
-                .get_data::<SQL_DATE_STRUCT>(i)
-                .map_err(|e| SnowflakeSchemaError::ValueConversionError(Box::new(e)))?
-            {
-                None => Ok(Field::Null),
-                Some(value) => {
-                    let date = NaiveDate::from_ymd_opt(
-                        value.year as i32,
-                        value.month as u32,
-                        value.day as u32,
-                    )
-                    .map_or_else(|| Err(InvalidDateError), Ok)?;
-                    Ok(Field::from(date))
-                }
-            }
-        }
-        SqlDataType::SQL_EXT_BIT => {
-            match cursor
-                .get_data::<bool>(i)
-                .map_err(|e| SnowflakeSchemaError::ValueConversionError(Box::new(e)))?
-            {
-                None => Ok(Field::Null),
-                Some(v) => Ok(Field::from(v)),
-            }
-        }
-        _ => Err(SnowflakeSchemaError::ColumnTypeNotSupported(format!(
-            "{:?}",
-            &column_descriptor.data_type
-        ))),
-    }
-}
-
-pub struct ResultIterator<'a, 'b> {
-    stmt: Option<Statement<'a, 'b, Executed, HasResult, AutocommitOn>>,
-    cols: i16,
-    schema: Vec<ColumnDescriptor>,
-}
-
-impl<'a, 'b> ResultIterator<'a, 'b> {
-    pub fn close_cursor(&mut self) -> Result<(), SnowflakeError> {
-        self.stmt
-            .take()
-            .unwrap()
-            .close_cursor()
-            .map_or_else(|e| Err(QueryError(Box::new(e))), |_| Ok(()))
-    }
-}
-
-impl Iterator for ResultIterator<'_, '_> {
-    type Item = Vec<Field>;
-
-    fn next(&mut self) -> Option<Self::Item> {
-        return if let Some(ref mut stmt) = self.stmt {
-            match stmt.fetch().unwrap() {
-                None => None,
-                Some(mut cursor) => {
-                    let mut values = vec![];
-                    for i in 1..(self.cols + 1) {
-                        let descriptor = self.schema.get((i - 1) as usize)?;
-                        let value = convert_data(&mut cursor, i as u16, descriptor).unwrap();
-                        values.push(value);
-                    }
-
-                    Some(values)
-                }
-            }
-        } else {
-            None
-        };
-    }
-}
-
 pub struct Client {
     conn_string: String,
 }
@@ -306,8 +137,10 @@ impl Client {
         conn: &Connection<AutocommitOn>,
         table_name: &String,
     ) -> Result<bool, SnowflakeError> {
-        let query =
-            format!("SELECT * FROM INFORMATION_SCHEMA.TABLES WHERE TABLE_NAME = '{table_name}';");
+        // Unquoted identifiers are stored in upper case
+        let query = format!(
+            "SELECT * FROM INFORMATION_SCHEMA.TABLES WHERE UPPER(TABLE_NAME) = UPPER('{table_name}');"
+        );
 
         let stmt = Statement::with_parent(conn).map_err(|e| QueryError(Box::new(e)))?;
         stmt.exec_direct(&query)
@@ -363,11 +196,7 @@ impl Client {
                 let schema = schema_result?;
                 Ok(Some((
                     schema.clone(),
-                    ResultIterator {
-                        cols,
-                        stmt: Some(stmt),
-                        schema,
-                    },
+                    ResultIterator::new(stmt, cols, schema),
                 )))
             }
             NoData(_) => Ok(None),
@@ -440,11 +269,7 @@ impl Client {
                 let schema = schema_result?;
 
                 let mut schemas: HashMap<String, Schema> = HashMap::new();
-                let iterator = ResultIterator {
-                    cols,
-                    stmt: Some(data),
-                    schema,
-                };
+                let iterator = ResultIterator::new(data, cols, schema);
 
                 for row_data in iterator {
                     let empty = "".to_string();
@@ -554,11 +379,7 @@ impl Client {
                 let schema = schema_result?;
 
                 let mut keys: HashMap<String, Vec<String>> = HashMap::new();
-                let iterator = ResultIterator {
-                    cols,
-                    stmt: Some(data),
-                    schema,
-                };
+                let iterator = ResultIterator::new(data, cols, schema);
 
                 for row_data in iterator {
                     let empty = "".to_string();
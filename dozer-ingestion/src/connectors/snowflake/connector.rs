@@ -6,8 +6,16 @@ use crate::connectors::snowflake::connection::client::Client;
 use crate::connectors::{Connector, ValidationResults};
 use crate::ingestion::Ingestor;
 use crate::{connectors::TableInfo, errors::ConnectorError};
-use dozer_types::ingestion_types::SnowflakeConfig;
+#[cfg(feature = "snowflake")]
+use dozer_types::ingestion_types::IngestionMessage;
+use dozer_types::ingestion_types::{SnowflakeChangeTracking, SnowflakeConfig};
+use dozer_types::parking_lot::Mutex;
 
+use crate::connectors::snowflake::position::ChangePosition;
+#[cfg(feature = "snowflake")]
+use crate::connectors::snowflake::reader::{
+    current_timestamp, read_all, ChangeReader, ChangesReader, IncrementalReader, CHANGES_WINDOW,
+};
 #[cfg(feature = "snowflake")]
 use crate::connectors::snowflake::stream_consumer::StreamConsumer;
 
@@ -29,11 +37,18 @@ use crate::errors::{SnowflakeError, SnowflakeStreamError};
 pub struct SnowflakeConnector {
     name: String,
     config: SnowflakeConfig,
+    // Last committed checkpoint, which is only recorded. The readers drop the changes committed
+    // before it once the current batch is ingested.
+    committed: Mutex<Option<(u64, u64)>>,
 }
 
 impl SnowflakeConnector {
     pub fn new(name: String, config: SnowflakeConfig) -> Self {
-        Self { name, config }
+        Self {
+            name,
+            config,
+            committed: Mutex::new(None),
+        }
     }
 }
 
@@ -81,17 +96,30 @@ impl Connector for SnowflakeConnector {
                 tables,
                 ingestor,
                 from_seq,
+                &self.committed,
             )
             .await
         })
     }
 
+    fn commit(&self, checkpoint: (u64, u64)) -> Result<(), ConnectorError> {
+        *self.committed.lock() = Some(checkpoint);
+        Ok(())
+    }
+
     fn get_tables(&self, tables: Option<&[TableInfo]>) -> Result<Vec<TableInfo>, ConnectorError> {
         self.get_tables_default(tables)
     }
 
-    fn can_start_from(&self, _last_checkpoint: (u64, u64)) -> Result<bool, ConnectorError> {
-        Ok(false)
+    fn can_start_from(&self, last_checkpoint: (u64, u64)) -> Result<bool, ConnectorError> {
+        // Streams keep their offset and windows of changes can be read again, but neither the
+        // snapshot of the `changes` mode nor the cursors of the `incremental` mode are stored.
+        let position = ChangePosition::from_seq(last_checkpoint);
+        Ok(match &self.config.change_tracking {
+            None | Some(SnowflakeChangeTracking::Stream(_)) => true,
+            Some(SnowflakeChangeTracking::Changes(_)) => !position.snapshot,
+            Some(SnowflakeChangeTracking::Incremental(_)) => false,
+        })
     }
 }
 
@@ -102,33 +130,113 @@ async fn run(
     tables: Vec<TableInfo>,
     ingestor: &Ingestor,
     from_seq: Option<(u64, u64)>,
+    committed: &Mutex<Option<(u64, u64)>>,
 ) -> Result<(), ConnectorError> {
     let client = Client::new(&config);
+    let mut resume = from_seq.map(ChangePosition::from_seq);
+    let (mut readers, mut batch) = create_readers(&name, &config, &client, &tables, resume)?;
+    let parallelism = config.parallelism as usize;
+    let is_changes = matches!(
+        config.change_tracking,
+        Some(SnowflakeChangeTracking::Changes(_))
+    );
 
-    // SNAPSHOT part - run it when stream table doesnt exist
-    let stream_client = Client::new(&config);
     let mut interval = time::interval(Duration::from_secs(5));
-
-    let mut consumer = StreamConsumer::new();
-    let mut iteration = 0;
     loop {
-        for (idx, table) in tables.iter().enumerate() {
-            // We only check stream status on first iteration
-            if iteration == 0 {
-                match from_seq {
-                    None | Some((0, _)) => {
+        if is_changes {
+            // A window of changes can only be read once it ended
+            let now = current_timestamp(&client)?;
+            if batch > now {
+                time::sleep(Duration::from_micros(batch - now)).await;
+            }
+        } else {
+            interval.tick().await;
+        }
+
+        debug!("[{}] Reading batch {}", name, batch);
+        let changes = read_all(&mut readers, &client, batch, parallelism)?;
+        // After a restart, tables return the batches read since the checkpoint, which are
+        // ingested batch by batch so that positions keep increasing.
+        let mut batches = changes
+            .into_iter()
+            .enumerate()
+            .flat_map(|(table_idx, table_changes)| {
+                let snapshot = table_changes.snapshot;
+                table_changes
+                    .batches
+                    .into_iter()
+                    .map(move |(table_batch, operations)| {
+                        (table_batch, table_idx, snapshot, operations)
+                    })
+            })
+            .collect::<Vec<_>>();
+        batches.sort_by_key(|(table_batch, table_idx, _, _)| (*table_batch, *table_idx));
+
+        for (table_batch, table_idx, snapshot, operations) in batches {
+            let messages = operations
+                .into_iter()
+                .enumerate()
+                .filter_map(|(row, op)| {
+                    let position = ChangePosition {
+                        batch: table_batch,
+                        table_idx: table_idx as u32,
+                        row: row as u32,
+                        snapshot,
+                    };
+                    // Changes up to the checkpoint were ingested before the restart
+                    if matches!(resume, Some(resume) if position <= resume) {
+                        return None;
+                    }
+                    let (txid, seq_in_tx) = position.to_seq();
+                    Some(IngestionMessage::new_op(txid, seq_in_tx, op))
+                })
+                .collect::<Vec<_>>();
+            if !messages.is_empty() {
+                ingestor
+                    .handle_batch(messages)
+                    .map_err(ConnectorError::IngestorError)?;
+            }
+        }
+
+        let committed = *committed.lock();
+        if let Some(checkpoint) = committed {
+            let committed_batch = ChangePosition::from_seq(checkpoint).batch;
+            for reader in readers.iter_mut() {
+                reader.commit(&client, committed_batch)?;
+            }
+        }
+        resume = None;
+        batch += if is_changes { CHANGES_WINDOW } else { 1 };
+    }
+}
+
+/// Creates the readers of the tables, returning them with the first batch to read.
+#[cfg(feature = "snowflake")]
+fn create_readers(
+    name: &str,
+    config: &SnowflakeConfig,
+    client: &Client,
+    tables: &[TableInfo],
+    resume: Option<ChangePosition>,
+) -> Result<(Vec<Box<dyn ChangeReader>>, u64), ConnectorError> {
+    let mut readers: Vec<Box<dyn ChangeReader>> = vec![];
+    match &config.change_tracking {
+        None | Some(SnowflakeChangeTracking::Stream(_)) => {
+            for (idx, table) in tables.iter().enumerate() {
+                match resume {
+                    None => {
                         info!("[{}][{}] Creating new stream", name, table.table_name);
-                        StreamConsumer::drop_stream(&client, &table.table_name)?;
-                        StreamConsumer::create_stream(&client, &table.table_name)?;
+                        StreamConsumer::drop_stream(client, &table.table_name)?;
+                        StreamConsumer::drop_stream_log_table(client, &table.table_name)?;
+                        StreamConsumer::create_stream(client, &table.table_name)?;
                     }
-                    Some((lsn, seq)) => {
+                    Some(position) => {
                         info!(
-                            "[{}][{}] Continuing ingestion from {}/{}",
-                            name, table.table_name, lsn, seq
+                            "[{}][{}] Continuing ingestion from batch {}",
+                            name, table.table_name, position.batch
                         );
-                        iteration = lsn;
                         if let Ok(false) =
-                            StreamConsumer::is_stream_created(&client, &table.table_name)
+                            StreamConsumer::is_stream_created(client, &table.table_name)
                         {
                             return Err(ConnectorError::SnowflakeError(
                                 SnowflakeError::SnowflakeStreamError(
@@ -138,19 +246,57 @@ async fn run(
                         }
                     }
                 }
+                let read_from = resume.map_or(0, |position| position.batch);
+                readers.push(Box::new(StreamConsumer::new(
+                    table.table_name.clone(),
+                    idx,
+                    read_from,
+                )));
             }
-
-            debug!(
-                "[{}][{}] Reading from changes stream",
-                name, table.table_name
-            );
-
-            consumer.consume_stream(&stream_client, &table.table_name, ingestor, idx, iteration)?;
-
-            interval.tick().await;
+            Ok((readers, resume.map_or(0, |position| position.batch + 1)))
+        }
+        Some(SnowflakeChangeTracking::Changes(_)) => {
+            // The window of the checkpoint is read again
+            let batch = match resume {
+                Some(position) => position.batch,
+                None => current_timestamp(client)?,
+            };
+            for (idx, table) in tables.iter().enumerate() {
+                readers.push(Box::new(ChangesReader::new(
+                    table.table_name.clone(),
+                    idx,
+                    resume.is_some(),
+                )));
+            }
+            Ok((readers, batch))
+        }
+        Some(SnowflakeChangeTracking::Incremental(incremental)) => {
+            let schemas = SchemaHelper::get_schema(config, Some(tables.to_vec()))?;
+            for (idx, table) in tables.iter().enumerate() {
+                let cursor_column = incremental
+                    .cursor_columns
+                    .iter()
+                    .find(|c| c.table == table.table_name)
+                    .ok_or_else(|| {
+                        SnowflakeError::SnowflakeStreamError(
+                            SnowflakeStreamError::CursorColumnNotConfigured(
+                                table.table_name.clone(),
+                            ),
+                        )
+                    })?;
+                let schema = schemas
+                    .iter()
+                    .find(|s| s.name == table.table_name)
+                    .ok_or_else(|| ConnectorError::TableNotFound(table.table_name.clone()))?;
+                readers.push(Box::new(IncrementalReader::new(
+                    &table.table_name,
+                    idx,
+                    &cursor_column.column,
+                    schema.schema.clone(),
+                )?));
+            }
+            Ok((readers, 0))
         }
-
-        iteration += 1;
     }
 }
 
@@ -161,6 +307,7 @@ async fn run(
     _tables: Vec<TableInfo>,
     _ingestor: &Ingestor,
     _from_seq: Option<(u64, u64)>,
+    _committed: &Mutex<Option<(u64, u64)>>,
 ) -> Result<(), ConnectorError> {
     Ok(())
 }
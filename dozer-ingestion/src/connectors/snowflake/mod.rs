@@ -1,6 +1,10 @@
 #[cfg(feature = "snowflake")]
 pub mod connection;
 pub mod connector;
+#[cfg_attr(not(feature = "snowflake"), allow(dead_code))]
+mod position;
+#[cfg(feature = "snowflake")]
+mod reader;
 #[cfg(feature = "snowflake")]
 mod schema_helper;
 #[cfg(feature = "snowflake")]
@@ -0,0 +1,71 @@
+/// Position of a change, stored in the checkpoint as the message identifier.
+///
+/// `txid` holds the batch of changes read from all tables, `seq_in_tx` holds the index of the
+/// table and the index of the change among the changes of the table in the batch. Rows of the
+/// initial snapshot of the `changes` mode are flagged, as the snapshot can't be resumed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ChangePosition {
+    pub batch: u64,
+    pub table_idx: u32,
+    pub row: u32,
+    pub snapshot: bool,
+}
+
+const SNAPSHOT_FLAG: u64 = 1 << 63;
+
+impl ChangePosition {
+    pub fn to_seq(self) -> (u64, u64) {
+        let snapshot = if self.snapshot { SNAPSHOT_FLAG } else { 0 };
+        (
+            self.batch,
+            snapshot | ((self.table_idx as u64) << 32) | (self.row as u64),
+        )
+    }
+
+    pub fn from_seq((txid, seq_in_tx): (u64, u64)) -> Self {
+        Self {
+            batch: txid,
+            table_idx: ((seq_in_tx & !SNAPSHOT_FLAG) >> 32) as u32,
+            row: seq_in_tx as u32,
+            snapshot: seq_in_tx & SNAPSHOT_FLAG != 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ChangePosition;
+
+    #[test]
+    fn test_position_roundtrip() {
+        for position in [
+            ChangePosition {
+                batch: 0,
+                table_idx: 0,
+                row: 0,
+                snapshot: false,
+            },
+            ChangePosition {
+                batch: 1_680_000_000_000_000,
+                table_idx: 3,
+                row: u32::MAX,
+                snapshot: true,
+            },
+        ] {
+            assert_eq!(ChangePosition::from_seq(position.to_seq()), position);
+        }
+    }
+
+    #[test]
+    fn test_position_order() {
+        let position = |batch, table_idx, row| ChangePosition {
+            batch,
+            table_idx,
+            row,
+            snapshot: false,
+        };
+        assert!(position(1, 0, 5) < position(1, 1, 0));
+        assert!(position(1, 1, 0) < position(2, 0, 0));
+        assert!(position(2, 0, 0) < position(2, 0, 1));
+    }
+}
@@ -0,0 +1,217 @@
+use std::thread;
+
+use dozer_types::ingestion_types::OdbcQuery;
+use dozer_types::types::{Operation, Schema};
+use odbc::create_environment_v3;
+
+use crate::connectors::odbc::query::QueryState;
+use crate::connectors::snowflake::connection::client::Client;
+use crate::connectors::snowflake::stream_consumer::StreamConsumer;
+use crate::errors::SnowflakeError::ConnectionError;
+use crate::errors::{ConnectorError, SnowflakeError, SnowflakeStreamError};
+
+/// Length of the windows of changes read by `ChangesReader`, in microseconds.
+pub const CHANGES_WINDOW: u64 = 5_000_000;
+
+/// Changes read from a table, by batch.
+#[derive(Debug)]
+pub struct TableChanges {
+    pub snapshot: bool,
+    pub batches: Vec<(u64, Vec<Operation>)>,
+}
+
+/// Reads the changes of a table, batch after batch.
+pub trait ChangeReader: Send {
+    /// Reads the changes of the batch. Changes read before a restart may be returned with the
+    /// batch they were read in.
+    fn read(&mut self, client: &Client, batch: u64) -> Result<TableChanges, ConnectorError>;
+
+    /// Called once the pipeline committed a checkpoint in `batch`. The changes of the previous
+    /// batches are not read again.
+    fn commit(&mut self, _client: &Client, _batch: u64) -> Result<(), ConnectorError> {
+        Ok(())
+    }
+}
+
+/// Reads a batch from all tables, with up to `parallelism` tables read at once. The changes are
+/// returned in the order of the readers.
+pub fn read_all(
+    readers: &mut [Box<dyn ChangeReader>],
+    client: &Client,
+    batch: u64,
+    parallelism: usize,
+) -> Result<Vec<TableChanges>, ConnectorError> {
+    if parallelism <= 1 || readers.len() <= 1 {
+        return readers.iter_mut().map(|r| r.read(client, batch)).collect();
+    }
+
+    let chunk_size = (readers.len() + parallelism - 1) / parallelism;
+    thread::scope(|scope| {
+        let handles = readers
+            .chunks_mut(chunk_size)
+            .map(|chunk| {
+                scope.spawn(move || {
+                    chunk
+                        .iter_mut()
+                        .map(|r| r.read(client, batch))
+                        .collect::<Result<Vec<_>, _>>()
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let mut changes = vec![];
+        for handle in handles {
+            changes.extend(handle.join().expect("Snowflake table reader panicked")?);
+        }
+        Ok(changes)
+    })
+}
+
+/// Returns the current time of Snowflake, in microseconds since the epoch.
+pub fn current_timestamp(client: &Client) -> Result<u64, ConnectorError> {
+    let env = create_environment_v3().map_err(|e| e.unwrap()).unwrap();
+    let conn = env
+        .connect_with_connection_string(&client.get_conn_string())
+        .map_err(|e| ConnectionError(Box::new(e)))?;
+
+    let query = "SELECT TO_VARCHAR(DATE_PART(EPOCH_MICROSECOND, CURRENT_TIMESTAMP()))";
+    let row = match client.fetch(&conn, query.to_string())? {
+        Some((_, mut rows)) => rows.try_next()?,
+        None => None,
+    };
+    let timestamp = row.and_then(|row| row.get(0)?.as_string()?.parse().ok());
+    timestamp.ok_or(ConnectorError::SnowflakeError(
+        SnowflakeError::SnowflakeStreamError(SnowflakeStreamError::CurrentTimestampError),
+    ))
+}
+
+fn timestamp_literal(micros: u64) -> String {
+    format!("TO_TIMESTAMP_LTZ({micros}, 6)")
+}
+
+/// Reads the `CHANGES` of a table, which must have change tracking enabled, in windows of
+/// `CHANGES_WINDOW` ending at the batch timestamp. The first batch reads the rows of the
+/// table at its timestamp.
+#[derive(Debug)]
+pub struct ChangesReader {
+    table_name: String,
+    table_idx: usize,
+    snapshot_taken: bool,
+}
+
+impl ChangesReader {
+    pub fn new(table_name: String, table_idx: usize, snapshot_taken: bool) -> Self {
+        Self {
+            table_name,
+            table_idx,
+            snapshot_taken,
+        }
+    }
+}
+
+impl ChangeReader for ChangesReader {
+    fn read(&mut self, client: &Client, batch: u64) -> Result<TableChanges, ConnectorError> {
+        let env = create_environment_v3().map_err(|e| e.unwrap()).unwrap();
+        let conn = env
+            .connect_with_connection_string(&client.get_conn_string())
+            .map_err(|e| ConnectionError(Box::new(e)))?;
+
+        let table_name = &self.table_name;
+        let snapshot = !self.snapshot_taken;
+        let query = if snapshot {
+            format!(
+                "SELECT * FROM {table_name} AT(TIMESTAMP => {})",
+                timestamp_literal(batch)
+            )
+        } else {
+            format!(
+                "SELECT * FROM {table_name} CHANGES(INFORMATION => DEFAULT)
+                    AT(TIMESTAMP => {}) END(TIMESTAMP => {})
+                    ORDER BY METADATA$ACTION, METADATA$ROW_ID",
+                timestamp_literal(batch - CHANGES_WINDOW),
+                timestamp_literal(batch)
+            )
+        };
+
+        let mut operations = vec![];
+        if let Some((schema, mut rows)) = client.fetch(&conn, query)? {
+            // Changes are followed by the action, update flag and row id columns
+            let used_columns_for_schema = schema.len() - 3;
+            while let Some(row) = rows.try_next()? {
+                let op = if snapshot {
+                    Operation::Insert {
+                        new: StreamConsumer::map_record(row, self.table_idx),
+                    }
+                } else {
+                    StreamConsumer::get_operation(
+                        row,
+                        used_columns_for_schema,
+                        used_columns_for_schema,
+                        self.table_idx,
+                    )?
+                };
+                operations.push(op);
+            }
+        }
+
+        self.snapshot_taken = true;
+        Ok(TableChanges {
+            snapshot,
+            batches: vec![(batch, operations)],
+        })
+    }
+}
+
+/// Reads the rows of a table having a cursor column value greater than, or equal to, the last
+/// read one. Rows read again are updates if the table has a primary key.
+#[derive(Debug)]
+pub struct IncrementalReader {
+    state: QueryState,
+}
+
+impl IncrementalReader {
+    pub fn new(
+        table_name: &str,
+        table_idx: usize,
+        cursor_column: &str,
+        schema: Schema,
+    ) -> Result<Self, ConnectorError> {
+        let query = OdbcQuery {
+            name: table_name.to_string(),
+            query: format!("SELECT * FROM {table_name}"),
+            cursor_column: cursor_column.to_string(),
+            primary_key: schema
+                .primary_index
+                .iter()
+                .map(|idx| schema.fields[*idx].name.clone())
+                .collect(),
+            soft_delete_column: None,
+        };
+        let state = QueryState::new(table_idx as u32, &query, schema)?;
+        Ok(Self { state })
+    }
+}
+
+impl ChangeReader for IncrementalReader {
+    fn read(&mut self, client: &Client, batch: u64) -> Result<TableChanges, ConnectorError> {
+        let env = create_environment_v3().map_err(|e| e.unwrap()).unwrap();
+        let conn = env
+            .connect_with_connection_string(&client.get_conn_string())
+            .map_err(|e| ConnectionError(Box::new(e)))?;
+
+        let query = self.state.start_poll()?;
+        let mut operations = vec![];
+        if let Some((_, mut rows)) = client.fetch(&conn, query)? {
+            while let Some(row) = rows.try_next()? {
+                if let Some((_, op)) = self.state.read_row(row)? {
+                    operations.push(op);
+                }
+            }
+        }
+
+        Ok(TableChanges {
+            snapshot: false,
+            batches: vec![(batch, operations)],
+        })
+    }
+}
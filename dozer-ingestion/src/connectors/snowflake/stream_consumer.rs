@@ -1,27 +1,65 @@
 use crate::connectors::snowflake::connection::client::Client;
 
+use crate::connectors::snowflake::reader::{ChangeReader, TableChanges};
 use crate::errors::{ConnectorError, SnowflakeError};
-use crate::ingestion::Ingestor;
-use dozer_types::ingestion_types::IngestionMessage;
 
-use crate::errors::SnowflakeStreamError::{CannotDetermineAction, UnsupportedActionInStream};
+use crate::errors::SnowflakeStreamError::{
+    CannotDetermineAction, InvalidBatchInStreamLog, UnsupportedActionInStream,
+};
+use dozer_types::rust_decimal::prelude::ToPrimitive;
 use dozer_types::types::{Field, Operation, Record, SchemaIdentifier};
 use odbc::create_environment_v3;
 
-#[derive(Default)]
-pub struct StreamConsumer {}
+/// Reads the changes of a table from a stream created on it.
+///
+/// Each batch is moved from the stream to a log table, which advances the stream offset, and
+/// read from there. After a restart, the log is read again from the batch of the checkpoint, so
+/// only the batches before the committed checkpoint are deleted from it.
+#[derive(Debug)]
+pub struct StreamConsumer {
+    table_name: String,
+    table_idx: usize,
+    read_from: u64,
+    log_table_exist: Option<bool>,
+    // Batches before this one were deleted from the log
+    deleted_before: u64,
+}
 
 impl StreamConsumer {
-    pub fn new() -> Self {
-        Self {}
+    /// Creates a consumer reading the log from batch `read_from`.
+    pub fn new(table_name: String, table_idx: usize, read_from: u64) -> Self {
+        Self {
+            table_name,
+            table_idx,
+            read_from,
+            log_table_exist: None,
+            deleted_before: 0,
+        }
     }
 
     pub fn get_stream_table_name(table_name: &str) -> String {
         format!("dozer_{table_name}_stream")
     }
 
-    pub fn get_stream_temp_table_name(table_name: &str) -> String {
-        format!("dozer_{table_name}_stream_temp")
+    pub fn get_stream_log_table_name(table_name: &str) -> String {
+        format!("dozer_{table_name}_stream_log")
+    }
+
+    pub fn drop_stream_log_table(
+        client: &Client,
+        table_name: &str,
+    ) -> Result<Option<bool>, SnowflakeError> {
+        let env = create_environment_v3().map_err(|e| e.unwrap()).unwrap();
+        let conn = env
+            .connect_with_connection_string(&client.get_conn_string())
+            .unwrap();
+
+        let query = format!(
+            "DROP TABLE IF EXISTS {}",
+            Self::get_stream_log_table_name(table_name),
+        );
+
+        client.exec(&conn, query)
     }
 
     pub fn is_stream_created(client: &Client, table_name: &str) -> Result<bool, ConnectorError> {
@@ -75,7 +113,7 @@ impl StreamConsumer {
         Ok(())
     }
 
-    fn map_record(row: Vec<Field>, table_idx: usize) -> Record {
+    pub fn map_record(row: Vec<Field>, table_idx: usize) -> Record {
         Record {
             schema_id: Some(SchemaIdentifier {
                 id: table_idx as u32,
@@ -86,7 +124,7 @@ impl StreamConsumer {
         }
     }
 
-    fn get_operation(
+    pub fn get_operation(
         row: Vec<Field>,
         action_idx: usize,
         used_columns_for_schema: usize,
@@ -118,55 +156,97 @@ impl StreamConsumer {
             ))
         }
     }
+}
 
-    pub fn consume_stream(
-        &mut self,
-        client: &Client,
-        table_name: &str,
-        ingestor: &Ingestor,
-        table_idx: usize,
-        iteration: u64,
-    ) -> Result<(), ConnectorError> {
+impl ChangeReader for StreamConsumer {
+    fn read(&mut self, client: &Client, batch: u64) -> Result<TableChanges, ConnectorError> {
         let env = create_environment_v3().map_err(|e| e.unwrap()).unwrap();
         let conn = env
             .connect_with_connection_string(&client.get_conn_string())
             .unwrap();
 
-        let temp_table_name = Self::get_stream_temp_table_name(table_name);
-        let stream_name = Self::get_stream_table_name(table_name);
-        let temp_table_exist = client.table_exist(&conn, &temp_table_name)?;
+        let log_table_name = Self::get_stream_log_table_name(&self.table_name);
+        let stream_name = Self::get_stream_table_name(&self.table_name);
+        let log_table_exist = match self.log_table_exist {
+            Some(exist) => exist,
+            None => client.table_exist(&conn, &log_table_name)?,
+        };
 
-        if !temp_table_exist {
-            let query = format!(
-                "CREATE OR REPLACE TEMP TABLE {temp_table_name} AS
-                    SELECT * FROM {stream_name} ORDER BY METADATA$ACTION;"
-            );
+        let query = if log_table_exist {
+            format!("INSERT INTO {log_table_name} SELECT *, {batch} FROM {stream_name};")
+        } else {
+            format!(
+                "CREATE TRANSIENT TABLE {log_table_name} AS
+                    SELECT *, {batch} AS DOZER_BATCH FROM {stream_name};"
+            )
+        };
+        client.exec(&conn, query)?;
+        self.log_table_exist = Some(true);
 
-            client.exec(&conn, query)?;
+        let query = format!(
+            "SELECT * FROM {log_table_name} WHERE DOZER_BATCH >= {}
+                ORDER BY DOZER_BATCH, METADATA$ACTION, METADATA$ROW_ID;",
+            self.read_from
+        );
+        let mut batches: Vec<(u64, Vec<Operation>)> = vec![];
+        if let Some((schema, mut rows)) = client.fetch(&conn, query)? {
+            // Changes are followed by the action, update flag, row id and batch columns
+            let used_columns_for_schema = schema.len() - 4;
+            let batch_idx = schema.len() - 1;
+
+            while let Some(row) = rows.try_next()? {
+                let row_batch = batch_value(&row[batch_idx]).ok_or_else(|| {
+                    ConnectorError::SnowflakeError(SnowflakeError::SnowflakeStreamError(
+                        InvalidBatchInStreamLog(row[batch_idx].clone()),
+                    ))
+                })?;
+                let op = Self::get_operation(
+                    row,
+                    used_columns_for_schema,
+                    used_columns_for_schema,
+                    self.table_idx,
+                )?;
+                match batches.last_mut() {
+                    Some((last, operations)) if *last == row_batch => operations.push(op),
+                    _ => batches.push((row_batch, vec![op])),
+                }
+            }
         }
 
-        let result = client.fetch(&conn, format!("SELECT * FROM {temp_table_name};"))?;
-        if let Some((schema, iterator)) = result {
-            let mut truncated_schema = schema.clone();
-            truncated_schema.truncate(schema.len() - 3);
-
-            let columns_length = schema.len();
-            let used_columns_for_schema = columns_length - 3;
-            let action_idx = used_columns_for_schema;
+        self.read_from = batch + 1;
+        Ok(TableChanges {
+            snapshot: false,
+            batches,
+        })
+    }
 
-            for (idx, row) in iterator.enumerate() {
-                let op = Self::get_operation(row, action_idx, used_columns_for_schema, table_idx)?;
-                ingestor
-                    .handle_message(IngestionMessage::new_op(iteration, idx as u64, op))
-                    .map_err(ConnectorError::IngestorError)?;
-            }
+    fn commit(&mut self, client: &Client, batch: u64) -> Result<(), ConnectorError> {
+        if batch <= self.deleted_before {
+            return Ok(());
         }
 
-        let query = format!("DROP TABLE {temp_table_name};");
+        let env = create_environment_v3().map_err(|e| e.unwrap()).unwrap();
+        let conn = env
+            .connect_with_connection_string(&client.get_conn_string())
+            .unwrap();
 
+        let query = format!(
+            "DELETE FROM {} WHERE DOZER_BATCH < {batch};",
+            Self::get_stream_log_table_name(&self.table_name),
+        );
         client
             .exec(&conn, query)
-            .map_err(ConnectorError::SnowflakeError)
-            .map(|_| ())
+            .map_err(ConnectorError::SnowflakeError)?;
+        self.deleted_before = batch;
+        Ok(())
+    }
+}
+
+fn batch_value(value: &Field) -> Option<u64> {
+    match value {
+        Field::Int(v) => u64::try_from(*v).ok(),
+        Field::UInt(v) => Some(*v),
+        Field::Decimal(v) => v.to_u64(),
+        _ => None,
     }
 }
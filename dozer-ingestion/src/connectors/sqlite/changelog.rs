@@ -0,0 +1,195 @@
+use crate::connectors::sqlite::helper::map_value;
+use crate::connectors::sqlite::schema_helper::{quote_identifier, ColumnDefinition};
+use crate::errors::SqliteError;
+use dozer_types::serde_json::{self, Value};
+use dozer_types::types::{Field, FieldType};
+use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, Value as SqliteValue, ValueRef};
+use rusqlite::Connection;
+
+/// Table in which the triggers record the changes of the ingested tables.
+pub const CHANGELOG_TABLE: &str = "_dozer_changelog";
+
+/// Number of change log entries read at once.
+const CHANGELOG_BATCH_SIZE: usize = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    Insert,
+    Update,
+    Delete,
+}
+
+impl ChangeKind {
+    fn code(&self) -> &'static str {
+        match self {
+            ChangeKind::Insert => "I",
+            ChangeKind::Update => "U",
+            ChangeKind::Delete => "D",
+        }
+    }
+}
+
+impl FromSql for ChangeKind {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        match value.as_str()? {
+            "I" => Ok(ChangeKind::Insert),
+            "U" => Ok(ChangeKind::Update),
+            "D" => Ok(ChangeKind::Delete),
+            _ => Err(FromSqlError::InvalidType),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChangeLogEntry {
+    pub id: u64,
+    pub table_name: String,
+    pub kind: ChangeKind,
+    pub old_values: Option<String>,
+    pub new_values: Option<String>,
+}
+
+/// Creates the change log table and the triggers recording the changes of `table_name` in it.
+pub fn install_triggers(
+    conn: &Connection,
+    table_name: &str,
+    columns: &[ColumnDefinition],
+) -> Result<(), SqliteError> {
+    let mut sql = format!(
+        "CREATE TABLE IF NOT EXISTS {CHANGELOG_TABLE} (\
+            id INTEGER PRIMARY KEY AUTOINCREMENT, \
+            table_name TEXT NOT NULL, \
+            op TEXT NOT NULL, \
+            old_values TEXT, \
+            new_values TEXT);"
+    );
+
+    for kind in [ChangeKind::Insert, ChangeKind::Update, ChangeKind::Delete] {
+        let (event, old_values, new_values) = match kind {
+            ChangeKind::Insert => ("INSERT", "NULL".to_string(), json_values("NEW", columns)),
+            ChangeKind::Update => (
+                "UPDATE",
+                json_values("OLD", columns),
+                json_values("NEW", columns),
+            ),
+            ChangeKind::Delete => ("DELETE", json_values("OLD", columns), "NULL".to_string()),
+        };
+        sql.push_str(&format!(
+            "CREATE TRIGGER IF NOT EXISTS {} AFTER {event} ON {} BEGIN \
+                INSERT INTO {CHANGELOG_TABLE} (table_name, op, old_values, new_values) \
+                VALUES ('{}', '{}', {old_values}, {new_values}); \
+            END;",
+            quote_identifier(&format!(
+                "{CHANGELOG_TABLE}_{table_name}_{}",
+                event.to_lowercase()
+            )),
+            quote_identifier(table_name),
+            table_name.replace('\'', "''"),
+            kind.code(),
+        ));
+    }
+
+    conn.execute_batch(&sql).map_err(SqliteError::QueryError)
+}
+
+/// Builds the JSON array of the values of a row. Blobs can't be written to JSON, they are
+/// written as hexadecimal strings.
+fn json_values(row: &str, columns: &[ColumnDefinition]) -> String {
+    let values: Vec<String> = columns
+        .iter()
+        .map(|c| {
+            let value = format!("{row}.{}", quote_identifier(&c.name));
+            if c.typ == FieldType::Binary {
+                format!("CASE WHEN {value} IS NULL THEN NULL ELSE hex({value}) END")
+            } else {
+                format!("CASE WHEN typeof({value}) = 'blob' THEN hex({value}) ELSE {value} END")
+            }
+        })
+        .collect();
+    format!("json_array({})", values.join(", "))
+}
+
+pub fn get_last_entry_id(conn: &Connection) -> Result<u64, SqliteError> {
+    conn.query_row(
+        &format!("SELECT COALESCE(MAX(id), 0) FROM {CHANGELOG_TABLE}"),
+        [],
+        |row| row.get(0),
+    )
+    .map_err(SqliteError::QueryError)
+}
+
+/// Removes the entries which were ingested before the checkpoint.
+pub fn prune(conn: &Connection, last_id: u64) -> Result<(), SqliteError> {
+    conn.execute(
+        &format!("DELETE FROM {CHANGELOG_TABLE} WHERE id <= ?1"),
+        [last_id],
+    )
+    .map(|_| ())
+    .map_err(SqliteError::QueryError)
+}
+
+pub fn read_entries(conn: &Connection, after_id: u64) -> Result<Vec<ChangeLogEntry>, SqliteError> {
+    let mut statement = conn
+        .prepare_cached(&format!(
+            "SELECT id, table_name, op, old_values, new_values FROM {CHANGELOG_TABLE} \
+             WHERE id > ?1 ORDER BY id LIMIT {CHANGELOG_BATCH_SIZE}"
+        ))
+        .map_err(SqliteError::QueryError)?;
+    let entries = statement
+        .query_map([after_id], |row| {
+            Ok(ChangeLogEntry {
+                id: row.get(0)?,
+                table_name: row.get(1)?,
+                kind: row.get(2)?,
+                old_values: row.get(3)?,
+                new_values: row.get(4)?,
+            })
+        })
+        .map_err(SqliteError::QueryError)?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(SqliteError::QueryError)?;
+    Ok(entries)
+}
+
+/// Maps the values recorded by a trigger, in the order of the columns of the table, keeping the
+/// ones at `selected` positions.
+pub fn map_values(
+    values: &str,
+    columns: &[ColumnDefinition],
+    selected: &[usize],
+) -> Result<Vec<Field>, SqliteError> {
+    let values: Vec<Value> =
+        serde_json::from_str(values).map_err(SqliteError::ChangeLogParseError)?;
+
+    selected
+        .iter()
+        .map(|idx| {
+            let column = &columns[*idx];
+            let error = || SqliteError::ValueConversionError(column.name.clone(), column.typ);
+            let value = match values.get(*idx).ok_or_else(error)? {
+                Value::Null => SqliteValue::Null,
+                Value::Bool(v) => SqliteValue::Integer(*v as i64),
+                Value::Number(v) => match v.as_i64() {
+                    Some(v) => SqliteValue::Integer(v),
+                    None => SqliteValue::Real(v.as_f64().ok_or_else(error)?),
+                },
+                Value::String(v) if column.typ == FieldType::Binary => {
+                    SqliteValue::Blob(decode_hex(v).ok_or_else(error)?)
+                }
+                Value::String(v) => SqliteValue::Text(v.clone()),
+                _ => return Err(error()),
+            };
+            map_value((&value).into(), column)
+        })
+        .collect()
+}
+
+fn decode_hex(value: &str) -> Option<Vec<u8>> {
+    if value.len() % 2 != 0 {
+        return None;
+    }
+    (0..value.len())
+        .step_by(2)
+        .map(|idx| u8::from_str_radix(value.get(idx..idx + 2)?, 16).ok())
+        .collect()
+}
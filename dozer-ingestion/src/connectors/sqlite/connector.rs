@@ -0,0 +1,425 @@
+use std::collections::HashMap;
+use std::thread;
+use std::time::Duration;
+
+use dozer_types::ingestion_types::{IngestionMessage, SqliteConfig};
+use dozer_types::log::{info, warn};
+use dozer_types::parking_lot::Mutex;
+use dozer_types::types::{Operation, Record, ReplicationChangesTrackingType, Schema, SourceSchema};
+use rusqlite::types::Value;
+use rusqlite::{Connection, OpenFlags, Row};
+
+use crate::connectors::sqlite::changelog::{self, ChangeKind, ChangeLogEntry};
+use crate::connectors::sqlite::helper::map_value;
+use crate::connectors::sqlite::schema_helper::{
+    get_columns, get_selected_columns, get_table_names, map_schema, quote_identifier,
+    ColumnDefinition,
+};
+use crate::connectors::{Connector, TableInfo, ValidationResults};
+use crate::errors::{ConnectorError, SqliteError};
+use crate::ingestion::Ingestor;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChangeDetection {
+    /// New rows are read by increasing `rowid`, tables are append only.
+    RowId,
+    /// Rows are read again when the column holding their update time increases. Deletes are not
+    /// detected, and the ingested rows are kept in memory to ingest updates.
+    UpdatedAt(String),
+    /// Triggers record all the changes in a change log table.
+    Triggers,
+}
+
+#[derive(Debug)]
+pub struct SqliteConnector {
+    pub id: u64,
+    name: String,
+    config: SqliteConfig,
+    // Last committed checkpoint, which is only recorded. The change log entries up to it are
+    // removed before reading the next entries.
+    committed: Mutex<Option<(u64, u64)>>,
+}
+
+/// A table being ingested.
+#[derive(Debug)]
+struct SqliteTable {
+    name: String,
+    schema: Schema,
+    columns: Vec<ColumnDefinition>,
+    /// Positions of the ingested columns among all the columns of the table.
+    selected: Vec<usize>,
+    all_columns: Vec<ColumnDefinition>,
+    // Last read `rowid`, and update time for `updated_at`
+    last_rowid: i64,
+    last_updated_at: Value,
+    // Rows read so far for `updated_at`, by `rowid`
+    rows: HashMap<i64, Record>,
+}
+
+impl SqliteConnector {
+    pub fn new(id: u64, name: String, config: SqliteConfig) -> Self {
+        Self {
+            id,
+            name,
+            config,
+            committed: Mutex::new(None),
+        }
+    }
+
+    pub fn change_detection(&self) -> Result<ChangeDetection, SqliteError> {
+        match self.config.change_detection.as_str() {
+            "rowid" => Ok(ChangeDetection::RowId),
+            "updated_at" => self
+                .config
+                .updated_at_column
+                .clone()
+                .map(ChangeDetection::UpdatedAt)
+                .ok_or(SqliteError::UpdatedAtColumnMissing),
+            "triggers" => Ok(ChangeDetection::Triggers),
+            other => Err(SqliteError::UnsupportedChangeDetection(other.to_string())),
+        }
+    }
+
+    fn connect(&self, read_only: bool) -> Result<Connection, SqliteError> {
+        let flags = if read_only {
+            OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX
+        } else {
+            OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_NO_MUTEX
+        };
+        Connection::open_with_flags(&self.config.path, flags)
+            .map_err(|e| SqliteError::ConnectionFailed(self.config.path.clone(), e))
+    }
+
+    fn get_tables_info(&self, conn: &Connection) -> Result<Vec<TableInfo>, ConnectorError> {
+        Ok(get_table_names(conn)?
+            .into_iter()
+            .enumerate()
+            .map(|(id, name)| TableInfo {
+                name: name.clone(),
+                table_name: name,
+                id: id as u32,
+                columns: None,
+            })
+            .collect())
+    }
+
+    fn get_tables_to_read(
+        &self,
+        conn: &Connection,
+        tables: &[TableInfo],
+        change_detection: &ChangeDetection,
+    ) -> Result<Vec<SqliteTable>, ConnectorError> {
+        tables
+            .iter()
+            .enumerate()
+            .map(|(id, table)| {
+                let all_columns = get_columns(conn, &table.table_name)?;
+                let columns = get_selected_columns(conn, table)?;
+                let schema = map_schema(id as u32, &columns);
+
+                // Updates are looked up by primary key, which must be fully ingested.
+                let pk_count = all_columns.iter().filter(|c| c.pk > 0).count();
+                if *change_detection != ChangeDetection::RowId
+                    && (pk_count == 0 || schema.primary_index.len() != pk_count)
+                {
+                    return Err(SqliteError::PrimaryKeyRequired(table.table_name.clone()).into());
+                }
+
+                let selected = columns
+                    .iter()
+                    .map(|c| {
+                        all_columns
+                            .iter()
+                            .position(|column| column.name == c.name)
+                            .expect("selected columns are columns of the table")
+                    })
+                    .collect();
+
+                Ok(SqliteTable {
+                    name: table.table_name.clone(),
+                    schema,
+                    columns,
+                    selected,
+                    all_columns,
+                    last_rowid: i64::MIN,
+                    last_updated_at: Value::Null,
+                    rows: HashMap::new(),
+                })
+            })
+            .collect()
+    }
+}
+
+impl Connector for SqliteConnector {
+    fn validate(&self, tables: Option<Vec<TableInfo>>) -> Result<(), ConnectorError> {
+        self.change_detection()?;
+        self.get_schemas(tables).map(|_| ())
+    }
+
+    fn validate_schemas(&self, tables: &[TableInfo]) -> Result<ValidationResults, ConnectorError> {
+        let conn = self.connect(true)?;
+        let change_detection = self.change_detection()?;
+        let mut results = HashMap::new();
+        for table in tables {
+            let r = self
+                .get_tables_to_read(&conn, std::slice::from_ref(table), &change_detection)
+                .map(|_| ());
+            results.insert(table.name.clone(), vec![(None, r)]);
+        }
+        Ok(results)
+    }
+
+    fn get_schemas(
+        &self,
+        table_names: Option<Vec<TableInfo>>,
+    ) -> Result<Vec<SourceSchema>, ConnectorError> {
+        let conn = self.connect(true)?;
+        let change_detection = self.change_detection()?;
+        let tables = match table_names {
+            Some(tables) => tables,
+            None => self.get_tables_info(&conn)?,
+        };
+        let replication_type = match change_detection {
+            ChangeDetection::RowId => ReplicationChangesTrackingType::Nothing,
+            _ => ReplicationChangesTrackingType::FullChanges,
+        };
+
+        Ok(self
+            .get_tables_to_read(&conn, &tables, &change_detection)?
+            .into_iter()
+            .zip(tables)
+            .map(|(table, info)| {
+                SourceSchema::new(info.name, table.schema, replication_type.clone())
+            })
+            .collect())
+    }
+
+    fn can_start_from(&self, (txid, _): (u64, u64)) -> Result<bool, ConnectorError> {
+        // Only the change log keeps a position, the checkpoint holds the id of its last ingested
+        // entry. Other change detections read the tables again.
+        Ok(self.change_detection()? == ChangeDetection::Triggers && txid > 0)
+    }
+
+    fn start(
+        &self,
+        from_seq: Option<(u64, u64)>,
+        ingestor: &Ingestor,
+        tables: Vec<TableInfo>,
+    ) -> Result<(), ConnectorError> {
+        let change_detection = self.change_detection()?;
+        let mut conn = self.connect(change_detection != ChangeDetection::Triggers)?;
+        let mut tables = self.get_tables_to_read(&conn, &tables, &change_detection)?;
+        let poll_interval = Duration::from_millis(self.config.poll_interval_ms);
+
+        info!(
+            "[{}] Reading {} tables with {:?} change detection",
+            self.name,
+            tables.len(),
+            change_detection
+        );
+        if let ChangeDetection::UpdatedAt(column) = &change_detection {
+            warn!(
+                "[{}] Deletes are not detected with the {column} column, and all the rows are kept in memory",
+                self.name
+            );
+        }
+        match change_detection {
+            ChangeDetection::RowId | ChangeDetection::UpdatedAt(_) => {
+                let updated_at = match &change_detection {
+                    ChangeDetection::UpdatedAt(column) => Some(column.as_str()),
+                    _ => None,
+                };
+                let mut seq_no = 0;
+                loop {
+                    let mut read = 0;
+                    for table in tables.iter_mut() {
+                        read += poll_table(&conn, table, updated_at, ingestor, &mut seq_no)?;
+                    }
+                    if read == 0 {
+                        thread::sleep(poll_interval);
+                    }
+                }
+            }
+            ChangeDetection::Triggers => {
+                for table in &tables {
+                    changelog::install_triggers(&conn, &table.name, &table.all_columns)?;
+                }
+
+                let mut last_id = match from_seq {
+                    Some((last_id, _)) if last_id > 0 => last_id,
+                    _ => snapshot(&mut conn, &mut tables, ingestor)?,
+                };
+                let mut pruned_id = 0;
+                if let Some((last_id, _)) = from_seq {
+                    changelog::prune(&conn, last_id)?;
+                    pruned_id = last_id;
+                }
+
+                let tables: HashMap<&str, &SqliteTable> =
+                    tables.iter().map(|t| (t.name.as_str(), t)).collect();
+                loop {
+                    // Entries are ingested as transactions of their id, so the committed ones are
+                    // up to the id of the checkpoint.
+                    let committed = *self.committed.lock();
+                    if let Some((committed_id, _)) = committed {
+                        if committed_id > pruned_id {
+                            changelog::prune(&conn, committed_id)?;
+                            pruned_id = committed_id;
+                        }
+                    }
+
+                    let entries = changelog::read_entries(&conn, last_id)?;
+                    if entries.is_empty() {
+                        thread::sleep(poll_interval);
+                        continue;
+                    }
+
+                    for entry in entries {
+                        last_id = entry.id;
+                        let Some(table) = tables.get(entry.table_name.as_str()) else {
+                            continue;
+                        };
+                        let op = map_entry(&entry, table)?;
+                        ingestor
+                            .handle_message(IngestionMessage::new_op(entry.id, 0, op))
+                            .map_err(ConnectorError::IngestorError)?;
+                    }
+                }
+            }
+        }
+    }
+
+    fn commit(&self, checkpoint: (u64, u64)) -> Result<(), ConnectorError> {
+        *self.committed.lock() = Some(checkpoint);
+        Ok(())
+    }
+
+    fn get_tables(&self, tables: Option<&[TableInfo]>) -> Result<Vec<TableInfo>, ConnectorError> {
+        self.get_tables_default(tables)
+    }
+}
+
+fn map_row(row: &Row, table: &SqliteTable) -> Result<Record, ConnectorError> {
+    let values = table
+        .columns
+        .iter()
+        .enumerate()
+        .map(|(idx, column)| {
+            let value = row.get_ref(idx + 1).map_err(SqliteError::QueryError)?;
+            map_value(value, column)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(Record::new(table.schema.identifier, values, None))
+}
+
+fn select_columns(table: &SqliteTable) -> String {
+    table
+        .columns
+        .iter()
+        .map(|c| quote_identifier(&c.name))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Reads the rows added, or updated when `updated_at` is set, since the last poll.
+fn poll_table(
+    conn: &Connection,
+    table: &mut SqliteTable,
+    updated_at: Option<&str>,
+    ingestor: &Ingestor,
+    seq_no: &mut u64,
+) -> Result<usize, ConnectorError> {
+    let columns = select_columns(table);
+    let sql = match updated_at {
+        None => format!(
+            "SELECT rowid, {columns} FROM {} WHERE rowid > ?1 ORDER BY rowid",
+            quote_identifier(&table.name)
+        ),
+        Some(updated_at) => {
+            let updated_at = quote_identifier(updated_at);
+            format!(
+                "SELECT rowid, {columns}, {updated_at} FROM {} \
+                 WHERE ?2 IS NULL OR {updated_at} > ?2 OR ({updated_at} = ?2 AND rowid > ?1) \
+                 ORDER BY {updated_at}, rowid",
+                quote_identifier(&table.name)
+            )
+        }
+    };
+
+    let mut statement = conn.prepare_cached(&sql).map_err(SqliteError::QueryError)?;
+    let mut rows = match updated_at {
+        None => statement.query([table.last_rowid]),
+        Some(_) => statement.query(rusqlite::params![table.last_rowid, table.last_updated_at]),
+    }
+    .map_err(SqliteError::QueryError)?;
+
+    let mut read = 0;
+    while let Some(row) = rows.next().map_err(SqliteError::QueryError)? {
+        let rowid: i64 = row.get(0).map_err(SqliteError::QueryError)?;
+        let new = map_row(row, table)?;
+        table.last_rowid = rowid;
+
+        let op = if updated_at.is_some() {
+            table.last_updated_at = row
+                .get(table.columns.len() + 1)
+                .map_err(SqliteError::QueryError)?;
+            match table.rows.insert(rowid, new.clone()) {
+                Some(old) => Operation::Update { old, new },
+                None => Operation::Insert { new },
+            }
+        } else {
+            Operation::Insert { new }
+        };
+
+        ingestor
+            .handle_message(IngestionMessage::new_op(0, *seq_no, op))
+            .map_err(ConnectorError::IngestorError)?;
+        *seq_no += 1;
+        read += 1;
+    }
+
+    Ok(read)
+}
+
+/// Reads the tables as of the last change log entry, which is returned.
+fn snapshot(
+    conn: &mut Connection,
+    tables: &mut [SqliteTable],
+    ingestor: &Ingestor,
+) -> Result<u64, ConnectorError> {
+    // A read transaction sees the tables and the change log at the same point.
+    let transaction = conn.transaction().map_err(SqliteError::QueryError)?;
+    let last_id = changelog::get_last_entry_id(&transaction)?;
+
+    let mut seq_no = 0;
+    for table in tables.iter_mut() {
+        poll_table(&transaction, table, None, ingestor, &mut seq_no)?;
+    }
+
+    transaction.commit().map_err(SqliteError::QueryError)?;
+    Ok(last_id)
+}
+
+fn map_entry(entry: &ChangeLogEntry, table: &SqliteTable) -> Result<Operation, ConnectorError> {
+    let record = |values: &Option<String>| -> Result<Record, ConnectorError> {
+        let values = changelog::map_values(
+            values.as_deref().unwrap_or("[]"),
+            &table.all_columns,
+            &table.selected,
+        )?;
+        Ok(Record::new(table.schema.identifier, values, None))
+    };
+
+    Ok(match entry.kind {
+        ChangeKind::Insert => Operation::Insert {
+            new: record(&entry.new_values)?,
+        },
+        ChangeKind::Update => Operation::Update {
+            old: record(&entry.old_values)?,
+            new: record(&entry.new_values)?,
+        },
+        ChangeKind::Delete => Operation::Delete {
+            old: record(&entry.old_values)?,
+        },
+    })
+}
@@ -0,0 +1,60 @@
+use crate::connectors::sqlite::schema_helper::ColumnDefinition;
+use crate::errors::SqliteError;
+use dozer_types::chrono::{DateTime, NaiveDate, NaiveDateTime, Offset, Utc};
+use dozer_types::ordered_float::OrderedFloat;
+use dozer_types::rust_decimal::prelude::FromPrimitive;
+use dozer_types::rust_decimal::Decimal;
+use dozer_types::types::{Field, FieldType, DATE_FORMAT};
+use rusqlite::types::ValueRef;
+use std::str::FromStr;
+
+/// Formats in which SQLite date and time functions write timestamps.
+const TIMESTAMP_FORMATS: [&str; 2] = ["%Y-%m-%d %H:%M:%S%.f", "%Y-%m-%dT%H:%M:%S%.f"];
+
+/// Maps a value to the type of its column. SQLite columns are dynamically typed, so values
+/// stored with another storage class are converted when they can be.
+pub fn map_value(value: ValueRef, column: &ColumnDefinition) -> Result<Field, SqliteError> {
+    let error = || SqliteError::ValueConversionError(column.name.clone(), column.typ);
+    let text = |value: &[u8]| std::str::from_utf8(value).map_err(|_| error());
+
+    let field = match (column.typ, value) {
+        (_, ValueRef::Null) => Field::Null,
+        (FieldType::Int, ValueRef::Integer(v)) => Field::Int(v),
+        (FieldType::Int, ValueRef::Text(v)) => Field::Int(text(v)?.parse().map_err(|_| error())?),
+        (FieldType::Float, ValueRef::Integer(v)) => Field::Float(OrderedFloat(v as f64)),
+        (FieldType::Float, ValueRef::Real(v)) => Field::Float(OrderedFloat(v)),
+        (FieldType::Float, ValueRef::Text(v)) => {
+            Field::Float(OrderedFloat(text(v)?.parse().map_err(|_| error())?))
+        }
+        (FieldType::Boolean, ValueRef::Integer(v)) => Field::Boolean(v != 0),
+        (FieldType::String, ValueRef::Text(v)) => Field::String(text(v)?.to_string()),
+        (FieldType::String, ValueRef::Integer(v)) => Field::String(v.to_string()),
+        (FieldType::String, ValueRef::Real(v)) => Field::String(v.to_string()),
+        (FieldType::Binary, ValueRef::Blob(v) | ValueRef::Text(v)) => Field::Binary(v.to_vec()),
+        (FieldType::Decimal, ValueRef::Integer(v)) => Field::Decimal(Decimal::from(v)),
+        (FieldType::Decimal, ValueRef::Real(v)) => {
+            Field::Decimal(Decimal::from_f64(v).ok_or_else(error)?)
+        }
+        (FieldType::Decimal, ValueRef::Text(v)) => {
+            Field::Decimal(Decimal::from_str(text(v)?).map_err(|_| error())?)
+        }
+        (FieldType::Timestamp, ValueRef::Text(v)) => {
+            let v = text(v)?;
+            let timestamp = TIMESTAMP_FORMATS
+                .iter()
+                .find_map(|format| NaiveDateTime::parse_from_str(v, format).ok())
+                .ok_or_else(error)?;
+            Field::Timestamp(DateTime::from_utc(timestamp, Utc.fix()))
+        }
+        (FieldType::Timestamp, ValueRef::Integer(v)) => {
+            let timestamp = NaiveDateTime::from_timestamp_opt(v, 0).ok_or_else(error)?;
+            Field::Timestamp(DateTime::from_utc(timestamp, Utc.fix()))
+        }
+        (FieldType::Date, ValueRef::Text(v)) => {
+            Field::Date(NaiveDate::parse_from_str(text(v)?, DATE_FORMAT).map_err(|_| error())?)
+        }
+        _ => return Err(error()),
+    };
+
+    Ok(field)
+}
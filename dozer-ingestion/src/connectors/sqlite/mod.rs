@@ -0,0 +1,7 @@
+mod changelog;
+pub mod connector;
+mod helper;
+mod schema_helper;
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,137 @@
+use crate::connectors::sqlite::changelog::CHANGELOG_TABLE;
+use crate::connectors::TableInfo;
+use crate::errors::{ConnectorError, SqliteError};
+use dozer_types::types::{FieldDefinition, FieldType, Schema, SchemaIdentifier, SourceDefinition};
+use rusqlite::Connection;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColumnDefinition {
+    pub name: String,
+    pub typ: FieldType,
+    pub nullable: bool,
+    /// Position in the primary key, starting at 1, or 0 if the column is not part of it.
+    pub pk: usize,
+}
+
+/// Maps a declared column type to a field type, following the affinity rules of SQLite. Types
+/// with numeric affinity are narrowed down by their name.
+pub fn map_affinity(declared_type: &str) -> FieldType {
+    let declared_type = declared_type.to_uppercase();
+    let contains = |names: &[&str]| names.iter().any(|name| declared_type.contains(name));
+
+    if contains(&["INT"]) {
+        FieldType::Int
+    } else if contains(&["CHAR", "CLOB", "TEXT"]) {
+        FieldType::String
+    } else if declared_type.is_empty() || contains(&["BLOB"]) {
+        FieldType::Binary
+    } else if contains(&["REAL", "FLOA", "DOUB"]) {
+        FieldType::Float
+    } else if contains(&["BOOL"]) {
+        FieldType::Boolean
+    } else if contains(&["DATETIME", "TIMESTAMP"]) {
+        FieldType::Timestamp
+    } else if contains(&["DATE"]) {
+        FieldType::Date
+    } else {
+        FieldType::Decimal
+    }
+}
+
+pub fn quote_identifier(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+/// Returns the user tables of the database.
+pub fn get_table_names(conn: &Connection) -> Result<Vec<String>, SqliteError> {
+    let mut statement = conn
+        .prepare(
+            "SELECT name FROM sqlite_master \
+             WHERE type = 'table' AND name NOT LIKE 'sqlite_%' AND name <> ?1 \
+             ORDER BY name",
+        )
+        .map_err(SqliteError::QueryError)?;
+    let names = statement
+        .query_map([CHANGELOG_TABLE], |row| row.get(0))
+        .map_err(SqliteError::QueryError)?
+        .collect::<Result<Vec<String>, _>>()
+        .map_err(SqliteError::QueryError)?;
+    Ok(names)
+}
+
+pub fn get_columns(
+    conn: &Connection,
+    table_name: &str,
+) -> Result<Vec<ColumnDefinition>, ConnectorError> {
+    let mut statement = conn
+        .prepare(&format!(
+            "PRAGMA table_info({})",
+            quote_identifier(table_name)
+        ))
+        .map_err(SqliteError::QueryError)?;
+    let columns = statement
+        .query_map([], |row| {
+            let declared_type: String = row.get(2)?;
+            let not_null: bool = row.get(3)?;
+            let pk: usize = row.get(5)?;
+            Ok(ColumnDefinition {
+                name: row.get(1)?,
+                typ: map_affinity(&declared_type),
+                nullable: !not_null && pk == 0,
+                pk,
+            })
+        })
+        .map_err(SqliteError::QueryError)?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(SqliteError::QueryError)?;
+
+    if columns.is_empty() {
+        return Err(ConnectorError::TableNotFound(table_name.to_string()));
+    }
+    Ok(columns)
+}
+
+/// Returns the definitions of the requested columns of a table, all of them when none is
+/// requested.
+pub fn get_selected_columns(
+    conn: &Connection,
+    table: &TableInfo,
+) -> Result<Vec<ColumnDefinition>, ConnectorError> {
+    let columns = get_columns(conn, &table.table_name)?;
+    match &table.columns {
+        Some(selected) if !selected.is_empty() => selected
+            .iter()
+            .map(|c| {
+                columns
+                    .iter()
+                    .find(|column| column.name == c.name)
+                    .cloned()
+                    .ok_or_else(|| {
+                        SqliteError::ColumnNotFound(c.name.clone(), table.table_name.clone()).into()
+                    })
+            })
+            .collect(),
+        _ => Ok(columns),
+    }
+}
+
+pub fn map_schema(id: u32, columns: &[ColumnDefinition]) -> Schema {
+    let mut primary_key: Vec<(usize, usize)> = columns
+        .iter()
+        .enumerate()
+        .filter(|(_, c)| c.pk > 0)
+        .map(|(idx, c)| (c.pk, idx))
+        .collect();
+    primary_key.sort();
+
+    Schema {
+        identifier: Some(SchemaIdentifier { id, version: 1 }),
+        fields: columns
+            .iter()
+            .map(|c| {
+                FieldDefinition::new(c.name.clone(), c.typ, c.nullable, SourceDefinition::Dynamic)
+            })
+            .collect(),
+        primary_index: primary_key.into_iter().map(|(_, idx)| idx).collect(),
+    }
+}
@@ -0,0 +1,248 @@
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use crate::connectors::sqlite::connector::SqliteConnector;
+use crate::connectors::sqlite::schema_helper::map_affinity;
+use crate::connectors::Connector;
+use crate::ingestion::{IngestionConfig, IngestionIterator, Ingestor};
+use dozer_types::chrono::NaiveDate;
+use dozer_types::ingestion_types::{IngestionMessageKind, SqliteConfig};
+use dozer_types::ordered_float::OrderedFloat;
+use dozer_types::rust_decimal::Decimal;
+use dozer_types::types::{Field, FieldType, Operation, ReplicationChangesTrackingType};
+use rusqlite::Connection;
+use tempdir::TempDir;
+
+fn create_database(dir: &TempDir) -> (String, Connection) {
+    let path = dir.path().join("test.db").to_str().unwrap().to_string();
+    let conn = Connection::open(&path).unwrap();
+    conn.execute_batch(
+        "CREATE TABLE users (
+            id INTEGER PRIMARY KEY,
+            name VARCHAR(50) NOT NULL,
+            balance DECIMAL(10, 2),
+            score REAL,
+            active BOOLEAN,
+            born DATE,
+            avatar BLOB,
+            updated_at DATETIME
+        );
+        INSERT INTO users VALUES
+            (1, 'alice', 10.5, 1.5, 1, '1990-01-02', x'0102', '2023-01-01 10:00:00'),
+            (2, 'bob', NULL, NULL, 0, NULL, NULL, '2023-01-01 11:00:00');",
+    )
+    .unwrap();
+    (path, conn)
+}
+
+fn config(path: String, change_detection: &str) -> SqliteConfig {
+    SqliteConfig {
+        path,
+        change_detection: change_detection.to_string(),
+        updated_at_column: Some("updated_at".to_string()),
+        poll_interval_ms: 10,
+    }
+}
+
+fn start(config: SqliteConfig) -> IngestionIterator {
+    let (ingestor, iterator) = Ingestor::initialize_channel(IngestionConfig::default());
+    thread::spawn(move || {
+        let connector = SqliteConnector::new(1, "sqlite".to_string(), config);
+        let tables = connector.get_tables(None).unwrap();
+        connector.start(None, &ingestor, tables).unwrap();
+    });
+    iterator
+}
+
+fn next_op(iterator: &mut IngestionIterator) -> ((u64, u64), Operation) {
+    let msg = iterator
+        .next_timeout(Duration::from_secs(5))
+        .expect("expected an operation");
+    let IngestionMessageKind::OperationEvent(op) = msg.kind else {
+        panic!("expected an operation");
+    };
+    ((msg.identifier.txid, msg.identifier.seq_in_tx), op)
+}
+
+fn id_of(op: &Operation) -> Field {
+    match op {
+        Operation::Insert { new } | Operation::Update { new, .. } => new.values[0].clone(),
+        Operation::Delete { old } => old.values[0].clone(),
+    }
+}
+
+#[test]
+fn test_it_maps_affinities() {
+    assert_eq!(map_affinity("INTEGER"), FieldType::Int);
+    assert_eq!(map_affinity("varchar(10)"), FieldType::String);
+    assert_eq!(map_affinity(""), FieldType::Binary);
+    assert_eq!(map_affinity("DOUBLE PRECISION"), FieldType::Float);
+    assert_eq!(map_affinity("BOOLEAN"), FieldType::Boolean);
+    assert_eq!(map_affinity("DATETIME"), FieldType::Timestamp);
+    assert_eq!(map_affinity("DATE"), FieldType::Date);
+    assert_eq!(map_affinity("NUMERIC(10, 2)"), FieldType::Decimal);
+}
+
+#[test]
+fn test_it_gets_schemas() {
+    let dir = TempDir::new("sqlite").unwrap();
+    let (path, _conn) = create_database(&dir);
+
+    let connector = SqliteConnector::new(1, "sqlite".to_string(), config(path, "rowid"));
+    let schemas = connector.get_schemas(None).unwrap();
+    assert_eq!(schemas.len(), 1);
+    assert_eq!(schemas[0].name, "users");
+    assert_eq!(schemas[0].schema.primary_index, vec![0]);
+    assert!(matches!(
+        schemas[0].replication_type,
+        ReplicationChangesTrackingType::Nothing
+    ));
+    let types: Vec<FieldType> = schemas[0].schema.fields.iter().map(|f| f.typ).collect();
+    assert_eq!(
+        types,
+        vec![
+            FieldType::Int,
+            FieldType::String,
+            FieldType::Decimal,
+            FieldType::Float,
+            FieldType::Boolean,
+            FieldType::Date,
+            FieldType::Binary,
+            FieldType::Timestamp
+        ]
+    );
+}
+
+#[test]
+fn test_it_polls_new_rows() {
+    let dir = TempDir::new("sqlite").unwrap();
+    let (path, conn) = create_database(&dir);
+    let mut iterator = start(config(path, "rowid"));
+
+    let (_, op) = next_op(&mut iterator);
+    let Operation::Insert { new } = op else {
+        panic!("expected an insert");
+    };
+    assert_eq!(
+        new.values[..7],
+        [
+            Field::Int(1),
+            Field::String("alice".to_string()),
+            Field::Decimal(Decimal::new(105, 1)),
+            Field::Float(OrderedFloat(1.5)),
+            Field::Boolean(true),
+            Field::Date(NaiveDate::from_ymd_opt(1990, 1, 2).unwrap()),
+            Field::Binary(vec![1, 2]),
+        ]
+    );
+    assert_eq!(id_of(&next_op(&mut iterator).1), Field::Int(2));
+
+    conn.execute("INSERT INTO users (id, name) VALUES (3, 'carol')", [])
+        .unwrap();
+    assert_eq!(id_of(&next_op(&mut iterator).1), Field::Int(3));
+}
+
+#[test]
+fn test_it_polls_updated_rows() {
+    let dir = TempDir::new("sqlite").unwrap();
+    let (path, conn) = create_database(&dir);
+    let mut iterator = start(config(path, "updated_at"));
+
+    assert!(matches!(next_op(&mut iterator).1, Operation::Insert { .. }));
+    assert!(matches!(next_op(&mut iterator).1, Operation::Insert { .. }));
+
+    conn.execute(
+        "UPDATE users SET name = 'alicia', updated_at = '2023-01-02 10:00:00' WHERE id = 1",
+        [],
+    )
+    .unwrap();
+    let Operation::Update { old, new } = next_op(&mut iterator).1 else {
+        panic!("expected an update");
+    };
+    assert_eq!(old.values[1], Field::String("alice".to_string()));
+    assert_eq!(new.values[1], Field::String("alicia".to_string()));
+}
+
+#[test]
+fn test_it_reads_change_log() {
+    let dir = TempDir::new("sqlite").unwrap();
+    let (path, conn) = create_database(&dir);
+    let mut iterator = start(config(path.clone(), "triggers"));
+
+    // Snapshot
+    assert_eq!(next_op(&mut iterator).0, (0, 0));
+    assert_eq!(next_op(&mut iterator).0, (0, 1));
+
+    conn.execute_batch(
+        "INSERT INTO users (id, name, avatar) VALUES (3, 'carol', x'ff00');
+        UPDATE users SET name = 'bobby' WHERE id = 2;
+        DELETE FROM users WHERE id = 1;",
+    )
+    .unwrap();
+
+    let (identifier, op) = next_op(&mut iterator);
+    assert_eq!(identifier, (1, 0));
+    let Operation::Insert { new } = op else {
+        panic!("expected an insert");
+    };
+    assert_eq!(new.values[6], Field::Binary(vec![255, 0]));
+
+    let (identifier, op) = next_op(&mut iterator);
+    assert_eq!(identifier, (2, 0));
+    let Operation::Update { old, new } = op else {
+        panic!("expected an update");
+    };
+    assert_eq!(old.values[1], Field::String("bob".to_string()));
+    assert_eq!(new.values[1], Field::String("bobby".to_string()));
+
+    let (identifier, op) = next_op(&mut iterator);
+    assert_eq!(identifier, (3, 0));
+    assert!(matches!(op, Operation::Delete { .. }));
+    assert_eq!(id_of(&op), Field::Int(1));
+
+    let connector = SqliteConnector::new(1, "sqlite".to_string(), config(path, "triggers"));
+    assert!(connector.can_start_from((3, 0)).unwrap());
+    assert!(!connector.can_start_from((0, 1)).unwrap());
+}
+
+#[test]
+fn test_it_trims_change_log_on_commit() {
+    let dir = TempDir::new("sqlite").unwrap();
+    let (path, conn) = create_database(&dir);
+    let connector = Arc::new(SqliteConnector::new(
+        1,
+        "sqlite".to_string(),
+        config(path, "triggers"),
+    ));
+    let (ingestor, mut iterator) = Ingestor::initialize_channel(IngestionConfig::default());
+    let reader = connector.clone();
+    thread::spawn(move || {
+        let tables = reader.get_tables(None).unwrap();
+        reader.start(None, &ingestor, tables).unwrap();
+    });
+    next_op(&mut iterator);
+    next_op(&mut iterator);
+
+    conn.execute_batch(
+        "UPDATE users SET name = 'alicia' WHERE id = 1;
+        UPDATE users SET name = 'bobby' WHERE id = 2;",
+    )
+    .unwrap();
+    assert_eq!(next_op(&mut iterator).0, (1, 0));
+    assert_eq!(next_op(&mut iterator).0, (2, 0));
+
+    // Committing only records the checkpoint, the entries are removed by the next poll.
+    connector.commit((1, 0)).unwrap();
+    let first_entry = || -> i64 {
+        conn.query_row("SELECT MIN(id) FROM _dozer_changelog", [], |row| row.get(0))
+            .unwrap()
+    };
+    let mut attempts = 0;
+    while first_entry() == 1 {
+        attempts += 1;
+        assert!(attempts < 500, "the change log was not trimmed");
+        thread::sleep(Duration::from_millis(10));
+    }
+    assert_eq!(first_entry(), 2);
+}
@@ -0,0 +1,143 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+
+use actix_web::{web, App, HttpServer};
+use dozer_types::ingestion_types::WebhookConfig;
+use dozer_types::log::info;
+use dozer_types::types::SourceSchema;
+
+use super::handler::{self, WebhookState};
+use super::sequencer::Sequencer;
+use crate::connectors::grpc::connector::read_schemas;
+use crate::connectors::grpc::{DefaultAdapter, GrpcIngestor};
+use crate::connectors::ValidationResults;
+use crate::errors::WebhookError;
+use crate::{
+    connectors::{Connector, TableInfo},
+    errors::ConnectorError,
+    ingestion::Ingestor,
+};
+
+/// Maximum size of a request body.
+const MAX_BODY_SIZE: usize = 16 * 1024 * 1024;
+
+#[derive(Debug)]
+pub struct WebhookConnector {
+    pub id: u64,
+    pub name: String,
+    pub config: WebhookConfig,
+    sequencer: Arc<Sequencer>,
+}
+
+impl WebhookConnector {
+    pub fn new(id: u64, name: String, config: WebhookConfig) -> Self {
+        Self {
+            id,
+            name,
+            config,
+            sequencer: Arc::new(Sequencer::default()),
+        }
+    }
+
+    /// Schemas are declared the same way as for the gRPC connector.
+    fn get_ingestor(&self) -> Result<GrpcIngestor<DefaultAdapter>, ConnectorError> {
+        let schemas_str = read_schemas(self.config.schemas.as_ref())?;
+        GrpcIngestor::<DefaultAdapter>::new(schemas_str)
+    }
+
+    pub fn serve(
+        &self,
+        from_seq: Option<(u64, u64)>,
+        ingestor: &Ingestor,
+    ) -> Result<(), ConnectorError> {
+        let address = format!("{}:{}", self.config.host, self.config.port);
+        let schema_map = self.get_ingestor()?.schema_map;
+        self.sequencer.open(
+            self.config.journal_path.as_deref().map(Path::new),
+            from_seq,
+            ingestor,
+        )?;
+
+        // Ingestor will live as long as the server
+        let ingestor = unsafe { std::mem::transmute::<&'_ Ingestor, &'static Ingestor>(ingestor) };
+        let state = web::Data::new(WebhookState {
+            schema_map,
+            ingestor,
+            max_batch_size: self.config.max_batch_size,
+            sequencer: self.sequencer.clone(),
+        });
+
+        let rt = tokio::runtime::Runtime::new().expect("Failed to initialize tokio runtime");
+        rt.block_on(async {
+            let server = HttpServer::new(move || {
+                App::new()
+                    .app_data(state.clone())
+                    .app_data(web::JsonConfig::default().limit(MAX_BODY_SIZE))
+                    .route("/ingest/{table}", web::post().to(handler::ingest))
+            })
+            .bind(&address)
+            .map_err(|e| WebhookError::BindFailed(address.clone(), e))?;
+
+            info!("Starting Dozer Webhook Ingestor on http://{}", address);
+            server.run().await.map_err(WebhookError::ServerFailed)
+        })
+        .map_err(ConnectorError::WebhookError)
+    }
+}
+
+impl Connector for WebhookConnector {
+    fn get_schemas(
+        &self,
+        table_names: Option<Vec<TableInfo>>,
+    ) -> Result<Vec<SourceSchema>, ConnectorError> {
+        let schemas = self.get_ingestor()?.get_schemas()?;
+        let schemas = table_names.map_or(schemas.clone(), |names| {
+            schemas
+                .into_iter()
+                .filter(|s| names.iter().any(|n| n.name == s.name))
+                .collect()
+        });
+        Ok(schemas)
+    }
+
+    fn start(
+        &self,
+        from_seq: Option<(u64, u64)>,
+        ingestor: &Ingestor,
+        _table_names: Vec<TableInfo>,
+    ) -> Result<(), ConnectorError> {
+        self.serve(from_seq, ingestor)
+    }
+
+    fn commit(&self, checkpoint: (u64, u64)) -> Result<(), ConnectorError> {
+        self.sequencer.commit(checkpoint);
+        Ok(())
+    }
+
+    fn validate(&self, table_names: Option<Vec<TableInfo>>) -> Result<(), ConnectorError> {
+        self.get_schemas(table_names).map(|_| ())
+    }
+
+    fn validate_schemas(&self, tables: &[TableInfo]) -> Result<ValidationResults, ConnectorError> {
+        let schema_map = self.get_ingestor()?.schema_map;
+        let mut results = HashMap::new();
+        for table in tables {
+            let r = schema_map.get(&table.name).map_or(
+                Err(ConnectorError::TableNotFound(table.name.clone())),
+                |_| Ok(()),
+            );
+            results.insert(table.name.clone(), vec![(None, r)]);
+        }
+        Ok(results)
+    }
+
+    fn get_tables(&self, tables: Option<&[TableInfo]>) -> Result<Vec<TableInfo>, ConnectorError> {
+        self.get_tables_default(tables)
+    }
+
+    fn can_start_from(&self, _last_checkpoint: (u64, u64)) -> Result<bool, ConnectorError> {
+        // Records which were not committed can only be ingested again from the journal.
+        Ok(self.config.journal_path.is_some())
+    }
+}
@@ -0,0 +1,120 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpRequest, HttpResponse, ResponseError};
+use dozer_types::json_value_to_field;
+use dozer_types::serde_json::{json, Map, Value};
+use dozer_types::types::{Field, Operation, Record, Schema};
+
+use super::sequencer::Sequencer;
+use crate::errors::{ConnectorError, WebhookError};
+use crate::ingestion::Ingestor;
+
+/// Header carrying the key which makes a request idempotent.
+pub const IDEMPOTENCY_KEY_HEADER: &str = "Idempotency-Key";
+
+pub struct WebhookState {
+    pub schema_map: &'static HashMap<String, Schema>,
+    pub ingestor: &'static Ingestor,
+    pub max_batch_size: u64,
+    pub sequencer: Arc<Sequencer>,
+}
+
+impl ResponseError for ConnectorError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            ConnectorError::WebhookError(WebhookError::TableNotFound(_)) => StatusCode::NOT_FOUND,
+            ConnectorError::WebhookError(WebhookError::BatchTooLarge(_, _)) => {
+                StatusCode::PAYLOAD_TOO_LARGE
+            }
+            ConnectorError::WebhookError(WebhookError::HandlerFailed(_)) => {
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
+            ConnectorError::WebhookError(_) => StatusCode::BAD_REQUEST,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        HttpResponse::build(self.status_code()).json(json!({ "error": self.to_string() }))
+    }
+}
+
+/// Ingests the records posted to `/ingest/{table}`, either a single JSON object or an array
+/// of objects inserted as one batch.
+pub async fn ingest(
+    state: web::Data<WebhookState>,
+    table: web::Path<String>,
+    request: HttpRequest,
+    body: web::Json<Value>,
+) -> Result<HttpResponse, ConnectorError> {
+    let table = table.into_inner();
+    let schema = state
+        .schema_map
+        .get(&table)
+        .ok_or(WebhookError::TableNotFound(table))?;
+
+    let records = map_records(schema, body.into_inner())?;
+    if records.len() as u64 > state.max_batch_size {
+        return Err(WebhookError::BatchTooLarge(records.len(), state.max_batch_size).into());
+    }
+
+    let key = request
+        .headers()
+        .get(IDEMPOTENCY_KEY_HEADER)
+        .and_then(|key| key.to_str().ok())
+        .map(str::to_string);
+
+    // Requests are journaled and sent to the pipeline synchronously, so this runs on a blocking
+    // thread rather than on the worker.
+    let count = records.len();
+    let operations = records
+        .into_iter()
+        .map(|record| Operation::Insert { new: record })
+        .collect();
+    let (seq_no, duplicate) =
+        web::block(move || state.sequencer.ingest(key, operations, state.ingestor))
+            .await
+            .map_err(WebhookError::HandlerFailed)??;
+
+    Ok(HttpResponse::Ok().json(json!({
+        "seq_no": seq_no,
+        "count": count,
+        "duplicate": duplicate,
+    })))
+}
+
+/// Validates a request body against the table schema.
+pub fn map_records(schema: &Schema, body: Value) -> Result<Vec<Record>, WebhookError> {
+    match body {
+        Value::Object(object) => Ok(vec![map_record(schema, object)?]),
+        Value::Array(values) => values
+            .into_iter()
+            .map(|value| match value {
+                Value::Object(object) => map_record(schema, object),
+                _ => Err(WebhookError::InvalidBody),
+            })
+            .collect(),
+        _ => Err(WebhookError::InvalidBody),
+    }
+}
+
+fn map_record(schema: &Schema, mut object: Map<String, Value>) -> Result<Record, WebhookError> {
+    let mut values = Vec::with_capacity(schema.fields.len());
+    for field in &schema.fields {
+        let value = match object.remove(&field.name) {
+            Some(value) => json_value_to_field(value, field.typ, field.nullable)
+                .map_err(|e| WebhookError::InvalidFieldValue(field.name.clone(), e))?,
+            None if field.nullable => Field::Null,
+            None => return Err(WebhookError::MissingField(field.name.clone())),
+        };
+        values.push(value);
+    }
+
+    if let Some(name) = object.keys().next() {
+        return Err(WebhookError::UnknownField(name.clone()));
+    }
+
+    Ok(Record::new(schema.identifier, values, None))
+}
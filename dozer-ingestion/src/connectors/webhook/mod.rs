@@ -0,0 +1,8 @@
+pub mod connector;
+mod handler;
+mod sequencer;
+
+pub use handler::IDEMPOTENCY_KEY_HEADER;
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,263 @@
+use std::collections::{HashMap, VecDeque};
+use std::path::Path;
+
+use dozer_types::ingestion_types::IngestionMessage;
+use dozer_types::log::info;
+use dozer_types::parking_lot::Mutex;
+use dozer_types::serde::{self, Deserialize, Serialize};
+use dozer_types::types::Operation;
+
+use crate::connectors::journal::Journal;
+use crate::errors::ConnectorError;
+use crate::ingestion::Ingestor;
+
+/// Number of idempotency keys remembered, older keys are forgotten first.
+pub const MAX_IDEMPOTENCY_KEYS: usize = 10_000;
+
+/// Requests appended to the journal before it is compacted, unless its last compaction left
+/// more entries.
+const MIN_REQUESTS_BEFORE_COMPACTION: usize = 1000;
+
+/// A request accepted by the webhook, whose records were given sequence numbers from `seq_no`.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[serde(crate = "self::serde")]
+pub struct JournalEntry {
+    pub seq_no: u64,
+    pub key: Option<String>,
+    pub operations: Vec<Operation>,
+}
+
+impl JournalEntry {
+    /// Sequence number following the records of the request.
+    fn end(&self) -> u64 {
+        self.seq_no + self.operations.len() as u64
+    }
+}
+
+/// Assigns the sequence numbers of the ingested records, and remembers the sequence number
+/// given to the requests with an idempotency key.
+///
+/// Records are sent in one transaction, and identified by their sequence number in it. With a
+/// journal, requests are persisted before being acknowledged, and the ones the pipeline had not
+/// committed are ingested again on restart. The journal is compacted to the committed
+/// checkpoint, only keeping the idempotency keys of the requests.
+#[derive(Debug, Default)]
+pub struct Sequencer {
+    state: Mutex<SequencerState>,
+    // Last committed checkpoint, which is only recorded so that committing never waits for a
+    // request to be ingested. The journal is compacted to it by the next request.
+    committed: Mutex<Option<(u64, u64)>>,
+}
+
+#[derive(Debug, Default)]
+struct SequencerState {
+    next_seq_no: u64,
+    // Sequence number of the requests with an idempotency key, by key
+    keys: HashMap<String, u64>,
+    key_order: VecDeque<String>,
+    journal: Option<Journal<JournalEntry>>,
+    // Checkpoint the journal was last compacted at
+    compacted_at: Option<(u64, u64)>,
+    // Entries left by the last compaction
+    num_compacted: usize,
+    // Requests appended since the last compaction
+    num_appended: usize,
+}
+
+impl Sequencer {
+    /// Opens the journal at `journal_path` if any, ingesting the records following
+    /// `last_checkpoint`. The entries committed by the pipeline are removed, only keeping the
+    /// idempotency keys of their requests.
+    pub fn open(
+        &self,
+        journal_path: Option<&Path>,
+        last_checkpoint: Option<(u64, u64)>,
+        ingestor: &Ingestor,
+    ) -> Result<(), ConnectorError> {
+        let mut state = self.state.lock();
+        *state = SequencerState {
+            next_seq_no: last_checkpoint.map_or(0, |(_, seq_no)| seq_no + 1),
+            ..SequencerState::default()
+        };
+        *self.committed.lock() = None;
+        let Some(path) = journal_path else {
+            return Ok(());
+        };
+        let (mut journal, entries) = Journal::open(path)?;
+
+        for entry in &entries {
+            if let Some(key) = &entry.key {
+                state.remember(key.clone(), entry.seq_no);
+            }
+            state.next_seq_no = state.next_seq_no.max(entry.end());
+        }
+
+        let (mut compacted, pending) = split(entries, last_checkpoint);
+        state.compacted_at = last_checkpoint;
+        state.num_compacted = compacted.len();
+        state.num_appended = pending.len();
+        compacted.extend(pending.iter().cloned());
+        journal.compact(&compacted)?;
+        state.journal = Some(journal);
+
+        info!(
+            "Replaying {} requests from webhook journal {}",
+            pending.len(),
+            path.display()
+        );
+        for entry in pending {
+            let skip = last_checkpoint.map_or(0, |(_, seq_no)| {
+                (seq_no + 1).saturating_sub(entry.seq_no) as usize
+            });
+            send(entry.seq_no, entry.operations, skip, ingestor)?;
+        }
+        Ok(())
+    }
+
+    /// Records that the messages up to `checkpoint` were committed.
+    pub fn commit(&self, checkpoint: (u64, u64)) {
+        *self.committed.lock() = Some(checkpoint);
+    }
+
+    /// Returns the sequence number of the first record of a previous request with `key`.
+    pub fn get(&self, key: &str) -> Option<u64> {
+        self.state.lock().keys.get(key).copied()
+    }
+
+    /// Ingests the records of a request, which is persisted first if there is a journal.
+    /// Returns the sequence number of its first record, and whether a request with the same
+    /// key was already acknowledged, in which case nothing is ingested.
+    ///
+    /// Sequence numbers are only given to the request once its records are sent, and the
+    /// request is removed from the journal if sending fails.
+    pub fn ingest(
+        &self,
+        key: Option<String>,
+        operations: Vec<Operation>,
+        ingestor: &Ingestor,
+    ) -> Result<(u64, bool), ConnectorError> {
+        let committed = *self.committed.lock();
+        let mut state = self.state.lock();
+        if let Some(seq_no) = key.as_ref().and_then(|key| state.keys.get(key)) {
+            return Ok((*seq_no, true));
+        }
+        if let Some(checkpoint) = committed {
+            state.compact(checkpoint)?;
+        }
+
+        let seq_no = state.next_seq_no;
+        let count = operations.len() as u64;
+        if let Some(journal) = &mut state.journal {
+            journal.append(&JournalEntry {
+                seq_no,
+                key: key.clone(),
+                operations: operations.clone(),
+            })?;
+            state.num_appended += 1;
+        }
+
+        // Sending under the lock keeps the records in order.
+        if let Err(e) = send(seq_no, operations, 0, ingestor) {
+            state.release()?;
+            return Err(e);
+        }
+        state.next_seq_no += count;
+        if let Some(key) = key {
+            state.remember(key, seq_no);
+        }
+        Ok((seq_no, false))
+    }
+}
+
+impl SequencerState {
+    /// Remembers the sequence number of a request with an idempotency key, forgetting the
+    /// oldest key over `MAX_IDEMPOTENCY_KEYS`.
+    fn remember(&mut self, key: String, seq_no: u64) {
+        if self.keys.insert(key.clone(), seq_no).is_none() {
+            self.key_order.push_back(key);
+        }
+        if self.key_order.len() > MAX_IDEMPOTENCY_KEYS {
+            if let Some(oldest) = self.key_order.pop_front() {
+                self.keys.remove(&oldest);
+            }
+        }
+    }
+
+    /// Removes the last request from the journal, as it was not ingested.
+    fn release(&mut self) -> Result<(), ConnectorError> {
+        let Some(journal) = &mut self.journal else {
+            return Ok(());
+        };
+        let mut entries = Journal::<JournalEntry>::read(journal.path())?;
+        entries.pop();
+        journal.compact(&entries)?;
+        self.num_appended = self.num_appended.saturating_sub(1);
+        Ok(())
+    }
+
+    /// Compacts the journal to `checkpoint`, keeping the requests following it. Compaction
+    /// waits for as many requests as the last one left entries.
+    fn compact(&mut self, checkpoint: (u64, u64)) -> Result<(), ConnectorError> {
+        let Some(journal) = &mut self.journal else {
+            return Ok(());
+        };
+        if self.compacted_at == Some(checkpoint)
+            || self.num_appended < self.num_compacted.max(MIN_REQUESTS_BEFORE_COMPACTION)
+        {
+            return Ok(());
+        }
+
+        let (mut compacted, pending) = split(Journal::read(journal.path())?, Some(checkpoint));
+        self.compacted_at = Some(checkpoint);
+        self.num_compacted = compacted.len();
+        self.num_appended = pending.len();
+        compacted.extend(pending);
+        journal.compact(&compacted)?;
+        Ok(())
+    }
+}
+
+/// Splits journal entries at `checkpoint`, returning the keys of the last committed requests
+/// which are remembered, and the requests following the checkpoint.
+fn split(
+    entries: Vec<JournalEntry>,
+    checkpoint: Option<(u64, u64)>,
+) -> (Vec<JournalEntry>, Vec<JournalEntry>) {
+    let (committed, pending): (Vec<_>, Vec<_>) = match checkpoint {
+        Some((_, seq_no)) => entries.into_iter().partition(|e| e.end() <= seq_no + 1),
+        None => (vec![], entries),
+    };
+
+    let keys = committed
+        .into_iter()
+        .filter(|e| e.key.is_some())
+        .map(|e| JournalEntry {
+            operations: vec![],
+            ..e
+        })
+        .collect::<Vec<_>>();
+    let num_pending_keys = pending.iter().filter(|e| e.key.is_some()).count();
+    let skip = (keys.len() + num_pending_keys).saturating_sub(MAX_IDEMPOTENCY_KEYS);
+    let compacted = keys.into_iter().skip(skip).collect();
+    (compacted, pending)
+}
+
+fn send(
+    seq_no: u64,
+    operations: Vec<Operation>,
+    skip: usize,
+    ingestor: &Ingestor,
+) -> Result<(), ConnectorError> {
+    let messages = operations
+        .into_iter()
+        .enumerate()
+        .skip(skip)
+        .map(|(idx, op)| IngestionMessage::new_op(0, seq_no + idx as u64, op))
+        .collect::<Vec<_>>();
+    if messages.is_empty() {
+        return Ok(());
+    }
+    ingestor
+        .handle_batch(messages)
+        .map_err(ConnectorError::IngestorError)
+}
@@ -0,0 +1,322 @@
+use std::thread;
+use std::time::Duration;
+
+use crate::connectors::journal::Journal;
+use crate::connectors::webhook::handler::map_records;
+use crate::connectors::webhook::sequencer::{JournalEntry, Sequencer};
+use crate::connectors::webhook::IDEMPOTENCY_KEY_HEADER;
+use crate::errors::WebhookError;
+use crate::ingestion::{IngestionConfig, IngestionIterator, Ingestor};
+use dozer_orchestrator::Connection;
+use dozer_types::ingestion_types::{GrpcConfigSchemas, IngestionMessageKind, WebhookConfig};
+use dozer_types::models::connection::ConnectionConfig;
+use dozer_types::serde_json::{self, json, Value};
+use dozer_types::types::{
+    Field, FieldDefinition, FieldType, Operation, Record, Schema, SourceDefinition,
+};
+use tempdir::TempDir;
+
+fn users_schemas() -> Value {
+    json!([{
+      "name": "users",
+      "schema": {
+        "fields": [
+          {
+            "name": "id",
+            "typ": "Int",
+            "nullable": false
+          },
+          {
+            "name": "name",
+            "typ": "String",
+            "nullable": true
+          }
+        ]
+      }
+    }])
+}
+
+fn ingest_webhook(port: u32) -> (reqwest::blocking::Client, String, IngestionIterator) {
+    let (ingestor, iterator) = Ingestor::initialize_channel(IngestionConfig::default());
+
+    thread::spawn(move || {
+        let connector = crate::connectors::get_connector(Connection {
+            config: Some(ConnectionConfig::Webhook(WebhookConfig {
+                port,
+                schemas: Some(GrpcConfigSchemas::Inline(users_schemas().to_string())),
+                max_batch_size: 2,
+                ..Default::default()
+            })),
+            name: "webhook".to_string(),
+        })
+        .unwrap();
+
+        let tables = connector.get_tables(None).unwrap();
+        connector.start(None, &ingestor, tables).unwrap();
+    });
+
+    let client = reqwest::blocking::Client::new();
+    let url = format!("http://127.0.0.1:{port}/ingest");
+    let retries = 10;
+    for r in 0..retries {
+        if client.post(format!("{url}/users")).send().is_ok() {
+            break;
+        }
+        if r == retries - 1 {
+            panic!("failed to connect after {r} times");
+        }
+        thread::sleep(Duration::from_millis(300));
+    }
+
+    (client, url, iterator)
+}
+
+fn post(
+    client: &reqwest::blocking::Client,
+    url: &str,
+    body: Value,
+    key: Option<&str>,
+) -> (u16, Value) {
+    let mut request = client
+        .post(url)
+        .header("Content-Type", "application/json")
+        .body(body.to_string());
+    if let Some(key) = key {
+        request = request.header(IDEMPOTENCY_KEY_HEADER, key);
+    }
+    let response = request.send().unwrap();
+    let status = response.status().as_u16();
+    (
+        status,
+        serde_json::from_str(&response.text().unwrap()).unwrap(),
+    )
+}
+
+#[test]
+fn ingest_webhook_batches() {
+    let (client, url, mut iterator) = ingest_webhook(45690);
+    let users_url = format!("{url}/users");
+
+    let (status, body) = post(
+        &client,
+        &users_url,
+        json!([{"id": 1, "name": "dario"}, {"id": 2}]),
+        Some("batch-1"),
+    );
+    assert_eq!(status, 200);
+    assert_eq!(body, json!({"seq_no": 0, "count": 2, "duplicate": false}));
+
+    for (seq_no, values) in [
+        (0, vec![Field::Int(1), Field::String("dario".to_string())]),
+        (1, vec![Field::Int(2), Field::Null]),
+    ] {
+        let msg = iterator.next().unwrap();
+        assert_eq!(msg.identifier.seq_in_tx, seq_no);
+        if let IngestionMessageKind::OperationEvent(Operation::Insert { new }) = msg.kind {
+            assert_eq!(new.values, values);
+        } else {
+            panic!("wrong operation kind");
+        }
+    }
+
+    // A retried request is acknowledged without being ingested again.
+    let (status, body) = post(
+        &client,
+        &users_url,
+        json!([{"id": 1, "name": "dario"}, {"id": 2}]),
+        Some("batch-1"),
+    );
+    assert_eq!(status, 200);
+    assert_eq!(body, json!({"seq_no": 0, "count": 2, "duplicate": true}));
+
+    let (status, body) = post(&client, &users_url, json!({"id": 3}), None);
+    assert_eq!(status, 200);
+    assert_eq!(body["seq_no"], json!(2));
+    assert_eq!(iterator.next().unwrap().identifier.seq_in_tx, 2);
+}
+
+#[test]
+fn ingest_webhook_rejects_invalid_requests() {
+    let (client, url, _iterator) = ingest_webhook(45691);
+    let users_url = format!("{url}/users");
+
+    let (status, _) = post(&client, &format!("{url}/orders"), json!({"id": 1}), None);
+    assert_eq!(status, 404);
+
+    let (status, _) = post(&client, &users_url, json!({"id": "one"}), None);
+    assert_eq!(status, 400);
+
+    let (status, _) = post(
+        &client,
+        &users_url,
+        json!([{"id": 1}, {"id": 2}, {"id": 3}]),
+        None,
+    );
+    assert_eq!(status, 413);
+}
+
+#[test]
+fn test_it_validates_records_against_schema() {
+    let schema = Schema {
+        identifier: None,
+        fields: vec![
+            FieldDefinition::new(
+                "id".to_string(),
+                FieldType::UInt,
+                false,
+                SourceDefinition::Dynamic,
+            ),
+            FieldDefinition::new(
+                "amount".to_string(),
+                FieldType::Decimal,
+                true,
+                SourceDefinition::Dynamic,
+            ),
+        ],
+        primary_index: vec![0],
+    };
+
+    let records = map_records(&schema, json!({"amount": "10.5", "id": 1})).unwrap();
+    assert_eq!(records[0].values[0], Field::UInt(1));
+
+    assert!(matches!(
+        map_records(&schema, json!({"amount": "10.5"})),
+        Err(WebhookError::MissingField(name)) if name == "id"
+    ));
+    assert!(matches!(
+        map_records(&schema, json!({"id": 1, "price": 2})),
+        Err(WebhookError::UnknownField(name)) if name == "price"
+    ));
+    assert!(matches!(
+        map_records(&schema, json!({"id": -1})),
+        Err(WebhookError::InvalidFieldValue(name, _)) if name == "id"
+    ));
+    assert!(matches!(
+        map_records(&schema, json!([1, 2])),
+        Err(WebhookError::InvalidBody)
+    ));
+}
+
+fn inserts(ids: &[i64]) -> Vec<Operation> {
+    ids.iter()
+        .map(|id| Operation::Insert {
+            new: Record::new(None, vec![Field::Int(*id)], None),
+        })
+        .collect()
+}
+
+fn received(iterator: &mut IngestionIterator, count: usize) -> Vec<(u64, i64)> {
+    (0..count)
+        .map(|_| {
+            let msg = iterator.next().unwrap();
+            let IngestionMessageKind::OperationEvent(Operation::Insert { new }) = msg.kind else {
+                panic!("wrong operation kind");
+            };
+            (msg.identifier.seq_in_tx, new.values[0].as_int().unwrap())
+        })
+        .collect()
+}
+
+#[test]
+fn test_it_forgets_oldest_idempotency_keys() {
+    let (ingestor, _iterator) = Ingestor::initialize_channel(IngestionConfig::default());
+    let sequencer = Sequencer::default();
+    let ingest = |key: Option<&str>, ids: &[i64]| {
+        sequencer
+            .ingest(key.map(str::to_string), inserts(ids), &ingestor)
+            .unwrap()
+    };
+    assert_eq!(ingest(Some("first"), &[1, 2, 3]), (0, false));
+    assert_eq!(ingest(None, &[4]), (3, false));
+    assert_eq!(ingest(Some("first"), &[1, 2, 3]), (0, true));
+
+    for idx in 0..10_000 {
+        ingest(Some(&idx.to_string()), &[idx]);
+    }
+    assert_eq!(sequencer.get("first"), None);
+    assert_eq!(sequencer.get("9999"), Some(10_003));
+}
+
+#[test]
+fn test_it_replays_uncommitted_requests() {
+    let dir = TempDir::new("webhook_journal").unwrap();
+    let path = dir.path().join("journal");
+
+    let (ingestor, mut iterator) = Ingestor::initialize_channel(IngestionConfig::default());
+    let sequencer = Sequencer::default();
+    sequencer.open(Some(&path), None, &ingestor).unwrap();
+    sequencer
+        .ingest(Some("a".to_string()), inserts(&[1, 2]), &ingestor)
+        .unwrap();
+    sequencer.ingest(None, inserts(&[3]), &ingestor).unwrap();
+    received(&mut iterator, 3);
+    drop(sequencer);
+
+    // The pipeline committed the first record.
+    let (ingestor, mut iterator) = Ingestor::initialize_channel(IngestionConfig::default());
+    let sequencer = Sequencer::default();
+    sequencer
+        .open(Some(&path), Some((0, 0)), &ingestor)
+        .unwrap();
+    assert_eq!(received(&mut iterator, 2), vec![(1, 2), (2, 3)]);
+    assert_eq!(sequencer.get("a"), Some(0));
+    assert_eq!(
+        sequencer
+            .ingest(Some("a".to_string()), inserts(&[1, 2]), &ingestor)
+            .unwrap(),
+        (0, true)
+    );
+    assert_eq!(
+        sequencer.ingest(None, inserts(&[4]), &ingestor).unwrap(),
+        (3, false)
+    );
+    drop(sequencer);
+
+    // Committed requests are compacted, but their keys are still remembered.
+    let (ingestor, mut iterator) = Ingestor::initialize_channel(IngestionConfig::default());
+    let sequencer = Sequencer::default();
+    sequencer
+        .open(Some(&path), Some((0, 3)), &ingestor)
+        .unwrap();
+    let entries = Journal::<JournalEntry>::read(&path).unwrap();
+    assert_eq!(
+        entries,
+        vec![JournalEntry {
+            seq_no: 0,
+            key: Some("a".to_string()),
+            operations: vec![],
+        }]
+    );
+    assert_eq!(sequencer.get("a"), Some(0));
+    assert_eq!(
+        sequencer.ingest(None, inserts(&[5]), &ingestor).unwrap(),
+        (4, false)
+    );
+    assert_eq!(received(&mut iterator, 1), vec![(4, 5)]);
+}
+
+#[test]
+fn test_it_releases_failed_requests() {
+    let dir = TempDir::new("webhook_journal").unwrap();
+    let path = dir.path().join("journal");
+
+    let (ingestor, iterator) = Ingestor::initialize_channel(IngestionConfig::default());
+    drop(iterator);
+    let sequencer = Sequencer::default();
+    sequencer.open(Some(&path), None, &ingestor).unwrap();
+    assert!(sequencer
+        .ingest(Some("a".to_string()), inserts(&[1]), &ingestor)
+        .is_err());
+    assert_eq!(sequencer.get("a"), None);
+    assert!(Journal::<JournalEntry>::read(&path).unwrap().is_empty());
+
+    // The retried request is given the same sequence number.
+    let (ingestor, mut iterator) = Ingestor::initialize_channel(IngestionConfig::default());
+    assert_eq!(
+        sequencer
+            .ingest(Some("a".to_string()), inserts(&[1]), &ingestor)
+            .unwrap(),
+        (0, false)
+    );
+    assert_eq!(received(&mut iterator, 1), vec![(0, 1)]);
+}
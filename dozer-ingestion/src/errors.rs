@@ -4,19 +4,22 @@ use dozer_types::errors::internal::BoxedError;
 use dozer_types::errors::types::{SerializationError, TypeError};
 use dozer_types::ingestion_types::IngestorError;
 use dozer_types::thiserror::Error;
+use dozer_types::types::{Field, FieldType};
 use dozer_types::{bincode, serde_json};
 use dozer_types::{rust_decimal, thiserror};
 
+use actix_web::error::BlockingError;
 use base64::DecodeError;
 
 use datafusion::error::DataFusionError;
+use datafusion::parquet::errors::ParquetError;
 #[cfg(feature = "snowflake")]
 use std::num::TryFromIntError;
 use std::str::Utf8Error;
 use std::string::FromUtf8Error;
 
 use dozer_types::log::error;
-#[cfg(feature = "snowflake")]
+#[cfg(feature = "odbc")]
 use odbc::DiagnosticRecord;
 use schema_registry_converter::error::SRCError;
 use tokio_postgres::Error;
@@ -60,9 +63,24 @@ pub enum ConnectorError {
     #[error(transparent)]
     DebeziumError(#[from] DebeziumError),
 
+    #[error(transparent)]
+    KafkaError(#[from] KafkaError),
+
     #[error(transparent)]
     ObjectStoreConnectorError(#[from] ObjectStoreConnectorError),
 
+    #[error(transparent)]
+    WebhookError(#[from] WebhookError),
+
+    #[error(transparent)]
+    JournalError(#[from] JournalError),
+
+    #[error(transparent)]
+    SqliteError(#[from] SqliteError),
+
+    #[error(transparent)]
+    OdbcError(#[from] OdbcError),
+
     #[error(transparent)]
     TypeError(#[from] TypeError),
 
@@ -78,6 +96,15 @@ pub enum ConnectorError {
     #[error("Failed fetching after {0} recursions")]
     EthTooManyRecurisions(usize),
 
+    #[error("Eth block {0} not found")]
+    EthBlockNotFound(u64),
+
+    #[error("Eth receipt of transaction {0} not found")]
+    EthReceiptNotFound(String),
+
+    #[error("Eth {0} value {1} is out of range")]
+    EthValueOutOfRange(String, String),
+
     #[error("Received empty message in connector")]
     EmptyMessage,
 }
@@ -241,6 +268,57 @@ pub enum PostgresSchemaError {
     ColumnNotFound,
 }
 
+#[derive(Error, Debug)]
+pub enum OdbcError {
+    #[error("Connection {0} needs Dozer to be built with the odbc feature")]
+    NotEnabled(String),
+
+    #[error("Failed to create the ODBC environment")]
+    EnvironmentError,
+
+    #[cfg(feature = "odbc")]
+    #[error("Failed to connect to the ODBC data source")]
+    ConnectionError(#[source] Box<DiagnosticRecord>),
+
+    #[cfg(feature = "odbc")]
+    #[error("ODBC query error")]
+    QueryError(#[source] Box<DiagnosticRecord>),
+
+    #[cfg(feature = "odbc")]
+    #[error("Value conversion Error")]
+    ValueConversionError(#[source] Box<DiagnosticRecord>),
+
+    #[error("Column type {0} not supported")]
+    ColumnTypeNotSupported(String),
+
+    #[error("Invalid date")]
+    InvalidDateError,
+
+    #[error("Invalid time")]
+    InvalidTimeError,
+
+    #[error("Decimal convert error")]
+    DecimalConvertError(#[source] rust_decimal::Error),
+
+    #[error("Query {0} not found")]
+    QueryNotFound(String),
+
+    #[error("Column {0} not found in query {1}")]
+    ColumnNotFound(String, String),
+
+    #[error("Column {0} of type {1} can't be used as cursor")]
+    UnsupportedCursorType(String, FieldType),
+
+    #[error("Column {0} can't be used as cursor, found value {1}")]
+    InvalidCursorValue(String, Field),
+
+    #[error("Query {0} needs a primary key to detect soft deletes")]
+    SoftDeleteWithoutPrimaryKey(String),
+
+    #[error("Checkpoint {0:?} is not in the ODBC state journal")]
+    CheckpointNotFound((u64, u64)),
+}
+
 #[cfg(feature = "snowflake")]
 #[derive(Error, Debug)]
 pub enum SnowflakeError {
@@ -264,20 +342,8 @@ pub enum SnowflakeSchemaError {
     #[error("Column type {0} not supported")]
     ColumnTypeNotSupported(String),
 
-    #[error("Value conversion Error")]
-    ValueConversionError(#[source] Box<DiagnosticRecord>),
-
-    #[error("Invalid date")]
-    InvalidDateError,
-
-    #[error("Invalid time")]
-    InvalidTimeError,
-
     #[error("Schema conversion Error: {0}")]
     SchemaConversionError(#[source] TryFromIntError),
-
-    #[error("Decimal convert error")]
-    DecimalConvertError(#[source] rust_decimal::Error),
 }
 
 #[derive(Error, Debug)]
@@ -293,6 +359,15 @@ pub enum SnowflakeStreamError {
 
     #[error("Stream not found")]
     StreamNotFound,
+
+    #[error("Failed to read the current timestamp")]
+    CurrentTimestampError,
+
+    #[error("No cursor column configured for table {0}")]
+    CursorColumnNotConfigured(String),
+
+    #[error("Invalid batch {0} in stream log")]
+    InvalidBatchInStreamLog(Field),
 }
 
 #[derive(Error, Debug)]
@@ -331,6 +406,75 @@ pub enum DebeziumStreamError {
     PollingError(#[source] kafka::Error),
 }
 
+#[derive(Error, Debug)]
+pub enum KafkaError {
+    #[error("Unsupported \"{0}\" message format")]
+    UnsupportedFormat(String),
+
+    #[error("Schemas are not defined")]
+    SchemasNotDefined,
+
+    #[error("Failed to read schemas: {0}")]
+    SchemasReadError(#[source] std::io::Error),
+
+    #[error("Schema for topic {0} not found")]
+    SchemaNotFound(String),
+
+    #[error("Schema registry url is not defined")]
+    SchemaRegistryUrlNotDefined,
+
+    #[error("Schema registry fetch failed")]
+    SchemaRegistryFetchError(#[source] SRCError),
+
+    #[error("JSON decode error")]
+    JsonDecodeError(#[source] serde_json::Error),
+
+    #[error("Avro decode error")]
+    AvroDecodeError(#[source] SRCError),
+
+    #[error("Avro schema error")]
+    AvroSchemaError(#[source] apache_avro::Error),
+
+    #[error("Protobuf decode error")]
+    ProtobufDecodeError(#[source] prost::DecodeError),
+
+    #[error("Protobuf descriptor error")]
+    ProtobufDescriptorError(#[source] prost_reflect::DescriptorError),
+
+    #[error("Protobuf schema error: {0}")]
+    ProtobufSchemaError(String),
+
+    #[error("Failed to write protobuf schema")]
+    ProtobufSchemaWriteError(#[source] std::io::Error),
+
+    #[error("Message is not in schema registry wire format")]
+    InvalidWireFormat,
+
+    #[error("Unsupported \"{0}\" type")]
+    TypeNotSupported(String),
+
+    #[error("Value of field \"{0}\" does not match its type")]
+    FieldValueMismatch(String),
+
+    #[error(transparent)]
+    TypeError(#[from] TypeError),
+
+    #[error("Failed to fetch topic metadata")]
+    MetadataFetchError(#[source] kafka::Error),
+
+    #[error("Failed to commit partition offset")]
+    OffsetCommitError(#[source] kafka::Error),
+
+    #[error("Checkpoint {0:?} is not in the Kafka state journal")]
+    CheckpointNotFound((u64, u64)),
+
+    #[error("Failed to access the Kafka key store")]
+    KeyStoreError(#[source] rusqlite::Error),
+
+    #[error("Failed to create the Kafka key store directory")]
+    KeyStoreIoError(#[source] std::io::Error),
+}
+
 #[derive(Error, Debug, PartialEq)]
 pub enum DebeziumSchemaError {
     #[error("Schema definition not found")]
@@ -380,8 +524,17 @@ pub enum ObjectStoreConnectorError {
     #[error(transparent)]
     TableReaderError(#[from] ObjectStoreTableReaderError),
 
+    #[error(transparent)]
+    DeltaError(#[from] ObjectStoreDeltaError),
+
+    #[error(transparent)]
+    PartitionError(#[from] ObjectStorePartitionError),
+
     #[error(transparent)]
     IngestorError(#[from] IngestorError),
+
+    #[error("File {0} was overwritten after being ingested")]
+    FileOverwritten(String),
 }
 
 #[derive(Error, Debug, PartialEq)]
@@ -427,4 +580,121 @@ pub enum ObjectStoreTableReaderError {
 
     #[error("Stream execution failed")]
     StreamExecutionError(DataFusionError),
+
+    #[error("Files listing failed")]
+    FilesListingFailed(object_store::Error),
+}
+
+#[derive(Error, Debug)]
+pub enum ObjectStoreDeltaError {
+    #[error("Failed to read delta log: {0}")]
+    LogReadError(#[source] object_store::Error),
+
+    #[error("Failed to parse delta commit: {0}")]
+    CommitParseError(#[source] serde_json::Error),
+
+    #[error("Failed to parse delta checkpoint: {0}")]
+    CheckpointParseError(#[source] ParquetError),
+
+    #[error("File {0} removed by a delta commit was vacuumed, and its rows can't be deleted")]
+    RemovedFileVacuumed(String),
+
+    #[error("Delta table metadata not found")]
+    MetadataNotFound,
+
+    #[error("Partitioned delta tables are not supported")]
+    PartitionedTableNotSupported,
+
+    #[error("Unsupported \"{0}\" change type")]
+    ChangeTypeNotSupported(String),
+
+    #[error("Update post-image without pre-image")]
+    UnmatchedUpdatePostimage,
+
+    #[error(transparent)]
+    SchemaError(#[from] ObjectStoreSchemaError),
+}
+
+#[derive(Error, Debug)]
+pub enum ObjectStorePartitionError {
+    #[error("Invalid glob pattern: {0}")]
+    InvalidPattern(#[source] globset::Error),
+
+    #[error("Filter on unknown partition column \"{0}\"")]
+    UnknownPartitionColumn(String),
+
+    #[error("Unsupported partition filter operator \"{0}\"")]
+    FilterOperatorNotSupported(String),
+
+    #[error("Value \"{1}\" of partition column \"{0}\" does not match its type")]
+    PartitionValueParseError(String, String),
+}
+
+#[derive(Error, Debug)]
+pub enum JournalError {
+    #[error("Failed to access journal {0}")]
+    IoError(String, #[source] std::io::Error),
+
+    #[error("Failed to serialize journal entry")]
+    SerializationError(#[source] bincode::Error),
+
+    #[error("Corrupted entry in journal {0}")]
+    CorruptedEntry(String, #[source] bincode::Error),
+}
+
+#[derive(Error, Debug)]
+pub enum WebhookError {
+    #[error("Failed to bind webhook server to {0}")]
+    BindFailed(String, #[source] std::io::Error),
+
+    #[error("Webhook server failed")]
+    ServerFailed(#[source] std::io::Error),
+
+    #[error("Table not found: {0}")]
+    TableNotFound(String),
+
+    #[error("Request body must be a record object or an array of record objects")]
+    InvalidBody,
+
+    #[error("Batch of {0} records exceeds the maximum of {1}")]
+    BatchTooLarge(usize, u64),
+
+    #[error("Unknown field \"{0}\"")]
+    UnknownField(String),
+
+    #[error("Missing value for non nullable field \"{0}\"")]
+    MissingField(String),
+
+    #[error("Invalid value for field \"{0}\": {1}")]
+    InvalidFieldValue(String, #[source] TypeError),
+
+    #[error("Webhook request handler failed")]
+    HandlerFailed(#[source] BlockingError),
+}
+
+#[derive(Error, Debug)]
+pub enum SqliteError {
+    #[error("Failed to open database {0}")]
+    ConnectionFailed(String, #[source] rusqlite::Error),
+
+    #[error("Query failed: {0}")]
+    QueryError(#[source] rusqlite::Error),
+
+    #[error("Unsupported change detection \"{0}\", expected rowid, updated_at or triggers")]
+    UnsupportedChangeDetection(String),
+
+    #[error("updated_at_column is required by the updated_at change detection")]
+    UpdatedAtColumnMissing,
+
+    #[error("Column {0} not found in table {1}")]
+    ColumnNotFound(String, String),
+
+    #[error("Table {0} needs a primary key to ingest updates")]
+    PrimaryKeyRequired(String),
+
+    #[error("Value of column {0} does not match its type {1}")]
+    ValueConversionError(String, FieldType),
+
+    #[error("Failed to parse change log entry")]
+    ChangeLogParseError(#[source] serde_json::Error),
 }
@@ -1,91 +1,180 @@
-use crossbeam::channel::{bounded, Receiver};
+use crossbeam::channel::{unbounded, Receiver, Sender};
 use dozer_types::ingestion_types::{IngestionMessage, IngestorError, IngestorForwarder};
 use dozer_types::log::warn;
 use std::sync::Arc;
 use std::time::Duration;
 
-use super::IngestionConfig;
+use super::stats::count_operations;
+use super::{IngestionConfig, IngestionStats};
 
 #[derive(Debug)]
 pub struct ChannelForwarder {
-    pub sender: crossbeam::channel::Sender<IngestionMessage>,
+    pub sender: Sender<Vec<IngestionMessage>>,
+    pub stats: Arc<IngestionStats>,
+}
+
+impl ChannelForwarder {
+    /// Sends a batch as a single channel message, once the queue has room for its messages.
+    fn send(&self, messages: Vec<IngestionMessage>) -> Result<(), IngestorError> {
+        let count = messages.len() as u64;
+        let operations = count_operations(&messages);
+        self.stats.on_queued(count);
+        match self.sender.send(messages) {
+            Ok(()) => {
+                self.stats.on_sent(operations);
+                Ok(())
+            }
+            Err(e) => {
+                self.stats.on_dequeued(count);
+                Err(IngestorError::ChannelError(Box::new(e)))
+            }
+        }
+    }
 }
 
 impl IngestorForwarder for ChannelForwarder {
     fn forward(&self, msg: IngestionMessage) -> Result<(), IngestorError> {
-        self.sender
-            .send(msg)
-            .map_err(|e| IngestorError::ChannelError(Box::new(e)))
+        self.send(vec![msg])
+    }
+
+    fn forward_batch(&self, msgs: Vec<IngestionMessage>) -> Result<(), IngestorError> {
+        if msgs.is_empty() {
+            return Ok(());
+        }
+        self.send(msgs)
     }
 }
 #[derive(Debug)]
 /// `IngestionIterator` is the receiver side of a spsc channel. The sender side is `Ingestor`.
 pub struct IngestionIterator {
-    pub rx: Receiver<IngestionMessage>,
+    pub rx: Receiver<Vec<IngestionMessage>>,
+    stats: Arc<IngestionStats>,
+    // Messages of the last received batch
+    pending: std::vec::IntoIter<IngestionMessage>,
 }
 
 impl Iterator for IngestionIterator {
     type Item = IngestionMessage;
     fn next(&mut self) -> Option<Self::Item> {
-        let msg = self.rx.recv();
-        match msg {
-            Ok(msg) => Some(msg),
-            Err(e) => {
-                warn!("IngestionIterator: Error in receiving {:?}", e.to_string());
-                None
+        loop {
+            if let Some(msg) = self.pending.next() {
+                return Some(msg);
+            }
+            let msg = self.rx.recv();
+            match msg {
+                Ok(msgs) => self.receive(msgs),
+                Err(e) => {
+                    warn!("IngestionIterator: Error in receiving {:?}", e.to_string());
+                    return None;
+                }
             }
         }
     }
 }
 impl IngestionIterator {
     pub fn next_timeout(&mut self, timeout: Duration) -> Option<IngestionMessage> {
-        let msg = self.rx.recv_timeout(timeout);
-        match msg {
-            Ok(msg) => Some(msg),
-            Err(e) => {
-                warn!("IngestionIterator: Error in receiving {:?}", e.to_string());
-                None
+        loop {
+            if let Some(msg) = self.pending.next() {
+                return Some(msg);
+            }
+            let msg = self.rx.recv_timeout(timeout);
+            match msg {
+                Ok(msgs) => self.receive(msgs),
+                Err(e) => {
+                    warn!("IngestionIterator: Error in receiving {:?}", e.to_string());
+                    return None;
+                }
             }
         }
     }
+
+    fn receive(&mut self, msgs: Vec<IngestionMessage>) {
+        self.stats.on_dequeued(msgs.len() as u64);
+        self.pending = msgs.into_iter();
+    }
 }
 
-#[derive(Debug)]
+impl Drop for IngestionIterator {
+    fn drop(&mut self) {
+        self.stats.on_closed();
+    }
+}
+
+#[derive(Debug, Clone)]
 /// `Ingestor` is the sender side of a spsc channel. The receiver side is `IngestionIterator`.
 pub struct Ingestor {
     pub sender: Arc<Box<dyn IngestorForwarder>>,
+    pub stats: Arc<IngestionStats>,
 }
 
 impl Ingestor {
     pub fn initialize_channel(config: IngestionConfig) -> (Ingestor, IngestionIterator) {
-        let (tx, rx) = bounded(config.forwarder_channel_cap);
-        let sender: Arc<Box<dyn IngestorForwarder>> =
-            Arc::new(Box::new(ChannelForwarder { sender: tx }));
-        let ingestor = Self { sender };
+        // The channel is bounded by the stats, which count messages rather than batches.
+        let (tx, rx) = unbounded();
+        let stats = Arc::new(IngestionStats::new(
+            config.connection_name,
+            config.forwarder_channel_cap,
+        ));
+        let sender: Arc<Box<dyn IngestorForwarder>> = Arc::new(Box::new(ChannelForwarder {
+            sender: tx,
+            stats: stats.clone(),
+        }));
+        let ingestor = Self {
+            sender,
+            stats: stats.clone(),
+        };
 
-        let iterator = IngestionIterator { rx };
+        let iterator = IngestionIterator {
+            rx,
+            stats,
+            pending: vec![].into_iter(),
+        };
         (ingestor, iterator)
     }
 
     pub fn handle_message(&self, message: IngestionMessage) -> Result<(), IngestorError> {
         self.sender.forward(message)
     }
+
+    /// Sends messages at once, which costs a single channel message. Messages are received in
+    /// order.
+    pub fn handle_batch(&self, messages: Vec<IngestionMessage>) -> Result<(), IngestorError> {
+        self.sender.forward_batch(messages)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::{ChannelForwarder, Ingestor, IngestorForwarder};
+    use crate::ingestion::{IngestionConfig, IngestionStats};
     use crossbeam::channel::unbounded;
     use dozer_types::ingestion_types::{IngestionMessage, IngestionMessageKind};
-    use dozer_types::types::{Operation, Record};
+    use dozer_types::types::{Operation, Record, SchemaIdentifier};
     use std::sync::Arc;
+    use std::thread;
+
+    fn insert(schema_id: u32) -> Operation {
+        Operation::Insert {
+            new: Record::new(
+                Some(SchemaIdentifier {
+                    id: schema_id,
+                    version: 1,
+                }),
+                vec![],
+                None,
+            ),
+        }
+    }
 
     #[tokio::test]
     async fn test_message_handle() {
         let (tx, rx) = unbounded();
-        let sender: Arc<Box<dyn IngestorForwarder>> =
-            Arc::new(Box::new(ChannelForwarder { sender: tx }));
-        let ingestor = Ingestor { sender };
+        let stats = Arc::new(IngestionStats::default());
+        let sender: Arc<Box<dyn IngestorForwarder>> = Arc::new(Box::new(ChannelForwarder {
+            sender: tx,
+            stats: stats.clone(),
+        }));
+        let ingestor = Ingestor { sender, stats };
 
         // Expected seq no - 2
         let operation = Operation::Insert {
@@ -110,8 +199,106 @@ mod tests {
         let expected_op_event_message = vec![operation, operation2].into_iter();
 
         for x in expected_op_event_message {
-            let msg = rx.recv().unwrap();
-            assert_eq!(IngestionMessageKind::OperationEvent(x), msg.kind);
+            let msgs = rx.recv().unwrap();
+            assert_eq!(msgs.len(), 1);
+            assert_eq!(IngestionMessageKind::OperationEvent(x), msgs[0].kind);
         }
     }
+
+    #[test]
+    fn test_batch_handle() {
+        let (ingestor, mut iterator) = Ingestor::initialize_channel(IngestionConfig::default());
+
+        ingestor
+            .handle_batch(vec![
+                IngestionMessage::new_op(1, 0, insert(0)),
+                IngestionMessage::new_op(1, 1, insert(1)),
+                IngestionMessage::new_op(1, 2, insert(1)),
+            ])
+            .unwrap();
+        ingestor.handle_batch(vec![]).unwrap();
+        ingestor
+            .handle_message(IngestionMessage::new_snapshotting_done(1, 3))
+            .unwrap();
+
+        // Batches are a single channel message
+        assert_eq!(iterator.rx.len(), 2);
+        assert_eq!(ingestor.stats.queue_depth(), 4);
+
+        for seq_in_tx in 0..4 {
+            let msg = iterator.next().unwrap();
+            assert_eq!(msg.identifier.seq_in_tx, seq_in_tx);
+        }
+        assert_eq!(ingestor.stats.queue_depth(), 0);
+        assert_eq!(ingestor.stats.operations().get(&0), Some(&1));
+        assert_eq!(ingestor.stats.operations().get(&1), Some(&2));
+    }
+
+    #[test]
+    fn test_capacity_counts_messages() {
+        let config = IngestionConfig {
+            forwarder_channel_cap: 2,
+            connection_name: "test".to_string(),
+        };
+        let (ingestor, mut iterator) = Ingestor::initialize_channel(config);
+
+        ingestor
+            .handle_batch(vec![
+                IngestionMessage::new_op(1, 0, insert(0)),
+                IngestionMessage::new_op(1, 1, insert(0)),
+            ])
+            .unwrap();
+        let sender = ingestor.clone();
+        let t = thread::spawn(move || {
+            sender
+                .handle_message(IngestionMessage::new_op(1, 2, insert(0)))
+                .unwrap();
+        });
+
+        // The message waits for the batch to be received.
+        while ingestor.stats.blocked_sends() == 0 {
+            thread::yield_now();
+        }
+        assert_eq!(ingestor.stats.queue_depth(), 2);
+        for seq_in_tx in 0..3 {
+            let msg = iterator.next().unwrap();
+            assert_eq!(msg.identifier.seq_in_tx, seq_in_tx);
+        }
+        t.join().unwrap();
+        assert_eq!(ingestor.stats.blocked_sends(), 1);
+        assert_eq!(ingestor.stats.queue_depth(), 0);
+    }
+
+    #[test]
+    fn test_closed_channel() {
+        let config = IngestionConfig {
+            forwarder_channel_cap: 1,
+            connection_name: "test".to_string(),
+        };
+        let (ingestor, iterator) = Ingestor::initialize_channel(config);
+
+        ingestor
+            .handle_message(IngestionMessage::new_op(1, 0, insert(0)))
+            .unwrap();
+        let sender = ingestor.clone();
+        let t = thread::spawn(move || {
+            let _ = sender.handle_message(IngestionMessage::new_op(1, 1, insert(0)));
+        });
+
+        // Dropping the receiver stops the waiting sender.
+        while ingestor.stats.blocked_sends() == 0 {
+            thread::yield_now();
+        }
+        drop(iterator);
+        t.join().unwrap();
+
+        // Messages which failed to be sent are not counted.
+        let depth = ingestor.stats.queue_depth();
+        let operations = ingestor.stats.operations();
+        assert!(ingestor
+            .handle_message(IngestionMessage::new_op(1, 2, insert(0)))
+            .is_err());
+        assert_eq!(ingestor.stats.queue_depth(), depth);
+        assert_eq!(ingestor.stats.operations(), operations);
+    }
 }
@@ -1,16 +1,33 @@
 mod ingestor;
+mod stats;
 
 pub use ingestor::ChannelForwarder;
 pub use ingestor::{IngestionIterator, Ingestor};
+pub use stats::{
+    IngestionStats, BLOCKED_TIME_COUNTER_NAME, OPERATIONS_COUNTER_NAME, QUEUE_DEPTH_GAUGE_NAME,
+};
 
 pub struct IngestionConfig {
+    /// Capacity of the channel, in messages
     forwarder_channel_cap: usize,
+    /// Name of the connection, labelling the ingestion metrics
+    connection_name: String,
+}
+
+impl IngestionConfig {
+    pub fn new(connection_name: String) -> Self {
+        Self {
+            connection_name,
+            ..Default::default()
+        }
+    }
 }
 
 impl Default for IngestionConfig {
     fn default() -> Self {
         Self {
             forwarder_channel_cap: 100000,
+            connection_name: String::new(),
         }
     }
 }
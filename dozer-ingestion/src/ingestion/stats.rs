@@ -0,0 +1,159 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use dozer_types::ingestion_types::{IngestionMessage, IngestionMessageKind};
+use dozer_types::parking_lot::{Condvar, Mutex};
+use dozer_types::types::Operation;
+use metrics::{counter, gauge};
+
+/// Gauge of the messages sent by a connection and not received by the pipeline yet.
+pub const QUEUE_DEPTH_GAUGE_NAME: &str = "ingestion_queue_depth";
+/// Counter of the time connectors of a connection were blocked by a full queue, in microseconds.
+pub const BLOCKED_TIME_COUNTER_NAME: &str = "ingestion_blocked_micros";
+/// Counter of the operations sent by a connection, labelled by schema id.
+pub const OPERATIONS_COUNTER_NAME: &str = "ingestion_operations";
+
+const CONNECTION_LABEL: &str = "connection";
+const SCHEMA_ID_LABEL: &str = "schema_id";
+
+/// Counters shared by an `Ingestor` and its `IngestionIterator`, which are also published as
+/// metrics. Connectors can read them to adapt their fetch sizes when the pipeline is slow.
+///
+/// The queue depth also bounds the messages in the channel, senders waiting for room when it
+/// reaches the capacity.
+#[derive(Debug, Default)]
+pub struct IngestionStats {
+    connection_name: String,
+    // Messages the queue holds before senders wait, or 0 for no limit
+    capacity: u64,
+    queue: Mutex<Queue>,
+    room: Condvar,
+    blocked_sends: AtomicU64,
+    blocked_micros: AtomicU64,
+    operations: Mutex<HashMap<u32, u64>>,
+}
+
+#[derive(Debug, Default)]
+struct Queue {
+    depth: u64,
+    // Whether the receiver was dropped
+    closed: bool,
+}
+
+impl IngestionStats {
+    pub fn new(connection_name: String, capacity: usize) -> Self {
+        Self {
+            connection_name,
+            capacity: capacity as u64,
+            ..Default::default()
+        }
+    }
+
+    /// Messages sent and not received yet.
+    pub fn queue_depth(&self) -> u64 {
+        self.queue.lock().depth
+    }
+
+    /// Sends which waited for room in the queue.
+    pub fn blocked_sends(&self) -> u64 {
+        self.blocked_sends.load(Ordering::Relaxed)
+    }
+
+    /// Total time spent waiting for room in the queue.
+    pub fn blocked_time(&self) -> Duration {
+        Duration::from_micros(self.blocked_micros.load(Ordering::Relaxed))
+    }
+
+    /// Operations sent so far, by schema id.
+    pub fn operations(&self) -> HashMap<u32, u64> {
+        self.operations.lock().clone()
+    }
+
+    /// Adds messages to the queue before they are sent, waiting for room first. A batch larger
+    /// than the capacity waits for the queue to be empty.
+    pub(crate) fn on_queued(&self, count: u64) {
+        let mut queue = self.queue.lock();
+        if self.is_full(&queue, count) {
+            self.blocked_sends.fetch_add(1, Ordering::Relaxed);
+            let start = Instant::now();
+            while self.is_full(&queue, count) {
+                self.room.wait(&mut queue);
+            }
+            self.on_blocked(start.elapsed());
+        }
+        queue.depth += count;
+        self.publish_depth(queue.depth);
+    }
+
+    /// Removes messages from the queue, once they are received or failed to be sent.
+    pub(crate) fn on_dequeued(&self, count: u64) {
+        let mut queue = self.queue.lock();
+        queue.depth -= count;
+        self.publish_depth(queue.depth);
+        self.room.notify_all();
+    }
+
+    /// Stops senders from waiting for room, as the receiver was dropped.
+    pub(crate) fn on_closed(&self) {
+        self.queue.lock().closed = true;
+        self.room.notify_all();
+    }
+
+    /// Counts the operations of messages which were sent.
+    pub(crate) fn on_sent(&self, sent: HashMap<u32, u64>) {
+        let mut operations = self.operations.lock();
+        for (schema_id, count) in sent {
+            *operations.entry(schema_id).or_default() += count;
+            counter!(
+                OPERATIONS_COUNTER_NAME,
+                count,
+                CONNECTION_LABEL => self.connection_name.clone(),
+                SCHEMA_ID_LABEL => schema_id.to_string()
+            );
+        }
+    }
+
+    fn is_full(&self, queue: &Queue, count: u64) -> bool {
+        self.capacity > 0 && !queue.closed && queue.depth > 0 && queue.depth + count > self.capacity
+    }
+
+    fn on_blocked(&self, duration: Duration) {
+        let micros = duration.as_micros() as u64;
+        self.blocked_micros.fetch_add(micros, Ordering::Relaxed);
+        counter!(
+            BLOCKED_TIME_COUNTER_NAME,
+            micros,
+            CONNECTION_LABEL => self.connection_name.clone()
+        );
+    }
+
+    fn publish_depth(&self, depth: u64) {
+        gauge!(
+            QUEUE_DEPTH_GAUGE_NAME,
+            depth as f64,
+            CONNECTION_LABEL => self.connection_name.clone()
+        );
+    }
+}
+
+/// Operations of messages, by schema id.
+pub(crate) fn count_operations(messages: &[IngestionMessage]) -> HashMap<u32, u64> {
+    let mut operations: HashMap<u32, u64> = HashMap::new();
+    for message in messages {
+        if let Some(schema_id) = get_schema_id(message) {
+            *operations.entry(schema_id).or_default() += 1;
+        }
+    }
+    operations
+}
+
+fn get_schema_id(message: &IngestionMessage) -> Option<u32> {
+    let record = match &message.kind {
+        IngestionMessageKind::OperationEvent(Operation::Insert { new }) => new,
+        IngestionMessageKind::OperationEvent(Operation::Update { new, .. }) => new,
+        IngestionMessageKind::OperationEvent(Operation::Delete { old }) => old,
+        IngestionMessageKind::SnapshottingDone => return None,
+    };
+    record.schema_id.map(|id| id.id)
+}
@@ -5,9 +5,12 @@ use dozer_cache::errors::CacheError;
 use dozer_core::errors::ExecutionError;
 use dozer_ingestion::errors::ConnectorError;
 use dozer_sql::pipeline::errors::PipelineError;
+use dozer_sql::sqlparser::parser::ParserError;
+use dozer_storage::errors::StorageError;
 use dozer_types::crossbeam::channel::RecvError;
 use dozer_types::errors::internal::BoxedError;
 use dozer_types::thiserror::Error;
+use dozer_types::types::FieldType;
 use dozer_types::{serde_yaml, thiserror};
 
 #[derive(Error, Debug)]
@@ -52,6 +55,56 @@ pub enum OrchestrationError {
     EndpointTableNotFound(String),
     #[error("Duplicate table name found: {0:?}")]
     DuplicateTable(String),
+    #[error(transparent)]
+    SourceTransformError(#[from] SourceTransformError),
+    #[error(transparent)]
+    SourceHistoryError(#[from] SourceHistoryError),
+}
+
+#[derive(Error, Debug)]
+pub enum SourceTransformError {
+    #[error("Column {0} not found in source {1}")]
+    ColumnNotFound(String, String),
+    #[error("Primary key column {0} of source {1} can only be hashed")]
+    RedactedPrimaryKey(String, String),
+    #[error("Invalid cast type {0}: {1}")]
+    InvalidCastType(String, #[source] ParserError),
+    #[error("Invalid filter {0}: {1}")]
+    InvalidFilter(String, #[source] ParserError),
+    #[error("Filter {0} must be a boolean expression, found {1}")]
+    FilterNotBoolean(String, FieldType),
+    #[error("Column {0} appears more than once in source {1}")]
+    ColumnConflict(String, String),
+    #[error("Source {0} can't be filtered, as the old records of its changes only hold their primary key")]
+    FilterWithoutOldRecords(String),
+    #[error(transparent)]
+    PipelineError(#[from] PipelineError),
+}
+
+#[derive(Error, Debug)]
+pub enum SourceHistoryError {
+    #[error("Column {0} not found in source {1}")]
+    ColumnNotFound(String, String),
+    #[error("Column {0} already exists in source {1}")]
+    FieldConflict(String, String),
+    #[error("Column {0} of source {1} must be part of its primary key")]
+    NotInPrimaryKey(String, String),
+    #[error("Timestamp field {0} must be a timestamp, found {1}")]
+    InvalidTimestampField(String, FieldType),
+    #[error("Timestamp field {0} of source {1} is null")]
+    NullTimestamp(String, String),
+    #[error("Source {0} has no primary key, so its history can only be inserted into")]
+    ChangeWithoutPrimaryKey(String),
+    #[error("Checkpoint {0:?} is not in the source history journal")]
+    CheckpointNotFound((u64, u64)),
+    #[error("Operations of transaction {0} are not in the source history journal")]
+    TransactionNotFound(u64),
+    #[error(transparent)]
+    StorageError(#[from] StorageError),
+    #[error("Failed to serialize or deserialize the source history: {0}")]
+    SerializationError(#[from] dozer_types::bincode::Error),
+    #[error("Failed to create a directory for the source history: {0}")]
+    IoError(#[from] std::io::Error),
 }
 
 #[derive(Error, Debug)]
@@ -117,8 +117,12 @@ impl<'a> PipelineBuilder<'a> {
             }
         }
 
-        let source_builder =
-            SourceBuilder::new(&used_sources, grouped_connections, Some(&self.progress));
+        let source_builder = SourceBuilder::new(
+            &used_sources,
+            grouped_connections,
+            Some(&self.progress),
+            Some(self.pipeline_dir),
+        );
 
         let conn_ports = source_builder.get_ports();
 
@@ -10,14 +10,21 @@ use dozer_types::indicatif::{MultiProgress, ProgressBar, ProgressStyle};
 use dozer_types::ingestion_types::{IngestionMessage, IngestionMessageKind, IngestorError};
 use dozer_types::log::info;
 use dozer_types::models::connection::Connection;
+use dozer_types::models::source::Source as SourceConfig;
+use dozer_types::node::OpIdentifier;
 use dozer_types::parking_lot::Mutex;
 use dozer_types::types::{
     Operation, ReplicationChangesTrackingType, Schema, SchemaIdentifier, SourceDefinition,
     SourceSchema,
 };
 use std::collections::HashMap;
+use std::path::PathBuf;
 use std::thread;
 
+use super::history_journal::{Change, HistoryJournal, JournalMessage, Resumed};
+use super::source_history::SourceHistory;
+use super::source_transform::SourceTransform;
+
 fn attach_progress(multi_pb: Option<MultiProgress>) -> ProgressBar {
     let pb = ProgressBar::new_spinner();
     multi_pb.as_ref().map(|m| m.add(pb.clone()));
@@ -45,9 +52,13 @@ pub struct ConnectorSourceFactory {
     pub schema_port_map: HashMap<u32, u16>,
     pub schema_map: HashMap<u16, Schema>,
     pub replication_changes_type_map: HashMap<u16, ReplicationChangesTrackingType>,
+    pub transforms: HashMap<u16, SourceTransform>,
+    pub histories: HashMap<u16, SourceHistory>,
     pub tables: Vec<TableInfo>,
     pub connection: Connection,
     pub progress: Option<MultiProgress>,
+    /// Directory of the databases persisting the histories, which are lost on restart without one
+    pub history_dir: Option<PathBuf>,
 }
 
 fn map_replication_type_to_output_port_type(
@@ -72,19 +83,24 @@ impl ConnectorSourceFactory {
     pub fn new(
         ports: HashMap<String, u16>,
         tables: Vec<TableInfo>,
+        sources: &[&SourceConfig],
         connection: Connection,
         progress: Option<MultiProgress>,
+        history_dir: Option<PathBuf>,
     ) -> Result<Self, ExecutionError> {
-        let (schema_map, schema_port_map, replication_changes_type_map) =
-            Self::get_schema_map(connection.clone(), tables.clone(), ports.clone())?;
+        let (schema_map, schema_port_map, replication_changes_type_map, transforms, histories) =
+            Self::get_schema_map(connection.clone(), tables.clone(), sources, ports.clone())?;
         Ok(Self {
             ports,
             schema_port_map,
             schema_map,
             replication_changes_type_map,
+            transforms,
+            histories,
             tables,
             connection,
             progress,
+            history_dir,
         })
     }
 
@@ -92,12 +108,15 @@ impl ConnectorSourceFactory {
     fn get_schema_map(
         connection: Connection,
         tables: Vec<TableInfo>,
+        sources: &[&SourceConfig],
         ports: HashMap<String, u16>,
     ) -> Result<
         (
             HashMap<u16, Schema>,
             HashMap<u32, u16>,
             HashMap<u16, ReplicationChangesTrackingType>,
+            HashMap<u16, SourceTransform>,
+            HashMap<u16, SourceHistory>,
         ),
         ExecutionError,
     > {
@@ -115,6 +134,8 @@ impl ConnectorSourceFactory {
         let mut schema_port_map: HashMap<u32, u16> = HashMap::new();
         let mut replication_changes_type_map: HashMap<u16, ReplicationChangesTrackingType> =
             HashMap::new();
+        let mut transforms = HashMap::new();
+        let mut histories = HashMap::new();
 
         for SourceSchema {
             name,
@@ -128,12 +149,49 @@ impl ConnectorSourceFactory {
                 .ok_or(ExecutionError::PortNotFound(name))?;
             let schema_id = get_schema_id(schema.identifier)?;
 
+            // Sources are transformed before the pipeline, which only sees the transformed schema.
+            let source = sources.iter().find(|s| &s.name == source_name);
+            let transform = match source {
+                Some(source) => SourceTransform::new(source, schema.clone(), &replication_type)
+                    .map_err(|e| InternalError(Box::new(e)))?,
+                None => None,
+            };
+            let schema = match transform {
+                Some(transform) => {
+                    let schema = transform.schema().clone();
+                    transforms.insert(port, transform);
+                    schema
+                }
+                None => schema,
+            };
+
+            // Histories give the full old records of their updates and deletes.
+            let history = match source {
+                Some(source) => SourceHistory::new(source, schema.clone(), &replication_type)
+                    .map_err(|e| InternalError(Box::new(e)))?,
+                None => None,
+            };
+            let (schema, replication_type) = match history {
+                Some(history) => {
+                    let schema = history.schema().clone();
+                    histories.insert(port, history);
+                    (schema, ReplicationChangesTrackingType::FullChanges)
+                }
+                None => (schema, replication_type),
+            };
+
             schema_port_map.insert(schema_id, port);
             schema_map.insert(port, schema);
             replication_changes_type_map.insert(port, replication_type);
         }
 
-        Ok((schema_map, schema_port_map, replication_changes_type_map))
+        Ok((
+            schema_map,
+            schema_port_map,
+            replication_changes_type_map,
+            transforms,
+            histories,
+        ))
     }
 }
 
@@ -190,7 +248,8 @@ impl SourceFactory<SchemaSQLContext> for ConnectorSourceFactory {
         &self,
         _output_schemas: HashMap<PortHandle, Schema>,
     ) -> Result<Box<dyn Source>, ExecutionError> {
-        let (ingestor, iterator) = Ingestor::initialize_channel(IngestionConfig::default());
+        let (ingestor, iterator) =
+            Ingestor::initialize_channel(IngestionConfig::new(self.connection.name.clone()));
         let connector = get_connector(self.connection.clone())
             .map_err(|e| ExecutionError::ConnectorError(Box::new(e)))?;
 
@@ -201,10 +260,24 @@ impl SourceFactory<SchemaSQLContext> for ConnectorSourceFactory {
             bars.insert(*port, pb);
         }
 
+        let history_journal = (!self.histories.is_empty())
+            .then(|| {
+                HistoryJournal::new(
+                    self.history_dir.as_deref(),
+                    &format!("history_{}", self.connection.name),
+                    self.histories.clone(),
+                    self.ports.values().copied().collect(),
+                )
+            })
+            .transpose()
+            .map_err(|e| InternalError(Box::new(e)))?;
+
         Ok(Box::new(ConnectorSource {
             ingestor,
             iterator: Mutex::new(iterator),
             schema_port_map: self.schema_port_map.clone(),
+            transforms: self.transforms.clone(),
+            history_journal,
             tables: self.tables.clone(),
             connector,
             bars,
@@ -217,6 +290,10 @@ pub struct ConnectorSource {
     ingestor: Ingestor,
     iterator: Mutex<IngestionIterator>,
     schema_port_map: HashMap<u32, u16>,
+    transforms: HashMap<u16, SourceTransform>,
+    // Set if any source has a history, in which case every message of the connector is sent as a
+    // transaction of its own
+    history_journal: Option<HistoryJournal>,
     tables: Vec<TableInfo>,
     connector: Box<dyn Connector>,
     bars: HashMap<u16, ProgressBar>,
@@ -224,6 +301,16 @@ pub struct ConnectorSource {
 
 impl Source for ConnectorSource {
     fn can_start_from(&self, last_checkpoint: (u64, u64)) -> Result<bool, ExecutionError> {
+        let last_checkpoint = match &self.history_journal {
+            Some(journal) => match journal
+                .connector_checkpoint(last_checkpoint)
+                .map_err(|e| InternalError(Box::new(e)))?
+            {
+                Some(checkpoint) => checkpoint,
+                None => return Ok(false),
+            },
+            None => last_checkpoint,
+        };
         self.connector
             .as_ref()
             .can_start_from(last_checkpoint)
@@ -235,13 +322,25 @@ impl Source for ConnectorSource {
         fw: &mut dyn SourceChannelForwarder,
         last_checkpoint: Option<(u64, u64)>,
     ) -> Result<(), ExecutionError> {
+        let mut resumed = self
+            .history_journal
+            .as_ref()
+            .map(|journal| journal.open(last_checkpoint))
+            .transpose()
+            .map_err(|e| InternalError(Box::new(e)))?;
+        let connector_checkpoint = match &resumed {
+            Some(resumed) => resumed.connector_checkpoint,
+            None => last_checkpoint,
+        };
+
         thread::scope(|scope| {
             let mut counter = HashMap::new();
             let t = scope.spawn(|| {
-                match self
-                    .connector
-                    .start(last_checkpoint, &self.ingestor, self.tables.clone())
-                {
+                match self.connector.start(
+                    connector_checkpoint,
+                    &self.ingestor,
+                    self.tables.clone(),
+                ) {
                     Ok(_) => {}
                     // If we get a channel error, it means the source sender thread has quit.
                     // Any error handling is done in that thread.
@@ -250,52 +349,102 @@ impl Source for ConnectorSource {
                 }
             });
 
+            // Operations of the checkpointed message which were not committed are sent first.
+            if let Some(resumed) = &mut resumed {
+                for (port, message) in std::mem::take(&mut resumed.pending) {
+                    fw.send(message, port)?;
+                }
+            }
+
             let mut iterator = self.iterator.lock();
 
-            for IngestionMessage { identifier, kind } in iterator.by_ref() {
-                let schema_id = match &kind {
-                    IngestionMessageKind::OperationEvent(Operation::Delete { old }) => {
-                        Some(get_schema_id(old.schema_id)?)
-                    }
-                    IngestionMessageKind::OperationEvent(Operation::Insert { new }) => {
-                        Some(get_schema_id(new.schema_id)?)
-                    }
-                    IngestionMessageKind::OperationEvent(Operation::Update { old: _, new }) => {
-                        Some(get_schema_id(new.schema_id)?)
+            loop {
+                let Some(IngestionMessage { identifier, kind }) = iterator.next() else {
+                    break;
+                };
+
+                let op = match kind {
+                    IngestionMessageKind::OperationEvent(op) => op,
+                    IngestionMessageKind::SnapshottingDone => {
+                        if let (Some(journal), Some(resumed)) =
+                            (&self.history_journal, &mut resumed)
+                        {
+                            send_with_history(
+                                fw,
+                                journal,
+                                resumed,
+                                identifier,
+                                Change::SnapshottingDone,
+                                None,
+                            )?;
+                            continue;
+                        }
+                        for port in self.schema_port_map.values() {
+                            fw.send(
+                                IngestionMessage::new_snapshotting_done(
+                                    identifier.txid,
+                                    identifier.seq_in_tx,
+                                ),
+                                *port,
+                            )?
+                        }
+                        continue;
                     }
-                    IngestionMessageKind::SnapshottingDone => None,
                 };
-                if let Some(schema_id) = schema_id {
-                    let port =
-                        self.schema_port_map
-                            .get(&schema_id)
-                            .ok_or(ExecutionError::SourceError(SourceError::PortError(
-                                schema_id,
-                            )))?;
-
-                    counter
-                        .entry(schema_id)
-                        .and_modify(|e| *e += 1)
-                        .or_insert(1);
-
-                    let schema_counter = counter.get(&schema_id).unwrap();
-                    if *schema_counter % 1000 == 0 {
-                        let pb = self.bars.get(port);
-                        if let Some(pb) = pb {
-                            pb.set_position(*schema_counter);
+                let schema_id = match &op {
+                    Operation::Delete { old } => get_schema_id(old.schema_id)?,
+                    Operation::Insert { new } => get_schema_id(new.schema_id)?,
+                    Operation::Update { old: _, new } => get_schema_id(new.schema_id)?,
+                };
+                let port =
+                    self.schema_port_map
+                        .get(&schema_id)
+                        .ok_or(ExecutionError::SourceError(SourceError::PortError(
+                            schema_id,
+                        )))?;
+
+                counter
+                    .entry(schema_id)
+                    .and_modify(|e| *e += 1)
+                    .or_insert(1);
+
+                let schema_counter = counter.get(&schema_id).unwrap();
+                if *schema_counter % 1000 == 0 {
+                    let pb = self.bars.get(port);
+                    if let Some(pb) = pb {
+                        pb.set_position(*schema_counter);
+                    }
+                }
+
+                let op = match self.transforms.get(port) {
+                    Some(transform) => {
+                        match transform
+                            .apply(op)
+                            .map_err(|e| InternalError(Box::new(e)))?
+                        {
+                            Some(op) => op,
+                            // Filtered out
+                            None => continue,
                         }
                     }
-                    fw.send(IngestionMessage { identifier, kind }, *port)?
-                } else {
-                    for port in self.schema_port_map.values() {
-                        fw.send(
-                            IngestionMessage::new_snapshotting_done(
-                                identifier.txid,
-                                identifier.seq_in_tx,
-                            ),
-                            *port,
-                        )?
+                    None => op,
+                };
+                match (&self.history_journal, &mut resumed) {
+                    (Some(journal), Some(resumed)) => {
+                        let (change, op) = if journal.has_history(*port) {
+                            (Change::History(*port, op), None)
+                        } else {
+                            (Change::Operation, Some((*port, op)))
+                        };
+                        send_with_history(fw, journal, resumed, identifier, change, op)?;
                     }
+                    _ => fw.send(
+                        IngestionMessage {
+                            identifier,
+                            kind: IngestionMessageKind::OperationEvent(op),
+                        },
+                        *port,
+                    )?,
                 }
             }
 
@@ -308,6 +457,65 @@ impl Source for ConnectorSource {
             Ok(())
         })
     }
+
+    fn commit(&self, checkpoint: (u64, u64)) -> Result<(), ExecutionError> {
+        let checkpoint = match &self.history_journal {
+            Some(journal) => match journal
+                .commit(checkpoint)
+                .map_err(|e| InternalError(Box::new(e)))?
+            {
+                Some(checkpoint) => checkpoint,
+                None => return Ok(()),
+            },
+            None => checkpoint,
+        };
+        self.connector
+            .commit(checkpoint)
+            .map_err(|e| ExecutionError::ConnectorError(Box::new(e)))
+    }
+}
+
+/// Sends the operations of a message of the connector as a transaction of their own, after
+/// applying it to the histories. `op` is the operation of a source without history. Messages the
+/// connector sends again after a restart only send the operations following the checkpoint.
+fn send_with_history(
+    fw: &mut dyn SourceChannelForwarder,
+    journal: &HistoryJournal,
+    resumed: &mut Resumed,
+    identifier: OpIdentifier,
+    change: Change,
+    op: Option<(u16, Operation)>,
+) -> Result<(), ExecutionError> {
+    let txid = resumed.next_txid;
+    resumed.next_txid += 1;
+    let mut kinds = journal
+        .apply(
+            resumed,
+            JournalMessage {
+                txid,
+                identifier: (identifier.txid, identifier.seq_in_tx),
+                change,
+            },
+        )
+        .map_err(|e| InternalError(Box::new(e)))?;
+    if let Some((port, op)) = op {
+        kinds.push((port, IngestionMessageKind::OperationEvent(op)));
+    }
+    for (seq_in_tx, (port, kind)) in kinds.into_iter().enumerate() {
+        // Operations up to the checkpoint were sent before the pipeline restarted from it.
+        if matches!(resumed.last_checkpoint, Some(checkpoint) if (txid, seq_in_tx as u64) <= checkpoint)
+        {
+            continue;
+        }
+        fw.send(
+            IngestionMessage {
+                identifier: OpIdentifier::new(txid, seq_in_tx as u64),
+                kind,
+            },
+            port,
+        )?;
+    }
+    Ok(())
 }
 
 fn get_schema_id(op_schema_id: Option<SchemaIdentifier>) -> Result<u32, ExecutionError> {
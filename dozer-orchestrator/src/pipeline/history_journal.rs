@@ -0,0 +1,470 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+use dozer_storage::errors::StorageError;
+use dozer_storage::lmdb::{Cursor, Database, DatabaseFlags};
+use dozer_storage::lmdb_storage::{
+    LmdbEnvironmentManager, LmdbEnvironmentOptions, LmdbExclusiveTransaction, SharedTransaction,
+};
+use dozer_types::bincode;
+use dozer_types::ingestion_types::{IngestionMessage, IngestionMessageKind};
+use dozer_types::node::OpIdentifier;
+use dozer_types::parking_lot::Mutex;
+use dozer_types::serde::{Deserialize, Serialize};
+use dozer_types::types::Operation;
+use tempdir::TempDir;
+
+use super::source_history::{HistoryDatabases, SourceHistory};
+use crate::errors::SourceHistoryError;
+
+// Keys of the committed checkpoint, and of the last transaction applied to the histories
+const CHECKPOINT_KEY: &[u8] = b"checkpoint";
+const LAST_APPLIED_KEY: &[u8] = b"last_applied";
+
+/// Message of the connector, sent as transaction `txid`.
+#[derive(Debug, Clone)]
+pub struct JournalMessage {
+    pub txid: u64,
+    /// Identifier of the message given by the connector
+    pub identifier: (u64, u64),
+    pub change: Change,
+}
+
+#[derive(Debug, Clone)]
+pub enum Change {
+    /// Operation of a source without history, which is sent as the only operation of its
+    /// transaction
+    Operation,
+    /// Operation applied to the history of a port
+    History(u16, Operation),
+    SnapshottingDone,
+}
+
+/// Operation sent for a message, stored until its transaction is committed.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(crate = "dozer_types::serde")]
+enum SentKind {
+    Operation(Operation),
+    SnapshottingDone,
+}
+
+impl From<SentKind> for IngestionMessageKind {
+    fn from(kind: SentKind) -> Self {
+        match kind {
+            SentKind::Operation(op) => IngestionMessageKind::OperationEvent(op),
+            SentKind::SnapshottingDone => IngestionMessageKind::SnapshottingDone,
+        }
+    }
+}
+
+/// State the sources resume from.
+#[derive(Debug)]
+pub struct Resumed {
+    pub next_txid: u64,
+    /// Checkpoint the connector resumes from
+    pub connector_checkpoint: Option<(u64, u64)>,
+    /// Operations of the checkpointed message which follow the checkpoint of the pipeline
+    pub pending: Vec<(u16, IngestionMessage)>,
+    /// Checkpoint of the pipeline, up to which the operations were already sent
+    pub last_checkpoint: Option<(u64, u64)>,
+    // Last transaction held by the histories, whose messages are not applied again
+    last_applied: u64,
+}
+
+/// Persists the histories of the sources of a connection with the checkpoints of the pipeline.
+///
+/// The operations of a message of the connector are sent as a transaction of their own. The
+/// histories are changed in an LMDB transaction which is committed with each checkpoint, together
+/// with the operations sent for the messages changing them since the checkpoint. The connector
+/// resumes from the message of the committed checkpoint, and the messages the histories already
+/// hold send their stored operations instead of being applied again. Messages of sources without
+/// history are sent again by the connector, and are not stored.
+#[derive(Debug)]
+pub struct HistoryJournal {
+    txn: SharedTransaction,
+    databases: HistoryDatabases,
+    // Operations sent for the messages changing the histories, by transaction
+    sent: Database,
+    // Committed checkpoint with the identifier of its message, and the last applied transaction
+    meta: Database,
+    histories: HashMap<u16, SourceHistory>,
+    // Ports the end of snapshotting is sent to
+    ports: Vec<u16>,
+    // Identifiers given by the connector to the transactions which were not committed
+    identifiers: Mutex<BTreeMap<u64, (u64, u64)>>,
+    // Directory of the databases if they are lost on restart
+    _temp_dir: Option<TempDir>,
+}
+
+impl HistoryJournal {
+    /// Opens the databases `name` in `dir`. Without `dir`, the histories are lost on restart.
+    pub fn new(
+        dir: Option<&Path>,
+        name: &str,
+        histories: HashMap<u16, SourceHistory>,
+        mut ports: Vec<u16>,
+    ) -> Result<Self, SourceHistoryError> {
+        let (dir, temp_dir) = match dir {
+            Some(dir) => (dir.to_path_buf(), None),
+            None => {
+                let temp_dir = TempDir::new(name)?;
+                (temp_dir.path().to_path_buf(), Some(temp_dir))
+            }
+        };
+        let env = LmdbEnvironmentManager::create(&dir, name, LmdbEnvironmentOptions::default())?;
+        let txn = env.create_txn()?;
+        let (databases, sent, meta) = {
+            let mut txn = txn.write();
+            (
+                HistoryDatabases::new(&mut txn)?,
+                txn.create_database(Some("history_sent"), Some(DatabaseFlags::empty()))?,
+                txn.create_database(Some("history_meta"), Some(DatabaseFlags::empty()))?,
+            )
+        };
+
+        ports.sort();
+        Ok(Self {
+            txn,
+            databases,
+            sent,
+            meta,
+            histories,
+            ports,
+            identifiers: Mutex::new(BTreeMap::new()),
+            _temp_dir: temp_dir,
+        })
+    }
+
+    pub fn has_history(&self, port: u16) -> bool {
+        self.histories.contains_key(&port)
+    }
+
+    /// Returns the checkpoint the connector resumes from to get to `checkpoint`, or `None` if the
+    /// histories were not committed at or before `checkpoint`.
+    pub fn connector_checkpoint(
+        &self,
+        checkpoint: (u64, u64),
+    ) -> Result<Option<(u64, u64)>, SourceHistoryError> {
+        Ok(self
+            .committed(&self.txn.read(), checkpoint)?
+            .map(|(_, identifier)| identifier))
+    }
+
+    /// Opens the histories to resume from `last_checkpoint`, or clears them without one.
+    pub fn open(&self, last_checkpoint: Option<(u64, u64)>) -> Result<Resumed, SourceHistoryError> {
+        let mut txn = self.txn.write();
+        let mut identifiers = self.identifiers.lock();
+        identifiers.clear();
+
+        let Some(checkpoint) = last_checkpoint else {
+            self.databases.clear(&mut txn)?;
+            for db in [self.sent, self.meta] {
+                txn.txn_mut().clear_db(db).map_err(StorageError::from)?;
+            }
+            txn.commit_and_renew()?;
+            return Ok(Resumed {
+                next_txid: 1,
+                connector_checkpoint: None,
+                pending: vec![],
+                last_checkpoint: None,
+                last_applied: 0,
+            });
+        };
+
+        let (committed, identifier) = self
+            .committed(&txn, checkpoint)?
+            .ok_or(SourceHistoryError::CheckpointNotFound(checkpoint))?;
+        let txid = committed.0;
+        let last_applied = match txn.get(self.meta, LAST_APPLIED_KEY)? {
+            Some(txid) => u64::from_be_bytes(
+                txid.try_into()
+                    .expect("The last applied transaction must be a u64"),
+            ),
+            None => txid,
+        };
+        identifiers.insert(txid, identifier);
+
+        let pending = self
+            .sent_kinds(&txn, txid)?
+            .unwrap_or_default()
+            .into_iter()
+            .enumerate()
+            .map(|(seq, (port, kind))| {
+                (
+                    port,
+                    IngestionMessage {
+                        identifier: OpIdentifier::new(txid, seq as u64),
+                        kind,
+                    },
+                )
+            })
+            .filter(|(_, message)| (txid, message.identifier.seq_in_tx) > checkpoint)
+            .collect();
+        Ok(Resumed {
+            next_txid: txid + 1,
+            connector_checkpoint: Some(identifier),
+            pending,
+            last_checkpoint: Some(checkpoint),
+            last_applied,
+        })
+    }
+
+    /// Applies a message to the histories, returning the operations to send. Messages the
+    /// histories already hold return the operations stored for them. Operations of sources without
+    /// history are sent by the caller.
+    pub fn apply(
+        &self,
+        resumed: &Resumed,
+        message: JournalMessage,
+    ) -> Result<Vec<(u16, IngestionMessageKind)>, SourceHistoryError> {
+        self.identifiers
+            .lock()
+            .insert(message.txid, message.identifier);
+        if matches!(message.change, Change::Operation) {
+            return Ok(vec![]);
+        }
+
+        let mut txn = self.txn.write();
+        if message.txid <= resumed.last_applied {
+            return self
+                .sent_kinds(&txn, message.txid)?
+                .ok_or(SourceHistoryError::TransactionNotFound(message.txid));
+        }
+
+        let sent = match message.change {
+            Change::Operation => vec![],
+            Change::History(port, op) => match self.histories.get(&port) {
+                Some(history) => history
+                    .apply(&mut self.databases.state(&mut txn, port), op)?
+                    .into_iter()
+                    .map(|op| (port, SentKind::Operation(op)))
+                    .collect(),
+                None => vec![],
+            },
+            Change::SnapshottingDone => self
+                .ports
+                .iter()
+                .map(|port| (*port, SentKind::SnapshottingDone))
+                .collect(),
+        };
+        txn.put(
+            self.sent,
+            &message.txid.to_be_bytes(),
+            &bincode::serialize(&sent)?,
+        )?;
+        txn.put(self.meta, LAST_APPLIED_KEY, &message.txid.to_be_bytes())?;
+        Ok(sent
+            .into_iter()
+            .map(|(port, kind)| (port, kind.into()))
+            .collect())
+    }
+
+    /// Commits the histories at `checkpoint`, returning the checkpoint of the connector.
+    pub fn commit(&self, checkpoint: (u64, u64)) -> Result<Option<(u64, u64)>, SourceHistoryError> {
+        let identifier = {
+            let mut identifiers = self.identifiers.lock();
+            let identifier = identifiers.get(&checkpoint.0).copied();
+            let uncommitted = identifiers.split_off(&checkpoint.0);
+            *identifiers = uncommitted;
+            identifier
+        };
+        let Some(identifier) = identifier else {
+            return Ok(None);
+        };
+
+        let mut txn = self.txn.write();
+        // Operations of the transactions preceding the checkpoint are never sent again.
+        let mut committed = vec![];
+        {
+            let mut cursor = txn.open_ro_cursor(self.sent)?;
+            for entry in cursor.iter_start() {
+                let (key, _) = entry.map_err(StorageError::from)?;
+                if key >= checkpoint.0.to_be_bytes().as_slice() {
+                    break;
+                }
+                committed.push(key.to_vec());
+            }
+        }
+        for key in committed {
+            txn.del(self.sent, &key, None)?;
+        }
+        txn.put(
+            self.meta,
+            CHECKPOINT_KEY,
+            &bincode::serialize(&(checkpoint, identifier))?,
+        )?;
+        txn.commit_and_renew()?;
+        Ok(Some(identifier))
+    }
+
+    /// Returns the committed checkpoint and the identifier of its message, if it's not after
+    /// `checkpoint`.
+    #[allow(clippy::type_complexity)]
+    fn committed(
+        &self,
+        txn: &LmdbExclusiveTransaction,
+        checkpoint: (u64, u64),
+    ) -> Result<Option<((u64, u64), (u64, u64))>, SourceHistoryError> {
+        let committed: Option<((u64, u64), (u64, u64))> = txn
+            .get(self.meta, CHECKPOINT_KEY)?
+            .map(bincode::deserialize)
+            .transpose()?;
+        Ok(committed.filter(|(committed, _)| *committed <= checkpoint))
+    }
+
+    fn sent_kinds(
+        &self,
+        txn: &LmdbExclusiveTransaction,
+        txid: u64,
+    ) -> Result<Option<Vec<(u16, IngestionMessageKind)>>, SourceHistoryError> {
+        let Some(sent) = txn.get(self.sent, &txid.to_be_bytes())? else {
+            return Ok(None);
+        };
+        let sent: Vec<(u16, SentKind)> = bincode::deserialize(sent)?;
+        Ok(Some(
+            sent.into_iter()
+                .map(|(port, kind)| (port, kind.into()))
+                .collect(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Change, HistoryJournal, JournalMessage};
+    use crate::pipeline::source_history::SourceHistory;
+    use dozer_types::chrono::{TimeZone, Utc};
+    use dozer_types::ingestion_types::IngestionMessageKind;
+    use dozer_types::models::source::{HistoryType, MasterHistoryConfig, Source};
+    use dozer_types::node::OpIdentifier;
+    use dozer_types::types::{
+        Field, FieldDefinition, FieldType, Operation, Record, ReplicationChangesTrackingType,
+        Schema, SourceDefinition,
+    };
+    use std::collections::HashMap;
+    use tempdir::TempDir;
+
+    fn history_journal(dir: &TempDir) -> HistoryJournal {
+        let source = Source {
+            name: "users".to_string(),
+            history_type: Some(HistoryType::Master(MasterHistoryConfig::AppendOnly {
+                unique_key_field: "id".to_string(),
+                open_date_field: "updated_at".to_string(),
+                closed_date_field: "closed_at".to_string(),
+                max_versions: None,
+            })),
+            ..Default::default()
+        };
+        let schema = Schema {
+            identifier: None,
+            fields: ["id", "updated_at"]
+                .into_iter()
+                .map(|name| {
+                    FieldDefinition::new(
+                        name.to_string(),
+                        FieldType::Timestamp,
+                        false,
+                        SourceDefinition::Dynamic,
+                    )
+                })
+                .collect(),
+            primary_index: vec![0],
+        };
+        let history = SourceHistory::new(
+            &source,
+            schema,
+            &ReplicationChangesTrackingType::FullChanges,
+        )
+        .unwrap()
+        .unwrap();
+        HistoryJournal::new(
+            Some(dir.path()),
+            "history",
+            HashMap::from([(1, history)]),
+            vec![1],
+        )
+        .unwrap()
+    }
+
+    fn insert(txid: u64, secs: i64) -> JournalMessage {
+        let time = Field::Timestamp(Utc.timestamp_opt(secs, 0).unwrap().into());
+        JournalMessage {
+            txid,
+            identifier: (txid * 10, 0),
+            change: Change::History(
+                1,
+                Operation::Insert {
+                    new: Record::new(None, vec![time.clone(), time], None),
+                },
+            ),
+        }
+    }
+
+    fn operation(txid: u64) -> JournalMessage {
+        JournalMessage {
+            txid,
+            identifier: (txid * 10, 0),
+            change: Change::Operation,
+        }
+    }
+
+    #[test]
+    fn test_it_resumes_from_committed_checkpoint() {
+        let dir = TempDir::new("history_journal").unwrap();
+        let journal = history_journal(&dir);
+        let resumed = journal.open(None).unwrap();
+        assert_eq!(journal.apply(&resumed, insert(1, 0)).unwrap().len(), 1);
+        assert_eq!(journal.apply(&resumed, insert(2, 10)).unwrap().len(), 2);
+        assert_eq!(journal.apply(&resumed, insert(3, 20)).unwrap().len(), 2);
+        assert!(journal.apply(&resumed, operation(4)).unwrap().is_empty());
+        assert_eq!(journal.commit((2, 0)).unwrap(), Some((20, 0)));
+        drop(journal);
+
+        // The connector resumes after the message of the checkpoint, whose other operations are
+        // sent again.
+        let journal = history_journal(&dir);
+        assert_eq!(journal.connector_checkpoint((2, 0)).unwrap(), Some((20, 0)));
+        assert_eq!(journal.connector_checkpoint((3, 0)).unwrap(), Some((20, 0)));
+        assert_eq!(journal.connector_checkpoint((1, 0)).unwrap(), None);
+        let resumed = journal.open(Some((2, 0))).unwrap();
+        assert_eq!(resumed.next_txid, 3);
+        assert_eq!(resumed.connector_checkpoint, Some((20, 0)));
+        let [(1, message)] = resumed.pending.as_slice() else {
+            panic!("expected a pending operation");
+        };
+        assert_eq!(message.identifier, OpIdentifier::new(2, 1));
+        assert!(matches!(
+            message.kind,
+            IngestionMessageKind::OperationEvent(Operation::Update { .. })
+        ));
+
+        // The histories already hold the following message, whose stored operations are sent
+        // instead of applying it again.
+        let ops = journal.apply(&resumed, insert(3, 20)).unwrap();
+        assert!(matches!(
+            ops.as_slice(),
+            [
+                (
+                    1,
+                    IngestionMessageKind::OperationEvent(Operation::Insert { .. })
+                ),
+                (
+                    1,
+                    IngestionMessageKind::OperationEvent(Operation::Update { .. })
+                )
+            ]
+        ));
+        assert!(journal.apply(&resumed, operation(4)).unwrap().is_empty());
+        assert_eq!(journal.apply(&resumed, insert(5, 30)).unwrap().len(), 2);
+
+        // Committing a later checkpoint drops the stored operations preceding it.
+        assert_eq!(journal.commit((5, 0)).unwrap(), Some((50, 0)));
+        assert_eq!(journal.connector_checkpoint((2, 0)).unwrap(), None);
+        let resumed = journal.open(Some((5, 0))).unwrap();
+        assert!(resumed.pending.is_empty());
+        assert!(matches!(
+            journal.apply(&resumed, insert(3, 20)),
+            Err(crate::errors::SourceHistoryError::TransactionNotFound(3))
+        ));
+    }
+}
@@ -1,7 +1,10 @@
 mod builder;
 pub mod connector_source;
+mod history_journal;
 mod sinks;
 pub mod source_builder;
+pub mod source_history;
+pub mod source_transform;
 mod streaming_sink;
 pub mod validate;
 pub use builder::PipelineBuilder;
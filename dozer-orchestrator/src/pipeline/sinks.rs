@@ -3,7 +3,8 @@ use dozer_api::grpc::internal::internal_pipeline_server::PipelineEventSenders;
 use dozer_api::grpc::types_helper;
 use dozer_cache::cache::expression::QueryExpression;
 use dozer_cache::cache::index::get_primary_key;
-use dozer_cache::cache::{CacheManager, RwCache};
+use dozer_cache::cache::{CacheManager, OnEvict, RetentionPolicy, RwCache};
+use dozer_cache::errors::CacheError;
 use dozer_core::epoch::Epoch;
 use dozer_core::errors::{ExecutionError, SinkError};
 use dozer_core::node::{PortHandle, Sink, SinkFactory};
@@ -14,16 +15,20 @@ use dozer_sql::pipeline::builder::SchemaSQLContext;
 use dozer_types::crossbeam::channel::Sender;
 use dozer_types::grpc_types::internal::AliasRedirected;
 use dozer_types::indicatif::{MultiProgress, ProgressBar, ProgressStyle};
-use dozer_types::log::{debug, info};
-use dozer_types::models::api_endpoint::{ApiEndpoint, ApiIndex};
+use dozer_types::log::{debug, error, info};
+use dozer_types::models::api_endpoint::{
+    ApiEndpoint, ApiIndex, CacheRetentionConfig, FullText, SecondaryIndex, SecondaryIndexConfig,
+    SortedInverted, Spatial,
+};
 use dozer_types::models::api_security::ApiSecurity;
 use dozer_types::models::flags::Flags;
 use dozer_types::node::SourceStates;
 use dozer_types::types::FieldType;
-use dozer_types::types::{IndexDefinition, Operation, Schema, SchemaIdentifier};
+use dozer_types::types::{IndexDefinition, Operation, Schema, SchemaIdentifier, TextAnalyzer};
 use std::collections::HashMap;
 use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Duration;
 
 fn attach_progress(multi_pb: Option<MultiProgress>) -> ProgressBar {
     let pb = ProgressBar::new_spinner();
@@ -126,36 +131,14 @@ impl CacheSinkFactory {
             version: 1,
         });
 
-        // Automatically create secondary indexes
-        let secondary_indexes = schema
-            .fields
-            .iter()
-            .enumerate()
-            .flat_map(|(idx, f)| match f.typ {
-                // Create sorted inverted indexes for these fields
-                FieldType::UInt
-                | FieldType::Int
-                | FieldType::Float
-                | FieldType::Boolean
-                | FieldType::Decimal
-                | FieldType::Timestamp
-                | FieldType::Date
-                | FieldType::Point => vec![IndexDefinition::SortedInverted(vec![idx])],
-
-                // Create sorted inverted and full text indexes for string fields.
-                FieldType::String => vec![
-                    IndexDefinition::SortedInverted(vec![idx]),
-                    IndexDefinition::FullText(idx),
-                ],
-
-                // Create full text indexes for text fields
-                // FieldType::Text => vec![IndexDefinition::FullText(idx)],
-                FieldType::Text => vec![],
-
-                // Skip creating indexes
-                FieldType::Binary | FieldType::Bson => vec![],
-            })
-            .collect();
+        let secondary_indexes = create_secondary_indexes(
+            &schema,
+            &self.api_endpoint.name,
+            self.api_endpoint
+                .index
+                .as_ref()
+                .and_then(|index| index.secondary.as_ref()),
+        )?;
         Ok((schema, secondary_indexes))
     }
 }
@@ -235,6 +218,157 @@ fn create_primary_indexes(
     Ok(primary_index)
 }
 
+fn create_secondary_indexes(
+    schema: &Schema,
+    endpoint_name: &str,
+    config: Option<&SecondaryIndexConfig>,
+) -> Result<Vec<IndexDefinition>, ExecutionError> {
+    let invalid = |reason: String| ExecutionError::InvalidSecondaryIndex {
+        endpoint_name: endpoint_name.to_string(),
+        reason,
+    };
+    let field_index = |name: &str| {
+        schema
+            .fields
+            .iter()
+            .position(|field| field.name == name)
+            .ok_or_else(|| ExecutionError::FieldNotFound(name.to_string()))
+    };
+
+    let skip_default = config.map_or(&[][..], |config| &config.skip_default[..]);
+    let skip_all = skip_default.iter().any(|name| name == "*");
+    let skipped_fields = skip_default
+        .iter()
+        .filter(|name| *name != "*")
+        .map(|name| field_index(name))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // Automatically create secondary indexes
+    let mut secondary_indexes = vec![];
+    for (idx, f) in schema.fields.iter().enumerate() {
+        if skip_all || skipped_fields.contains(&idx) {
+            continue;
+        }
+        match f.typ {
+            // Create sorted inverted indexes for these fields
+            FieldType::UInt
+            | FieldType::Int
+            | FieldType::Float
+            | FieldType::Boolean
+            | FieldType::Decimal
+            | FieldType::Timestamp
+            | FieldType::Date => secondary_indexes.push(IndexDefinition::SortedInverted(vec![idx])),
+
+            // Create sorted inverted and spatial indexes for point fields.
+            FieldType::Point => {
+                secondary_indexes.push(IndexDefinition::SortedInverted(vec![idx]));
+                secondary_indexes.push(IndexDefinition::Spatial(idx));
+            }
+
+            // Create sorted inverted and full text indexes for string fields.
+            FieldType::String => {
+                secondary_indexes.push(IndexDefinition::SortedInverted(vec![idx]));
+                secondary_indexes.push(IndexDefinition::FullText(idx, TextAnalyzer::default()));
+            }
+
+            // Create full text indexes for text fields
+            // FieldType::Text => vec![IndexDefinition::FullText(idx, TextAnalyzer::default())],
+            FieldType::Text => (),
+
+            // Skip creating indexes
+            FieldType::Binary | FieldType::Bson => (),
+        }
+    }
+
+    // Create the configured indexes
+    for create in config.map_or(&[][..], |config| &config.create[..]) {
+        let index = match &create.index {
+            Some(SecondaryIndex::SortedInverted(SortedInverted { fields })) => {
+                if fields.is_empty() {
+                    return Err(invalid(
+                        "sorted inverted index must have fields".to_string(),
+                    ));
+                }
+                let mut indexes = vec![];
+                for name in fields {
+                    let idx = field_index(name)?;
+                    if indexes.contains(&idx) {
+                        return Err(invalid(format!(
+                            "field `{name}` appears twice in sorted inverted index"
+                        )));
+                    }
+                    if matches!(schema.fields[idx].typ, FieldType::Binary | FieldType::Bson) {
+                        return Err(invalid(format!(
+                            "field `{name}` of type {} can't be in a sorted inverted index",
+                            schema.fields[idx].typ
+                        )));
+                    }
+                    indexes.push(idx);
+                }
+                IndexDefinition::SortedInverted(indexes)
+            }
+            Some(SecondaryIndex::FullText(FullText {
+                field,
+                lowercase,
+                stop_words,
+                stemming,
+            })) => {
+                let idx = field_index(field)?;
+                if !matches!(schema.fields[idx].typ, FieldType::String | FieldType::Text) {
+                    return Err(invalid(format!(
+                        "field `{field}` of type {} can't have a full text index",
+                        schema.fields[idx].typ
+                    )));
+                }
+                IndexDefinition::FullText(
+                    idx,
+                    TextAnalyzer {
+                        lowercase: *lowercase,
+                        stop_words: *stop_words,
+                        stemming: *stemming,
+                    },
+                )
+            }
+            Some(SecondaryIndex::Spatial(Spatial { field })) => {
+                let idx = field_index(field)?;
+                if schema.fields[idx].typ != FieldType::Point {
+                    return Err(invalid(format!(
+                        "field `{field}` of type {} can't have a spatial index",
+                        schema.fields[idx].typ
+                    )));
+                }
+                IndexDefinition::Spatial(idx)
+            }
+            None => return Err(invalid("index type is missing".to_string())),
+        };
+        if !secondary_indexes.contains(&index) {
+            secondary_indexes.push(index);
+        }
+    }
+    Ok(secondary_indexes)
+}
+
+fn create_retention_policy(
+    endpoint_name: &str,
+    config: &CacheRetentionConfig,
+) -> Result<RetentionPolicy, ExecutionError> {
+    let ttl = match (&config.timestamp_field, config.retention_period) {
+        (Some(field), Some(period)) => Some((field.clone(), Duration::from_secs(period))),
+        (None, None) => None,
+        _ => {
+            return Err(ExecutionError::InvalidCacheRetention {
+                endpoint_name: endpoint_name.to_string(),
+                reason: "`timestamp_field` and `retention_period` must be set together".to_string(),
+            })
+        }
+    };
+    Ok(RetentionPolicy {
+        ttl,
+        max_records: config.max_records,
+        max_bytes: config.max_bytes,
+    })
+}
+
 fn get_field_names(schema: &Schema, indexes: &[usize]) -> Vec<String> {
     indexes
         .iter()
@@ -251,6 +385,23 @@ fn open_or_create_cache(
 ) -> Result<(Box<dyn RwCache>, Option<usize>), ExecutionError> {
     let append_only = schema.primary_index.is_empty();
 
+    let cache = cache_manager.open_rw_cache(name).map_err(|e| {
+        ExecutionError::SinkError(SinkError::CacheOpenFailed(name.to_string(), Box::new(e)))
+    })?;
+    let indexes_changed = match &cache {
+        Some(cache) => {
+            let (_, cache_secondary_indexes) =
+                cache.get_schema_and_indexes_by_name(name).map_err(|e| {
+                    ExecutionError::SinkError(SinkError::CacheOpenFailed(
+                        name.to_string(),
+                        Box::new(e),
+                    ))
+                })?;
+            cache_secondary_indexes != &secondary_indexes
+        }
+        None => false,
+    };
+
     let create_cache = || {
         cache_manager
             .create_cache(vec![(name.to_string(), schema, secondary_indexes)])
@@ -262,13 +413,14 @@ fn open_or_create_cache(
             })
     };
 
-    let cache = cache_manager.open_rw_cache(name).map_err(|e| {
-        ExecutionError::SinkError(SinkError::CacheOpenFailed(name.to_string(), Box::new(e)))
-    })?;
     if let Some(cache) = cache {
         if append_only {
             debug!("Cache {} is append only", name);
-            Ok((cache, None))
+            if indexes_changed {
+                rebuild_cache(cache_manager, &*cache, create_cache()?, name)
+            } else {
+                Ok((cache, None))
+            }
         } else {
             let cache_checkpoint = cache.get_checkpoint().map_err(|e| {
                 ExecutionError::SinkError(SinkError::CacheGetCheckpointFailed(
@@ -278,7 +430,11 @@ fn open_or_create_cache(
             })?;
             if &cache_checkpoint == checkpoint {
                 debug!("Cache {} is consistent with the pipeline", name);
-                Ok((cache, None))
+                if indexes_changed {
+                    rebuild_cache(cache_manager, &*cache, create_cache()?, name)
+                } else {
+                    Ok((cache, None))
+                }
             } else {
                 let old_name = cache.name();
                 let old_count = cache
@@ -311,6 +467,40 @@ fn open_or_create_cache(
     }
 }
 
+/// Copies the records of `cache`, whose secondary indexes aren't the configured ones, to
+/// `new_cache` at the checkpoint of `cache`, and serves `new_cache` instead.
+fn rebuild_cache(
+    cache_manager: &dyn CacheManager,
+    cache: &dyn RwCache,
+    new_cache: Box<dyn RwCache>,
+    name: &str,
+) -> Result<(Box<dyn RwCache>, Option<usize>), ExecutionError> {
+    info!(
+        "[pipeline] Cache {} has other secondary indexes than configured, rebuilding it as {}",
+        name,
+        new_cache.name()
+    );
+    let rebuild_failed = |e: CacheError| {
+        ExecutionError::SinkError(SinkError::CacheRebuildFailed(name.to_string(), Box::new(e)))
+    };
+    let (schema, _) = new_cache
+        .get_schema_and_indexes_by_name(name)
+        .map_err(rebuild_failed)?;
+    let (_, records) = cache
+        .query(name, &QueryExpression::with_no_limit())
+        .map_err(rebuild_failed)?;
+    for mut record in records {
+        record.record.schema_id = schema.identifier;
+        new_cache
+            .insert(&mut record.record)
+            .map_err(rebuild_failed)?;
+    }
+    let checkpoint = cache.get_checkpoint().map_err(rebuild_failed)?;
+    new_cache.commit(&checkpoint).map_err(rebuild_failed)?;
+    create_alias(cache_manager, new_cache.name(), name)?;
+    Ok((new_cache, None))
+}
+
 fn create_alias(
     cache_manager: &dyn CacheManager,
     name: &str,
@@ -452,13 +642,43 @@ impl CacheSink {
         multi_pb: Option<MultiProgress>,
     ) -> Result<Self, ExecutionError> {
         let query = QueryExpression::with_no_limit();
-        let (cache, current_alias_count) = open_or_create_cache(
+        let (mut cache, current_alias_count) = open_or_create_cache(
             &*cache_manager,
             &api_endpoint.name,
             checkpoint,
             schema,
             secondary_indexes,
         )?;
+        if let Some(config) = &api_endpoint.retention {
+            let policy = create_retention_policy(&api_endpoint.name, config)?;
+            // Evicted records are deleted for the clients of `OnEvent`.
+            let on_evict = notifier.as_ref().map(|notifier| {
+                let sender = notifier.1.clone();
+                let endpoint_name = api_endpoint.name.clone();
+                Box::new(move |record| {
+                    let op = types_helper::map_delete_operation(endpoint_name.clone(), record);
+                    if let Err(e) = sender.try_send(op) {
+                        error!("Failed to send eviction of a record of {endpoint_name}: {e}");
+                    }
+                }) as OnEvict
+            });
+            cache
+                .set_retention_policy(&api_endpoint.name, policy, on_evict)
+                .map_err(|e| {
+                    ExecutionError::SinkError(SinkError::CacheRetentionFailed(
+                        api_endpoint.name.clone(),
+                        Box::new(e),
+                    ))
+                })?;
+        }
+        cache
+            .set_history(&api_endpoint.name, api_endpoint.history)
+            .map_err(|e| {
+                ExecutionError::SinkError(SinkError::CacheHistoryFailed(
+                    api_endpoint.name.clone(),
+                    Box::new(e),
+                ))
+            })?;
         let counter = cache.count(&api_endpoint.name, &query).map_err(|e| {
             ExecutionError::SinkError(SinkError::CacheCountFailed(
                 api_endpoint.name.clone(),
@@ -509,16 +729,24 @@ fn try_send<T: Send + Sync + 'static>(sender: &Sender<T>, msg: T) -> Result<(),
 #[cfg(test)]
 mod tests {
 
+    use super::CacheSink;
     use crate::test_utils;
 
-    use dozer_cache::cache::index;
+    use dozer_cache::cache::{index, RetentionPolicy};
     use dozer_core::node::Sink;
     use dozer_core::storage::lmdb_storage::LmdbEnvironmentManager;
     use dozer_core::DEFAULT_PORT_HANDLE;
 
+    use dozer_types::models::api_endpoint::{
+        CacheRetentionConfig, CreateSecondaryIndex, FullText, SecondaryIndex, SecondaryIndexConfig,
+        SortedInverted, Spatial,
+    };
     use dozer_types::node::NodeHandle;
-    use dozer_types::types::{Field, IndexDefinition, Operation, Record, SchemaIdentifier};
+    use dozer_types::types::{
+        Field, IndexDefinition, Operation, Record, SchemaIdentifier, TextAnalyzer,
+    };
     use std::collections::HashMap;
+    use std::time::Duration;
     use tempdir::TempDir;
 
     #[test]
@@ -612,4 +840,166 @@ mod tests {
 
         assert_eq!(updated_values, record.values);
     }
+
+    #[test]
+    fn rebuild_cache_with_other_secondary_indexes() {
+        let tmp_dir = TempDir::new("example").unwrap();
+        let env =
+            LmdbEnvironmentManager::create(tmp_dir.path(), "test", Default::default()).unwrap();
+        let txn = env.create_txn().unwrap();
+
+        let schema = test_utils::get_schema();
+        let (cache_manager, mut sink) = test_utils::init_sink(
+            schema.clone(),
+            vec![IndexDefinition::SortedInverted(vec![0])],
+        );
+        let values = vec![Field::Int(1), Field::String("Film name".to_string())];
+        let insert_operation = Operation::Insert {
+            new: Record::new(schema.identifier, values.clone(), None),
+        };
+        sink.process(DEFAULT_PORT_HANDLE, insert_operation, &txn, &HashMap::new())
+            .unwrap();
+        let epoch = dozer_core::epoch::Epoch::from(
+            0,
+            NodeHandle::new(Some(DEFAULT_PORT_HANDLE), "".to_string()),
+            0,
+            0,
+        );
+        sink.commit(&epoch, &txn).unwrap();
+        let old_name = sink.cache.name().to_string();
+        drop(sink);
+
+        let secondary_indexes = vec![
+            IndexDefinition::SortedInverted(vec![0]),
+            IndexDefinition::SortedInverted(vec![1]),
+        ];
+        let sink = CacheSink::new(
+            cache_manager.clone(),
+            test_utils::init_endpoint(),
+            &epoch.details,
+            schema.clone(),
+            secondary_indexes.clone(),
+            None,
+            None,
+        )
+        .unwrap();
+        assert_ne!(sink.cache.name(), old_name);
+        assert_eq!(sink.cache.get_checkpoint().unwrap(), epoch.details);
+
+        let cache = cache_manager.open_ro_cache("films").unwrap().unwrap();
+        assert_eq!(cache.name(), sink.cache.name());
+        assert_eq!(
+            cache.get_schema_and_indexes_by_name("films").unwrap().1,
+            secondary_indexes
+        );
+        let key = index::get_primary_key(&schema.primary_index, &values);
+        assert_eq!(cache.get(&key).unwrap().record.values, values);
+    }
+
+    #[test]
+    fn configured_secondary_indexes() {
+        let schema = test_utils::get_schema();
+        let create = |skip_default: &[&str], create: Vec<SecondaryIndex>| {
+            let config = SecondaryIndexConfig {
+                skip_default: skip_default.iter().map(|name| name.to_string()).collect(),
+                create: create
+                    .into_iter()
+                    .map(|index| CreateSecondaryIndex { index: Some(index) })
+                    .collect(),
+            };
+            super::create_secondary_indexes(&schema, "films", Some(&config))
+        };
+        let sorted_inverted = |fields: &[&str]| {
+            SecondaryIndex::SortedInverted(SortedInverted {
+                fields: fields.iter().map(|name| name.to_string()).collect(),
+            })
+        };
+        let full_text = |field: &str| {
+            SecondaryIndex::FullText(FullText {
+                field: field.to_string(),
+                lowercase: true,
+                stop_words: false,
+                stemming: false,
+            })
+        };
+
+        assert_eq!(
+            super::create_secondary_indexes(&schema, "films", None).unwrap(),
+            vec![
+                IndexDefinition::SortedInverted(vec![0]),
+                IndexDefinition::SortedInverted(vec![1]),
+                IndexDefinition::FullText(1, TextAnalyzer::default()),
+            ]
+        );
+        assert_eq!(
+            create(&["film_name"], vec![]).unwrap(),
+            vec![IndexDefinition::SortedInverted(vec![0])]
+        );
+        assert_eq!(
+            create(
+                &["*"],
+                vec![
+                    sorted_inverted(&["film_name", "film_id"]),
+                    full_text("film_name")
+                ]
+            )
+            .unwrap(),
+            vec![
+                IndexDefinition::SortedInverted(vec![1, 0]),
+                IndexDefinition::FullText(
+                    1,
+                    TextAnalyzer {
+                        lowercase: true,
+                        ..Default::default()
+                    }
+                ),
+            ]
+        );
+        // Indexes that are created by default are not duplicated.
+        assert_eq!(
+            create(&[], vec![sorted_inverted(&["film_id"])])
+                .unwrap()
+                .len(),
+            3
+        );
+
+        assert!(create(&["unknown"], vec![]).is_err());
+        assert!(create(&[], vec![sorted_inverted(&[])]).is_err());
+        assert!(create(&[], vec![sorted_inverted(&["film_id", "film_id"])]).is_err());
+        assert!(create(&[], vec![full_text("film_id")]).is_err());
+        assert!(create(
+            &[],
+            vec![SecondaryIndex::Spatial(Spatial {
+                field: "film_name".to_string()
+            })]
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn configured_retention_policy() {
+        let config = CacheRetentionConfig {
+            timestamp_field: Some("created_at".to_string()),
+            retention_period: Some(30 * 24 * 60 * 60),
+            max_records: Some(1000),
+            max_bytes: None,
+        };
+        assert_eq!(
+            super::create_retention_policy("films", &config).unwrap(),
+            RetentionPolicy {
+                ttl: Some((
+                    "created_at".to_string(),
+                    Duration::from_secs(30 * 24 * 60 * 60)
+                )),
+                max_records: Some(1000),
+                max_bytes: None,
+            }
+        );
+
+        let config = CacheRetentionConfig {
+            retention_period: Some(60),
+            ..Default::default()
+        };
+        assert!(super::create_retention_policy("films", &config).is_err());
+    }
 }
@@ -6,12 +6,14 @@ use dozer_sql::pipeline::builder::SchemaSQLContext;
 use dozer_types::indicatif::MultiProgress;
 use dozer_types::models::source::Source;
 use std::collections::HashMap;
+use std::path::Path;
 use std::sync::Arc;
 
 pub struct SourceBuilder<'a> {
     used_sources: &'a [String],
     grouped_connections: HashMap<&'a str, Vec<&'a Source>>,
     progress: Option<&'a MultiProgress>,
+    pipeline_dir: Option<&'a Path>,
 }
 
 const SOURCE_PORTS_RANGE_START: u16 = 1000;
@@ -21,11 +23,13 @@ impl<'a> SourceBuilder<'a> {
         used_sources: &'a [String],
         grouped_connections: HashMap<&'a str, Vec<&'a Source>>,
         progress: Option<&'a MultiProgress>,
+        pipeline_dir: Option<&'a Path>,
     ) -> Self {
         Self {
             used_sources,
             grouped_connections,
             progress,
+            pipeline_dir,
         }
     }
 
@@ -84,8 +88,10 @@ impl<'a> SourceBuilder<'a> {
                 let source_factory = ConnectorSourceFactory::new(
                     ports.clone(),
                     tables,
+                    &sources_group,
                     connection.clone(),
                     self.progress.cloned(),
+                    self.pipeline_dir.map(Path::to_path_buf),
                 )?;
 
                 asm.add(AppSource::new(
@@ -118,7 +124,7 @@ mod tests {
     use dozer_types::ingestion_types::{GrpcConfig, GrpcConfigSchemas};
     use dozer_types::models::app_config::{
         default_app_buffer_size, default_app_max_map_size, default_cache_max_map_size,
-        default_commit_size, default_commit_timeout, Config,
+        default_cache_max_scan_records, default_commit_size, default_commit_timeout, Config,
     };
 
     use dozer_core::appsource::{AppSourceId, AppSourceMappings};
@@ -148,6 +154,9 @@ mod tests {
                     columns: vec!["id".to_string(), "name".to_string()],
                     connection: Some(grpc_conn.clone()),
                     refresh_config: None,
+                    transforms: vec![],
+                    filter: None,
+                    history_type: None,
                 },
                 Source {
                     name: "customers".to_string(),
@@ -155,6 +164,9 @@ mod tests {
                     columns: vec!["id".to_string(), "name".to_string()],
                     connection: Some(grpc_conn),
                     refresh_config: None,
+                    transforms: vec![],
+                    filter: None,
+                    history_type: None,
                 },
             ],
             endpoints: vec![],
@@ -165,6 +177,7 @@ mod tests {
             app_buffer_size: Some(default_app_buffer_size()),
             commit_size: Some(default_commit_size()),
             commit_timeout: Some(default_commit_timeout()),
+            cache_max_scan_records: Some(default_cache_max_scan_records()),
         }
     }
 
@@ -182,6 +195,7 @@ mod tests {
             &tables,
             SourceBuilder::group_connections(&config.sources),
             None,
+            None,
         );
         let asm = source_builder.build_source_manager().unwrap();
 
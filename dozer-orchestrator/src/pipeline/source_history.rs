@@ -0,0 +1,931 @@
+use std::collections::BTreeMap;
+
+use dozer_storage::errors::StorageError;
+use dozer_storage::lmdb::{Cursor, Database, DatabaseFlags};
+use dozer_storage::lmdb_storage::LmdbExclusiveTransaction;
+use dozer_types::bincode;
+use dozer_types::chrono::{DateTime, Duration, FixedOffset};
+use dozer_types::models::source::{
+    HistoryType, MasterHistoryConfig, Source, TransactionalHistoryConfig,
+};
+use dozer_types::serde::{de::DeserializeOwned, Serialize};
+use dozer_types::types::{
+    Field, FieldDefinition, FieldType, Operation, Record, ReplicationChangesTrackingType, Schema,
+    SourceDefinition,
+};
+
+use crate::errors::SourceHistoryError;
+
+/// Primary key added to transactional sources without one, so that their records can be deleted.
+pub const HISTORY_ROW_ID_FIELD: &str = "dozer_history_row_id";
+
+/// History kept for a source, turning the operations of its table into the operations on its
+/// history. Histories keep their state in the `HistoryDatabases` of their connection, and only
+/// depend on the operations they are given, so that they can be built again from the operations
+/// following a saved state.
+#[derive(Debug, Clone)]
+pub enum SourceHistory {
+    Master(MasterHistory),
+    Transactional(TransactionalHistory),
+}
+
+impl SourceHistory {
+    /// Returns `None` if the source only keeps the current version of its records.
+    pub fn new(
+        source: &Source,
+        schema: Schema,
+        replication_type: &ReplicationChangesTrackingType,
+    ) -> Result<Option<Self>, SourceHistoryError> {
+        Ok(match &source.history_type {
+            None | Some(HistoryType::Master(MasterHistoryConfig::Overwrite)) => None,
+            Some(HistoryType::Master(MasterHistoryConfig::AppendOnly {
+                unique_key_field,
+                open_date_field,
+                closed_date_field,
+                max_versions,
+            })) => Some(SourceHistory::Master(MasterHistory::new(
+                &source.name,
+                unique_key_field,
+                open_date_field,
+                closed_date_field,
+                *max_versions,
+                schema,
+                replication_type,
+            )?)),
+            Some(HistoryType::Transactional(TransactionalHistoryConfig::RetainPartial {
+                timestamp_field,
+                retention_period,
+            })) => Some(SourceHistory::Transactional(TransactionalHistory::new(
+                &source.name,
+                timestamp_field,
+                *retention_period,
+                schema,
+            )?)),
+        })
+    }
+
+    /// Schema of the history.
+    pub fn schema(&self) -> &Schema {
+        match self {
+            SourceHistory::Master(history) => &history.schema,
+            SourceHistory::Transactional(history) => &history.schema,
+        }
+    }
+
+    pub fn apply(
+        &self,
+        state: &mut HistoryState,
+        op: Operation,
+    ) -> Result<Vec<Operation>, SourceHistoryError> {
+        match self {
+            SourceHistory::Master(history) => history.apply(state, op),
+            SourceHistory::Transactional(history) => history.apply(state, op),
+        }
+    }
+}
+
+/// Databases holding the state of the histories of a connection. Their keys start with the port
+/// of the source of each history.
+#[derive(Debug, Clone, Copy)]
+pub struct HistoryDatabases {
+    // Versions of the records of master histories, by unique key
+    versions: Database,
+    // Records in the window of transactional histories, by timestamp and arrival
+    window: Database,
+    // Position of the records in the window, by primary key
+    positions: Database,
+    // Latest timestamp and arrival counter of transactional histories
+    watermarks: Database,
+}
+
+impl HistoryDatabases {
+    pub fn new(txn: &mut LmdbExclusiveTransaction) -> Result<Self, SourceHistoryError> {
+        let mut create = |name| txn.create_database(Some(name), Some(DatabaseFlags::empty()));
+        Ok(Self {
+            versions: create("history_versions")?,
+            window: create("history_window")?,
+            positions: create("history_positions")?,
+            watermarks: create("history_watermarks")?,
+        })
+    }
+
+    /// Removes the state of every history.
+    pub fn clear(&self, txn: &mut LmdbExclusiveTransaction) -> Result<(), SourceHistoryError> {
+        for db in [self.versions, self.window, self.positions, self.watermarks] {
+            txn.txn_mut().clear_db(db).map_err(StorageError::from)?;
+        }
+        Ok(())
+    }
+
+    /// State of the history of the source at `port`.
+    pub fn state<'a>(&self, txn: &'a mut LmdbExclusiveTransaction, port: u16) -> HistoryState<'a> {
+        HistoryState {
+            txn,
+            databases: *self,
+            port,
+        }
+    }
+}
+
+/// State of a history in the databases of its connection, which is changed in the transaction
+/// committed with the checkpoints of the pipeline.
+pub struct HistoryState<'a> {
+    txn: &'a mut LmdbExclusiveTransaction,
+    databases: HistoryDatabases,
+    port: u16,
+}
+
+impl HistoryState<'_> {
+    fn key(&self, suffix: &[u8]) -> Vec<u8> {
+        let mut key = self.port.to_be_bytes().to_vec();
+        key.extend_from_slice(suffix);
+        key
+    }
+
+    fn get<T: DeserializeOwned>(
+        &self,
+        db: Database,
+        key: &[u8],
+    ) -> Result<Option<T>, SourceHistoryError> {
+        Ok(self
+            .txn
+            .get(db, key)?
+            .map(bincode::deserialize)
+            .transpose()?)
+    }
+
+    fn put<T: Serialize>(
+        &mut self,
+        db: Database,
+        key: &[u8],
+        value: &T,
+    ) -> Result<(), SourceHistoryError> {
+        Ok(self.txn.put(db, key, &bincode::serialize(value)?)?)
+    }
+
+    fn del(&mut self, db: Database, key: &[u8]) -> Result<(), SourceHistoryError> {
+        self.txn.del(db, key, None)?;
+        Ok(())
+    }
+
+    /// First entry of the history in `db`.
+    fn first(&self, db: Database) -> Result<Option<(Vec<u8>, Vec<u8>)>, SourceHistoryError> {
+        let prefix = self.key(&[]);
+        let mut cursor = self.txn.open_ro_cursor(db)?;
+        let first = cursor
+            .iter_from(&prefix)
+            .next()
+            .transpose()
+            .map_err(StorageError::from)?;
+        Ok(first
+            .filter(|(key, _)| key.starts_with(&prefix))
+            .map(|(key, value)| (key.to_vec(), value.to_vec())))
+    }
+}
+
+fn find_field(schema: &Schema, name: &str, source_name: &str) -> Result<usize, SourceHistoryError> {
+    schema
+        .fields
+        .iter()
+        .position(|f| f.name == name)
+        .ok_or_else(|| {
+            SourceHistoryError::ColumnNotFound(name.to_string(), source_name.to_string())
+        })
+}
+
+fn find_timestamp_field(
+    schema: &Schema,
+    name: &str,
+    source_name: &str,
+) -> Result<usize, SourceHistoryError> {
+    let idx = find_field(schema, name, source_name)?;
+    match schema.fields[idx].typ {
+        FieldType::Timestamp => Ok(idx),
+        typ => Err(SourceHistoryError::InvalidTimestampField(
+            name.to_string(),
+            typ,
+        )),
+    }
+}
+
+fn check_new_field(
+    schema: &Schema,
+    name: &str,
+    source_name: &str,
+) -> Result<(), SourceHistoryError> {
+    if schema.fields.iter().any(|f| f.name == name) {
+        return Err(SourceHistoryError::FieldConflict(
+            name.to_string(),
+            source_name.to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Records of sources without primary key can't be matched, so only their inserts are kept.
+fn check_change(
+    op: &Operation,
+    has_primary_key: bool,
+    source_name: &str,
+) -> Result<(), SourceHistoryError> {
+    if !has_primary_key && !matches!(op, Operation::Insert { .. }) {
+        return Err(SourceHistoryError::ChangeWithoutPrimaryKey(
+            source_name.to_string(),
+        ));
+    }
+    Ok(())
+}
+
+fn get_timestamp(
+    record: &Record,
+    idx: usize,
+    schema: &Schema,
+    source_name: &str,
+) -> Result<DateTime<FixedOffset>, SourceHistoryError> {
+    match &record.values[idx] {
+        Field::Timestamp(time) => Ok(*time),
+        _ => Err(SourceHistoryError::NullTimestamp(
+            schema.fields[idx].name.clone(),
+            source_name.to_string(),
+        )),
+    }
+}
+
+/// Keeps every version of the records of a source (slowly changing dimension of type 2). Versions
+/// are identified by the unique key of their record and the date they were opened at, and are
+/// closed at the opening date of the following version of their record. Inserts and updates add
+/// versions, and deletes remove them. With `max_versions`, the oldest versions of a record are
+/// deleted once it has more.
+#[derive(Debug, Clone)]
+pub struct MasterHistory {
+    source_name: String,
+    schema: Schema,
+    has_primary_key: bool,
+    key_index: Vec<usize>,
+    open_date_idx: usize,
+    closed_date_idx: usize,
+    max_versions: Option<u32>,
+}
+
+// Versions of a record by opening date, which are stored together under its unique key
+type Versions = BTreeMap<DateTime<FixedOffset>, Record>;
+
+impl MasterHistory {
+    fn new(
+        source_name: &str,
+        unique_key_field: &str,
+        open_date_field: &str,
+        closed_date_field: &str,
+        max_versions: Option<u32>,
+        mut schema: Schema,
+        replication_type: &ReplicationChangesTrackingType,
+    ) -> Result<Self, SourceHistoryError> {
+        let key_idx = find_field(&schema, unique_key_field, source_name)?;
+        let open_date_idx = find_timestamp_field(&schema, open_date_field, source_name)?;
+        check_new_field(&schema, closed_date_field, source_name)?;
+
+        // Old records of deletes only hold the primary key, which must identify their version.
+        if replication_type == &ReplicationChangesTrackingType::OnlyPK {
+            for (idx, name) in [
+                (key_idx, unique_key_field),
+                (open_date_idx, open_date_field),
+            ] {
+                if !schema.primary_index.contains(&idx) {
+                    return Err(SourceHistoryError::NotInPrimaryKey(
+                        name.to_string(),
+                        source_name.to_string(),
+                    ));
+                }
+            }
+        }
+
+        let has_primary_key = !schema.primary_index.is_empty();
+        let closed_date_idx = schema.fields.len();
+        schema.fields.push(FieldDefinition::new(
+            closed_date_field.to_string(),
+            FieldType::Timestamp,
+            true,
+            SourceDefinition::Dynamic,
+        ));
+        schema.primary_index = vec![key_idx, open_date_idx];
+
+        Ok(Self {
+            source_name: source_name.to_string(),
+            schema,
+            has_primary_key,
+            key_index: vec![key_idx],
+            open_date_idx,
+            closed_date_idx,
+            max_versions,
+        })
+    }
+
+    fn apply(
+        &self,
+        state: &mut HistoryState,
+        op: Operation,
+    ) -> Result<Vec<Operation>, SourceHistoryError> {
+        check_change(&op, self.has_primary_key, &self.source_name)?;
+        let record = match &op {
+            Operation::Insert { new } | Operation::Update { new, .. } => new,
+            Operation::Delete { old } => old,
+        };
+        let (key, date) = self.version_of(record)?;
+        let key = state.key(&key);
+        let db = state.databases.versions;
+        let mut versions: Versions = state.get(db, &key)?.unwrap_or_default();
+
+        let ops = match op {
+            Operation::Insert { new } => self.insert(&mut versions, date, new),
+            // The record changed, and its previous version is kept unless it has the same
+            // opening date.
+            Operation::Update { old: _, new } => self.insert(&mut versions, date, new),
+            Operation::Delete { old: _ } => self.remove(&mut versions, date),
+        };
+
+        if versions.is_empty() {
+            state.del(db, &key)?;
+        } else {
+            state.put(db, &key, &versions)?;
+        }
+        Ok(ops)
+    }
+
+    fn version_of(
+        &self,
+        record: &Record,
+    ) -> Result<(Vec<u8>, DateTime<FixedOffset>), SourceHistoryError> {
+        Ok((
+            record.get_key(&self.key_index),
+            get_timestamp(record, self.open_date_idx, &self.schema, &self.source_name)?,
+        ))
+    }
+
+    /// Adds or replaces a version, closing the version preceding it and deleting the oldest
+    /// versions over `max_versions`.
+    fn insert(
+        &self,
+        versions: &mut Versions,
+        date: DateTime<FixedOffset>,
+        record: Record,
+    ) -> Vec<Operation> {
+        let closed_date = versions
+            .range(date..)
+            .find(|(open_date, _)| **open_date != date)
+            .map_or(Field::Null, |(open_date, _)| Field::Timestamp(*open_date));
+        let mut values = record.values;
+        values.push(closed_date);
+        let new = Record::new(record.schema_id, values, None);
+
+        let mut ops = vec![match versions.insert(date, new.clone()) {
+            Some(old) => Operation::Update { old, new },
+            None => Operation::Insert { new },
+        }];
+        ops.extend(self.close_previous(versions, date));
+
+        let max_versions = self.max_versions.map_or(usize::MAX, |max| max as usize);
+        while versions.len() > max_versions {
+            let (oldest_date, oldest) = versions.pop_first().expect("versions must not be empty");
+            if oldest_date == date {
+                // The new version is the oldest, and nothing else changed.
+                return vec![];
+            }
+            ops.push(Operation::Delete { old: oldest });
+        }
+        ops
+    }
+
+    /// Removes a version, opening the version preceding it until the one following it.
+    fn remove(&self, versions: &mut Versions, date: DateTime<FixedOffset>) -> Vec<Operation> {
+        let Some(old) = versions.remove(&date) else {
+            return vec![];
+        };
+
+        let mut ops = vec![Operation::Delete { old }];
+        ops.extend(self.close_previous(versions, date));
+        ops
+    }
+
+    /// Closes the version preceding `date` at the opening date of the version following it.
+    fn close_previous(
+        &self,
+        versions: &mut Versions,
+        date: DateTime<FixedOffset>,
+    ) -> Option<Operation> {
+        let closed_date = versions
+            .range(date..)
+            .next()
+            .map_or(Field::Null, |(open_date, _)| Field::Timestamp(*open_date));
+        let (_, previous) = versions.range_mut(..date).next_back()?;
+        if previous.values[self.closed_date_idx] == closed_date {
+            return None;
+        }
+        let old = previous.clone();
+        previous.values[self.closed_date_idx] = closed_date;
+        Some(Operation::Update {
+            old,
+            new: previous.clone(),
+        })
+    }
+}
+
+/// Keeps the records of a source for a retention period, measured from the latest timestamp of
+/// the inserted and updated records, deleting the ones falling out of it.
+#[derive(Debug, Clone)]
+pub struct TransactionalHistory {
+    source_name: String,
+    schema: Schema,
+    timestamp_idx: usize,
+    // Retention period in seconds
+    retention_period: i64,
+    // Whether the source has no primary key, and `HISTORY_ROW_ID_FIELD` was added
+    row_id: bool,
+}
+
+// Latest timestamp of the records, and the number of records put in the window
+type Watermark = (Option<DateTime<FixedOffset>>, u64);
+
+impl TransactionalHistory {
+    fn new(
+        source_name: &str,
+        timestamp_field: &str,
+        retention_period: u32,
+        mut schema: Schema,
+    ) -> Result<Self, SourceHistoryError> {
+        let timestamp_idx = find_timestamp_field(&schema, timestamp_field, source_name)?;
+
+        let row_id = schema.primary_index.is_empty();
+        if row_id {
+            check_new_field(&schema, HISTORY_ROW_ID_FIELD, source_name)?;
+            schema.fields.push(FieldDefinition::new(
+                HISTORY_ROW_ID_FIELD.to_string(),
+                FieldType::UInt,
+                false,
+                SourceDefinition::Dynamic,
+            ));
+            schema.primary_index = vec![schema.fields.len() - 1];
+        }
+
+        Ok(Self {
+            source_name: source_name.to_string(),
+            schema,
+            timestamp_idx,
+            retention_period: retention_period as i64,
+            row_id,
+        })
+    }
+
+    fn apply(
+        &self,
+        state: &mut HistoryState,
+        op: Operation,
+    ) -> Result<Vec<Operation>, SourceHistoryError> {
+        check_change(&op, !self.row_id, &self.source_name)?;
+        match op {
+            Operation::Insert { new } => {
+                let old = self.remove(state, &new)?;
+                self.replace(state, old, new)
+            }
+            Operation::Update { old, new } => {
+                let old = self.remove(state, &old)?;
+                self.replace(state, old, new)
+            }
+            Operation::Delete { old } => Ok(self
+                .remove(state, &old)?
+                .map(|old| Operation::Delete { old })
+                .into_iter()
+                .collect()),
+        }
+    }
+
+    fn is_expired(
+        &self,
+        watermark: Option<DateTime<FixedOffset>>,
+        time: DateTime<FixedOffset>,
+    ) -> bool {
+        matches!(watermark, Some(watermark) if time + Duration::seconds(self.retention_period) <= watermark)
+    }
+
+    /// Removes a record from the window, returning it if it was there.
+    fn remove(
+        &self,
+        state: &mut HistoryState,
+        record: &Record,
+    ) -> Result<Option<Record>, SourceHistoryError> {
+        if self.row_id {
+            return Ok(None);
+        }
+        let key = state.key(&record.get_key(&self.schema.primary_index));
+        let db = state.databases.positions;
+        let Some(position) = state.txn.get(db, &key)?.map(<[u8]>::to_vec) else {
+            return Ok(None);
+        };
+        state.del(db, &key)?;
+        let window = state.databases.window;
+        let old = state.get(window, &position)?;
+        state.del(window, &position)?;
+        Ok(old)
+    }
+
+    /// Puts the new version of a record in the window, unless it is already expired, and deletes
+    /// the records expiring with its timestamp.
+    fn replace(
+        &self,
+        state: &mut HistoryState,
+        old: Option<Record>,
+        new: Record,
+    ) -> Result<Vec<Operation>, SourceHistoryError> {
+        let time = get_timestamp(&new, self.timestamp_idx, &self.schema, &self.source_name)?;
+        let watermark_key = state.key(&[]);
+        let watermarks = state.databases.watermarks;
+        let (watermark, counter): Watermark =
+            state.get(watermarks, &watermark_key)?.unwrap_or_default();
+        let watermark = watermark.max(Some(time));
+        if self.is_expired(watermark, time) {
+            state.put(watermarks, &watermark_key, &(watermark, counter))?;
+            return Ok(old
+                .map(|old| Operation::Delete { old })
+                .into_iter()
+                .collect());
+        }
+
+        let counter = counter + 1;
+        state.put(watermarks, &watermark_key, &(watermark, counter))?;
+        let mut new = new;
+        if self.row_id {
+            new.values.push(Field::UInt(counter));
+        }
+        let position = state.key(&window_key(time, counter));
+        let key = state.key(&new.get_key(&self.schema.primary_index));
+        state.txn.put(state.databases.positions, &key, &position)?;
+        state.put(state.databases.window, &position, &new)?;
+
+        let mut ops = vec![match old {
+            Some(old) => Operation::Update { old, new },
+            None => Operation::Insert { new },
+        }];
+        while let Some((position, old)) = state.first(state.databases.window)? {
+            let old: Record = bincode::deserialize(&old)?;
+            let time = get_timestamp(&old, self.timestamp_idx, &self.schema, &self.source_name)?;
+            if !self.is_expired(watermark, time) {
+                break;
+            }
+            state.del(state.databases.window, &position)?;
+            let key = state.key(&old.get_key(&self.schema.primary_index));
+            state.del(state.databases.positions, &key)?;
+            ops.push(Operation::Delete { old });
+        }
+        Ok(ops)
+    }
+}
+
+/// Key ordering the records of the window by timestamp, and by arrival.
+fn window_key(time: DateTime<FixedOffset>, counter: u64) -> [u8; 20] {
+    let mut key = [0; 20];
+    // Flipping the sign bit orders negative timestamps before positive ones.
+    key[..8].copy_from_slice(&((time.timestamp() as u64) ^ (1 << 63)).to_be_bytes());
+    key[8..12].copy_from_slice(&time.timestamp_subsec_nanos().to_be_bytes());
+    key[12..].copy_from_slice(&counter.to_be_bytes());
+    key
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{HistoryDatabases, SourceHistory, HISTORY_ROW_ID_FIELD};
+    use crate::errors::SourceHistoryError;
+    use dozer_storage::lmdb_storage::{LmdbEnvironmentManager, SharedTransaction};
+    use dozer_types::chrono::{TimeZone, Utc};
+    use dozer_types::models::source::{
+        HistoryType, MasterHistoryConfig, Source, TransactionalHistoryConfig,
+    };
+    use dozer_types::types::{
+        Field, FieldDefinition, FieldType, Operation, Record, ReplicationChangesTrackingType,
+        Schema, SchemaIdentifier, SourceDefinition,
+    };
+    use tempdir::TempDir;
+
+    fn schema(primary_index: Vec<usize>) -> Schema {
+        Schema {
+            identifier: Some(SchemaIdentifier { id: 1, version: 1 }),
+            fields: vec![
+                FieldDefinition::new(
+                    "id".to_string(),
+                    FieldType::Int,
+                    false,
+                    SourceDefinition::Dynamic,
+                ),
+                FieldDefinition::new(
+                    "name".to_string(),
+                    FieldType::String,
+                    true,
+                    SourceDefinition::Dynamic,
+                ),
+                FieldDefinition::new(
+                    "updated_at".to_string(),
+                    FieldType::Timestamp,
+                    false,
+                    SourceDefinition::Dynamic,
+                ),
+            ],
+            primary_index,
+        }
+    }
+
+    fn source(history_type: HistoryType) -> Source {
+        Source {
+            name: "users".to_string(),
+            history_type: Some(history_type),
+            ..Default::default()
+        }
+    }
+
+    fn master_config(max_versions: Option<u32>) -> HistoryType {
+        HistoryType::Master(MasterHistoryConfig::AppendOnly {
+            unique_key_field: "id".to_string(),
+            open_date_field: "updated_at".to_string(),
+            closed_date_field: "closed_at".to_string(),
+            max_versions,
+        })
+    }
+
+    /// History with its state in a database of its own.
+    struct TestHistory {
+        history: SourceHistory,
+        txn: SharedTransaction,
+        databases: HistoryDatabases,
+        _dir: TempDir,
+    }
+
+    impl TestHistory {
+        fn schema(&self) -> &Schema {
+            self.history.schema()
+        }
+
+        fn apply(&self, op: Operation) -> Result<Vec<Operation>, SourceHistoryError> {
+            let mut txn = self.txn.write();
+            self.history
+                .apply(&mut self.databases.state(&mut txn, 1), op)
+        }
+    }
+
+    fn history(history_type: HistoryType, primary_index: Vec<usize>) -> TestHistory {
+        let dir = TempDir::new("source_history").unwrap();
+        let env =
+            LmdbEnvironmentManager::create(dir.path(), "history", Default::default()).unwrap();
+        let txn = env.create_txn().unwrap();
+        let databases = HistoryDatabases::new(&mut txn.write()).unwrap();
+        let history = SourceHistory::new(
+            &source(history_type),
+            schema(primary_index),
+            &ReplicationChangesTrackingType::FullChanges,
+        )
+        .unwrap()
+        .unwrap();
+        TestHistory {
+            history,
+            txn,
+            databases,
+            _dir: dir,
+        }
+    }
+
+    fn transactional(primary_index: Vec<usize>) -> TestHistory {
+        history(
+            HistoryType::Transactional(TransactionalHistoryConfig::RetainPartial {
+                timestamp_field: "updated_at".to_string(),
+                retention_period: 60,
+            }),
+            primary_index,
+        )
+    }
+
+    fn timestamp(secs: i64) -> Field {
+        Field::Timestamp(Utc.timestamp_opt(1_700_000_000 + secs, 0).unwrap().into())
+    }
+
+    fn record(id: i64, name: &str, secs: i64) -> Record {
+        Record::new(
+            schema(vec![]).identifier,
+            vec![
+                Field::Int(id),
+                Field::String(name.to_string()),
+                timestamp(secs),
+            ],
+            None,
+        )
+    }
+
+    fn insert(id: i64, name: &str, secs: i64) -> Operation {
+        Operation::Insert {
+            new: record(id, name, secs),
+        }
+    }
+
+    #[test]
+    fn test_master_history_keeps_versions() {
+        let history = history(master_config(None), vec![0]);
+        let schema = history.schema();
+        assert_eq!(schema.fields[3].name, "closed_at");
+        assert_eq!(schema.primary_index, vec![0, 2]);
+
+        let ops = history.apply(insert(1, "alice", 0)).unwrap();
+        let [Operation::Insert { new }] = ops.as_slice() else {
+            panic!("expected an insert");
+        };
+        assert_eq!(new.values[2..], [timestamp(0), Field::Null]);
+
+        // Updates opening a new version close the previous one
+        let ops = history
+            .apply(Operation::Update {
+                old: record(1, "alice", 0),
+                new: record(1, "alicia", 10),
+            })
+            .unwrap();
+        let [Operation::Insert { new }, Operation::Update { old, new: closed }] = ops.as_slice()
+        else {
+            panic!("expected an insert and an update");
+        };
+        assert_eq!(
+            new.values[1..],
+            [
+                Field::String("alicia".to_string()),
+                timestamp(10),
+                Field::Null
+            ]
+        );
+        assert_eq!(old.values[2..], [timestamp(0), Field::Null]);
+        assert_eq!(closed.values[2..], [timestamp(0), timestamp(10)]);
+
+        // Versions arriving late are closed by the following version
+        let ops = history.apply(insert(1, "ali", 5)).unwrap();
+        let [Operation::Insert { new }, Operation::Update { new: closed, .. }] = ops.as_slice()
+        else {
+            panic!("expected an insert and an update");
+        };
+        assert_eq!(new.values[2..], [timestamp(5), timestamp(10)]);
+        assert_eq!(closed.values[2..], [timestamp(0), timestamp(5)]);
+
+        // Deleting a version opens the previous one until the following one
+        let ops = history
+            .apply(Operation::Delete {
+                old: record(1, "ali", 5),
+            })
+            .unwrap();
+        let [Operation::Delete { old }, Operation::Update { new: closed, .. }] = ops.as_slice()
+        else {
+            panic!("expected a delete and an update");
+        };
+        assert_eq!(old.values[1], Field::String("ali".to_string()));
+        assert_eq!(closed.values[2..], [timestamp(0), timestamp(10)]);
+
+        // Versions are changed in place
+        let ops = history
+            .apply(Operation::Update {
+                old: record(1, "alicia", 10),
+                new: record(1, "alice", 10),
+            })
+            .unwrap();
+        let [Operation::Update { old, new }] = ops.as_slice() else {
+            panic!("expected an update");
+        };
+        assert_eq!(old.values[1], Field::String("alicia".to_string()));
+        assert_eq!(
+            new.values[1..],
+            [
+                Field::String("alice".to_string()),
+                timestamp(10),
+                Field::Null
+            ]
+        );
+    }
+
+    #[test]
+    fn test_master_history_deletes_oldest_versions() {
+        let history = history(master_config(Some(2)), vec![0]);
+        history.apply(insert(1, "a", 10)).unwrap();
+        history.apply(insert(1, "b", 20)).unwrap();
+
+        // The third version deletes the first one
+        let ops = history.apply(insert(1, "c", 30)).unwrap();
+        let [Operation::Insert { .. }, Operation::Update { .. }, Operation::Delete { old }] =
+            ops.as_slice()
+        else {
+            panic!("expected an insert, an update and a delete");
+        };
+        assert_eq!(old.values[2..], [timestamp(10), timestamp(20)]);
+
+        // Versions older than the kept ones are not added
+        assert!(history.apply(insert(1, "d", 0)).unwrap().is_empty());
+        assert!(history
+            .apply(Operation::Delete {
+                old: record(1, "a", 10)
+            })
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_transactional_history_expires_records() {
+        let history = transactional(vec![0]);
+        history.apply(insert(1, "a", 0)).unwrap();
+        history.apply(insert(2, "b", 30)).unwrap();
+
+        let ops = history.apply(insert(3, "c", 60)).unwrap();
+        let [Operation::Insert { .. }, Operation::Delete { old }] = ops.as_slice() else {
+            panic!("expected an insert and a delete");
+        };
+        assert_eq!(old.values[0], Field::Int(1));
+
+        // Records older than the retention period are not kept
+        let ops = history
+            .apply(Operation::Update {
+                old: record(2, "b", 30),
+                new: record(2, "b", 0),
+            })
+            .unwrap();
+        assert!(matches!(ops.as_slice(), [Operation::Delete { .. }]));
+        assert!(history
+            .apply(Operation::Delete {
+                old: record(2, "b", 0)
+            })
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_transactional_history_adds_row_ids() {
+        let history = transactional(vec![]);
+        assert_eq!(history.schema().fields[3].name, HISTORY_ROW_ID_FIELD);
+        assert_eq!(history.schema().primary_index, vec![3]);
+
+        for secs in [0, 1] {
+            let ops = history.apply(insert(1, "a", secs)).unwrap();
+            let [Operation::Insert { new }] = ops.as_slice() else {
+                panic!("expected an insert");
+            };
+            assert_eq!(new.values[3], Field::UInt(secs as u64 + 1));
+        }
+
+        // Records without primary key can't be matched
+        assert!(matches!(
+            history.apply(Operation::Delete {
+                old: record(1, "a", 0)
+            }),
+            Err(SourceHistoryError::ChangeWithoutPrimaryKey(_))
+        ));
+    }
+
+    #[test]
+    fn test_it_validates_history() {
+        let conflict = source(HistoryType::Master(MasterHistoryConfig::AppendOnly {
+            unique_key_field: "id".to_string(),
+            open_date_field: "updated_at".to_string(),
+            closed_date_field: "name".to_string(),
+            max_versions: None,
+        }));
+        assert!(matches!(
+            SourceHistory::new(
+                &conflict,
+                schema(vec![0]),
+                &ReplicationChangesTrackingType::FullChanges
+            ),
+            Err(SourceHistoryError::FieldConflict(_, _))
+        ));
+
+        assert!(matches!(
+            SourceHistory::new(
+                &source(master_config(None)),
+                schema(vec![0]),
+                &ReplicationChangesTrackingType::OnlyPK
+            ),
+            Err(SourceHistoryError::NotInPrimaryKey(_, _))
+        ));
+
+        let string_timestamp = source(HistoryType::Transactional(
+            TransactionalHistoryConfig::RetainPartial {
+                timestamp_field: "name".to_string(),
+                retention_period: 60,
+            },
+        ));
+        assert!(matches!(
+            SourceHistory::new(
+                &string_timestamp,
+                schema(vec![0]),
+                &ReplicationChangesTrackingType::FullChanges
+            ),
+            Err(SourceHistoryError::InvalidTimestampField(
+                _,
+                FieldType::String
+            ))
+        ));
+
+        let overwrite = source(HistoryType::Master(MasterHistoryConfig::Overwrite));
+        assert!(SourceHistory::new(
+            &overwrite,
+            schema(vec![0]),
+            &ReplicationChangesTrackingType::FullChanges
+        )
+        .unwrap()
+        .is_none());
+    }
+}
@@ -0,0 +1,489 @@
+use std::collections::HashSet;
+
+use dozer_sql::pipeline::record_expression::RecordExpression;
+use dozer_sql::sqlparser::ast::{Expr as SqlExpr, Ident};
+use dozer_sql::sqlparser::dialect::GenericDialect;
+use dozer_sql::sqlparser::parser::Parser;
+use dozer_types::models::source::{ColumnTransform, Redaction, Source};
+use dozer_types::types::{
+    Field, FieldType, Operation, Record, ReplicationChangesTrackingType, Schema,
+};
+use sha2::{Digest, Sha256};
+
+use crate::errors::SourceTransformError;
+
+/// What is done to a column of the source table.
+#[derive(Debug, Clone)]
+struct ColumnOp {
+    cast: Option<RecordExpression>,
+    redact: Option<Redaction>,
+}
+
+/// Renames, casts, redactions and filter of a source, applied to the operations of its table
+/// before they are sent to the pipeline.
+#[derive(Debug, Clone)]
+pub struct SourceTransform {
+    source_schema: Schema,
+    schema: Schema,
+    columns: Vec<ColumnOp>,
+    filter: Option<RecordExpression>,
+}
+
+impl SourceTransform {
+    /// Returns `None` if the source has neither transforms nor filter.
+    pub fn new(
+        source: &Source,
+        source_schema: Schema,
+        replication_type: &ReplicationChangesTrackingType,
+    ) -> Result<Option<Self>, SourceTransformError> {
+        if source.transforms.is_empty() && source.filter.is_none() {
+            return Ok(None);
+        }
+
+        let mut columns = vec![
+            ColumnOp {
+                cast: None,
+                redact: None,
+            };
+            source_schema.fields.len()
+        ];
+        let mut schema = source_schema.clone();
+
+        for ColumnTransform {
+            column,
+            rename,
+            cast,
+            redact,
+        } in &source.transforms
+        {
+            let idx = source_schema
+                .fields
+                .iter()
+                .position(|f| &f.name == column)
+                .ok_or_else(|| {
+                    SourceTransformError::ColumnNotFound(column.clone(), source.name.clone())
+                })?;
+            let field = &mut schema.fields[idx];
+
+            if let Some(name) = rename {
+                field.name = name.clone();
+            }
+
+            if let Some(typ) = cast {
+                let expression = build_cast(column, typ, &source_schema)?;
+                field.typ = expression.return_type(&source_schema)?;
+                columns[idx].cast = Some(expression);
+            }
+
+            if let Some(redaction) = redact {
+                match redaction {
+                    Redaction::Mask(_) | Redaction::Nullify(_)
+                        if source_schema.primary_index.contains(&idx) =>
+                    {
+                        // Keys would collide once redacted.
+                        return Err(SourceTransformError::RedactedPrimaryKey(
+                            column.clone(),
+                            source.name.clone(),
+                        ));
+                    }
+                    Redaction::Mask(_) | Redaction::Hash(_) => field.typ = FieldType::String,
+                    Redaction::Nullify(_) => field.nullable = true,
+                }
+                columns[idx].redact = Some(redaction.clone());
+            }
+        }
+
+        let mut names = HashSet::new();
+        for field in &schema.fields {
+            if !names.insert(&field.name) {
+                return Err(SourceTransformError::ColumnConflict(
+                    field.name.clone(),
+                    source.name.clone(),
+                ));
+            }
+        }
+
+        // The filter is evaluated on the old records of updates and deletes, which must be full.
+        if source.filter.is_some() && replication_type == &ReplicationChangesTrackingType::OnlyPK {
+            return Err(SourceTransformError::FilterWithoutOldRecords(
+                source.name.clone(),
+            ));
+        }
+        let filter = source
+            .filter
+            .as_ref()
+            .map(|filter| build_filter(filter, &source_schema))
+            .transpose()?;
+
+        Ok(Some(Self {
+            source_schema,
+            schema,
+            columns,
+            filter,
+        }))
+    }
+
+    /// Schema of the records once transformed.
+    pub fn schema(&self) -> &Schema {
+        &self.schema
+    }
+
+    /// Transforms the records of an operation, returning `None` if none of them matches the
+    /// filter. An update of a record entering or leaving the filter becomes an insert or a delete.
+    pub fn apply(&self, op: Operation) -> Result<Option<Operation>, SourceTransformError> {
+        Ok(match op {
+            Operation::Insert { new } => {
+                if self.matches(&new)? {
+                    Some(Operation::Insert {
+                        new: self.transform(new)?,
+                    })
+                } else {
+                    None
+                }
+            }
+            Operation::Delete { old } => {
+                if self.matches(&old)? {
+                    Some(Operation::Delete {
+                        old: self.transform(old)?,
+                    })
+                } else {
+                    None
+                }
+            }
+            Operation::Update { old, new } => match (self.matches(&old)?, self.matches(&new)?) {
+                (true, true) => Some(Operation::Update {
+                    old: self.transform(old)?,
+                    new: self.transform(new)?,
+                }),
+                (true, false) => Some(Operation::Delete {
+                    old: self.transform(old)?,
+                }),
+                (false, true) => Some(Operation::Insert {
+                    new: self.transform(new)?,
+                }),
+                (false, false) => None,
+            },
+        })
+    }
+
+    fn matches(&self, record: &Record) -> Result<bool, SourceTransformError> {
+        match &self.filter {
+            None => Ok(true),
+            Some(filter) => {
+                Ok(filter.evaluate(record, &self.source_schema)? == Field::Boolean(true))
+            }
+        }
+    }
+
+    fn transform(&self, record: Record) -> Result<Record, SourceTransformError> {
+        let values = self
+            .columns
+            .iter()
+            .zip(record.values.iter())
+            .map(|(op, value)| {
+                // Nulls are kept, where casts would turn them into default values.
+                let value = match &op.cast {
+                    Some(cast) if value != &Field::Null => {
+                        cast.evaluate(&record, &self.source_schema)?
+                    }
+                    _ => value.clone(),
+                };
+                Ok(match &op.redact {
+                    Some(redaction) => redact(redaction, value),
+                    None => value,
+                })
+            })
+            .collect::<Result<Vec<_>, SourceTransformError>>()?;
+        Ok(Record::new(record.schema_id, values, record.version))
+    }
+}
+
+fn build_cast(
+    column: &str,
+    typ: &str,
+    schema: &Schema,
+) -> Result<RecordExpression, SourceTransformError> {
+    let data_type = Parser::new(&GenericDialect {})
+        .try_with_sql(typ)
+        .and_then(|mut parser| parser.parse_data_type())
+        .map_err(|e| SourceTransformError::InvalidCastType(typ.to_string(), e))?;
+    let expression = SqlExpr::Cast {
+        expr: Box::new(SqlExpr::Identifier(Ident::new(column))),
+        data_type,
+    };
+    Ok(RecordExpression::new(&expression, schema)?)
+}
+
+fn build_filter(filter: &str, schema: &Schema) -> Result<RecordExpression, SourceTransformError> {
+    let expression = Parser::new(&GenericDialect {})
+        .try_with_sql(filter)
+        .and_then(|mut parser| parser.parse_expr())
+        .map_err(|e| SourceTransformError::InvalidFilter(filter.to_string(), e))?;
+    let expression = RecordExpression::new(&expression, schema)?;
+    let return_type = expression.return_type(schema)?;
+    if return_type != FieldType::Boolean {
+        return Err(SourceTransformError::FilterNotBoolean(
+            filter.to_string(),
+            return_type,
+        ));
+    }
+    Ok(expression)
+}
+
+fn redact(redaction: &Redaction, value: Field) -> Field {
+    if value == Field::Null {
+        return Field::Null;
+    }
+    match redaction {
+        Redaction::Mask(config) => {
+            let value = value.to_string().unwrap_or_default();
+            let count = value.chars().count();
+            let keep_from = count.saturating_sub(config.keep_last as usize);
+            Field::String(
+                value
+                    .chars()
+                    .enumerate()
+                    .map(|(i, c)| if i < keep_from { '*' } else { c })
+                    .collect(),
+            )
+        }
+        Redaction::Hash(config) => {
+            let mut hasher = Sha256::new();
+            if let Some(salt) = &config.salt {
+                hasher.update(salt.as_bytes());
+            }
+            match value.to_string() {
+                Some(value) => hasher.update(value.as_bytes()),
+                None => hasher.update(value.encode()),
+            }
+            Field::String(format!("{:x}", hasher.finalize()))
+        }
+        Redaction::Nullify(_) => Field::Null,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SourceTransform;
+    use crate::errors::SourceTransformError;
+    use dozer_types::models::source::{
+        ColumnTransform, HashConfig, MaskConfig, NullifyConfig, Redaction, Source,
+    };
+    use dozer_types::types::{
+        Field, FieldDefinition, FieldType, Operation, Record, ReplicationChangesTrackingType,
+        Schema, SchemaIdentifier, SourceDefinition,
+    };
+
+    fn schema() -> Schema {
+        let field = |name: &str, typ| {
+            FieldDefinition::new(name.to_string(), typ, true, SourceDefinition::Dynamic)
+        };
+        Schema {
+            identifier: Some(SchemaIdentifier { id: 1, version: 1 }),
+            fields: vec![
+                field("id", FieldType::Int),
+                field("email", FieldType::String),
+                field("card", FieldType::String),
+                field("balance", FieldType::String),
+            ],
+            primary_index: vec![0],
+        }
+    }
+
+    fn transform(column: &str) -> ColumnTransform {
+        ColumnTransform {
+            column: column.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn source(transforms: Vec<ColumnTransform>, filter: Option<&str>) -> Source {
+        Source {
+            name: "users".to_string(),
+            table_name: "users".to_string(),
+            transforms,
+            filter: filter.map(|f| f.to_string()),
+            ..Default::default()
+        }
+    }
+
+    fn new_transform(source: &Source) -> Result<Option<SourceTransform>, SourceTransformError> {
+        SourceTransform::new(
+            source,
+            schema(),
+            &ReplicationChangesTrackingType::FullChanges,
+        )
+    }
+
+    fn record(id: i64, balance: &str) -> Record {
+        Record::new(
+            schema().identifier,
+            vec![
+                Field::Int(id),
+                Field::String("alice@example.com".to_string()),
+                Field::String("4111111111111111".to_string()),
+                Field::String(balance.to_string()),
+            ],
+            None,
+        )
+    }
+
+    #[test]
+    fn test_it_transforms_columns() {
+        let transforms = vec![
+            ColumnTransform {
+                rename: Some("email_hash".to_string()),
+                redact: Some(Redaction::Hash(HashConfig { salt: None })),
+                ..transform("email")
+            },
+            ColumnTransform {
+                redact: Some(Redaction::Mask(MaskConfig { keep_last: 4 })),
+                ..transform("card")
+            },
+            ColumnTransform {
+                cast: Some("INT".to_string()),
+                ..transform("balance")
+            },
+        ];
+        let transform = new_transform(&source(transforms, None)).unwrap().unwrap();
+
+        let fields = &transform.schema().fields;
+        assert_eq!(fields[1].name, "email_hash");
+        assert_eq!(fields[3].name, "balance");
+        assert_eq!(fields[3].typ, FieldType::Int);
+
+        let Some(Operation::Insert { new }) = transform
+            .apply(Operation::Insert {
+                new: record(1, "42"),
+            })
+            .unwrap()
+        else {
+            panic!("expected an insert");
+        };
+        assert_eq!(
+            new.values,
+            vec![
+                Field::Int(1),
+                Field::String(
+                    "ff8d9819fc0e12bf0d24892e45987e249a28dce836a85cad60e28eaaa8c6d976".to_string()
+                ),
+                Field::String("************1111".to_string()),
+                Field::Int(42),
+            ]
+        );
+
+        // Nulls are neither cast nor redacted
+        let mut null_record = record(2, "");
+        null_record.values[2] = Field::Null;
+        null_record.values[3] = Field::Null;
+        let Some(Operation::Insert { new }) = transform
+            .apply(Operation::Insert { new: null_record })
+            .unwrap()
+        else {
+            panic!("expected an insert");
+        };
+        assert_eq!(new.values[2], Field::Null);
+        assert_eq!(new.values[3], Field::Null);
+    }
+
+    #[test]
+    fn test_it_filters_rows() {
+        let source = source(vec![], Some("CAST(balance AS INT) > 0"));
+        let transform = new_transform(&source).unwrap().unwrap();
+
+        assert!(transform
+            .apply(Operation::Insert {
+                new: record(1, "0")
+            })
+            .unwrap()
+            .is_none());
+        assert!(matches!(
+            transform.apply(Operation::Insert {
+                new: record(1, "10")
+            }),
+            Ok(Some(Operation::Insert { .. }))
+        ));
+
+        // Updates moving records in or out of the filter become inserts or deletes
+        let update = |old, new| Operation::Update {
+            old: record(1, old),
+            new: record(1, new),
+        };
+        assert!(matches!(
+            transform.apply(update("10", "20")),
+            Ok(Some(Operation::Update { .. }))
+        ));
+        assert!(matches!(
+            transform.apply(update("10", "0")),
+            Ok(Some(Operation::Delete { .. }))
+        ));
+        assert!(matches!(
+            transform.apply(update("0", "10")),
+            Ok(Some(Operation::Insert { .. }))
+        ));
+        assert!(transform.apply(update("0", "-10")).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_it_validates_transforms() {
+        assert!(new_transform(&source(vec![], None)).unwrap().is_none());
+
+        assert!(matches!(
+            new_transform(&source(vec![transform("missing")], None)),
+            Err(SourceTransformError::ColumnNotFound(_, _))
+        ));
+
+        let nullify_key = ColumnTransform {
+            redact: Some(Redaction::Nullify(NullifyConfig {})),
+            ..transform("id")
+        };
+        assert!(matches!(
+            new_transform(&source(vec![nullify_key], None)),
+            Err(SourceTransformError::RedactedPrimaryKey(_, _))
+        ));
+
+        let bad_cast = ColumnTransform {
+            cast: Some("123".to_string()),
+            ..transform("balance")
+        };
+        assert!(matches!(
+            new_transform(&source(vec![bad_cast], None)),
+            Err(SourceTransformError::InvalidCastType(_, _))
+        ));
+
+        assert!(matches!(
+            new_transform(&source(vec![], Some("balance"))),
+            Err(SourceTransformError::FilterNotBoolean(_, FieldType::String))
+        ));
+
+        let rename_to_existing = ColumnTransform {
+            rename: Some("email".to_string()),
+            ..transform("card")
+        };
+        assert!(matches!(
+            new_transform(&source(vec![rename_to_existing], None)),
+            Err(SourceTransformError::ColumnConflict(_, _))
+        ));
+        let swap = vec![
+            ColumnTransform {
+                rename: Some("card".to_string()),
+                ..transform("email")
+            },
+            ColumnTransform {
+                rename: Some("email".to_string()),
+                ..transform("card")
+            },
+        ];
+        assert!(new_transform(&source(swap, None)).unwrap().is_some());
+
+        // Old records only holding their primary key can't be filtered
+        assert!(matches!(
+            SourceTransform::new(
+                &source(vec![], Some("id > 0")),
+                schema(),
+                &ReplicationChangesTrackingType::OnlyPK
+            ),
+            Err(SourceTransformError::FilterWithoutOldRecords(_))
+        ));
+    }
+}
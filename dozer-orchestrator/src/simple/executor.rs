@@ -82,7 +82,12 @@ impl<'a> Executor<'a> {
 
         let used_sources = pipeline.get_entry_points_sources_names();
 
-        let source_builder = SourceBuilder::new(&used_sources, grouped_connections, None);
+        let source_builder = SourceBuilder::new(
+            &used_sources,
+            grouped_connections,
+            None,
+            Some(self.pipeline_dir),
+        );
         let asm = source_builder.build_source_manager()?;
 
         let mut app = App::new(asm);
@@ -5,7 +5,7 @@ use dozer_types::models::{
     api_security::ApiSecurity,
     app_config::{
         default_app_buffer_size, default_app_max_map_size, default_cache_max_map_size,
-        default_commit_size, default_commit_timeout, Config,
+        default_cache_max_scan_records, default_commit_size, default_commit_timeout, Config,
     },
 };
 use std::{
@@ -29,6 +29,12 @@ fn get_cache_max_map_size(config: &Config) -> u64 {
         .unwrap_or(default_cache_max_map_size())
 }
 
+fn get_cache_max_scan_records(config: &Config) -> u64 {
+    config
+        .cache_max_scan_records
+        .unwrap_or(default_cache_max_scan_records())
+}
+
 fn get_app_max_map_size(config: &Config) -> u64 {
     config
         .app_max_map_size
@@ -91,6 +97,7 @@ pub fn get_cache_manager_options(config: &Config) -> CacheManagerOptions {
     CacheManagerOptions {
         path: Some(get_cache_dir(config)),
         max_size: get_cache_max_map_size(config) as usize,
+        max_scan_records: get_cache_max_scan_records(config) as usize,
         ..CacheManagerOptions::default()
     }
 }
@@ -5,6 +5,7 @@ mod expression;
 mod planner;
 mod product;
 mod projection;
+pub mod record_expression;
 mod selection;
 
 #[cfg(test)]
@@ -0,0 +1,28 @@
+use dozer_types::types::{Field, FieldType, Record, Schema};
+use sqlparser::ast::Expr as SqlExpr;
+
+use crate::pipeline::errors::PipelineError;
+use crate::pipeline::expression::builder::ExpressionBuilder;
+use crate::pipeline::expression::execution::{Expression, ExpressionExecutor};
+
+/// Scalar expression on the records of a schema, evaluated outside of a pipeline.
+#[derive(Debug, Clone)]
+pub struct RecordExpression {
+    expression: Expression,
+}
+
+impl RecordExpression {
+    pub fn new(sql_expression: &SqlExpr, schema: &Schema) -> Result<Self, PipelineError> {
+        let expression =
+            ExpressionBuilder::new(schema.fields.len()).build(false, sql_expression, schema)?;
+        Ok(Self { expression })
+    }
+
+    pub fn return_type(&self, schema: &Schema) -> Result<FieldType, PipelineError> {
+        Ok(self.expression.get_type(schema)?.return_type)
+    }
+
+    pub fn evaluate(&self, record: &Record, schema: &Schema) -> Result<Field, PipelineError> {
+        self.expression.evaluate(record, schema)
+    }
+}
@@ -35,13 +35,21 @@ fn convert_filter(filter: Option<&FilterExpression>) -> Document {
     fn insert_filter_to_document_recursive(document: &mut Document, filter: &FilterExpression) {
         match filter {
             FilterExpression::Simple(name, operator, value) => match operator {
-                Operator::LT | Operator::LTE | Operator::EQ | Operator::GT | Operator::GTE => {
+                Operator::LT
+                | Operator::LTE
+                | Operator::EQ
+                | Operator::GT
+                | Operator::GTE
+                | Operator::NE
+                | Operator::In => {
                     let operator = match operator {
                         Operator::LT => "$lt",
                         Operator::LTE => "$lte",
                         Operator::EQ => "$eq",
                         Operator::GT => "$gt",
                         Operator::GTE => "$gte",
+                        Operator::NE => "$ne",
+                        Operator::In => "$in",
                         _ => unreachable!(),
                     };
                     document.insert(name, doc! {operator: bson::to_bson(value).unwrap()});
@@ -61,9 +69,25 @@ fn convert_filter(filter: Option<&FilterExpression>) -> Document {
                     insert_filter_to_document_recursive(document, filter)
                 }
             }
+            FilterExpression::Or(filters) => {
+                let filters = filters
+                    .iter()
+                    .map(convert_filter_recursive)
+                    .collect::<Vec<_>>();
+                document.insert("$or", filters);
+            }
+            FilterExpression::Not(filter) => {
+                document.insert("$nor", vec![convert_filter_recursive(filter)]);
+            }
         }
     }
 
+    fn convert_filter_recursive(filter: &FilterExpression) -> Document {
+        let mut document = Document::new();
+        insert_filter_to_document_recursive(&mut document, filter);
+        document
+    }
+
     let mut document = Document::new();
     if let Some(filter) = filter {
         insert_filter_to_document_recursive(&mut document, filter);
@@ -349,6 +349,11 @@ fn write_dozer_config_for_running_in_docker_compose(
             }
             ConnectionConfig::S3Storage(_) => {}
             ConnectionConfig::LocalStorage(_) => {}
+            ConnectionConfig::Webhook(_) => (),
+            ConnectionConfig::Sqlite(_) => (),
+            ConnectionConfig::Odbc(_) => {
+                todo!("Map odbc connection string")
+            }
         }
     }
 
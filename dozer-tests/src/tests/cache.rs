@@ -1,7 +1,7 @@
 use dozer_cache::cache::{expression::QueryExpression, RoCache};
 use dozer_types::{
     serde_json::{self, json, Value},
-    types::IndexDefinition,
+    types::{IndexDefinition, TextAnalyzer},
 };
 use mongodb::Collection;
 
@@ -21,7 +21,7 @@ async fn test_cache_query() {
         IndexDefinition::SortedInverted(vec![3, 7, 0]),
         IndexDefinition::SortedInverted(vec![5, 0]),
         IndexDefinition::SortedInverted(vec![7, 0]),
-        IndexDefinition::FullText(12),
+        IndexDefinition::FullText(12, TextAnalyzer::default()),
     ];
 
     let (cache, schema_name, collection) = load_database(secondary_indexes).await;
@@ -37,6 +37,11 @@ async fn test_cache_query() {
         json!({ "$filter": { "rental_rate": { "$gt": 2 } } }),
         json!({ "$filter": { "film_id": { "$gte": 113 }, "release_year": 2006 } }),
         json!({ "$filter": { "film_id": { "$gte": 113 }, "release_year": 2006, "rental_rate": 0.99 } }),
+        json!({ "$filter": { "film_id": { "$in": [3, 17, 317] } } }),
+        json!({ "$filter": { "$or": [{ "film_id": { "$lt": 30 } }, { "rental_rate": { "$gt": 4 } }] } }),
+        json!({ "$filter": { "$or": [{ "film_id": { "$lt": 30 } }, { "length": { "$gt": 180 } }] } }),
+        json!({ "$filter": { "rental_rate": { "$ne": 0.99 } } }),
+        json!({ "$filter": { "$not": { "film_id": { "$gte": 30 } } } }),
         // only order by
         json!({ "$order_by": { "film_id": "desc" } }),
         json!({ "$order_by": { "original_language_id": "asc" } }),
@@ -58,6 +63,7 @@ async fn test_cache_query() {
         json!({ "$filter": { "rental_rate": { "$gt": 2 } }, "$order_by": { "rental_rate": "desc" } }),
         json!({ "$filter": { "film_id": { "$gte": 113 }, "release_year": 2006 },  "$order_by": { "film_id": "asc" } }),
         json!({ "$filter": { "film_id": { "$gte": 113 }, "release_year": 2006 },  "$order_by": { "film_id": "desc" } }),
+        json!({ "$filter": { "$or": [{ "film_id": { "$lt": 30 } }, { "rental_rate": { "$gt": 4 } }] }, "$order_by": { "film_id": "desc" } }),
         json!({ "$filter": { "film_id": { "$gte": 113 }, "release_year": 2006, "rental_rate": 0.99 }, "$order_by": { "film_id": "asc" } }),
         json!({ "$filter": { "film_id": { "$gte": 113 }, "release_year": 2006, "rental_rate": 0.99 }, "$order_by": { "film_id": "desc" } }),
         // filter + skip
@@ -42,6 +42,11 @@ pub enum IngestorError {
 
 pub trait IngestorForwarder: Send + Sync + Debug {
     fn forward(&self, msg: IngestionMessage) -> Result<(), IngestorError>;
+
+    /// Forwards messages in order. Forwarders able to send them at once should override it.
+    fn forward_batch(&self, msgs: Vec<IngestionMessage>) -> Result<(), IngestorError> {
+        msgs.into_iter().try_for_each(|msg| self.forward(msg))
+    }
 }
 
 #[derive(Serialize, Deserialize, Eq, PartialEq, Clone, ::prost::Message, Hash)]
@@ -72,6 +77,10 @@ pub struct GrpcConfig {
     #[prost(string, tag = "5", default = "default")]
     #[serde(default = "default_grpc_adapter")]
     pub adapter: String,
+    #[prost(string, optional, tag = "6")]
+    #[serde(default)]
+    /// file in which requests are persisted before being acknowledged, so that they are ingested exactly once across restarts; Type: String
+    pub journal_path: Option<String>,
 }
 
 fn default_grpc_adapter() -> String {
@@ -94,9 +103,147 @@ pub enum GrpcConfigSchemas {
     Path(String),
 }
 
+#[derive(Serialize, Deserialize, Eq, PartialEq, Clone, ::prost::Message, Hash)]
+pub struct WebhookConfig {
+    #[prost(string, tag = "1", default = "0.0.0.0")]
+    #[serde(default = "default_ingest_host")]
+    pub host: String,
+    #[prost(uint32, tag = "2", default = "8090")]
+    #[serde(default = "default_webhook_port")]
+    pub port: u32,
+    #[prost(oneof = "GrpcConfigSchemas", tags = "3,4")]
+    pub schemas: Option<GrpcConfigSchemas>,
+    #[prost(uint64, tag = "5", default = "1000")]
+    #[serde(default = "default_webhook_max_batch_size")]
+    /// maximum number of records accepted in a single request; Default: 1000
+    pub max_batch_size: u64,
+    #[prost(string, optional, tag = "6")]
+    #[serde(default)]
+    /// file in which requests are persisted before being acknowledged, so that they are ingested exactly once across restarts; Type: String
+    pub journal_path: Option<String>,
+}
+
+fn default_webhook_port() -> u32 {
+    8090
+}
+
+fn default_webhook_max_batch_size() -> u64 {
+    1000
+}
+
+impl WebhookConfig {
+    pub fn convert_to_table(&self) -> PrettyTable {
+        table!(
+            ["host", self.host],
+            ["port", self.port],
+            ["max_batch_size", self.max_batch_size]
+        )
+    }
+}
+
+#[derive(Serialize, Deserialize, Eq, PartialEq, Clone, ::prost::Message, Hash)]
+pub struct SqliteConfig {
+    #[prost(string, tag = "1")]
+    /// path of the database file
+    pub path: String,
+    #[prost(string, tag = "2", default = "rowid")]
+    #[serde(default = "default_sqlite_change_detection")]
+    /// how changes are picked up: `rowid`, `updated_at` or `triggers`; Default: rowid
+    pub change_detection: String,
+    #[prost(string, optional, tag = "3")]
+    #[serde(default)]
+    /// column holding the last update time of the rows, required by `updated_at`
+    pub updated_at_column: Option<String>,
+    #[prost(uint64, tag = "4", default = "1000")]
+    #[serde(default = "default_sqlite_poll_interval")]
+    /// interval between two polls for changes, in milliseconds; Default: 1000
+    pub poll_interval_ms: u64,
+}
+
+fn default_sqlite_change_detection() -> String {
+    "rowid".to_owned()
+}
+
+fn default_sqlite_poll_interval() -> u64 {
+    1000
+}
+
+impl SqliteConfig {
+    pub fn convert_to_table(&self) -> PrettyTable {
+        table!(
+            ["path", self.path],
+            ["change_detection", self.change_detection],
+            [
+                "updated_at_column",
+                self.updated_at_column.as_deref().unwrap_or("--------")
+            ],
+            ["poll_interval_ms", self.poll_interval_ms]
+        )
+    }
+}
+
+#[derive(Serialize, Deserialize, Eq, PartialEq, Clone, ::prost::Message, Hash)]
+pub struct OdbcConfig {
+    #[prost(string, tag = "1")]
+    /// ODBC connection string, naming the driver or the data source to use
+    pub connection_string: String,
+    #[prost(message, repeated, tag = "2")]
+    /// queries exposed as tables
+    pub queries: Vec<OdbcQuery>,
+    #[prost(uint64, tag = "3", default = "5000")]
+    #[serde(default = "default_odbc_poll_interval")]
+    /// interval between two polls for new rows, in milliseconds; Default: 5000
+    pub poll_interval_ms: u64,
+    #[prost(string, optional, tag = "4")]
+    #[serde(default)]
+    /// file in which the state of the queries is persisted, so that ingestion resumes from the last checkpoint; Type: String
+    pub state_path: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Eq, PartialEq, Clone, ::prost::Message, Hash)]
+pub struct OdbcQuery {
+    #[prost(string, tag = "1")]
+    /// name of the table exposing the rows of the query
+    pub name: String,
+    #[prost(string, tag = "2")]
+    /// SELECT statement reading the rows
+    pub query: String,
+    #[prost(string, tag = "3")]
+    /// column increasing when rows are added, or updated, such as an id or an update time
+    pub cursor_column: String,
+    #[prost(string, repeated, tag = "4")]
+    #[serde(default)]
+    /// columns identifying a row; when set, rows read again are ingested as updates
+    pub primary_key: Vec<String>,
+    #[prost(string, optional, tag = "5")]
+    #[serde(default)]
+    /// column flagging deleted rows, which requires a primary key
+    pub soft_delete_column: Option<String>,
+}
+
+fn default_odbc_poll_interval() -> u64 {
+    5000
+}
+
+impl OdbcConfig {
+    pub fn convert_to_table(&self) -> PrettyTable {
+        let queries = self
+            .queries
+            .iter()
+            .map(|q| q.name.as_str())
+            .collect::<Vec<_>>()
+            .join(", ");
+        table!(
+            ["connection_string", "*************"],
+            ["queries", queries],
+            ["poll_interval_ms", self.poll_interval_ms]
+        )
+    }
+}
+
 #[derive(Serialize, Deserialize, Eq, PartialEq, Clone, ::prost::Message, Hash)]
 pub struct EthConfig {
-    #[prost(oneof = "EthProviderConfig", tags = "2,3")]
+    #[prost(oneof = "EthProviderConfig", tags = "2,3,4")]
     pub provider: Option<EthProviderConfig>,
 }
 
@@ -106,6 +253,8 @@ pub enum EthProviderConfig {
     Log(EthLogConfig),
     #[prost(message, tag = "3")]
     Trace(EthTraceConfig),
+    #[prost(message, tag = "4")]
+    Block(EthBlockConfig),
 }
 
 #[derive(Serialize, Deserialize, Eq, PartialEq, Clone, ::prost::Message, Hash)]
@@ -117,6 +266,10 @@ pub struct EthLogConfig {
     #[prost(message, repeated, tag = "3")]
     #[serde(default)]
     pub contracts: Vec<EthContract>,
+    #[prost(uint64, tag = "4")]
+    #[serde(default)]
+    /// number of blocks mined on top of a block before its logs are ingested; Default: 0
+    pub confirmations: u64,
 }
 
 #[derive(Serialize, Deserialize, Eq, PartialEq, Clone, ::prost::Message, Hash)]
@@ -133,6 +286,25 @@ pub struct EthTraceConfig {
     pub batch_size: u64,
 }
 
+#[derive(Serialize, Deserialize, Eq, PartialEq, Clone, ::prost::Message, Hash)]
+pub struct EthBlockConfig {
+    #[prost(string, tag = "1")]
+    pub wss_url: String,
+    // Starting block
+    #[prost(uint64, tag = "2")]
+    pub from_block: u64,
+    #[prost(uint64, optional, tag = "3")]
+    pub to_block: Option<u64>,
+    #[prost(uint64, tag = "4", default = "100")]
+    #[serde(default = "default_block_batch_size")]
+    /// number of blocks requested at once while backfilling; Default: 100
+    pub batch_size: u64,
+}
+
+fn default_block_batch_size() -> u64 {
+    100
+}
+
 fn default_batch_size() -> u64 {
     3
 }
@@ -152,12 +324,24 @@ impl EthConfig {
                 if !log.contracts.is_empty() {
                     table.add_row(row!["contracts", format!("{:?}", log.contracts)]);
                 }
+                if log.confirmations > 0 {
+                    table.add_row(row!["confirmations", log.confirmations]);
+                }
             }
             EthProviderConfig::Trace(trace) => {
                 table.add_row(row!["https_url", format!("{:?}", trace.https_url)]);
                 table.add_row(row!["provider", "traces"]);
                 table.add_row(row!("trace", format!("{trace:?}")));
             }
+            EthProviderConfig::Block(block) => {
+                table.add_row(row!["wss_url", format!("{:?}", block.wss_url)]);
+                table.add_row(row!["provider", "blocks"]);
+                table.add_row(row!["from_block", block.from_block]);
+                if let Some(to_block) = block.to_block {
+                    table.add_row(row!["to_block", to_block]);
+                }
+                table.add_row(row!["batch_size", block.batch_size]);
+            }
         }
         table
     }
@@ -179,6 +363,30 @@ pub struct KafkaConfig {
     pub broker: String,
     #[prost(string, optional, tag = "3")]
     pub schema_registry_url: Option<String>,
+    #[prost(string, tag = "4", default = "debezium")]
+    #[serde(default = "default_kafka_format")]
+    /// format of the message values: `debezium`, `json`, `avro` or `protobuf`; Default: debezium
+    pub format: String,
+    #[prost(oneof = "KafkaConfigSchemas", tags = "5,6")]
+    #[serde(default)]
+    /// declared schemas of the topics, required for `json` format
+    pub schemas: Option<KafkaConfigSchemas>,
+    #[prost(string, optional, tag = "7")]
+    #[serde(default)]
+    /// file in which the consumed offsets and the key changes of the upserted records are persisted, so that ingestion resumes from the last checkpoint. The keys are kept in a SQLite database at the same path with a `.keys` suffix, or in a temporary directory without state path; Type: String
+    pub state_path: Option<String>,
+}
+
+fn default_kafka_format() -> String {
+    "debezium".to_owned()
+}
+
+#[derive(Serialize, Deserialize, Eq, PartialEq, Clone, ::prost::Oneof, Hash)]
+pub enum KafkaConfigSchemas {
+    #[prost(string, tag = "5")]
+    Inline(String),
+    #[prost(string, tag = "6")]
+    Path(String),
 }
 
 impl KafkaConfig {
@@ -190,7 +398,8 @@ impl KafkaConfig {
                 self.schema_registry_url
                     .as_ref()
                     .map_or("--------", |url| url)
-            ]
+            ],
+            ["format", self.format]
         )
     }
 }
@@ -213,10 +422,27 @@ pub struct SnowflakeConfig {
     pub warehouse: String,
     #[prost(string, optional, tag = "8")]
     pub driver: Option<String>,
+    #[prost(oneof = "SnowflakeChangeTracking", tags = "9,10,11")]
+    #[serde(default)]
+    /// how changes of the tables are read; Default: stream
+    pub change_tracking: Option<SnowflakeChangeTracking>,
+    #[prost(uint32, tag = "12", default = "1")]
+    #[serde(default = "default_snowflake_parallelism")]
+    /// number of tables read concurrently, each with its own connection; Default: 1
+    pub parallelism: u32,
+}
+
+fn default_snowflake_parallelism() -> u32 {
+    1
 }
 
 impl SnowflakeConfig {
     pub fn convert_to_table(&self) -> PrettyTable {
+        let change_tracking = match &self.change_tracking {
+            None | Some(SnowflakeChangeTracking::Stream(_)) => "stream",
+            Some(SnowflakeChangeTracking::Changes(_)) => "changes",
+            Some(SnowflakeChangeTracking::Incremental(_)) => "incremental",
+        };
         table!(
             ["server", self.server],
             ["port", self.port],
@@ -225,11 +451,48 @@ impl SnowflakeConfig {
             ["database", self.database],
             ["schema", self.schema],
             ["warehouse", self.warehouse],
-            ["driver", self.driver.as_ref().map_or("default", |d| d)]
+            ["driver", self.driver.as_ref().map_or("default", |d| d)],
+            ["change_tracking", change_tracking],
+            ["parallelism", self.parallelism]
         )
     }
 }
 
+#[derive(Serialize, Deserialize, Eq, PartialEq, Clone, ::prost::Oneof, Hash)]
+pub enum SnowflakeChangeTracking {
+    #[prost(message, tag = "9")]
+    /// reads a stream created on each table, which keeps the offset of the ingested changes
+    Stream(SnowflakeStreamConfig),
+    #[prost(message, tag = "10")]
+    /// reads the `CHANGES` of each table, which must have change tracking enabled
+    Changes(SnowflakeChangesConfig),
+    #[prost(message, tag = "11")]
+    /// reads the rows of each table having a cursor column value greater than the last read one
+    Incremental(SnowflakeIncrementalConfig),
+}
+
+#[derive(Serialize, Deserialize, Eq, PartialEq, Clone, ::prost::Message, Hash)]
+pub struct SnowflakeStreamConfig {}
+
+#[derive(Serialize, Deserialize, Eq, PartialEq, Clone, ::prost::Message, Hash)]
+pub struct SnowflakeChangesConfig {}
+
+#[derive(Serialize, Deserialize, Eq, PartialEq, Clone, ::prost::Message, Hash)]
+pub struct SnowflakeIncrementalConfig {
+    #[prost(message, repeated, tag = "1")]
+    /// cursor column of each table
+    pub cursor_columns: Vec<SnowflakeCursorColumn>,
+}
+
+#[derive(Serialize, Deserialize, Eq, PartialEq, Clone, ::prost::Message, Hash)]
+pub struct SnowflakeCursorColumn {
+    #[prost(string, tag = "1")]
+    pub table: String,
+    #[prost(string, tag = "2")]
+    /// column increasing when rows are added, or updated, such as an id or an update time
+    pub column: String,
+}
+
 #[derive(Serialize, Deserialize, Eq, PartialEq, Clone, ::prost::Message, Hash)]
 pub struct DataFusionConfig {
     #[prost(string, tag = "1")]
@@ -263,6 +526,33 @@ pub struct Table {
     pub file_type: String,
     #[prost(string, tag = "4")]
     pub extension: String,
+    #[prost(bool, tag = "5")]
+    #[serde(default)]
+    /// keep polling the prefix and ingest new files as they land; Default: false
+    pub watch: bool,
+    #[prost(string, repeated, tag = "6")]
+    #[serde(default)]
+    /// glob patterns, relative to the prefix, of the files to ingest; Default: all files
+    pub patterns: Vec<String>,
+    #[prost(bool, tag = "7")]
+    #[serde(default)]
+    /// extract `key=value` directories of the file paths into columns; Default: false
+    pub hive_partitioning: bool,
+    #[prost(message, repeated, tag = "8")]
+    #[serde(default)]
+    /// only ingest the files of the partitions matching all the filters
+    pub partition_filters: Vec<PartitionFilter>,
+}
+
+#[derive(Serialize, Deserialize, Eq, PartialEq, Clone, ::prost::Message, Hash)]
+pub struct PartitionFilter {
+    #[prost(string, tag = "1")]
+    pub column: String,
+    #[prost(string, tag = "2")]
+    /// one of `=`, `!=`, `<`, `<=`, `>` and `>=`
+    pub operator: String,
+    #[prost(string, tag = "3")]
+    pub value: String,
 }
 
 #[derive(Serialize, Deserialize, Eq, PartialEq, Clone, ::prost::Message, Hash)]
@@ -283,6 +573,10 @@ pub struct S3Storage {
     pub details: Option<S3Details>,
     #[prost(message, repeated, tag = "2")]
     pub tables: Vec<Table>,
+    #[prost(string, optional, tag = "3")]
+    #[serde(default)]
+    /// file in which the ingested files are persisted, so that ingestion resumes from the last checkpoint; Type: String
+    pub state_path: Option<String>,
 }
 
 impl S3Storage {
@@ -313,6 +607,10 @@ pub struct LocalStorage {
     pub details: Option<LocalDetails>,
     #[prost(message, repeated, tag = "2")]
     pub tables: Vec<Table>,
+    #[prost(string, optional, tag = "3")]
+    #[serde(default)]
+    /// file in which the ingested files are persisted, so that ingestion resumes from the last checkpoint; Type: String
+    pub state_path: Option<String>,
 }
 
 impl LocalStorage {
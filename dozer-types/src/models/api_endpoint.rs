@@ -4,7 +4,80 @@ use serde::{Deserialize, Serialize};
 #[derive(Serialize, Deserialize, Eq, PartialEq, Clone, ::prost::Message)]
 pub struct ApiIndex {
     #[prost(string, repeated, tag = "1")]
+    #[serde(default)]
     pub primary_key: Vec<String>,
+    #[prost(message, optional, tag = "2")]
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    /// secondary indexes of the cache; by default every field gets a sorted inverted index, string fields also get a full text index, and point fields also get a spatial index
+    pub secondary: Option<SecondaryIndexConfig>,
+}
+
+#[derive(Serialize, Deserialize, Eq, PartialEq, Clone, ::prost::Message)]
+pub struct SecondaryIndexConfig {
+    #[prost(string, repeated, tag = "1")]
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    /// fields which don't get the default indexes; `"*"` skips them for all fields; Type: Array of String
+    pub skip_default: Vec<String>,
+    #[prost(message, repeated, tag = "2")]
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    /// indexes created in addition to the default ones
+    pub create: Vec<CreateSecondaryIndex>,
+}
+
+#[derive(Serialize, Deserialize, Eq, PartialEq, Clone, ::prost::Message)]
+pub struct CreateSecondaryIndex {
+    #[prost(oneof = "SecondaryIndex", tags = "1, 2, 3")]
+    /// Type: `!SortedInverted`, `!FullText` or `!Spatial`
+    pub index: Option<SecondaryIndex>,
+}
+
+#[derive(Serialize, Deserialize, Eq, PartialEq, Clone, ::prost::Oneof)]
+pub enum SecondaryIndex {
+    #[prost(message, tag = "1")]
+    /// answers equality filters on all fields, and range filters and sorting on the last one
+    SortedInverted(SortedInverted),
+    #[prost(message, tag = "2")]
+    /// answers `$contains`, `$matches_any`, `$matches_all`, `$matches_phrase` and `$matches_prefix` filters on a string or text field, and sorting by `$score`
+    FullText(FullText),
+    #[prost(message, tag = "3")]
+    /// answers `$within_radius`, `$within_bbox` and `$nearest` filters on a point field
+    Spatial(Spatial),
+}
+
+#[derive(Serialize, Deserialize, Eq, PartialEq, Clone, ::prost::Message)]
+pub struct SortedInverted {
+    #[prost(string, repeated, tag = "1")]
+    /// fields of the index, in order; Type: Array of String
+    pub fields: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Eq, PartialEq, Clone, ::prost::Message)]
+pub struct FullText {
+    #[prost(string, tag = "1")]
+    /// field of the index; Type: String
+    pub field: String,
+    #[prost(bool, tag = "2")]
+    #[serde(default)]
+    /// lowercase the text, so searches are case insensitive; Type: Boolean
+    pub lowercase: bool,
+    #[prost(bool, tag = "3")]
+    #[serde(default)]
+    /// ignore common English words, like "the" or "of"; Type: Boolean
+    pub stop_words: bool,
+    #[prost(bool, tag = "4")]
+    #[serde(default)]
+    /// reduce English words to their stem, so "running" also finds "runs"; Type: Boolean
+    pub stemming: bool,
+}
+
+#[derive(Serialize, Deserialize, Eq, PartialEq, Clone, ::prost::Message)]
+pub struct Spatial {
+    #[prost(string, tag = "1")]
+    /// point field of the index; Type: String
+    pub field: String,
 }
 
 #[derive(Deserialize, Eq, PartialEq, Clone, ::prost::Message)]
@@ -20,6 +93,40 @@ pub struct ApiEndpoint {
     pub path: String,
     #[prost(message, tag = "4")]
     pub index: Option<ApiIndex>,
+    #[prost(message, optional, tag = "5")]
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    /// limits on the records kept in the cache, the ones over them are removed in the background
+    pub retention: Option<CacheRetentionConfig>,
+    #[prost(bool, tag = "6")]
+    #[serde(default)]
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    /// keep the previous versions of the records, which can be queried `$as_of` a past commit and listed at `{path}/{id}/history`; Type: Boolean
+    pub history: bool,
+}
+
+#[derive(Serialize, Deserialize, Eq, PartialEq, Clone, ::prost::Message)]
+pub struct CacheRetentionConfig {
+    #[prost(string, optional, tag = "1")]
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    /// timestamp or date column the retention period applies to; Type: String
+    pub timestamp_field: Option<String>,
+    #[prost(uint64, optional, tag = "2")]
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    /// records whose `timestamp_field` is older than this number of seconds are removed; Type: u64
+    pub retention_period: Option<u64>,
+    #[prost(uint64, optional, tag = "3")]
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    /// only the latest records are kept when there are more; Type: u64
+    pub max_records: Option<u64>,
+    #[prost(uint64, optional, tag = "4")]
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    /// only the latest records are kept when they take more bytes; Type: u64
+    pub max_bytes: Option<u64>,
 }
 
 impl Serialize for ApiEndpoint {
@@ -27,11 +134,18 @@ impl Serialize for ApiEndpoint {
     where
         S: serde::Serializer,
     {
-        let mut state = serializer.serialize_struct("ApiEndpoint", 4)?;
+        let len = 4 + self.retention.is_some() as usize + self.history as usize;
+        let mut state = serializer.serialize_struct("ApiEndpoint", len)?;
         state.serialize_field("name", &self.name)?;
         state.serialize_field("table_name", &self.table_name)?;
         state.serialize_field("path", &self.path)?;
         state.serialize_field("index", &self.index)?;
+        if let Some(retention) = &self.retention {
+            state.serialize_field("retention", retention)?;
+        }
+        if self.history {
+            state.serialize_field("history", &self.history)?;
+        }
 
         state.end()
     }
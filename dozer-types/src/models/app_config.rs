@@ -71,6 +71,11 @@ pub struct Config {
     #[prost(uint64, optional, tag = "14")]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub commit_timeout: Option<u64>,
+
+    /// Maximum number of records a cache query may scan when no secondary index answers it
+    #[prost(uint64, optional, tag = "15")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cache_max_scan_records: Option<u64>,
 }
 
 pub fn default_home_dir() -> String {
@@ -97,6 +102,10 @@ pub fn default_commit_timeout() -> u64 {
     50
 }
 
+pub fn default_cache_max_scan_records() -> u64 {
+    100_000
+}
+
 impl<'de> Deserialize<'de> for Config {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
@@ -130,6 +139,8 @@ impl<'de> Deserialize<'de> for Config {
                 let mut app_buffer_size: Option<u32> = Some(default_app_buffer_size());
                 let mut commit_size: Option<u32> = Some(default_commit_size());
                 let mut commit_timeout: Option<u64> = Some(default_commit_timeout());
+                let mut cache_max_scan_records: Option<u64> =
+                    Some(default_cache_max_scan_records());
 
                 while let Some(key) = access.next_key()? {
                     match key {
@@ -172,6 +183,9 @@ impl<'de> Deserialize<'de> for Config {
                         "commit_timeout" => {
                             commit_timeout = access.next_value::<Option<u64>>()?;
                         }
+                        "cache_max_scan_records" => {
+                            cache_max_scan_records = access.next_value::<Option<u64>>()?;
+                        }
                         _ => {
                             access.next_value::<IgnoredAny>()?;
                         }
@@ -242,6 +256,7 @@ impl<'de> Deserialize<'de> for Config {
                     app_buffer_size,
                     commit_size,
                     commit_timeout,
+                    cache_max_scan_records,
                 })
             }
         }
@@ -21,6 +21,21 @@ pub struct Source {
     #[serde(skip_serializing_if = "Option::is_none")]
     /// setting for how to refresh the data; Default: RealTime
     pub refresh_config: Option<RefreshConfig>,
+    #[prost(message, repeated, tag = "8")]
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    /// renames, casts and redactions applied to the columns before the records are ingested; Type: ColumnTransform[]
+    pub transforms: Vec<ColumnTransform>,
+    #[prost(string, optional, tag = "9")]
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    /// SQL predicate on the columns of the table, rows not matching it are not ingested; Type: String
+    pub filter: Option<String>,
+    #[prost(message, optional, tag = "10")]
+    #[serde(default)]
+    #[serde(with = "serde_yaml::with::singleton_map_recursive")]
+    /// how the history of the records is kept, only their current version is kept if not set; Type: `Master` or `Transactional` map
+    pub history_type: Option<HistoryType>,
 }
 fn default_refresh_config() -> Option<RefreshConfig> {
     Some(RefreshConfig::default())
@@ -30,7 +45,7 @@ impl Serialize for Source {
     where
         S: serde::Serializer,
     {
-        let mut state = serializer.serialize_struct("Source", 5)?;
+        let mut state = serializer.serialize_struct("Source", 8)?;
         state.serialize_field("name", &self.name)?;
         state.serialize_field("table_name", &self.table_name)?;
         state.serialize_field("columns", &self.columns)?;
@@ -39,9 +54,92 @@ impl Serialize for Source {
             &Value::Ref(self.connection.to_owned().unwrap_or_default().name),
         )?;
         state.serialize_field("refresh_config", &self.refresh_config)?;
+        if self.transforms.is_empty() {
+            state.skip_field("transforms")?;
+        } else {
+            state.serialize_field("transforms", &self.transforms)?;
+        }
+        if self.filter.is_none() {
+            state.skip_field("filter")?;
+        } else {
+            state.serialize_field("filter", &self.filter)?;
+        }
+        if self.history_type.is_none() {
+            state.skip_field("history_type")?;
+        } else {
+            state.serialize_field("history_type", &SingletonMap(&self.history_type))?;
+        }
         state.end()
     }
 }
+
+// YAML tags can't be nested, so nested enums are written as maps with a single key.
+struct SingletonMap<'a, T>(&'a T);
+
+impl<'a, T: Serialize> Serialize for SingletonMap<'a, T> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serde_yaml::with::singleton_map_recursive::serialize(self.0, serializer)
+    }
+}
+
+#[derive(Serialize, Deserialize, Eq, PartialEq, Clone, ::prost::Message)]
+pub struct ColumnTransform {
+    #[prost(string, tag = "1")]
+    /// name of the column in the source table; Type: String
+    pub column: String,
+    #[prost(string, optional, tag = "2")]
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    /// name given to the column; Type: String
+    pub rename: Option<String>,
+    #[prost(string, optional, tag = "3")]
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    /// SQL type the column is cast to, overriding the inferred one, eg. `DECIMAL` or `TIMESTAMP`; Type: String
+    pub cast: Option<String>,
+    #[prost(oneof = "Redaction", tags = "4, 5, 6")]
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    /// how the values are redacted, they never reach the cache in clear; Type: `!Mask`, `!Hash` or `!Nullify`
+    pub redact: Option<Redaction>,
+}
+
+#[derive(Serialize, Deserialize, Eq, PartialEq, Clone, ::prost::Oneof)]
+pub enum Redaction {
+    #[prost(message, tag = "4")]
+    /// replaces the characters of the values with `*`, the column becomes a string
+    Mask(MaskConfig),
+    #[prost(message, tag = "5")]
+    /// replaces the values with their SHA-256 hash in hexadecimal, the column becomes a string
+    Hash(HashConfig),
+    #[prost(message, tag = "6")]
+    /// replaces the values with null
+    Nullify(NullifyConfig),
+}
+
+#[derive(Serialize, Deserialize, Eq, PartialEq, Clone, ::prost::Message)]
+pub struct MaskConfig {
+    #[prost(uint32, tag = "1")]
+    #[serde(default)]
+    /// number of trailing characters left in clear; Default: 0
+    pub keep_last: u32,
+}
+
+#[derive(Serialize, Deserialize, Eq, PartialEq, Clone, ::prost::Message)]
+pub struct HashConfig {
+    #[prost(string, optional, tag = "1")]
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    /// prepended to the values before hashing, so that hashes of known values can't be precomputed; Type: String
+    pub salt: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Eq, PartialEq, Clone, ::prost::Message)]
+pub struct NullifyConfig {}
+
 #[derive(Debug, Serialize, Deserialize, Eq, PartialEq, Clone)]
 pub enum Value {
     Ref(String),
@@ -58,6 +156,10 @@ pub enum MasterHistoryConfig {
         unique_key_field: String,
         open_date_field: String,
         closed_date_field: String,
+        #[serde(default)]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        /// number of versions kept for each unique key, the oldest ones being deleted; Default: all versions
+        max_versions: Option<u32>,
     },
     Overwrite,
 }
@@ -69,6 +171,51 @@ pub enum TransactionalHistoryConfig {
         retention_period: u32,
     },
 }
+
+impl Default for HistoryType {
+    fn default() -> Self {
+        HistoryType::Master(MasterHistoryConfig::Overwrite)
+    }
+}
+
+// History types have struct variants, which prost can't derive, so they are encoded as JSON.
+impl ::prost::Message for HistoryType {
+    fn encode_raw<B: ::prost::bytes::BufMut>(&self, buf: &mut B) {
+        ::prost::encoding::string::encode(1, &self.to_json(), buf);
+    }
+
+    fn merge_field<B: ::prost::bytes::Buf>(
+        &mut self,
+        tag: u32,
+        wire_type: ::prost::encoding::WireType,
+        buf: &mut B,
+        ctx: ::prost::encoding::DecodeContext,
+    ) -> Result<(), ::prost::DecodeError> {
+        if tag != 1 {
+            return ::prost::encoding::skip_field(wire_type, tag, buf, ctx);
+        }
+        let mut json = String::new();
+        ::prost::encoding::string::merge(wire_type, &mut json, buf, ctx)?;
+        *self = serde_json::from_str(&json)
+            .map_err(|_| ::prost::DecodeError::new("invalid history type"))?;
+        Ok(())
+    }
+
+    fn encoded_len(&self) -> usize {
+        ::prost::encoding::string::encoded_len(1, &self.to_json())
+    }
+
+    fn clear(&mut self) {
+        *self = HistoryType::default();
+    }
+}
+
+impl HistoryType {
+    fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("history types serialize to JSON")
+    }
+}
+
 #[derive(Serialize, Deserialize, Eq, PartialEq, Clone, ::prost::Oneof)]
 pub enum RefreshConfig {
     // Hour { minute: u32 },
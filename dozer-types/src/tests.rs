@@ -9,4 +9,8 @@ mod field_serialize_test;
 #[cfg(test)]
 mod flags_config_yaml_deserialize;
 #[cfg(test)]
+mod kafka_yaml_deserialize;
+#[cfg(test)]
 mod postgres_yaml_deserialize;
+#[cfg(test)]
+mod snowflake_yaml_deserialize;
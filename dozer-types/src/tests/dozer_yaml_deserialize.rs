@@ -1,4 +1,12 @@
+use crate::models::api_endpoint::{
+    CacheRetentionConfig, CreateSecondaryIndex, FullText, SecondaryIndex, SecondaryIndexConfig,
+    SortedInverted,
+};
 use crate::models::app_config::Config;
+use crate::models::source::{
+    ColumnTransform, HashConfig, HistoryType, MaskConfig, MasterHistoryConfig, Redaction,
+    TransactionalHistoryConfig,
+};
 
 #[test]
 fn error_wrong_reference_connection_name() {
@@ -166,3 +174,266 @@ fn error_missing_field_inner() {
         .to_string()
         .starts_with("connections[0].config: missing field `password`"));
 }
+
+#[test]
+fn source_transforms() {
+    let input_config = r#"
+    app_name: working_app
+    home_dir: './.dozer'
+    connections:
+    - config: !Postgres
+        user: postgres
+        password: postgres
+        host: localhost
+        port: 5432
+        database: users
+      name: users
+    sources:
+    - name: users
+      table_name: users
+      columns:
+      - id
+      - email
+      - card_number
+      - balance
+      connection: !Ref users
+      filter: balance > 0
+      transforms:
+      - column: email
+        rename: email_hash
+        redact: !Hash
+          salt: users
+      - column: card_number
+        redact: !Mask
+          keep_last: 4
+      - column: balance
+        cast: DECIMAL
+  "#;
+    let config = serde_yaml::from_str::<Config>(input_config).unwrap();
+    let source = &config.sources[0];
+    assert_eq!(source.filter.as_deref(), Some("balance > 0"));
+    assert_eq!(
+        source.transforms,
+        vec![
+            ColumnTransform {
+                column: "email".to_string(),
+                rename: Some("email_hash".to_string()),
+                cast: None,
+                redact: Some(Redaction::Hash(HashConfig {
+                    salt: Some("users".to_string())
+                })),
+            },
+            ColumnTransform {
+                column: "card_number".to_string(),
+                rename: None,
+                cast: None,
+                redact: Some(Redaction::Mask(MaskConfig { keep_last: 4 })),
+            },
+            ColumnTransform {
+                column: "balance".to_string(),
+                rename: None,
+                cast: Some("DECIMAL".to_string()),
+                redact: None,
+            },
+        ]
+    );
+
+    // Sources without transforms are written as before
+    let mut source = source.clone();
+    source.transforms = vec![];
+    source.filter = None;
+    let yaml = serde_yaml::to_string(&source).unwrap();
+    assert!(!yaml.contains("transforms"));
+    assert!(!yaml.contains("filter"));
+}
+
+#[test]
+fn source_history_type() {
+    let input_config = r#"
+    app_name: working_app
+    home_dir: './.dozer'
+    connections:
+    - config: !Postgres
+        user: postgres
+        password: postgres
+        host: localhost
+        port: 5432
+        database: users
+      name: users
+    sources:
+    - name: users
+      table_name: users
+      columns: []
+      connection: !Ref users
+      history_type:
+        Master:
+          AppendOnly:
+            unique_key_field: id
+            open_date_field: valid_from
+            closed_date_field: valid_to
+            max_versions: 10
+    - name: payments
+      table_name: payments
+      columns: []
+      connection: !Ref users
+      history_type:
+        Transactional:
+          RetainPartial:
+            timestamp_field: paid_at
+            retention_period: 86400
+  "#;
+    let config = serde_yaml::from_str::<Config>(input_config).unwrap();
+    assert_eq!(
+        config.sources[0].history_type,
+        Some(HistoryType::Master(MasterHistoryConfig::AppendOnly {
+            unique_key_field: "id".to_string(),
+            open_date_field: "valid_from".to_string(),
+            closed_date_field: "valid_to".to_string(),
+            max_versions: Some(10),
+        }))
+    );
+    assert_eq!(
+        config.sources[1].history_type,
+        Some(HistoryType::Transactional(
+            TransactionalHistoryConfig::RetainPartial {
+                timestamp_field: "paid_at".to_string(),
+                retention_period: 86400,
+            }
+        ))
+    );
+
+    // History types are written back as they were read.
+    let yaml = serde_yaml::to_string(&config).unwrap();
+    let read_back = serde_yaml::from_str::<Config>(&yaml).unwrap();
+    assert_eq!(
+        read_back.sources[0].history_type,
+        config.sources[0].history_type
+    );
+    assert_eq!(
+        read_back.sources[1].history_type,
+        config.sources[1].history_type
+    );
+}
+
+#[test]
+fn endpoint_secondary_indexes() {
+    let input_config = r#"
+    app_name: working_app
+    home_dir: './.dozer'
+    endpoints:
+    - name: users
+      table_name: users
+      path: /users
+      index:
+        primary_key:
+          - id
+        secondary:
+          skip_default:
+            - email
+          create:
+            - index: !SortedInverted
+                fields:
+                  - name
+                  - id
+            - index: !FullText
+                field: bio
+                lowercase: true
+                stemming: true
+    - name: payments
+      table_name: payments
+      path: /payments
+      index:
+        primary_key:
+          - id
+  "#;
+    let config = serde_yaml::from_str::<Config>(input_config).unwrap();
+    let index = config.endpoints[0].index.as_ref().unwrap();
+    assert_eq!(
+        index.secondary,
+        Some(SecondaryIndexConfig {
+            skip_default: vec!["email".to_string()],
+            create: vec![
+                CreateSecondaryIndex {
+                    index: Some(SecondaryIndex::SortedInverted(SortedInverted {
+                        fields: vec!["name".to_string(), "id".to_string()],
+                    })),
+                },
+                CreateSecondaryIndex {
+                    index: Some(SecondaryIndex::FullText(FullText {
+                        field: "bio".to_string(),
+                        lowercase: true,
+                        stop_words: false,
+                        stemming: true,
+                    })),
+                },
+            ],
+        })
+    );
+
+    let index = config.endpoints[1].index.as_ref().unwrap();
+    assert_eq!(index.secondary, None);
+    assert!(!serde_yaml::to_string(index).unwrap().contains("secondary"));
+}
+
+#[test]
+fn endpoint_cache_retention() {
+    let input_config = r#"
+    app_name: working_app
+    home_dir: './.dozer'
+    endpoints:
+    - name: events
+      table_name: events
+      path: /events
+      retention:
+        timestamp_field: created_at
+        retention_period: 2592000
+        max_bytes: 1073741824
+    - name: users
+      table_name: users
+      path: /users
+  "#;
+    let config = serde_yaml::from_str::<Config>(input_config).unwrap();
+    assert_eq!(
+        config.endpoints[0].retention,
+        Some(CacheRetentionConfig {
+            timestamp_field: Some("created_at".to_string()),
+            retention_period: Some(2592000),
+            max_records: None,
+            max_bytes: Some(1073741824),
+        })
+    );
+    assert!(serde_yaml::to_string(&config.endpoints[0])
+        .unwrap()
+        .contains("retention_period"));
+
+    assert_eq!(config.endpoints[1].retention, None);
+    assert!(!serde_yaml::to_string(&config.endpoints[1])
+        .unwrap()
+        .contains("retention"));
+}
+
+#[test]
+fn endpoint_history() {
+    let input_config = r#"
+    app_name: working_app
+    home_dir: './.dozer'
+    endpoints:
+    - name: events
+      table_name: events
+      path: /events
+      history: true
+    - name: users
+      table_name: users
+      path: /users
+  "#;
+    let config = serde_yaml::from_str::<Config>(input_config).unwrap();
+    assert!(config.endpoints[0].history);
+    assert!(serde_yaml::to_string(&config.endpoints[0])
+        .unwrap()
+        .contains("history: true"));
+
+    assert!(!config.endpoints[1].history);
+    assert!(!serde_yaml::to_string(&config.endpoints[1])
+        .unwrap()
+        .contains("history"));
+}
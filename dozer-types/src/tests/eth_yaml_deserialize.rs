@@ -1,5 +1,5 @@
 use crate::{
-    ingestion_types::{EthConfig, EthFilter, EthLogConfig},
+    ingestion_types::{EthBlockConfig, EthConfig, EthFilter, EthLogConfig},
     models::connection::ConnectionConfig,
 };
 #[test]
@@ -27,6 +27,7 @@ fn standard() {
                 filter: Some(expected_eth_filter),
                 wss_url: "wss://link".to_owned(),
                 contracts: vec![],
+                confirmations: 0,
             },
         )),
     };
@@ -56,6 +57,30 @@ fn config_without_empty_array() {
                 wss_url: "wss://link".to_owned(),
                 filter: Some(expected_eth_filter),
                 contracts: vec![],
+                confirmations: 0,
+            },
+        )),
+    };
+    let expected = ConnectionConfig::Ethereum(expected_eth_config);
+    assert_eq!(expected, deserializer_result);
+}
+
+#[test]
+fn block_provider() {
+    let eth_config = r#"
+  !Ethereum
+    provider: !Block
+        wss_url: wss://link
+        from_block: 16000000
+  "#;
+    let deserializer_result = serde_yaml::from_str::<ConnectionConfig>(eth_config).unwrap();
+    let expected_eth_config = EthConfig {
+        provider: Some(crate::ingestion_types::EthProviderConfig::Block(
+            EthBlockConfig {
+                wss_url: "wss://link".to_owned(),
+                from_block: 16000000,
+                to_block: None,
+                batch_size: 100,
             },
         )),
     };
@@ -41,6 +41,7 @@ fn test_storage_params_config() {
     commit_timeout: 100
     app_buffer_size: 10000
     commit_size: 1000
+    cache_max_scan_records: 5000
 "#;
     let deserializer_result = serde_yaml::from_str::<Config>(input_config_without_flag).unwrap();
     assert_eq!(deserializer_result.cache_max_map_size, Some(1073741824));
@@ -48,6 +49,7 @@ fn test_storage_params_config() {
     assert_eq!(deserializer_result.commit_timeout, Some(100));
     assert_eq!(deserializer_result.app_buffer_size, Some(10000));
     assert_eq!(deserializer_result.commit_size, Some(1000));
+    assert_eq!(deserializer_result.cache_max_scan_records, Some(5000));
 }
 
 #[test]
@@ -61,4 +63,5 @@ fn test_default_storage_params_config() {
     assert_eq!(deserializer_result.commit_timeout, Some(50));
     assert_eq!(deserializer_result.app_buffer_size, Some(20000));
     assert_eq!(deserializer_result.commit_size, Some(10000));
+    assert_eq!(deserializer_result.cache_max_scan_records, Some(100_000));
 }
@@ -0,0 +1,41 @@
+use crate::{
+    ingestion_types::{KafkaConfig, KafkaConfigSchemas},
+    models::connection::ConnectionConfig,
+};
+
+#[test]
+fn standard() {
+    let kafka_config = r#"
+    !Kafka
+    broker: localhost:9092
+    schema_registry_url: http://localhost:8081
+  "#;
+    let deserializer_result = serde_yaml::from_str::<ConnectionConfig>(kafka_config).unwrap();
+    let expected = ConnectionConfig::Kafka(KafkaConfig {
+        broker: "localhost:9092".to_owned(),
+        schema_registry_url: Some("http://localhost:8081".to_owned()),
+        format: "debezium".to_owned(),
+        schemas: None,
+        state_path: None,
+    });
+    assert_eq!(expected, deserializer_result);
+}
+
+#[test]
+fn json_format_with_schemas() {
+    let kafka_config = r#"
+    !Kafka
+    broker: localhost:9092
+    format: json
+    schemas: !Path ./schemas.json
+  "#;
+    let deserializer_result = serde_yaml::from_str::<ConnectionConfig>(kafka_config).unwrap();
+    let expected = ConnectionConfig::Kafka(KafkaConfig {
+        broker: "localhost:9092".to_owned(),
+        schema_registry_url: None,
+        format: "json".to_owned(),
+        schemas: Some(KafkaConfigSchemas::Path("./schemas.json".to_owned())),
+        state_path: None,
+    });
+    assert_eq!(expected, deserializer_result);
+}
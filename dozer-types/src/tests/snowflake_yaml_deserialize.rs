@@ -0,0 +1,69 @@
+use crate::{
+    ingestion_types::{
+        SnowflakeChangeTracking, SnowflakeConfig, SnowflakeCursorColumn, SnowflakeIncrementalConfig,
+    },
+    models::connection::ConnectionConfig,
+};
+
+fn config(change_tracking: Option<SnowflakeChangeTracking>, parallelism: u32) -> ConnectionConfig {
+    ConnectionConfig::Snowflake(SnowflakeConfig {
+        server: "server".to_owned(),
+        port: "443".to_owned(),
+        user: "bob".to_owned(),
+        password: "password".to_owned(),
+        database: "database".to_owned(),
+        schema: "schema".to_owned(),
+        warehouse: "warehouse".to_owned(),
+        driver: None,
+        change_tracking,
+        parallelism,
+    })
+}
+
+#[test]
+fn standard() {
+    let snowflake_config = r#"
+    !Snowflake
+    server: server
+    port: "443"
+    user: bob
+    password: password
+    database: database
+    schema: schema
+    warehouse: warehouse
+  "#;
+    let deserializer_result = serde_yaml::from_str::<ConnectionConfig>(snowflake_config).unwrap();
+    assert_eq!(config(None, 1), deserializer_result);
+}
+
+#[test]
+fn incremental_with_parallelism() {
+    let snowflake_config = r#"
+    !Snowflake
+    server: server
+    port: "443"
+    user: bob
+    password: password
+    database: database
+    schema: schema
+    warehouse: warehouse
+    change_tracking: !Incremental
+      cursor_columns:
+        - table: CUSTOMERS
+          column: UPDATED_AT
+    parallelism: 4
+  "#;
+    let deserializer_result = serde_yaml::from_str::<ConnectionConfig>(snowflake_config).unwrap();
+    let expected = config(
+        Some(SnowflakeChangeTracking::Incremental(
+            SnowflakeIncrementalConfig {
+                cursor_columns: vec![SnowflakeCursorColumn {
+                    table: "CUSTOMERS".to_owned(),
+                    column: "UPDATED_AT".to_owned(),
+                }],
+            },
+        )),
+        4,
+    );
+    assert_eq!(expected, deserializer_result);
+}
@@ -166,8 +166,21 @@ impl Display for Schema {
 pub enum IndexDefinition {
     /// The sorted inverted index, supporting `Eq` filter on multiple fields and `LT`, `LTE`, `GT`, `GTE` filter on at most one field.
     SortedInverted(Vec<usize>),
-    /// Full text index, supporting `Contains`, `MatchesAny` and `MatchesAll` filter on exactly one field.
-    FullText(usize),
+    /// Full text index, supporting `Contains`, `MatchesAny`, `MatchesAll`, `MatchesPhrase` and `MatchesPrefix` filter on exactly one field.
+    FullText(usize, TextAnalyzer),
+    /// Spatial index, supporting `WithinRadius`, `WithinBbox` and `Nearest` filter on exactly one point field.
+    Spatial(usize),
+}
+
+/// How the text of a full text index is turned into terms, after being split into Unicode words.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, Default, PartialEq, Eq)]
+pub struct TextAnalyzer {
+    /// Lowercase the words.
+    pub lowercase: bool,
+    /// Remove common English words, like "the" or "of".
+    pub stop_words: bool,
+    /// Reduce English words to their stem, so "running" and "runs" are the same term.
+    pub stemming: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
@@ -323,7 +336,7 @@ impl Display for Record {
     }
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Operation {
     Delete { old: Record },
     Insert { new: Record },